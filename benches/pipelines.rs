@@ -0,0 +1,170 @@
+//! pipelines.rs
+//!
+//! Timings of the core pipelines, to compare changes to the regexes and
+//! caches: the cold parse of data files by `setupstring()`, the evaluation
+//! of conditions, `getweek()`/`monthday()` over a century, the assembly
+//! of Vespers and the hours of a year on 1, 2 and 4 threads.
+//!
+//! ```text
+//! cargo bench --bench pipelines
+//! DIVINUM_OFFICIUM_BENCH_DATA=../divinum-officium/web/www/horas cargo bench --bench pipelines
+//! ```
+//!
+//! Without `DIVINUM_OFFICIUM_BENCH_DATA` the files are those of a small tree
+//! generated in the temporary directory. A filter given on the command line
+//! (e.g. `cargo bench --bench pipelines -- getweek`) runs only the benches
+//! whose name contains it.
+//!
+//! The harness is this file rather than criterion, so that the benches
+//! build with the dependencies of the crate alone (and offline). It takes
+//! what criterion would report first: the median time of a call over
+//! `SAMPLES` samples, with the fastest and slowest sample, so that two runs
+//! can be compared by whether their ranges overlap. Moving to criterion
+//! means turning each `bench(name, f)` into a `bench_function(name, f)`.
+
+use std::fs;
+use std::hint::black_box;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use divinum_officium::date::{getweek, monthday, CalendarSystem};
+use divinum_officium::export::batch::generate_year_with_threads;
+use divinum_officium::horas::{Hora, Kalendar};
+use divinum_officium::setup_string::{ResolveDirectives, SetupStringContext};
+
+/// How long each bench runs, after a first call to warm up.
+const RUN_TIME: Duration = Duration::from_secs(2);
+
+/// The samples `RUN_TIME` is divided into.
+const SAMPLES: u32 = 20;
+
+fn main() {
+    let filter = std::env::args().skip(1).find(|a| !a.starts_with('-')).unwrap_or_default();
+    let (root, generated) = match std::env::var_os("DIVINUM_OFFICIUM_BENCH_DATA") {
+        Some(dir) => (PathBuf::from(dir), false),
+        None => (generate_tree(), true),
+    };
+    let files = sancti_files(&root);
+
+    let bench = |name: &str, f: &mut dyn FnMut()| {
+        if name.contains(&filter) {
+            report(name, f);
+        }
+    };
+
+    bench("setupstring cold parse of the Sancti files", &mut || {
+        let mut ctx = context(&root);
+        for file in &files {
+            black_box(ctx.setupstring("Latin", file, ResolveDirectives::None));
+        }
+    });
+    let mut ctx = context(&root);
+    bench("setupstring cached", &mut || {
+        for file in &files {
+            black_box(ctx.setupstring("Latin", file, ResolveDirectives::None));
+        }
+    });
+    let ctx = context(&root);
+    bench("condition evaluation", &mut || {
+        for cond in ["rubrica monastica", "rubrica 1960 aut rubrica 1955", "tempore paschali et rubrica tridentina nisi die Dominica"] {
+            black_box(ctx.explain_condition(black_box(cond)).verdict);
+        }
+    });
+    bench("getweek over a century", &mut || {
+        for_each_day(1950, 2050, |d, m, y| {
+            black_box(getweek(d, m, y, false, false, CalendarSystem::Gregorian));
+        })
+    });
+    bench("monthday over a century", &mut || {
+        for_each_day(1950, 2050, |d, m, y| {
+            black_box(monthday(d, m, y, false, false, CalendarSystem::Gregorian));
+        })
+    });
+    let kalendar = Kalendar::new("Divino Afflatu");
+    let mut ctx = context(&root);
+    bench("Vespers of a week", &mut || {
+        for day in 20..27 {
+            black_box(kalendar.build_hour(&mut ctx, &format!("01-{}-2025", day), Hora::Vespera, "Latin").ok());
+        }
+    });
+    for threads in [1, 2, 4] {
+        bench(&format!("generate_year on {} thread(s)", threads), &mut || {
+            let mut ctx = context(&root);
+            let hours = [Hora::Laudes, Hora::Vespera];
+            generate_year_with_threads(&mut ctx, 2025, &hours, &["Latin"], "Divino Afflatu", threads, |hour| {
+                black_box(hour.text.ok());
+            });
+        });
+    }
+
+    if generated {
+        let _ = fs::remove_dir_all(&root);
+    }
+}
+
+/// Runs `f` for `RUN_TIME` in `SAMPLES` samples and prints the median time
+/// of a call, with the range of the samples.
+fn report(name: &str, f: &mut dyn FnMut()) {
+    f();
+    let mut samples = Vec::new();
+    let mut calls = 0u32;
+    for _ in 0..SAMPLES {
+        let start = Instant::now();
+        let mut n = 0u32;
+        while n == 0 || start.elapsed() < RUN_TIME / SAMPLES {
+            f();
+            n += 1;
+        }
+        samples.push(start.elapsed() / n);
+        calls += n;
+    }
+    samples.sort();
+    let (median, low, high) = (samples[samples.len() / 2], samples[0], samples[samples.len() - 1]);
+    println!("{:<45} {:>12.3?} [{:.3?} .. {:.3?}] ({} calls)", name, median, low, high, calls);
+}
+
+fn context(root: &Path) -> SetupStringContext {
+    SetupStringContext::new(root, "Divino Afflatu")
+}
+
+fn for_each_day(from: i32, to: i32, mut f: impl FnMut(u32, u32, i32)) {
+    for year in from..to {
+        for month in 1..=12 {
+            for day in 1..=28 {
+                f(day, month, year);
+            }
+        }
+    }
+}
+
+/// The files of `Latin/Sancti` under `root`, as `setupstring()` names them.
+fn sancti_files(root: &Path) -> Vec<String> {
+    let mut files: Vec<String> = fs::read_dir(root.join("Latin/Sancti"))
+        .map(|dir| dir.flatten().filter_map(|e| e.file_name().into_string().ok()).collect())
+        .unwrap_or_default();
+    files.retain(|f| f.ends_with(".txt"));
+    files.sort();
+    files.into_iter().map(|f| format!("Sancti/{}", f)).collect()
+}
+
+/// A tree with a feast for each day of January and the Ordinarium of
+/// Vespers, its sections as long as those of the data.
+fn generate_tree() -> PathBuf {
+    let root = std::env::temp_dir().join(format!("divinum-officium-bench-{}", std::process::id()));
+    let write = |rel: &str, content: &str| {
+        let path = root.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    };
+    let lectio = "Lorem ipsum dolor sit amet, consectetúr adipíscing elit, sed do eiúsmod tempor incidídunt.\n".repeat(6);
+    for day in 1..=31 {
+        let mut text = String::from("[Rank]\nS. Nomen Confessoris;;Duplex;;3;;vide C4a\n\n[Rank] (rubrica 1960)\nS. Nomen Confessoris;;III. classis;;3\n\n");
+        text.push_str("[Oratio]\nDeus, qui beátum N. * mirábili pietáte decorásti:\n(sed rubrica monastica dicitur)\nda nobis, quǽsumus.\n$Per Dominum\n\n");
+        for n in 1..=9 {
+            text.push_str(&format!("[Lectio{}]\n{}\n[Responsory{}]\nR. Honéstum fecit illum Dóminus.\nV. Et dedit illi claritátem ætérnam.\n\n", n, lectio, n));
+        }
+        write(&format!("Latin/Sancti/01-{:02}.txt", day), &text);
+    }
+    write("Latin/Ordinarium/Vespera.txt", "#Incipit\nDeus in adjutorium\n\n#Psalmi\n&psalmi\n\n#Capitulum Hymnus Versus\n&capitulum\n\n#Oratio\n&oratio\n");
+    root
+}
@@ -0,0 +1,369 @@
+//! divinum-officium
+//!
+//! The command line of the crate, in the place of the CGI scripts of the
+//! Perl site (`officium.pl`, `kalendar.pl`):
+//!
+//! ```text
+//! divinum-officium hour Laudes --date 01-25-2025 --version "Divino Afflatu" --format md
+//! divinum-officium kalendar --date 01-2025
+//! divinum-officium color --date 12-25-2025
+//! divinum-officium missa --date 01-21-2025 --version "Divino Afflatu"
+//! ```
+//!
+//! The data is read from `--datafolder` (the `web/www/horas` folder of the
+//! Perl project by default, `web/www/missa` for the Mass).
+
+use std::collections::HashMap;
+use std::process::ExitCode;
+
+use chrono::{Datelike, Local, NaiveDate};
+use divinum_officium::horas::officium::json_string;
+use divinum_officium::horas::{build_hour, build_hour_json, Hora, Kalendar};
+use divinum_officium::liturgical_color;
+use divinum_officium::missa::preface::PREFATIONES_FILE;
+use divinum_officium::missa::propers::Proper;
+use divinum_officium::missa::{preface_for, propers};
+use divinum_officium::render::html::escape_html;
+use divinum_officium::scripting::{expand_directives, DirectiveContext};
+use divinum_officium::setup_string::{ResolveDirectives, SetupStringContext};
+
+const USAGE: &str = "\
+usage: divinum-officium <command> [options]
+
+commands:
+  hour <hora>   the text of an hour (Matutinum, Laudes, ..., Completorium)
+  missa         the proper of the Mass and its preface
+  kalendar      the offices of the month of --date (MM-YYYY or MM-DD-YYYY)
+  color         the liturgical color of the day
+
+options:
+  --date MM-DD-YYYY      the day (today by default)
+  --version NAME         the rubrics (\"Rubrics 1960 - 1960\" by default)
+  --lang LANG            the language (Latin by default)
+  --format FORMAT        html, text, md or json (text by default)
+  --datafolder PATH      the data (web/www/horas by default, web/www/missa for missa)";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Html,
+    Text,
+    Markdown,
+    Json,
+}
+
+impl Format {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "html" => Some(Format::Html),
+            "text" => Some(Format::Text),
+            "md" => Some(Format::Markdown),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+/// The parsed command line.
+struct Args {
+    command: String,
+    operands: Vec<String>,
+    date: String,
+    version: String,
+    lang: String,
+    format: Format,
+    datafolder: String,
+}
+
+fn parse_args(args: &[String]) -> Result<Args, String> {
+    let mut options: HashMap<&str, String> = HashMap::new();
+    let mut positional = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.strip_prefix("--") {
+            Some(name @ ("date" | "version" | "lang" | "format" | "datafolder")) => {
+                let value = iter.next().ok_or_else(|| format!("--{} needs a value", name))?;
+                options.insert(name, value.clone());
+            }
+            Some(name) => return Err(format!("unknown option --{}", name)),
+            None => positional.push(arg.clone()),
+        }
+    }
+    if positional.is_empty() {
+        return Err("no command given".to_string());
+    }
+    let command = positional.remove(0);
+    let format = match options.get("format") {
+        Some(f) => Format::parse(f).ok_or_else(|| format!("unknown format {}", f))?,
+        None => Format::Text,
+    };
+    let today = Local::now().date_naive();
+    Ok(Args {
+        operands: positional,
+        date: options
+            .remove("date")
+            .unwrap_or_else(|| format!("{:02}-{:02}-{}", today.month(), today.day(), today.year())),
+        version: options.remove("version").unwrap_or_else(|| "Rubrics 1960 - 1960".to_string()),
+        lang: options.remove("lang").unwrap_or_else(|| "Latin".to_string()),
+        format,
+        datafolder: options.remove("datafolder").unwrap_or_else(|| {
+            let folder = if command == "missa" { "missa" } else { "horas" };
+            format!("web/www/{}", folder)
+        }),
+        command,
+    })
+}
+
+fn context(args: &Args) -> SetupStringContext {
+    SetupStringContext::new(&args.datafolder, &args.version)
+}
+
+/// Renders the text of an hour: its `#` lines are the headings of the
+/// chapters, a `_` line separates the parts of a chapter.
+fn render(text: &str, format: Format) -> String {
+    let mut out = String::new();
+    for line in text.lines() {
+        let heading = line.strip_prefix('#').map(str::trim);
+        let rendered = match (format, heading) {
+            (Format::Markdown, Some(heading)) => format!("## {}\n", heading),
+            (Format::Markdown, None) if line == "_" => String::new(),
+            (Format::Markdown, None) => format!("{}  ", line),
+            (Format::Html, Some(heading)) => format!("<h2>{}</h2>", escape_html(heading)),
+            (Format::Html, None) if line == "_" => "<br/>".to_string(),
+            (Format::Html, None) => format!("{}<br/>", escape_html(line)),
+            _ => line.to_string(),
+        };
+        out.push_str(&rendered);
+        out.push('\n');
+    }
+    if format == Format::Html {
+        out = format!("<html><body>\n{}</body></html>\n", out);
+    }
+    out
+}
+
+/// Returns the title of the office of `date` (the first part of its rank)
+/// and the file it comes from.
+fn office_title(ctx: &mut SetupStringContext, args: &Args, date: &str) -> Result<(String, String), String> {
+    let office = Kalendar::new(&args.version).office(ctx, date, &args.lang).map_err(|e| e.to_string())?;
+    let title = ctx
+        .setupstring(&args.lang, &office.winner, ResolveDirectives::None)
+        .and_then(|sections| sections.get("Rank").cloned())
+        .and_then(|rank| rank.lines().next().map(|l| l.split(";;").next().unwrap_or("").trim().to_string()))
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| office.dayname.clone());
+    Ok((title, office.winner))
+}
+
+fn hour(args: &Args) -> Result<String, String> {
+    let name = args.operands.first().ok_or("which hour? e.g. `hour Laudes`")?;
+    let mut ctx = context(args);
+    if args.format == Format::Json {
+        return Ok(build_hour_json(&mut ctx, &args.date, name, &args.lang, &args.version));
+    }
+    let hora = name.parse::<Hora>().map_err(|e| e.to_string())?;
+    let text = build_hour(&mut ctx, &args.date, hora, &args.lang, &args.version).map_err(|e| e.to_string())?;
+    let text = expand_directives(&text, &mut DirectiveContext { files: &mut ctx, lang: &args.lang });
+    Ok(render(&text, args.format))
+}
+
+fn kalendar(args: &Args) -> Result<String, String> {
+    let mut parts = args.date.split('-');
+    let month: u32 = parts.next().and_then(|m| m.parse().ok()).ok_or("invalid --date")?;
+    let year: i32 = parts.last().and_then(|y| y.parse().ok()).ok_or("invalid --date")?;
+    let mut ctx = context(args);
+    let mut rows = Vec::new();
+    for day in (1..=31).filter(|&d| NaiveDate::from_ymd_opt(year, month, d).is_some()) {
+        let date = format!("{:02}-{:02}-{}", month, day, year);
+        let (title, file) = office_title(&mut ctx, args, &date)?;
+        rows.push((date, title, file));
+    }
+    Ok(match args.format {
+        Format::Json => {
+            let rows: Vec<String> = rows
+                .iter()
+                .map(|(date, title, file)| {
+                    format!(
+                        "{{\"date\":{},\"office\":{},\"file\":{}}}",
+                        json_string(date),
+                        json_string(title),
+                        json_string(file)
+                    )
+                })
+                .collect();
+            format!("[{}]\n", rows.join(","))
+        }
+        Format::Html => {
+            let rows: String = rows
+                .iter()
+                .map(|(date, title, _)| format!("<tr><td>{}</td><td>{}</td></tr>\n", date, escape_html(title)))
+                .collect();
+            format!("<html><body><table>\n{}</table></body></html>\n", rows)
+        }
+        Format::Markdown => {
+            let rows: String = rows.iter().map(|(date, title, _)| format!("| {} | {} |\n", date, title)).collect();
+            format!("| Date | Office |\n|---|---|\n{}", rows)
+        }
+        Format::Text => rows.iter().map(|(date, title, _)| format!("{}  {}\n", date, title)).collect(),
+    })
+}
+
+fn color(args: &Args) -> Result<String, String> {
+    let mut ctx = context(args);
+    let (title, _) = office_title(&mut ctx, args, &args.date)?;
+    let color = liturgical_color(&title);
+    Ok(match args.format {
+        Format::Json => format!(
+            "{{\"date\":{},\"office\":{},\"color\":{}}}\n",
+            json_string(&args.date),
+            json_string(&title),
+            json_string(color)
+        ),
+        _ => format!("{}\n", color),
+    })
+}
+
+/// The parts of the proper of the Mass of `args.date`, by their Latin
+/// heading, with the preface after the Secret.
+fn missa_parts(args: &Args) -> Result<Vec<(&'static str, String)>, String> {
+    let mut ctx = context(args);
+    let propers = propers(&mut ctx, &args.date, &args.version, &args.lang).map_err(|e| e.to_string())?;
+    let preface = preface_for(&mut ctx, &args.date, &args.version).map_err(|e| e.to_string())?;
+    let preface = ctx
+        .setupstring(&args.lang, PREFATIONES_FILE, ResolveDirectives::All)
+        .and_then(|prefaces| prefaces.get(preface.section()).map(|t| t.trim().to_string()));
+    let text = |proper: Option<Proper>| proper.map(|p| p.text);
+    let parts = [
+        ("Introitus", text(propers.introit)),
+        ("Oratio", text(propers.collect)),
+        ("Lectio", text(propers.epistle)),
+        ("Graduale", text(propers.gradual)),
+        ("Evangelium", text(propers.gospel)),
+        ("Offertorium", text(propers.offertory)),
+        ("Secreta", text(propers.secret)),
+        ("Prefatio", preface),
+        ("Communio", text(propers.communion)),
+        ("Postcommunio", text(propers.postcommunion)),
+    ];
+    Ok(parts.into_iter().filter_map(|(name, text)| Some((name, text?))).collect())
+}
+
+fn missa(args: &Args) -> Result<String, String> {
+    let parts = missa_parts(args)?;
+    if args.format == Format::Json {
+        let parts: Vec<String> =
+            parts.iter().map(|(name, text)| format!("{}:{}", json_string(name), json_string(text))).collect();
+        return Ok(format!("{{\"date\":{},\"parts\":{{{}}}}}\n", json_string(&args.date), parts.join(",")));
+    }
+    let text: Vec<String> = parts.iter().map(|(name, text)| format!("#{}\n{}", name, text)).collect();
+    Ok(render(&text.join("\n"), args.format))
+}
+
+fn main() -> ExitCode {
+    let argv: Vec<String> = std::env::args().skip(1).collect();
+    let result = parse_args(&argv).and_then(|args| match args.command.as_str() {
+        "hour" => hour(&args),
+        "kalendar" => kalendar(&args),
+        "color" => color(&args),
+        "missa" => missa(&args),
+        "help" => Ok(format!("{}\n", USAGE)),
+        other => Err(format!("unknown command {}", other)),
+    });
+    match result {
+        Ok(out) => {
+            print!("{}", out);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("divinum-officium: {}\n\n{}", e, USAGE);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Result<Args, String> {
+        parse_args(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let parsed = args(&["hour", "Laudes", "--date", "01-25-2025", "--format", "md", "--lang", "English"]).unwrap();
+        assert_eq!((parsed.command.as_str(), parsed.operands.as_slice()), ("hour", ["Laudes".to_string()].as_slice()));
+        assert_eq!((parsed.date.as_str(), parsed.lang.as_str()), ("01-25-2025", "English"));
+        assert_eq!((parsed.format, parsed.version.as_str()), (Format::Markdown, "Rubrics 1960 - 1960"));
+        assert_eq!(parsed.datafolder, "web/www/horas");
+        assert_eq!(args(&["missa"]).unwrap().datafolder, "web/www/missa");
+        assert_eq!(args(&["missa", "--datafolder", "/srv/missa"]).unwrap().datafolder, "/srv/missa");
+
+        assert_eq!(args(&[]).err().as_deref(), Some("no command given"));
+        assert_eq!(args(&["hour", "--date"]).err().as_deref(), Some("--date needs a value"));
+        assert_eq!(args(&["hour", "--colour", "red"]).err().as_deref(), Some("unknown option --colour"));
+        assert_eq!(args(&["hour", "--format", "pdf"]).err().as_deref(), Some("unknown format pdf"));
+    }
+
+    #[test]
+    fn test_render() {
+        let text = "#Hymnus\nJam lucis orto sídere,\n_\nV. Deus <in> adjutórium & R.";
+        assert_eq!(render(text, Format::Text), format!("{}\n", text));
+        assert_eq!(render(text, Format::Markdown), "## Hymnus\n\nJam lucis orto sídere,  \n\nV. Deus <in> adjutórium & R.  \n");
+        assert_eq!(
+            render(text, Format::Html),
+            "<html><body>\n<h2>Hymnus</h2>\nJam lucis orto sídere,<br/>\n<br/>\nV. Deus &lt;in&gt; adjutórium &amp; R.<br/>\n</body></html>\n"
+        );
+    }
+
+    #[test]
+    fn test_hour() {
+        let root = std::env::temp_dir().join(format!("divinum-officium-cli-hour-{}", std::process::id()));
+        for (file, content) in [
+            ("Latin/Tempora/Epi2-2.txt", "[Rank]\nFeria Tertia;;Feria;;1\n"),
+            ("Latin/Ordinarium/Vespera.txt", "#Incipit\n$Pater noster\n&psalm(116)\n"),
+            ("Latin/Psalterium/Common/Prayers.txt", "[Pater noster]\nPater noster, qui es in cælis.\n"),
+            ("Latin/Psalterium/Psalmorum/Psalm116.txt", "116:1 Laudáte Dóminum, omnes gentes.\n"),
+        ] {
+            let path = root.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        let datafolder = root.to_string_lossy().to_string();
+        let parsed = args(&["hour", "Vespera", "--date", "01-21-2025", "--datafolder", &datafolder, "--format", "html"]);
+        let text = hour(&parsed.unwrap()).unwrap();
+        assert!(text.contains("Pater noster, qui es in cælis.<br/>"));
+        assert!(text.contains("116:1 Laudáte Dóminum, omnes gentes.<br/>"));
+        assert!(!text.contains("$Pater") && !text.contains("&amp;psalm"));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_missa() {
+        let root = std::env::temp_dir().join(format!("divinum-officium-cli-missa-{}", std::process::id()));
+        for (file, content) in [
+            ("Latin/Tempora/Epi2-2.txt", "[Rank]\nFeria Tertia;;Feria;;1\n"),
+            (
+                "Latin/Sancti/01-21.txt",
+                "[Rank]\nS. Agnetis Virginis et Martyris;;Duplex;;3;;vide C6\n\n\
+                 [Introitus]\nv. Me exspectavérunt peccatóres.\n\n[Oratio]\nOmnípotens sempitérne Deus.\n",
+            ),
+            ("Latin/Commune/C6.txt", "[Communio]\nQuinque prudéntes vírgines.\n"),
+            ("Latin/Ordo/Prefationes.txt", "[Communis]\nVere dignum et justum est.\n"),
+        ] {
+            let path = root.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        let datafolder = root.to_string_lossy().to_string();
+        let parsed = args(&["missa", "--date", "01-21-2025", "--version", "Divino Afflatu", "--datafolder", &datafolder]);
+        let text = missa(&parsed.unwrap()).unwrap();
+        assert_eq!(
+            text,
+            "#Introitus\nv. Me exspectavérunt peccatóres.\n#Oratio\nOmnípotens sempitérne Deus.\n\
+             #Prefatio\nVere dignum et justum est.\n#Communio\nQuinque prudéntes vírgines.\n"
+        );
+        let parsed = args(&["missa", "--date", "01-21-2025", "--format", "json", "--datafolder", &datafolder]);
+        assert!(missa(&parsed.unwrap()).unwrap().starts_with("{\"date\":\"01-21-2025\",\"parts\":{\"Introitus\":"));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,216 @@
+//! compare.rs
+//!
+//! The comparison of an hour in two rubrical versions, as the "compare"
+//! page of the Perl site shows e.g. the Tridentine and the 1960 office of a
+//! day side by side.
+//!
+//! ```ignore
+//! let cmp = compare_hour(&mut ctx, "01-25-2025", Hora::Laudes, "Latin", "Tridentine - 1570", "Rubrics 1960 - 1960")?;
+//! for difference in &cmp.differences {
+//!     println!("{}", difference);
+//! }
+//! ```
+//!
+//! The chapters of the two hours are paired by their titles, in the order
+//! of the first version (a chapter only the second has comes after the
+//! chapter it follows there); the summary lists what differs in the
+//! psalmody, the chapters said in one version only (e.g. the Preces) and
+//! the lessons.
+
+use std::fmt;
+use std::io;
+
+use crate::horas::{Hora, Kalendar, OfficeElement};
+use crate::regex::contains_ci;
+use crate::setup_string::SetupStringContext;
+
+/// A difference between the two versions of an hour.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Difference {
+    /// The psalms (their headings, e.g. "Psalmus 109") of the two versions.
+    Psalms { a: Vec<String>, b: Vec<String> },
+    /// A chapter said only in the first version (`in_a`) or the second.
+    Omitted { section: String, in_a: bool },
+    /// The lessons of the two versions differ.
+    Lessons { a: Vec<String>, b: Vec<String> },
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Difference::Psalms { a, b } => write!(f, "psalms: {} / {}", a.join(", "), b.join(", ")),
+            Difference::Omitted { section, in_a } => {
+                write!(f, "{}: only in version {}", section, if *in_a { "A" } else { "B" })
+            }
+            Difference::Lessons { a, b } => write!(f, "lessons: {} / {} lessons differ", a.len(), b.len()),
+        }
+    }
+}
+
+/// An hour in two versions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HourComparison {
+    /// The chapters of the two versions, paired by title; `None` where a
+    /// version does not have the chapter.
+    pub pairs: Vec<(Option<OfficeElement>, Option<OfficeElement>)>,
+    pub differences: Vec<Difference>,
+}
+
+/// Returns `hora` on `date` ("MM-DD-YYYY") in `version_a` and `version_b`,
+/// aligned, with the summary of their differences.
+pub fn compare_hour(
+    ctx: &mut SetupStringContext,
+    date: &str,
+    hora: Hora,
+    lang: &str,
+    version_a: &str,
+    version_b: &str,
+) -> io::Result<HourComparison> {
+    let a = Kalendar::new(version_a).build_hour_elements(ctx, date, hora, lang)?;
+    let b = Kalendar::new(version_b).build_hour_elements(ctx, date, hora, lang)?;
+    Ok(compare_elements(a, b))
+}
+
+/// Aligns the elements of two versions of an hour and sums up their
+/// differences.
+pub fn compare_elements(a: Vec<OfficeElement>, b: Vec<OfficeElement>) -> HourComparison {
+    let mut differences = Vec::new();
+    let (psalms_a, psalms_b) = (psalms(&a), psalms(&b));
+    if psalms_a != psalms_b {
+        differences.push(Difference::Psalms { a: psalms_a, b: psalms_b });
+    }
+    let (lessons_a, lessons_b) = (lessons(&a), lessons(&b));
+    if lessons_a != lessons_b {
+        differences.push(Difference::Lessons { a: lessons_a, b: lessons_b });
+    }
+
+    let mut b: Vec<Option<OfficeElement>> = b.into_iter().map(Some).collect();
+    let mut pairs = Vec::new();
+    let mut next_b = 0;
+    for element in a {
+        let found = title(&element).and_then(|t| {
+            (next_b..b.len()).find(|&j| b[j].as_ref().and_then(title) == Some(t))
+        });
+        match found {
+            Some(j) => {
+                // The chapters of B skipped to get there are said in B only.
+                pairs.extend(b[next_b..j].iter_mut().filter_map(Option::take).map(|element| (None, Some(element))));
+                pairs.push((Some(element), b[j].take()));
+                next_b = j + 1;
+            }
+            None => pairs.push((Some(element), None)),
+        }
+    }
+    pairs.extend(b.into_iter().skip(next_b).flatten().map(|element| (None, Some(element))));
+
+    for pair in &pairs {
+        let (section, in_a) = match pair {
+            (Some(element), None) => (element, true),
+            (None, Some(element)) => (element, false),
+            _ => continue,
+        };
+        if let Some(title) = title(section) {
+            differences.push(Difference::Omitted { section: title.to_string(), in_a });
+        }
+    }
+    HourComparison { pairs, differences }
+}
+
+fn title(element: &OfficeElement) -> Option<&str> {
+    match element {
+        OfficeElement::Section { title, .. } => Some(title),
+        _ => None,
+    }
+}
+
+/// All the elements of `elements`, with those of their sections.
+fn flatten(elements: &[OfficeElement]) -> Vec<&OfficeElement> {
+    elements.iter().flat_map(|e| std::iter::once(e).chain(flatten(e.elements()))).collect()
+}
+
+/// The headings of the psalms, in order.
+fn psalms(elements: &[OfficeElement]) -> Vec<String> {
+    flatten(elements)
+        .into_iter()
+        .filter_map(|e| match e {
+            OfficeElement::Psalm { title, .. } => Some(title.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The text of the lessons: what follows a "Lectio" rubric up to the next
+/// rubric or responsory.
+fn lessons(elements: &[OfficeElement]) -> Vec<String> {
+    let mut lessons = Vec::new();
+    let mut current: Option<String> = None;
+    for element in flatten(elements) {
+        match element {
+            OfficeElement::Rubric(r) if contains_ci(r, "Lectio") || contains_ci(r, "Lesson") => {
+                lessons.extend(current.replace(String::new()));
+            }
+            OfficeElement::Text(t) => {
+                if let Some(lesson) = current.as_mut() {
+                    if !lesson.is_empty() {
+                        lesson.push('\n');
+                    }
+                    lesson.push_str(t);
+                }
+            }
+            OfficeElement::Section { .. } => {}
+            _ => lessons.extend(current.take()),
+        }
+    }
+    lessons.extend(current);
+    lessons
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::horas::elements::parse_hour;
+
+    #[test]
+    fn test_compare_elements() {
+        let a = parse_hour(
+            "#Incipit\nDeus in adjutorium\n#Psalmi\n!Psalmus 92\n92:1 Dóminus regnávit.\n!Psalmus 99\n99:1 Jubiláte Deo.\n\
+             #Preces\nKyrie eleison.\n#Oratio\nOrémus.",
+        );
+        let b = parse_hour(
+            "#Incipit\nDeus in adjutorium\n#Hymnus\nÆterne rerum Cónditor\n#Psalmi\n!Psalmus 92\n92:1 Dóminus regnávit.\n!Psalmus 62\n62:1 Deus, Deus meus.\n#Oratio\nOrémus.",
+        );
+        let cmp = compare_elements(a, b);
+        let titles: Vec<(Option<&str>, Option<&str>)> = cmp
+            .pairs
+            .iter()
+            .map(|(a, b)| (a.as_ref().and_then(title), b.as_ref().and_then(title)))
+            .collect();
+        assert_eq!(titles, [
+            (Some("Incipit"), Some("Incipit")),
+            (None, Some("Hymnus")),
+            (Some("Psalmi"), Some("Psalmi")),
+            (Some("Preces"), None),
+            (Some("Oratio"), Some("Oratio")),
+        ]);
+        assert_eq!(cmp.differences, [
+            Difference::Psalms {
+                a: vec!["Psalmus 92".to_string(), "Psalmus 99".to_string()],
+                b: vec!["Psalmus 92".to_string(), "Psalmus 62".to_string()],
+            },
+            Difference::Omitted { section: "Hymnus".to_string(), in_a: false },
+            Difference::Omitted { section: "Preces".to_string(), in_a: true },
+        ]);
+        assert_eq!(cmp.differences[2].to_string(), "Preces: only in version A");
+    }
+
+    #[test]
+    fn test_compare_lessons() {
+        let a = parse_hour("#Nocturn 1\n!Lectio 1\nIn princípio.\nR. Ecce * Agnus.\n!Lectio 2\nErat lux.");
+        let b = parse_hour("#Nocturn 1\n!Lectio 1\nIn princípio.\nR. Ecce * Agnus.\n!Lectio 2\nFuit homo.");
+        let cmp = compare_elements(a, b);
+        assert_eq!(cmp.differences, [Difference::Lessons {
+            a: vec!["In princípio.".to_string(), "Erat lux.".to_string()],
+            b: vec!["In princípio.".to_string(), "Fuit homo.".to_string()],
+        }]);
+    }
+}
@@ -7,9 +7,21 @@
 //! that the same logic is preserved. All dates are handled using a purely
 //! algorithmic approach, avoiding external libraries, and thus can handle
 //! historical dates outside the usual platform range if needed.
+//!
+//! This is the policy of the whole crate: date math is proleptic civil
+//! arithmetic on (day, month, year) triples of a calendar (Gregorian unless
+//! a `CalendarSystem` says otherwise), with no time of day, timezone or
+//! locale. Nothing computes a date from the system clock or `chrono::Local`;
+//! only the entry points (the command line and the server) read the clock,
+//! to default to the date of today.
 
 use chrono::Datelike;
 
+#[cfg(feature = "reference-tests")]
+mod reference;
+#[cfg(feature = "reference-tests")]
+pub use reference::{verify_against_reference, ReferenceMismatch};
+
 /// Returns `true` if the given year is a leap year under the Gregorian rules.
 ///
 /// ```
@@ -26,18 +38,94 @@ pub fn leap_year(year: i32) -> bool {
     (year % 4 == 0) && ((year % 100 != 0) || (year % 400 == 0))
 }
 
-/// Computes the date of Easter (day, month, year) for the given year (Gregorian).
+/// The calendar in which the dates of a computation are reckoned.
+///
+/// The Gregorian calendar is the one of the rubrics since 1582; the Julian
+/// one serves for the offices before the reform and for the Old Calendarist
+/// usage. In the Julian calendar a date (day, month, year) is a Julian date
+/// throughout: its day of the week, the leap years and Easter follow the
+/// Julian rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CalendarSystem {
+    #[default]
+    Gregorian,
+    Julian,
+}
+
+impl CalendarSystem {
+    /// Returns `true` if `year` is a leap year in this calendar.
+    ///
+    /// ```
+    /// # use divinum_officium::date::CalendarSystem;
+    /// assert!(!CalendarSystem::Gregorian.leap_year(1900));
+    /// assert!(CalendarSystem::Julian.leap_year(1900));
+    /// ```
+    pub fn leap_year(self, year: i32) -> bool {
+        match self {
+            CalendarSystem::Gregorian => leap_year(year),
+            CalendarSystem::Julian => year.rem_euclid(4) == 0,
+        }
+    }
+
+    /// The day-of-year index (1-based) of a date of this calendar.
+    pub fn date_to_ydays(self, day: u32, month: u32, year: i32) -> u32 {
+        const MONTHS_CUM: [u32; 12] = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+        MONTHS_CUM[(month - 1) as usize] + day + u32::from(month > 2 && self.leap_year(year))
+    }
+
+    /// The date `(day, month, year)` of the day-of-year index `day_of_year`
+    /// of `year` in this calendar.
+    pub fn ydays_to_date(self, day_of_year: u32, year: i32) -> (u32, u32, i32) {
+        const MONTH_LENGTH: [u32; 12] = [31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+        let mut d = day_of_year;
+        for (m, len) in MONTH_LENGTH.iter().enumerate() {
+            let len = len + u32::from(m == 1 && self.leap_year(year));
+            if d <= len {
+                return (d, m as u32 + 1, year);
+            }
+            d -= len;
+        }
+        (d, 13, year)
+    }
+
+    /// The day of the week (0 = Sunday) of a date of this calendar.
+    ///
+    /// ```
+    /// # use divinum_officium::date::CalendarSystem;
+    /// // 25 December 2023 (Julian) is 7 January 2024 (Gregorian), a Sunday.
+    /// assert_eq!(CalendarSystem::Julian.day_of_week(25, 12, 2023), 0);
+    /// assert_eq!(CalendarSystem::Gregorian.day_of_week(25, 12, 2023), 1);
+    /// ```
+    pub fn day_of_week(self, day: u32, month: u32, year: i32) -> u32 {
+        match self {
+            CalendarSystem::Gregorian => day_of_week(day, month, year),
+            CalendarSystem::Julian => {
+                let (d, m, y) = julian_to_gregorian(day, month, year);
+                day_of_week(d, m, y)
+            }
+        }
+    }
+}
+
+/// Computes the date of Easter (day, month, year) for the given year, as a
+/// date of `calendar`.
 ///
-/// This follows the algorithm also found in `Date::Easter` (CPAN), known as
-/// “Anonymous Gregorian Computus”:
+/// For the Gregorian calendar this follows the algorithm also found in
+/// `Date::Easter` (CPAN), known as “Anonymous Gregorian Computus”; for the
+/// Julian one it is `julian_easter()`:
 ///
 /// ```
-/// # use divinum_officium::date::geteaster;
-/// let (eday, emonth, eyear) = geteaster(2024);
+/// # use divinum_officium::date::{geteaster, CalendarSystem};
+/// let (eday, emonth, eyear) = geteaster(2024, CalendarSystem::Gregorian);
 /// // Easter 2024 is 03-31-2024
 /// assert_eq!((eday, emonth, eyear), (31, 3, 2024));
+/// assert_eq!(geteaster(2025, CalendarSystem::Julian), (7, 4, 2025));
 /// ```
-pub fn geteaster(year: i32) -> (u32, u32, i32) {
+pub fn geteaster(year: i32, calendar: CalendarSystem) -> (u32, u32, i32) {
+    if calendar == CalendarSystem::Julian {
+        return julian_easter(year);
+    }
     // G = year mod 19
     // C = year / 100
     // H = (C - C/4 - (8*C+13)/25 + 19*G + 15) mod 30
@@ -62,27 +150,180 @@ pub fn geteaster(year: i32) -> (u32, u32, i32) {
     (day, month, year)
 }
 
+/// Computes the date of Easter (day, month, year) in the Julian calendar,
+/// by the computus of the Julian calendar (as the Eastern churches still
+/// reckon it). `julian_to_gregorian()` gives the same day in the Gregorian
+/// calendar.
+///
+/// ```
+/// # use divinum_officium::date::{julian_easter, julian_to_gregorian};
+/// assert_eq!(julian_easter(2025), (7, 4, 2025));
+/// assert_eq!(julian_to_gregorian(7, 4, 2025), (20, 4, 2025));
+/// assert_eq!(julian_to_gregorian(7, 4, 1570), (17, 4, 1570)); // proleptic
+/// ```
+pub fn julian_easter(year: i32) -> (u32, u32, i32) {
+    let y = year as i64;
+    let d = (19 * (y % 19) + 15) % 30;
+    let e = (2 * (y % 4) + 4 * (y % 7) - d + 34) % 7;
+    let month = (d + e + 114) / 31;
+    let day = (d + e + 114) % 31 + 1;
+    (day as u32, month as u32, year)
+}
+
+/// Converts a date of the Julian calendar to the Gregorian calendar (for
+/// the years before 1582 the Gregorian date is proleptic).
+pub fn julian_to_gregorian(day: u32, month: u32, year: i32) -> (u32, u32, i32) {
+    // Through the Julian day number.
+    let a = (14 - month as i64) / 12;
+    let y = year as i64 + 4800 - a;
+    let m = month as i64 + 12 * a - 3;
+    let jdn = day as i64 + (153 * m + 2) / 5 + 365 * y + y / 4 - 32083;
+
+    let a = jdn + 32044;
+    let b = (4 * a + 3) / 146097;
+    let c = a - 146097 * b / 4;
+    let d = (4 * c + 3) / 1461;
+    let e = c - 1461 * d / 4;
+    let m = (5 * e + 2) / 153;
+    let day = e - (153 * m + 2) / 5 + 1;
+    let month = m + 3 - 12 * (m / 10);
+    let year = 100 * b + d - 4800 + m / 10;
+    (day as u32, month as u32, year as i32)
+}
+
+/// Returns the dominical (Sunday) letter of `year`: the letter, from A for
+/// 1 January to G for 7 January, of the first Sunday of the year. A leap
+/// year has two, the second serving from March (after the leap day).
+///
+/// ```
+/// # use divinum_officium::date::dominical_letter;
+/// assert_eq!(dominical_letter(2025), "E");
+/// assert_eq!(dominical_letter(2024), "GF");
+/// assert_eq!(dominical_letter(2023), "A");
+/// ```
+pub fn dominical_letter(year: i32) -> String {
+    let first_sunday = (7 - day_of_week(1, 1, year)) % 7;
+    let letter = (b'A' + first_sunday as u8) as char;
+    if leap_year(year) {
+        let second = (b'A' + ((first_sunday + 6) % 7) as u8) as char;
+        format!("{}{}", letter, second)
+    } else {
+        letter.to_string()
+    }
+}
+
+/// Returns the Roman indiction (1..=15) of `year`, its place in the 15-year
+/// cycle counted from 3 BC, as the Martyrology and old documents date by it.
+///
+/// ```
+/// # use divinum_officium::date::indiction;
+/// assert_eq!(indiction(2025), 3);
+/// assert_eq!(indiction(2037), 15);
+/// ```
+pub fn indiction(year: i32) -> i32 {
+    (year + 2).rem_euclid(15) + 1
+}
+
+/// Returns the golden number (1..=19) of `year`, its place in the 19-year
+/// lunar cycle.
+///
+/// ```
+/// # use divinum_officium::date::golden_number;
+/// assert_eq!(golden_number(2000), 6);
+/// assert_eq!(golden_number(2025), 12);
+/// ```
+pub fn golden_number(year: i32) -> i32 {
+    year.rem_euclid(19) + 1
+}
+
+/// Returns the Gregorian epact (0..=29, 0 being written `*`) of `year`: the
+/// age of the moon on 1 January in the ecclesiastical lunar calendar.
+///
+/// The epact follows the golden number, corrected by the solar and lunar
+/// equations of the century (Clavius).
+///
+/// ```
+/// # use divinum_officium::date::epact;
+/// assert_eq!(epact(2000), 24);
+/// assert_eq!(epact(2024), 19);
+/// assert_eq!(epact(2025), 0);
+/// assert_eq!(epact(1900), 29);
+/// ```
+pub fn epact(year: i32) -> i32 {
+    let golden = golden_number(year);
+    let century = year / 100 + 1;
+    let solar = 3 * century / 4 - 12;
+    let lunar = (8 * century + 5) / 25 - 5;
+    (11 * golden + 20 + lunar - solar).rem_euclid(30)
+}
+
+/// Returns the age of the moon (1..=30) on the given date in the Gregorian
+/// ecclesiastical lunar calendar, as announced in the Martyrology.
+///
+/// The new moons fall on the days of `STARDAYS` moved back by the epact of
+/// the year. In the hollow months after every other entry of the table, as
+/// in the calendar of Clavius (and `epactcycle()`), epacts xxv and xxiv fall
+/// on the same day, as do 25 (a golden number above 11) and xxvi. In a leap
+/// year the bissextile day (24 February) has the same age as the day after it.
+///
+/// ```
+/// # use divinum_officium::date::lunar_day;
+/// // The paschal full moons (luna XIV) of 2000, 2024 and 2025.
+/// assert_eq!(lunar_day(18, 4, 2000), 14);
+/// assert_eq!(lunar_day(25, 3, 2024), 14);
+/// assert_eq!(lunar_day(13, 4, 2025), 14);
+/// assert_eq!(lunar_day(24, 2, 2024), lunar_day(25, 2, 2024));
+/// ```
+pub fn lunar_day(day: u32, month: u32, year: i32) -> u32 {
+    let epact = epact(year);
+    let golden = golden_number(year);
+    let mut yday = date_to_ydays(day, month, year) as i32;
+    if leap_year(year) && yday >= 56 {
+        yday -= 1;
+    }
+    let newmoon = crate::STARDAYS
+        .iter()
+        .enumerate()
+        .map(|(k, &star)| match epact {
+            _ if k % 2 == 1 || epact < 25 => star - epact,
+            25 if golden <= 11 => star - 24,
+            _ => star - epact + i32::from(epact > 25),
+        })
+        .take_while(|&s| s <= yday)
+        .last()
+        .unwrap_or(1 - epact);
+    (yday - newmoon + 1).clamp(1, 30) as u32
+}
+
 /// Returns the day-of-year (1-based) for the First Sunday of Advent of `year`.
 ///
 /// The First Sunday of Advent is the Sunday nearest to November 30 (St. Andrew),
 /// but always before December 25. For the Divinum Officium logic, it’s computed
 /// by backing up from Christmas to the previous Sunday minus 21 days (3 weeks).
 ///
+/// The index is the one of the dates of `calendar`:
+///
 /// ```
-/// # use divinum_officium::date::getadvent;
-/// let advent_2023 = getadvent(2023);
-/// assert_eq!(getadvent(2023), 337);
+/// # use divinum_officium::date::{getadvent, CalendarSystem};
+/// assert_eq!(getadvent(2023, CalendarSystem::Gregorian), 337);
+/// // Christmas 2023 (Julian) is a Sunday: Advent begins on 27 November.
+/// assert_eq!(getadvent(2023, CalendarSystem::Julian), 331);
 /// ```
-pub fn getadvent(year: i32) -> u32 {
+pub fn getadvent(year: i32, calendar: CalendarSystem) -> u32 {
     // Start from Christmas (Dec 25) in the given year.
-    let christmas_ydays = date_to_ydays(25, 12, year);
-    let christmas_dow = day_of_week(25, 12, year);
+    let christmas_ydays = calendar.date_to_ydays(25, 12, year);
+    let christmas_dow = calendar.day_of_week(25, 12, year);
     // The day_of_week returns 0=Sunday, 1=Monday, etc. We want
     // the Sunday prior to Christmas, minus an additional 21 days:
     //   advent1 = Christmas - christmas_dow - 21
     // Because if christmas_dow = 0 => Christmas is Sunday => Advent starts 28 days earlier.
     // If christmas_dow=1 => Monday => Advent starts 22 days earlier, and so on.
     // This matches the original code logic: 1st Sunday of Advent is 3 Sundays before Christmas Sunday.
+    //
+    // A Christmas on a Sunday is not its own "Sunday before": counted as 0,
+    // it would put Advent on 4 December, three Sundays short (2022, 2011).
+    // It is counted as 7, so that the fourth Sunday is the 18th.
+    let christmas_dow = if christmas_dow == 0 { 7 } else { christmas_dow };
     let advent1 = christmas_ydays as i32 - christmas_dow as i32 - 21;
     advent1 as u32
 }
@@ -173,15 +414,20 @@ pub fn ydays_to_date(day_of_year: u32, year: i32) -> (u32, u32, i32) {
 /// - `day`, `month`, `year` = the current date
 /// - `tomorrow` indicates if we want the label for the “next” day (used in e.g. Vesper logic)
 /// - `missa` toggles a small variant label in the post-Pentecost/Epiphany season
+/// - `calendar` is the calendar of the date, and of the Easter and Advent it
+///   is reckoned from
 ///
 /// This function replicates a complicated logic deciding which period
 /// of the year we are in (Advent, Christmas, Epiphany, Septuagesima,
 /// Lent, Easter, Pentecost, or after Pentecost).
 ///
 /// ```
-/// # use divinum_officium::date::getweek;
-/// let week_label = getweek(20, 12, 2023, false, false);
+/// # use divinum_officium::date::{getweek, CalendarSystem};
+/// let week_label = getweek(20, 12, 2023, false, false, CalendarSystem::Gregorian);
 /// assert_eq!(week_label, "Adv3".to_string()); // third week of advent
+/// // 20 April 2025 (Gregorian) is Easter; in the Julian calendar it is 7 April.
+/// assert_eq!(getweek(20, 4, 2025, false, false, CalendarSystem::Gregorian), "Pasc0");
+/// assert_eq!(getweek(7, 4, 2025, false, false, CalendarSystem::Julian), "Pasc0");
 /// ```
 pub fn getweek(
     day: u32,
@@ -189,17 +435,18 @@ pub fn getweek(
     year: i32,
     tomorrow: bool,
     missa: bool,
+    calendar: CalendarSystem,
 ) -> String {
     // Convert to day-of-year, possibly increment for "tomorrow."
-    let mut t = date_to_ydays(day, month, year) as i32;
+    let mut t = calendar.date_to_ydays(day, month, year) as i32;
     if tomorrow {
         t += 1;
     }
 
     // Advent starts:
-    let advent1 = getadvent(year) as i32;
+    let advent1 = getadvent(year, calendar) as i32;
     // Christmas day-of-year
-    let christmas = date_to_ydays(25, 12, year) as i32;
+    let christmas = calendar.date_to_ydays(25, 12, year) as i32;
     let t_day = if tomorrow { day + 1 } else { day } as i32;
 
     // If t >= advent1 but < Christmas, it's Advent
@@ -213,7 +460,7 @@ pub fn getweek(
 
     // If within the days close to Jan 6: handle Christmas/Epiphany boundary
     // The "ordtime" logic (the “6 + 7 - day_of_week(6, 1, year)” portion).
-    let ordtime = 6 + 7 - day_of_week(6, 1, year) as i32;
+    let ordtime = 6 + 7 - calendar.day_of_week(6, 1, year) as i32;
 
     if month == 1 && (day as i32) < (ordtime - (tomorrow as i32)) {
         // still Christmas time
@@ -221,8 +468,8 @@ pub fn getweek(
     }
 
     // Easter
-    let (e_day, e_month, _eyear) = geteaster(year);
-    let easter_ydays = date_to_ydays(e_day, e_month, year) as i32;
+    let (e_day, e_month, _eyear) = geteaster(year, calendar);
+    let easter_ydays = calendar.date_to_ydays(e_day, e_month, year) as i32;
 
     // Pre-Lent / Quadragesima
     if t < easter_ydays - 63 {
@@ -290,9 +537,12 @@ pub fn getweek(
 /// Most uses of this logic in Divinum Officium are for partial expansions of August–December
 /// ferias, bridging to an additional file like `Tempora/081-1.txt`.
 ///
+/// The date is one of `calendar`, in which the weeks and Advent are
+/// reckoned.
+///
 /// ```
-/// # use divinum_officium::date::monthday;
-/// let md = monthday(8, 9, 2023, false, false);
+/// # use divinum_officium::date::{monthday, CalendarSystem};
+/// let md = monthday(8, 9, 2023, false, false, CalendarSystem::Gregorian);
 /// // Might return "081-1-2" or similar. The original code returns "081-1" with a suffix day-of-week.
 /// if md.is_empty() {
 ///    // Not in that chunk
@@ -303,13 +553,14 @@ pub fn monthday(
     month: u32,
     year: i32,
     modernstyle: bool,
-    tomorrow: bool
+    tomorrow: bool,
+    calendar: CalendarSystem,
 ) -> String {
     // Only for months >= 7 in original code
     if month < 7 {
         return "".to_string();
     }
-    let day_of_year = date_to_ydays(day, month, year);
+    let day_of_year = calendar.date_to_ydays(day, month, year);
     let mut base = day_of_year as i32;
     if tomorrow {
         base += 1;
@@ -318,13 +569,12 @@ pub fn monthday(
     // detect the first Sunday for each month from Aug=8..Dec=12
     // store those in an array for day-of-year, see how far we got.
     // If base < first_sunday, no result. If base >= that sunday => lit_month = that month
-    let leap = leap_year(year);
     let mut lit_month = 0;
     let mut first_sunday_day_of_year = Vec::new();
     for m in 8..=12 {
         // day-of-year for 1st of month
-        let first_of_month = date_to_ydays(1, m, year);
-        let dofweek = day_of_week(1, m, year);
+        let first_of_month = calendar.date_to_ydays(1, m, year);
+        let dofweek = calendar.day_of_week(1, m, year);
         // This replicates: first_sunday_day_of_year = first_of_month - dofweek + 7 if dofweek >=4
         // in the original code, plus a condition if modernstyle => dofweek=0 => ...
         // The original code uses a repeated approach that tries to ensure the first Sunday is
@@ -345,7 +595,7 @@ pub fn monthday(
     }
     // If > 10 => might check Advent boundary
     if lit_month > 10 {
-        let advent = getadvent(year) as i32;
+        let advent = getadvent(year, calendar) as i32;
         if base >= advent {
             return "".to_string();
         }
@@ -353,12 +603,12 @@ pub fn monthday(
 
     // figure out which index in `first_sunday_day_of_year` is for our lit_month
     let idx = (lit_month - 8) as usize;
-    let day_of_week = day_of_week(day, month, year);
+    let day_of_week = calendar.day_of_week(day, month, year);
     let mut w = (base - first_sunday_day_of_year[idx]) / 7; // which week
     // special handling for October (10) + 1960 rubrics => skipping certain weeks
     if lit_month == 10 && modernstyle && w >= 2 {
         // The original logic: “the III. week vanishes in certain years”
-        let offset = ydays_to_date(first_sunday_day_of_year[idx] as u32, year);
+        let offset = calendar.ydays_to_date(first_sunday_day_of_year[idx] as u32, year);
         let first_sunday_day = offset.0;
         // If that day is >=4 => skip the 3rd
         if first_sunday_day >= 4 {
@@ -367,7 +617,7 @@ pub fn monthday(
     }
     // special handling for November
     if lit_month == 11 && (w > 0 || modernstyle) {
-        let advent = getadvent(year) as i32;
+        let advent = getadvent(year, calendar) as i32;
         // The code uses 4 - floor((advent - base - 1)/7).
         // Then if modernstyle => skip the second week
         let alt_w = 4 - ((advent - base - 1) / 7);
@@ -392,18 +642,19 @@ pub fn monthday(
 /// Returns the special Divinum Officium “Sancti” folder date string in `MM-DD` format.
 /// This function adjusts for leap year in the historical sense: if day=24 and month=2 in
 /// a leap year, it becomes “02-29”; subsequent days in February are effectively shifted.
+/// The leap years are those of `calendar`.
 ///
 /// ```
-/// # use divinum_officium::date::get_sday;
-/// assert_eq!(get_sday(2, 24, 2024), "02-29");
-/// assert_eq!(get_sday(2, 25, 2024), "02-30");
+/// # use divinum_officium::date::{get_sday, CalendarSystem};
+/// assert_eq!(get_sday(2, 24, 2024, CalendarSystem::Gregorian), "02-29");
+/// assert_eq!(get_sday(2, 25, 2024, CalendarSystem::Gregorian), "02-30");
 /// // And so forth until 02-31 => after that, 03-01 is 03-01 again.
 /// ```
-pub fn get_sday(month: u32, day: u32, year: i32) -> String {
+pub fn get_sday(month: u32, day: u32, year: i32, calendar: CalendarSystem) -> String {
     // The leap day is “kept” on Feb 24 => “02-29”.
     // Then 25 => “02-30”, 26 => “02-31”, 27 => “02-32” if a leap year.
     // That’s how older rubrics number them in “Sancti/” data files.
-    if leap_year(year) && month == 2 {
+    if calendar.leap_year(year) && month == 2 {
         if day == 24 {
             // 24 => 29
             return "02-29".to_string();
@@ -433,10 +684,10 @@ pub fn nextday(month: u32, day: u32, year: i32) -> String {
     let max = if leap_year(year) { 366 } else { 365 };
     if total > max {
         // if we pass end of the year, jump to 1 Jan next year
-        return get_sday(1, 1, year + 1);
+        return get_sday(1, 1, year + 1, CalendarSystem::Gregorian);
     }
     let (d2, m2, y2) = ydays_to_date(total as u32, year);
-    get_sday(m2, d2, y2)
+    get_sday(m2, d2, y2, CalendarSystem::Gregorian)
 }
 
 /// Takes an original date string in `MM-DD-YYYY` format, shifts it by `inc` days
@@ -506,11 +757,10 @@ pub fn days_to_date(days: i32) -> (i32, i32, i32, i32, i32, i32, i32, i32, i32)
     if days > 0 && days < 24837 {
         // For 1970..2038 range, we can rely on standard localtime approach in the original code.
         // But let's replicate the approach in a consistent manner. We'll just do exactly:
-        let epoch_seconds = (days as i64) * 24 * 60 * 60 + 12 * 3600;
-        let naive = chrono::NaiveDateTime::from_timestamp_opt(epoch_seconds, 0);
-        if let Some(ndt) = naive {
+        // 719163 is the day number of 1970-01-01 counted from 0001-01-01.
+        let naive = chrono::NaiveDate::from_num_days_from_ce_opt(days + 719_163);
+        if let Some(date) = naive {
             // year-1900, etc.
-            let date = ndt.date();
             let sec = 0;
             let min = 0;
             let hour = 6; // forced by the original code "12:00:00"
@@ -768,3 +1018,78 @@ fn date_to_days_fallback(day: u32, month: u32, year: i32) -> i32 {
     // done
     ret
 }
+
+/// The names of a liturgical day, the `@dayname` of the Perl code: where the
+/// day stands in the Proper of Time, its office and its rank.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DayNames {
+    /// The week of the Proper of Time, e.g. "Pent12" (see `getweek()`).
+    pub week: String,
+    /// 0 = Sunday.
+    pub dayofweek: u32,
+    /// The title of the office of the day, e.g. "S. Agnetis Virginis et Martyris".
+    pub title: String,
+    /// The description of its rank, e.g. "Duplex" or "III. classis".
+    pub rank: String,
+    /// The title of the commemorated office, or empty.
+    pub commemoratio: String,
+}
+
+impl DayNames {
+    /// The day in the Proper of Time, e.g. "Pent12-3" (the name of its file
+    /// in `Tempora/`).
+    pub fn day(&self) -> String {
+        format!("{}-{}", self.week, self.dayofweek)
+    }
+
+    /// The names as the `daynames` of an `InputConfig`: the day, the title
+    /// of the office and the title of the commemoration.
+    pub fn to_vec(&self) -> Vec<String> {
+        vec![self.day(), self.title.clone(), self.commemoratio.clone()]
+    }
+}
+
+/// Returns the names of `date` ("MM-DD-YYYY") in `version`, resolving its
+/// office as `horas::Kalendar::office()` does from the Latin files of
+/// `ctx.datafolder`.
+///
+/// ```ignore
+/// let names = liturgical_daynames(&mut ctx, "01-21-2025", "Divino Afflatu")?;
+/// assert_eq!(names.day(), "Epi2-2");
+/// assert_eq!(names.title, "S. Agnetis Virginis et Martyris");
+/// ```
+pub fn liturgical_daynames(
+    ctx: &mut crate::setup_string::SetupStringContext,
+    date: &str,
+    version: &str,
+) -> std::io::Result<DayNames> {
+    let office = crate::horas::Kalendar::new(version).office(ctx, date, "Latin")?;
+    Ok(crate::horas::officium::daynames(ctx, &office, "Latin"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_getadvent_christmas_on_sunday() {
+        // Christmas 2022 was a Sunday: Advent began on 27 November, not on
+        // 4 December.
+        assert_eq!(getadvent(2022, CalendarSystem::Gregorian), date_to_ydays(27, 11, 2022));
+        assert_eq!(getadvent(2011, CalendarSystem::Gregorian), date_to_ydays(27, 11, 2011));
+        assert_eq!(getadvent(2024, CalendarSystem::Gregorian), date_to_ydays(1, 12, 2024));
+    }
+
+    #[test]
+    fn test_julian_monthday_and_sday() {
+        // Advent 2023 began on 27 November in the Julian calendar, on
+        // 3 December in the Gregorian one.
+        assert_eq!(monthday(28, 11, 2023, false, false, CalendarSystem::Julian), "");
+        assert_eq!(monthday(28, 11, 2023, false, false, CalendarSystem::Gregorian), "115-2");
+        // 1900 was a leap year only in the Julian calendar.
+        assert_eq!(get_sday(2, 24, 1900, CalendarSystem::Julian), "02-29");
+        assert_eq!(get_sday(2, 24, 1900, CalendarSystem::Gregorian), "02-24");
+        assert_eq!(CalendarSystem::Julian.ydays_to_date(60, 1900), (29, 2, 1900));
+        assert_eq!(CalendarSystem::Gregorian.ydays_to_date(60, 1900), (1, 3, 1900));
+    }
+}
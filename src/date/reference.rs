@@ -0,0 +1,116 @@
+//! reference.rs
+//!
+//! The check of `getweek()` and `monthday()` against reference tables of
+//! the labels the Perl code gives, so that a change to their arithmetic (the
+//! weeks of October and November that vanish in some years are the
+//! intricate part) is caught for every day of the years of the tables.
+//!
+//! A table is a CSV file, one call per line:
+//!
+//! ```text
+//! # function,date,flag1,flag2,expected
+//! getweek,12-20-2023,0,0,Adv3
+//! monthday,10-15-2023,1,0,102-0
+//! ```
+//!
+//! The flags are `tomorrow` and `missa` for `getweek`, `modernstyle` and
+//! `tomorrow` for `monthday`, as 0 or 1. Empty lines and lines beginning
+//! with `#` are skipped. The tables themselves (1900–2100) are generated
+//! from the Perl code and are not part of the crate.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::{getweek, monthday, CalendarSystem};
+
+/// A call whose result differs from the reference table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReferenceMismatch {
+    pub file: PathBuf,
+    /// The line of the call in `file` (1-based).
+    pub line: usize,
+    /// "getweek" or "monthday".
+    pub function: String,
+    /// "MM-DD-YYYY".
+    pub date: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+/// Checks `getweek()` and `monthday()` against the reference table `path`,
+/// or against all the `.csv` tables of the directory `path`, returning the
+/// calls whose result differs.
+///
+/// Fails with `InvalidData` for a malformed line.
+///
+/// ```
+/// # use divinum_officium::date::verify_against_reference;
+/// let path = std::env::temp_dir().join(format!("reference-{}.csv", std::process::id()));
+/// std::fs::write(&path, "getweek,12-20-2023,0,0,Adv3\ngetweek,12-21-2023,0,0,Adv4\n").unwrap();
+/// let mismatches = verify_against_reference(&path).unwrap();
+/// assert_eq!(mismatches.len(), 1);
+/// assert_eq!((mismatches[0].line, mismatches[0].actual.as_str()), (2, "Adv3"));
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+pub fn verify_against_reference(path: &Path) -> io::Result<Vec<ReferenceMismatch>> {
+    if !path.is_dir() {
+        return verify_table(path);
+    }
+    let mut tables: Vec<PathBuf> = fs::read_dir(path)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<_>>()?;
+    tables.retain(|p| p.extension().is_some_and(|e| e == "csv"));
+    tables.sort();
+    let mut mismatches = Vec::new();
+    for table in tables {
+        mismatches.extend(verify_table(&table)?);
+    }
+    Ok(mismatches)
+}
+
+fn verify_table(path: &Path) -> io::Result<Vec<ReferenceMismatch>> {
+    let text = fs::read_to_string(path)?;
+    let mut mismatches = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("{}:{}: invalid line {}", path.display(), i + 1, line));
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let [function, date, flag1, flag2, expected] = fields[..] else { return Err(invalid()) };
+        let (month, day, year) = parse_date(date).ok_or_else(invalid)?;
+        let flag = |f: &str| match f {
+            "0" => Some(false),
+            "1" => Some(true),
+            _ => None,
+        };
+        let (flag1, flag2) = flag(flag1).zip(flag(flag2)).ok_or_else(invalid)?;
+        let actual = match function {
+            "getweek" => getweek(day, month, year, flag1, flag2, CalendarSystem::Gregorian),
+            "monthday" => monthday(day, month, year, flag1, flag2, CalendarSystem::Gregorian),
+            _ => return Err(invalid()),
+        };
+        if actual != expected {
+            mismatches.push(ReferenceMismatch {
+                file: path.to_path_buf(),
+                line: i + 1,
+                function: function.to_string(),
+                date: date.to_string(),
+                expected: expected.to_string(),
+                actual,
+            });
+        }
+    }
+    Ok(mismatches)
+}
+
+/// Parses "MM-DD-YYYY" into (month, day, year).
+fn parse_date(date: &str) -> Option<(u32, u32, i32)> {
+    let mut parts = date.split('-');
+    let month = parts.next()?.parse().ok().filter(|m| (1..=12).contains(m))?;
+    let day = parts.next()?.parse().ok().filter(|d| (1..=31).contains(d))?;
+    let year = parts.next()?.parse().ok()?;
+    parts.next().is_none().then_some((month, day, year))
+}
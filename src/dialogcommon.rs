@@ -36,15 +36,21 @@
 //!   you may need references to the “setup store” or “global config.” We
 //!   provide placeholders here to illustrate the approach.
 //!
-//! - **`version_displayname(version)`**: Given a version identifier, tries
-//!   to find a “display name” substring from the “versions” line in the
-//!   dialog. If not found, returns the input `version`. This is used
-//!   to produce a more user-friendly label on the UI.
+//! - **`version_displayname(version)`**: Given a version identifier, finds
+//!   its “display name” in the “versions” section of the dialog, where an
+//!   entry is "Display name/Version". If not found, returns the input
+//!   `version`. `version_from_displayname()` maps back, and `versions()`
+//!   (or `DialogData::versions()`) lists the versions of the dialog with
+//!   their parsed `RubricsVersion`.
 
 use std::collections::HashMap;
 use once_cell::sync::Lazy;
+use std::io;
+use std::path::Path;
 use std::sync::Mutex;
 
+use crate::rubrics::RubricsVersion;
+
 // Global dialog cache using DialogData (default mode "horas")
 static DIALOG_DATA: Lazy<Mutex<DialogData>> =
     Lazy::new(|| Mutex::new(DialogData::new("horas")));
@@ -67,7 +73,7 @@ pub fn get_dialog(name: &str) -> Vec<String> {
 pub fn get_ini(file_path: &str) -> HashMap<String, String> {
     let mut result = HashMap::new();
 
-    if let Ok(content) = std::fs::read_to_string(file_path) {
+    if let Ok(content) = crate::fileio::data_source().read_to_string(file_path) {
         for line in content.lines() {
             let trimmed = line.trim();
             // Skip empty lines or comments.
@@ -89,7 +95,7 @@ pub fn get_ini(file_path: &str) -> HashMap<String, String> {
 /// It requires the line to start with a `$`, followed by a word, an equals sign (with
 /// optional spaces), and a value enclosed in single quotes. If the line does not match,
 /// None is returned.
-fn parse_line(line: &str) -> Option<(String, String)> {
+pub(crate) fn parse_line(line: &str) -> Option<(String, String)> {
     let line = line.trim();
     if !line.starts_with('$') {
         return None;
@@ -155,40 +161,39 @@ impl DialogData {
         }
     }
 
-    /// Loads the `.dialog` file (e.g. `horas.dialog` or `missa.dialog`) via
-    /// the `setupstring` approach. In Perl, this used `setupstring('', "$1.dialog")`.
-    ///
-    /// For demonstration, we mimic that by simply reading a plain text file
-    /// named e.g. `mode.dialog` from some data folder. In Divinum Officium,
-    /// that typically is `$datafolder/horas/horas.dialog` or `$datafolder/missa/missa.dialog`.
+    /// Reads `{basedir}/{mode}/{mode}.dialog`, e.g. `www/horas/horas.dialog`,
+    /// through the data source of the crate.
+    pub fn open(basedir: &Path, mode: &str) -> io::Result<Self> {
+        let path = basedir.join(mode).join(format!("{}.dialog", mode));
+        let content = crate::fileio::data_source().read_to_string(&path.to_string_lossy())?;
+        Ok(Self {
+            loaded: true,
+            data: parse_dialog(&content),
+            mode: mode.to_string(),
+        })
+    }
+
+    /// Loads the `.dialog` file (e.g. `horas.dialog` or `missa.dialog`). In
+    /// Perl this was `%_dialog = %{ setupstring('', "$1.dialog") }`: the file
+    /// has the `[name]` sections of the data files, see `parse_dialog()`.
+    /// A missing file leaves the table empty.
     pub fn load_dialog(&mut self, full_path: &str) {
         if self.loaded {
             return;
         }
-        // In Perl, we do: %_dialog = %{ setupstring('', "$1.dialog") };
-        // then chomp all lines. We'll approximate that by reading each line
-        // as key => value. The real file might have multiple lines, or
-        // sections. This is a partial mock. In the real code, you'd integrate
-        // with `setupstring` from SetupString.
-        if let Ok(content) = std::fs::read_to_string(full_path) {
-            // Suppose each line is "key=value" for demonstration. The real
-            // "horas.dialog" might have a different structure.
-            for line in content.lines() {
-                let mut line = line.to_string();
-                line = chompd(&line);
-                // Possibly parse "key=value" or something. The real code in
-                // DO might store entire lines in a single key. We'll assume
-                // a simplistic approach:
-                if let Some(idx) = line.find('=') {
-                    let key = &line[..idx].trim();
-                    let val = &line[idx+1..].trim();
-                    self.data.insert(key.to_string(), val.to_string());
-                }
-            }
+        if let Ok(content) = crate::fileio::data_source().read_to_string(full_path) {
+            self.data = parse_dialog(&content);
         }
         self.loaded = true;
     }
 
+    /// The names of the sections of the dialog file.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.data.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
     /// Returns the raw (comma-separated) string from the dialog data,
     /// for a given key (`name`). If the key is missing, returns an empty string.
     ///
@@ -222,6 +227,47 @@ impl DialogData {
     }
 }
 
+/// Parses the content of a `.dialog` file into its sections: each `[name]`
+/// line starts a section whose value is the following lines, up to the next
+/// section, with the trailing newlines removed. Lines before the first
+/// section are ignored.
+///
+/// ```
+/// # use divinum_officium::dialogcommon::parse_dialog;
+/// let dialog = parse_dialog("[horas]\nMatutinum,Laudes,\nPrima\n\n[languages]\nLatin,English\n");
+/// assert_eq!(dialog["horas"], "Matutinum,Laudes,\nPrima");
+/// assert_eq!(dialog["languages"], "Latin,English");
+/// ```
+pub fn parse_dialog(content: &str) -> HashMap<String, String> {
+    let mut data = HashMap::new();
+    let mut current: Option<(String, String)> = None;
+    for line in content.lines() {
+        let line = chompd(line);
+        let header = line
+            .trim()
+            .strip_prefix('[')
+            .and_then(|rest| rest.strip_suffix(']'))
+            .map(|name| name.trim().to_string());
+        match (header, current.as_mut()) {
+            (Some(name), _) => {
+                if let Some((name, value)) = current.take() {
+                    data.insert(name, value.trim_end_matches('\n').to_string());
+                }
+                current = Some((name, String::new()));
+            }
+            (None, Some((_, value))) => {
+                value.push_str(&line);
+                value.push('\n');
+            }
+            (None, None) => {}
+        }
+    }
+    if let Some((name, value)) = current {
+        data.insert(name, value.trim_end_matches('\n').to_string());
+    }
+    data
+}
+
 /// A convenience function replicating the original `gethoras($C9f)`.
 /// Under the hood, it calls `get_dialog_array("horas")` and possibly
 /// restricts the array to indexes [0,1,6].
@@ -320,37 +366,73 @@ pub fn set_runtime_options(
     *smallblack = smallblack.replace("black", "");
 }
 
-/// The original code tries to find a user-friendly portion of a
-/// “versions” string that references the given `version`. If not found,
-/// just returns the input. This is a partial replication of the logic.
-pub fn version_displayname(dialog_data: &mut DialogData, version: &str) -> String {
-    let s = dialog_data.get_dialog_line("versions");
-    // The Perl approach: `my $i = index($s, $version) - 1;`
-    // if i == -1 or s[i] = ',' => return version
-    // else find the preceding comma => substring
-    if let Some(idx) = s.find(version) {
-        let i = idx as i32 - 1;
-        if i < 0 || &s.as_bytes()[i as usize..i as usize+1] == b"," {
-            return version.to_string();
-        }
-        // find rindex of ',' in s[..i-1]
-        let scope_end = (i - 1).max(0) as usize;
-        let sub_slice = &s[..scope_end];
-        if let Some(k) = sub_slice.rfind(',') {
-            let start = k + 1;
-            let extracted = &s[start..(i as usize)];
-            return extracted.trim().to_string();
-        } else {
-            return version.to_string();
-        }
-    } else {
-        version.to_string()
+/// A version of the rubrics as the "versions" section of the dialog lists
+/// it: an entry "Display name/Version" or a bare version, its own display
+/// name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionEntry {
+    /// The version as the data and the setupstring cache know it, e.g.
+    /// "Rubrics 1960 - 1960".
+    pub name: String,
+    /// The name shown to the user.
+    pub display: String,
+    /// What the name says of the rubrics.
+    pub rubrics: RubricsVersion,
+}
+
+impl VersionEntry {
+    /// Parses one entry of the "versions" section.
+    pub fn parse(entry: &str) -> Self {
+        let (display, name) = match entry.rsplit_once('/') {
+            Some((display, name)) => (display.trim(), name.trim()),
+            None => (entry.trim(), entry.trim()),
+        };
+        Self { name: name.to_string(), display: display.to_string(), rubrics: RubricsVersion::parse(name) }
+    }
+}
+
+impl DialogData {
+    /// The versions of the "versions" section, in their order.
+    pub fn versions(&mut self) -> Vec<VersionEntry> {
+        self.get_dialog_array("versions").iter().filter(|v| !v.is_empty()).map(|v| VersionEntry::parse(v)).collect()
+    }
+
+    /// The version whose display name or name is `name`, e.g. the version
+    /// "Rubrics 1960 - 1960" for "1960" if the dialog lists it as
+    /// "1960/Rubrics 1960 - 1960".
+    pub fn version_named(&mut self, name: &str) -> Option<VersionEntry> {
+        let versions = self.versions();
+        let found = versions.iter().position(|v| v.display == name).or_else(|| versions.iter().position(|v| v.name == name));
+        found.map(|i| versions[i].clone())
     }
 }
 
+/// The versions of the dialog of the crate, as `DialogData::versions()`
+/// gives them.
+pub fn versions() -> Vec<VersionEntry> {
+    DIALOG_DATA.lock().unwrap().versions()
+}
+
+/// Returns the display name of `version` from the "versions" section of the
+/// dialog, or `version` itself if the dialog does not list it.
+pub fn version_displayname(dialog_data: &mut DialogData, version: &str) -> String {
+    dialog_data
+        .versions()
+        .into_iter()
+        .find(|v| v.name == version)
+        .map_or_else(|| version.to_string(), |v| v.display)
+}
+
+/// Returns the version whose display name is `display`, or `display` itself
+/// if the dialog lists none (the name may be a version already).
+pub fn version_from_displayname(dialog_data: &mut DialogData, display: &str) -> String {
+    dialog_data.version_named(display).map_or_else(|| display.to_string(), |v| v.name)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::rubrics::RubricsFamily;
     /// Tests the helper function with several inputs.
     #[test]
     fn test_parse_line() {
@@ -379,4 +461,22 @@ mod tests {
         assert_eq!(parse_line("$var=value'"), None);  // missing opening quote
         assert_eq!(parse_line("$var='value"), None);    // missing closing quote
     }
+
+    #[test]
+    fn test_versions() {
+        let mut data = DialogData::new("horas");
+        data.loaded = true;
+        data.data = parse_dialog("[versions]\nTridentine - 1570,Divino Afflatu/Divino Afflatu - 1954,\n1960/Rubrics 1960 - 1960,Monastic Tridentinum 1617\n");
+        let versions = data.versions();
+        assert_eq!(versions.len(), 4);
+        assert_eq!(versions[2].display, "1960");
+        assert_eq!(versions[2].rubrics.family, RubricsFamily::Rubrics1960);
+        assert!(versions[3].rubrics.monastic);
+        assert_eq!(version_displayname(&mut data, "Rubrics 1960 - 1960"), "1960");
+        assert_eq!(version_displayname(&mut data, "Tridentine - 1570"), "Tridentine - 1570");
+        assert_eq!(version_displayname(&mut data, "Unknown"), "Unknown");
+        assert_eq!(version_from_displayname(&mut data, "1960"), "Rubrics 1960 - 1960");
+        assert_eq!(version_from_displayname(&mut data, "Divino Afflatu - 1954"), "Divino Afflatu - 1954");
+        assert!(data.version_named("1955").is_none());
+    }
 }
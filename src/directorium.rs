@@ -12,7 +12,11 @@
 
 use crate::date;
 use crate::fileio;
-use std::collections::HashMap;
+use crate::horas::rank::Rank;
+use crate::regex::ci_contains;
+use crate::rubrics::{RubricsFamily, RubricsVersion};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::io;
 use std::sync::Mutex;
 use once_cell::sync::Lazy;
@@ -94,6 +98,50 @@ fn is_cached(key: &str) -> bool {
     DCACHE.lock().unwrap().contains_key(key)
 }
 
+/// Evicts from the caches what was read from the file at `path` of the
+/// tables, in DATA_FOLDER ("…/Tabulae/Kalendaria/1960.txt") or relative to
+/// it ("Kalendaria/1960.txt"), after it changed: the kalendar or tempora of
+/// the versions reading it, all the transfer tables of its kind, or
+/// everything for `data.txt`. Returns the number of entries evicted.
+pub fn invalidate(path: &str) -> usize {
+    let path = path.replace('\\', "/");
+    let relative = match path.rfind("Tabulae/") {
+        Some(i) => &path[i + "Tabulae/".len()..],
+        None => path.trim_start_matches("./"),
+    };
+    let (kind, name) = relative.split_once('/').unwrap_or(("", relative));
+    let name = name.strip_suffix(".txt").unwrap_or(name);
+    let mut dcache = DCACHE.lock().unwrap();
+    let before = dcache.len();
+    if kind.is_empty() && name == "data" {
+        let mut data = DATA.lock().unwrap();
+        let evicted = data.len() + before;
+        data.clear();
+        dcache.clear();
+        return evicted;
+    }
+    let data = DATA.lock().unwrap();
+    // The versions whose data names the file.
+    let reading = |field: fn(&Data) -> &str| -> Vec<&String> {
+        data.iter().filter(|(_, d)| field(d) == name).map(|(version, _)| version).collect()
+    };
+    match kind {
+        "Kalendaria" => {
+            for version in reading(|d| &d.kalendar) {
+                dcache.remove(&format!("kalendar:{}", version));
+            }
+        }
+        "Tempora" => {
+            for version in reading(|d| &d.transfer) {
+                dcache.remove(&format!("Tempora:{}", version));
+            }
+        }
+        "Transfer" | "Stransfer" => dcache.retain(|key, _| !key.starts_with(&format!("{}:", kind))),
+        _ => {}
+    }
+    before - dcache.len()
+}
+
 /// --- Helper functions replacing regexes --- ///
 
 /// Remove an optional prefix "Hy" or "seant" from the beginning of a string.
@@ -267,7 +315,7 @@ fn load_transfer(
         }
     }
     let isleap = date::leap_year(year);
-    let (e_day, e_month, _) = date::geteaster(year);
+    let (e_day, e_month, _) = date::geteaster(year, date::CalendarSystem::Gregorian);
     let e_value = e_month * 100 + e_day; // as in Perl: month*100 + day
     let letter_index = (e_value as i32 - 319 + if e_month == 4 { 1 } else { 0 }) % 7;
     let letters = ["a", "b", "c", "d", "e", "f", "g"];
@@ -403,44 +451,82 @@ pub fn get_tempora(version: &str, key: &str) -> Option<String> {
     None
 }
 
+/// Returns the table of transferred offices of `year`: each day ("MM-DD")
+/// mapped to the office(s) said on it, "~"-separated, as the `Transfer`
+/// files give them for the Easter date and dominical letter of the year.
+/// Entries of the version's `tbase` fill in the days it does not set itself,
+/// then those of its own `tbase`, and so on; a version met twice on the chain
+/// ends it, so that a cycle of `tbase`s does not loop.
+///
+/// The table also carries the `Hy…` (hymn) and `dirge…` entries used by
+/// `hymnmerge()`, `hymnshift()` and `dirge()`.
+pub fn transfer_table(year: i32, version: &str) -> HashMap<String, String> {
+    let mut table = HashMap::new();
+    let mut visited = HashSet::new();
+    let mut version = version.to_string();
+    while visited.insert(version.clone()) {
+        let cache_key = format!("Transfer:{}:{}", version, year);
+        if !is_cached(&cache_key) {
+            let _ = load_transfer(year, &version, None);
+        }
+        if let Some(map) = DCACHE.lock().unwrap().get(&cache_key) {
+            for (key, val) in map {
+                table.entry(key.clone()).or_insert_with(|| val.clone());
+            }
+        }
+        match DATA.lock().unwrap().get(&version).map(|d| d.tbase.clone()) {
+            Some(tbase) if !tbase.is_empty() => version = tbase,
+            _ => break,
+        }
+    }
+    table
+}
+
 /// Checks whether a given saint or season is transferred.
-/// Returns Some(destination) if transferred, or None otherwise.
+/// Returns Some(destination) if transferred, or None otherwise: the day
+/// ("MM-DD") of `year` to which the office `s` (e.g. "Sancti/03-19") went.
 pub fn transfered(s: &str, year: i32, version: &str) -> Option<String> {
     let s = s.replacen("SanctiM/", "", 1).replacen("Sancti/", "", 1);
     if s.trim().is_empty() {
         return None;
     }
-    let transfer_key = format!("Transfer:{}:{}", version, year);
+    let transfer_map = transfer_table(year, version);
     let temp_key = format!("Tempora:{}", version);
-    let dcache = DCACHE.lock().unwrap();
-    let transfer_map = dcache.get(&transfer_key).cloned();
-    if let Some(ref tm) = transfer_map {
-        for (key, val) in tm.iter() {
-            if key.to_lowercase().contains("dirge") || key.to_lowercase().contains("hy") {
-                continue;
-            }
-            if !val.is_empty() {
-                if !val.starts_with(key)
-                    && (s.to_lowercase().contains(&val.to_lowercase())
-                        || val.to_lowercase().contains(&s.to_lowercase()))
-                    && !tm.get(key).map_or(false, |v| v.trim_end().ends_with("v"))
-                {
-                    return Some(key.clone());
-                }
-            }
+    if !is_cached(&temp_key) {
+        let _ = load_tempora(version);
+    }
+    let temp_map = DCACHE.lock().unwrap().get(&temp_key).cloned().unwrap_or_default();
+    find_transfer(&s, &transfer_map, &temp_map)
+}
+
+/// Looks up the destination of the office `s` in a transfer table, directly
+/// or through the tempora table of the version.
+fn find_transfer(
+    s: &str,
+    transfer_map: &HashMap<String, String>,
+    temp_map: &HashMap<String, String>,
+) -> Option<String> {
+    let s_lower = s.to_lowercase();
+    for (key, val) in transfer_map.iter() {
+        if key.to_lowercase().contains("dirge") || key.to_lowercase().contains("hy") {
+            continue;
+        }
+        if !val.is_empty()
+            && !val.starts_with(key.as_str())
+            && (s_lower.contains(&val.to_lowercase()) || val.to_lowercase().contains(&s_lower))
+            && !val.trim_end().ends_with('v')
+        {
+            return Some(key.clone());
         }
     }
-    let temp_map = dcache.get(&temp_key).cloned();
-    if let Some(ref temp_map) = temp_map {
-        for (key, val) in temp_map.iter() {
-            if key.to_lowercase().contains("dirge") {
-                continue;
-            }
-            if val.to_lowercase().contains(&s.to_lowercase()) {
-                if let Some(t_val) = transfer_map.as_ref().and_then(|m| m.get(key)) {
-                    if !t_val.trim_end().ends_with("v") {
-                        return Some(key.clone());
-                    }
+    for (key, val) in temp_map.iter() {
+        if key.to_lowercase().contains("dirge") {
+            continue;
+        }
+        if val.to_lowercase().contains(&s_lower) {
+            if let Some(t_val) = transfer_map.get(key) {
+                if !t_val.trim_end().ends_with('v') {
+                    return Some(key.clone());
                 }
             }
         }
@@ -464,7 +550,7 @@ pub fn dirge(version: &str, hora: &str, day: u32, month: u32, year: i32) -> bool
         return false;
     }
     let sday = if hora.contains("Laudes") {
-        date::get_sday(month, day, year)
+        date::get_sday(month, day, year, date::CalendarSystem::Gregorian)
     } else {
         date::nextday(month, day, year)
     };
@@ -476,16 +562,79 @@ pub fn dirge(version: &str, hora: &str, day: u32, month: u32, year: i32) -> bool
 
 /// Determines whether the Matutinum Hymn should be merged with Vesperas.
 pub fn hymnmerge(version: &str, day: u32, month: u32, year: i32) -> bool {
-    let key = format!("Hy{}", date::get_sday(month, day, year));
+    let key = format!("Hy{}", date::get_sday(month, day, year, date::CalendarSystem::Gregorian));
     get_transfer(year, version, &key).map_or(false, |v| v == "1")
 }
 
 /// Determines whether the Hymns should be shifted according to the transfer table.
 pub fn hymnshift(version: &str, day: u32, month: u32, year: i32) -> bool {
-    let key = format!("Hy{}", date::get_sday(month, day, year));
+    let key = format!("Hy{}", date::get_sday(month, day, year, date::CalendarSystem::Gregorian));
     get_transfer(year, version, &key).map_or(false, |v| v == "2")
 }
 
+/// Whose Vespers are said on the evening of a day, when the office of the
+/// day (its second Vespers) meets that of the next day (its first Vespers).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Concurrence {
+    /// The second Vespers of today, with the commemoration of tomorrow if
+    /// `commemoratio`.
+    Today { commemoratio: bool },
+    /// The first Vespers of tomorrow, with the commemoration of today if
+    /// `commemoratio`.
+    Tomorrow { commemoratio: bool },
+    /// Split Vespers, "a capitulo de sequenti": the psalms of today, the
+    /// rest from the chapter of tomorrow, with the commemoration of today.
+    ACapitulo,
+}
+
+impl Concurrence {
+    /// The `vespera` of the hours: 1 for first Vespers (of tomorrow, also
+    /// from the chapter), 3 for second Vespers.
+    pub fn vespera(&self) -> i32 {
+        match self {
+            Concurrence::Today { .. } => 3,
+            Concurrence::Tomorrow { .. } | Concurrence::ACapitulo => 1,
+        }
+    }
+}
+
+/// Resolves the concurrence of the office of `today` with that of
+/// `tomorrow` (their `[Rank]` lines) at Vespers under `version`.
+///
+/// Ferias and simple feasts have no second Vespers, ferias no first
+/// Vespers; since 1955 only the feasts of the first and second class (and
+/// since 1960 only those of the first class) and the Sundays have first
+/// Vespers. Otherwise the office of the higher class has the Vespers and
+/// the other is commemorated; at equal class the Vespers are split before
+/// 1960 and are of today since.
+pub fn vespers_concurrence(today: &Rank, tomorrow: &Rank, version: &str) -> Concurrence {
+    let rubrics = RubricsVersion::parse(version);
+    let class = |rank: &Rank| rank.precedence.floor() as u32;
+    let second_vespers = today.precedence >= 2.0;
+    let first_vespers = tomorrow.precedence > 1.0
+        && !ci_contains(&tomorrow.class, "Feria")
+        && match rubrics.family {
+            RubricsFamily::Rubrics1960 => tomorrow.precedence >= 6.0 || tomorrow.is_dominica(),
+            RubricsFamily::Reduced1955 => tomorrow.precedence >= 5.0 || tomorrow.is_dominica(),
+            _ => true,
+        };
+    match (second_vespers, first_vespers) {
+        (_, false) => Concurrence::Today { commemoratio: false },
+        (false, true) => Concurrence::Tomorrow { commemoratio: false },
+        (true, true) => {
+            // The office of the first class admits no commemoration of one
+            // of the third under the rubrics of 1960.
+            let admits = |higher: &Rank, lower: &Rank| !(rubrics.is_1960() && class(higher) >= 6 && class(lower) < 5);
+            match class(today).cmp(&class(tomorrow)) {
+                Ordering::Greater => Concurrence::Today { commemoratio: admits(today, tomorrow) },
+                Ordering::Less => Concurrence::Tomorrow { commemoratio: admits(tomorrow, today) },
+                Ordering::Equal if rubrics.is_1960() => Concurrence::Today { commemoratio: true },
+                Ordering::Equal => Concurrence::ACapitulo,
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -531,6 +680,28 @@ mod tests {
         assert_eq!(check_coronatio(20, 4), None);
     }
 
+    #[test]
+    fn test_invalidate() {
+        let version = "test_invalidate";
+        DATA.lock().unwrap().insert(version.to_string(), Data {
+            kalendar: "invalidate_kal".to_string(),
+            transfer: "invalidate_tr".to_string(),
+            stransfer: "".to_string(),
+            base: "".to_string(),
+            tbase: "".to_string(),
+        });
+        {
+            let mut dcache = DCACHE.lock().unwrap();
+            dcache.insert(format!("kalendar:{}", version), HashMap::new());
+            dcache.insert(format!("Tempora:{}", version), HashMap::new());
+        }
+        assert_eq!(invalidate("Kalendaria/other.txt"), 0);
+        assert_eq!(invalidate("../../www/Tabulae/Kalendaria/invalidate_kal.txt"), 1);
+        assert!(!DCACHE.lock().unwrap().contains_key(&format!("kalendar:{}", version)));
+        assert_eq!(invalidate("Tempora\\invalidate_tr.txt"), 1);
+        assert_eq!(invalidate("Kalendaria/invalidate_kal.txt"), 0);
+    }
+
     #[test]
     fn test_get_kalendar_with_cache() {
         // Simulate a version and preloaded kalendar data.
@@ -627,6 +798,74 @@ mod tests {
         assert_eq!(transfered("Sancti/Unknown", year, version), None);
     }
 
+    #[test]
+    fn test_transfer_table_with_tbase() {
+        let (version, base, year) = ("test_transfer_child", "test_transfer_base", 2025);
+        {
+            let mut data_lock = DATA.lock().unwrap();
+            for (ver, tbase) in [(version, base), (base, "")] {
+                data_lock.insert(ver.to_string(), Data {
+                    kalendar: "".to_string(),
+                    transfer: ver.to_string(),
+                    stransfer: "".to_string(),
+                    base: "".to_string(),
+                    tbase: tbase.to_string(),
+                });
+            }
+            let mut dcache = DCACHE.lock().unwrap();
+            dcache.insert(
+                format!("Transfer:{}:{}", version, year),
+                HashMap::from([("03-20".to_string(), "03-19".to_string())]),
+            );
+            dcache.insert(
+                format!("Transfer:{}:{}", base, year),
+                HashMap::from([
+                    ("03-20".to_string(), "03-20".to_string()),
+                    ("04-28".to_string(), "03-25".to_string()),
+                ]),
+            );
+            dcache.insert(format!("Tempora:{}", version), HashMap::new());
+        }
+        let table = transfer_table(year, version);
+        assert_eq!(table.get("03-20").map(String::as_str), Some("03-19"));
+        assert_eq!(table.get("04-28").map(String::as_str), Some("03-25"));
+        // St Joseph went to the 20th, the Annunciation to the 28th of April.
+        assert_eq!(transfered("Sancti/03-19", year, version), Some("03-20".to_string()));
+        assert_eq!(transfered("Sancti/03-25", year, version), Some("04-28".to_string()));
+        assert_eq!(transfered("Sancti/03-21", year, version), None);
+    }
+
+    #[test]
+    fn test_transfer_table_tbase_cycle() {
+        let (a, b, year) = ("test_transfer_cycle_a", "test_transfer_cycle_b", 2025);
+        {
+            let mut data_lock = DATA.lock().unwrap();
+            for (ver, tbase) in [(a, b), (b, a)] {
+                data_lock.insert(ver.to_string(), Data {
+                    kalendar: "".to_string(),
+                    transfer: ver.to_string(),
+                    stransfer: "".to_string(),
+                    base: "".to_string(),
+                    tbase: tbase.to_string(),
+                });
+            }
+            let mut dcache = DCACHE.lock().unwrap();
+            dcache.insert(format!("Transfer:{}:{}", a, year), HashMap::from([("03-20".to_string(), "03-19".to_string())]));
+            dcache.insert(
+                format!("Transfer:{}:{}", b, year),
+                HashMap::from([
+                    ("03-20".to_string(), "03-20".to_string()),
+                    ("04-28".to_string(), "03-25".to_string()),
+                ]),
+            );
+        }
+        // A → B → A: each table is merged once and the walk ends.
+        let table = transfer_table(year, a);
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.get("03-20").map(String::as_str), Some("03-19"));
+        assert_eq!(transfer_table(year, b).get("03-20").map(String::as_str), Some("03-20"));
+    }
+
     #[test]
     fn test_dirge() {
         // For dirge, simulate get_transfer values for "dirge1" and "dirge2".
@@ -673,5 +912,26 @@ mod tests {
         let shift = hymnshift(version, 1, 1, year);
         assert_eq!(shift, true);
     }
-}
 
+    #[test]
+    fn test_vespers_concurrence() {
+        let da = "Divino Afflatu - 1954";
+        let feria = Rank::parse("Feria Tertia;;Feria;;1");
+        let simplex = Rank::parse("S. Hyginii Papae et Martyris;;Simplex;;1.1");
+        let duplex = Rank::parse("S. Agnetis Virginis et Martyris;;Duplex;;3");
+        let duplex2 = Rank::parse("S. Vincentii et Anastasii;;Semiduplex;;3");
+        let primae = Rank::parse("Nativitas Domini;;Duplex I. classis;;6.5");
+        assert_eq!(vespers_concurrence(&duplex, &feria, da), Concurrence::Today { commemoratio: false });
+        assert_eq!(vespers_concurrence(&feria, &duplex, da), Concurrence::Tomorrow { commemoratio: false });
+        assert_eq!(vespers_concurrence(&duplex, &simplex, da), Concurrence::Today { commemoratio: true });
+        assert_eq!(vespers_concurrence(&duplex, &primae, da), Concurrence::Tomorrow { commemoratio: true });
+        assert_eq!(vespers_concurrence(&duplex, &duplex2, da), Concurrence::ACapitulo);
+        assert_eq!(Concurrence::ACapitulo.vespera(), 1);
+
+        let r60 = "Rubrics 1960 - 1960";
+        assert_eq!(vespers_concurrence(&duplex, &duplex2, r60), Concurrence::Today { commemoratio: false });
+        assert_eq!(vespers_concurrence(&duplex, &primae, r60), Concurrence::Tomorrow { commemoratio: false });
+        let dominica = Rank::parse("Dominica II post Epiphaniam;;Semiduplex Dominica minor;;5");
+        assert_eq!(vespers_concurrence(&duplex, &dominica, r60), Concurrence::Tomorrow { commemoratio: true });
+    }
+}
@@ -0,0 +1,126 @@
+//! The Divinum Officium data compiled into the binary, with the
+//! `embedded-data` feature, for WASM and single-binary deployments.
+//!
+//! The snapshot is the folder named by the `DIVINUM_OFFICIUM_DATA`
+//! environment variable at build time (the `web/www` folder of the Perl
+//! project), embedded with `include_dir!`; its files keep their paths
+//! relative to that folder. Mounted under a prefix, they replace the
+//! filesystem for the whole crate:
+//!
+//! ```ignore
+//! divinum_officium::embedded::install("www");
+//! let langs = divinum_officium::vernaculars("www/horas")?;
+//! let mut ctx = SetupStringContext::new("www/horas", "");
+//! ```
+
+use std::io;
+use std::sync::Arc;
+
+use include_dir::{include_dir, Dir, DirEntry};
+
+use crate::fileio::{self, decode, set_data_source, DataSource};
+
+// A build without the variable stops here, with this message, rather than
+// on the error of `include_dir!`.
+const _: &str = env!(
+    "DIVINUM_OFFICIUM_DATA",
+    "the `embedded-data` feature embeds the folder named by DIVINUM_OFFICIUM_DATA, \
+     which is not set: build with DIVINUM_OFFICIUM_DATA=/path/to/divinum-officium/web/www"
+);
+
+static DATA: Dir<'static> = include_dir!("$DIVINUM_OFFICIUM_DATA");
+
+/// The embedded files, as a `DataSource` answering for the paths under
+/// `prefix`.
+#[derive(Debug, Clone)]
+pub struct EmbeddedData {
+    prefix: String,
+}
+
+impl EmbeddedData {
+    /// The embedded files under `prefix`: with the prefix "www", the file
+    /// `horas/Latin/Psalterium/Comment.txt` of the snapshot is read as
+    /// `www/horas/Latin/Psalterium/Comment.txt`. An empty prefix mounts the
+    /// files at their own paths.
+    pub fn new(prefix: &str) -> Self {
+        Self { prefix: prefix.trim_matches('/').to_string() }
+    }
+
+    /// The paths of the embedded files, relative to the snapshot.
+    pub fn files() -> Vec<String> {
+        fn walk(dir: &Dir<'static>, files: &mut Vec<String>) {
+            for entry in dir.entries() {
+                match entry {
+                    DirEntry::Dir(dir) => walk(dir, files),
+                    DirEntry::File(file) => files.push(file.path().to_string_lossy().replace('\\', "/")),
+                }
+            }
+        }
+        let mut files = Vec::new();
+        walk(&DATA, &mut files);
+        files
+    }
+
+    /// The path in the snapshot of `path`, if it is under the prefix.
+    fn relative(&self, path: &str) -> Option<String> {
+        let path = path.replace('\\', "/");
+        let mut parts: Vec<&str> = path.split('/').filter(|p| !p.is_empty() && *p != ".").collect();
+        let prefix: Vec<&str> = self.prefix.split('/').filter(|p| !p.is_empty()).collect();
+        if !parts.starts_with(&prefix) {
+            return None;
+        }
+        parts.drain(..prefix.len());
+        Some(parts.join("/"))
+    }
+
+    fn lookup(&self, path: &str) -> Option<&'static [u8]> {
+        DATA.get_file(self.relative(path)?).map(|file| file.contents())
+    }
+}
+
+impl DataSource for EmbeddedData {
+    fn read_to_string(&self, path: &str) -> io::Result<String> {
+        let bytes = self.lookup(path).ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, path.to_string()))?;
+        decode(bytes.to_vec(), path).map(|(text, _)| text)
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.lookup(path).is_some()
+    }
+
+    fn read_dir(&self, path: &str) -> io::Result<Vec<fileio::DirEntry>> {
+        let not_found = || io::Error::new(io::ErrorKind::NotFound, path.to_string());
+        let relative = self.relative(path).ok_or_else(not_found)?;
+        let dir = if relative.is_empty() { &DATA } else { DATA.get_dir(&relative).ok_or_else(not_found)? };
+        Ok(dir
+            .entries()
+            .iter()
+            .map(|entry| fileio::DirEntry {
+                name: entry.path().file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(),
+                is_dir: matches!(entry, DirEntry::Dir(_)),
+            })
+            .collect())
+    }
+}
+
+/// Makes the embedded files, mounted under `prefix`, the data source of
+/// the process.
+pub fn install(prefix: &str) {
+    set_data_source(Arc::new(EmbeddedData::new(prefix)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedded_paths_under_prefix() {
+        let data = EmbeddedData::new("/www/");
+        for path in EmbeddedData::files() {
+            assert!(data.exists(&format!("www/{}", path)));
+            assert!(data.exists(&format!("/www//{}", path)));
+            assert!(!data.exists(&path));
+        }
+        assert!(data.read_to_string("www/horas/Nonexistent.txt").is_err());
+    }
+}
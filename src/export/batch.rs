@@ -0,0 +1,279 @@
+//! batch.rs
+//!
+//! The hours of a whole year, generated on several threads at once.
+//!
+//! The days of a year are independent of each other, so `generate_year()`
+//! hands them out to worker threads (one per available core by default),
+//! each with its own `SetupStringContext`. The threads are those of
+//! `std::thread::scope`, which the year does not outlive; the crate does
+//! not take a thread pool for this one loop.
+//!
+//! The parsed files are shared between the workers. The cache of a
+//! `SetupStringContext` holds them behind an `Arc`, so each worker starts
+//! with those of the caller's context without copying them, and a
+//! `SharedCache` passes on those parsed during the year: after a day a
+//! worker appends to it the files it has parsed itself, and before the next
+//! it takes the entries the others have appended since it last looked. A
+//! day that parses nothing new costs no more than a look at the length of
+//! the cache, and a file is parsed about once for the whole year rather
+//! than once per worker. The files and the inclusions the workers recorded
+//! (`SetupStringContext::included_by`) go back into the caller's context
+//! at the end. The process-wide state the hours read (the translations, the
+//! data of the directorium, the registered rites) is behind locks already.
+//!
+//! The sink is called on the calling thread, day after day in order, as
+//! soon as the days before are done, so that an ebook or an ordo can be
+//! written while the rest of the year is generated. A panic of a worker is
+//! raised again on the calling thread once the others are done.
+//!
+//! ```ignore
+//! let hours = [Hora::Laudes, Hora::Vespera];
+//! generate_year(&mut ctx, 2025, &hours, &["Latin", "English"], "Divino Afflatu", |hour| {
+//!     if let Ok(text) = hour.text {
+//!         write_page(&hour.date, hour.hora, &hour.lang, &text);
+//!     }
+//! });
+//! ```
+//!
+//! `cargo bench --bench pipelines -- generate_year` times a year of Lauds
+//! and Vespers on 1, 2 and 4 threads. It has only been measured on a single
+//! core, where no speedup is shown: about 39 ms on one, two or four
+//! threads over the generated tree of the bench, the workers taking turns
+//! on the one core. The gain on several cores is still to be measured.
+//! The threads are those of `std::thread::scope` rather than of rayon,
+//! which the crate does not depend on for this one loop.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, RwLock};
+use std::thread;
+
+use chrono::{Datelike, NaiveDate};
+
+use crate::horas::{Hora, Kalendar};
+use crate::setup_string::{FileSections, SetupStringContext};
+
+/// An hour of a day in a language, as `generate_year()` gives it.
+#[derive(Debug)]
+pub struct HourOutput {
+    /// "MM-DD-YYYY".
+    pub date: String,
+    pub hora: Hora,
+    pub lang: String,
+    /// The rendered text, or why the hour could not be built (e.g.
+    /// `InvalidInput` for an hour the office of the day does not have).
+    pub text: io::Result<String>,
+}
+
+/// The parsed files of a context, by version and path, as in
+/// `SetupStringContext::cache_by_version`.
+type Cache = HashMap<String, HashMap<String, Arc<FileSections>>>;
+
+/// A file of the cache: its version, its path and its sections.
+type Entry = (String, String, Arc<FileSections>);
+
+/// The files parsed by the workers of a year, in the order they were handed
+/// over. The same file may be there twice, parsed by two workers at once;
+/// the first is kept.
+struct SharedCache(RwLock<Vec<Entry>>);
+
+/// What a worker has of the `SharedCache`: the entries it has taken, and
+/// the files of its cache it has taken or handed over.
+struct Seen {
+    entries: usize,
+    files: HashSet<(String, String)>,
+}
+
+impl Seen {
+    /// The files `cache` starts with, which the other workers have too.
+    fn new(cache: &Cache) -> Self {
+        let files = cache.iter().flat_map(|(version, files)| files.keys().map(|f| (version.clone(), f.clone()))).collect();
+        Seen { entries: 0, files }
+    }
+}
+
+/// The number of files of `cache`.
+fn len(cache: &Cache) -> usize {
+    cache.values().map(HashMap::len).sum()
+}
+
+impl SharedCache {
+    /// Adds to `cache` the files handed over since `seen`.
+    fn take(&self, cache: &mut Cache, seen: &mut Seen) {
+        let shared = self.0.read().unwrap();
+        for (version, fname, sections) in &shared[seen.entries..] {
+            if seen.files.insert((version.clone(), fname.clone())) {
+                cache.entry(version.clone()).or_default().entry(fname.clone()).or_insert_with(|| Arc::clone(sections));
+            }
+        }
+        seen.entries = shared.len();
+    }
+
+    /// Hands over the files `cache` has parsed since `seen`, if any.
+    fn give(&self, cache: &Cache, seen: &mut Seen) {
+        if len(cache) == seen.files.len() {
+            return;
+        }
+        let mut parsed = Vec::new();
+        for (version, files) in cache {
+            for (fname, sections) in files {
+                if seen.files.insert((version.clone(), fname.clone())) {
+                    parsed.push((version.clone(), fname.clone(), Arc::clone(sections)));
+                }
+            }
+        }
+        self.0.write().unwrap().extend(parsed);
+    }
+}
+
+/// Generates `hours` in each of `langs` for every day of `year` under
+/// `version`, on as many threads as the machine has cores, and passes them
+/// to `sink` in the order of the days, then of `hours`, then of `langs`.
+pub fn generate_year<F: FnMut(HourOutput)>(
+    ctx: &mut SetupStringContext,
+    year: i32,
+    hours: &[Hora],
+    langs: &[&str],
+    version: &str,
+    sink: F,
+) {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    generate_year_with_threads(ctx, year, hours, langs, version, threads, sink);
+}
+
+/// `generate_year()` on `threads` worker threads (at least one).
+pub fn generate_year_with_threads<F: FnMut(HourOutput)>(
+    ctx: &mut SetupStringContext,
+    year: i32,
+    hours: &[Hora],
+    langs: &[&str],
+    version: &str,
+    threads: usize,
+    mut sink: F,
+) {
+    let days = days_of_year(year);
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::channel();
+    let shared = SharedCache(RwLock::new(Vec::new()));
+    let mut included_by = Vec::new();
+    thread::scope(|scope| {
+        let mut workers = Vec::new();
+        for _ in 0..threads.clamp(1, days.len().max(1)) {
+            let tx = tx.clone();
+            let mut worker = ctx.clone();
+            let mut seen = Seen::new(&worker.cache_by_version);
+            let (days, next, shared) = (&days, &next, &shared);
+            workers.push(scope.spawn(move || {
+                let kalendar = Kalendar::new(version);
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(date) = days.get(i) else { break };
+                    shared.take(&mut worker.cache_by_version, &mut seen);
+                    let mut outputs = Vec::with_capacity(hours.len() * langs.len());
+                    for &hora in hours {
+                        for &lang in langs {
+                            let text = kalendar.build_hour(&mut worker, date, hora, lang);
+                            outputs.push(HourOutput { date: date.clone(), hora, lang: lang.to_string(), text });
+                        }
+                    }
+                    shared.give(&worker.cache_by_version, &mut seen);
+                    if tx.send((i, outputs)).is_err() {
+                        break;
+                    }
+                }
+                worker.included_by
+            }));
+        }
+        drop(tx);
+
+        // The days come back in any order; they leave in the order of the year.
+        let mut pending = BTreeMap::new();
+        let mut due = 0;
+        for (i, outputs) in rx {
+            pending.insert(i, outputs);
+            while let Some(outputs) = pending.remove(&due) {
+                outputs.into_iter().for_each(&mut sink);
+                due += 1;
+            }
+        }
+        for worker in workers {
+            match worker.join() {
+                Ok(included) => included_by.push(included),
+                Err(panic) => std::panic::resume_unwind(panic),
+            }
+        }
+    });
+    for (version, fname, sections) in shared.0.into_inner().unwrap() {
+        ctx.cache_by_version.entry(version).or_default().entry(fname).or_insert(sections);
+    }
+    for (file, including) in included_by.into_iter().flatten() {
+        ctx.included_by.entry(file).or_default().extend(including);
+    }
+}
+
+/// The dates of `year` as "MM-DD-YYYY".
+fn days_of_year(year: i32) -> Vec<String> {
+    let Some(first) = NaiveDate::from_ymd_opt(year, 1, 1) else {
+        return Vec::new();
+    };
+    first
+        .iter_days()
+        .take_while(|d| d.year() == year)
+        .map(|d| format!("{:02}-{:02}-{}", d.month(), d.day(), d.year()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::data_tree;
+
+    #[test]
+    fn test_generate_year() {
+        let (_root, mut ctx) = data_tree(&[("Latin/Ordinarium/Laudes.txt", "#Incipit\nDeus in adjutorium\n")]);
+
+        let mut outputs = Vec::new();
+        generate_year_with_threads(&mut ctx, 2025, &[Hora::Laudes, Hora::Vespera], &["Latin"], "Divino Afflatu", 3, |hour| {
+            outputs.push(hour)
+        });
+        assert_eq!(outputs.len(), 2 * 365);
+        assert_eq!((outputs[0].date.as_str(), outputs[0].hora), ("01-01-2025", Hora::Laudes));
+        assert_eq!((outputs[729].date.as_str(), outputs[729].hora), ("12-31-2025", Hora::Vespera));
+        assert!(outputs[0].text.as_ref().unwrap().contains("Deus in adjutorium"));
+        // There is no Ordinarium of Vespers in the data.
+        assert_eq!(outputs[1].text.as_ref().unwrap_err().kind(), io::ErrorKind::NotFound);
+        let order: Vec<(&str, &str)> = outputs.iter().map(|o| (&o.date[..2], &o.date[3..5])).collect();
+        assert!(order.windows(2).all(|w| w[0] <= w[1]));
+        assert!(ctx.cache_by_version.values().any(|files| files.contains_key("Ordinarium/Laudes.txt")));
+        assert_eq!(days_of_year(2024).len(), 366);
+    }
+
+    #[test]
+    fn test_shared_cache() {
+        let file = |name: &str| (name.to_string(), Arc::new(FileSections::from([("Oratio".to_string(), name.to_string())])));
+        let start = Cache::from([("Latin".to_string(), HashMap::from([file("Sancti/01-21.txt")]))]);
+        let shared = SharedCache(RwLock::new(Vec::new()));
+        let (mut one, mut two) = (start.clone(), start.clone());
+        let (mut seen_one, mut seen_two) = (Seen::new(&one), Seen::new(&two));
+
+        // The files a context starts with are not handed over again.
+        shared.give(&one, &mut seen_one);
+        assert!(shared.0.read().unwrap().is_empty());
+
+        // What a worker parsed reaches the other, shared and not copied.
+        one.get_mut("Latin").unwrap().extend([file("Sancti/01-22.txt"), file("Sancti/01-23.txt")]);
+        two.get_mut("Latin").unwrap().extend([file("Sancti/01-23.txt"), file("Sancti/01-24.txt")]);
+        shared.give(&one, &mut seen_one);
+        shared.give(&two, &mut seen_two);
+        shared.take(&mut one, &mut seen_one);
+        shared.take(&mut two, &mut seen_two);
+        assert_eq!((one["Latin"].len(), two["Latin"].len()), (4, 4));
+        assert!(Arc::ptr_eq(&one["Latin"]["Sancti/01-24.txt"], &two["Latin"]["Sancti/01-24.txt"]));
+        assert_eq!(seen_one.entries, 4);
+
+        // Nothing new: nothing handed over.
+        shared.give(&one, &mut seen_one);
+        assert_eq!(shared.0.into_inner().unwrap().len(), 4);
+    }
+}
@@ -0,0 +1,306 @@
+//! book.rs
+//!
+//! The offices of a range of days as a book: a directory of HTML pages,
+//! one per hour, linked to each other in the order they are said and to a
+//! table of contents (`index.html`) listing the days and their hours, as the
+//! printable breviary of the Perl site.
+//!
+//! ```ignore
+//! let options = BookOptions::new("Divino Afflatu", "Latin");
+//! let book = book(&mut ctx, from, to, &options)?;
+//! book.write_to(Path::new("breviarium"))?;
+//! ```
+//!
+//! Each hour goes through the structured pipeline (`build_hour_elements()`),
+//! so that its antiphons, psalms and responsories are marked up as such.
+//!
+//! `book()` keeps every page in memory. For a long range (a year of offices
+//! on a small machine) `write_book()` writes each page as soon as the next
+//! one is built, keeping only the table of contents:
+//!
+//! ```ignore
+//! let contents = write_book(&mut ctx, from, to, &options, Path::new("breviarium"))?;
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use chrono::{Datelike, NaiveDate};
+
+use crate::fileio::do_write;
+use crate::horas::{Hora, Kalendar, OfficeElement};
+use crate::setup_string::{ResolveDirectives, SetupStringContext};
+
+/// What goes into a book.
+#[derive(Debug, Clone)]
+pub struct BookOptions {
+    pub version: String,
+    pub lang: String,
+    /// The hours of each day, all of them by default.
+    pub hours: Vec<Hora>,
+}
+
+impl BookOptions {
+    pub fn new(version: &str, lang: &str) -> Self {
+        Self { version: version.to_string(), lang: lang.to_string(), hours: Hora::all().to_vec() }
+    }
+
+    /// Only `hours` for each day.
+    pub fn with_hours(mut self, hours: &[Hora]) -> Self {
+        self.hours = hours.to_vec();
+        self
+    }
+}
+
+/// A page of a book: an hour of a day.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BookPage {
+    /// "MM-DD-YYYY".
+    pub date: String,
+    pub hora: Hora,
+    /// The name of the file of the page, e.g. "2025-01-25-Laudes.html".
+    pub file: String,
+    pub elements: Vec<OfficeElement>,
+}
+
+/// The days of a book, with their titles and pages in order.
+#[derive(Debug, Clone, Default)]
+pub struct Book {
+    pub title: String,
+    /// The date and title of each day.
+    pub days: Vec<(String, String)>,
+    pub pages: Vec<BookPage>,
+}
+
+/// Builds the offices from `from` to `to` (both included).
+///
+/// An hour that the office of the day does not have (e.g. Prime with the
+/// Office of the Dead) is left out; other failures end the book.
+pub fn book(ctx: &mut SetupStringContext, from: NaiveDate, to: NaiveDate, options: &BookOptions) -> io::Result<Book> {
+    build(ctx, from, to, options, |book, page| {
+        book.pages.push(page);
+        Ok(())
+    })
+}
+
+/// Builds the offices from `from` to `to` like `book()` and writes them
+/// into `dir` as they are built, creating it if needed, with the table of
+/// contents last.
+///
+/// At most two pages are held at once: a page is written when the next one
+/// (its "next" link) is known. The book returned is the table of contents:
+/// its pages have no elements.
+pub fn write_book(
+    ctx: &mut SetupStringContext,
+    from: NaiveDate,
+    to: NaiveDate,
+    options: &BookOptions,
+    dir: &Path,
+) -> io::Result<Book> {
+    fs::create_dir_all(dir)?;
+    let mut pending: Option<BookPage> = None;
+    let mut book = build(ctx, from, to, options, |book, page| match pending.replace(page) {
+        Some(current) => book.write_page(dir, current, pending.as_ref()),
+        None => Ok(()),
+    })?;
+    if let Some(last) = pending {
+        book.write_page(dir, last, None)?;
+    }
+    do_write(dir.join("index.html"), [book.index_html()])?;
+    Ok(book)
+}
+
+/// Builds the days from `from` to `to` into a book, passing each page to
+/// `on_page` as soon as it is built.
+fn build<F>(ctx: &mut SetupStringContext, from: NaiveDate, to: NaiveDate, options: &BookOptions, mut on_page: F) -> io::Result<Book>
+where
+    F: FnMut(&mut Book, BookPage) -> io::Result<()>,
+{
+    let kalendar = Kalendar::new(&options.version);
+    let mut book = Book {
+        title: format!("{} - {}", from.format("%d.%m.%Y"), to.format("%d.%m.%Y")),
+        ..Book::default()
+    };
+    for day in from.iter_days().take_while(|d| *d <= to) {
+        let date = format!("{:02}-{:02}-{}", day.month(), day.day(), day.year());
+        book.days.push((date.clone(), day_title(ctx, &kalendar, &date, &options.lang)?));
+        for &hora in &options.hours {
+            let elements = match kalendar.build_hour_elements(ctx, &date, hora, &options.lang) {
+                Ok(elements) => elements,
+                Err(e) if e.kind() == io::ErrorKind::InvalidInput => continue,
+                Err(e) => return Err(e),
+            };
+            let file = format!("{}-{}.html", day.format("%Y-%m-%d"), hora);
+            on_page(&mut book, BookPage { date: date.clone(), hora, file, elements })?;
+        }
+    }
+    Ok(book)
+}
+
+/// The title of the office of `date`: the first part of its rank, or the
+/// day of the Proper of Time.
+fn day_title(ctx: &mut SetupStringContext, kalendar: &Kalendar, date: &str, lang: &str) -> io::Result<String> {
+    let office = kalendar.office(ctx, date, lang)?;
+    Ok(ctx
+        .setupstring(lang, &office.winner, ResolveDirectives::None)
+        .and_then(|sections| sections.get("Rank").cloned())
+        .and_then(|rank| rank.lines().next().map(|l| l.split(";;").next().unwrap_or("").trim().to_string()))
+        .filter(|title| !title.is_empty())
+        .unwrap_or(office.dayname))
+}
+
+impl Book {
+    /// The table of contents: the days, each with links to its hours.
+    pub fn index_html(&self) -> String {
+        let mut body = format!("<h1>{}</h1>\n<ul>\n", escape_html(&self.title));
+        for (date, title) in &self.days {
+            let links: Vec<String> = self
+                .pages
+                .iter()
+                .filter(|p| &p.date == date)
+                .map(|p| format!("<a href=\"{}\">{}</a>", p.file, p.hora))
+                .collect();
+            body.push_str(&format!("<li>{} {}: {}</li>\n", date, escape_html(title), links.join(" ")));
+        }
+        body.push_str("</ul>\n");
+        page(&self.title, &body)
+    }
+
+    /// The page of `pages[i]`, with the links to the previous and next hours
+    /// and to the table of contents.
+    pub fn page_html(&self, i: usize) -> String {
+        let prev = i.checked_sub(1).and_then(|p| self.pages.get(p));
+        self.render_page(&self.pages[i], prev, self.pages.get(i + 1))
+    }
+
+    fn render_page(&self, current: &BookPage, prev: Option<&BookPage>, next: Option<&BookPage>) -> String {
+        let title = self.days.iter().find(|(d, _)| *d == current.date).map(|(_, t)| t.as_str()).unwrap_or("");
+        let mut nav = Vec::new();
+        if let Some(prev) = prev {
+            nav.push(format!("<a href=\"{}\">&lt; {} {}</a>", prev.file, prev.date, prev.hora));
+        }
+        nav.push("<a href=\"index.html\">Index</a>".to_string());
+        if let Some(next) = next {
+            nav.push(format!("<a href=\"{}\">{} {} &gt;</a>", next.file, next.date, next.hora));
+        }
+        let nav = format!("<nav>{}</nav>\n", nav.join(" | "));
+        let body = format!(
+            "{}<h1>{} {}</h1>\n<h2>{}</h2>\n{}{}",
+            nav,
+            current.hora,
+            current.date,
+            escape_html(title),
+            elements_html(&current.elements),
+            nav
+        );
+        page(&format!("{} {}", current.hora, current.date), &body)
+    }
+
+    /// Writes `current`, the page after the last one of the book, into
+    /// `dir`, and adds it to the book without its elements.
+    fn write_page(&mut self, dir: &Path, mut current: BookPage, next: Option<&BookPage>) -> io::Result<()> {
+        do_write(dir.join(&current.file), [self.render_page(&current, self.pages.last(), next)])?;
+        current.elements = Vec::new();
+        self.pages.push(current);
+        Ok(())
+    }
+
+    /// Writes the table of contents and the pages into `dir`, creating it
+    /// if needed.
+    pub fn write_to(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        do_write(dir.join("index.html"), [self.index_html()])?;
+        for (i, p) in self.pages.iter().enumerate() {
+            do_write(dir.join(&p.file), [self.page_html(i)])?;
+        }
+        Ok(())
+    }
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{}</title></head><body>\n{}</body></html>\n",
+        escape_html(title),
+        body
+    )
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// The text of a block, its lines separated by line breaks.
+fn lines_html(text: &str) -> String {
+    text.lines().map(escape_html).collect::<Vec<_>>().join("<br/>\n")
+}
+
+/// The HTML of the elements of an hour.
+fn elements_html(elements: &[OfficeElement]) -> String {
+    elements
+        .iter()
+        .map(|element| match element {
+            OfficeElement::Section { title, elements } => {
+                format!("<h3>{}</h3>\n{}", escape_html(title), elements_html(elements))
+            }
+            OfficeElement::Antiphon { text, .. } => {
+                format!("<p class=\"antiphon\"><span class=\"red\">Ant.</span> {}</p>\n", escape_html(text))
+            }
+            OfficeElement::Hymn { text, .. } => format!("<p class=\"hymn\">{}</p>\n", lines_html(text)),
+            OfficeElement::Responsory { text, .. } => format!("<p class=\"responsory\">{}</p>\n", lines_html(text)),
+            OfficeElement::Psalm { title, verses } => format!(
+                "<p class=\"psalm\"><span class=\"red\">{}</span><br/>\n{}</p>\n",
+                escape_html(title),
+                lines_html(&verses.join("\n"))
+            ),
+            OfficeElement::Versicle(text) => format!("<p class=\"versicle\">{}</p>\n", lines_html(text)),
+            OfficeElement::Rubric(text) => format!("<p class=\"rubric\"><i>{}</i></p>\n", escape_html(text)),
+            OfficeElement::Text(text) => format!("<p>{}</p>\n", escape_html(text)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::data_tree;
+
+    #[test]
+    fn test_book_of_two_days() {
+        let (root, mut ctx) = data_tree(&[
+            ("Latin/Tempora/Epi2-2.txt", "[Rank]\nFeria Tertia;;Feria;;1\n"),
+            ("Latin/Tempora/Epi2-3.txt", "[Rank]\nFeria Quarta;;Feria;;1\n"),
+            ("Latin/Ordinarium/Laudes.txt", "#Incipit\nDeus in adjutorium\n"),
+            ("Latin/Ordinarium/Vespera.txt", "#Incipit\nDeus in adjutorium\nAnt. Dixit Dóminus.\n"),
+        ]);
+        let options = BookOptions::new("Divino Afflatu", "Latin").with_hours(&[Hora::Laudes, Hora::Vespera]);
+        let from = NaiveDate::from_ymd_opt(2025, 1, 21).unwrap();
+        let book = book(&mut ctx, from, from.succ_opt().unwrap(), &options).unwrap();
+        assert_eq!(book.days, [
+            ("01-21-2025".to_string(), "Feria Tertia".to_string()),
+            ("01-22-2025".to_string(), "Feria Quarta".to_string()),
+        ]);
+        let files: Vec<&str> = book.pages.iter().map(|p| p.file.as_str()).collect();
+        assert_eq!(files, ["2025-01-21-Laudes.html", "2025-01-21-Vespera.html", "2025-01-22-Laudes.html", "2025-01-22-Vespera.html"]);
+
+        let index = book.index_html();
+        assert!(index.contains("<li>01-21-2025 Feria Tertia: <a href=\"2025-01-21-Laudes.html\">Laudes</a>"));
+        let page = book.page_html(1);
+        assert!(page.contains("<a href=\"2025-01-21-Laudes.html\">&lt; 01-21-2025 Laudes</a>"));
+        assert!(page.contains("<a href=\"2025-01-22-Laudes.html\">01-22-2025 Laudes &gt;</a>"));
+        assert!(page.contains("<p class=\"antiphon\"><span class=\"red\">Ant.</span> Dixit Dóminus.</p>"));
+
+        let out = root.join("book");
+        book.write_to(&out).unwrap();
+        assert!(out.join("index.html").is_file());
+        assert!(out.join("2025-01-22-Vespera.html").is_file());
+
+        let streamed = root.join("streamed");
+        let contents = write_book(&mut ctx, from, from.succ_opt().unwrap(), &options, &streamed).unwrap();
+        assert_eq!(contents.pages.len(), 4);
+        assert!(contents.pages.iter().all(|p| p.elements.is_empty()));
+        for file in ["index.html", "2025-01-21-Laudes.html", "2025-01-21-Vespera.html", "2025-01-22-Vespera.html"] {
+            assert_eq!(fs::read_to_string(streamed.join(file)).unwrap(), fs::read_to_string(out.join(file)).unwrap(), "{}", file);
+        }
+    }
+}
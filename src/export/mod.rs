@@ -0,0 +1,7 @@
+//! Exports of the offices of several days at once.
+
+pub mod batch;
+pub mod book;
+pub mod ordo;
+
+pub use ordo::ordo;
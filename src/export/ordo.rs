@@ -0,0 +1,190 @@
+//! ordo.rs
+//!
+//! The ordo of a year: for each day a one-line summary of its office, as
+//! the kalendar of the Perl site shows it and a printed *Ordo divini officii
+//! recitandi* lists it: the feast and its rank, the colour, the
+//! commemorations and the marks for Matins (nine or three lessons, whether
+//! the *Te Deum* is said) and for the Office of the Dead.
+//!
+//! ```ignore
+//! for entry in ordo(&mut ctx, 2025, "Divino Afflatu", "Latin")? {
+//!     println!("{}", entry.line());
+//! }
+//! ```
+//!
+//! `ordo()` collects the year; `ordo_entries()` yields it a day at a time
+//! and `write_ordo()` writes the lines to a `Write` as they are made.
+//!
+//! The office of each day is resolved by `Kalendar::office()` (with the
+//! transfers of the directorium), the colour by `liturgical_color()`.
+
+use std::io::{self, Write};
+
+use chrono::{Datelike, NaiveDate};
+
+use crate::directorium::dirge;
+use crate::horas::Kalendar;
+use crate::liturgical_color;
+use crate::regex::contains_ci;
+use crate::setup_string::{FileSections, ResolveDirectives, SetupStringContext};
+
+/// The ordo entry of a day.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrdoEntry {
+    /// "MM-DD-YYYY".
+    pub date: String,
+    pub feast: String,
+    /// The class of the rank, e.g. "Duplex majus" or "II. classis".
+    pub rank: String,
+    pub rank_num: f64,
+    pub color: String,
+    /// The titles of the commemorated offices.
+    pub commemorations: Vec<String>,
+    /// The number of lessons at Matins, 9 or 3.
+    pub lectiones: u8,
+    pub te_deum: bool,
+    /// Whether the Office of the Dead is said on the day.
+    pub dirge: bool,
+}
+
+impl OrdoEntry {
+    /// The line of the entry, e.g.
+    /// "01-21 S. Agnetis Virginis et Martyris, Duplex, red; 9 lect., Te Deum".
+    pub fn line(&self) -> String {
+        let mut line = format!("{} {}", &self.date[..5.min(self.date.len())], self.feast);
+        if !self.rank.is_empty() {
+            line.push_str(&format!(", {}", self.rank));
+        }
+        line.push_str(&format!(", {}", self.color));
+        for com in &self.commemorations {
+            line.push_str(&format!("; com. {}", com));
+        }
+        line.push_str(&format!("; {} lect.", self.lectiones));
+        if self.te_deum {
+            line.push_str(", Te Deum");
+        }
+        if self.dirge {
+            line.push_str("; dirge");
+        }
+        line
+    }
+}
+
+/// Returns the ordo entries of every day of `year`.
+pub fn ordo(ctx: &mut SetupStringContext, year: i32, version: &str, lang: &str) -> io::Result<Vec<OrdoEntry>> {
+    ordo_entries(ctx, year, version, lang)?.collect()
+}
+
+/// The ordo entries of the days of `year`, each resolved when it is asked
+/// for.
+pub fn ordo_entries<'a>(
+    ctx: &'a mut SetupStringContext,
+    year: i32,
+    version: &str,
+    lang: &'a str,
+) -> io::Result<impl Iterator<Item = io::Result<OrdoEntry>> + 'a> {
+    let kalendar = Kalendar::new(version);
+    let first = NaiveDate::from_ymd_opt(year, 1, 1)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("invalid year {}", year)))?;
+    Ok(first.iter_days().take_while(move |d| d.year() == year).map(move |day| {
+        let date = format!("{:02}-{:02}-{}", day.month(), day.day(), day.year());
+        ordo_entry(ctx, &kalendar, &date, lang)
+    }))
+}
+
+/// Writes the line of each day of `year` to `out`, one day at a time.
+pub fn write_ordo<W: Write>(ctx: &mut SetupStringContext, year: i32, version: &str, lang: &str, mut out: W) -> io::Result<()> {
+    for entry in ordo_entries(ctx, year, version, lang)? {
+        writeln!(out, "{}", entry?.line())?;
+    }
+    out.flush()
+}
+
+/// Returns the ordo entry of `date` ("MM-DD-YYYY") in `kalendar`.
+pub fn ordo_entry(ctx: &mut SetupStringContext, kalendar: &Kalendar, date: &str, lang: &str) -> io::Result<OrdoEntry> {
+    let office = kalendar.office(ctx, date, lang)?;
+    let winner = ctx.setupstring(lang, &office.winner, ResolveDirectives::None).unwrap_or_default();
+    let (feast, rank, rank_num) = rank_fields(&winner);
+    let feast = if feast.is_empty() { office.dayname.clone() } else { feast };
+    let commemorations = if office.commemoratio.is_empty() {
+        Vec::new()
+    } else {
+        let com = ctx.setupstring(lang, &office.commemoratio, ResolveDirectives::None).unwrap_or_default();
+        vec![rank_fields(&com).0].into_iter().filter(|t| !t.is_empty()).collect()
+    };
+    let rule = winner.get("Rule").map(String::as_str).unwrap_or("");
+    let sunday_or_feria = office.winner.starts_with("Tempora");
+    let lectiones = if contains_ci(rule, "9 lectiones") {
+        9
+    } else if contains_ci(rule, "3 lectiones") {
+        3
+    } else if rank_num >= 2.0 || (office.dayofweek == 0 && sunday_or_feria) {
+        9
+    } else {
+        3
+    };
+    // The Te Deum is not said on the days of Advent and from Septuagesima to
+    // Easter, unless a feast is kept.
+    let penitential = ["Adv", "Quadp", "Quad"].iter().any(|s| office.dayname.starts_with(s));
+    let no_te_deum = contains_ci(rule, "no Te Deum") || (penitential && sunday_or_feria);
+    Ok(OrdoEntry {
+        date: office.date.clone(),
+        color: liturgical_color(&feast).to_string(),
+        feast,
+        rank,
+        rank_num,
+        commemorations,
+        lectiones,
+        te_deum: !no_te_deum,
+        dirge: dirge(kalendar.version(), "Laudes", office.day, office.month, office.year),
+    })
+}
+
+/// The title, class and number of the `[Rank]` line of an office
+/// ("title;;class;;rank;;commune").
+fn rank_fields(office: &FileSections) -> (String, String, f64) {
+    let line = office.get("Rank").and_then(|r| r.lines().next()).unwrap_or("");
+    let mut fields = line.split(";;").map(str::trim);
+    let title = fields.next().unwrap_or("").to_string();
+    let class = fields.next().unwrap_or("").to_string();
+    let num = fields.next().and_then(|n| n.parse().ok()).unwrap_or(0.0);
+    (title, class, num)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::data_tree;
+
+    #[test]
+    fn test_ordo_entries() {
+        let (_root, mut ctx) = data_tree(&[
+            ("Latin/Tempora/Epi2-2.txt", "[Rank]\nFeria Tertia;;Feria;;1\n"),
+            ("Latin/Sancti/01-21.txt", "[Rank]\nS. Agnetis Virginis et Martyris;;Duplex;;3;;vide C6\n"),
+            ("Latin/Tempora/Adv1-0.txt", "[Rank]\nDominica I Adventus;;Semiduplex Dominica I. classis;;6\n"),
+            ("Latin/Sancti/11-30.txt", "[Rank]\nS. Andreæ Apostoli;;Duplex II. classis;;5.1\n"),
+        ]);
+        let kalendar = Kalendar::new("Divino Afflatu");
+
+        let agnes = ordo_entry(&mut ctx, &kalendar, "01-21-2025", "Latin").unwrap();
+        assert_eq!(agnes.feast, "S. Agnetis Virginis et Martyris");
+        assert_eq!(agnes.rank, "Duplex");
+        assert!(agnes.commemorations.is_empty());
+        assert_eq!(agnes.line(), format!("01-21 S. Agnetis Virginis et Martyris, Duplex, {}; 9 lect., Te Deum", agnes.color));
+
+        // 2025-11-30, the first Sunday of Advent, outranks St Andrew.
+        let advent = ordo_entry(&mut ctx, &kalendar, "11-30-2025", "Latin").unwrap();
+        assert_eq!(advent.feast, "Dominica I Adventus");
+        assert_eq!(advent.commemorations, ["S. Andreæ Apostoli"]);
+        assert_eq!(advent.lectiones, 9);
+        assert!(!advent.te_deum);
+        assert!(advent.line().contains("; com. S. Andreæ Apostoli; 9 lect."));
+
+        assert_eq!(ordo(&mut ctx, 2024, "Divino Afflatu", "Latin").unwrap().len(), 366);
+        let mut out = Vec::new();
+        write_ordo(&mut ctx, 2025, "Divino Afflatu", "Latin", &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().count(), 365);
+        assert!(out.lines().nth(20).unwrap().starts_with("01-21 S. Agnetis Virginis et Martyris, Duplex"));
+    }
+}
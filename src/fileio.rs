@@ -3,18 +3,240 @@
 //! This module provides basic file reading and writing functionality,
 //! analogous to the FileIO.pm module in the Perl codebase.
 //!
-//! Both functions assume UTF-8 encoding for input and output.
+//! Files are written in UTF-8. They are read in UTF-8, or in UTF-16 with a
+//! byte order mark; a file that is not valid UTF-8 is read as Windows-1252
+//! (of which Latin-1 text is a part), as some legacy data files are, and a
+//! file that is neither is an `InvalidData` error naming it (see `decode`).
+//!
+//! Reading goes through a `DataSource`, so that the data can be served from
+//! somewhere else than the filesystem (a zip archive, an object store, data
+//! compiled into the binary). The source is process-wide, like the caches of
+//! `directorium`, and defaults to the filesystem; `set_data_source` replaces
+//! it. With the `async` feature, `AsyncDataSource` fetches files for backends
+//! that can only be read asynchronously; as the office is built synchronously,
+//! such a backend fills a `MemorySource` with the files first.
+//!
+//! `list_files()` and `walk()` find the data files, e.g. those of
+//! `Sancti/*.txt`, through the data source too.
 
+use std::collections::HashMap;
 use std::fs;
 use std::io::{self, Write};
 use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use once_cell::sync::Lazy;
+
+/// A backend the data files are read from. Paths are those the rest of the
+/// crate builds, `{datafolder}/{lang}/{file}`, with `/` as separator.
+pub trait DataSource: Send + Sync {
+    /// Returns the whole content of the file at `path`.
+    fn read_to_string(&self, path: &str) -> io::Result<String>;
+
+    /// Returns whether there is a file at `path`.
+    fn exists(&self, path: &str) -> bool {
+        self.read_to_string(path).is_ok()
+    }
+
+    /// Returns the entries of the directory at `path`, in any order. A
+    /// source that cannot be listed returns `Unsupported`.
+    fn read_dir(&self, path: &str) -> io::Result<Vec<DirEntry>> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, format!("{}: the data source cannot be listed", path)))
+    }
+}
+
+/// An entry of a directory of a data source.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DirEntry {
+    /// The name of the entry, without its directory.
+    pub name: String,
+    pub is_dir: bool,
+}
+
+/// The filesystem, the default data source.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileSystem;
+
+impl DataSource for FileSystem {
+    fn read_to_string(&self, path: &str) -> io::Result<String> {
+        let bytes = fs::read(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path, e)))?;
+        decode(bytes, path).map(|(text, _)| text)
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        Path::new(path).is_file()
+    }
+
+    fn read_dir(&self, path: &str) -> io::Result<Vec<DirEntry>> {
+        let entries = fs::read_dir(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path, e)))?;
+        let mut listed = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            listed.push(DirEntry { name: entry.file_name().to_string_lossy().into_owned(), is_dir: entry.path().is_dir() });
+        }
+        Ok(listed)
+    }
+}
+
+/// Files held in memory, by path.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySource {
+    files: HashMap<String, String>,
+}
+
+impl MemorySource {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-/// Reads a text file (assumed to be in UTF‑8) and returns its lines as a vector of strings.
+    /// Adds (or replaces) the file at `path`.
+    pub fn insert(&mut self, path: &str, content: &str) {
+        self.files.insert(normalize_path(path), content.to_string());
+    }
+}
+
+impl DataSource for MemorySource {
+    fn read_to_string(&self, path: &str) -> io::Result<String> {
+        self.files
+            .get(&normalize_path(path))
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, path.to_string()))
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.files.contains_key(&normalize_path(path))
+    }
+
+    /// The directories are those of the paths of the files.
+    fn read_dir(&self, path: &str) -> io::Result<Vec<DirEntry>> {
+        let dir = normalize_path(path);
+        let prefix = if dir.is_empty() || dir == "/" { dir.clone() } else { format!("{}/", dir) };
+        let mut entries: Vec<DirEntry> = self
+            .files
+            .keys()
+            .filter_map(|file| file.strip_prefix(&prefix))
+            .map(|rest| match rest.split_once('/') {
+                Some((name, _)) => DirEntry { name: name.to_string(), is_dir: true },
+                None => DirEntry { name: rest.to_string(), is_dir: false },
+            })
+            .collect();
+        if entries.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, path.to_string()));
+        }
+        entries.sort();
+        entries.dedup();
+        Ok(entries)
+    }
+}
+
+/// Makes the paths written in different ways compare equal: backslashes and
+/// repeated slashes become single slashes, and `.` components are dropped.
+fn normalize_path(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let absolute = path.starts_with('/');
+    let parts: Vec<&str> = path.split('/').filter(|part| !part.is_empty() && *part != ".").collect();
+    format!("{}{}", if absolute { "/" } else { "" }, parts.join("/"))
+}
+
+/// The encoding a data file was read in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Windows1252,
+}
+
+/// The characters of the bytes 0x80 to 0x9F in Windows-1252, `None` for the
+/// five it leaves undefined.
+const WINDOWS_1252_HIGH: [Option<char>; 32] = [
+    Some('€'), None, Some('‚'), Some('ƒ'), Some('„'), Some('…'), Some('†'), Some('‡'),
+    Some('ˆ'), Some('‰'), Some('Š'), Some('‹'), Some('Œ'), None, Some('Ž'), None,
+    None, Some('‘'), Some('’'), Some('“'), Some('”'), Some('•'), Some('–'), Some('—'),
+    Some('˜'), Some('™'), Some('š'), Some('›'), Some('œ'), None, Some('ž'), Some('Ÿ'),
+];
+
+/// Decodes the content of the data file `path`, without its byte order
+/// mark: UTF-8, UTF-16 after its byte order mark, or else Windows-1252.
+///
+/// A file with a byte order mark it does not follow, or that is not UTF-8
+/// and has bytes no Windows-1252 text has (a NUL, or one of the five bytes
+/// Windows-1252 leaves undefined), is an `InvalidData` error naming `path`
+/// and the first offending byte, rather than text of the wrong characters.
+pub fn decode(bytes: Vec<u8>, path: &str) -> io::Result<(String, Encoding)> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path, message));
+    if let Some(rest) = bytes.strip_prefix(b"\xEF\xBB\xBF") {
+        return match std::str::from_utf8(rest) {
+            Ok(text) => Ok((text.to_string(), Encoding::Utf8)),
+            Err(e) => Err(invalid(format!("invalid UTF-8 after a byte order mark at offset {}", e.valid_up_to() + 3))),
+        };
+    }
+    for (bom, encoding) in [(b"\xFF\xFE", Encoding::Utf16Le), (b"\xFE\xFF", Encoding::Utf16Be)] {
+        let Some(rest) = bytes.strip_prefix(bom) else { continue };
+        if rest.len() % 2 != 0 {
+            return Err(invalid("odd number of bytes in UTF-16".to_string()));
+        }
+        let units: Vec<u16> = rest
+            .chunks_exact(2)
+            .map(|pair| match encoding {
+                Encoding::Utf16Le => u16::from_le_bytes([pair[0], pair[1]]),
+                _ => u16::from_be_bytes([pair[0], pair[1]]),
+            })
+            .collect();
+        return String::from_utf16(&units)
+            .map(|text| (text, encoding))
+            .map_err(|_| invalid("invalid UTF-16".to_string()));
+    }
+    let error = match String::from_utf8(bytes) {
+        Ok(text) => return Ok((text, Encoding::Utf8)),
+        Err(e) => e,
+    };
+    let offset = error.utf8_error().valid_up_to();
+    let bytes = error.into_bytes();
+    let mut text = String::with_capacity(bytes.len() + bytes.len() / 8);
+    for (i, &byte) in bytes.iter().enumerate() {
+        let c = match byte {
+            0 => None,
+            0x80..=0x9F => WINDOWS_1252_HIGH[usize::from(byte - 0x80)],
+            _ => Some(char::from(byte)),
+        };
+        match c {
+            Some(c) => text.push(c),
+            None => {
+                return Err(invalid(format!(
+                    "neither UTF-8 (invalid byte 0x{:02X} at offset {}) nor Windows-1252 (byte 0x{:02X} at offset {})",
+                    bytes[offset], offset, byte, i
+                )))
+            }
+        }
+    }
+    Ok((text, Encoding::Windows1252))
+}
+
+static DATA_SOURCE: Lazy<RwLock<Arc<dyn DataSource>>> = Lazy::new(|| RwLock::new(Arc::new(FileSystem)));
+
+/// Makes `source` the data source of the process.
+pub fn set_data_source(source: Arc<dyn DataSource>) {
+    *DATA_SOURCE.write().unwrap() = source;
+}
+
+/// Returns the data source of the process.
+pub fn data_source() -> Arc<dyn DataSource> {
+    DATA_SOURCE.read().unwrap().clone()
+}
+
+/// Returns whether the data source has a file at `path`, for the
+/// `file_exists` argument of `setup_string::checkfile`.
+pub fn file_exists(path: &str) -> bool {
+    data_source().exists(path)
+}
+
+/// Reads a text file and returns its lines as a vector of strings.
 ///
 /// This function:
 /// - Opens the file at the specified path.
-/// - Reads the entire contents into a string.
-/// - Removes a leading UTF‑8 byte order mark (BOM) if present.
+/// - Reads the entire contents into a string, decoding it (see `decode`).
+/// - Removes a leading byte order mark (BOM) if present.
 /// - Splits the contents into lines (handling both Unix (`\n`) and Windows (`\r\n`) line breaks).
 ///
 /// # Arguments
@@ -40,12 +262,99 @@ use std::path::Path;
 /// # }
 /// ```
 pub fn do_read<P: AsRef<Path>>(filename: P) -> io::Result<Vec<String>> {
+    do_read_from(&*data_source(), filename)
+}
+
+/// Like `do_read`, but reads from `source` instead of the data source of the
+/// process.
+pub fn do_read_from<P: AsRef<Path>>(source: &dyn DataSource, filename: P) -> io::Result<Vec<String>> {
     // Read the entire file contents as a UTF-8 string.
-    let content = fs::read_to_string(filename)?;
+    let content = source.read_to_string(&filename.as_ref().to_string_lossy())?;
+    Ok(split_lines(content))
+}
+
+/// Returns the names of the files of the directory `dir` of the data source
+/// that match `pattern`, sorted: `list_files("data/horas/Latin/Sancti",
+/// "*.txt")`. In the pattern, `*` matches any run of characters and `?` any
+/// one character.
+pub fn list_files(dir: &str, pattern: &str) -> io::Result<Vec<String>> {
+    list_files_from(&*data_source(), dir, pattern)
+}
+
+/// Like `list_files`, but lists `source` instead of the data source of the
+/// process.
+pub fn list_files_from(source: &dyn DataSource, dir: &str, pattern: &str) -> io::Result<Vec<String>> {
+    let mut files: Vec<String> = source
+        .read_dir(dir)?
+        .into_iter()
+        .filter(|entry| !entry.is_dir && glob_match(pattern, &entry.name))
+        .map(|entry| entry.name)
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Returns the paths of all the files under `datafolder` in the data source,
+/// relative to it with `/` as separator and sorted, e.g.
+/// `"Latin/Sancti/01-21.txt"`.
+pub fn walk(datafolder: &str) -> io::Result<Vec<String>> {
+    walk_from(&*data_source(), datafolder)
+}
+
+/// Like `walk`, but walks `source` instead of the data source of the process.
+pub fn walk_from(source: &dyn DataSource, datafolder: &str) -> io::Result<Vec<String>> {
+    fn walk_dir(source: &dyn DataSource, root: &str, rel: &str, files: &mut Vec<String>) -> io::Result<()> {
+        let dir = if rel.is_empty() { root.to_string() } else { format!("{}/{}", root, rel) };
+        for entry in source.read_dir(&dir)? {
+            let path = if rel.is_empty() { entry.name } else { format!("{}/{}", rel, entry.name) };
+            if entry.is_dir {
+                walk_dir(source, root, &path, files)?;
+            } else {
+                files.push(path);
+            }
+        }
+        Ok(())
+    }
+    let mut files = Vec::new();
+    walk_dir(source, datafolder.trim_end_matches(['/', '\\']), "", &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+/// Whether `name` matches the glob `pattern` (`*` and `?`).
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name): (Vec<char>, Vec<char>) = (pattern.chars().collect(), name.chars().collect());
+    let (mut p, mut n) = (0, 0);
+    // The position of the last `*` and of the name where it started to match.
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((sp, sn)) => {
+                    p = sp + 1;
+                    n = sn + 1;
+                    star = Some((sp, sn + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
 
+/// Splits the content of a file into lines, as `do_read` returns them.
+fn split_lines(content: String) -> Vec<String> {
     // If the file is empty, return an empty vector.
     if content.is_empty() {
-        return Ok(Vec::new());
+        return Vec::new();
     }
 
     // Remove the UTF-8 BOM if it exists.
@@ -57,9 +366,46 @@ pub fn do_read<P: AsRef<Path>>(filename: P) -> io::Result<Vec<String>> {
 
     // Split the content into lines.
     // The `.lines()` iterator splits on both `\n` and `\r\n` and does not include the newline characters.
-    let lines = content.lines().map(|line| line.to_string()).collect();
+    content.lines().map(|line| line.to_string()).collect()
+}
+
+/// A data source read asynchronously, e.g. over the network.
+#[cfg(feature = "async")]
+pub trait AsyncDataSource: Send + Sync {
+    /// Returns the whole content of the file at `path`.
+    fn read_to_string<'a>(
+        &'a self,
+        path: &'a str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = io::Result<String>> + Send + 'a>>;
+}
+
+#[cfg(feature = "async")]
+impl<T: DataSource> AsyncDataSource for T {
+    fn read_to_string<'a>(
+        &'a self,
+        path: &'a str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = io::Result<String>> + Send + 'a>> {
+        Box::pin(std::future::ready(DataSource::read_to_string(self, path)))
+    }
+}
 
-    Ok(lines)
+/// Reads the file at `path` from `source` and returns its lines, like `do_read`.
+#[cfg(feature = "async")]
+pub async fn do_read_async(source: &dyn AsyncDataSource, path: &str) -> io::Result<Vec<String>> {
+    Ok(split_lines(source.read_to_string(path).await?))
+}
+
+/// Fetches the files at `paths` from `source` into a `MemorySource`, to be
+/// used with `set_data_source`. A file missing from the source is skipped.
+#[cfg(feature = "async")]
+pub async fn preload(source: &dyn AsyncDataSource, paths: &[&str]) -> MemorySource {
+    let mut memory = MemorySource::new();
+    for path in paths {
+        if let Ok(content) = source.read_to_string(path).await {
+            memory.insert(path, &content);
+        }
+    }
+    memory
 }
 
 /// Writes the given content to a file in UTF‑8 encoding.
@@ -105,3 +451,87 @@ where
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_source() {
+        let mut memory = MemorySource::new();
+        memory.insert("data/horas/Latin/Psalterium/Comment.txt", "\u{FEFF}[Source]\r\nex Psalterio\r\n");
+        assert!(memory.exists("data/horas//Latin/./Psalterium/Comment.txt"));
+        assert!(!memory.exists("data/horas/English/Psalterium/Comment.txt"));
+        assert_eq!(
+            do_read_from(&memory, "data/horas/Latin/Psalterium/Comment.txt").unwrap(),
+            vec!["[Source]", "ex Psalterio"]
+        );
+        let err = do_read_from(&memory, "data/horas/Latin/Missing.txt").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_list_files_and_walk() {
+        let mut memory = MemorySource::new();
+        for path in ["data/Latin/Sancti/01-21.txt", "data/Latin/Sancti/01-21r.txt", "data/Latin/Sancti/README", "data/Latin/Tempora/Adv1-0.txt", "data/English/Sancti/01-21.txt"] {
+            memory.insert(path, "[Rank]\n");
+        }
+        assert_eq!(list_files_from(&memory, "data/Latin/Sancti", "*.txt").unwrap(), ["01-21.txt", "01-21r.txt"]);
+        assert_eq!(list_files_from(&memory, "data/Latin/Sancti/", "01-2?.txt").unwrap(), ["01-21.txt"]);
+        assert_eq!(list_files_from(&memory, "data/Latin", "*").unwrap(), Vec::<String>::new());
+        assert_eq!(list_files_from(&memory, "data/Latin/Commune", "*").unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert_eq!(
+            walk_from(&memory, "data/").unwrap(),
+            ["English/Sancti/01-21.txt", "Latin/Sancti/01-21.txt", "Latin/Sancti/01-21r.txt", "Latin/Sancti/README", "Latin/Tempora/Adv1-0.txt"]
+        );
+
+        let root = std::env::temp_dir().join(format!("fileio-walk-{}", std::process::id()));
+        fs::create_dir_all(root.join("Latin/Sancti")).unwrap();
+        fs::write(root.join("Latin/Sancti/01-21.txt"), "").unwrap();
+        fs::write(root.join("Latin/Sancti/01-22.txt"), "").unwrap();
+        let root_str = root.to_string_lossy().into_owned();
+        assert_eq!(walk_from(&FileSystem, &root_str).unwrap(), ["Latin/Sancti/01-21.txt", "Latin/Sancti/01-22.txt"]);
+        assert_eq!(list_files_from(&FileSystem, &format!("{}/Latin/Sancti", root_str), "*-22.*").unwrap(), ["01-22.txt"]);
+        let _ = fs::remove_dir_all(&root);
+
+        assert!(glob_match("*", "") && glob_match("a*b*c", "aXbYbZc") && !glob_match("a*b", "aXc"));
+        assert!(glob_match("Ps?lm*æ", "Psalmæ") && !glob_match("?", "æœ"));
+    }
+
+    #[test]
+    fn test_decode() {
+        let decoded = |bytes: &[u8]| decode(bytes.to_vec(), "Sancti/01-21.txt");
+        assert_eq!(decoded("Cæli".as_bytes()).unwrap(), ("Cæli".to_string(), Encoding::Utf8));
+        assert_eq!(decoded(b"\xEF\xBB\xBF[Rank]").unwrap(), ("[Rank]".to_string(), Encoding::Utf8));
+        assert_eq!(decoded(b"\xFF\xFEC\x00\xE6\x00").unwrap(), ("Cæ".to_string(), Encoding::Utf16Le));
+        assert_eq!(decoded(b"\xFE\xFF\x01\x53").unwrap(), ("œ".to_string(), Encoding::Utf16Be));
+        // Latin-1 and Windows-1252: æ, –, “ and ”.
+        assert_eq!(
+            decoded(b"C\xE6li \x96 \x93Gloria\x94").unwrap(),
+            ("Cæli – “Gloria”".to_string(), Encoding::Windows1252)
+        );
+
+        let err = decoded(b"Gl\xF3ria\x00").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            "Sancti/01-21.txt: neither UTF-8 (invalid byte 0xF3 at offset 2) nor Windows-1252 (byte 0x00 at offset 6)"
+        );
+        assert!(decoded(b"\xEF\xBB\xBFC\xE6li").unwrap_err().to_string().contains("offset 4"));
+        assert!(decoded(b"\xFF\xFE\x00").is_err());
+    }
+
+    #[test]
+    fn test_read_legacy_file() {
+        let path = std::env::temp_dir().join(format!("fileio-{}.txt", std::process::id()));
+        fs::write(&path, b"[Oratio]\r\nDeus, qui c\x9Cli \x85\r\n").unwrap();
+        let path = path.to_string_lossy().into_owned();
+        assert_eq!(do_read_from(&FileSystem, &path).unwrap(), vec!["[Oratio]", "Deus, qui cœli …"]);
+        fs::write(&path, b"\x81").unwrap();
+        assert!(do_read_from(&FileSystem, &path).unwrap_err().to_string().starts_with(&path));
+        let _ = fs::remove_file(&path);
+        let err = do_read_from(&FileSystem, &path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert!(err.to_string().starts_with(&path));
+    }
+}
@@ -0,0 +1,45 @@
+//! fuzz.rs
+//!
+//! Entry points for the fuzz targets of `fuzz/` (`cargo fuzz run
+//! conditions`), behind the `fuzzing` feature: the parsers of the text of
+//! the data files, which come from forks of the data as well as from the
+//! project. Each takes arbitrary input and must return without panicking.
+//!
+//! The context is that of a Divino Afflatu office with a data folder that
+//! does not exist, so that an inclusion of another file is reported missing
+//! and those within the input are expanded.
+
+use crate::setup_string::SetupStringContext;
+
+fn context() -> SetupStringContext {
+    SetupStringContext {
+        hora: "Laudes".to_string(),
+        ..SetupStringContext::new("/nonexistent/divinum-officium-fuzz", "Divino Afflatu")
+    }
+}
+
+/// Evaluates `input` as the condition of a conditional, e.g.
+/// "rubrica monastica et tempore paschali".
+pub fn evaluate_condition(input: &str) -> bool {
+    context().evaluate_condition(input)
+}
+
+/// Applies the conditionals of the lines of `input`, as in a section.
+pub fn process_conditional_lines(input: &str) -> String {
+    let lines: Vec<&str> = input.lines().collect();
+    context().process_conditional_lines(&lines)
+}
+
+/// Parses `input` as a data file and expands the inclusions (`@File:Section:subs`)
+/// of each of its sections.
+pub fn expand_inclusions_in_text(input: &str) -> Vec<String> {
+    let mut ctx = context();
+    let lines: Vec<String> = input.lines().map(str::to_string).collect();
+    let sections = ctx.setupstring_parse_file(&lines, "Latin", "Fuzz.txt");
+    let mut names: Vec<&String> = sections.keys().collect();
+    names.sort();
+    names
+        .into_iter()
+        .map(|name| ctx.expand_inclusions_in_text(&sections[name], &sections, "Latin", "Fuzz.txt", name))
+        .collect()
+}
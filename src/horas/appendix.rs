@@ -34,11 +34,9 @@ pub struct AppendixConfig {
 
 /// Removes a case–insensitive leading "appendix " from the input string.
 fn strip_appendix_prefix(s: &str) -> String {
-    let lower = s.to_lowercase();
-    if lower.starts_with("appendix ") {
-        s[8..].trim().to_string()
-    } else {
-        s.trim().to_string()
+    match s.get(..9) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("appendix ") => s[9..].trim().to_string(),
+        _ => s.trim().to_string(),
     }
 }
 
@@ -148,6 +146,8 @@ mod tests {
         assert_eq!(strip_appendix_prefix("Appendix Index"), "Index".to_string());
         assert_eq!(strip_appendix_prefix("appendix   MySection  "), "MySection".to_string());
         assert_eq!(strip_appendix_prefix("Section"), "Section".to_string());
+        assert_eq!(strip_appendix_prefix("Æterna"), "Æterna");
+        assert_eq!(strip_appendix_prefix("Appendiæ x"), "Appendiæ x");
     }
 
     #[test]
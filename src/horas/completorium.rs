@@ -0,0 +1,182 @@
+//! completorium.rs
+//!
+//! The parts proper to Compline, from the `Completorium` branches of
+//! `/horas/specials.pl`: the Confiteor after the short lesson, the Nunc
+//! dimittis with its antiphon, and the final antiphon of Our Lady, sung
+//! according to the season.
+//!
+//! The final antiphon is the Alma Redemptoris Mater from Advent to the
+//! Purification, the Ave Regina caelorum from then until Holy Week, the
+//! Regina caeli in Paschaltide and the Salve Regina from Trinity to Advent.
+//! The Dominican and Cistercian books always end Compline with the Salve
+//! Regina; the antiphon is the `Rite`'s to choose.
+
+use super::rite::rite_of;
+use super::specials::{rubric_line, translate, InputConfig};
+use super::Hora;
+use crate::rubrics::RubricsVersion;
+use crate::setup_string::{ResolveDirectives, SetupStringProvider};
+
+/// The final antiphons of Our Lady.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarianAntiphon {
+    AlmaRedemptoris,
+    AveRegina,
+    ReginaCaeli,
+    SalveRegina,
+}
+
+impl MarianAntiphon {
+    /// The name of the antiphon among the Prayers, as `$ant {name}` refers to it.
+    pub fn name(self) -> &'static str {
+        match self {
+            MarianAntiphon::AlmaRedemptoris => "Alma Redemptoris Mater",
+            MarianAntiphon::AveRegina => "Ave Regina caelorum",
+            MarianAntiphon::ReginaCaeli => "Regina caeli",
+            MarianAntiphon::SalveRegina => "Salve Regina",
+        }
+    }
+}
+
+/// Returns the final antiphon of Compline of the day `dayname` (e.g.
+/// "Adv1-0", "Pasc7-6") on `day`/`month`, `dayofweek` 0 being Sunday, in the
+/// rite of `version`.
+pub fn antiphona_finalis(
+    version: &RubricsVersion,
+    dayname: &str,
+    day: u32,
+    month: u32,
+    dayofweek: u32,
+) -> Option<MarianAntiphon> {
+    rite_of(version).antiphona_finalis(version, dayname, day, month, dayofweek)
+}
+
+/// The final antiphon of Compline by the seasons, as the Roman Breviary
+/// has it.
+///
+/// Compline is the last hour of the day, so the boundaries of the seasons
+/// fall within it: the Alma Redemptoris Mater begins on the Saturday before
+/// the first Sunday of Advent and is said until the Purification inclusive,
+/// the Regina caeli begins on Holy Saturday and gives way to the Salve Regina
+/// on the Saturday after Pentecost. On Maundy Thursday and Good Friday there
+/// is no final antiphon.
+pub fn seasonal_antiphona_finalis(dayname: &str, day: u32, month: u32, dayofweek: u32) -> Option<MarianAntiphon> {
+    let eve_of_advent = dayofweek == 6 && ((month == 11 && day >= 26) || (month == 12 && day <= 2));
+    let antiphon = match dayname {
+        "Quad6-4" | "Quad6-5" => return None,
+        "Quad6-6" => MarianAntiphon::ReginaCaeli,
+        "Pasc7-6" => MarianAntiphon::SalveRegina,
+        _ if dayname.starts_with("Pasc") => MarianAntiphon::ReginaCaeli,
+        _ if dayname.starts_with("Adv") || dayname.starts_with("Nat") || eve_of_advent => {
+            MarianAntiphon::AlmaRedemptoris
+        }
+        _ if month == 1 || (month == 2 && day <= 2) => MarianAntiphon::AlmaRedemptoris,
+        _ if dayname.starts_with("Quad") || month == 2 || month == 3 => MarianAntiphon::AveRegina,
+        _ => MarianAntiphon::SalveRegina,
+    };
+    Some(antiphon)
+}
+
+/// Returns the chapter of the final antiphon: its heading, the antiphon
+/// (as a `$ant` reference) and the Divinum auxilium, or only the Divinum
+/// auxilium when there is no antiphon.
+pub fn antiphona_finalis_chapter(config: &InputConfig, lang: &str) -> Vec<String> {
+    let version = config.rubrics();
+    let mut lines = Vec::new();
+    let antiphon =
+        antiphona_finalis(&version, config.dayname0(), config.day, config.month, config.dayofweek);
+    if let Some(antiphon) = antiphon {
+        let heading = if version.dominican { "Antiphonae finalis" } else { "Antiphona finalis BMV" };
+        lines.push(format!("#{}", translate(heading, lang)));
+        lines.push(format!("$ant {}", antiphon.name()));
+    }
+    lines.push("&Divinum_auxilium".to_string());
+    lines
+}
+
+/// Returns the Nunc dimittis (Psalm 233 of the psalter files) between its
+/// antiphon "Salva nos", which has its own form in Paschaltide. The monastic
+/// Compline has no canticle.
+pub fn nunc_dimittis(
+    config: &InputConfig,
+    files: &mut dyn SetupStringProvider,
+    lang: &str,
+) -> Option<String> {
+    if config.hora != Hora::Completorium || config.rubrics().monastic {
+        return None;
+    }
+    let minor = files.setupstring(lang, "Psalterium/Special/Minor Special.txt", ResolveDirectives::All)?;
+    let paschal = config.dayname0().starts_with("Pasc").then(|| minor.get("Ant 4 Pasch")).flatten();
+    let ant = paschal.or_else(|| minor.get("Ant 4"))?.trim();
+    Some(format!("Ant. {}\n&psalm(233)\nAnt. {}", ant, ant))
+}
+
+/// Returns the Confiteor of Compline after the short lesson and the
+/// adjutorium: the Pater noster said in silence (omitted from 1955), the
+/// Confiteor with the Misereatur, and the Indulgentiam (omitted in 1960).
+pub fn confiteor(version: &RubricsVersion, lang: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    if !version.uses_1955_simplifications() {
+        lines.push(rubric_line("secreto", lang, version));
+        lines.push("$Pater noster".to_string());
+    }
+    lines.push("$Confiteor".to_string());
+    lines.push("$Misereatur".to_string());
+    if !version.is_1960() {
+        lines.push("$Indulgentiam".to_string());
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::horas::specials::tests::TestFiles;
+    use MarianAntiphon::*;
+
+    #[test]
+    fn test_antiphona_finalis_by_season() {
+        let da = RubricsVersion::parse("Divino Afflatu");
+        let cases = [
+            ("Pent24-6", 29, 11, 6, Some(AlmaRedemptoris)),
+            ("Pent24-5", 28, 11, 5, Some(SalveRegina)),
+            ("Adv2-3", 10, 12, 3, Some(AlmaRedemptoris)),
+            ("Epi4-0", 2, 2, 0, Some(AlmaRedemptoris)),
+            ("Epi4-1", 3, 2, 1, Some(AveRegina)),
+            ("Quad6-3", 16, 4, 3, Some(AveRegina)),
+            ("Quad6-5", 18, 4, 5, None),
+            ("Quad6-6", 19, 4, 6, Some(ReginaCaeli)),
+            ("Pasc7-5", 13, 6, 5, Some(ReginaCaeli)),
+            ("Pasc7-6", 14, 6, 6, Some(SalveRegina)),
+            ("Pent10-3", 13, 8, 3, Some(SalveRegina)),
+        ];
+        for (dayname, day, month, dayofweek, expected) in cases {
+            assert_eq!(antiphona_finalis(&da, dayname, day, month, dayofweek), expected, "{}", dayname);
+        }
+        let op = RubricsVersion::parse("Ordo Praedicatorum - 1962");
+        assert_eq!(antiphona_finalis(&op, "Adv2-3", 10, 12, 3), Some(SalveRegina));
+    }
+
+    #[test]
+    fn test_nunc_dimittis_and_confiteor() {
+        let mut config = InputConfig::new(Hora::Completorium);
+        config.version = "Divino Afflatu".to_string();
+        config.daynames = vec!["Pasc2-3".to_string()];
+        let mut files = TestFiles::default().with("Latin", "Psalterium/Special/Minor Special.txt", &[
+            ("Ant 4", "Salva nos, * Dómine, vigilántes"),
+            ("Ant 4 Pasch", "Salva nos, * Dómine, vigilántes, allelúja"),
+        ]);
+        assert_eq!(
+            nunc_dimittis(&config, &mut files, "Latin").as_deref(),
+            Some("Ant. Salva nos, * Dómine, vigilántes, allelúja\n&psalm(233)\nAnt. Salva nos, * Dómine, vigilántes, allelúja")
+        );
+        config.version = "Monastic - 1963".to_string();
+        assert!(nunc_dimittis(&config, &mut files, "Latin").is_none());
+
+        assert_eq!(confiteor(&RubricsVersion::parse("Divino Afflatu"), "Latin").len(), 5);
+        assert_eq!(
+            confiteor(&RubricsVersion::parse("Rubrics 1960 - 1960"), "Latin"),
+            vec!["$Confiteor", "$Misereatur"]
+        );
+    }
+}
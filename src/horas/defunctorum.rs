@@ -0,0 +1,222 @@
+//! defunctorum.rs
+//!
+//! The Office of the Dead (*Officium Defunctorum*), said as a votive office
+//! with `votive = Defunctorum` (the Perl site names it after its file, `C9`).
+//!
+//! The office has only Vespers, Matins and Lauds, all taken from
+//! `Commune/C9.txt`, and differs from the other offices throughout: the
+//! hours begin directly with the psalms (no *Deus in adjutorium*, no hymn),
+//! every psalm ends with the *Requiem æternam* instead of the *Gloria
+//! Patri*, and Matins has no absolutions, blessings or *Tu autem*. The three
+//! nocturns of Matins (Ps 5-7, 22-26, 39-41) read the nine lessons from Job.
+//!
+//! The sections of the file are the usual ones: `Ant Vespera`, `Ant
+//! Matutinum` and `Ant Laudes` give the antiphons and psalms as
+//! "antiphon;;psalm" lines (with the versicle of each nocturn in Matins),
+//! `Versum 1` and `Versum 2` the versicles of Vespers and Lauds, `Ant 1`
+//! and `Ant 2` the antiphons of the Magnificat and of the Benedictus,
+//! `Invit` the invitatory, `LectioN` and `ResponsoryN` the lessons, `Preces`
+//! the prayers said kneeling and `Oratio` the collect.
+//!
+//! The psalms the hours refer to (`&psalm(114)`) are expanded with
+//! `registry()`, whose `Gloria` is the *Requiem æternam*.
+
+use super::matutinum::psalm_groups;
+use super::specials::{rubric_line, translate, InputConfig};
+use super::Hora;
+use crate::regex::contains_ci;
+use crate::rubrics::RubricsVersion;
+use crate::scripting::{DirectiveContext, FunctionRegistry};
+use crate::setup_string::{FileSections, ResolveDirectives, SetupStringProvider};
+
+/// The file of the Office of the Dead.
+pub const DEFUNCTORUM_FILE: &str = "Commune/C9.txt";
+
+/// Whether `votive` selects the Office of the Dead ("Defunctorum", or the
+/// name of its file, "C9").
+pub fn is_defunctorum(votive: &str) -> bool {
+    let votive = votive.trim().trim_end_matches(".txt");
+    contains_ci(votive, "Defunct") || votive.rsplit('/').next() == Some("C9")
+}
+
+/// Whether the Office of the Dead has `hora`: it has only Matins, Lauds and
+/// Vespers.
+pub fn has_hour(hora: Hora) -> bool {
+    matches!(hora, Hora::Matutinum | Hora::Laudes | Hora::Vespera)
+}
+
+/// The functions for the directives of the hours of the Office of the Dead:
+/// the built-in ones, with the `Gloria` closing the psalms replaced by the
+/// `Requiem` of the Prayers.
+pub fn registry() -> FunctionRegistry {
+    fn requiem(ctx: &mut DirectiveContext, _args: &[String]) -> Option<String> {
+        ctx.files
+            .setupstring(ctx.lang, "Psalterium/Common/Prayers.txt", ResolveDirectives::All)?
+            .get("Requiem")
+            .map(|t| t.trim_end().to_string())
+    }
+    let mut registry = FunctionRegistry::with_builtins();
+    registry.register_data("Gloria", requiem);
+    registry
+}
+
+/// Returns the lines of `config.hora` of the Office of the Dead, or `None`
+/// for an hour the office does not have or if `Commune/C9.txt` is missing.
+pub fn defunctorum(config: &InputConfig, files: &mut dyn SetupStringProvider, lang: &str) -> Option<Vec<String>> {
+    if !has_hour(config.hora) {
+        return None;
+    }
+    let office = files.setupstring(lang, DEFUNCTORUM_FILE, ResolveDirectives::All)?;
+    config.build.setbuild(DEFUNCTORUM_FILE, &format!("Ant {}", config.hora), "Defunctorum");
+    let mut lines = Vec::new();
+    match config.hora {
+        Hora::Matutinum => {
+            if let Some(invit) = section(&office, "Invit") {
+                lines.push(format!("#{}", translate("Invitatorium", lang)));
+                lines.push(format!("Ant. {}", invit));
+                lines.push("&psalm(94)".to_string());
+                lines.push(format!("Ant. {}", invit));
+            }
+            lines.extend(nocturns(&office, lang, &config.rubrics()));
+        }
+        Hora::Laudes | Hora::Vespera => {
+            let (ind, canticle, psalm) = if config.hora == Hora::Laudes {
+                (2, "Benedictus", 231)
+            } else {
+                (1, "Magnificat", 232)
+            };
+            lines.push(format!("#{}", translate("Psalmi", lang)));
+            for (ant, psalm) in psalmi(&office, &format!("Ant {}", config.hora)) {
+                lines.extend(antiphoned(&ant, &psalm.to_string()));
+            }
+            if let Some(versum) = section(&office, &format!("Versum {}", ind)) {
+                lines.push(format!("#{}", translate("Versus", lang)));
+                lines.push(versum);
+            }
+            if let Some(ant) = section(&office, &format!("Ant {}", ind)) {
+                lines.push(format!("#{}", translate(&format!("Canticum: {}", canticle), lang)));
+                lines.extend(antiphoned(&ant, &psalm.to_string()));
+            }
+        }
+        _ => unreachable!(),
+    }
+    lines.push(format!("#{}", translate("Preces", lang)));
+    let version = config.rubrics();
+    lines.push(rubric_line("flexis genibus", lang, &version));
+    lines.push(rubric_line("secreto", lang, &version));
+    lines.push("$Pater noster".to_string());
+    lines.extend(section(&office, "Preces"));
+    lines.push(format!("#{}", translate("Oratio", lang)));
+    lines.push("$Oremus".to_string());
+    lines.extend(section(&office, "Oratio"));
+    lines.push("V. Réquiem ætérnam dona eis, Dómine.".to_string());
+    lines.push("R. Et lux perpétua lúceat eis.".to_string());
+    lines.push("V. Requiéscant in pace.".to_string());
+    lines.push("R. Amen.".to_string());
+    Some(lines)
+}
+
+/// The three nocturns of Matins: the psalms with their antiphons, the
+/// versicle, the Pater noster in silence and three lessons from Job with
+/// their responsories, without absolution, blessings or *Tu autem*.
+fn nocturns(office: &FileSections, lang: &str, version: &RubricsVersion) -> Vec<String> {
+    let groups = office.get("Ant Matutinum").map(|text| psalm_groups(text, 3)).unwrap_or_default();
+    let mut lines = Vec::new();
+    for (i, (psalmi, versum)) in groups.iter().enumerate().take(3) {
+        lines.push(format!("#{} {}", translate("Nocturn", lang), i + 1));
+        for (ant, psalm) in psalmi {
+            lines.extend(antiphoned(ant, psalm));
+        }
+        lines.extend(versum.iter().cloned());
+        lines.push(rubric_line("secreto", lang, version));
+        lines.push("$Pater noster Et".to_string());
+        for n in i * 3 + 1..=i * 3 + 3 {
+            let Some(lectio) = section(office, &format!("Lectio{}", n)) else { continue };
+            lines.push(format!("!{} {}", translate("Lectio", lang), n));
+            lines.push(lectio);
+            lines.extend(section(office, &format!("Responsory{}", n)));
+        }
+    }
+    lines
+}
+
+/// The "antiphon;;psalm" lines of section `name`.
+fn psalmi(office: &FileSections, name: &str) -> Vec<(String, String)> {
+    office
+        .get(name)
+        .map(|text| {
+            text.lines()
+                .filter_map(|l| l.split_once(";;"))
+                .map(|(ant, psalm)| (ant.trim().to_string(), psalm.trim().to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// A psalm between its antiphon.
+fn antiphoned(ant: &str, psalm: &str) -> Vec<String> {
+    vec![format!("Ant. {}", ant), format!("&psalm({})", psalm), format!("Ant. {}", ant)]
+}
+
+/// The section `name` of the office, trimmed, if it is not empty.
+fn section(office: &FileSections, name: &str) -> Option<String> {
+    office.get(name).map(|t| t.trim().to_string()).filter(|t| !t.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::horas::specials::tests::TestFiles;
+    use crate::scripting::DirectiveContext;
+
+    fn files() -> TestFiles {
+        TestFiles::default()
+            .with("Latin", DEFUNCTORUM_FILE, &[
+                ("Ant Vespera", "Placébo Dómino * in regióne vivórum.;;114\nHei mihi, * Dómine.;;119"),
+                ("Versum 1", "V. Audívi vocem de cælo dicéntem mihi.\nR. Beáti mórtui qui in Dómino moriúntur."),
+                ("Ant 1", "Omne quod dat mihi Pater, * ad me véniet."),
+                ("Invit", "Regem, cui ómnia vivunt, * Veníte, adorémus."),
+                ("Ant Matutinum", "Dírige, * Dómine.;;5\nV. A porta ínferi.\nR. Erue, Dómine.\nIn loco páscuæ * ibi me collocávit.;;22\nV. Credo vidére.\nR. In terra vivéntium.\nComplaceat tibi, * Dómine.;;39\nV. Dóminus.\nR. Erue."),
+                ("Lectio1", "Parce mihi, Dómine, nihil enim sunt dies mei."),
+                ("Responsory1", "R. Credo quod Redémptor meus vivit."),
+                ("Lectio9", "Quare de vulva eduxísti me?"),
+                ("Preces", "V. A porta ínferi.\nR. Erue, Dómine, ánimas eórum."),
+                ("Oratio", "Fidélium, Deus, ómnium Cónditor et Redémptor."),
+            ])
+            .with("Latin", "Psalterium/Common/Prayers.txt", &[
+                ("Gloria", "Glória Patri."),
+                ("Requiem", "Réquiem ætérnam * dona eis, Dómine."),
+            ])
+            .with("Latin", "Psalterium/Psalmorum/Psalm114.txt", &[("__preamble", "114:1 Diléxi, quóniam exáudiet Dóminus.")])
+    }
+
+    #[test]
+    fn test_defunctorum_vespers() {
+        assert!(is_defunctorum("Defunctorum") && is_defunctorum("C9") && !is_defunctorum("C10"));
+        let mut files = files();
+        let config = InputConfig::new(Hora::Vespera);
+        let lines = defunctorum(&config, &mut files, "Latin").unwrap();
+        assert_eq!(lines[1], "Ant. Placébo Dómino * in regióne vivórum.");
+        assert_eq!(lines[2], "&psalm(114)");
+        assert!(lines.contains(&"&psalm(232)".to_string()));
+        assert!(!lines.iter().any(|l| l.contains("Deus in adjutorium")));
+        assert!(lines.contains(&"Fidélium, Deus, ómnium Cónditor et Redémptor.".to_string()));
+        assert!(defunctorum(&InputConfig::new(Hora::Prima), &mut files, "Latin").is_none());
+
+        let mut ctx = DirectiveContext { files: &mut files, lang: "Latin" };
+        let psalm = registry().expand("&psalm(114)", &mut ctx);
+        assert!(psalm.ends_with("Réquiem ætérnam * dona eis, Dómine."));
+    }
+
+    #[test]
+    fn test_defunctorum_matins() {
+        let config = InputConfig::new(Hora::Matutinum);
+        let lines = defunctorum(&config, &mut files(), "Latin").unwrap();
+        assert_eq!(lines[1], "Ant. Regem, cui ómnia vivunt, * Veníte, adorémus.");
+        assert_eq!(lines.iter().filter(|l| l.contains("Nocturn")).count(), 3);
+        let lectio1 = lines.iter().position(|l| l.ends_with("Lectio 1")).unwrap();
+        assert_eq!(lines[lectio1 + 2], "R. Credo quod Redémptor meus vivit.");
+        assert!(lines.iter().any(|l| l.ends_with("Lectio 9")));
+        assert!(!lines.iter().any(|l| l.contains("Jube domne") || l.contains("Tu autem")));
+    }
+}
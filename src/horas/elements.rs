@@ -0,0 +1,310 @@
+//! elements.rs
+//!
+//! The structured output of an hour: the rendered text cut into its chapters
+//! and, within them, into antiphons, hymns, psalms, responsories, versicles,
+//! rubrics and plain text, for front ends that lay the office out themselves.
+//!
+//! ```ignore
+//! let elements = build_hour_elements(&mut ctx, "01-25-2025", Hora::Laudes, "Latin", "Divino Afflatu")?;
+//! for chant in elements.iter().flat_map(|e| e.chant_refs()) {
+//!     println!("{}", chant.path);
+//! }
+//! ```
+//!
+//! Antiphons, hymns and responsories can carry a reference to their chant:
+//! if the data folder has a GABC score for one, `attach_chant()` (called by
+//! `build_hour_elements()`) sets its `chant`. The scores are the companions
+//! `gabc/{part}/{incipit}.gabc` of the data, where the part is "antiphona",
+//! "hymnus" or "responsorium" and the incipit the opening words (up to the
+//! asterisk, or the first line of a hymn) in lower case, without accents or
+//! punctuation, joined by "-": `gabc/antiphona/ave-maria.gabc`.
+
+use std::path::Path;
+
+use crate::fileio::DataSource;
+use crate::language_text_tools::strip_accents;
+use crate::regex::contains_ci;
+
+/// A reference to the GABC score of a chant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChantRef {
+    /// The path of the score, under the data folder.
+    pub path: String,
+    /// The opening words the score was found by.
+    pub incipit: String,
+}
+
+/// A part of an hour.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OfficeElement {
+    /// A chapter (a `#` heading of the Ordinarium) and what it contains.
+    Section { title: String, elements: Vec<OfficeElement> },
+    /// An antiphon, without its "Ant.".
+    Antiphon { text: String, chant: Option<ChantRef> },
+    /// The stanzas of a hymn.
+    Hymn { text: String, chant: Option<ChantRef> },
+    /// A responsory, short or of a lesson: the "R." and "V." lines.
+    Responsory { text: String, chant: Option<ChantRef> },
+    /// A psalm or canticle: its heading ("Psalmus 109") and verses.
+    Psalm { title: String, verses: Vec<String> },
+    /// A versicle with its response.
+    Versicle(String),
+    /// A rubric, e.g. "secreto" or the heading of a lesson.
+    Rubric(String),
+    Text(String),
+}
+
+impl OfficeElement {
+    /// The elements of a section (none for the others).
+    pub fn elements(&self) -> &[OfficeElement] {
+        match self {
+            OfficeElement::Section { elements, .. } => elements,
+            _ => &[],
+        }
+    }
+
+    /// The chant of the element, for an antiphon, hymn or responsory.
+    pub fn chant(&self) -> Option<&ChantRef> {
+        match self {
+            OfficeElement::Antiphon { chant, .. }
+            | OfficeElement::Hymn { chant, .. }
+            | OfficeElement::Responsory { chant, .. } => chant.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// The chants of the element and of the elements it contains, in order
+    /// (an antiphon repeated after its psalm is listed twice).
+    pub fn chant_refs(&self) -> Vec<&ChantRef> {
+        let mut refs: Vec<&ChantRef> = self.chant().into_iter().collect();
+        for element in self.elements() {
+            refs.extend(element.chant_refs());
+        }
+        refs
+    }
+
+    /// Sets the chant of the element and of the elements it contains from
+    /// the GABC scores of `source` under `datafolder`, as the module doc
+    /// describes.
+    pub fn attach_chant(&mut self, source: &dyn DataSource, datafolder: &Path) {
+        let (part, text, chant) = match self {
+            OfficeElement::Section { elements, .. } => {
+                elements.iter_mut().for_each(|e| e.attach_chant(source, datafolder));
+                return;
+            }
+            OfficeElement::Antiphon { text, chant } => ("antiphona", text.as_str(), chant),
+            OfficeElement::Hymn { text, chant } => ("hymnus", text.as_str(), chant),
+            OfficeElement::Responsory { text, chant } => ("responsorium", text.as_str(), chant),
+            _ => return,
+        };
+        let incipit = incipit(text);
+        let slug = slug(&incipit);
+        if slug.is_empty() {
+            return;
+        }
+        let path = format!("gabc/{}/{}.gabc", part, slug);
+        if source.exists(&datafolder.join(&path).to_string_lossy()) {
+            *chant = Some(ChantRef { path, incipit });
+        }
+    }
+}
+
+/// Sets the chants of `elements` from the scores of `source` under
+/// `datafolder`.
+pub fn attach_chant(elements: &mut [OfficeElement], source: &dyn DataSource, datafolder: &Path) {
+    elements.iter_mut().for_each(|e| e.attach_chant(source, datafolder));
+}
+
+/// The opening words of a chant: up to the asterisk, or its first line,
+/// without the "R." of a responsory.
+fn incipit(text: &str) -> String {
+    let first = text.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or("");
+    let first = first.strip_prefix("R.br.").or_else(|| first.strip_prefix("R.")).unwrap_or(first);
+    let first = first.split('*').next().unwrap_or("");
+    first.trim().trim_end_matches([',', ';', ':', '.']).to_string()
+}
+
+/// The name of the score of `incipit`: its words in lower case, without
+/// accents or punctuation, joined by "-".
+fn slug(incipit: &str) -> String {
+    strip_accents(incipit)
+        .to_lowercase()
+        .replace('æ', "ae")
+        .replace('œ', "oe")
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Cuts the rendered text of an hour into its elements: one `Section` per
+/// `#` chapter (anything before the first heading stays at the top level).
+pub fn parse_hour(text: &str) -> Vec<OfficeElement> {
+    let mut elements = Vec::new();
+    let mut title: Option<String> = None;
+    let mut lines: Vec<&str> = Vec::new();
+    for line in text.lines() {
+        if let Some(heading) = line.strip_prefix('#') {
+            match title.take() {
+                Some(t) => elements.push(section(t, &lines)),
+                None => elements.extend(parse_lines(&lines, false)),
+            }
+            title = Some(heading.trim().to_string());
+            lines.clear();
+        } else {
+            lines.push(line);
+        }
+    }
+    match title {
+        Some(t) => elements.push(section(t, &lines)),
+        None => elements.extend(parse_lines(&lines, false)),
+    }
+    elements
+}
+
+fn section(title: String, lines: &[&str]) -> OfficeElement {
+    let hymn = contains_ci(&title, "Hymn");
+    OfficeElement::Section { elements: parse_lines(lines, hymn), title }
+}
+
+/// Whether `line` is a verse of a psalm ("109:1 Dixit Dóminus").
+fn is_verse(line: &str) -> bool {
+    let number = line.split_whitespace().next().unwrap_or("");
+    number.split_once(':').is_some_and(|(c, v)| {
+        !c.is_empty() && c.chars().all(|ch| ch.is_ascii_digit()) && v.starts_with(|ch: char| ch.is_ascii_digit())
+    })
+}
+
+/// The elements of the lines of a chapter; the text of a hymn chapter is its
+/// hymn.
+fn parse_lines(lines: &[&str], hymn: bool) -> Vec<OfficeElement> {
+    let mut elements = Vec::new();
+    let mut hymn_text: Vec<&str> = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i].trim();
+        i += 1;
+        if line.is_empty() || line == "_" {
+            if !hymn_text.is_empty() {
+                hymn_text.push("");
+            }
+            continue;
+        }
+        if let Some(ant) = line.strip_prefix("Ant.") {
+            elements.push(OfficeElement::Antiphon { text: ant.trim().to_string(), chant: None });
+        } else if let Some(title) = line.strip_prefix('!') {
+            let next = lines[i..].iter().map(|l| l.trim()).find(|l| !l.is_empty());
+            if next.is_some_and(is_verse) {
+                let mut verses = Vec::new();
+                while i < lines.len() && (is_verse(lines[i].trim()) || lines[i].trim().is_empty()) {
+                    if !lines[i].trim().is_empty() {
+                        verses.push(lines[i].trim().to_string());
+                    }
+                    i += 1;
+                }
+                // The Gloria Patri (or Requiem) closing the psalm.
+                while i < lines.len() && !is_boundary(lines[i].trim()) {
+                    verses.push(lines[i].trim().to_string());
+                    i += 1;
+                }
+                elements.push(OfficeElement::Psalm { title: title.trim().to_string(), verses });
+            } else {
+                elements.push(OfficeElement::Rubric(title.trim().to_string()));
+            }
+        } else if line.starts_with("/:") && line.ends_with(":/") {
+            elements.push(OfficeElement::Rubric(line.trim_matches(['/', ':']).trim().to_string()));
+        } else if line.starts_with("R.") || line.starts_with("V.") {
+            let mut block = vec![line];
+            while i < lines.len() && (lines[i].trim().starts_with("R.") || lines[i].trim().starts_with("V.")) {
+                block.push(lines[i].trim());
+                i += 1;
+            }
+            let text = block.join("\n");
+            if block[0].starts_with("R.") && text.contains('*') {
+                elements.push(OfficeElement::Responsory { text, chant: None });
+            } else {
+                elements.push(OfficeElement::Versicle(text));
+            }
+        } else if hymn {
+            hymn_text.push(line);
+        } else {
+            elements.push(OfficeElement::Text(line.to_string()));
+        }
+    }
+    if !hymn_text.is_empty() {
+        while hymn_text.last() == Some(&"") {
+            hymn_text.pop();
+        }
+        elements.insert(0, OfficeElement::Hymn { text: hymn_text.join("\n"), chant: None });
+    }
+    elements
+}
+
+/// Whether `line` starts another element than the lines of a psalm.
+fn is_boundary(line: &str) -> bool {
+    line.is_empty()
+        || line == "_"
+        || line.starts_with("Ant.")
+        || line.starts_with('!')
+        || line.starts_with("/:")
+        || line.starts_with("V.")
+        || line.starts_with("R.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fileio::MemorySource;
+
+    const VESPERS: &str = "#Psalmi\nAnt. Dixit Dóminus * Dómino meo: Sede a dextris meis.\n!Psalmus 109\n109:1 Dixit Dóminus Dómino meo: * Sede a dextris meis.\n109:2 Donec ponam inimícos tuos.\nGlória Patri.\nAnt. Dixit Dóminus * Dómino meo: Sede a dextris meis.\n\
+#Hymnus\nAve, maris stella,\nDei Mater alma,\n_\nSumens illud Ave\nV. Dirigátur, Dómine, orátio mea.\nR. Sicut incénsum in conspéctu tuo.\n\
+#Preces\n/:secreto:/\nR.br. Inclína cor meum, Deus, * In testimónia tua.\nV. Avérte óculos meos.\n";
+
+    #[test]
+    fn test_parse_hour() {
+        let elements = parse_hour(VESPERS);
+        assert_eq!(elements.len(), 3);
+        let psalmi = elements[0].elements();
+        assert_eq!(
+            psalmi[0],
+            OfficeElement::Antiphon { text: "Dixit Dóminus * Dómino meo: Sede a dextris meis.".to_string(), chant: None }
+        );
+        match &psalmi[1] {
+            OfficeElement::Psalm { title, verses } => {
+                assert_eq!(title, "Psalmus 109");
+                assert_eq!(verses.len(), 3);
+                assert_eq!(verses[2], "Glória Patri.");
+            }
+            other => panic!("not a psalm: {:?}", other),
+        }
+        assert_eq!(psalmi.len(), 3);
+        let hymnus = elements[1].elements();
+        assert_eq!(
+            hymnus[0],
+            OfficeElement::Hymn { text: "Ave, maris stella,\nDei Mater alma,\n\nSumens illud Ave".to_string(), chant: None }
+        );
+        assert!(matches!(&hymnus[1], OfficeElement::Versicle(v) if v.ends_with("in conspéctu tuo.")));
+        let preces = elements[2].elements();
+        assert_eq!(preces[0], OfficeElement::Rubric("secreto".to_string()));
+        assert!(matches!(&preces[1], OfficeElement::Responsory { .. }));
+    }
+
+    #[test]
+    fn test_attach_chant() {
+        let mut source = MemorySource::new();
+        source.insert("/data/horas/gabc/antiphona/dixit-dominus.gabc", "name:Dixit Dominus;\n%%\n(c4)");
+        source.insert("/data/horas/gabc/hymnus/ave-maris-stella.gabc", "name:Ave maris stella;\n%%\n(c3)");
+        let mut elements = parse_hour(VESPERS);
+        attach_chant(&mut elements, &source, Path::new("/data/horas"));
+
+        let refs: Vec<&str> = elements.iter().flat_map(|e| e.chant_refs()).map(|c| c.path.as_str()).collect();
+        assert_eq!(
+            refs,
+            ["gabc/antiphona/dixit-dominus.gabc", "gabc/antiphona/dixit-dominus.gabc", "gabc/hymnus/ave-maris-stella.gabc"]
+        );
+        assert_eq!(elements[1].elements()[0].chant().unwrap().incipit, "Ave, maris stella");
+        assert_eq!(slug("Ecce ancílla Dómini, cæli"), "ecce-ancilla-domini-caeli");
+    }
+}
@@ -0,0 +1,141 @@
+//! hora.rs
+//!
+//! The canonical hours of the Divine Office.
+//!
+//! In the Perl code the current hour is held in the global `$hora` as a plain
+//! string ("Matutinum", "Laudes", "Vespera", ...) and compared by name all over
+//! the place. Here the hour is an enum; its `Display` form is the Latin name
+//! used in the data files, so `format!("Ant {}", hora)` still yields the same
+//! section keys as before.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// One of the eight canonical hours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Hora {
+    Matutinum,
+    Laudes,
+    Prima,
+    Tertia,
+    Sexta,
+    Nona,
+    Vespera,
+    Completorium,
+}
+
+/// Error returned when a string does not name a known hour.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseHoraError(pub String);
+
+impl fmt::Display for ParseHoraError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown hour: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParseHoraError {}
+
+impl Hora {
+    /// All hours in the order they are said, from Matins to Compline.
+    pub fn all() -> [Hora; 8] {
+        [
+            Hora::Matutinum,
+            Hora::Laudes,
+            Hora::Prima,
+            Hora::Tertia,
+            Hora::Sexta,
+            Hora::Nona,
+            Hora::Vespera,
+            Hora::Completorium,
+        ]
+    }
+
+    /// The Latin name, as used in the data files and in `horas.dialog`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Hora::Matutinum => "Matutinum",
+            Hora::Laudes => "Laudes",
+            Hora::Prima => "Prima",
+            Hora::Tertia => "Tertia",
+            Hora::Sexta => "Sexta",
+            Hora::Nona => "Nona",
+            Hora::Vespera => "Vespera",
+            Hora::Completorium => "Completorium",
+        }
+    }
+
+    /// Laudes and Vespera, which share the "major" psalter and chapter routines.
+    pub fn is_major(&self) -> bool {
+        matches!(self, Hora::Laudes | Hora::Vespera)
+    }
+
+    /// Tertia, Sexta and Nona (the "little hours" proper).
+    pub fn is_minor(&self) -> bool {
+        matches!(self, Hora::Tertia | Hora::Sexta | Hora::Nona)
+    }
+}
+
+impl fmt::Display for Hora {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Hora {
+    type Err = ParseHoraError;
+
+    /// Parses the Latin name case-insensitively. The English names used by
+    /// the web front end ("Matins", "Lauds", "Vespers", ...) are accepted too,
+    /// as is "Vesperae", which some callers pass.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hora = match s.trim().to_lowercase().as_str() {
+            "matutinum" | "matins" => Hora::Matutinum,
+            "laudes" | "lauds" => Hora::Laudes,
+            "prima" | "prime" => Hora::Prima,
+            "tertia" | "terce" => Hora::Tertia,
+            "sexta" | "sext" => Hora::Sexta,
+            "nona" | "none" => Hora::Nona,
+            "vespera" | "vesperae" | "vespers" => Hora::Vespera,
+            "completorium" | "compline" => Hora::Completorium,
+            _ => return Err(ParseHoraError(s.to_string())),
+        };
+        Ok(hora)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display_roundtrip() {
+        for hora in Hora::all() {
+            assert_eq!(hora.to_string().parse::<Hora>(), Ok(hora));
+        }
+    }
+
+    #[test]
+    fn test_parse_aliases() {
+        assert_eq!("laudes".parse::<Hora>(), Ok(Hora::Laudes));
+        assert_eq!("Vespers".parse::<Hora>(), Ok(Hora::Vespera));
+        assert_eq!(" Compline ".parse::<Hora>(), Ok(Hora::Completorium));
+        assert!("Missa".parse::<Hora>().is_err());
+    }
+
+    #[test]
+    fn test_all_order() {
+        let all = Hora::all();
+        assert_eq!(all.first(), Some(&Hora::Matutinum));
+        assert_eq!(all.last(), Some(&Hora::Completorium));
+    }
+
+    #[test]
+    fn test_groups() {
+        assert!(Hora::Vespera.is_major());
+        assert!(!Hora::Prima.is_major());
+        assert!(Hora::Sexta.is_minor());
+        assert!(!Hora::Completorium.is_minor());
+    }
+}
@@ -1,6 +1,8 @@
-use crate::date::getweek;
+use crate::date::{getweek, CalendarSystem};
 use crate::regex::{contains_ci, remove_after, starts_with_ignore_case};
+use crate::rubrics::RubricsVersion;
 use crate::setup_string::{ResolveDirectives, SetupStringProvider};
+use super::Hora;
 
 /// Returns the rank name (a string) given the many parameters that the Perl code used as globals.
 pub fn rankname(
@@ -28,6 +30,8 @@ pub fn rankname(
     }
 
 
+    let cist = RubricsVersion::parse(version).is_cistercian();
+
     // 1) Pull "Latin" winner => latwinner => latwinner["Rank"], removing everything after `;;`
     let latin_sections = match provider.setupstring("Latin", winner, ResolveDirectives::None) {
         Some(fs) => fs,
@@ -110,7 +114,7 @@ pub fn rankname(
 
         // if ($latname =~ /Vigilia Epi/i) { ... }
         if contains_ci(&latname, "vigilia epi") {
-            if contains_ci(version, "cist") {
+            if cist {
                 // $rankname = $t{Vigilia};
                 // $rankname .= $dayofweek ? $t{privilegiata} : $ranktable[2];
                 let mut vig = t_vigilia.clone();
@@ -136,7 +140,7 @@ pub fn rankname(
         // elsif ($latname =~ /^In Vigilia/i && $rank <= 2.5) { ... }
         if starts_with_ignore_case(&latname, "In Vigilia") && rank <= 2.5 {
             // $rankname = $version =~ /cist/i ? $t{Vigilia} : $ranktable[1];
-            if contains_ci(version, "cist") {
+            if cist {
                 return t_vigilia.replace('\n', "");
             } else {
                 return get_ranktable(1).replace('\n', "");
@@ -148,8 +152,9 @@ pub fn rankname(
 
             let weekname = getweek(
                 day, month, year,
-                dayofweek == 6 && contains_ci(hora, "Vespera|Completorium"), 
-                false /* missaf */
+                dayofweek == 6 && matches!(hora.parse(), Ok(Hora::Vespera | Hora::Completorium)),
+                false /* missaf */,
+                CalendarSystem::Gregorian,
             );
 
             // The giant if:
@@ -198,7 +203,7 @@ pub fn rankname(
             let mut rn = sundaytable.get(idx).cloned().unwrap_or_default();
 
             // if cist, we do some replacements
-            if contains_ci(version, "cist") {
+            if cist {
                 if contains_ci(&weekname, "pasc0") || contains_ci(&weekname, "pasc7") {
                     rn = get_ranktable(6);
                 }
@@ -261,7 +266,7 @@ pub fn rankname(
     }
 
     // elsif ($version =~ /cist/i && $winner =~ /Pasc[07]-[1-6]/)
-    if contains_ci(version, "cist")
+    if cist
        && (
             contains_ci(winner, "Pasc0-1")
             || contains_ci(winner, "Pasc0-2")
@@ -325,7 +330,7 @@ pub fn rankname(
     // elsif ($version =~ /Trident/ && $latname =~ /^In Octava/i)
     if version.contains("Trident") && starts_with_ignore_case(&latname, "In Octava") {
         // 'Duplex/xij.L.' - all other Octaves pre Divino
-        let i = if contains_ci(version, "cist") { 2 } else { 3 };
+        let i = if cist { 2 } else { 3 };
         return get_ranktable(i).replace('\n', "");
     }
 
@@ -336,7 +341,7 @@ pub fn rankname(
             || contains_ci(&latname, "vigilia pent"))
     {
         // 'Semiduplex/iij.L.' - all other Octaves pre Divino
-        let i = if contains_ci(version, "cist") { 1 } else { 2 };
+        let i = if cist { 1 } else { 2 };
         return get_ranktable(i).replace('\n', "");
     }
 
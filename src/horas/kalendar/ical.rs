@@ -8,7 +8,7 @@
 //!
 //! The main public function is `ical_output(version1: &str, kyear: i32) -> Vec<String>`.
 
-use chrono::{Local, NaiveDate, Timelike, Datelike};
+use chrono::{Datelike, Timelike, Utc};
 use std::fmt::Write as FmtWrite;
 use std::path::Path;
 use crate::date::leap_year;
@@ -158,10 +158,11 @@ pub fn ical_output(ctx: &OrdoContext, version1: &str, kyear: i32) -> Vec<String>
     // Calculate the total number of days in the year.
     let to = 365 + if leap_year(kyear) { 1 } else { 0 };
 
-    // Build DTSTAMP using the current local time.
-    let now = Local::now();
+    // Build DTSTAMP, the time the calendar is made, in UTC as RFC 5545 wants
+    // it (and so that it does not depend on the timezone of the machine).
+    let now = Utc::now();
     let dtstamp = format!(
-        "{:04}{:02}{:02}T{:02}{:02}{:02}",
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
         now.year(),
         now.month(),
         now.day(),
@@ -6,7 +6,6 @@
 //! computing “romanday” strings, producing a dominica letter,
 //! computing an epact cycle, and ultimately building table rows.
 
-use chrono::{Datelike, Local, Timelike};
 use crate::directorium::get_kalendar;
 use crate::horas::horascommon::rankname;
 use crate::regex::{ci_contains, subdirname};
@@ -47,11 +46,11 @@ pub fn romannumber(mut d: i32, version: &str) -> String {
 /// Returns a “romanday” string for a date given in "mm-dd" format.
 /// Uses the provided version when calling romannumber.
 pub fn romanday(date: &str, version: &str) -> String {
-    if date.len() < 5 {
+    let (Some(m), Some(d)) = (date.get(0..2), date.get(3..5)) else {
         return String::new();
-    }
-    let m: i32 = date[0..2].parse().unwrap_or(0);
-    let d: i32 = date[3..5].parse().unwrap_or(0);
+    };
+    let m: i32 = m.parse().unwrap_or(0);
+    let d: i32 = d.parse().unwrap_or(0);
     if d == 1 {
         return "{Kal.}".to_string();
     }
@@ -185,29 +184,30 @@ pub fn findkalentry(ctx: &mut SetupStringContext, entry: &str, ver: &str, day: u
 
 /// Returns the kalendar entry for a given date (in "mm-dd-yyyy" format) and version string.
 pub fn kalendar_entry(ctx: &mut SetupStringContext, date: &str, ver: &str) -> String {
-    if date.len() < 5 {
+    let Some(date_trimmed) = date.get(0..5) else {
         return String::new();
-    }
-    let date_trimmed = &date[0..5];
+    };
     let kal_str = get_kalendar(ver, date_trimmed).unwrap_or_default();
     let mut kal_entries: Vec<&str> = kal_str.split('~').collect();
     if kal_entries.is_empty() {
         return String::new();
     }
     let first = kal_entries.remove(0);
-    let (antiphon, rankfont) = findkalentry(ctx, first, ver);
+    let part = |i: usize| date.split('-').nth(i).and_then(|p| p.parse().ok());
+    let (month, day, year) = (part(0).unwrap_or(0), part(1).unwrap_or(0), part(2).unwrap_or(0) as i32);
+    let (antiphon, rankfont) = findkalentry(ctx, first, ver, day, month, year);
     let mut output = format!("{} {}", antiphon, rankfont);
     if (ver.contains("1955") || ver.contains("196"))
         && date_trimmed.starts_with("01-")
         && {
-            let d: i32 = date_trimmed[3..5].parse().unwrap_or(0);
+            let d: i32 = date_trimmed.get(3..5).and_then(|d| d.parse().ok()).unwrap_or(0);
             (7..=12).contains(&d)
         }
     {
         return String::new();
     }
     for ke in kal_entries {
-        let (d1, _d2) = findkalentry(ctx, ke, ver);
+        let (d1, _d2) = findkalentry(ctx, ke, ver, day, month, year);
         output.push_str(&format!(" Com. {}", d1));
     }
     output
@@ -334,6 +334,7 @@ mod tests {
         assert_eq!(romanday("03-01", "Monastic 1962"), "{Kal.}".to_string());
         // For a date with day 15 in month 3 (since 3 is in {3,5,7,10}), we return "{Idib.}"
         assert_eq!(romanday("03-15", "Monastic 1962"), "{Idib.}".to_string());
+        assert_eq!(romanday("ää-1", "Monastic 1962"), "");
     }
 
     #[test]
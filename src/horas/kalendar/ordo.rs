@@ -10,6 +10,7 @@
 use std::collections::HashMap;
 use std::mem;
 use crate::directorium::dirge;
+use crate::regex::ci_find;
 use crate::{liturgical_color, setfont};
 use crate::setup_string::{setupstring, ResolveDirectives};
 
@@ -138,7 +139,7 @@ fn maybe_append_l1(c1: &mut String, version: &str, winner: &str, winner_map: &Ha
 /// If the date (first five characters) is between "01-13" and "12-24", then if winner (case‑insensitively)
 /// contains "sancti", swap c1 and c2; otherwise, clear c2 unless it contains "Commemoratio" or "Scriptura".
 fn maybe_swap_or_clear_columns(date: &str, c1: &mut String, c2: &mut String, winner: &str) {
-    let date_prefix = &date[0..5];
+    let date_prefix = date.get(0..5).unwrap_or(date);
     if date_prefix < "12-24" && date_prefix > "01-13" {
         if winner.to_lowercase().contains("sancti") {
             mem::swap(c1, c2);
@@ -178,14 +179,9 @@ fn compute_cv(daynames: &[String]) -> String {
         return String::new();
     }
     let cv = &daynames[2];
-    let lower = cv.to_lowercase();
-    if let Some(pos) = lower.find("vespera") {
-        return cv[pos..].trim().to_string();
-    }
-    if let Some(pos) = lower.find("a capitulo") {
-        return cv[pos..].trim().to_string();
-    }
-    String::new()
+    ci_find(cv, "vespera")
+        .or_else(|| ci_find(cv, "a capitulo"))
+        .map_or_else(String::new, |pos| cv[pos..].trim().to_string())
 }
 
 /// The main ordo_entry function.
@@ -256,7 +252,7 @@ pub fn html_header(ctx: &HtmlHeaderContext) -> String {
         mmenu.push("<A HREF=\"#\" onclick=\"setkm(-1)\">«</A>\n".to_string());
     }
     for i in 1..=12 {
-        let mn = &ctx.monthnames[i][..3];
+        let mn: String = ctx.monthnames[i].chars().take(3).collect();
         let line = if i == ctx.kmonth {
             mn.to_string()
         } else {
@@ -365,6 +361,9 @@ mod tests {
         let mut c2 = "Other".to_string();
         maybe_swap_or_clear_columns("12-25-2024", &mut c1, &mut c2, "not");
         assert_eq!(c2, "");
+        // A date shorter than five bytes, or whose fifth byte is within a letter.
+        maybe_swap_or_clear_columns("äää", &mut c1, &mut c2, "sancti");
+        maybe_swap_or_clear_columns("01", &mut c1, &mut c2, "sancti");
     }
 
     #[test]
@@ -383,6 +382,8 @@ mod tests {
         ];
         let cv = compute_cv(&daynames);
         assert_eq!(cv.to_lowercase(), "vespera extra".to_string());
+        let daynames = vec![String::new(), String::new(), "ẞẞẞ Vespera de sequenti".to_string()];
+        assert_eq!(compute_cv(&daynames), "Vespera de sequenti");
     }
 
     #[test]
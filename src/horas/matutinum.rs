@@ -0,0 +1,325 @@
+//! matutinum.rs
+//!
+//! The nocturns of Matins, from `psalmi_matutinum()`, `lectiones()` and
+//! `nocturn()` in `/horas/specmatins.pl`: each nocturn with its antiphons
+//! and psalms, its versicle, the Pater noster and the absolution, and its
+//! lessons, each with its benediction and responsory.
+//!
+//! A double or semidouble office (under the 1960 rubrics, one of the first
+//! or second class) has three nocturns of three lessons; the other offices
+//! have one nocturn with three lessons. The monastic Sundays and feasts have
+//! three nocturns of four lessons.
+//!
+//! The last responsory of each nocturn ends with the Gloria Patri. When the
+//! Te Deum is said, it takes the place of the last responsory of Matins, and
+//! the Gloria goes to the one before it; the monastic Matins keep the
+//! twelfth responsory before the Te Deum.
+
+use super::psalterium::{antiphon_doubling, intonation, psalm_directive};
+use super::specials::{rubric_line, translate, InputConfig};
+use super::Hora;
+use super::specmatins::dayofweek2i;
+use crate::regex::contains_ci;
+use crate::rubrics::GloriaPart;
+use crate::setup_string::{FileSections, ResolveDirectives, SetupStringProvider};
+
+/// A lesson of Matins with the benediction before it and the responsory
+/// after it (`None` where the Te Deum is said instead).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lectio {
+    /// The number of the lesson in Matins, from 1.
+    pub number: usize,
+    pub benedictio: String,
+    pub text: String,
+    pub responsory: Option<String>,
+}
+
+/// A nocturn: its antiphons and psalms, versicle, absolution and lessons.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Nocturn {
+    /// The number of the nocturn, from 1.
+    pub number: usize,
+    /// The antiphons with their psalms ("3", "9(2-12)", ...).
+    pub psalmi: Vec<(String, String)>,
+    /// The versicle and response after the psalms.
+    pub versum: Vec<String>,
+    pub absolutio: String,
+    pub lectiones: Vec<Lectio>,
+}
+
+/// Returns whether the Te Deum is said at Matins of the office of `config`.
+///
+/// A `Te Deum` or `no Te Deum` in the rule of the office decides. Otherwise
+/// it is said on Sundays and feasts, but not on the Sundays of Advent and
+/// from Septuagesima to Easter, nor on the ferias outside Paschaltide.
+pub fn te_deum(config: &InputConfig) -> bool {
+    if contains_ci(&config.rule, "no Te Deum") {
+        return false;
+    }
+    if contains_ci(&config.rule, "Te Deum") {
+        return true;
+    }
+    let dayname = config.dayname0();
+    let penitential = dayname.starts_with("Adv") || dayname.starts_with("Quad");
+    let feria = config.winner.contains("Tempora") && config.dayofweek != 0 && config.rank < 2.0;
+    if feria {
+        return dayname.starts_with("Pasc");
+    }
+    !(penitential && config.winner.contains("Tempora"))
+}
+
+/// Returns the number of nocturns and of lessons in each of them.
+pub fn nocturn_count(config: &InputConfig) -> (usize, usize) {
+    let version = config.rubrics();
+    let three = if version.is_1960() { config.rank >= 5.0 } else { config.rank >= 2.0 };
+    let three = three || config.dayofweek == 0 && config.winner.contains("Tempora");
+    match (three, version.monastic) {
+        (true, true) => (3, 4),
+        (true, false) => (3, 3),
+        (false, _) => (1, 3),
+    }
+}
+
+/// Builds the nocturns of Matins of the office of `config`.
+///
+/// The antiphons and psalms are those of the office (`Ant Matutinum`), or
+/// else those of the day of the week in `Psalterium/Psalmi/Psalmi
+/// matutinum.txt` ("Day0" ... "Day6"). The lessons and responsories are the
+/// `LectioN` and `ResponsoryN` of the office, or of its commune. The
+/// absolutions and benedictions come from `Psalterium/Benedictions.txt`
+/// ("Absolutiones", "Nocturn 1" ... "Nocturn 3"); an office of one nocturn
+/// takes those of the weekday (`dayofweek2i()`).
+pub fn nocturns(config: &InputConfig, files: &mut dyn SetupStringProvider, lang: &str) -> Vec<Nocturn> {
+    let (count, per_nocturn) = nocturn_count(config);
+    let te_deum = te_deum(config);
+    let psalter = files.setupstring(lang, "Psalterium/Psalmi/Psalmi matutinum.txt", ResolveDirectives::All);
+    let psalmi = config
+        .winners()
+        .get("Ant Matutinum")
+        .or_else(|| psalter.as_ref()?.get(&format!("Day{}", config.dayofweek)))
+        .map(|text| psalm_groups(text, count))
+        .unwrap_or_default();
+    let benedictions = files
+        .setupstring(lang, "Psalterium/Benedictions.txt", ResolveDirectives::All)
+        .unwrap_or_default();
+
+    let total = count * per_nocturn;
+    let monastic = config.rubrics().monastic;
+    (1..=count)
+        .map(|number| {
+            // An office of one nocturn says the absolution and benedictions
+            // of the nocturn of its weekday.
+            let set = if count == 1 { dayofweek2i(config.dayofweek) as usize } else { number };
+            let absolutio = section_line(&benedictions, "Absolutiones", set - 1);
+            let lectiones = (1..=per_nocturn)
+                .map(|i| {
+                    let n = (number - 1) * per_nocturn + i;
+                    let responsory = if te_deum && n == total && !monastic {
+                        None
+                    } else {
+                        let gloria = (i == per_nocturn || (te_deum && !monastic && n + 1 == total))
+                            && config.gloria_patri(GloriaPart::Responsory);
+                        lesson_part(config, &format!("Responsory{}", n)).map(|r| responsory_gloria(&r, gloria))
+                    };
+                    Lectio {
+                        number: n,
+                        benedictio: section_line(&benedictions, &format!("Nocturn {}", set), i - 1),
+                        text: lesson_part(config, &format!("Lectio{}", n)).unwrap_or_default(),
+                        responsory,
+                    }
+                })
+                .collect();
+            let (psalmi, versum) = psalmi.get(number - 1).cloned().unwrap_or_default();
+            Nocturn { number, psalmi, versum, absolutio, lectiones }
+        })
+        .collect()
+}
+
+/// Returns the lines of Matins from the first nocturn to the Te Deum, as
+/// `specials()` puts them in the script.
+pub fn matutinum(config: &InputConfig, files: &mut dyn SetupStringProvider, lang: &str) -> Vec<String> {
+    let version = config.rubrics();
+    let doubling = antiphon_doubling(config.rank, &version, Hora::Matutinum);
+    let gloria = config.gloria_patri(GloriaPart::Psalm);
+    let mut lines = Vec::new();
+    for nocturn in nocturns(config, files, lang) {
+        lines.push(format!("!{} {}", translate("Nocturn", lang), nocturn.number));
+        for (ant, psalm) in &nocturn.psalmi {
+            if let Some(intonation) = intonation(ant, doubling, lang, &version) {
+                lines.push(format!("Ant. {}", intonation));
+            }
+            lines.push(psalm_directive(psalm, gloria));
+            lines.push(format!("Ant. {}", ant));
+        }
+        lines.extend(nocturn.versum.iter().cloned());
+        lines.push(rubric_line("secreto", lang, &version));
+        lines.push("$Pater noster Et".to_string());
+        lines.push(format!("Absolutio. {}", nocturn.absolutio));
+        lines.push("$Amen".to_string());
+        for lectio in &nocturn.lectiones {
+            lines.push("$Jube domne".to_string());
+            lines.push(format!("Benedictio. {}", lectio.benedictio));
+            lines.push("$Amen".to_string());
+            lines.push(format!("!{} {}", translate("Lectio", lang), lectio.number));
+            lines.push(lectio.text.trim_end().to_string());
+            lines.push("$Tu autem".to_string());
+            if let Some(responsory) = &lectio.responsory {
+                lines.push(responsory.trim_end().to_string());
+            }
+        }
+    }
+    if te_deum(config) {
+        lines.push("$Te Deum".to_string());
+    }
+    lines
+}
+
+/// The antiphons and psalms of a nocturn with its versicle.
+pub(crate) type PsalmGroup = (Vec<(String, String)>, Vec<String>);
+
+/// Splits a psalm section of Matins into the psalms and the versicle of each
+/// of `count` nocturns. The lines are "antiphon;;psalm" followed by the
+/// "V." and "R." lines that close the nocturn; with one nocturn, all the
+/// psalms go into it, with the last versicle.
+pub(crate) fn psalm_groups(text: &str, count: usize) -> Vec<PsalmGroup> {
+    let mut groups: Vec<PsalmGroup> = Vec::new();
+    let mut current = (Vec::new(), Vec::new());
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if line.starts_with("V.") || line.starts_with("R.") {
+            current.1.push(line.to_string());
+            if line.starts_with("R.") {
+                groups.push(std::mem::take(&mut current));
+            }
+        } else if let Some((ant, psalm)) = line.split_once(";;") {
+            current.0.push((ant.trim().to_string(), psalm.trim().to_string()));
+        }
+    }
+    if !current.0.is_empty() || !current.1.is_empty() {
+        groups.push(current);
+    }
+    if count == 1 && groups.len() > 1 {
+        let versum = groups.last().map(|g| g.1.clone()).unwrap_or_default();
+        let psalmi = groups.into_iter().flat_map(|g| g.0).collect();
+        return vec![(psalmi, versum)];
+    }
+    groups
+}
+
+/// The `key` ("Lectio4", "Responsory4") of the office, or of its commune.
+fn lesson_part(config: &InputConfig, key: &str) -> Option<String> {
+    config
+        .winners()
+        .get(key)
+        .or_else(|| config.commune_map.get(key))
+        .filter(|t| !t.trim().is_empty())
+        .cloned()
+}
+
+/// Adds the Gloria Patri to the last responsory of a nocturn, unless the
+/// responsory has it already.
+pub(crate) fn responsory_gloria(responsory: &str, gloria: bool) -> String {
+    if gloria && !responsory.contains("&Gloria") && !responsory.contains("Glória Patri") {
+        format!("{}\n&Gloria1", responsory.trim_end())
+    } else {
+        responsory.to_string()
+    }
+}
+
+/// The line `index` of the section `name`, or an empty string.
+pub(crate) fn section_line(sections: &FileSections, name: &str, index: usize) -> String {
+    sections
+        .get(name)
+        .and_then(|s| s.lines().filter(|l| !l.trim().is_empty()).nth(index))
+        .map(|l| l.trim().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::horas::specials::tests::TestFiles;
+    use crate::horas::Hora;
+
+    fn benedictions() -> TestFiles {
+        TestFiles::default().with("Latin", "Psalterium/Benedictions.txt", &[
+            ("Absolutiones", "Exáudi, Dómine Jesu Christe.\nIpsíus pietas et misericórdia.\nA vínculis peccatórum."),
+            ("Nocturn 1", "Benedictióne perpétua.\nUnigénitus Dei Fílius.\nSpíritus Sancti grátia."),
+            ("Nocturn 2", "Deus Pater omnípotens.\nChristus perpétue.\nIgnem sui amóris."),
+            ("Nocturn 3", "Evangélica léctio.\nDivínum auxílium.\nAd societátem cívium."),
+        ])
+    }
+
+    fn feast() -> InputConfig {
+        let mut config = InputConfig::new(Hora::Matutinum);
+        config.version = "Divino Afflatu".to_string();
+        config.winner = "Sancti/01-21.txt".to_string();
+        config.rank = 3.0;
+        config.dayofweek = 2;
+        config.daynames = vec!["Epi2-2".to_string()];
+        let mut ant = String::new();
+        for n in 1..=3 {
+            ant.push_str(&format!("Ant{}a;;{}\nAnt{}b;;{}\nAnt{}c;;{}\nV. Versus {}.\nR. Responsio {}.\n", n, n, n, n + 1, n, n + 2, n, n));
+        }
+        config.winner_map.insert("Ant Matutinum".to_string(), ant);
+        for n in 1..=9 {
+            config.winner_map.insert(format!("Lectio{}", n), format!("Lesson {}.", n));
+            config.winner_map.insert(format!("Responsory{}", n), format!("R. Responsory {}.", n));
+        }
+        config
+    }
+
+    #[test]
+    fn test_nocturns_of_a_feast() {
+        let config = feast();
+        let mut files = benedictions();
+        let nocturns = nocturns(&config, &mut files, "Latin");
+        assert_eq!(nocturns.len(), 3);
+        let second = &nocturns[1];
+        assert_eq!(second.psalmi.len(), 3);
+        assert_eq!(second.psalmi[0], ("Ant2a".to_string(), "2".to_string()));
+        assert_eq!(second.versum, vec!["V. Versus 2.", "R. Responsio 2."]);
+        assert_eq!(second.absolutio, "Ipsíus pietas et misericórdia.");
+        assert_eq!(second.lectiones[0].number, 4);
+        assert_eq!(second.lectiones[0].benedictio, "Deus Pater omnípotens.");
+        assert_eq!(second.lectiones[0].text, "Lesson 4.");
+        assert_eq!(second.lectiones[2].responsory.as_deref(), Some("R. Responsory 6.\n&Gloria1"));
+        // The Te Deum replaces the ninth responsory; the eighth has the Gloria.
+        let third = &nocturns[2];
+        assert_eq!(third.lectiones[1].responsory.as_deref(), Some("R. Responsory 8.\n&Gloria1"));
+        assert_eq!(third.lectiones[2].responsory, None);
+        let lines = matutinum(&config, &mut files, "Latin");
+        assert_eq!(lines.last().map(String::as_str), Some("$Te Deum"));
+    }
+
+    #[test]
+    fn test_one_nocturn_feria() {
+        let mut config = feast();
+        config.winner = "Tempora/Adv1-2.txt".to_string();
+        config.daynames = vec!["Adv1-2".to_string()];
+        config.rank = 1.0;
+        assert!(!te_deum(&config));
+        assert_eq!(nocturn_count(&config), (1, 3));
+        let mut files = benedictions();
+        let nocturns = nocturns(&config, &mut files, "Latin");
+        assert_eq!(nocturns.len(), 1);
+        assert_eq!(nocturns[0].psalmi.len(), 9);
+        assert_eq!(nocturns[0].versum, vec!["V. Versus 3.", "R. Responsio 3."]);
+        // Tuesday takes the absolution and benedictions of the second nocturn.
+        assert_eq!(nocturns[0].absolutio, "Ipsíus pietas et misericórdia.");
+        assert_eq!(nocturns[0].lectiones[2].responsory.as_deref(), Some("R. Responsory 3.\n&Gloria1"));
+
+        config.rule = "Te Deum".to_string();
+        assert!(te_deum(&config));
+    }
+
+    #[test]
+    fn test_monastic_twelve_lessons() {
+        let mut config = feast();
+        config.version = "Monastic - 1930".to_string();
+        assert_eq!(nocturn_count(&config), (3, 4));
+        let mut files = benedictions();
+        let nocturns = nocturns(&config, &mut files, "Latin");
+        assert_eq!(nocturns.iter().map(|n| n.lectiones.len()).sum::<usize>(), 12);
+        assert_eq!(nocturns[2].lectiones[3].number, 12);
+    }
+}
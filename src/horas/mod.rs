@@ -1,11 +1,29 @@
 mod appendix;
-mod altovadum;
+pub(crate) mod altovadum;
+pub mod necrologium;
 mod kalendar;
 mod webdia;
 mod horasjs;
 mod specmatins;
-mod monastic;
-mod horascommon;
+pub mod monastic;
+pub(crate) mod horascommon;
+mod postprocess;
+pub mod hora;
+pub mod specials;
+pub mod octave;
+pub mod officium;
+pub mod psalterium;
+pub mod rank;
+pub mod completorium;
+pub mod matutinum;
+pub mod defunctorum;
+pub mod parvum;
+pub mod elements;
+pub mod tenebrae;
+pub mod rite;
 
-// mod kalendar;
-// mod specials;
\ No newline at end of file
+pub use hora::Hora;
+pub use elements::{ChantRef, OfficeElement};
+pub use officium::{
+    build_hour, build_hour_bilingual, build_hour_compare, build_hour_elements, build_hour_json, build_tenebrae, DayOffice, Kalendar,
+};
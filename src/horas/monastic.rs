@@ -1,16 +1,26 @@
+use std::collections::HashMap;
+
 use crate::{
-    date::{leap_year, monthday}, 
-    fileio::do_read, 
-    language_text_tools::{alleluia_ant, prayer, translate, LanguageTextContext}, 
-    setup_string::{checkfile, setupstring, ResolveDirectives},
+    date::{leap_year, monthday, CalendarSystem},
+    fileio::do_read,
+    language_text_tools::{alleluia_ant, prayer, translate, LanguageTextContext},
+    setup_string::{checkfile, ResolveDirectives, SetupStringProvider},
     regex::{replace_from_first, remove_prefix_to_last},
+    rubrics::{gloria_patri_allowed, GloriaContext, GloriaPart, RubricsVersion},
 };
 
-use super::specmatins::get_c10_readingname;
+use super::matutinum::{responsory_gloria, section_line, te_deum};
+use super::psalterium::{antiphon_doubling, intonation};
+use super::rank::Rank;
+use super::postprocess::{alleluia_required, matins_lectio_responsory_alleluia, postprocess_ant, postprocess_vr};
+use super::specials::{gettempora, specials_build::BuildTrace, InputConfig};
+use super::specmatins::{dayofweek2i, get_c10_readingname, nocturn};
+use super::Hora;
 
 
 /// Holds all context data (formerly globals)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LiturgyContext {
     pub day: i32,
     pub month: i32,
@@ -22,15 +32,104 @@ pub struct LiturgyContext {
     pub version: String,
     pub rank: f32,
     pub rule: String,
-    pub winner: std::collections::HashMap<String, String>,
-    pub commune: Option<std::collections::HashMap<String, String>>,
+    pub winner: HashMap<String, String>,
+    pub commune: Option<HashMap<String, String>>,
     pub votive: String,
     pub datafolder: String,
+    /// The language of the first column; empty when only one is shown.
+    pub lang1: String,
+    /// The column being built: 2 for the second, the first otherwise.
+    pub column: usize,
+    /// Whether the columns compare two versions in the same language, as
+    /// `InputConfig::compare`.
+    pub compare: bool,
+    /// The winner and commune read in the language of the second column.
+    pub winner2: HashMap<String, String>,
+    pub commune2: Option<HashMap<String, String>>,
+    /// The building script of the hour.
+    pub build: BuildTrace,
+}
+
+impl LiturgyContext {
+    /// The `version` string as a structured `RubricsVersion`.
+    pub fn rubrics(&self) -> RubricsVersion {
+        RubricsVersion::parse(&self.version)
+    }
+
+    /// Whether `lang` is the language of the first column (the Perl
+    /// `columnsel()`), whose texts are in `winner` and `commune`; in compare
+    /// mode, whether the column being built is the first.
+    pub fn columnsel(&self, lang: &str) -> bool {
+        if self.compare {
+            self.column != 2
+        } else {
+            self.lang1.is_empty() || lang.eq_ignore_ascii_case(&self.lang1)
+        }
+    }
+
+    /// The winner map of the column of `lang`.
+    pub fn winners(&self, lang: &str) -> &HashMap<String, String> {
+        if self.columnsel(lang) {
+            &self.winner
+        } else {
+            &self.winner2
+        }
+    }
+
+    /// The commune map of the column of `lang`, if the office has one.
+    pub fn communes(&self, lang: &str) -> Option<&HashMap<String, String>> {
+        if self.columnsel(lang) {
+            self.commune.as_ref()
+        } else {
+            self.commune2.as_ref()
+        }
+    }
+
+    /// The first dayname (the week, e.g. "Pasc2-3"), or "".
+    pub fn dayname0(&self) -> &str {
+        self.dayname.first().map(String::as_str).unwrap_or("")
+    }
+
+    /// Looks up `name` in the proper of the office in `lang`, and in its
+    /// commune if `flag` is set, recording the substitution in the building
+    /// script.
+    pub fn getproprium(&self, name: &str, lang: &str, flag: bool) -> Option<String> {
+        if let Some(w) = self.winners(lang).get(name) {
+            self.build.setbuild1(name, "subst");
+            return Some(w.clone());
+        }
+        let w = self.communes(lang).filter(|_| flag)?.get(name)?;
+        self.build.setbuild1(name, "subst commune");
+        Some(w.clone())
+    }
+
+    /// The office as an `InputConfig` of Matins, for the helpers shared with
+    /// the Roman office (`gettempora()`, `te_deum()`).
+    pub fn input_config(&self) -> InputConfig {
+        let mut config = InputConfig::new(Hora::Matutinum);
+        config.version = self.version.clone();
+        config.rule = self.rule.clone();
+        config.rank = self.rank as f64;
+        config.day = self.day as u32;
+        config.month = self.month as u32;
+        config.year = self.year;
+        config.dayofweek = self.dayofweek as u32;
+        config.daynames = self.dayname.clone();
+        config.votive = self.votive.clone();
+        config.column = if self.column == 2 { 2 } else { 1 };
+        config.lang1 = self.lang1.clone();
+        config.compare = self.compare;
+        config.winner_map = self.winner.clone();
+        config.winner2_map = self.winner2.clone();
+        let commune = if config.column == 2 { &self.commune2 } else { &self.commune };
+        config.commune_map = commune.clone().unwrap_or_default();
+        config
+    }
 }
 
 /// Generates a name for a feria given the weekday.
 pub fn makeferia(dayofweek: usize) -> String {
-    let nametab = vec!["Sunday", "II.", "III.", "IV.", "V.", "VI.", "Sabbato"];
+    let nametab = ["Sunday", "II.", "III.", "IV.", "V.", "VI.", "Sabbato"];
     let name = match nametab.get(dayofweek) {
         Some(s) => s.to_string(),
         None => "".to_string(),
@@ -44,16 +143,19 @@ pub fn makeferia(dayofweek: usize) -> String {
 
 /// Generates the appropriate psalm and lessons for the monastic version.
 ///
-/// Returns a vector of output lines.
-pub fn psalmi_matutinum_monastic(ctx2: &LanguageTextContext, lang: &str, ctx: &LiturgyContext) -> Vec<String> {
-    // (Dummy psalm numbers; they are not used further in our code.)
-    let _psalmnum1 = -1;
-    let _psalmnum2 = -1;
-
-    // Read the antiphons-psalms from the psalterium (dummy stub)
-    let psalmi_map = setupstring(
-        lang, "Psalterium/Psalmi/Psalmi matutinum.txt", ResolveDirectives::All
-    ).unwrap_or_default();
+/// Returns the lines of Matins from the first nocturn: the psalmody of each
+/// nocturn with its lessons, and the Capitulum closing the second nocturn
+/// of an office without the third.
+pub fn psalmi_matutinum_monastic(
+    ctx2: &LanguageTextContext,
+    lang: &str,
+    ctx: &LiturgyContext,
+    files: &mut dyn SetupStringProvider,
+) -> Vec<String> {
+    // Read the antiphons-psalms from the psalterium
+    let psalmi_map = files
+        .setupstring(lang, "Psalterium/Psalmi/Psalmi matutinum.txt", ResolveDirectives::All)
+        .unwrap_or_default();
 
     let daym_key = format!("Daym{}", ctx.dayofweek);
     let mut psalmi: Vec<String> = psalmi_map
@@ -85,15 +187,14 @@ pub fn psalmi_matutinum_monastic(ctx2: &LanguageTextContext, lang: &str, ctx: &L
         }
     }
 
-    setbuild(
+    ctx.build.setbuild(
         "Psalterium/Psalmi/Psalmi matutinum monastic",
         &format!("dayM{}", ctx.dayofweek),
         "Psalmi ord",
     );
 
-    let comment = 1;
-    let prefix = translate(ctx2, "Antiphonae", lang);
-    let name = gettempora("Psalmi Matutinum Monastic", lang);
+    let name = gettempora(&ctx.input_config(), "Psalmi Matutinum Monastic");
+    let dayname0 = ctx.dayname0();
 
     // Special Adv–Pasc antiphons for Sundays.
     if ctx.dayofweek == 0 && matches_adv_or_pasch(&name) {
@@ -103,280 +204,256 @@ pub fn psalmi_matutinum_monastic(ctx2: &LanguageTextContext, lang: &str, ctx: &L
     }
 
     // Special antiphons for non‐Quad weekdays.
-    if (ctx.dayofweek > 0
-        && !contains_quad(ctx.dayname.get(0).unwrap_or(&"".to_string())))
-        || ctx
-            .winner
-            .values()
-            .any(|v| v.contains("Pasc6-0"))
-    {
-        let start = if matches_pasc(ctx.dayname.get(0).unwrap_or(&"".to_string()))
-            || matches_nat23(ctx.dayname.get(0).unwrap_or(&"".to_string()))
-        {
-            0
-        } else {
-            8
-        };
+    if (ctx.dayofweek > 0 && !contains_quad(dayname0)) || ctx.winner.values().any(|v| v.contains("Pasc6-0")) {
+        let start = if matches_pasc(dayname0) || matches_nat23(dayname0) { 0 } else { 8 };
         let mut p: Vec<String> = vec![];
-        if matches_pasc(ctx.dayname.get(0).unwrap_or(&"".to_string())) {
+        if matches_pasc(dayname0) {
             if let Some(val) = psalmi_map.get("Daym Pasch") {
                 p = val.lines().map(|s| s.to_string()).collect();
             }
-        } else if matches_nat23(ctx.dayname.get(0).unwrap_or(&"".to_string())) {
+        } else if matches_nat23(dayname0) {
             if let Some(val) = psalmi_map.get("Daym Nat") {
                 p = val.lines().map(|s| s.to_string()).collect();
             }
         }
-        let mut psalmi = psalmi; // shadow to make mutable
         for i in start..14 {
             let mut p_line = p.get(i).cloned().unwrap_or_default();
-            if let Some(psalm_line) = psalmi.get(i) {
-                if let Some(idx) = psalm_line.find(";;") {
-                    let suffix = &psalm_line[idx + 2..];
-                    p_line = format!(";;{}", suffix);
-                }
+            if let Some(idx) = psalmi.get(i).and_then(|l| l.find(";;")) {
+                p_line = psalmi[i][idx..].to_string();
             }
             if i == 0 || i == 8 {
-                if !matches_nat23_or_pasc0(ctx.dayname.get(0).unwrap_or(&"".to_string())) {
-                    p_line = format!("{}{}", alleluia_ant(ctx2, lang), p_line);
+                // The psalms of the nocturn are said under one antiphon, the
+                // Alleluia or the antiphon of the season.
+                let ant = if !matches_nat23_or_pasc0(dayname0) {
+                    alleluia_ant(ctx2, lang)
                 } else {
-                    let current = p.get(i).unwrap_or(&"".to_string()).to_string();
-                    p_line = format!("{}{}", current, p_line);
-                }
+                    p.get(i).and_then(|l| l.split(";;").next()).unwrap_or("").to_string()
+                };
+                p_line = format!("{}{}", ant, p_line);
             }
             if let Some(line) = psalmi.get_mut(i) {
                 *line = p_line;
             }
         }
-        setbuild2("Antiphonas Psalmi weekday special no Quad");
+        ctx.build.setbuild2("Antiphonas Psalmi weekday special no Quad");
     }
 
     // Change of versicle for Adv, Quad, Pasc, etc.
-    if !name.is_empty()
-        && (ctx
-            .winner
-            .values()
-            .any(|v| v.starts_with("Tempora"))
-            || (name == "Nat" || name == "Epi"))
-    {
-        let mut i = if ctx.dayofweek == 0 { 1 } else { ctx.dayofweek };
-        let src = "Psalterium".to_string();
-        if i > 3 {
-            i -= 3;
-        }
-        if name != "Asc" {
-            if let Some(val) = psalmi_map.get(&format!("{} {} Versum", name, i)) {
-                let parts: Vec<&str> = val.lines().collect();
-                if parts.len() >= 2 && psalmi.len() > 7 {
-                    psalmi[6] = parts[0].to_string();
-                    psalmi[7] = parts[1].to_string();
-                }
-            }
-            if ctx.dayofweek == 0 {
-                if let Some(val) = psalmi_map.get(&format!("{} 2 Versum", name)) {
-                    let parts: Vec<&str> = val.lines().collect();
-                    if parts.len() >= 2 && psalmi.len() > 15 {
-                        psalmi[14] = parts[0].to_string();
-                        psalmi[15] = parts[1].to_string();
-                    }
-                }
-                if let Some(val) = psalmi_map.get(&format!("{} 3 Versum", name)) {
-                    let parts: Vec<&str> = val.lines().collect();
-                    if parts.len() >= 2 && psalmi.len() > 18 {
-                        psalmi[17] = parts[0].to_string();
-                        psalmi[18] = parts[1].to_string();
-                    }
-                }
-            }
+    if !name.is_empty() && (ctx.winner.values().any(|v| v.starts_with("Tempora")) || (name == "Nat" || name == "Epi")) {
+        let i = dayofweek2i(ctx.dayofweek as u32);
+        let versicles = if name != "Asc" {
+            [format!("{} {} Versum", name, i), format!("{} 2 Versum", name), format!("{} 3 Versum", name)]
+                .map(|key| psalmi_map.get(&key).cloned())
         } else {
-            let c = if columnsel(lang) {
-                ctx.winner.clone()
-            } else {
-                ctx.winner.clone()
-            };
-            let src = "commune".to_string();
-            if let Some(val) = c.get(&format!("Nocturn {} Versum", i)) {
-                let parts: Vec<&str> = val.lines().collect();
-                if parts.len() >= 2 && psalmi.len() > 7 {
-                    psalmi[6] = parts[0].to_string();
-                    psalmi[7] = parts[1].to_string();
-                }
-            }
-            if ctx.dayofweek == 0 {
-                if let Some(val) = c.get("Nocturn 2 Versum") {
-                    let parts: Vec<&str> = val.lines().collect();
-                    if parts.len() >= 2 && psalmi.len() > 15 {
-                        psalmi[14] = parts[0].to_string();
-                        psalmi[15] = parts[1].to_string();
-                    }
-                }
-                if let Some(val) = c.get("Nocturn 3 Versum") {
-                    let parts: Vec<&str> = val.lines().collect();
-                    if parts.len() >= 2 && psalmi.len() > 18 {
-                        psalmi[17] = parts[0].to_string();
-                        psalmi[18] = parts[1].to_string();
-                    }
-                }
-            }
+            [format!("Nocturn {} Versum", i), "Nocturn 2 Versum".to_string(), "Nocturn 3 Versum".to_string()]
+                .map(|key| ctx.winners(lang).get(&key).cloned())
+        };
+        // The versicle of the first nocturn, and on Sundays those of the
+        // second and the third.
+        let nocturns = if ctx.dayofweek == 0 { 3 } else { 1 };
+        for (versicle, at) in versicles.iter().zip([6, 14, 17]).take(nocturns) {
+            set_versum(&mut psalmi, at, versicle.as_deref());
         }
-        setbuild(&src, &format!("{} {} Versum", name, i), "subst");
+        let src = if name != "Asc" { "Psalterium" } else { "commune" };
+        ctx.build.setbuild(src, &format!("{} {} Versum", name, i), "subst");
     }
 
     if ctx.month == 12 && ctx.day == 24 {
-        if ctx.dayofweek != 0 {
-            if let Some(val) = psalmi_map.get("Nat24 Versum") {
-                let parts: Vec<&str> = val.lines().collect();
-                if parts.len() >= 2 && psalmi.len() > 7 {
-                    psalmi[6] = parts[0].to_string();
-                    psalmi[7] = parts[1].to_string();
-                }
-            }
-        } else {
-            if let Some(val) = psalmi_map.get("Nat24 Versum") {
-                let parts: Vec<&str> = val.lines().collect();
-                if parts.len() >= 2 && psalmi.len() > 18 {
-                    psalmi[17] = parts[0].to_string();
-                    psalmi[18] = parts[1].to_string();
-                }
-            }
-        }
-        setbuild2("subst: Versus Nat24");
+        let at = if ctx.dayofweek != 0 { 6 } else { 17 };
+        set_versum(&mut psalmi, at, psalmi_map.get("Nat24 Versum").map(String::as_str));
+        ctx.build.setbuild2("subst: Versus Nat24");
     }
 
-    if ctx.winner.contains_key("Cantica") {
-        if let Some(cantica) = ctx.winner.get("Cantica") {
-            let c_lines: Vec<&str> = cantica.lines().collect();
-            for i in 0..3 {
-                if psalmi.len() > i + 16 {
-                    psalmi[i + 16] = c_lines.get(i).unwrap_or(&"").to_string();
-                }
+    if let Some(cantica) = ctx.winner.get("Cantica") {
+        let c_lines: Vec<&str> = cantica.lines().collect();
+        for i in 0..3 {
+            if psalmi.len() > i + 16 {
+                psalmi[i + 16] = c_lines.get(i).unwrap_or(&"").to_string();
             }
         }
     }
 
+    let label = translate(ctx2, "Nocturn", lang);
+    let version = ctx.rubrics();
+    let doubling = antiphon_doubling(ctx.rank as f64, &version, Hora::Matutinum);
+    let intone = |ant: &str| intonation(ant, doubling, lang, &version);
+    let mut output = nocturn(1, &label, &psalmi, &[0, 1, 2, 3, 4, 5, 6, 7], &intone);
+
     // Decide between lectiones or brevis/legend readings.
     let rule_contains_12 = ctx.rule.contains("12 lectiones");
     let rule_contains_3 = ctx.rule.contains("3 lectiones");
-    let version_lower = ctx.version.to_lowercase();
-    let cond_divino = (ctx.rank >= 4.0 && version_lower.contains("divino"))
-        || (ctx.rank >= 2.0 && version_lower.contains("trident"));
-    let dayname1 = ctx.dayname.get(1).unwrap_or(&"".to_string());
-    let dayname1_lower = dayname1.to_lowercase();
+    let rubrics = ctx.rubrics();
+    let cond_divino = (ctx.rank >= 4.0 && rubrics.is_divino())
+        || (ctx.rank >= 2.0 && rubrics.is_tridentine());
+    let dayname1_lower = ctx.dayname.get(1).map(|d| d.to_lowercase()).unwrap_or_default();
     let cond_dayname1 = !(dayname1_lower.contains("feria")
         || dayname1_lower.contains("sabbato")
         || dayname1_lower.contains("infra octavam"));
+    let twelve_lessons = rule_contains_12 || (cond_divino && cond_dayname1 && !rule_contains_3);
+    let rank = Rank::of(&ctx.winner);
 
-    if rule_contains_12 || (cond_divino && cond_dayname1 && !rule_contains_3) {
-        lectiones(1, lang);
-    } else if matches_pasc1_6_or_pent(ctx.dayname.get(0).unwrap_or(&"".to_string()))
+    if twelve_lessons {
+        output.extend(lectiones(1, lang, ctx, ctx2, files));
+    } else if matches_pasc1_6_or_pent(dayname0)
         && !starts_with_11_digit_dash(&monthday(
             ctx.day as u32,
             ctx.month as u32,
             ctx.year,
-        ctx.version.contains("196"),
+            rubrics.is_1960(),
             false,
+            CalendarSystem::Gregorian,
         ))
-        && !contains_rank_keywords(ctx.winner.get("Rank").unwrap_or(&"".to_string()))
-        && ((!ctx
-            .winner
-            .get("Rank")
-            .unwrap_or(&"".to_string())
-            .to_lowercase()
-            .contains("secunda")
-            && ctx
-                .winner
-                .get("Rank")
-                .unwrap_or(&"".to_string())
-                .to_lowercase()
-                .contains("roga"))
-            || ctx.version.contains("196"))
+        && !has_ferial_lessons(&rank)
+        && ((!rank.title_contains("secunda") && rank.title_contains("roga")) || rubrics.is_1960())
         && !rule_contains_3
     {
         if ctx.winner.contains_key("Tempora")
             || !(ctx.winner.contains_key("Lectio94") || ctx.winner.contains_key("Lectio4"))
         {
-            brevis_monastic(lang, ctx, ctx2);
-        } else if ctx.winner.contains_key("Lectio94") || ctx.winner.contains_key("Lectio4") {
-            legend_monastic(lang, ctx, ctx2);
+            output.extend(brevis_monastic(lang, ctx, ctx2, files));
+        } else {
+            output.extend(legend_monastic(lang, ctx, ctx2, files));
         }
     } else {
-        lectiones(0, lang);
+        output.extend(lectiones(0, lang, ctx, ctx2, files));
     }
-    if !ctx.rule.contains("12 lectiones") {
-        if psalmi.len() > 14 {
-            psalmi[14].clear();
-        }
-        if psalmi.len() > 15 {
-            psalmi[15].clear();
+    if !rule_contains_12 {
+        for line in psalmi.iter_mut().skip(14).take(2) {
+            line.clear();
         }
     }
-    nocturn(2, lang, &psalmi, &vec![8, 9, 10, 11, 12, 13, 14, 15]);
-
-    if rule_contains_12 || (cond_divino && cond_dayname1 && !rule_contains_3) {
-        lectiones(2, lang);
-        if let Some(line) = psalmi.get(16) {
-            let parts: Vec<&str> = line.split(";;").collect();
-            let ant = parts.get(0).unwrap_or(&"").to_string();
-            let mut p = parts.get(1).unwrap_or(&"").to_string();
-            let w = ctx.winner.clone();
-            if let Some(ant_3n) = w.get("Ant Matutinum 3N") {
-                let t_lines: Vec<String> =
-                    ant_3n.lines().map(|s| s.to_string()).collect();
-                for (i, line) in t_lines.iter().enumerate() {
-                    if psalmi.len() > 16 + i {
-                        psalmi[16 + i] = line.clone();
-                    }
+    output.extend(nocturn(2, &label, &psalmi, &[8, 9, 10, 11, 12, 13, 14, 15], &intone));
+
+    if twelve_lessons {
+        output.extend(lectiones(2, lang, ctx, ctx2, files));
+        if let Some(ant_3n) = ctx.winners(lang).get("Ant Matutinum 3N") {
+            for (i, line) in ant_3n.lines().enumerate() {
+                if let Some(entry) = psalmi.get_mut(16 + i) {
+                    *entry = line.to_string();
                 }
-                let parts_new: Vec<&str> = psalmi[16].split(";;").collect();
-                let ant_new = parts_new.get(0).unwrap_or(&ant).to_string();
-                p = parts_new.get(1).unwrap_or(&p).to_string();
-            }
-            p = p.replace(&['(', '-'][..], ",").replace(")", "");
-            postprocess_ant(&ant, lang);
-            if psalmi.len() > 16 {
-                psalmi[16] = format!("{};;{}", ant, p);
             }
         }
-        nocturn(3, lang, &psalmi, &vec![16, 17, 18]);
-        lectiones(3, lang);
-        return psalmi;
+        if let Some(line) = psalmi.get_mut(16) {
+            let (ant, p) = line.split_once(";;").unwrap_or((line.as_str(), ""));
+            let mut ant = ant.to_string();
+            let p = p.replace(['(', '-'], ",").replace(')', "");
+            postprocess_ant(&mut ant, dayname0, &ctx.votive, files, lang);
+            *line = format!("{};;{}", ant, p);
+        }
+        output.extend(nocturn(3, &label, &psalmi, &[16, 17, 18], &intone));
+        output.extend(lectiones(3, lang, ctx, ctx2, files));
+        return output;
     }
 
     // After 2nd nocturn: handle the Capitulum.
-    let (w, _c) = getproprium("MM Capitulum", lang, ctx, 0, 1);
-    let mut capitulum = if w.is_empty() {
-        if let Some(commune) = &ctx.commune {
-            commune.get("MM Capitulum").cloned().unwrap_or_default()
-        } else {
-            "".to_string()
-        }
-    } else {
-        w
-    };
+    let mut capitulum = ctx
+        .getproprium("MM Capitulum", lang, false)
+        .or_else(|| ctx.communes(lang)?.get("MM Capitulum").cloned())
+        .unwrap_or_default();
     if capitulum.is_empty() {
-        let temp_name = gettempora("MM Capitulum", lang);
-        let s_map = setupstring(lang, "Psalterium/Special/Matutinum Special.txt", ResolveDirectives::All).unwrap_or_default();
+        let season = gettempora(&ctx.input_config(), "MM Capitulum");
+        let s_map = files
+            .setupstring(lang, "Psalterium/Special/Matutinum Special.txt", ResolveDirectives::All)
+            .unwrap_or_default();
         capitulum = s_map
-            .get(&format!("MM Capitulum{}", temp_name))
+            .get(&format!("MM Capitulum {}", season))
+            .or_else(|| s_map.get("MM Capitulum"))
             .cloned()
             .unwrap_or_default();
     }
-    if ctx
-        .dayname
-        .get(0)
-        .unwrap_or(&"".to_string())
-        .to_lowercase()
-        .contains("pasc")
-    {
-        postprocess_vr(&capitulum, lang);
+    if dayname0.to_lowercase().contains("pasc") {
+        postprocess_vr(&mut capitulum, dayname0, &ctx.votive, files, lang);
     }
-    let mut output = psalmi;
     output.push("!!Capitulum".to_string());
     output.push(capitulum);
     output.push("".to_string());
     output
 }
 
+/// Puts the versicle `text` ("V. ...\nR. ...") of a nocturn at `at` and
+/// `at + 1` of the psalmody.
+fn set_versum(psalmi: &mut [String], at: usize, text: Option<&str>) {
+    let Some(text) = text else { return };
+    let parts: Vec<&str> = text.lines().collect();
+    if parts.len() >= 2 && psalmi.len() > at + 1 {
+        psalmi[at] = parts[0].to_string();
+        psalmi[at + 1] = parts[1].to_string();
+    }
+}
+
+/// Returns the lessons of the nocturn `num` of the monastic Matins, with the
+/// Pater noster and absolution before them: the four lessons of each of the
+/// three nocturns of an office of twelve lessons (`num` 1 to 3), or the
+/// three lessons of the first nocturn of the other offices (`num` 0).
+///
+/// Each lesson has its benediction and responsory; the last responsory of
+/// the nocturn ends with the Gloria Patri. The third nocturn ends with the
+/// Te Deum and, where it is read, the Gospel with the *Te decet laus*.
+pub fn lectiones(
+    num: usize,
+    lang: &str,
+    ctx: &LiturgyContext,
+    lctx: &LanguageTextContext,
+    files: &mut dyn SetupStringProvider,
+) -> Vec<String> {
+    let benedictions = files
+        .setupstring(lang, "Psalterium/Benedictions.txt", ResolveDirectives::All)
+        .unwrap_or_default();
+    // The ferial nocturn says the absolution and benedictions of its weekday.
+    let (set, first, count) = if num == 0 {
+        (dayofweek2i(ctx.dayofweek as u32) as usize, 1, 3)
+    } else {
+        (num, (num - 1) * 4 + 1, 4)
+    };
+    let part = |key: &str| -> Option<String> {
+        ctx.winners(lang)
+            .get(key)
+            .or_else(|| ctx.communes(lang)?.get(key))
+            .filter(|t| !t.trim().is_empty())
+            .cloned()
+    };
+
+    let mut output = vec![
+        "".to_string(),
+        "$Pater noster_".to_string(),
+        "_".to_string(),
+        format!("Absolutio. {}", section_line(&benedictions, "Absolutiones", set - 1)),
+        "$Amen".to_string(),
+    ];
+    for i in 0..count {
+        let n = first + i;
+        output.push("".to_string());
+        output.push(prayer(lctx, "Jube domne", lang));
+        output.push(format!("Benedictio. {}", section_line(&benedictions, &format!("Nocturn {}", set), i)));
+        output.push("$Amen".to_string());
+        output.push(format!("!{} {}", translate(lctx, "Lectio", lang), n));
+        output.push(part(&format!("Lectio{}", n)).unwrap_or_default().trim_end().to_string());
+        output.push("$Tu autem".to_string());
+        output.push("_".to_string());
+        if let Some(resp) = part(&format!("Responsory{}", n)) {
+            let mut resp = responsory_gloria(&resp, i + 1 == count && responsory_gloria_allowed(ctx));
+            matins_lectio_responsory_alleluia(&mut resp, ctx.dayname0(), &ctx.votive, files, lang);
+            output.push(resp.trim_end().to_string());
+        }
+    }
+    if num == 3 && te_deum(&ctx.input_config()) {
+        output.push("_".to_string());
+        output.push("$Te Deum".to_string());
+        if lectio_e_required(ctx) {
+            output.push("_".to_string());
+            output.push(lectio_e(lang, ctx, lctx, files));
+            output.push("$Te decet".to_string());
+        }
+    }
+    output
+}
+
+/// Whether the responsories of Matins end with the Gloria Patri on the day
+/// of `ctx`.
+fn responsory_gloria_allowed(ctx: &LiturgyContext) -> bool {
+    gloria_patri_allowed(&GloriaContext { dayname: ctx.dayname0(), votive: &ctx.votive, part: GloriaPart::Responsory })
+}
+
 /// Returns the proper legend reading if appropriate.
 pub fn monastic_lectio3(w: &str, lang: &str, ctx: &LiturgyContext) -> String {
     if !ctx
@@ -390,18 +467,14 @@ pub fn monastic_lectio3(w: &str, lang: &str, ctx: &LiturgyContext) -> String {
     {
         return w.to_string();
     }
-    let winner_map = if columnsel(lang) {
-        ctx.winner.clone()
-    } else {
-        ctx.winner.clone()
-    };
+    let winner_map = ctx.winners(lang);
     let mut str_val = if let Some(val) = winner_map.get("Lectio94") {
         val.to_string()
     } else {
-        winner_map.get("Lectio4").unwrap_or(&"".to_string()).to_string()
+        winner_map.get("Lectio4").cloned().unwrap_or_default()
     };
     str_val = remove_te_deum(&str_val);
-    if str_val.chars().next().map(|c| c.is_alphabetic()).unwrap_or(false) {
+    if str_val.chars().next().is_some_and(|c| c.is_alphabetic()) {
         str_val = format!("v. {}", str_val);
     }
     let mut output = Vec::new();
@@ -409,28 +482,23 @@ pub fn monastic_lectio3(w: &str, lang: &str, ctx: &LiturgyContext) -> String {
     output.push("$Tu autem".to_string());
     output.push("_".to_string());
 
-    let mut resp = if let Some(r) = winner_map.get("Responsory1") {
-        r.to_string()
-    } else {
-        let commune_map = if columnsel(lang) {
-            ctx.commune.clone().unwrap_or_default()
-        } else {
-            ctx.commune.clone().unwrap_or_default()
-        };
-        commune_map
-            .get("Responsory1")
-            .cloned()
-            .unwrap_or_else(|| "Responsory for ne lesson not found!".to_string())
-    };
-    resp = responsory_gloria(&resp, 3);
-    // (Dummy stub for alleluia handling.)
-    output.push(resp);
-    output
+    let resp = winner_map
+        .get("Responsory1")
+        .or_else(|| ctx.communes(lang)?.get("Responsory1"))
+        .cloned()
+        .unwrap_or_else(|| "Responsory for ne lesson not found!".to_string());
+    output.push(responsory_gloria(&resp, responsory_gloria_allowed(ctx)));
+    output.join("\n")
 }
 
 
 /// Return lines that implement “Absolution and Benedictio” logic.
-pub fn absolutio_benedictio(lang: &str, ctx: &LiturgyContext, lctx: &LanguageTextContext) -> Vec<String> {
+pub fn absolutio_benedictio(
+    lang: &str,
+    ctx: &LiturgyContext,
+    lctx: &LanguageTextContext,
+    files: &mut dyn SetupStringProvider,
+) -> Vec<String> {
     let mut output = Vec::new();
 
     // Check if the “commune” map has an entry whose value contains "C10".
@@ -444,19 +512,21 @@ pub fn absolutio_benedictio(lang: &str, ctx: &LiturgyContext, lctx: &LanguageTex
         // In the original Perl: we read from commune's "Benedictio" lines.
         // e.g. @a = split("\n", $m{Benedictio}); abs=$a[0]; ben=$a[3].
         let m = ctx.commune.as_ref().unwrap();
-        let benedictio_all = m.get("Benedictio").unwrap_or(&"".to_string());
+        let benedictio_all = m.get("Benedictio").map(String::as_str).unwrap_or("");
         let lines: Vec<&str> = benedictio_all.lines().collect();
-        let abs_str = lines.get(0).unwrap_or(&"").to_string();
+        let abs_str = lines.first().unwrap_or(&"").to_string();
         let ben_str = lines.get(3).unwrap_or(&"").to_string();
         // We might do a setbuild2("Special benedictio") call here. Omitted for brevity.
         (abs_str, ben_str)
     } else {
         // Otherwise read from Psalterium/Benedictions.txt => "Nocturn i" and "Absolutiones".
-        let ben_map = setupstring(lang, "Psalterium/Benedictions.txt", ResolveDirectives::All).unwrap_or_default();
-        let i = dayofweek2i(ctx);
+        let ben_map = files
+            .setupstring(lang, "Psalterium/Benedictions.txt", ResolveDirectives::All)
+            .unwrap_or_default();
+        let i = dayofweek2i(ctx.dayofweek as u32) as usize;
         let nocturn_key = format!("Nocturn {}", i);
-        let a_all = ben_map.get(&nocturn_key).unwrap_or(&"".to_string());
-        let absolutiones_all = ben_map.get("Absolutiones").unwrap_or(&"".to_string());
+        let a_all = ben_map.get(&nocturn_key).map(String::as_str).unwrap_or("");
+        let absolutiones_all = ben_map.get("Absolutiones").map(String::as_str).unwrap_or("");
 
         let a_lines: Vec<&str> = a_all.lines().collect();
         let abs_lines: Vec<&str> = absolutiones_all.lines().collect();
@@ -496,30 +566,31 @@ pub fn absolutio_benedictio(lang: &str, ctx: &LiturgyContext, lctx: &LanguageTex
 }
 
 /// Returns the “Legend (contracted reading) for monastic days”.
-pub fn legend_monastic(lang: &str, ctx: &LiturgyContext, lctx: &LanguageTextContext) -> Vec<String> {
+pub fn legend_monastic(
+    lang: &str,
+    ctx: &LiturgyContext,
+    lctx: &LanguageTextContext,
+    files: &mut dyn SetupStringProvider,
+) -> Vec<String> {
     let mut output = Vec::new();
 
     // 1) Insert the absolution & benediction lines first.
-    let mut ab = absolutio_benedictio(lang, ctx, lctx);
+    let mut ab = absolutio_benedictio(lang, ctx, lctx, files);
     output.append(&mut ab);
 
     // 2) Gather the reading from the “winner” map, either “Lectio94” or “Lectio4”.
-    let winner_map = if columnsel(lang) {
-        ctx.winner.clone()
-    } else {
-        ctx.winner.clone() // or “winner2” in older code
-    };
+    let winner_map = ctx.winners(lang);
 
     let mut reading = if let Some(v) = winner_map.get("Lectio94") {
         v.clone()
     } else {
         // fallback: Lectio4 plus possibly Lectio5 and Lectio6 if some condition is met
-        let mut s = winner_map.get("Lectio4").unwrap_or(&"".to_string()).to_string();
+        let mut s = winner_map.get("Lectio4").cloned().unwrap_or_default();
         if let Some(l5) = winner_map.get("Lectio5") {
             // In Perl: `if (exists($w{Lectio5}) && $w{Lectio5} !~ /!/) { $str .= $w{Lectio5} . $w{Lectio6}; }`
             // So we do a simple check if it does NOT contain '!'
             if !l5.contains('!') {
-                let l6 = winner_map.get("Lectio6").unwrap_or(&"".to_string());
+                let l6 = winner_map.get("Lectio6").map(String::as_str).unwrap_or("");
                 s.push_str(l5);
                 s.push_str(l6);
             }
@@ -528,18 +599,7 @@ pub fn legend_monastic(lang: &str, ctx: &LiturgyContext, lctx: &LanguageTextCont
     };
 
     // Remove "&teDeum" with trailing spaces
-    loop {
-        if let Some(pos) = reading.find("&teDeum") {
-            // remove any subsequent whitespace as well
-            let mut end = pos + "&teDeum".len();
-            while end < reading.len() && reading.as_bytes()[end].is_ascii_whitespace() {
-                end += 1;
-            }
-            reading.replace_range(pos..end, "");
-        } else {
-            break;
-        }
-    }
+    reading = remove_te_deum(&reading);
 
     // If the text starts with an alphabetic letter, prepend "v. ".
     if let Some(ch) = reading.chars().next() {
@@ -554,37 +614,16 @@ pub fn legend_monastic(lang: &str, ctx: &LiturgyContext, lctx: &LanguageTextCont
     output.push("_".to_string());
 
     // 4) Build the responsory (Responsory1 from the winner or from the commune).
-    let mut resp = if let Some(r) = winner_map.get("Responsory1") {
-        r.clone()
-    } else {
-        let commune_map = if columnsel(lang) {
-            ctx.commune.clone().unwrap_or_default()
-        } else {
-            ctx.commune.clone().unwrap_or_default()
-        };
-        // fallback
-        commune_map
-            .get("Responsory1")
-            .cloned()
-            .unwrap_or_else(|| "Responsory for ne lesson not found!".to_string())
-    };
+    let resp = winner_map
+        .get("Responsory1")
+        .or_else(|| ctx.communes(lang)?.get("Responsory1"))
+        .cloned()
+        .unwrap_or_else(|| "Responsory for ne lesson not found!".to_string());
 
-    // Add Gloria if needed
-    resp = responsory_gloria(&resp, 3);
-
-    // If alleluia is required, we might do something like:
-    if alleluia_required(
-        ctx.dayname.get(0).unwrap_or(&"".to_string()),
-        &ctx.votive,
-    ) {
-        let appended = matins_lectio_responsory_alleluia(&resp, lang);
-        // In the original code, the new text got appended or replaced.
-        // You might either modify `resp` or push another line.
-        // We'll just push it for demonstration:
-        if !appended.is_empty() {
-            resp.push('\n');
-            resp.push_str(&appended);
-        }
+    // Add Gloria, and the alleluia in Paschaltide
+    let mut resp = responsory_gloria(&resp, responsory_gloria_allowed(ctx));
+    if alleluia_required(ctx.dayname0(), &ctx.votive) {
+        matins_lectio_responsory_alleluia(&mut resp, ctx.dayname0(), &ctx.votive, files, lang);
     }
 
     output.push(resp);
@@ -593,23 +632,23 @@ pub fn legend_monastic(lang: &str, ctx: &LiturgyContext, lctx: &LanguageTextCont
 }
 
 /// Implements the “brevis” (short) reading.
-pub fn brevis_monastic(lang: &str, ctx: &LiturgyContext, lctx: &LanguageTextContext) -> Vec<String> {
+pub fn brevis_monastic(
+    lang: &str,
+    ctx: &LiturgyContext,
+    lctx: &LanguageTextContext,
+    files: &mut dyn SetupStringProvider,
+) -> Vec<String> {
     let mut output = Vec::new();
-    output.extend(absolutio_benedictio(lang, ctx, lctx));
-    let lectio: String;
-    if let Some(commune) = &ctx.commune {
-        if commune.values().any(|v| v.contains("C10")) {
+    output.extend(absolutio_benedictio(lang, ctx, lctx, files));
+    let commune = ctx.commune.as_ref().filter(|c| !c.is_empty());
+    let lectio = match commune {
+        Some(commune) if commune.values().any(|v| v.contains("C10")) => {
             let name = get_c10_readingname(&ctx.version, ctx.month as u32, ctx.day as u32);
             let mut resp_lines: Vec<String> = commune
                 .get("Responsory3")
-                .cloned()
-                .unwrap_or_default()
-                .lines()
-                .map(|s| s.to_string())
-                .collect();
-            if matches_pasc(
-                ctx.dayname.get(0).unwrap_or(&"".to_string()).as_str(),
-            ) {
+                .map(|r| r.lines().map(|s| s.to_string()).collect())
+                .unwrap_or_default();
+            if matches_pasc(ctx.dayname0()) {
                 if let Some(line) = resp_lines.get_mut(1) {
                     compress_alleluia(line);
                 }
@@ -617,101 +656,78 @@ pub fn brevis_monastic(lang: &str, ctx: &LiturgyContext, lctx: &LanguageTextCont
                     compress_alleluia(line);
                 }
             }
-            let reading = commune
-                .get(&name)
-                .unwrap_or(&"".to_string())
-                .replace(".teDeum", "");
-            lectio = format!("{}\n$Tu autem\n_\n{}", reading, resp_lines.join("\n"));
-            setbuild2(&format!("Mariae {}", name));
-        } else if let Some(commune) = &ctx.commune {
-            if !commune.is_empty() && !commune.keys().any(|k| k.starts_with("C")) {
-                lectio = commune.get("MM LB").unwrap_or(&"".to_string()).to_string();
-            } else {
-                let b_map = setupstring(lang, "Psalterium/Special/Matutinum Special.txt", ResolveDirectives::All).unwrap_or_default();
-                let key = if matches_pasc(ctx.dayname.get(0).unwrap_or(&"".to_string())) {
-                    "MM LB Pasch".to_string()
-                } else {
-                    format!("MM LB{}", ctx.dayofweek)
-                };
-                lectio = b_map.get(&key).cloned().unwrap_or_default();
-            }
-        } else {
-            let b_map = setupstring(lang, "Psalterium/Special/Matutinum Special.txt", ResolveDirectives::All).unwrap_or_default();
-            let key = if matches_pasc(ctx.dayname.get(0).unwrap_or(&"".to_string())) {
+            let reading = commune.get(&name).map(|r| r.replace(".teDeum", "")).unwrap_or_default();
+            ctx.build.setbuild2(&format!("Mariae {}", name));
+            format!("{}\n$Tu autem\n_\n{}", reading, resp_lines.join("\n"))
+        }
+        Some(commune) if !commune.keys().any(|k| k.starts_with('C')) => {
+            commune.get("MM LB").cloned().unwrap_or_default()
+        }
+        _ => {
+            let b_map = files
+                .setupstring(lang, "Psalterium/Special/Matutinum Special.txt", ResolveDirectives::All)
+                .unwrap_or_default();
+            let key = if matches_pasc(ctx.dayname0()) {
                 "MM LB Pasch".to_string()
             } else {
                 format!("MM LB{}", ctx.dayofweek)
             };
-            lectio = b_map.get(&key).cloned().unwrap_or_default();
+            b_map.get(&key).cloned().unwrap_or_default()
         }
-    } else {
-        let b_map = setupstring(lang, "Psalterium/Special/Matutinum Special.txt", ResolveDirectives::All).unwrap_or_default();
-        let key = if matches_pasc(ctx.dayname.get(0).unwrap_or(&"".to_string())) {
-            "MM LB Pasch".to_string()
-        } else {
-            format!("MM LB{}", ctx.dayofweek)
-        };
-        lectio = b_map.get(&key).cloned().unwrap_or_default();
-    }
+    };
     let lectio = lectio.replace("&Gloria1?", "&Gloria1");
     output.push(lectio);
     output
 }
 
 /// Returns the Evangelium text.
-pub fn lectio_e(lang: &str, ctx: &LiturgyContext, tctx: &LanguageTextContext) -> String {
-    let winner_map = if columnsel(lang) {
-        ctx.winner.clone()
-    } else {
-        ctx.winner.clone()
-    };
-    let mut e_lines: Vec<String> = if let Some(e) = winner_map.get("LectioE") {
-        e.lines().map(|s| s.to_string()).collect()
-    } else {
-        vec![]
-    };
-
-    if e_lines.is_empty() || e_lines.get(0).map(|s| s.starts_with("@")).unwrap_or(false) {
-        let parts: Vec<&str> = e_lines.get(0).unwrap_or(&"").split(':').collect();
-        let mut w_val = if !parts.is_empty() && !parts[0].is_empty() {
+pub fn lectio_e(
+    lang: &str,
+    ctx: &LiturgyContext,
+    tctx: &LanguageTextContext,
+    files: &mut dyn SetupStringProvider,
+) -> String {
+    let winner_map = ctx.winners(lang);
+    let mut e_lines: Vec<String> = winner_map
+        .get("LectioE")
+        .map(|e| e.lines().map(|s| s.to_string()).collect())
+        .unwrap_or_default();
+
+    if e_lines.first().is_none_or(|s| s.starts_with('@')) {
+        let reference = e_lines.first().map(|s| s.trim_start_matches('@').to_string()).unwrap_or_default();
+        let parts: Vec<&str> = reference.split(':').collect();
+        let w_val = if !parts[0].is_empty() {
             format!("{}.txt", parts[0])
         } else {
-            ctx.winner
-                .get("default")
-                .unwrap_or(&"default".to_string())
-                .to_string()
+            ctx.winner.get("default").cloned().unwrap_or_else(|| "default".to_string())
         };
-        w_val = w_val.replace("M", "");
+        let w_val = w_val.replace('M', "");
         let s_val = parts.get(1).unwrap_or(&"Evangelium").replace("LectioE", "Evangelium");
-        let missa_map = setupstring(&format!("../missa/{}", lang), &w_val, ResolveDirectives::All).unwrap_or_default();
+        let missa_map = files
+            .setupstring(&format!("../missa/{}", lang), &w_val, ResolveDirectives::All)
+            .unwrap_or_default();
         e_lines = missa_map
             .get(&s_val)
-            .cloned()
-            .unwrap_or_default()
-            .lines()
-            .map(|s| s.to_string())
-            .collect();
+            .map(|e| e.lines().map(|s| s.to_string()).collect())
+            .unwrap_or_default();
     }
-    let mut begin = e_lines.get(0).cloned().unwrap_or_default();
-    begin = format!("v.{}", begin.trim_start_matches("v. "));
-    if ctx.version.starts_with("Monastic") {
-        begin = begin.replace("+", "++");
-        if let Some(next_line) = e_lines.get(1).cloned() {
-            begin = format!(
-                "{}\n{}\nR. {}",
-                begin,
-                next_line,
-                translate(tctx, "Gloria tibi Domine", lang)
-            );
+    if e_lines.is_empty() {
+        return String::new();
+    }
+    let mut begin = format!("v.{}", e_lines[0].trim_start_matches("v. "));
+    if ctx.rubrics().monastic {
+        begin = begin.replace('+', "++");
+        if let Some(next_line) = e_lines.get(1) {
+            begin = format!("{}\n{}\nR. {}", begin, next_line, translate(tctx, "Gloria tibi Domine", lang));
         }
     } else {
-        begin = begin.replace("+", "");
+        begin = begin.replace('+', "");
         if e_lines.len() > 1 {
             e_lines.remove(1);
         }
     }
     e_lines[0] = begin;
-    e_lines.retain(|line| !line.starts_with("!"));
+    e_lines.retain(|line| !line.starts_with('!'));
     if e_lines.len() > 1 {
         e_lines[1] = format!("v.{}", e_lines[1].trim_start_matches("v. "));
     }
@@ -720,21 +736,26 @@ pub fn lectio_e(lang: &str, ctx: &LiturgyContext, tctx: &LanguageTextContext) ->
 
 /// Determines if the Evangelium is required.
 pub fn lectio_e_required(ctx: &LiturgyContext) -> bool {
-    ctx.rank > 2.0 || ctx.commune.as_ref().map(|m| m.contains_key("C10")).unwrap_or(false)
+    ctx.rank > 2.0 || ctx.commune.as_ref().is_some_and(|m| m.contains_key("C10"))
 }
 
 /// For Ordo Praedicatorum: returns the text of the Regula
-pub fn regula_vel_evangelium(lang: &str, ctx: &LiturgyContext, tctx: &LanguageTextContext) -> String {
-    let ben_map = setupstring(lang, "Psalterium/Benedictions.txt", ResolveDirectives::All).unwrap_or_default();
+pub fn regula_vel_evangelium(
+    lang: &str,
+    ctx: &LiturgyContext,
+    tctx: &LanguageTextContext,
+    files: &mut dyn SetupStringProvider,
+) -> String {
+    let ben_map = files.setupstring(lang, "Psalterium/Benedictions.txt", ResolveDirectives::All).unwrap_or_default();
     let b_lines_vec = ben_map.get("Nocturn 3").cloned().unwrap_or_default();
     let b_lines: Vec<&str> = b_lines_vec.lines().collect();
-    let r_map = setupstring(lang, "Regula/OrdoPraedicatorum.txt", ResolveDirectives::All).unwrap_or_default();
+    let r_map = files.setupstring(lang, "Regula/OrdoPraedicatorum.txt", ResolveDirectives::All).unwrap_or_default();
     let be: String;
     let mut output: Vec<String> = vec![];
 
     if lectio_e_required(ctx) {
         be = b_lines.get(3).unwrap_or(&"").to_string();
-        output.push(lectio_e(lang, ctx, tctx));
+        output.push(lectio_e(lang, ctx, tctx, files));
     } else {
         be = r_map.get("Benedictio").cloned().unwrap_or_default();
         output.push("_".to_string());
@@ -754,11 +775,15 @@ pub fn regula_vel_evangelium(lang: &str, ctx: &LiturgyContext, tctx: &LanguageTe
 
 /// Returns the text of the Regula for the day.
 pub fn regula<F: Fn(&str) -> bool>(
-    lang: &str, ctx: &LiturgyContext, pctx: &LanguageTextContext, 
-    langfb: &str, file_exists: &F
+    lang: &str,
+    ctx: &LiturgyContext,
+    pctx: &LanguageTextContext,
+    files: &mut dyn SetupStringProvider,
+    langfb: &str,
+    file_exists: &F,
 ) -> String {
-    if ctx.version.to_lowercase().contains("ordo praedicatorum") {
-        return regula_vel_evangelium(lang, ctx, pctx);
+    if ctx.rubrics().dominican {
+        return regula_vel_evangelium(lang, ctx, pctx, files);
     }
     let mut output = format!("{}\n", prayer(pctx, "benedictio Prima", lang));
     let mut d = ctx.day;
@@ -768,16 +793,14 @@ pub fn regula<F: Fn(&str) -> bool>(
     let fname = format!("{:02}-{:02}", ctx.month, d);
 
     if !(file_exists)(&format!("{}/Latin/Regula/{}.txt", ctx.datafolder, fname)) {
-        let regulatable_lines = do_read(&format!("{}/Latin/Regula/Regulatable.txt", ctx.datafolder));
-        if regulatable_lines.iter().any(|line| line.contains(&fname)) {
-            // adjust fname if necessary
-        } else {
+        let regulatable_lines = do_read(format!("{}/Latin/Regula/Regulatable.txt", ctx.datafolder)).unwrap_or_default();
+        if !regulatable_lines.iter().any(|line| line.contains(&fname)) {
             return output;
         }
     }
     let fname_checked = checkfile(&ctx.datafolder, langfb, lang, &format!("Regula/{}.txt", fname), file_exists);
     let lines = do_read(&fname_checked).unwrap_or_default();
-    let mut title = lines.get(0).cloned().unwrap_or_default();
+    let mut title = lines.first().cloned().unwrap_or_default();
     let content: Vec<String> = lines
         .iter()
         .skip(1)
@@ -857,38 +880,20 @@ fn starts_with_11_digit_dash(s: &str) -> bool {
     }
 }
 
-/// Returns true if `s` (case‑insensitively) contains any of the keywords:
-/// “vigil”, “quatuor” (or “quattuor”), “infra octavam” or “post octavam asc”.
-fn contains_rank_keywords(s: &str) -> bool {
-    let lower = s.to_lowercase();
-    lower.contains("vigil")
-        || lower.contains("quatuor")
-        || lower.contains("quattuor")
-        || lower.contains("infra octavam")
-        || lower.contains("post octavam asc")
+/// Returns true if the office of `rank` is a vigil, an Ember day, a day
+/// within an octave or after the octave of the Ascension.
+fn has_ferial_lessons(rank: &Rank) -> bool {
+    rank.is_vigil() || rank.is_quattuor_temporum() || rank.within_octave() || rank.title_contains("post octavam asc")
 }
 
 /// Remove any occurrence of "&teDeum" followed by any amount of whitespace.
 fn remove_te_deum(text: &str) -> String {
     let mut result = text.to_string();
-    loop {
-        if let Some(pos) = result.find("&teDeum") {
-            let mut end = pos + "&teDeum".len();
-            // Extend end index while the following characters are whitespace.
-            while let Some(ch) = result[end..].chars().next() {
-                if ch.is_whitespace() {
-                    end += ch.len_utf8();
-                } else {
-                    break;
-                }
-                if end >= result.len() {
-                    break;
-                }
-            }
-            result.replace_range(pos..end, "");
-        } else {
-            break;
-        }
+    while let Some(pos) = result.find("&teDeum") {
+        // Extend the end while the following characters are whitespace.
+        let rest = &result[pos + "&teDeum".len()..];
+        let end = result.len() - rest.trim_start().len();
+        result.replace_range(pos..end, "");
     }
     result
 }
@@ -917,6 +922,7 @@ mod tests {
 
     use std::collections::HashMap;
     use crate::{language_text_tools::initialize_language_text_context, setup_string::SetupStringProvider};
+    use crate::horas::specials::tests::TestFiles;
     use super::*;
 
     /// A dummy setup provider that holds a mapping from (lang, file) to key/value data.
@@ -976,12 +982,13 @@ mod tests {
             rule: "".to_string(),
             rank: 1.0,
             day: 2024,
-            month: 01,
-            year: 01,
+            month: 1,
+            year: 1,
             datafolder: "/data".to_string(),
+            ..Default::default()
         };
 
-        let lines = absolutio_benedictio("la", &ctx, &dummy_lang_ctx());
+        let lines = absolutio_benedictio("la", &ctx, &dummy_lang_ctx(), &mut DummySetupStringContext::new());
         assert!(lines.contains(&"Absolutio. AbsLine1".to_string()));
         assert!(lines.contains(&"Benedictio. BenLine4".to_string()));
     }
@@ -1000,12 +1007,13 @@ mod tests {
             rule: "".to_string(),
             rank: 1.0,
             day: 2024,
-            month: 01,
-            year: 01,
+            month: 1,
+            year: 1,
             datafolder: "/data".to_string(),
+            ..Default::default()
         };
 
-        let lines = absolutio_benedictio("la", &ctx, &dummy_lang_ctx());
+        let lines = absolutio_benedictio("la", &ctx, &dummy_lang_ctx(), &mut DummySetupStringContext::new());
         // We only check that some known lines exist:
         assert!(lines.contains(&"$Pater noster_".to_string()));
         assert!(lines.contains(&"Absolutio. ".to_string())); // presumably empty though
@@ -1028,12 +1036,13 @@ mod tests {
             rule: "".to_string(),
             rank: 3.0,
             day: 2024,
-            month: 01,
-            year: 01,
+            month: 1,
+            year: 1,
             datafolder: "/data".to_string(),
+            ..Default::default()
         };
 
-        let lines = legend_monastic("la", &ctx, &dummy_lang_ctx());
+        let lines = legend_monastic("la", &ctx, &dummy_lang_ctx(), &mut DummySetupStringContext::new());
         // The first lines come from `absolutio_benedictio`.
         // Then we expect the reading, “$Tu autem”, “_”, and then the Responsory.
         let reading_pos = lines
@@ -1070,12 +1079,13 @@ mod tests {
             rule: "".to_string(),
             rank: 2.5,
             day: 2024,
-            month: 01,
-            year: 01,
+            month: 1,
+            year: 1,
             datafolder: "/data".to_string(),
+            ..Default::default()
         };
 
-        let lines = legend_monastic("la", &ctx, &dummy_lang_ctx());
+        let lines = legend_monastic("la", &ctx, &dummy_lang_ctx(), &mut DummySetupStringContext::new());
         // The reading line should contain "Part4 Part5 Part6".
         let combined = lines.iter().find(|l| l.contains("Part4 Part5 Part6"));
         assert!(
@@ -1098,11 +1108,12 @@ mod tests {
             rule: "".to_string(),
             rank: 3.0,
             day: 2024,
-            month: 01,
-            year: 01,
+            month: 1,
+            year: 1,
             datafolder: "/data".to_string(),
+            ..Default::default()
         };
-        let lines = legend_monastic("la", &ctx, &dummy_lang_ctx());
+        let lines = legend_monastic("la", &ctx, &dummy_lang_ctx(), &mut DummySetupStringContext::new());
 
         // We should see that "&teDeum" was removed.
         let found = lines
@@ -1130,4 +1141,123 @@ mod tests {
         let title = "some stuff#Rest of title";
         assert_eq!(replace_title(title), "v. Rest of title");
     }
+
+    /// The monastic Psalterium of Matins: the psalms of Sunday and Monday,
+    /// with the benedictions and the chapter of the ferial office.
+    fn psalterium() -> TestFiles {
+        let mut sunday: Vec<String> = (1..=6).map(|n| format!("Antiphona {};;{}", n, n + 2)).collect();
+        sunday.extend(["V. Memor fui.".to_string(), "R. Et custodívi.".to_string()]);
+        sunday.extend((7..=12).map(|n| format!("Antiphona {};;{}", n, n + 2)));
+        sunday.extend(["V. Quóniam tu.".to_string(), "R. Et lex tua.".to_string()]);
+        sunday.extend(["Antiphona 13;;239;240;241".to_string(), "V. Exaltáre.".to_string(), "R. Cantábimus.".to_string()]);
+        let mut monday: Vec<String> = (1..=6).map(|n| format!("Feria {};;{}", n, n + 30)).collect();
+        monday.extend(["V. Dómine.".to_string(), "R. Intende.".to_string()]);
+        monday.extend((7..=12).map(|n| format!("Feria {};;{}", n, n + 30)));
+        monday.extend(["V. Ego dixi.".to_string(), "R. Sana.".to_string()]);
+        let absolutiones = "Exáudi.\nIpsíus pietas.\nA vínculis.";
+        TestFiles::default()
+            .with("Latin", "Psalterium/Psalmi/Psalmi matutinum.txt", &[("Daym0", &sunday.join("\n")), ("Daym1", &monday.join("\n"))])
+            .with("Latin", "Psalterium/Benedictions.txt", &[
+                ("Absolutiones", absolutiones),
+                ("Nocturn 1", "Benedictióne perpétua.\nUnigénitus.\nSpíritus Sancti.\nPer evangélica dicta."),
+                ("Nocturn 2", "Deus Pater.\nChristus.\nIgnem.\nDivínum auxílium."),
+                ("Nocturn 3", "Evangélica léctio.\nDivínum.\nAd societátem.\nCujus festum."),
+            ])
+            .with("Latin", "Psalterium/Special/Matutinum Special.txt", &[
+                ("MM Capitulum", "!Rom 13:11\nv. Fratres: Hora est jam nos de somno súrgere.\n$Deo gratias\n_\nV. Ego dixi.\nR. Sana."),
+            ])
+    }
+
+    fn lessons(count: usize) -> HashMap<String, String> {
+        let mut winner = HashMap::new();
+        for n in 1..=count {
+            winner.insert(format!("Lectio{}", n), format!("Lesson {}.", n));
+            winner.insert(format!("Responsory{}", n), format!("R. Responsory {}.", n));
+        }
+        winner
+    }
+
+    #[test]
+    fn test_matutinum_monastic_twelve_lessons() {
+        let ctx = LiturgyContext {
+            day: 26,
+            month: 1,
+            year: 2025,
+            dayofweek: 0,
+            dayname: vec!["Epi3-0".to_string(), "Dominica III post Epiphaniam".to_string()],
+            version: "Monastic - 1930".to_string(),
+            rank: 5.0,
+            rule: "12 lectiones".to_string(),
+            winner: lessons(12),
+            ..Default::default()
+        };
+        let lines = psalmi_matutinum_monastic(&dummy_lang_ctx(), "Latin", &ctx, &mut psalterium());
+        let headings: Vec<&String> = lines.iter().filter(|l| l.starts_with("!Nocturn")).collect();
+        assert_eq!(headings.len(), 3);
+        let second = lines.iter().position(|l| l.starts_with("!Nocturn") && l.ends_with("II.")).unwrap();
+        assert_eq!(lines[second + 1..second + 4], ["Ant. Antiphona 7", "&psalm(9)", "Ant. Antiphona 7"]);
+        assert!(lines.contains(&"V. Quóniam tu.".to_string()));
+        // The four lessons of each nocturn, the last responsory with the Gloria.
+        assert_eq!(lines.iter().filter(|l| l.starts_with("Benedictio. ")).count(), 12);
+        assert!(lines.contains(&"R. Responsory 4.\n&Gloria1".to_string()));
+        assert!(lines.contains(&"R. Responsory 3.".to_string()));
+        // The cantica of the third nocturn under one antiphon.
+        let third = lines.iter().position(|l| l.ends_with("III.")).unwrap();
+        assert_eq!(lines[third + 1..third + 6], ["Ant. Antiphona 13", "&psalm(239)", "&psalm(240)", "&psalm(241)", "Ant. Antiphona 13"]);
+        assert!(lines.contains(&"Lesson 12.".to_string()));
+        assert!(lines.contains(&"$Te Deum".to_string()));
+        assert!(!lines.contains(&"!!Capitulum".to_string()));
+    }
+
+    #[test]
+    fn test_columnsel() {
+        let mut winner2 = HashMap::new();
+        winner2.insert("Oratio".to_string(), "Almighty God.".to_string());
+        let mut ctx = LiturgyContext {
+            lang1: "Latin".to_string(),
+            winner: lessons(3),
+            winner2,
+            ..Default::default()
+        };
+        assert!(ctx.columnsel("Latin") && !ctx.columnsel("English"));
+        assert_eq!(ctx.getproprium("Oratio", "English", false).as_deref(), Some("Almighty God."));
+        assert_eq!(ctx.input_config().winners()["Lectio1"], "Lesson 1.");
+        // Comparing two versions in Latin, the column decides.
+        ctx.compare = true;
+        ctx.column = 2;
+        assert!(!ctx.columnsel("Latin"));
+        let config = ctx.input_config();
+        assert!(config.compare && !config.columnsel("Latin"));
+        assert_eq!(config.winners()["Oratio"], "Almighty God.");
+    }
+
+    #[test]
+    fn test_matutinum_monastic_feria() {
+        let ctx = LiturgyContext {
+            day: 27,
+            month: 1,
+            year: 2025,
+            dayofweek: 1,
+            dayname: vec!["Epi3-1".to_string(), "Feria II".to_string()],
+            version: "Monastic - 1930".to_string(),
+            rank: 1.0,
+            winner: lessons(3),
+            ..Default::default()
+        };
+        let lines = psalmi_matutinum_monastic(&dummy_lang_ctx(), "Latin", &ctx, &mut psalterium());
+        assert_eq!(lines.iter().filter(|l| l.starts_with("!Nocturn")).count(), 2);
+        // Three lessons with the absolution and benedictions of Monday.
+        assert!(lines.contains(&"Absolutio. Exáudi.".to_string()));
+        assert_eq!(lines.iter().filter(|l| l.starts_with("Benedictio. ")).count(), 3);
+        assert!(lines.contains(&"R. Responsory 3.\n&Gloria1".to_string()));
+        // The psalms of the second nocturn are said under the Alleluia.
+        let second = lines.iter().position(|l| l.ends_with("II.")).unwrap();
+        assert!(lines[second + 1].starts_with("Ant. Alleluja"));
+        assert_eq!(lines[second + 2..second + 8].iter().filter(|l| l.starts_with("&psalm(")).count(), 6);
+        assert!(!lines.contains(&"V. Ego dixi.".to_string()), "the versicle follows the chapter");
+        assert_eq!(lines[lines.len() - 3], "!!Capitulum");
+        assert!(lines[lines.len() - 2].contains("Fratres: Hora est"));
+        assert!(!lines.contains(&"$Te Deum".to_string()));
+        assert_eq!(ctx.build.entries()[0].key.as_deref(), Some("dayM1"));
+    }
 }
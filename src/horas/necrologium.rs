@@ -0,0 +1,242 @@
+//! necrologium.rs
+//!
+//! The Necrologium read at Prima in the monasteries that keep one: the
+//! deceased brethren, benefactors and familiars of the community whose
+//! anniversary falls on the day, concluded by "Quorum animae".
+//!
+//! Each community keeps its necrology in its own layout, so the reading is
+//! described by a `Necrology`: the directory of the data under the language
+//! folders, how the entries of a day are found (`DayPattern`), and the
+//! translators by which entries kept in Latin are read in other languages.
+//! The Necrologium of Vyšší Brod (Altovadum) is `Necrology::altovadum()`,
+//! its Czech rendering being one such translator.
+//!
+//! ```ignore
+//! let necrology = Necrology {
+//!     dir: "Necrologium Sancta Crux".to_string(),
+//!     pattern: DayPattern::DateSection,
+//!     translators: Vec::new(),
+//! };
+//! let text = necrology.text(&ctx, "Latin", 23, 2, 2023, largefont, datafolder, &file_exists);
+//! ```
+
+use crate::date::leap_year;
+use crate::fileio::do_read;
+use crate::horas::altovadum::translate_cz;
+use crate::language_text_tools::{ligaturize, translate, LanguageTextContext};
+use crate::regex::ci_contains;
+use crate::setfont;
+use crate::setup_string::checkfile;
+
+/// The Latin names of the months, by which the monthly files are named.
+const MENSIS: [&str; 13] = [
+    "zero-ius", "Januarius", "Februarius", "Martius", "Aprilis", "Majus", "Junius", "Julius", "Augustus",
+    "September", "October", "November", "December",
+];
+
+/// How the entries of a day are found in the data of a necrology.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DayPattern {
+    /// One file per month (`Januarius.txt` …); the entries of a day follow a
+    /// line with "Die <d>." up to that of the next day, as at Vyšší Brod.
+    DieInMonthFile,
+    /// One file for the year (`Necrologium.txt`); the entries of a day are
+    /// the lines of its section `[MM-DD]`.
+    DateSection,
+}
+
+/// Renders the entries of a necrology kept in Latin in another language.
+#[derive(Debug, Clone, Copy)]
+pub struct Translator {
+    /// The languages the translator is used for.
+    pub langs: &'static [&'static str],
+    /// Translates one line of the necrology.
+    pub translate: fn(&str) -> String,
+}
+
+/// The necrology of a community.
+#[derive(Debug, Clone)]
+pub struct Necrology {
+    /// The directory of the necrology in the language folders.
+    pub dir: String,
+    /// How the entries of a day are found.
+    pub pattern: DayPattern,
+    /// The translators of the Latin entries; a language without one reads
+    /// the necrology in its own folder.
+    pub translators: Vec<Translator>,
+}
+
+impl Necrology {
+    /// The Necrologium of Vyšší Brod, read in Latin or in Czech.
+    pub fn altovadum() -> Self {
+        Necrology {
+            dir: "Necrologium".to_string(),
+            pattern: DayPattern::DieInMonthFile,
+            translators: vec![Translator {
+                langs: &["Bohemice", "Cesky"],
+                translate: |line| translate_cz(&[line.to_string()]),
+            }],
+        }
+    }
+
+    /// The translator of the necrology for `lang`, if it has one.
+    pub fn translator(&self, lang: &str) -> Option<&Translator> {
+        self.translators.iter().find(|t| t.langs.iter().any(|l| ci_contains(lang, l)))
+    }
+
+    /// The path of the file holding the entries of `month` in `lang`.
+    pub fn file<F: Fn(&str) -> bool>(
+        &self,
+        ctx: &LanguageTextContext,
+        lang: &str,
+        month: u32,
+        datafolder: &str,
+        file_exists_fn: &F,
+    ) -> String {
+        let name = match self.pattern {
+            DayPattern::DieInMonthFile => MENSIS.get(month as usize).copied().unwrap_or(MENSIS[0]),
+            DayPattern::DateSection => "Necrologium",
+        };
+        let lang = if self.translator(lang).is_some() { "Latin" } else { lang };
+        checkfile(datafolder, &ctx.fb_lang, lang, &format!("{}/{}.txt", self.dir, name), file_exists_fn)
+    }
+
+    /// Returns the raw lines of `lines` (the file of `file()`) that make the
+    /// entries of `day`/`month`, the heading of the day first if the data
+    /// have one.
+    pub fn day_lines<'a>(&self, lines: &'a [String], day: u32, month: u32, year: i32) -> &'a [String] {
+        let (start, end) = match self.pattern {
+            DayPattern::DieInMonthFile => {
+                let mut tomorrow = day + 1;
+                if day == 28 && month == 2 && !leap_year(year) {
+                    tomorrow += 1;
+                }
+                let today = format!("Die {}.", day);
+                let tomorrow = format!("Die {}.", tomorrow);
+                let Some(start) = lines.iter().position(|l| ci_contains(l, &today)) else {
+                    return &[];
+                };
+                let end = lines[start + 1..]
+                    .iter()
+                    .position(|l| ci_contains(after_hash(l), &tomorrow))
+                    .map_or(lines.len(), |p| start + 1 + p);
+                (start, end)
+            }
+            DayPattern::DateSection => {
+                let header = format!("[{:02}-{:02}]", month, day);
+                let Some(start) = lines.iter().position(|l| l.trim() == header) else {
+                    return &[];
+                };
+                let end = lines[start + 1..]
+                    .iter()
+                    .position(|l| l.trim_start().starts_with('['))
+                    .map_or(lines.len(), |p| start + 1 + p);
+                (start + 1, end)
+            }
+        };
+        &lines[start..end]
+    }
+
+    /// Formats the entries of the day for `lang`: the heading as a versicle,
+    /// each entry on its line, translated if the language has a translator.
+    pub fn format_entries(&self, lines: &[String], lang: &str) -> String {
+        let translator = self.translator(lang);
+        let mut result = String::new();
+        for (i, line) in lines.iter().enumerate() {
+            let mut entry = line.trim().to_string();
+            if entry.is_empty() {
+                continue;
+            }
+            entry = after_hash(&entry).to_string();
+            if entry.trim().is_empty() {
+                entry = format!("_{}", entry);
+            }
+            if let Some(t) = translator {
+                entry = (t.translate)(&entry);
+            }
+            entry = ligaturize(&entry, lang).replace("Tento", "Teuto").replace('•', "r. ");
+            let heading = i == 0 && self.pattern == DayPattern::DieInMonthFile;
+            if heading {
+                result.push_str(&format!("v. {}\n_\n", entry));
+            } else if ci_contains(&entry, "Die") || ci_contains(&entry, "Dne") {
+                result.push_str(&format!("\n_\nv. {}\n_\n", entry));
+            } else {
+                result.push_str(&format!("{}\n", entry));
+            }
+        }
+        result
+    }
+
+    /// Returns the Necrologium of `day`/`month`/`year` in `lang`, headed by
+    /// its title in `largefont` and concluded by "Quorum animae".
+    #[allow(clippy::too_many_arguments)]
+    pub fn text<F: Fn(&str) -> bool>(
+        &self,
+        ctx: &LanguageTextContext,
+        lang: &str,
+        day: u32,
+        month: u32,
+        year: i32,
+        largefont: &str,
+        datafolder: &str,
+        file_exists_fn: &F,
+    ) -> String {
+        let mut t = setfont(largefont, &translate(ctx, "Necrologium", lang));
+        t.push('\n');
+        let fname = self.file(ctx, lang, month, datafolder, file_exists_fn);
+        let lines = do_read(&fname).unwrap_or_default();
+        t.push_str(&self.format_entries(self.day_lines(&lines, day, month, year), lang));
+        t.push_str("$Quorum animae\n");
+        t
+    }
+}
+
+/// The part of `line` after its first '#', the whole line if it has none.
+fn after_hash(line: &str) -> &str {
+    line.find('#').map_or(line, |i| &line[i + 1..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_day_lines_die_in_month_file() {
+        let necrology = Necrology::altovadum();
+        let data = lines("#Die 22. Februarii\nObiit P. Adalbertus\n#Die 23. Februarii\nObiit P. Bernardus\nObiit Fr. Carolus\n#Die 24. Februarii\nObiit P. Dominicus");
+        assert_eq!(necrology.day_lines(&data, 23, 2, 2023), &data[2..5]);
+        assert_eq!(necrology.day_lines(&data, 24, 2, 2023), &data[5..]);
+        assert!(necrology.day_lines(&data, 25, 2, 2023).is_empty());
+        // Without a 29th the 28th of February runs up to the 1st of March.
+        let feb = lines("#Die 28. Februarii\nObiit A\n#Die 29. Februarii\nObiit B\n#Die 30.");
+        assert_eq!(necrology.day_lines(&feb, 28, 2, 2023).len(), 4);
+        assert_eq!(necrology.day_lines(&feb, 28, 2, 2024).len(), 2);
+    }
+
+    #[test]
+    fn test_day_lines_date_section() {
+        let necrology = Necrology { dir: "Necrologium".to_string(), pattern: DayPattern::DateSection, translators: Vec::new() };
+        let data = lines("[02-22]\nObiit A\n[02-23]\nObiit B\nObiit C\n\n[02-24]\nObiit D");
+        assert_eq!(necrology.day_lines(&data, 23, 2, 2023), &data[3..6]);
+        assert!(necrology.day_lines(&data, 1, 3, 2023).is_empty());
+        assert_eq!(necrology.format_entries(necrology.day_lines(&data, 23, 2, 2023), "Latin"), "Obiit B\nObiit C\n");
+    }
+
+    #[test]
+    fn test_format_entries() {
+        let necrology = Necrology::altovadum();
+        let data = lines("   Die 23. something #header\nObiit P. Bernardus");
+        let latin = necrology.format_entries(&data, "Latin");
+        assert!(latin.starts_with("v. header\n_\n"));
+        assert!(latin.ends_with("Obiit P. Bernardus\n"));
+        assert!(necrology.translator("Latin").is_none());
+        assert!(necrology.translator("Bohemice").is_some());
+        let upper = |line: &str| line.to_uppercase();
+        let necrology = Necrology { translators: vec![Translator { langs: &["Test"], translate: upper }], ..necrology };
+        assert!(necrology.format_entries(&data, "Test").ends_with("OBIIT P. BERNARDUS\n"));
+    }
+}
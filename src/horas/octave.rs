@@ -0,0 +1,251 @@
+//! octave.rs
+//!
+//! The octaves of the kalendar: which feasts have one under a version, and
+//! whether a date is a day within the octave of a feast or its octave day.
+//!
+//! The octaves are those of the general kalendar as *Divino Afflatu*
+//! ordered them: the privileged octaves of Easter and Pentecost (first
+//! order), of the Epiphany and Corpus Christi (second order), of Christmas,
+//! the Ascension and the Sacred Heart (third order); the common octaves of
+//! the Immaculate Conception, St Joseph, St John the Baptist, Sts Peter and
+//! Paul, the Assumption and All Saints; the simple octaves of St Stephen,
+//! St John, the Holy Innocents, St Lawrence and the Nativity of Our Lady.
+//! The Tridentine kalendar has them without those of St Joseph and of the
+//! Sacred Heart; the reform of 1955 kept only Christmas, Easter and
+//! Pentecost, and the rubrics of 1960 followed it.
+//!
+//! The octave of Pentecost ends on the Saturday: Trinity Sunday is not its
+//! octave day.
+//!
+//! ```ignore
+//! let version = RubricsVersion::parse("Divino Afflatu - 1954");
+//! let day = octave_of(9, 1, 2025, &version, CalendarSystem::Gregorian);
+//! // Some(OctaveDay { feast: "Epiphaniæ", day: 4, .. })
+//! ```
+
+use super::rank::Octave;
+use crate::date::{geteaster, CalendarSystem};
+use crate::regex::ci_contains;
+use crate::rubrics::{RubricsFamily, RubricsVersion};
+
+/// The rank of an octave, by which the octaves that run together are
+/// ordered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OctaveKind {
+    /// A privileged octave of the first order: Easter, Pentecost.
+    PrivilegedFirst,
+    /// Of the second order: the Epiphany, Corpus Christi.
+    PrivilegedSecond,
+    /// Of the third order: Christmas, the Ascension, the Sacred Heart.
+    PrivilegedThird,
+    /// A common octave, e.g. of the Assumption.
+    Common,
+    /// A simple octave, e.g. of St Stephen: only its octave day is kept.
+    Simple,
+}
+
+/// When a feast with an octave falls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeastDate {
+    /// On a fixed date, (month, day).
+    Fixed(u32, u32),
+    /// So many days after Easter.
+    Easter(i32),
+}
+
+/// A feast with an octave.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OctaveFeast {
+    /// The feast as the titles of the days of its octave name it, e.g.
+    /// "Epiphaniæ" in "Die II infra octavam Epiphaniæ".
+    pub name: &'static str,
+    /// A part of the name by which a title is recognized, whatever its
+    /// spelling ("Epiphani" for "Epiphaniæ" and "Epiphaniae").
+    pub stem: &'static str,
+    pub date: FeastDate,
+    pub kind: OctaveKind,
+    /// Whether the octave ends on the seventh day, without an octave day.
+    pub no_octave_day: bool,
+}
+
+const fn feast(name: &'static str, stem: &'static str, date: FeastDate, kind: OctaveKind) -> OctaveFeast {
+    OctaveFeast { name, stem, date, kind, no_octave_day: false }
+}
+
+/// The octaves of the general kalendar of *Divino Afflatu*.
+pub const OCTAVES: &[OctaveFeast] = &[
+    feast("Paschæ", "Pasch", FeastDate::Easter(0), OctaveKind::PrivilegedFirst),
+    OctaveFeast { no_octave_day: true, ..feast("Pentecostes", "Pentecost", FeastDate::Easter(49), OctaveKind::PrivilegedFirst) },
+    feast("Epiphaniæ", "Epiphani", FeastDate::Fixed(1, 6), OctaveKind::PrivilegedSecond),
+    feast("Corporis Christi", "Corporis Christi", FeastDate::Easter(60), OctaveKind::PrivilegedSecond),
+    feast("Nativitatis", "Nativitatis", FeastDate::Fixed(12, 25), OctaveKind::PrivilegedThird),
+    feast("Ascensionis", "Ascension", FeastDate::Easter(39), OctaveKind::PrivilegedThird),
+    feast("Sacratissimi Cordis Jesu", "Cordis", FeastDate::Easter(68), OctaveKind::PrivilegedThird),
+    feast("Immaculatæ Conceptionis B.M.V.", "Conceptionis", FeastDate::Fixed(12, 8), OctaveKind::Common),
+    feast("S. Joseph", "Joseph", FeastDate::Easter(24), OctaveKind::Common),
+    feast("S. Joannis Baptistæ", "Baptist", FeastDate::Fixed(6, 24), OctaveKind::Common),
+    feast("Ss. Petri et Pauli", "Petri et Pauli", FeastDate::Fixed(6, 29), OctaveKind::Common),
+    feast("Assumptionis B.M.V.", "Assumptionis", FeastDate::Fixed(8, 15), OctaveKind::Common),
+    feast("Omnium Sanctorum", "Omnium Sanctorum", FeastDate::Fixed(11, 1), OctaveKind::Common),
+    feast("S. Stephani", "Stephan", FeastDate::Fixed(12, 26), OctaveKind::Simple),
+    feast("S. Joannis", "Joannis Apostoli", FeastDate::Fixed(12, 27), OctaveKind::Simple),
+    feast("Ss. Innocentium", "Innocent", FeastDate::Fixed(12, 28), OctaveKind::Simple),
+    feast("S. Laurentii", "Laurent", FeastDate::Fixed(8, 10), OctaveKind::Simple),
+    feast("Nativitatis B.M.V.", "Nativitatis B", FeastDate::Fixed(9, 8), OctaveKind::Simple),
+];
+
+impl OctaveFeast {
+    /// Whether `version` keeps the octave.
+    pub fn kept(&self, version: &RubricsVersion) -> bool {
+        match version.family {
+            RubricsFamily::Reduced1955 | RubricsFamily::Rubrics1960 => {
+                matches!(self.name, "Nativitatis" | "Paschæ" | "Pentecostes")
+            }
+            RubricsFamily::Tridentine => !matches!(self.name, "S. Joseph" | "Sacratissimi Cordis Jesu"),
+            RubricsFamily::DivinoAfflatu => true,
+        }
+    }
+
+    /// Whether `title` (the feast named by the title of an office) is this
+    /// feast.
+    pub fn names(&self, title: &str) -> bool {
+        self.named_by(title).is_some()
+    }
+
+    /// How much of `title` names this feast: the length of its name or stem
+    /// found in it, the longer.
+    fn named_by(&self, title: &str) -> Option<usize> {
+        [self.name, self.stem].into_iter().filter(|key| ci_contains(title, key)).map(str::len).max()
+    }
+
+    /// The day of the octave of this feast that `month`/`day` is, from 1 for
+    /// the feast to 8 for the octave day, if it is one.
+    pub fn day_of_octave(&self, day: u32, month: u32, year: i32, calendar: CalendarSystem) -> Option<u32> {
+        let date = calendar.date_to_ydays(day, month, year) as i32;
+        let days_in = |year: i32| if calendar.leap_year(year) { 366 } else { 365 };
+        let start = |year: i32| match self.date {
+            FeastDate::Fixed(m, d) => calendar.date_to_ydays(d, m, year) as i32,
+            FeastDate::Easter(offset) => {
+                let (d, m, y) = geteaster(year, calendar);
+                calendar.date_to_ydays(d, m, y) as i32 + offset
+            }
+        };
+        // An octave begun late in the previous year runs into this one.
+        let elapsed = [date - start(year), date + days_in(year - 1) - start(year - 1)]
+            .into_iter()
+            .find(|n| (0..8).contains(n))?;
+        let n = elapsed as u32 + 1;
+        if n == 8 && self.no_octave_day {
+            return None;
+        }
+        Some(n)
+    }
+}
+
+/// A day in the octave of a feast.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OctaveDay {
+    pub feast: &'static OctaveFeast,
+    /// From 2 for the day after the feast to 8 for the octave day.
+    pub day: u32,
+}
+
+impl OctaveDay {
+    /// Whether the day is the octave day.
+    pub fn is_octave_day(&self) -> bool {
+        self.day == 8
+    }
+
+    /// The octave as the `[Rank]` line of the day would name it.
+    pub fn octave(&self) -> Octave {
+        let name = self.feast.name.to_string();
+        if self.is_octave_day() {
+            Octave::Day(name)
+        } else {
+            Octave::Within(name)
+        }
+    }
+}
+
+/// Returns the days of octaves that `month`/`day`/`year` is under `version`,
+/// the highest octave first. The feasts themselves are not in their octave,
+/// and the days within a simple octave are not kept, only its octave day.
+pub fn octaves(day: u32, month: u32, year: i32, version: &RubricsVersion, calendar: CalendarSystem) -> Vec<OctaveDay> {
+    let mut days: Vec<OctaveDay> = OCTAVES
+        .iter()
+        .filter(|feast| feast.kept(version))
+        .filter_map(|feast| {
+            let day = feast.day_of_octave(day, month, year, calendar)?;
+            let kept = day > 1 && (day == 8 || feast.kind != OctaveKind::Simple);
+            kept.then_some(OctaveDay { feast, day })
+        })
+        .collect();
+    days.sort_by_key(|d| d.feast.kind);
+    days
+}
+
+/// The highest octave `month`/`day`/`year` is in under `version`, if any.
+pub fn octave_of(day: u32, month: u32, year: i32, version: &RubricsVersion, calendar: CalendarSystem) -> Option<OctaveDay> {
+    octaves(day, month, year, version, calendar).into_iter().next()
+}
+
+/// Whether `version` keeps the octave an office names (`Rank::octave`): an
+/// office within or on the octave day of an octave suppressed, or of a feast
+/// not in the table, is not kept.
+pub fn octave_kept(octave: &Octave, version: &RubricsVersion) -> bool {
+    let (Octave::Within(name) | Octave::Day(name)) = octave;
+    feast_named(name).is_some_and(|feast| feast.kept(version))
+}
+
+/// The feast of the table that `title` names; of two that it names, as
+/// Christmas and the Nativity of Our Lady in "Nativitatis B.M.V.", the one
+/// named more fully.
+pub fn feast_named(title: &str) -> Option<&'static OctaveFeast> {
+    OCTAVES.iter().filter_map(|feast| Some((feast.named_by(title)?, feast))).max_by_key(|(len, _)| *len).map(|(_, feast)| feast)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GREGORIAN: CalendarSystem = CalendarSystem::Gregorian;
+
+    fn names(day: u32, month: u32, year: i32, version: &str) -> Vec<(&'static str, u32)> {
+        octaves(day, month, year, &RubricsVersion::parse(version), GREGORIAN).iter().map(|d| (d.feast.name, d.day)).collect()
+    }
+
+    #[test]
+    fn test_octaves_divino() {
+        let da = "Divino Afflatu - 1954";
+        assert_eq!(names(9, 1, 2025, da), [("Epiphaniæ", 4)]);
+        assert_eq!(names(13, 1, 2025, da), [("Epiphaniæ", 8)]);
+        assert_eq!(names(14, 1, 2025, da), []);
+        // Christmas, St Stephen and St John run together; only the octave
+        // day of a simple octave is kept.
+        assert_eq!(names(1, 1, 2025, da), [("Nativitatis", 8)]);
+        assert_eq!(names(2, 1, 2025, da), [("S. Stephani", 8)]);
+        assert_eq!(names(28, 12, 2024, da), [("Nativitatis", 4)]);
+        // Easter 2025 is on the 20th of April; Pentecost on the 8th of June.
+        assert_eq!(names(27, 4, 2025, da), [("Paschæ", 8)]);
+        assert_eq!(names(14, 6, 2025, da), [("Pentecostes", 7)]);
+        assert_eq!(names(15, 6, 2025, da), []);
+        assert!(octave_of(20, 4, 2025, &RubricsVersion::parse(da), GREGORIAN).is_none());
+    }
+
+    #[test]
+    fn test_octaves_1955_suppression() {
+        assert_eq!(names(9, 1, 2025, "Reduced - 1955"), []);
+        assert_eq!(names(20, 8, 2025, "Rubrics 1960 - 1960"), []);
+        assert_eq!(names(20, 8, 2025, "Divino Afflatu - 1954"), [("Assumptionis B.M.V.", 6)]);
+        assert_eq!(names(30, 12, 2024, "Rubrics 1960 - 1960"), [("Nativitatis", 6)]);
+        let day = octave_of(30, 12, 2024, &RubricsVersion::parse("Rubrics 1960 - 1960"), GREGORIAN).unwrap();
+        assert_eq!(day.octave(), Octave::Within("Nativitatis".to_string()));
+
+        let within = Octave::Within("Epiphaniæ".to_string());
+        assert!(octave_kept(&within, &RubricsVersion::parse("Divino Afflatu - 1954")));
+        assert!(!octave_kept(&within, &RubricsVersion::parse("Rubrics 1960 - 1960")));
+        assert!(octave_kept(&Octave::Day("Paschae".to_string()), &RubricsVersion::parse("Rubrics 1960 - 1960")));
+        assert_eq!(feast_named("Nativitatis B.M.V.").map(|f| f.name), Some("Nativitatis B.M.V."));
+        assert_eq!(feast_named("S. Joannis Baptistæ").map(|f| f.name), Some("S. Joannis Baptistæ"));
+    }
+}
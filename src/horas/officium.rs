@@ -0,0 +1,1108 @@
+//! officium.rs
+//!
+//! The end-to-end entry point of the Hours, as `officium.pl` is for the web
+//! site: given a date, an hour, a language and a rubrical version, it
+//! resolves the office of the day, loads the Ordinarium script of the hour
+//! and lets `specials` fill it in. `build_hour_bilingual` does the same for
+//! the two columns of the page: column 2 says the office as column 1 decides
+//! it, in the texts of its own language (`winner2_map`), and
+//! `build_hour_compare` sets the hour of two versions side by side.
+//!
+//! ```ignore
+//! let mut ctx = SetupStringContext::new("web/www/horas", "");
+//! let text = build_hour(&mut ctx, "01-25-2025", Hora::Laudes, "Latin", "Divino Afflatu")?;
+//! ```
+//!
+//! The kalendar resolution is the simple occurrence of the day in the Proper
+//! of Time and the Proper of Saints: the office with the higher rank wins and
+//! the other is commemorated unless it is a simple feria. Transferred offices
+//! (`directorium::transfer_table`) are taken into account, and so are the
+//! octaves the version keeps (`octave`): the day records its octave, and the
+//! office of a day within an octave suppressed is not said. At Vespers the
+//! office of the next day is resolved too, and their concurrence
+//! (`directorium::vespers_concurrence`) decides whose Vespers are said. The
+//! finer precedence rules of `precedence()` are not applied here.
+//!
+//! The rendered text follows the accent setting of
+//! `language_text_tools::set_accented()`, and its headings are translated
+//! from the `Translate.txt` of the language (`load_translations()`).
+//!
+//! With `ctx.votive` set to "Defunctorum" the hours are those of the Office
+//! of the Dead (`defunctorum`) instead of the office of the day, and with
+//! "Parvum BMV" those of the Little Office of Our Lady (`parvum`).
+//!
+//! `build_tenebrae` returns the Tenebrae of the Triduum, its Matins and
+//! Lauds as one service (`tenebrae`).
+//!
+//! `build_hour_elements` returns the hour as structured `OfficeElement`s,
+//! with the chants of the data folder attached.
+//!
+//! `build_hour_json` returns the hour as JSON, for a web front end (and the
+//! `wasm` bindings).
+//!
+//! The typefaces of the page are those of the `StyleSheet` of the kalendar
+//! (`with_styles()`), the default preferences unless it is given another.
+//!
+//! `load_winner` loads the file of an office as an `OfficeFile`, its
+//! `[Rank]` line parsed (`rank::Rank`), for the callers that fill an
+//! `InputConfig` themselves.
+//!
+//! A `Kalendar` with overlays layers the propers of a diocese or an order
+//! (e.g. `Sancti-Polonia/`) over the base `Sancti/` and `Tempora/` trees:
+//!
+//! ```ignore
+//! let kalendar = Kalendar::new("Divino Afflatu").with_overlay("Sancti-Polonia");
+//! let text = kalendar.build_hour(&mut ctx, "05-03-2025", Hora::Laudes, "Latin")?;
+//! ```
+
+use std::io;
+
+use super::defunctorum::{self, defunctorum, is_defunctorum};
+use super::elements::{attach_chant, parse_hour, OfficeElement};
+use super::horascommon::rankname;
+use super::parvum::{is_parvum, parvum, PARVUM_FILE};
+use super::octave::{octave_kept, octave_of, OctaveDay};
+use super::rank::Rank;
+use super::specials::{specials, specials_sections, InputConfig};
+use super::tenebrae::{tenebrae, tenebrae_day};
+use super::Hora;
+use crate::date::{get_sday, getweek, prevnext, CalendarSystem, DayNames};
+use crate::directorium::{get_kalendar, get_transfer, transfered, vespers_concurrence, Concurrence};
+use crate::fileio::data_source;
+use crate::language_text_tools::{accent_mode, apply_accents, load_translations};
+use crate::render::style::StyleSheet;
+use crate::rubrics::RubricsVersion;
+use crate::scripting::{DirectiveContext, ExpandMode, FunctionRegistry};
+use crate::setup_string::{FileSections, ResolveDirectives, SetupStringContext};
+
+/// Returns the rendered text of `hora` on `date` ("MM-DD-YYYY") in `lang`
+/// according to `version` (e.g. "Rubrics 1960 - 1960").
+///
+/// Fails with `InvalidInput` for a malformed date and with `NotFound` if the
+/// Ordinarium of the hour cannot be loaded from `ctx.datafolder`.
+pub fn build_hour(
+    ctx: &mut SetupStringContext,
+    date: &str,
+    hora: Hora,
+    lang: &str,
+    version: &str,
+) -> io::Result<String> {
+    Kalendar::new(version).build_hour(ctx, date, hora, lang)
+}
+
+/// Returns `hora` on `date` as structured elements, as `build_hour()`
+/// returns its text, with the GABC scores of `ctx.datafolder` attached to
+/// its chants.
+pub fn build_hour_elements(
+    ctx: &mut SetupStringContext,
+    date: &str,
+    hora: Hora,
+    lang: &str,
+    version: &str,
+) -> io::Result<Vec<OfficeElement>> {
+    Kalendar::new(version).build_hour_elements(ctx, date, hora, lang)
+}
+
+/// Returns the Tenebrae of `date` ("MM-DD-YYYY"), one of the last three
+/// days of Holy Week: its Matins and Lauds, which are sung on the evening
+/// before, as `tenebrae` makes them.
+///
+/// Fails with `InvalidInput` for another day.
+pub fn build_tenebrae(ctx: &mut SetupStringContext, date: &str, lang: &str, version: &str) -> io::Result<String> {
+    Kalendar::new(version).build_tenebrae(ctx, date, lang)
+}
+
+/// Returns `hora` (its Latin or English name, e.g. "Laudes" or "Lauds") as
+/// a JSON object for a web front end: `{"date", "hour", "lang", "version",
+/// "text"}`, or `{"error"}` if the hour cannot be built.
+pub fn build_hour_json(ctx: &mut SetupStringContext, date: &str, hour: &str, lang: &str, version: &str) -> String {
+    let result = hour
+        .parse::<Hora>()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))
+        .and_then(|hora| build_hour(ctx, date, hora, lang, version).map(|text| (hora, text)));
+    match result {
+        Ok((hora, text)) => format!(
+            "{{\"date\":{},\"hour\":{},\"lang\":{},\"version\":{},\"text\":{}}}",
+            json_string(date),
+            json_string(&hora.to_string()),
+            json_string(lang),
+            json_string(version),
+            json_string(&text)
+        ),
+        Err(e) => format!("{{\"error\":{}}}", json_string(&e.to_string())),
+    }
+}
+
+/// Quotes `s` as a JSON string.
+pub fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Returns `hora` in two languages, as the two columns of the web page.
+///
+/// The office of the day is resolved once and each column is rendered from
+/// the files of its own language. The result pairs the sections of the two
+/// columns (one per item of the Ordinarium), column 1 in `lang1` first; a
+/// column with fewer sections is padded with empty strings.
+pub fn build_hour_bilingual(
+    ctx: &mut SetupStringContext,
+    date: &str,
+    hora: Hora,
+    lang1: &str,
+    lang2: &str,
+    version: &str,
+) -> io::Result<Vec<(String, String)>> {
+    Kalendar::new(version).build_hour_bilingual(ctx, date, hora, lang1, lang2)
+}
+
+/// Returns `hora` in `lang` according to `version1` and to `version2`, side
+/// by side as the compare mode of the web page shows them, paired as
+/// `build_hour_bilingual()` pairs its columns. Each column is the office
+/// its own version says on `date`.
+pub fn build_hour_compare(
+    ctx: &mut SetupStringContext,
+    date: &str,
+    hora: Hora,
+    lang: &str,
+    version1: &str,
+    version2: &str,
+) -> io::Result<Vec<(String, String)>> {
+    Kalendar::new(version1).build_hour_compare(ctx, date, hora, lang, &Kalendar::new(version2))
+}
+
+/// The kalendar of a rubrical version, optionally with overlay trees of
+/// proper offices layered over the base `Sancti/` and `Tempora/` data.
+///
+/// An overlay is a directory next to the tree it extends, named after it
+/// ("Sancti-Polonia" extends "Sancti", "Tempora-Cist" extends "Tempora").
+/// Where an overlay has the file of a day, it replaces the one of the base
+/// tree, and its rank is the one used in the occurrence of the day; later
+/// overlays take precedence over earlier ones.
+///
+/// The dates of a kalendar are Gregorian unless it is given another
+/// calendar (`with_calendar()`): in the Julian calendar the feasts keep
+/// their Julian dates and the movable ones follow the Julian Easter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Kalendar {
+    version: String,
+    overlays: Vec<String>,
+    calendar: CalendarSystem,
+    styles: StyleSheet,
+    expand: ExpandMode,
+    priest: bool,
+    imperatae: Vec<String>,
+}
+
+impl Kalendar {
+    /// The kalendar of `version` (e.g. "Rubrics 1960 - 1960") without overlays.
+    pub fn new(version: &str) -> Self {
+        Kalendar {
+            version: version.to_string(),
+            overlays: Vec::new(),
+            calendar: CalendarSystem::Gregorian,
+            styles: StyleSheet::default(),
+            expand: ExpandMode::All,
+            priest: false,
+            imperatae: Vec::new(),
+        }
+    }
+
+    /// Reckons the dates given to the kalendar in `calendar`.
+    pub fn with_calendar(mut self, calendar: CalendarSystem) -> Self {
+        self.calendar = calendar;
+        self
+    }
+
+    /// Renders the hours with the typefaces of `styles`.
+    pub fn with_styles(mut self, styles: StyleSheet) -> Self {
+        self.styles = styles;
+        self
+    }
+
+    /// Writes out the psalms and the prayers of the hours whose directives
+    /// the kalendar expands itself (the votive offices) as `expand` says;
+    /// the other hours leave them to the front end (`expand_directives_with()`).
+    pub fn with_expand_mode(mut self, expand: ExpandMode) -> Self {
+        self.expand = expand;
+        self
+    }
+
+    /// Builds the hours as said by a priest ("Dominus vobiscum") if
+    /// `priest`, otherwise with "Domine, exaudi" in its place, the default.
+    pub fn with_priest(mut self, priest: bool) -> Self {
+        self.priest = priest;
+        self
+    }
+
+    /// Adds the orationes imperatae `references` ("file:section") to the
+    /// orations of the hours, on the days the version admits them.
+    pub fn with_orationes_imperatae(mut self, references: Vec<String>) -> Self {
+        self.imperatae = references;
+        self
+    }
+
+    /// Adds the overlay tree `path`, relative to the language folders of
+    /// the data (e.g. "Sancti-Polonia").
+    pub fn with_overlay(mut self, path: &str) -> Self {
+        self.overlays.push(path.trim_matches('/').to_string());
+        self
+    }
+
+    /// The rubrical version of the kalendar.
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// The calendar of the dates of the kalendar.
+    pub fn calendar(&self) -> CalendarSystem {
+        self.calendar
+    }
+
+    /// Returns the office of `date` ("MM-DD-YYYY") in this kalendar, reading
+    /// the candidate files in `lang`.
+    pub fn office(&self, ctx: &mut SetupStringContext, date: &str, lang: &str) -> io::Result<DayOffice> {
+        resolve_office(ctx, self, date, lang)
+    }
+
+    /// Returns the rendered text of `hora` on `date` ("MM-DD-YYYY") in `lang`,
+    /// as `build_hour()` does for the base kalendar.
+    pub fn build_hour(
+        &self,
+        ctx: &mut SetupStringContext,
+        date: &str,
+        hora: Hora,
+        lang: &str,
+    ) -> io::Result<String> {
+        let office = resolve_office(ctx, self, date, lang)?;
+        let fallback = ctx.langfb.clone();
+        load_translations(ctx, lang, &fallback);
+        if is_defunctorum(&ctx.votive) {
+            if !defunctorum::has_hour(hora) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("the Office of the Dead has no {}", hora),
+                ));
+            }
+            return build_votive(ctx, &office, hora, lang, defunctorum::DEFUNCTORUM_FILE, self);
+        }
+        if is_parvum(&ctx.votive) {
+            return build_votive(ctx, &office, hora, lang, PARVUM_FILE, self);
+        }
+        let (office, vespera) = self.vespers_office(ctx, office, hora, lang);
+        let script = load_ordinarium(ctx, lang, hora)?;
+        let mut config = office_config(ctx, &office, hora, lang, 1);
+        config.vespera = vespera;
+        self.configure(&mut config);
+        Ok(apply_accents(&specials(&config, ctx, script, lang, None).text(), lang, accent_mode()))
+    }
+
+    /// Returns the Tenebrae of `date`, as `build_tenebrae()` does for the
+    /// base kalendar.
+    pub fn build_tenebrae(&self, ctx: &mut SetupStringContext, date: &str, lang: &str) -> io::Result<String> {
+        let office = resolve_office(ctx, self, date, lang)?;
+        let day = tenebrae_day(&office.dayname).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("{} ({}) has no Tenebrae", date, office.dayname))
+        })?;
+        let matutinum = self.build_hour(ctx, date, Hora::Matutinum, lang)?;
+        let laudes = self.build_hour(ctx, date, Hora::Laudes, lang)?;
+        Ok(tenebrae(&matutinum, &laudes, day))
+    }
+
+    /// Returns `hora` as structured elements, as `build_hour_elements()` does
+    /// for the base kalendar.
+    pub fn build_hour_elements(
+        &self,
+        ctx: &mut SetupStringContext,
+        date: &str,
+        hora: Hora,
+        lang: &str,
+    ) -> io::Result<Vec<OfficeElement>> {
+        let mut elements = parse_hour(&self.build_hour(ctx, date, hora, lang)?);
+        attach_chant(&mut elements, &*data_source(), &ctx.datafolder);
+        Ok(elements)
+    }
+
+    /// Returns `hora` in two languages, as `build_hour_bilingual()` does for
+    /// the base kalendar.
+    pub fn build_hour_bilingual(
+        &self,
+        ctx: &mut SetupStringContext,
+        date: &str,
+        hora: Hora,
+        lang1: &str,
+        lang2: &str,
+    ) -> io::Result<Vec<(String, String)>> {
+        let office = resolve_office(ctx, self, date, lang1)?;
+        let (office, vespera) = self.vespers_office(ctx, office, hora, lang1);
+        let mut columns = Vec::with_capacity(2);
+        let fallback = ctx.langfb.clone();
+        let configs = column_configs(ctx, &office, hora, lang1, lang2);
+        for (mut config, lang) in configs.into_iter().zip([lang1, lang2]) {
+            load_translations(ctx, lang, &fallback);
+            ctx.dayname[1] = config.daynames.get(1).cloned().unwrap_or_default();
+            let script = load_ordinarium(ctx, lang, hora)?;
+            config.vespera = vespera;
+            self.configure(&mut config);
+            let sections = specials_sections(&mut config, ctx, script, lang, None);
+            columns.push(sections.iter().map(|s| apply_accents(s, lang, accent_mode())).collect());
+        }
+        Ok(pair_columns(columns))
+    }
+
+    /// Returns `hora` in `lang` in this kalendar and in `other`, as
+    /// `build_hour_compare()` does.
+    pub fn build_hour_compare(
+        &self,
+        ctx: &mut SetupStringContext,
+        date: &str,
+        hora: Hora,
+        lang: &str,
+        other: &Kalendar,
+    ) -> io::Result<Vec<(String, String)>> {
+        let fallback = ctx.langfb.clone();
+        load_translations(ctx, lang, &fallback);
+        let mut columns = Vec::with_capacity(2);
+        for (column, kalendar) in [(1, self), (2, other)] {
+            let office = resolve_office(ctx, kalendar, date, lang)?;
+            let (office, vespera) = kalendar.vespers_office(ctx, office, hora, lang);
+            let script = load_ordinarium(ctx, lang, hora)?;
+            let mut config = office_config(ctx, &office, hora, lang, column);
+            config.lang1 = lang.to_string();
+            config.compare = true;
+            config.vespera = vespera;
+            kalendar.configure(&mut config);
+            let sections = specials_sections(&mut config, ctx, script, lang, None);
+            columns.push(sections.iter().map(|s| apply_accents(s, lang, accent_mode())).collect());
+        }
+        Ok(pair_columns(columns))
+    }
+
+    /// Gives `config` the typefaces and the preferences of the kalendar.
+    fn configure(&self, config: &mut InputConfig) {
+        config.styles = self.styles.clone();
+        config.preferences.priest = self.priest;
+        config.preferences.imperatae = self.imperatae.clone();
+    }
+
+    /// Returns the office whose `hora` is said on the day of `office`, with
+    /// the `vespera` of the hours: at Vespers the office of the day (3) or
+    /// that of the next day (1), as `vespers_concurrence()` resolves them,
+    /// the other commemorated if it is; at the other hours `office` itself.
+    fn vespers_office(&self, ctx: &mut SetupStringContext, office: DayOffice, hora: Hora, lang: &str) -> (DayOffice, i32) {
+        if hora != Hora::Vespera {
+            return (office, 3);
+        }
+        // Resolving the next day moves the context to it.
+        let (version, dayofweek, dayname) = (ctx.version.clone(), ctx.dayofweek, ctx.dayname.clone());
+        let Ok(tomorrow) = resolve_office(ctx, self, &prevnext(&office.date, 1), lang) else {
+            return (office, 3);
+        };
+        let mut rank = |fname: &str| Rank::of(&ctx.setupstring(lang, fname, ResolveDirectives::All).unwrap_or_default());
+        let concurrence = vespers_concurrence(&rank(&office.winner), &rank(&tomorrow.winner), self.version());
+        match concurrence {
+            Concurrence::Today { commemoratio } => {
+                (ctx.version, ctx.dayofweek, ctx.dayname) = (version, dayofweek, dayname);
+                let mut office = office;
+                if commemoratio && office.commemoratio.is_empty() {
+                    office.commemoratio = tomorrow.winner;
+                }
+                (office, concurrence.vespera())
+            }
+            Concurrence::Tomorrow { commemoratio: false } => (tomorrow, concurrence.vespera()),
+            Concurrence::Tomorrow { commemoratio: true } | Concurrence::ACapitulo => {
+                let tomorrow = DayOffice { commemoratio: office.winner, ..tomorrow };
+                (tomorrow, concurrence.vespera())
+            }
+        }
+    }
+
+    /// Loads `file` ("Sancti/05-03.txt") from the last overlay of its tree
+    /// that has it, or else from the base tree. Returns the name of the file
+    /// actually read with its sections.
+    fn load(&self, ctx: &mut SetupStringContext, lang: &str, file: &str) -> Option<(String, FileSections)> {
+        if let Some((tree, name)) = file.split_once('/') {
+            for overlay in self.overlays.iter().rev() {
+                if overlay.split('-').next() != Some(tree) {
+                    continue;
+                }
+                let fname = format!("{}/{}", overlay, name);
+                if let Some(sections) = ctx.setupstring(lang, &fname, ResolveDirectives::All) {
+                    return Some((fname, sections));
+                }
+            }
+        }
+        ctx.setupstring(lang, file, ResolveDirectives::All).map(|sections| (file.to_string(), sections))
+    }
+}
+
+/// The office of a day as resolved from the kalendar: the files of the
+/// winner, of the commemorated office (if any) and of the commune.
+#[derive(Debug, Clone)]
+pub struct DayOffice {
+    pub date: String,
+    pub month: u32,
+    pub day: u32,
+    pub year: i32,
+    /// 0 = Sunday.
+    pub dayofweek: u32,
+    /// The day in the Proper of Time, e.g. "Epi2-2".
+    pub dayname: String,
+    pub version: String,
+    /// The file of the office of the day, e.g. "Sancti/01-21.txt".
+    pub winner: String,
+    /// The file of the commemorated office, or empty.
+    pub commemoratio: String,
+    pub commune: String,
+    /// How the commune is used: "ex" or "vide" (empty without commune).
+    pub communetype: String,
+    /// The octave the day is in, if the version keeps it.
+    pub octave: Option<OctaveDay>,
+}
+
+/// Resolves the office of `date` in `kalendar`, reading the candidate files
+/// in `lang`.
+fn resolve_office(
+    ctx: &mut SetupStringContext,
+    kalendar: &Kalendar,
+    date: &str,
+    lang: &str,
+) -> io::Result<DayOffice> {
+    let version = kalendar.version();
+    let (month, day, year) = parse_date(date)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("invalid date {}", date)))?;
+    let dayofweek = kalendar.calendar.day_of_week(day, month, year);
+    let week = getweek(day, month, year, false, false, kalendar.calendar);
+    let dayname = format!("{}-{}", week, dayofweek);
+
+    ctx.version = version.to_string();
+    ctx.dayofweek = dayofweek as u8;
+    ctx.dayname = [dayname.clone(), String::new()];
+
+    // The two candidates of the day. An office transferred to this day takes
+    // the place of the saint of the kalendar, and a saint transferred away
+    // leaves the day to the Proper of Time.
+    let tempora = format!("Tempora/{}.txt", dayname);
+    let sday = get_sday(month, day, year, kalendar.calendar);
+    let sancti = match get_transfer(year, version, &sday).filter(|t| !t.trim().is_empty()) {
+        Some(entry) => Some(entry),
+        None => match get_kalendar(version, &sday) {
+            Some(entry) if transfered(&entry, year, version).is_some() => None,
+            Some(entry) => Some(entry),
+            None => Some(sday.clone()),
+        },
+    }
+    .map(|entry| sancti_file(&entry))
+    .unwrap_or_default();
+    let tempora_office = kalendar.load(ctx, lang, &tempora);
+    // A day within an octave that the version suppressed is not kept.
+    let rubrics = RubricsVersion::parse(version);
+    let sancti_office = if sancti.is_empty() { None } else { kalendar.load(ctx, lang, &sancti) }
+        .filter(|(_, s)| Rank::of(s).octave.is_none_or(|o| octave_kept(&o, &rubrics)));
+    let octave = octave_of(day, month, year, &rubrics, kalendar.calendar);
+
+    let (winner, winner_map, commemoratio) = match (tempora_office, sancti_office) {
+        (Some((tempora, t)), Some((sancti, s))) if rank_of(&s) > rank_of(&t) => {
+            // A simple feria yielding to a feast is not commemorated.
+            let commemoratio = if rank_of(&t) > 1.0 { tempora } else { String::new() };
+            (sancti, s, commemoratio)
+        }
+        (Some((tempora, t)), Some((sancti, s))) => {
+            let commemoratio = if rank_of(&s) > 1.0 { sancti } else { String::new() };
+            (tempora, t, commemoratio)
+        }
+        (Some((tempora, t)), None) => (tempora, t, String::new()),
+        (None, Some((sancti, s))) => (sancti, s, String::new()),
+        (None, None) => (tempora, FileSections::new(), String::new()),
+    };
+    let (commune, communetype) = commune_of(&winner_map);
+
+    Ok(DayOffice {
+        date: date.to_string(),
+        month,
+        day,
+        year,
+        dayofweek,
+        dayname,
+        version: version.to_string(),
+        winner,
+        commemoratio,
+        commune,
+        communetype,
+        octave,
+    })
+}
+
+/// Returns `hora` of the votive office of `file` (the Office of the Dead or
+/// the Little Office) said on the day of `office`, as `kalendar` says it
+/// (its expansion of the psalms and prayers, by a priest or not).
+///
+/// Fails with `NotFound` if `file` cannot be loaded.
+fn build_votive(
+    ctx: &mut SetupStringContext,
+    office: &DayOffice,
+    hora: Hora,
+    lang: &str,
+    file: &str,
+    kalendar: &Kalendar,
+) -> io::Result<String> {
+    ctx.hora = hora.to_string();
+    let mut config = InputConfig { version: office.version.clone(), date1: office.date.clone(), ..InputConfig::new(hora) };
+    config.daynames = vec![office.dayname.clone()];
+    config.day = office.day;
+    config.month = office.month;
+    config.year = office.year;
+    config.dayofweek = office.dayofweek;
+    kalendar.configure(&mut config);
+    let (lines, registry) = if file == PARVUM_FILE {
+        (parvum(&config, ctx, lang), FunctionRegistry::with_builtins())
+    } else {
+        (defunctorum(&config, ctx, lang), defunctorum::registry())
+    };
+    let lines = lines
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{}/{} cannot be opened", lang, file)))?;
+    let text = registry.with_expand_mode(kalendar.expand).expand(&lines.join("\n"), &mut DirectiveContext { files: ctx, lang });
+    Ok(apply_accents(&text, lang, accent_mode()))
+}
+
+/// Loads the files of `office` in `lang` and fills an `InputConfig` for
+/// `hora` in the given column.
+pub(crate) fn office_config(
+    ctx: &mut SetupStringContext,
+    office: &DayOffice,
+    hora: Hora,
+    lang: &str,
+    column: usize,
+) -> InputConfig {
+    ctx.hora = hora.to_string();
+    let mut load = |fname: &str| {
+        if fname.is_empty() {
+            FileSections::new()
+        } else {
+            ctx.setupstring(lang, fname, ResolveDirectives::All).unwrap_or_default()
+        }
+    };
+    let commune_map = load(&office.commune);
+    let commemoratio_map = load(&office.commemoratio);
+    let winner = load_winner(ctx, lang, &office.winner)
+        .unwrap_or_else(|_| OfficeFile { fname: office.winner.clone(), ..OfficeFile::default() });
+
+    let names = daynames(ctx, office, lang);
+    ctx.dayname[1] = names.title.clone();
+    let mut config = InputConfig::new(hora);
+    config.column = column;
+    winner.configure(&mut config);
+    config.daynames = names.to_vec();
+    config.commune_rule = commune_map.get("Rule").cloned().unwrap_or_default();
+    config.commune = office.commune.clone();
+    config.commune_map = commune_map;
+    config.communetype = office.communetype.clone();
+    if !office.commemoratio.is_empty() {
+        config.commemoentries = vec![office.commemoratio.clone()];
+    }
+    config.commemoratio = office.commemoratio.clone();
+    config.commemoratio_map = commemoratio_map;
+    config.date1 = office.date.clone();
+    config.day = office.day;
+    config.month = office.month;
+    config.year = office.year;
+    config.dayofweek = office.dayofweek;
+    config.version = office.version.clone();
+    config
+}
+
+/// The `InputConfig`s of the two columns of `hora` on the day of `office`,
+/// in `lang1` and `lang2`. The office is read in both languages; column 1
+/// decides the hour (its rule and rank, `winner_map`) and column 2 says it
+/// with the texts of `lang2` (`winner2_map`), as the Perl code reads
+/// `%winner` in `$lang1` and `%winner2` in `$lang2`.
+pub(crate) fn column_configs(
+    ctx: &mut SetupStringContext,
+    office: &DayOffice,
+    hora: Hora,
+    lang1: &str,
+    lang2: &str,
+) -> [InputConfig; 2] {
+    let mut config1 = office_config(ctx, office, hora, lang1, 1);
+    let mut config2 = office_config(ctx, office, hora, lang2, 2);
+    config1.winner2_map = config2.winner2_map.clone();
+    config2.winner_map = config1.winner_map.clone();
+    config2.rule = config1.rule.clone();
+    config2.rank = config1.rank;
+    config2.duplex = config1.duplex;
+    for config in [&mut config1, &mut config2] {
+        config.lang1 = lang1.to_string();
+    }
+    [config1, config2]
+}
+
+/// Pairs the sections of two columns, the shorter padded with empty strings.
+fn pair_columns(columns: Vec<Vec<String>>) -> Vec<(String, String)> {
+    let mut columns = columns.into_iter();
+    let column1 = columns.next().unwrap_or_default();
+    let column2 = columns.next().unwrap_or_default();
+    let len = column1.len().max(column2.len());
+    let mut column1 = column1.into_iter();
+    let mut column2 = column2.into_iter();
+    (0..len)
+        .map(|_| (column1.next().unwrap_or_default(), column2.next().unwrap_or_default()))
+        .collect()
+}
+
+/// Returns the names of the day of `office`: its week, the titles of the
+/// office and of the commemoration (the first part of their `[Rank]` lines)
+/// and the description of the rank, as `rankname()` gives it from
+/// `Psalterium/Comment.txt` or else the class of the `[Rank]` line.
+pub fn daynames(ctx: &mut SetupStringContext, office: &DayOffice, lang: &str) -> DayNames {
+    let mut load = |fname: &str| {
+        if fname.is_empty() {
+            FileSections::new()
+        } else {
+            ctx.setupstring(lang, fname, ResolveDirectives::None).unwrap_or_default()
+        }
+    };
+    let winner_map = load(&office.winner);
+    let commemoratio_map = load(&office.commemoratio);
+    let field = |map: &FileSections, n: usize| {
+        map.get("Rank")
+            .and_then(|r| r.lines().next())
+            .and_then(|r| r.split(";;").nth(n))
+            .unwrap_or("")
+            .trim()
+            .to_string()
+    };
+    let rank = rankname(
+        ctx,
+        lang,
+        rank_of(&winner_map),
+        &office.winner,
+        &office.commune,
+        &office.version,
+        office.day,
+        office.month,
+        office.year,
+        office.dayofweek as u8,
+        "Laudes",
+    );
+    DayNames {
+        week: office.dayname.rsplit_once('-').map_or(office.dayname.as_str(), |(w, _)| w).to_string(),
+        dayofweek: office.dayofweek,
+        title: field(&winner_map, 0),
+        rank: if rank.trim().is_empty() { field(&winner_map, 1) } else { rank.trim().to_string() },
+        commemoratio: field(&commemoratio_map, 0),
+    }
+}
+
+/// The file of the first office of a kalendar or transfer entry
+/// ("01-21~01-21c" gives "Sancti/01-21.txt").
+fn sancti_file(entry: &str) -> String {
+    let name = entry.split('~').next().unwrap_or(entry).trim().trim_end_matches(".txt");
+    if name.contains('/') {
+        format!("{}.txt", name)
+    } else {
+        format!("Sancti/{}.txt", name)
+    }
+}
+
+/// Parses "MM-DD-YYYY" into `(month, day, year)`.
+pub(crate) fn parse_date(date: &str) -> Option<(u32, u32, i32)> {
+    let mut parts = date.trim().split('-');
+    let month: u32 = parts.next()?.parse().ok()?;
+    let day: u32 = parts.next()?.parse().ok()?;
+    let year: i32 = parts.next()?.parse().ok()?;
+    if parts.next().is_some() || !(1..=12).contains(&month) || day == 0 || day > 31 {
+        return None;
+    }
+    Some((month, day, year))
+}
+
+/// A file of an office loaded for the hours: its parsed `[Rank]`, its
+/// `[Rule]` and all its sections.
+#[derive(Debug, Clone, Default)]
+pub struct OfficeFile {
+    /// The file, e.g. "Sancti/01-25.txt".
+    pub fname: String,
+    pub rank: Rank,
+    pub rule: String,
+    pub sections: FileSections,
+}
+
+impl OfficeFile {
+    /// The file of the commune of the office and how it is used, from the
+    /// `[Rank]` line.
+    pub fn commune(&self) -> Option<(String, String)> {
+        self.rank.commune_file()
+    }
+
+    /// Loads the commune of the office, if it has one and it can be loaded.
+    pub fn load_commune(&self, ctx: &mut SetupStringContext, lang: &str) -> Option<OfficeFile> {
+        let (fname, _) = self.commune()?;
+        load_winner(ctx, lang, &fname).ok()
+    }
+
+    /// Makes the office the winner of `config`.
+    pub fn configure(&self, config: &mut InputConfig) {
+        config.winner = self.fname.clone();
+        config.rule = self.rule.clone();
+        config.winner_map = self.sections.clone();
+        config.winner2_map = self.sections.clone();
+        config.rank = self.rank.precedence;
+        config.duplex = self.rank.duplex();
+    }
+}
+
+/// Loads the office file `fname` (a winner as the kalendar resolves it, e.g.
+/// "Sancti/01-25.txt", or a commune) in `lang` with all its inclusions.
+///
+/// Fails with `NotFound` if the file cannot be loaded.
+pub fn load_winner(ctx: &mut SetupStringContext, lang: &str, fname: &str) -> io::Result<OfficeFile> {
+    let sections = ctx
+        .setupstring(lang, fname, ResolveDirectives::All)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{}/{} cannot be opened", lang, fname)))?;
+    Ok(OfficeFile {
+        fname: fname.to_string(),
+        rank: Rank::of(&sections),
+        rule: sections.get("Rule").cloned().unwrap_or_default(),
+        sections,
+    })
+}
+
+/// The rank number of an office: the third field of its
+/// `[Rank]` line ("title;;class;;rank;;commune").
+fn rank_of(office: &FileSections) -> f64 {
+    Rank::of(office).precedence
+}
+
+/// The commune named in the fourth field of the `[Rank]` line, as
+/// `("Commune/C4a.txt", "ex")` or `("Commune/C11.txt", "vide")`.
+fn commune_of(office: &FileSections) -> (String, String) {
+    Rank::of(office).commune_file().unwrap_or_default()
+}
+
+/// Loads the script of the hour from `Ordinarium/`, with the `#Prelude`
+/// pseudo-item in front as `getordinarium()` does.
+fn load_ordinarium(ctx: &mut SetupStringContext, lang: &str, hora: Hora) -> io::Result<Vec<String>> {
+    let name = if hora.is_minor() { "Minor" } else { hora.as_str() };
+    let fname = format!("Ordinarium/{}.txt", name);
+    let path = ctx.datafolder.join(lang).join(&fname);
+    let not_found = || {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} cannot be opened or gives an empty script", path.display()),
+        )
+    };
+    let sections = ctx.setupstring(lang, &fname, ResolveDirectives::None);
+    let text = sections
+        .as_ref()
+        .and_then(|s| s.get("__preamble"))
+        .filter(|t| !t.trim().is_empty())
+        .ok_or_else(not_found)?;
+    let mut script = vec!["#Prelude".to_string(), String::new()];
+    script.extend(text.lines().map(|l| l.to_string()));
+    Ok(script)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempRoot;
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("01-25-2025"), Some((1, 25, 2025)));
+        assert_eq!(parse_date("13-01-2025"), None);
+        assert_eq!(parse_date("2025-01-25"), None);
+    }
+
+    #[test]
+    fn test_commune_of() {
+        let mut office = FileSections::new();
+        office.insert("Rank".to_string(), "S. Agnetis Virginis;;Duplex;;3;;vide C6".to_string());
+        assert_eq!(commune_of(&office), ("Commune/C6.txt".to_string(), "vide".to_string()));
+        office.insert("Rank".to_string(), "Feria;;Feria;;1".to_string());
+        assert_eq!(commune_of(&office), (String::new(), String::new()));
+    }
+
+    #[test]
+    fn test_load_winner() {
+        let root = TempRoot::new("officium-winner");
+        root.write("Latin/Sancti/01-25.txt", "[Rank]\nIn Conversione S. Pauli Apostoli;;Duplex majus;;4;;ex C1\n\n[Rule]\nvide C1;\n\n[Oratio]\nDeus, qui univérsum mundum.\n");
+        root.write("Latin/Commune/C1.txt", "[Rank]\nCommune Apostolorum;;Duplex;;3\n\n[Capitulum Laudes]\nJam non estis hóspites.\n");
+        let mut ctx = SetupStringContext::new(&*root, "");
+        let winner = load_winner(&mut ctx, "Latin", "Sancti/01-25.txt").unwrap();
+        assert_eq!(winner.rank.precedence, 4.0);
+        assert_eq!(winner.rank.title, "In Conversione S. Pauli Apostoli");
+        assert_eq!(winner.rule.trim(), "vide C1;");
+        assert!(winner.sections["Oratio"].starts_with("Deus, qui"));
+        let commune = winner.load_commune(&mut ctx, "Latin").unwrap();
+        assert_eq!(commune.fname, "Commune/C1.txt");
+        assert!(commune.sections.contains_key("Capitulum Laudes"));
+
+        let mut config = InputConfig::new(Hora::Laudes);
+        winner.configure(&mut config);
+        assert_eq!((config.winner.as_str(), config.rank, config.duplex), ("Sancti/01-25.txt", 4.0, 3));
+        assert_eq!(config.winner_map.get("Oratio"), winner.sections.get("Oratio"));
+        assert_eq!(load_winner(&mut ctx, "Latin", "Sancti/02-30.txt").unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_build_hour_saint_wins_over_feria() {
+        let root = TempRoot::new("officium-saint");
+        // 2025-01-21 is a Tuesday of the second week after Epiphany.
+        root.write("Latin/Tempora/Epi2-2.txt", "[Rank]\nFeria Tertia;;Feria;;1\n\n[Oratio]\nOratio feriae.\n");
+        root.write(
+            "Latin/Sancti/01-21.txt",
+            "[Rank]\nS. Agnetis Virginis et Martyris;;Duplex;;3;;vide C6\n\n[Oratio]\nOmnípotens sempitérne Deus.\n$Per Dominum\n",
+        );
+        root.write("Latin/Commune/C6.txt", "[Rule]\nPsalmi Dominica\n");
+        root.write("Latin/Ordinarium/Laudes.txt", "#Incipit\nDeus in adjutorium\n\n#Oratio\n");
+        let mut ctx = SetupStringContext::new(&*root, "");
+
+        let text = build_hour(&mut ctx, "01-21-2025", Hora::Laudes, "Latin", "Divino Afflatu").unwrap();
+        assert!(text.contains("Deus in adjutorium"));
+        assert!(text.contains("$Domine exaudi\n$Oremus\nOmnípotens sempitérne Deus.\n$Per Dominum"));
+        assert!(!text.contains("Oratio feriae"));
+        let priest = Kalendar::new("Divino Afflatu").with_priest(true);
+        let text = priest.build_hour(&mut ctx, "01-21-2025", Hora::Laudes, "Latin").unwrap();
+        assert!(text.contains("$Dominus vobiscum\n$Oremus\nOmnípotens sempitérne Deus."));
+        root.write("Latin/Commune/C10.txt", "[Oratio]\nDeus, refúgium nostrum et virtus.\n");
+        let imperata = Kalendar::new("Divino Afflatu").with_orationes_imperatae(vec!["Commune/C10:Oratio".to_string()]);
+        let text = imperata.build_hour(&mut ctx, "01-21-2025", Hora::Laudes, "Latin").unwrap();
+        assert!(text.contains("Omnípotens sempitérne Deus.\n_\nDeus, refúgium nostrum et virtus.\n$Per Dominum"));
+        assert_eq!(ctx.dayname[0], "Epi2-2");
+    }
+
+    #[test]
+    fn test_build_hour_bilingual_pairs_sections() {
+        let root = TempRoot::new("officium-bilingual");
+        root.write("Latin/Tempora/Epi2-2.txt", "[Rank]\nFeria Tertia;;Feria;;1\n\n[Oratio]\nOratio feriae.\n");
+        root.write("English/Tempora/Epi2-2.txt", "[Rank]\nTuesday;;Feria;;1\n\n[Oratio]\nPrayer of the feria.\n");
+        root.write("Latin/Ordinarium/Vespera.txt", "#Incipit\nDeus in adjutorium\n\n#Oratio\n");
+        root.write("English/Ordinarium/Vespera.txt", "#Incipit\nO God, come to my assistance\n\n#Oratio\n");
+        let mut ctx = SetupStringContext::new(&*root, "");
+
+        let sections =
+            build_hour_bilingual(&mut ctx, "01-21-2025", Hora::Vespera, "Latin", "English", "Divino Afflatu").unwrap();
+        let oratio = sections.iter().find(|(l, _)| l.contains("Oratio feriae")).unwrap();
+        assert!(oratio.1.contains("Prayer of the feria"));
+        let incipit = sections.iter().find(|(l, _)| l.contains("Deus in adjutorium")).unwrap();
+        assert!(incipit.1.contains("O God, come to my assistance"));
+    }
+
+    #[test]
+    fn test_column_configs() {
+        let root = TempRoot::new("officium-columns");
+        root.write("Latin/Sancti/01-21.txt", "[Rank]\nS. Agnetis;;Duplex;;3\n\n[Rule]\nPsalmi Dominica\n\n[Oratio]\nOmnípotens sempitérne Deus.\n");
+        root.write("English/Sancti/01-21.txt", "[Rank]\nSt. Agnes;;Simplex;;1.1\n\n[Rule]\n\n[Oratio]\nAlmighty, everlasting God.\n");
+        let mut ctx = SetupStringContext::new(&*root, "");
+        let office = Kalendar::new("Divino Afflatu").office(&mut ctx, "01-21-2025", "Latin").unwrap();
+        let [config1, config2] = column_configs(&mut ctx, &office, Hora::Laudes, "Latin", "English");
+        assert_eq!(config1.winners()["Oratio"].trim(), "Omnípotens sempitérne Deus.");
+        assert_eq!(config2.winners()["Oratio"].trim(), "Almighty, everlasting God.");
+        assert_eq!(config1.winner2_map, config2.winner2_map);
+        // Column 2 keeps the rule and the rank of column 1.
+        assert_eq!((config2.rule.trim(), config2.rank, config2.duplex), ("Psalmi Dominica", 3.0, 3));
+        assert!(config1.columnsel("latin") && !config2.columnsel("English"));
+    }
+
+    #[test]
+    fn test_build_hour_compare() {
+        let root = TempRoot::new("officium-compare");
+        root.write("Latin/Tempora/Epi2-2.txt", "[Rank]\nFeria Tertia;;Feria;;1\n\n[Oratio]\nOratio feriae.\n\n[Oratio] (rubrica 1960)\nOratio anni 1960.\n");
+        root.write("Latin/Ordinarium/Laudes.txt", "#Incipit\nDeus in adjutorium\n\n#Oratio\n");
+        let mut ctx = SetupStringContext::new(&*root, "");
+        let sections =
+            build_hour_compare(&mut ctx, "01-21-2025", Hora::Laudes, "Latin", "Divino Afflatu", "Rubrics 1960 - 1960").unwrap();
+        let (left, right) = sections.iter().find(|(l, _)| l.contains("#Oratio")).unwrap();
+        assert!(left.contains("Oratio feriae.") && !left.contains("1960"));
+        assert!(right.contains("Oratio anni 1960.") && !right.contains("feriae"));
+        assert_eq!(sections[0].0, sections[0].1);
+    }
+
+    #[test]
+    fn test_build_hour_cistercian_vespers() {
+        let root = TempRoot::new("officium-cistercian");
+        root.write("Latin/Tempora/Epi2-2.txt", "[Rank]\nFeria Tertia;;Feria;;1\n\n[Oratio]\nOratio feriae.\n");
+        root.write(
+            "Latin/Psalterium/Psalmi/Psalmi major.txt",
+            "[Daym2 Vespera]\nAntiphona monastica.;;129\n\n[Dayc2 Vespera]\nInclinávit Dóminus * aurem suam mihi.;;114\nDómine, líbera * ánimam meam.;;115\n",
+        );
+        root.write("Latin/Ordinarium/Vespera.txt", "#Incipit\nDeus in adjutorium\n\n#Psalmi\n\n#Oratio\n");
+        let mut ctx = SetupStringContext::new(&*root, "");
+
+        let text =
+            build_hour(&mut ctx, "01-21-2025", Hora::Vespera, "Latin", "Monastic Tridentinum Cisterciensis 1951").unwrap();
+        // The Cistercian table of the psalter, with only the incipit of the
+        // antiphon before the psalm.
+        assert!(!text.contains("Antiphona monastica"));
+        assert!(text.contains("Ant. Inclinávit Dóminus "));
+        assert!(!text.contains("Ant. Inclinávit Dóminus * aurem"));
+        assert!(text.contains("&psalm(114)\nAnt. Inclinávit Dóminus aurem suam mihi."));
+        assert!(text.contains("&psalm(115)"));
+        assert!(text.contains("$Oremus\nOratio feriae."));
+    }
+
+    #[test]
+    fn test_liturgical_daynames() {
+        let root = TempRoot::new("officium-daynames");
+        root.write("Latin/Tempora/Epi2-2.txt", "[Rank]\nFeria Tertia;;Feria;;1\n");
+        root.write("Latin/Sancti/01-21.txt", "[Rank]\nS. Agnetis Virginis et Martyris;;Duplex;;3;;vide C6\n");
+        let mut ctx = SetupStringContext::new(&*root, "");
+
+        let names = crate::date::liturgical_daynames(&mut ctx, "01-21-2025", "Divino Afflatu").unwrap();
+        assert_eq!(names.week, "Epi2");
+        assert_eq!(names.day(), "Epi2-2");
+        assert_eq!(names.title, "S. Agnetis Virginis et Martyris");
+        assert_eq!(names.rank, "Duplex");
+        assert_eq!(names.to_vec(), ["Epi2-2", "S. Agnetis Virginis et Martyris", ""]);
+    }
+
+    #[test]
+    fn test_build_hour_votive_offices() {
+        let root = TempRoot::new("officium-votive");
+        root.write("Latin/Commune/C12.txt", "[Ant Tertia]\nMaría Virgo assúmpta est.\n\n[Oratio]\nConcéde nos fámulos tuos.\n");
+        root.write("Latin/Psalterium/Psalmorum/Psalm119.txt", "119:1 Ad Dóminum cum tribulárer clamávi.\n");
+        root.write("Latin/Psalterium/Common/Prayers.txt", "[Gloria]\nGlória Patri.\n");
+        let mut ctx = SetupStringContext::new(&*root, "");
+        ctx.votive = "Parvum BMV".to_string();
+
+        let text = build_hour(&mut ctx, "08-12-2025", Hora::Tertia, "Latin", "Divino Afflatu").unwrap();
+        assert!(text.contains("Ant. María Virgo assúmpta est.\n!Psalmus 119\n119:1 Ad Dóminum cum tribulárer clamávi.\nGlória Patri."));
+        assert!(text.contains("Concéde nos fámulos tuos."));
+        let skeleton = Kalendar::new("Divino Afflatu").with_expand_mode(ExpandMode::Skeleton);
+        let text = skeleton.build_hour(&mut ctx, "08-12-2025", Hora::Tertia, "Latin").unwrap();
+        assert!(text.contains("Ant. María Virgo assúmpta est.\n!Psalmus 119\n"));
+        assert!(!text.contains("Ad Dóminum"));
+
+        ctx.votive = "Defunctorum".to_string();
+        let err = build_hour(&mut ctx, "08-12-2025", Hora::Tertia, "Latin", "Divino Afflatu").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let err = build_hour(&mut ctx, "08-12-2025", Hora::Laudes, "Latin", "Divino Afflatu").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_kalendar_overlay_rank() {
+        let root = TempRoot::new("officium-overlay");
+        // 2025-05-03 is a Saturday: Ss. Philip and James in the base kalendar,
+        // Our Lady Queen of Poland in the Polish propers.
+        root.write("Latin/Tempora/Pasc2-6.txt", "[Rank]\nSabbato;;Feria;;1\n\n[Oratio]\nOratio feriae.\n");
+        root.write(
+            "Latin/Sancti/05-03.txt",
+            "[Rank]\nSs. Philippi et Jacobi;;Duplex II. classis;;5\n\n[Oratio]\nDeus, qui nos ánnua Apostolórum.\n",
+        );
+        root.write(
+            "Latin/Sancti-Polonia/05-03.txt",
+            "[Rank]\nB. M. V. Reginæ Poloniæ;;Duplex I. classis;;6\n\n[Oratio]\nDeus, qui ad defensiónem.\n",
+        );
+        root.write("Latin/Ordinarium/Laudes.txt", "#Oratio\n");
+        let mut ctx = SetupStringContext::new(&*root, "");
+
+        let base = Kalendar::new("Divino Afflatu");
+        let office = resolve_office(&mut ctx, &base, "05-03-2025", "Latin").unwrap();
+        assert_eq!(office.winner, "Sancti/05-03.txt");
+
+        let polonia = base.with_overlay("Sancti-Polonia");
+        let office = resolve_office(&mut ctx, &polonia, "05-03-2025", "Latin").unwrap();
+        assert_eq!(office.winner, "Sancti-Polonia/05-03.txt");
+        let text = polonia.build_hour(&mut ctx, "05-03-2025", Hora::Laudes, "Latin").unwrap();
+        assert!(text.contains("Deus, qui ad defensiónem."));
+        assert!(!text.contains("Apostolórum"));
+
+        // A day the overlay does not define keeps the base office.
+        let office = resolve_office(&mut ctx, &polonia, "05-02-2025", "Latin").unwrap();
+        assert!(!office.winner.starts_with("Sancti-Polonia"));
+    }
+
+    #[test]
+    fn test_resolve_office_octaves() {
+        let root = TempRoot::new("officium-octave");
+        root.write(
+            "Latin/Sancti/01-09.txt",
+            "[Rank]\nDie IV infra octavam Epiphaniæ;;Semiduplex;;5.6;;ex Sancti/01-06\n\n[Oratio]\nDeus, cujus Unigénitus.\n",
+        );
+        let mut ctx = SetupStringContext::new(&*root, "");
+        let office = resolve_office(&mut ctx, &Kalendar::new("Divino Afflatu"), "01-09-2025", "Latin").unwrap();
+        assert_eq!(office.winner, "Sancti/01-09.txt");
+        assert_eq!(office.octave.map(|o| (o.feast.name, o.day)), Some(("Epiphaniæ", 4)));
+
+        // The octave of the Epiphany was suppressed in 1955.
+        let office = resolve_office(&mut ctx, &Kalendar::new("Rubrics 1960"), "01-09-2025", "Latin").unwrap();
+        assert!(office.winner.starts_with("Tempora/"));
+        assert!(office.octave.is_none());
+    }
+
+    #[test]
+    fn test_build_hour_vespers_concurrence() {
+        let root = TempRoot::new("officium-concurrence");
+        // 2025-01-20 is a Monday, the eve of St Agnes.
+        root.write("Latin/Tempora/Epi2-1.txt", "[Rank]\nFeria Secunda;;Feria;;1\n");
+        root.write(
+            "Latin/Sancti/01-21.txt",
+            "[Rank]\nS. Agnetis Virginis et Martyris;;Duplex;;3\n\n[Special Vespera 1]\nPrimae Vesperae.\n\n[Special Vespera 3]\nSecundae Vesperae.\n",
+        );
+        root.write("Latin/Ordinarium/Vespera.txt", "#Incipit\nDeus in adjutorium\n");
+        let mut ctx = SetupStringContext::new(&*root, "");
+        let kalendar = Kalendar::new("Divino Afflatu");
+        let text = kalendar.build_hour(&mut ctx, "01-20-2025", Hora::Vespera, "Latin").unwrap();
+        assert!(text.contains("Primae Vesperae."));
+        let text = kalendar.build_hour(&mut ctx, "01-21-2025", Hora::Vespera, "Latin").unwrap();
+        assert!(text.contains("Secundae Vesperae."));
+        assert_eq!(ctx.dayname[0], "Epi2-2");
+    }
+
+    #[test]
+    fn test_kalendar_julian_calendar() {
+        let root = TempRoot::new("officium-julian");
+        root.write("Latin/Tempora/Pasc0-0.txt", "[Rank]\nDominica Resurrectionis;;Duplex I. classis;;7\n");
+        let mut ctx = SetupStringContext::new(&*root, "");
+
+        // Easter 2025 is 20 April, 7 April in the Julian calendar; Gregorian
+        // 7 April is the Monday of Passion Week.
+        let julian = Kalendar::new("Divino Afflatu").with_calendar(CalendarSystem::Julian);
+        let office = julian.office(&mut ctx, "04-07-2025", "Latin").unwrap();
+        assert_eq!((office.dayname.as_str(), office.dayofweek), ("Pasc0-0", 0));
+        assert_eq!(office.winner, "Tempora/Pasc0-0.txt");
+        let office = Kalendar::new("Divino Afflatu").office(&mut ctx, "04-07-2025", "Latin").unwrap();
+        assert_eq!((office.dayname.as_str(), office.dayofweek), ("Quad5-1", 1));
+    }
+
+    #[test]
+    fn test_build_hour_json() {
+        let root = TempRoot::new("officium-json");
+        root.write("Latin/Tempora/Epi2-2.txt", "[Rank]\nFeria Tertia;;Feria;;1\n");
+        root.write("Latin/Ordinarium/Laudes.txt", "#Incipit\nDeus in \"adjutorium\"\n");
+        let mut ctx = SetupStringContext::new(&*root, "");
+        let json = build_hour_json(&mut ctx, "01-21-2025", "Lauds", "Latin", "Divino Afflatu");
+        assert!(json.starts_with("{\"date\":\"01-21-2025\",\"hour\":\"Laudes\",\"lang\":\"Latin\""));
+        assert!(json.contains("Deus in \\\"adjutorium\\\""));
+        assert!(!json.contains('\n'));
+        let json = build_hour_json(&mut ctx, "01-21-2025", "Brunch", "Latin", "Divino Afflatu");
+        assert!(json.starts_with("{\"error\":"));
+    }
+
+    #[test]
+    fn test_build_hour_errors() {
+        let root = TempRoot::new("officium-errors");
+        let mut ctx = SetupStringContext::new(&*root, "");
+        let err = build_hour(&mut ctx, "not a date", Hora::Laudes, "Latin", "Rubrics 1960 - 1960").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let err = build_tenebrae(&mut ctx, "04-16-2025", "Latin", "Rubrics 1960 - 1960").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let err = build_hour(&mut ctx, "01-21-2025", Hora::Sexta, "Latin", "Rubrics 1960 - 1960").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,262 @@
+//! parvum.rs
+//!
+//! The Little Office of the Blessed Virgin Mary (*Officium Parvum B.M.V.*),
+//! said as a votive cursus with `votive = "Parvum BMV"` (the Perl site names
+//! it after its file, `C12`).
+//!
+//! It is simpler than the Divine Office: its psalms do not follow the
+//! psalter of the week (Matins has three psalms in the three groups of days
+//! below, the other hours always the same ones) and its texts change only
+//! with three seasons: Advent, Christmastide (from Christmas to the
+//! Purification) and the rest of the year. Matins has a single nocturn of
+//! three lessons.
+//!
+//! The texts come from `Commune/C12.txt`: `Invit`, `Hymnus {hora}`, `Ant
+//! {hora}` (one antiphon per psalm at Lauds and Vespers and for the three
+//! psalms of Matins, a single one at the other hours), `Capitulum {hora}`,
+//! `Versum {hora}`, `Ant Benedictus`, `Ant Magnificat`, `LectioN`,
+//! `ResponsoryN` and `Oratio`. A section of Advent or of Christmastide is the
+//! same name with " Adv" or " Nat" (`Ant Laudes Adv`) and replaces the one of
+//! the year when the file has it.
+
+use super::specials::{dominus_vobiscum, rubric_line, translate, InputConfig};
+use super::Hora;
+use crate::regex::contains_ci;
+use crate::setup_string::{FileSections, ResolveDirectives, SetupStringProvider};
+
+/// The file of the Little Office.
+pub const PARVUM_FILE: &str = "Commune/C12.txt";
+
+/// Whether `votive` selects the Little Office ("Parvum BMV", or the name of
+/// its file, "C12").
+pub fn is_parvum(votive: &str) -> bool {
+    let votive = votive.trim().trim_end_matches(".txt");
+    contains_ci(votive, "Parvum") || votive.rsplit('/').next() == Some("C12")
+}
+
+/// The seasons of the Little Office.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParvumSeason {
+    Adventus,
+    /// From Christmas to the Purification (2 February).
+    Nativitas,
+    PerAnnum,
+}
+
+impl ParvumSeason {
+    /// The season of the day of `config`.
+    pub fn of(config: &InputConfig) -> Self {
+        let dayname = config.daynames.first().map(String::as_str).unwrap_or("");
+        if dayname.starts_with("Adv") {
+            ParvumSeason::Adventus
+        } else if (config.month == 12 && config.day >= 25) || config.month == 1 || (config.month == 2 && config.day < 2) {
+            ParvumSeason::Nativitas
+        } else {
+            ParvumSeason::PerAnnum
+        }
+    }
+
+    /// The suffix of the sections of the season, empty outside Advent and
+    /// Christmastide.
+    pub fn suffix(self) -> &'static str {
+        match self {
+            ParvumSeason::Adventus => "Adv",
+            ParvumSeason::Nativitas => "Nat",
+            ParvumSeason::PerAnnum => "",
+        }
+    }
+}
+
+/// The psalms of `hora` of the Little Office on `dayofweek` (0 = Sunday).
+/// Lauds and Vespers are the Marian psalms of the feasts of Our Lady (210 is
+/// the *Benedicite*), Matins changes with the day.
+pub fn psalmi(hora: Hora, dayofweek: u32) -> &'static [u32] {
+    match hora {
+        Hora::Matutinum => match dayofweek {
+            2 | 5 => &[44, 45, 86],
+            3 | 6 => &[95, 96, 97],
+            _ => &[8, 18, 23],
+        },
+        Hora::Laudes => &[92, 99, 62, 210, 148],
+        Hora::Prima => &[53, 84, 116],
+        Hora::Tertia => &[119, 120, 121],
+        Hora::Sexta => &[122, 123, 124],
+        Hora::Nona => &[125, 126, 127],
+        Hora::Vespera => &[109, 112, 121, 126, 147],
+        Hora::Completorium => &[128, 129, 130],
+    }
+}
+
+/// Returns the lines of `config.hora` of the Little Office, or `None` if
+/// `Commune/C12.txt` is missing.
+pub fn parvum(config: &InputConfig, files: &mut dyn SetupStringProvider, lang: &str) -> Option<Vec<String>> {
+    let office = files.setupstring(lang, PARVUM_FILE, ResolveDirectives::All)?;
+    let season = ParvumSeason::of(config);
+    let hora = config.hora;
+    let get = |name: &str| section(&office, name, season);
+    config.build.setbuild(PARVUM_FILE, &format!("Ant {}", hora), "Parvum BMV");
+
+    let mut lines = vec![
+        format!("#{}", translate("Incipit", lang)),
+        rubric_line("secreto", lang, &config.rubrics()),
+        "$Ave Maria".to_string(),
+    ];
+    if hora == Hora::Matutinum {
+        lines.push("$Domine labia".to_string());
+    } else if hora == Hora::Completorium {
+        lines.push("$Converte nos".to_string());
+    }
+    lines.push("$Deus in adjutorium".to_string());
+
+    if hora == Hora::Matutinum {
+        if let Some(invit) = get("Invit") {
+            lines.push(format!("#{}", translate("Invitatorium", lang)));
+            lines.extend(antiphoned(&invit, 94));
+        }
+    }
+    // The hymn comes after the psalms at Lauds and Vespers, before them at
+    // the other hours.
+    let hymnus = get(&format!("Hymnus {}", hora));
+    if !hora.is_major() {
+        lines.push(format!("#{}", translate("Hymnus", lang)));
+        lines.extend(hymnus.clone());
+    }
+
+    lines.push(format!("#{}", translate("Psalmi", lang)));
+    let antiphons: Vec<String> = get(&format!("Ant {}", hora))
+        .map(|a| a.lines().map(|l| l.trim().to_string()).filter(|l| !l.is_empty()).collect())
+        .unwrap_or_default();
+    let psalms = psalmi(hora, config.dayofweek);
+    if antiphons.len() >= psalms.len() {
+        for (ant, psalm) in antiphons.iter().zip(psalms) {
+            lines.extend(antiphoned(ant, *psalm));
+        }
+    } else {
+        // A single antiphon for the whole psalmody.
+        let ant = antiphons.first().map(|a| format!("Ant. {}", a));
+        lines.extend(ant.clone());
+        lines.extend(psalms.iter().map(|psalm| format!("&psalm({})", psalm)));
+        lines.extend(ant);
+    }
+
+    if hora == Hora::Matutinum {
+        lines.extend(get("Versum Matutinum"));
+        lines.push("$Pater noster Et".to_string());
+        for n in 1..=3 {
+            let Some(lectio) = get(&format!("Lectio{}", n)) else { continue };
+            lines.push(format!("!{} {}", translate("Lectio", lang), n));
+            lines.push(lectio);
+            lines.push("$Tu autem".to_string());
+            lines.extend(get(&format!("Responsory{}", n)));
+        }
+        lines.push("$Te Deum".to_string());
+    } else {
+        if let Some(capitulum) = get(&format!("Capitulum {}", hora)) {
+            lines.push(format!("#{}", translate("Capitulum", lang)));
+            lines.push(capitulum);
+            lines.push("$Deo gratias".to_string());
+        }
+        if hora.is_major() {
+            lines.push(format!("#{}", translate("Hymnus", lang)));
+            lines.extend(hymnus);
+        }
+        lines.extend(get(&format!("Versum {}", hora)));
+        let canticle = match hora {
+            Hora::Laudes => Some(("Benedictus", 231)),
+            Hora::Vespera => Some(("Magnificat", 232)),
+            Hora::Completorium => Some(("Nunc dimittis", 233)),
+            _ => None,
+        };
+        if let Some((name, psalm)) = canticle {
+            lines.push(format!("#{}", translate(&format!("Canticum: {}", name), lang)));
+            match get(&format!("Ant {}", name)) {
+                Some(ant) => lines.extend(antiphoned(&ant, psalm)),
+                None => lines.push(format!("&psalm({})", psalm)),
+            }
+        }
+    }
+
+    lines.push(format!("#{}", translate("Oratio", lang)));
+    lines.push("$Kyrie".to_string());
+    lines.push(dominus_vobiscum(config).to_string());
+    lines.push("$Oremus".to_string());
+    lines.extend(get("Oratio"));
+    lines.push(dominus_vobiscum(config).to_string());
+    lines.push("$Benedicamus Domino".to_string());
+    lines.push("$Fidelium animae".to_string());
+    Some(lines)
+}
+
+/// A psalm between its antiphon.
+fn antiphoned(ant: &str, psalm: u32) -> Vec<String> {
+    vec![format!("Ant. {}", ant), format!("&psalm({})", psalm), format!("Ant. {}", ant)]
+}
+
+/// The section `name` of the office in `season` (its seasonal section if
+/// the file has one), trimmed, if it is not empty.
+fn section(office: &FileSections, name: &str, season: ParvumSeason) -> Option<String> {
+    let seasonal = match season.suffix() {
+        "" => None,
+        suffix => office.get(&format!("{} {}", name, suffix)),
+    };
+    seasonal.or_else(|| office.get(name)).map(|t| t.trim().to_string()).filter(|t| !t.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::horas::specials::tests::TestFiles;
+
+    fn files() -> TestFiles {
+        TestFiles::default().with("Latin", PARVUM_FILE, &[
+            ("Hymnus Laudes", "O gloriósa Vírginum,"),
+            ("Hymnus Tertia", "Meménto, rerum Cónditor,"),
+            ("Ant Laudes", "Assúmpta est María.\nMaria Virgo assúmpta est.\nIn odórem.\nBenedícta fília.\nPulchra es."),
+            ("Ant Laudes Adv", "Missus est Gábriel.\nAve, María.\nNe tímeas, María.\nDabit ei Dóminus.\nEcce ancílla Dómini."),
+            ("Ant Tertia", "María Virgo assúmpta est."),
+            ("Ant Benedictus", "Beáta Dei Génitrix."),
+            ("Oratio", "Concéde nos fámulos tuos."),
+            ("Oratio Nat", "Deus, qui salútis ætérnæ."),
+        ])
+    }
+
+    #[test]
+    fn test_parvum_seasons() {
+        assert!(is_parvum("Parvum BMV") && is_parvum("C12") && !is_parvum("C1"));
+        let mut config = InputConfig::new(Hora::Laudes);
+        config.month = 8;
+        config.day = 15;
+        assert_eq!(ParvumSeason::of(&config), ParvumSeason::PerAnnum);
+        let lines = parvum(&config, &mut files(), "Latin").unwrap();
+        assert_eq!(lines.iter().filter(|l| l.starts_with("&psalm(")).count(), 6);
+        assert!(lines.contains(&"Ant. Assúmpta est María.".to_string()));
+        assert!(lines.contains(&"Concéde nos fámulos tuos.".to_string()));
+        let hymn = lines.iter().position(|l| l == "O gloriósa Vírginum,").unwrap();
+        assert!(hymn > lines.iter().position(|l| l == "&psalm(148)").unwrap());
+
+        config.daynames = vec!["Adv1-3".to_string()];
+        config.month = 12;
+        config.day = 3;
+        let lines = parvum(&config, &mut files(), "Latin").unwrap();
+        assert!(lines.contains(&"Ant. Missus est Gábriel.".to_string()));
+
+        config.daynames = vec!["Epi1-2".to_string()];
+        config.month = 1;
+        config.day = 14;
+        let lines = parvum(&config, &mut files(), "Latin").unwrap();
+        assert!(lines.contains(&"Deus, qui salútis ætérnæ.".to_string()));
+    }
+
+    #[test]
+    fn test_parvum_psalms() {
+        assert_eq!(psalmi(Hora::Matutinum, 0), &[8, 18, 23]);
+        assert_eq!(psalmi(Hora::Matutinum, 5), &[44, 45, 86]);
+        assert_eq!(psalmi(Hora::Matutinum, 6), &[95, 96, 97]);
+
+        let lines = parvum(&InputConfig::new(Hora::Tertia), &mut files(), "Latin").unwrap();
+        let psalmi: Vec<&String> = lines.iter().filter(|l| l.starts_with("&psalm(")).collect();
+        assert_eq!(psalmi, ["&psalm(119)", "&psalm(120)", "&psalm(121)"]);
+        assert_eq!(lines.iter().filter(|l| *l == "Ant. María Virgo assúmpta est.").count(), 2);
+        assert!(lines.iter().position(|l| l == "Meménto, rerum Cónditor,").unwrap() < lines.iter().position(|l| l == "&psalm(119)").unwrap());
+    }
+}
@@ -0,0 +1,333 @@
+//! postprocess.rs
+//!
+//! Final adjustments applied to antiphons, versicles and short responsories
+//! once they have been selected, as in the `postprocess_*` subs of the Perl
+//! code. At present this is the alleluia (`alleluia_mode()`): in Paschaltide
+//! every antiphon and versicle ends with "alleluja", and the short
+//! responsories take the Paschal form; from Septuagesima to Easter the
+//! alleluia is not said, and "Laus tibi, Domine" follows the "Deus in
+//! adjutorium".
+
+use crate::regex::contains_ci;
+use crate::setup_string::{ResolveDirectives, SetupStringProvider};
+
+/// How the alleluia is said on a day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AlleluiaMode {
+    /// Paschaltide: the antiphons, versicles and responsories take it.
+    Paschal,
+    /// The rest of the year: the texts are said as they are.
+    Ordinary,
+    /// From Septuagesima to Easter, and in the Office of the Dead: the
+    /// alleluia is taken away, and after the "Deus in adjutorium" the
+    /// "Laus tibi, Domine, Rex aeternae gloriae" is said instead.
+    Suppressed,
+}
+
+/// The alleluia of the day `dayname` ("Quadp1-0", "Pasc2-3") for the
+/// office, or the votive office `votive`.
+pub(crate) fn alleluia_mode(dayname: &str, votive: &str) -> AlleluiaMode {
+    if contains_ci(votive, "C9") || contains_ci(votive, "Defunct") || contains_ci(dayname, "Quad") {
+        AlleluiaMode::Suppressed
+    } else if contains_ci(dayname, "Pasc") {
+        AlleluiaMode::Paschal
+    } else {
+        AlleluiaMode::Ordinary
+    }
+}
+
+/// Whether the texts of the day take the Paschal alleluia: the office is of
+/// Paschaltide and is not the Office of the Dead.
+pub(crate) fn alleluia_required(dayname: &str, votive: &str) -> bool {
+    alleluia_mode(dayname, votive) == AlleluiaMode::Paschal
+}
+
+/// The line of the script said after the "Deus in adjutorium": `$Alleluia`
+/// becomes `$Laus tibi` when the alleluia is suppressed.
+pub(crate) fn incipit_alleluia(line: &str, dayname: &str, votive: &str) -> String {
+    if line.trim() == "$Alleluia" && alleluia_mode(dayname, votive) == AlleluiaMode::Suppressed {
+        "$Laus tibi".to_string()
+    } else {
+        line.to_string()
+    }
+}
+
+/// The word "Alleluia" in `lang`, taken from the `[Alleluia]` prayer
+/// ("v. Allelúja." in the Latin data), or the Latin spelling if missing.
+pub(crate) fn alleluia_word(files: &mut dyn SetupStringProvider, lang: &str) -> String {
+    let text = files
+        .setupstring(lang, "Psalterium/Common/Prayers.txt", ResolveDirectives::None)
+        .and_then(|p| p.get("Alleluia").cloned())
+        .unwrap_or_default();
+    let text = text.trim().trim_start_matches("v. ");
+    let word = text.split(['.', ',']).next().unwrap_or("").trim();
+    if word.is_empty() {
+        "Allelúja".to_string()
+    } else {
+        word.to_string()
+    }
+}
+
+/// Appends ", alleluja." to `text` unless it already ends with an alleluia.
+fn ensure_single_alleluia(text: &mut String, alleluia: &str) {
+    let body = text.trim_end().trim_end_matches(['.', ',', ';', ':', '!']);
+    if body.to_lowercase().ends_with(&alleluia.to_lowercase()) || body.is_empty() {
+        return;
+    }
+    *text = format!("{}, {}.", body, alleluia.to_lowercase());
+}
+
+/// Takes away the alleluias that end `text`, keeping its final stop.
+fn strip_alleluia(text: &mut String, alleluia: &str) {
+    let lower = alleluia.to_lowercase();
+    let mut body = text.trim_end().trim_end_matches(['.', ',', ';', ':', '!']);
+    let mut stripped = false;
+    while body.to_lowercase().ends_with(&lower) {
+        let Some(end) = body.len().checked_sub(lower.len()).filter(|&e| body.is_char_boundary(e)) else {
+            break;
+        };
+        body = body[..end].trim_end_matches([' ', ',']);
+        stripped = true;
+    }
+    if stripped && !body.is_empty() {
+        *text = format!("{}.", body);
+    }
+}
+
+/// Like `ensure_single_alleluia`, with the double "alleluja, alleluja."
+fn ensure_double_alleluia(text: &mut String, alleluia: &str) {
+    let body = text.trim_end().trim_end_matches(['.', ',', ';', ':', '!']);
+    let lower = alleluia.to_lowercase();
+    if body.to_lowercase().ends_with(&format!("{}, {}", lower, lower)) || body.is_empty() {
+        return;
+    }
+    let body = if body.to_lowercase().ends_with(&lower) {
+        body[..body.len() - lower.len()].trim_end_matches([' ', ','])
+    } else {
+        body
+    };
+    *text = format!("{}, {}, {}.", body, lower, lower);
+}
+
+/// Adds the Paschal alleluia to an antiphon, or takes it away when the
+/// alleluia is suppressed.
+pub(crate) fn postprocess_ant(
+    ant: &mut String,
+    dayname: &str,
+    votive: &str,
+    files: &mut dyn SetupStringProvider,
+    lang: &str,
+) {
+    let mode = alleluia_mode(dayname, votive);
+    if mode == AlleluiaMode::Ordinary {
+        return;
+    }
+    let alleluia = alleluia_word(files, lang);
+    if mode == AlleluiaMode::Paschal {
+        ensure_single_alleluia(ant, &alleluia);
+    } else {
+        strip_alleluia(ant, &alleluia);
+    }
+}
+
+/// Adds the Paschal alleluia to every `V.` and `R.` line of a versicle, or
+/// takes it away when the alleluia is suppressed.
+pub(crate) fn postprocess_vr(
+    vr: &mut String,
+    dayname: &str,
+    votive: &str,
+    files: &mut dyn SetupStringProvider,
+    lang: &str,
+) {
+    let mode = alleluia_mode(dayname, votive);
+    if mode == AlleluiaMode::Ordinary {
+        return;
+    }
+    let alleluia = alleluia_word(files, lang);
+    let lines: Vec<String> = vr
+        .lines()
+        .map(|line| {
+            let mut line = line.to_string();
+            if line.starts_with("V.") || (line.starts_with("R.") && !line.starts_with("R.br")) {
+                if mode == AlleluiaMode::Paschal {
+                    ensure_single_alleluia(&mut line, &alleluia);
+                } else {
+                    strip_alleluia(&mut line, &alleluia);
+                }
+            }
+            line
+        })
+        .collect();
+    *vr = lines.join("\n");
+}
+
+/// Puts a short responsory (`R.br.` ... `V.` ... `R.` ... `&Gloria` ...) into
+/// its Paschal form: the response ends with a double alleluia, and its
+/// repeated second half becomes "R. Alleluja, alleluja." The versicle that
+/// follows the responsory (after the `_` separator) takes a single alleluia.
+pub(crate) fn postprocess_short_resp(
+    lines: &mut [String],
+    dayname: &str,
+    votive: &str,
+    files: &mut dyn SetupStringProvider,
+    lang: &str,
+) {
+    let mode = alleluia_mode(dayname, votive);
+    if mode == AlleluiaMode::Suppressed {
+        let alleluia = alleluia_word(files, lang);
+        for line in lines.iter_mut().filter(|l| l.starts_with("R.") || l.starts_with("V.")) {
+            strip_alleluia(line, &alleluia);
+        }
+        return;
+    }
+    if mode != AlleluiaMode::Paschal {
+        return;
+    }
+    let alleluia = alleluia_word(files, lang);
+    let repeat = format!("R. {}, {}.", alleluia, alleluia.to_lowercase());
+    let mut in_resp = false;
+    let mut after_versicle = false;
+    let mut after_resp = false;
+    for line in lines.iter_mut() {
+        if line.starts_with("R.br") {
+            ensure_double_alleluia(line, &alleluia);
+            in_resp = true;
+            after_versicle = false;
+            continue;
+        }
+        if line.trim() == "_" {
+            if in_resp {
+                after_resp = true;
+            }
+            in_resp = false;
+            continue;
+        }
+        if in_resp {
+            if line.starts_with("V.") {
+                after_versicle = true;
+            } else if line.starts_with("R.") && after_versicle {
+                *line = repeat.clone();
+                after_versicle = false;
+            } else if line.starts_with("R.") {
+                ensure_double_alleluia(line, &alleluia);
+            }
+        } else if after_resp && (line.starts_with("V.") || line.starts_with("R.")) {
+            ensure_single_alleluia(line, &alleluia);
+        }
+    }
+}
+
+/// Adds the Paschal alleluia to a responsory of Matins: to the response and
+/// to its part repeated after the verse, the `R.` lines. The Gloria and the
+/// verse are left as they are.
+pub(crate) fn matins_lectio_responsory_alleluia(
+    resp: &mut String,
+    dayname: &str,
+    votive: &str,
+    files: &mut dyn SetupStringProvider,
+    lang: &str,
+) {
+    if !alleluia_required(dayname, votive) {
+        return;
+    }
+    let alleluia = alleluia_word(files, lang);
+    let lines: Vec<String> = resp
+        .lines()
+        .map(|line| {
+            let mut line = line.to_string();
+            if line.starts_with("R.") {
+                ensure_single_alleluia(&mut line, &alleluia);
+            }
+            line
+        })
+        .collect();
+    *resp = lines.join("\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::setup_string::FileSections;
+
+    struct NoFiles;
+
+    impl SetupStringProvider for NoFiles {
+        fn setupstring(&mut self, _: &str, _: &str, _: ResolveDirectives) -> Option<FileSections> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_alleluia_required() {
+        assert!(alleluia_required("Pasc2-3", ""));
+        assert!(!alleluia_required("Pasc2-3", "C9"));
+        assert!(!alleluia_required("Quad2-3", ""));
+    }
+
+    #[test]
+    fn test_alleluia_mode() {
+        assert_eq!(alleluia_mode("Epi3-6", ""), AlleluiaMode::Ordinary);
+        assert_eq!(alleluia_mode("Quadp1-0", ""), AlleluiaMode::Suppressed);
+        assert_eq!(alleluia_mode("Quad6-6", ""), AlleluiaMode::Suppressed);
+        assert_eq!(alleluia_mode("Pasc0-0", ""), AlleluiaMode::Paschal);
+        assert_eq!(alleluia_mode("Pasc2-3", "Defunctorum"), AlleluiaMode::Suppressed);
+        assert_eq!(incipit_alleluia("$Alleluia", "Quadp2-1", ""), "$Laus tibi");
+        assert_eq!(incipit_alleluia("$Alleluia", "Pent05-1", ""), "$Alleluia");
+    }
+
+    #[test]
+    fn test_postprocess_suppressed() {
+        let mut ant = "Dóminus regnávit, decórem índuit, allelúja, allelúja.".to_string();
+        postprocess_ant(&mut ant, "Quadp1-0", "", &mut NoFiles, "Latin");
+        assert_eq!(ant, "Dóminus regnávit, decórem índuit.");
+        let mut vr = "V. Dóminus regnávit, allelúja.\nR. Decórem índuit.".to_string();
+        postprocess_vr(&mut vr, "Quad2-3", "", &mut NoFiles, "Latin");
+        assert_eq!(vr, "V. Dóminus regnávit.\nR. Decórem índuit.");
+        // The rest of the year the texts are left as they are.
+        let mut ant = "Allelúja, * allelúja.".to_string();
+        postprocess_ant(&mut ant, "Epi3-0", "", &mut NoFiles, "Latin");
+        assert_eq!(ant, "Allelúja, * allelúja.");
+    }
+
+    #[test]
+    fn test_postprocess_ant() {
+        let mut ant = "Surrexit Dominus vere.".to_string();
+        postprocess_ant(&mut ant, "Pasc1-0", "", &mut NoFiles, "Latin");
+        assert_eq!(ant, "Surrexit Dominus vere, allelúja.");
+        // Already present: unchanged.
+        postprocess_ant(&mut ant, "Pasc1-0", "", &mut NoFiles, "Latin");
+        assert_eq!(ant, "Surrexit Dominus vere, allelúja.");
+    }
+
+    #[test]
+    fn test_postprocess_short_resp() {
+        let mut lines: Vec<String> = [
+            "R.br. Christe, Fili Dei vivi, * Miserére nobis.",
+            "R. Christe, Fili Dei vivi, * Miserére nobis.",
+            "V. Qui sedes ad déxteram Patris.",
+            "R. Miserére nobis.",
+            "&Gloria1",
+            "_",
+            "V. Exsúrge, Christe, ádjuva nos.",
+            "R. Et líbera nos propter nomen tuum.",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        postprocess_short_resp(&mut lines, "Pasc2-1", "", &mut NoFiles, "Latin");
+        assert_eq!(lines[0], "R.br. Christe, Fili Dei vivi, * Miserére nobis, allelúja, allelúja.");
+        assert_eq!(lines[1], "R. Christe, Fili Dei vivi, * Miserére nobis, allelúja, allelúja.");
+        assert_eq!(lines[3], "R. Allelúja, allelúja.");
+        assert_eq!(lines[6], "V. Exsúrge, Christe, ádjuva nos, allelúja.");
+    }
+
+    #[test]
+    fn test_matins_lectio_responsory_alleluia() {
+        let mut resp = "R. Surréxit pastor bonus, * Qui ánimam suam pósuit.\nV. Etenim Pascha nostrum.\nR. Qui ánimam suam pósuit.".to_string();
+        matins_lectio_responsory_alleluia(&mut resp, "Pasc2-0", "", &mut NoFiles, "Latin");
+        assert_eq!(
+            resp,
+            "R. Surréxit pastor bonus, * Qui ánimam suam pósuit, allelúja.\nV. Etenim Pascha nostrum.\nR. Qui ánimam suam pósuit, allelúja."
+        );
+    }
+}
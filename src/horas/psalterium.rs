@@ -0,0 +1,253 @@
+//! psalterium.rs
+//!
+//! How the antiphons of the psalter are said around their psalms.
+//!
+//! In a double office (and at every office under the 1960 rubrics) the
+//! antiphon is *doubled*: said in full before the psalm and again after it.
+//! Otherwise only its incipit, the words before the asterisk, is intoned
+//! before the psalm, and the whole antiphon is said after it. The
+//! Cistercians never double the antiphons; their books mark the intonation
+//! with the rubric "Antiphona". The monastic Compline is said without
+//! antiphon.
+//!
+//! `antiphon_doubling()` decides which of these applies; `intonation()`
+//! gives the line said before the psalm. `antetpsalm()` and the nocturns of
+//! the Roman and monastic Matins both ask them.
+//!
+//! The hours name their psalms by `&psalm(N)` directives, left to the front
+//! end; `load_psalm()` reads a psalm itself, verse by verse, for the callers
+//! that need its text (the stanzas of the invitatory, a renderer), with the
+//! Gloria Patri when the day has it at that part of the office.
+
+use super::Hora;
+use crate::language_text_tools::rubric_label;
+use crate::rubrics::{gloria_patri_allowed, GloriaContext, RubricsVersion};
+use crate::setup_string::{ResolveDirectives, SetupStringProvider};
+
+/// How the antiphons of an hour are said around their psalms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Doubling {
+    /// Said in full before and after the psalms.
+    Doubled,
+    /// Only the incipit before the psalms, in full after them.
+    Incipit,
+    /// The incipit followed by the rubric "Antiphona", as in the Cistercian
+    /// books.
+    IncipitWithRubric,
+    /// The psalms are said without antiphon.
+    Omitted,
+}
+
+/// Whether the antiphons of an office of `rank` are doubled at `hora` under
+/// the rubrics of `version`.
+///
+/// The antiphons are doubled on doubles (rank 3 and above, the `$duplex > 2`
+/// of the Perl code) and at every office under the 1960 rubrics, at all the
+/// hours. The Cistercian use intones them only, and the monastic Compline
+/// has none.
+pub fn antiphon_doubling(rank: f64, version: &RubricsVersion, hora: Hora) -> Doubling {
+    if version.monastic && hora == Hora::Completorium {
+        Doubling::Omitted
+    } else if version.is_cistercian() {
+        Doubling::IncipitWithRubric
+    } else if version.is_1960() || rank >= 3.0 {
+        Doubling::Doubled
+    } else {
+        Doubling::Incipit
+    }
+}
+
+/// The line said before the psalms for the antiphon `ant`: the antiphon in
+/// full if it is doubled, else its incipit, ending with a full stop instead
+/// of a comma (and the rubric for the Cistercians). `None` if the antiphon
+/// is omitted.
+pub fn intonation(ant: &str, doubling: Doubling, lang: &str, version: &RubricsVersion) -> Option<String> {
+    let mut incipit = match doubling {
+        Doubling::Omitted => return None,
+        Doubling::Doubled => return Some(ant.to_string()),
+        Doubling::Incipit | Doubling::IncipitWithRubric => {
+            ant.split('*').next().unwrap_or("").trim_end().to_string()
+        }
+    };
+    if incipit.ends_with(',') {
+        incipit = incipit.trim_end_matches(',').to_string() + ".";
+    }
+    if doubling == Doubling::IncipitWithRubric {
+        incipit.push(' ');
+        incipit.push_str(&rubric_label("Antiphona", lang, version));
+    }
+    Some(incipit)
+}
+
+/// The directive of the psalm `psalm` ("116", or "118(1-16)"), which ends
+/// with the Gloria Patri only if `gloria` (see `rubrics::gloria_patri_allowed()`).
+pub fn psalm_directive(psalm: impl std::fmt::Display, gloria: bool) -> String {
+    if gloria {
+        format!("&psalm({})", psalm)
+    } else {
+        format!("&psalm({},'nogloria')", psalm)
+    }
+}
+
+/// A psalm of `Psalterium/Psalmorum`, as `load_psalm()` reads it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Psalm {
+    pub number: u32,
+    /// The heading, e.g. "Psalmus 118 [1-16]".
+    pub title: String,
+    /// The verses, as their label ("94:7a", empty for a line without one),
+    /// the number of the verse (7) and their text.
+    pub verses: Vec<(String, Option<u32>, String)>,
+    /// The Gloria Patri said after the psalm, if it is said.
+    pub gloria: Option<String>,
+}
+
+impl Psalm {
+    /// The psalm as the lines of the hour: the heading as a "!" rubric, the
+    /// numbered verses and the Gloria Patri.
+    pub fn text(&self) -> String {
+        let mut lines = vec![format!("!{}", self.title)];
+        lines.extend(self.lines());
+        lines.extend(self.gloria.clone());
+        lines.join("\n")
+    }
+
+    /// The verses as the lines of the psalm file: "109:1 Dixit Dóminus ...",
+    /// or the text alone for a line without a label.
+    pub fn lines(&self) -> impl Iterator<Item = String> + '_ {
+        self.verses.iter().map(|(label, _, text)| match label.as_str() {
+            "" => text.clone(),
+            label => format!("{} {}", label, text),
+        })
+    }
+}
+
+/// Reads the psalm `spec` ("116", or "118(1-16)" for a range of verses) in
+/// `lang`, its heading translated.
+///
+/// The Gloria Patri of the Prayers is added if `gloria` is given and the day
+/// has it at that part (`gloria_patri_allowed()`): not in the Triduum nor in
+/// the Office of the Dead, and in Passiontide at the psalms only.
+pub fn load_psalm(
+    files: &mut dyn SetupStringProvider,
+    lang: &str,
+    spec: &str,
+    gloria: Option<&GloriaContext>,
+) -> Option<Psalm> {
+    let (number, range) = match spec.split_once('(') {
+        Some((number, range)) => {
+            let (from, to) = range.trim_end_matches(')').split_once('-')?;
+            (number.trim().parse::<u32>().ok()?, Some((from.trim().parse::<u32>().ok()?, to.trim().parse::<u32>().ok()?)))
+        }
+        None => (spec.trim().parse::<u32>().ok()?, None),
+    };
+    let fname = format!("Psalterium/Psalmorum/Psalm{}.txt", number);
+    let text = files.setupstring(lang, &fname, ResolveDirectives::All)?.get("__preamble")?.clone();
+    let mut verses = Vec::new();
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let (label, verse) = match line.split_once(' ') {
+            Some((label, verse)) if label.contains(':') => (label.to_string(), verse.to_string()),
+            _ => (String::new(), line.to_string()),
+        };
+        let n = label
+            .split_once(':')
+            .and_then(|(_, v)| v.trim_end_matches(|c: char| !c.is_ascii_digit()).parse::<u32>().ok());
+        if range.is_some_and(|(from, to)| !n.is_some_and(|n| n >= from && n <= to)) {
+            continue;
+        }
+        verses.push((label, n, verse));
+    }
+
+    let heading = files
+        .setupstring(lang, "Psalterium/Common/Translate.txt", ResolveDirectives::All)
+        .and_then(|t| t.get("Psalmus").map(|p| p.trim().to_string()))
+        .unwrap_or_else(|| "Psalmus".to_string());
+    let title = match range {
+        Some((from, to)) => format!("{} {} [{}-{}]", heading, number, from, to),
+        None => format!("{} {}", heading, number),
+    };
+    let gloria = gloria.filter(|ctx| gloria_patri_allowed(ctx)).and_then(|_| {
+        let prayers = files.setupstring(lang, "Psalterium/Common/Prayers.txt", ResolveDirectives::All)?;
+        prayers.get("Gloria").map(|t| t.trim_end().to_string())
+    });
+    Some(Psalm { number, title, verses, gloria })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::horas::specials::tests::TestFiles;
+
+    #[test]
+    fn test_antiphon_doubling_simple_feast() {
+        let rubrics1960 = RubricsVersion::parse("Rubrics 1960 - 1960");
+        let divino = RubricsVersion::parse("Divino Afflatu - 1954");
+        // A simple feast (III. class in 1960) still doubles its antiphons.
+        assert_eq!(antiphon_doubling(1.1, &rubrics1960, Hora::Laudes), Doubling::Doubled);
+        assert_eq!(antiphon_doubling(1.1, &rubrics1960, Hora::Matutinum), Doubling::Doubled);
+        assert_eq!(antiphon_doubling(1.1, &divino, Hora::Laudes), Doubling::Incipit);
+        assert_eq!(antiphon_doubling(2.0, &divino, Hora::Vespera), Doubling::Incipit);
+        assert_eq!(antiphon_doubling(3.0, &divino, Hora::Vespera), Doubling::Doubled);
+    }
+
+    #[test]
+    fn test_antiphon_doubling_monastic() {
+        let cist = RubricsVersion::parse("Monastic Tridentinum Cisterciensis 1951");
+        let monastic = RubricsVersion::parse("Monastic - 1930");
+        assert_eq!(antiphon_doubling(6.0, &cist, Hora::Laudes), Doubling::IncipitWithRubric);
+        assert_eq!(antiphon_doubling(6.0, &monastic, Hora::Laudes), Doubling::Doubled);
+        assert_eq!(antiphon_doubling(6.0, &monastic, Hora::Completorium), Doubling::Omitted);
+    }
+
+    #[test]
+    fn test_intonation() {
+        let version = RubricsVersion::parse("Divino Afflatu - 1954");
+        let ant = "Dixit Dóminus, * Dómino meo: Sede a dextris meis.";
+        assert_eq!(intonation(ant, Doubling::Doubled, "Latin", &version).as_deref(), Some(ant));
+        assert_eq!(intonation(ant, Doubling::Incipit, "Latin", &version).as_deref(), Some("Dixit Dóminus."));
+        assert_eq!(intonation(ant, Doubling::Omitted, "Latin", &version), None);
+    }
+
+    #[test]
+    fn test_psalm_directive() {
+        assert_eq!(psalm_directive(116, true), "&psalm(116)");
+        assert_eq!(psalm_directive("118(1-16)", false), "&psalm(118(1-16),'nogloria')");
+    }
+
+    #[test]
+    fn test_load_psalm() {
+        use crate::rubrics::GloriaPart;
+
+        let mut files = TestFiles::default()
+            .with("Latin", "Psalterium/Psalmorum/Psalm116.txt", &[(
+                "__preamble",
+                "116:1 Laudáte Dóminum, omnes gentes: * laudáte eum, omnes pópuli.\n116:2 Quóniam confirmáta est.",
+            )])
+            .with("Latin", "Psalterium/Common/Prayers.txt", &[("Gloria", "Glória Patri.\nSicut erat.")]);
+        let psalm_ctx = GloriaContext { dayname: "Quad5-2", votive: "", part: GloriaPart::Psalm };
+        let psalm = load_psalm(&mut files, "Latin", "116", Some(&psalm_ctx)).unwrap();
+        assert_eq!((psalm.number, psalm.title.as_str()), (116, "Psalmus 116"));
+        assert_eq!(psalm.verses[1], ("116:2".to_string(), Some(2), "Quóniam confirmáta est.".to_string()));
+        assert_eq!(psalm.gloria.as_deref(), Some("Glória Patri.\nSicut erat."));
+        assert!(psalm.text().starts_with("!Psalmus 116\n116:1 Laudáte Dóminum"));
+
+        // In Passiontide the invitatory psalm has no Gloria Patri.
+        let invit_ctx = GloriaContext { part: GloriaPart::Invitatorium, ..psalm_ctx };
+        assert_eq!(load_psalm(&mut files, "Latin", "116", Some(&invit_ctx)).unwrap().gloria, None);
+        let range = load_psalm(&mut files, "Latin", "116(2-2)", None).unwrap();
+        assert_eq!((range.title.as_str(), range.verses.len(), range.gloria), ("Psalmus 116 [2-2]", 1, None));
+        assert!(load_psalm(&mut files, "Latin", "117", None).is_none());
+
+        // A suffixed label keeps its number; a line without one is kept whole.
+        let mut files = files.with("Latin", "Psalterium/Psalmorum/Psalm94.txt", &[(
+            "__preamble",
+            "94:7a Quia ipse est Dóminus Deus noster.\n(sequens versus dicitur flexis genibus)",
+        )]);
+        let psalm = load_psalm(&mut files, "Latin", "94", None).unwrap();
+        assert_eq!((psalm.verses[0].1, psalm.verses[1].1), (Some(7), None));
+        assert_eq!(
+            psalm.lines().collect::<Vec<_>>(),
+            ["94:7a Quia ipse est Dóminus Deus noster.", "(sequens versus dicitur flexis genibus)"]
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! rank.rs
+//!
+//! The `[Rank]` line of an office, "title;;class;;precedence;;commune":
+//!
+//! ```text
+//! [Rank]
+//! Die II infra octavam Epiphaniæ;;Semiduplex;;5.6;;ex Sancti/01-06
+//! ```
+//!
+//! `Rank::parse` splits it into its fields, with the octave the title names,
+//! so that the hours ask `is_dominica()` or `within_octave()` instead of
+//! searching the line.
+//!
+//! ```ignore
+//! let rank = Rank::of(config.winners());
+//! if rank.is_dominica() && rank.precedence < 6.0 { ... }
+//! ```
+
+use crate::regex::{capture_first, ci_contains};
+use crate::setup_string::FileSections;
+
+/// The octave a day belongs to, as its title names it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Octave {
+    /// A day within the octave of the feast, e.g. "Die II infra octavam
+    /// Epiphaniæ" within that of "Epiphaniæ".
+    Within(String),
+    /// The octave day of the feast, e.g. "In Octava Epiphaniæ".
+    Day(String),
+}
+
+/// The `[Rank]` line of an office.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Rank {
+    /// The title of the office, e.g. "S. Agnetis Virginis et Martyris".
+    pub title: String,
+    /// The class of the office, e.g. "Duplex" or "Semiduplex I. classis".
+    pub class: String,
+    /// The number by which offices are compared, 0 without one.
+    pub precedence: f64,
+    /// The commune reference, e.g. "vide C6" or "ex Sancti/01-06".
+    pub commune: String,
+    /// The octave of the day, if its title names one.
+    pub octave: Option<Octave>,
+}
+
+impl Rank {
+    /// Parses the first line of the text of a `[Rank]` section.
+    pub fn parse(line: &str) -> Self {
+        let line = line.lines().next().unwrap_or("");
+        let mut fields = line.split(";;").map(str::trim);
+        let title = fields.next().unwrap_or("").to_string();
+        let class = fields.next().unwrap_or("").to_string();
+        let precedence = fields.next().and_then(|n| n.parse().ok()).unwrap_or(0.0);
+        let commune = fields.next().unwrap_or("").to_string();
+        let octave = octave_of(&title);
+        Rank { title, class, precedence, commune, octave }
+    }
+
+    /// The rank of the sections of an office file, the default without a
+    /// `[Rank]` section.
+    pub fn of(sections: &FileSections) -> Self {
+        sections.get("Rank").map(|r| Self::parse(r)).unwrap_or_default()
+    }
+
+    /// The file of the commune and how it is used, as
+    /// `("Commune/C4a.txt", "ex")` or `("Commune/C11.txt", "vide")`.
+    pub fn commune_file(&self) -> Option<(String, String)> {
+        let mut words = self.commune.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some(kind @ ("ex" | "vide")), Some(name)) => {
+                let name = name.trim_end_matches(".txt");
+                let file = if name.contains('/') { name.to_string() } else { format!("Commune/{}", name) };
+                Some((format!("{}.txt", file), kind.to_string()))
+            }
+            _ => None,
+        }
+    }
+
+    /// The `duplex` of the hours: 3 for doubles, 2 for semidoubles, 1 for
+    /// simples and ferias.
+    pub fn duplex(&self) -> u32 {
+        if self.precedence >= 3.0 {
+            3
+        } else if self.precedence >= 2.0 {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Whether the title contains `word`, ignoring case.
+    pub fn title_contains(&self, word: &str) -> bool {
+        ci_contains(&self.title, word)
+    }
+
+    /// Whether the office is of a Sunday.
+    pub fn is_dominica(&self) -> bool {
+        self.title_contains("Dominica")
+    }
+
+    /// Whether the office is of a vigil.
+    pub fn is_vigil(&self) -> bool {
+        self.title_contains("vigil")
+    }
+
+    /// Whether the office is of an Ember day, "Quatuor" or "Quattuor
+    /// Temporum".
+    pub fn is_quattuor_temporum(&self) -> bool {
+        self.title_contains("quatuor") || self.title_contains("quattuor")
+    }
+
+    /// Whether the day is within an octave (not the octave day).
+    pub fn within_octave(&self) -> bool {
+        matches!(self.octave, Some(Octave::Within(_)))
+    }
+
+    /// Whether the day is an octave day.
+    pub fn is_octave_day(&self) -> bool {
+        matches!(self.octave, Some(Octave::Day(_)))
+    }
+}
+
+/// The octave named by the title of an office.
+fn octave_of(title: &str) -> Option<Octave> {
+    if let Some(feast) = capture_first(title, r"infra octavam\s+(.+)") {
+        return Some(Octave::Within(feast.trim().to_string()));
+    }
+    capture_first(title, r"^(?:(?:in|die) octava|dies octavæ)\s+(.+)").map(|feast| Octave::Day(feast.trim().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let rank = Rank::parse("S. Agnetis Virginis et Martyris;;Duplex;;3;;vide C6\n");
+        assert_eq!((rank.title.as_str(), rank.class.as_str(), rank.precedence), ("S. Agnetis Virginis et Martyris", "Duplex", 3.0));
+        assert_eq!(rank.commune_file(), Some(("Commune/C6.txt".to_string(), "vide".to_string())));
+        assert_eq!((rank.octave.clone(), rank.duplex()), (None, 3));
+        let rank = Rank::parse("Die II infra octavam Epiphaniæ;;Semiduplex;;5.6;;ex Sancti/01-06");
+        assert_eq!(rank.octave, Some(Octave::Within("Epiphaniæ".to_string())));
+        assert!(rank.within_octave() && !rank.is_octave_day());
+        assert_eq!(rank.commune_file(), Some(("Sancti/01-06.txt".to_string(), "ex".to_string())));
+        let rank = Rank::parse("In Octava Epiphaniæ;;Duplex majus;;5.61");
+        assert_eq!(rank.octave, Some(Octave::Day("Epiphaniæ".to_string())));
+        assert_eq!(Rank::parse(""), Rank::default());
+    }
+
+    #[test]
+    fn test_predicates() {
+        assert!(Rank::parse("Dominica II post Epiphaniam;;Semiduplex Dominica minor;;5").is_dominica());
+        assert!(Rank::parse("Feria IV Quatuor Temporum Septembris;;Feria major;;2").is_quattuor_temporum());
+        assert!(Rank::parse("In Vigilia Pentecostes;;Semiduplex;;5").is_vigil());
+        let rank = Rank::parse("Feria Secunda in Rogationibus;;Feria;;1.5");
+        assert!(rank.title_contains("roga") && !rank.is_dominica() && rank.duplex() == 1);
+    }
+}
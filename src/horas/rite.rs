@@ -0,0 +1,231 @@
+//! rite.rs
+//!
+//! The rites of the office as extension points. Where the Roman, monastic
+//! and Dominican books differ in the distribution of the psalms, the choice
+//! of a hymn, the preces or the final antiphon of Our Lady, the hours ask the
+//! `Rite` of the version instead of testing the version string.
+//!
+//! The rites of the crate are `Roman`, `Monastic` and `Dominican`. Another
+//! rite (Ambrosian, Braga, ...) implements `Rite` and is registered with
+//! `register_rite`; like the data source of `fileio`, the registry is
+//! process-wide.
+//!
+//! ```ignore
+//! struct Ambrosian;
+//!
+//! impl Rite for Ambrosian {
+//!     fn name(&self) -> &str { "Ambrosian" }
+//!     fn matches(&self, version: &RubricsVersion) -> bool { version.name.contains("Ambrosian") }
+//!     fn psalter_table(&self, _: &RubricsVersion) -> Option<String> { Some("Dayamb".to_string()) }
+//! }
+//!
+//! register_rite(Arc::new(Ambrosian));
+//! ```
+
+use std::sync::{Arc, RwLock};
+
+use once_cell::sync::Lazy;
+
+use super::completorium::{seasonal_antiphona_finalis, MarianAntiphon};
+use super::specials::{preces_required, PrecesKind};
+use super::Hora;
+use crate::rubrics::RubricsVersion;
+
+/// A rite of the office: the hooks where its books differ from the Roman
+/// Breviary. Every hook has the Roman rule as its default.
+pub trait Rite: Send + Sync {
+    /// The name of the rite, e.g. "Roman".
+    fn name(&self) -> &str;
+
+    /// Whether `version` is of this rite.
+    fn matches(&self, version: &RubricsVersion) -> bool;
+
+    /// The prefix of the weekly tables of "Psalmi major.txt" the rite
+    /// distributes the psalms of Lauds and Vespers by ("Daym" gives "Daym2
+    /// Vespera", "DaymF Laudes" for feasts), or `None` for the Roman psalter.
+    fn psalter_table(&self, _version: &RubricsVersion) -> Option<String> {
+        None
+    }
+
+    /// The name of a hymn the rite sings at `hora` instead of the one of
+    /// the office, given the `rule` of the office.
+    fn proper_hymn(&self, _version: &RubricsVersion, _hora: Hora, _rule: &str) -> Option<String> {
+        None
+    }
+
+    /// Which preces are said at `hora` (see `preces_required()`).
+    fn preces(&self, version: &RubricsVersion, dayname: &str, rank: f64, hora: Hora) -> PrecesKind {
+        preces_required(version, dayname, rank, hora)
+    }
+
+    /// The final antiphon of Compline (see `antiphona_finalis()`).
+    fn antiphona_finalis(
+        &self,
+        _version: &RubricsVersion,
+        dayname: &str,
+        day: u32,
+        month: u32,
+        dayofweek: u32,
+    ) -> Option<MarianAntiphon> {
+        seasonal_antiphona_finalis(dayname, day, month, dayofweek)
+    }
+
+    /// The day of December on which the great O antiphons begin at the
+    /// Magnificat (see `specials::antiphons::o_antiphon()`). They run to the
+    /// 23rd: seven from the 17th, or eight with "O Virgo virginum" from the
+    /// 16th.
+    fn o_antiphons_from(&self, _version: &RubricsVersion) -> u32 {
+        17
+    }
+}
+
+/// The Roman Breviary, the rite of every version that is not of another.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Roman;
+
+impl Rite for Roman {
+    fn name(&self) -> &str {
+        "Roman"
+    }
+
+    fn matches(&self, _version: &RubricsVersion) -> bool {
+        true
+    }
+}
+
+/// The monastic Breviary, with the Cistercian uses.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Monastic;
+
+impl Rite for Monastic {
+    fn name(&self) -> &str {
+        "Monastic"
+    }
+
+    fn matches(&self, version: &RubricsVersion) -> bool {
+        version.monastic
+    }
+
+    fn psalter_table(&self, version: &RubricsVersion) -> Option<String> {
+        Some(version.psalter_table().to_string())
+    }
+
+    fn proper_hymn(&self, version: &RubricsVersion, hora: Hora, rule: &str) -> Option<String> {
+        let hac_die = (rule.contains("C4") || rule.contains("C5")) && rule.contains("Hac die");
+        (version.has_hac_die_hymn() && hora == Hora::Vespera && hac_die).then(|| "Hymnus Vespera Hac die".to_string())
+    }
+
+    fn antiphona_finalis(
+        &self,
+        version: &RubricsVersion,
+        dayname: &str,
+        day: u32,
+        month: u32,
+        dayofweek: u32,
+    ) -> Option<MarianAntiphon> {
+        if version.salve_regina_at_compline() {
+            return Some(MarianAntiphon::SalveRegina);
+        }
+        seasonal_antiphona_finalis(dayname, day, month, dayofweek)
+    }
+}
+
+/// The Breviary of the Order of Preachers.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Dominican;
+
+impl Rite for Dominican {
+    fn name(&self) -> &str {
+        "Dominican"
+    }
+
+    fn matches(&self, version: &RubricsVersion) -> bool {
+        version.dominican
+    }
+
+    fn antiphona_finalis(&self, _: &RubricsVersion, _: &str, _: u32, _: u32, _: u32) -> Option<MarianAntiphon> {
+        Some(MarianAntiphon::SalveRegina)
+    }
+
+    /// The Order keeps the eighth O antiphon, "O Virgo virginum", on the
+    /// 23rd, beginning "O Sapientia" on the 16th.
+    fn o_antiphons_from(&self, _: &RubricsVersion) -> u32 {
+        16
+    }
+}
+
+static RITES: Lazy<RwLock<Vec<Arc<dyn Rite>>>> = Lazy::new(|| RwLock::new(Vec::new()));
+
+/// Registers `rite`. It is asked before the rites of the crate and those
+/// registered earlier, so it can also take over some of their versions.
+pub fn register_rite(rite: Arc<dyn Rite>) {
+    RITES.write().unwrap().push(rite);
+}
+
+/// Returns the rite of `version`: the last registered rite that matches it,
+/// else the Dominican, monastic or Roman rite.
+pub fn rite_of(version: &RubricsVersion) -> Arc<dyn Rite> {
+    if let Some(rite) = RITES.read().unwrap().iter().rev().find(|r| r.matches(version)) {
+        return rite.clone();
+    }
+    if Dominican.matches(version) {
+        Arc::new(Dominican)
+    } else if Monastic.matches(version) {
+        Arc::new(Monastic)
+    } else {
+        Arc::new(Roman)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Ambrosian;
+
+    impl Rite for Ambrosian {
+        fn name(&self) -> &str {
+            "Ambrosian"
+        }
+
+        fn matches(&self, version: &RubricsVersion) -> bool {
+            version.name.contains("Ambrosian")
+        }
+
+        fn psalter_table(&self, _: &RubricsVersion) -> Option<String> {
+            Some("Dayamb".to_string())
+        }
+
+        fn preces(&self, _: &RubricsVersion, _: &str, _: f64, _: Hora) -> PrecesKind {
+            PrecesKind::Omit
+        }
+    }
+
+    #[test]
+    fn test_rite_of() {
+        assert_eq!(rite_of(&RubricsVersion::parse("Rubrics 1960 - 1960")).name(), "Roman");
+        assert_eq!(rite_of(&RubricsVersion::parse("Monastic Tridentinum 1617")).name(), "Monastic");
+        assert_eq!(rite_of(&RubricsVersion::parse("Ordo Praedicatorum - 1962")).name(), "Dominican");
+
+        let cist = RubricsVersion::parse("Monastic Tridentinum Cisterciensis 1951");
+        let rite = rite_of(&cist);
+        assert_eq!(rite.psalter_table(&cist).as_deref(), Some("Dayc"));
+        assert_eq!(rite.antiphona_finalis(&cist, "Adv2-3", 10, 12, 3), Some(MarianAntiphon::SalveRegina));
+        assert_eq!(rite.proper_hymn(&cist, Hora::Vespera, "ex C4a;\nHac die"), Some("Hymnus Vespera Hac die".to_string()));
+        assert_eq!(rite.proper_hymn(&cist, Hora::Laudes, "ex C4a;\nHac die"), None);
+        let roman = RubricsVersion::parse("Divino Afflatu - 1954");
+        assert_eq!(rite_of(&roman).psalter_table(&roman), None);
+    }
+
+    #[test]
+    fn test_register_rite() {
+        let version = RubricsVersion::parse("Ambrosian - 1954");
+        assert_eq!(rite_of(&version).name(), "Roman");
+        register_rite(Arc::new(Ambrosian));
+        let rite = rite_of(&version);
+        assert_eq!(rite.name(), "Ambrosian");
+        assert_eq!(rite.psalter_table(&version).as_deref(), Some("Dayamb"));
+        assert_eq!(rite.preces(&version, "Adv1-3", 1.0, Hora::Laudes), PrecesKind::Omit);
+        assert_eq!(rite.antiphona_finalis(&version, "Adv2-3", 10, 12, 3), Some(MarianAntiphon::AlmaRedemptoris));
+    }
+}
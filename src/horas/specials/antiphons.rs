@@ -0,0 +1,313 @@
+//! antiphons.rs
+//!
+//! The antiphons and versicles of the office by season, from `getantvers()`
+//! in `/horas/specials.pl`, and the antiphons of the Gospel canticles built
+//! on it: the Benedictus at Lauds ("Ant 2") and the Magnificat at first
+//! ("Ant 1") and second ("Ant 3") Vespers.
+//!
+//! An antiphon is looked up in the proper of the office, its commune, the
+//! season of the Psalterium ("Adv Ant 2") and the Psalterium of the day of
+//! the week ("Day3 Ant 2"), as `proprium::get_proprium()` does; in
+//! Paschaltide it gets its alleluia.
+//!
+//! From the 17th to the 23rd of December the Magnificat of the season has
+//! the great O antiphons (`OAntiphon`, "Adv Ant 17" ... "Adv Ant 23" of
+//! `Major Special`), always doubled; the rites that keep "O Virgo virginum"
+//! begin them on the 16th (`Rite::o_antiphons_from()`). The canticle is
+//! then headed by the rubric "Antiphona major".
+//!
+//! ```ignore
+//! let ant = get_benedictus_ant(&mut ctx, "12-08-2025", "Latin", "Divino Afflatu")?;
+//! let ant = get_magnificat_ant(&mut ctx, "12-07-2025", 1, "Latin", "Divino Afflatu")?;
+//! ```
+
+use std::io;
+
+use super::proprium::{get_proprium, Proprium};
+use super::{rubric_line, InputConfig};
+use crate::date::prevnext;
+use crate::horas::officium::{office_config, Kalendar};
+use crate::horas::postprocess::{postprocess_ant, postprocess_vr};
+use crate::horas::psalterium::{antiphon_doubling, intonation, psalm_directive, Doubling};
+use crate::horas::rite::rite_of;
+use crate::horas::Hora;
+use crate::regex::contains_ci;
+use crate::rubrics::{GloriaPart, RubricsVersion};
+use crate::setup_string::{ResolveDirectives, SetupStringContext, SetupStringProvider};
+
+/// The great O antiphons of the Magnificat before Christmas, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OAntiphon {
+    Sapientia,
+    Adonai,
+    RadixJesse,
+    ClavisDavid,
+    Oriens,
+    RexGentium,
+    Emmanuel,
+    VirgoVirginum,
+}
+
+impl OAntiphon {
+    const ALL: [OAntiphon; 8] = [
+        OAntiphon::Sapientia,
+        OAntiphon::Adonai,
+        OAntiphon::RadixJesse,
+        OAntiphon::ClavisDavid,
+        OAntiphon::Oriens,
+        OAntiphon::RexGentium,
+        OAntiphon::Emmanuel,
+        OAntiphon::VirgoVirginum,
+    ];
+
+    /// The opening words of the antiphon, e.g. "O Sapiéntia".
+    pub fn incipit(self) -> &'static str {
+        match self {
+            OAntiphon::Sapientia => "O Sapiéntia",
+            OAntiphon::Adonai => "O Adonái",
+            OAntiphon::RadixJesse => "O Radix Jesse",
+            OAntiphon::ClavisDavid => "O Clavis David",
+            OAntiphon::Oriens => "O Oriens",
+            OAntiphon::RexGentium => "O Rex Géntium",
+            OAntiphon::Emmanuel => "O Emmánuel",
+            OAntiphon::VirgoVirginum => "O Virgo vírginum",
+        }
+    }
+
+    /// The section of `Psalterium/Special/Major Special.txt` with the text
+    /// of the antiphon: "Adv Ant 17" for "O Sapientia", the day of the
+    /// Roman Breviary, to "Adv Ant 23", and "Adv Ant Virgo" for the eighth.
+    pub fn key(self) -> String {
+        match self {
+            OAntiphon::VirgoVirginum => "Adv Ant Virgo".to_string(),
+            other => format!("Adv Ant {}", 17 + other as u32),
+        }
+    }
+}
+
+/// The O antiphon said at the Magnificat at Vespers on the evening of
+/// `day`/`month` in the rite of `version`, if any.
+pub fn o_antiphon(version: &RubricsVersion, day: u32, month: u32) -> Option<OAntiphon> {
+    if month != 12 || day > 23 {
+        return None;
+    }
+    let from = rite_of(version).o_antiphons_from(version);
+    let index = day.checked_sub(from)? as usize;
+    OAntiphon::ALL.get(index).copied()
+}
+
+/// Looks up "`item` `ind`" (e.g. "Ant 2", "Versum 3") for the hour of
+/// `config`, through the chain of `get_proprium()`. An antiphon ("Ant")
+/// gets the Paschal alleluia, a versicle ("Versum") on each of its lines.
+pub fn getantvers(
+    config: &InputConfig,
+    files: &mut dyn SetupStringProvider,
+    item: &str,
+    ind: i32,
+    lang: &str,
+) -> Option<Proprium> {
+    let mut found = get_proprium(config, files, &format!("{} {}", item, ind), lang)?;
+    found.text = found.text.trim_end().to_string();
+    if item == "Ant" {
+        postprocess_ant(&mut found.text, config.dayname0(), &config.votive, files, lang);
+    } else if item == "Versum" {
+        postprocess_vr(&mut found.text, config.dayname0(), &config.votive, files, lang);
+    }
+    Some(found)
+}
+
+/// The antiphon of the Benedictus of the office of `config` ("Ant 2").
+pub fn benedictus_ant(config: &InputConfig, files: &mut dyn SetupStringProvider, lang: &str) -> Option<String> {
+    let config = InputConfig { hora: Hora::Laudes, ..config.clone() };
+    getantvers(&config, files, "Ant", 2, lang).map(|found| found.text)
+}
+
+/// The antiphon of the Magnificat of the Vespers `config.vespera` of the
+/// office of `config`: "Ant 1" at first Vespers, "Ant 3" at second Vespers,
+/// or the O antiphon of the evening in an office of the season.
+pub fn magnificat_ant(config: &InputConfig, files: &mut dyn SetupStringProvider, lang: &str) -> Option<String> {
+    magnificat(config, files, lang).map(|(ant, _)| ant)
+}
+
+/// The antiphon of the Magnificat, with the O antiphon it is, if any.
+fn magnificat(
+    config: &InputConfig,
+    files: &mut dyn SetupStringProvider,
+    lang: &str,
+) -> Option<(String, Option<OAntiphon>)> {
+    let config = InputConfig { hora: Hora::Vespera, ..config.clone() };
+    // First Vespers are said on the evening before the day of the office.
+    let evening = if config.vespera == 1 { config.day.saturating_sub(1) } else { config.day };
+    let of_season = contains_ci(&config.winner, "tempora") || config.winner.is_empty();
+    if let Some(o) = o_antiphon(&config.rubrics(), evening, config.month).filter(|_| of_season) {
+        let ant = files
+            .setupstring(lang, "Psalterium/Special/Major Special.txt", ResolveDirectives::All)
+            .and_then(|special| special.get(&o.key()).cloned());
+        if let Some(ant) = ant {
+            config.build.setbuild("Psalterium/Special/Major Special", &o.key(), "subst");
+            return Some((ant.trim_end().to_string(), Some(o)));
+        }
+    }
+    let ant = getantvers(&config, files, "Ant", config.vespera, lang)?.text;
+    Some((ant, None))
+}
+
+/// The lines of the Gospel canticle of `config.hora` (the Benedictus at
+/// Lauds, the Magnificat at Vespers) with its antiphon, doubled as the
+/// antiphons of the psalms are (`psalterium::antiphon_doubling()`); an O
+/// antiphon is always doubled, after the rubric "Antiphona major".
+/// Empty at the other hours.
+pub fn canticum(config: &InputConfig, files: &mut dyn SetupStringProvider, lang: &str) -> Vec<String> {
+    let (ant, psalm) = match config.hora {
+        Hora::Laudes => (benedictus_ant(config, files, lang).map(|ant| (ant, None)), 231),
+        Hora::Vespera => (magnificat(config, files, lang), 232),
+        _ => return Vec::new(),
+    };
+    let canticle = psalm_directive(psalm, config.gloria_patri(GloriaPart::Psalm));
+    let Some((ant, o)) = ant else {
+        return vec![canticle];
+    };
+    let version = config.rubrics();
+    let mut lines = Vec::new();
+    let doubling = if o.is_some() {
+        lines.push(rubric_line("Antiphona major", lang, &version));
+        Doubling::Doubled
+    } else {
+        antiphon_doubling(config.rank, &version, config.hora)
+    };
+    if let Some(antp) = intonation(&ant, doubling, lang, &version) {
+        lines.push(format!("Ant. {}", antp));
+    }
+    lines.push(canticle);
+    lines.push(format!("Ant. {}", ant.replace("* ", "")));
+    lines
+}
+
+/// The antiphon of the Benedictus of `date` ("MM-DD-YYYY") in `lang`
+/// according to `version`, or `None` if the data have none.
+///
+/// Fails with `InvalidInput` for a malformed date.
+pub fn get_benedictus_ant(
+    ctx: &mut SetupStringContext,
+    date: &str,
+    lang: &str,
+    version: &str,
+) -> io::Result<Option<String>> {
+    let office = Kalendar::new(version).office(ctx, date, lang)?;
+    let config = office_config(ctx, &office, Hora::Laudes, lang, 1);
+    Ok(benedictus_ant(&config, ctx, lang))
+}
+
+/// The antiphon of the Magnificat at the Vespers `vespera` of `date`: for
+/// first Vespers (1) that of the office of the following day, for second
+/// Vespers (3) that of the office of `date`.
+///
+/// Fails with `InvalidInput` for a malformed date or another `vespera`.
+pub fn get_magnificat_ant(
+    ctx: &mut SetupStringContext,
+    date: &str,
+    vespera: i32,
+    lang: &str,
+    version: &str,
+) -> io::Result<Option<String>> {
+    let date = match vespera {
+        1 => prevnext(date, 1),
+        3 => date.to_string(),
+        _ => {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("no Vespers {}", vespera)));
+        }
+    };
+    let office = Kalendar::new(version).office(ctx, &date, lang)?;
+    let mut config = office_config(ctx, &office, Hora::Vespera, lang, 1);
+    config.vespera = vespera;
+    Ok(magnificat_ant(&config, ctx, lang))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::horas::specials::tests::TestFiles;
+
+    fn psalterium() -> TestFiles {
+        TestFiles::default().with("Latin", "Psalterium/Special/Major Special.txt", &[
+            ("Day1 Ant 2", "Benedíctus Dóminus * Deus Israël."),
+            ("Day1 Ant 3", "Magníficat * ánima mea Dóminum."),
+            ("Adv Ant 2", "Spíritus Sanctus * in te descéndet, María."),
+            ("Adv Ant 17", "O Sapiéntia, * quæ ex ore Altíssimi prodísti: veni ad docéndum nos viam prudéntiæ."),
+            ("Adv Ant Virgo", "O Virgo vírginum, * quómodo fiet istud?"),
+        ])
+    }
+
+    #[test]
+    fn test_canticle_antiphons_by_season() {
+        let mut config = InputConfig::new(Hora::Laudes);
+        config.dayofweek = 1;
+        config.daynames = vec!["Epi2-1".to_string()];
+        let mut files = psalterium();
+        assert_eq!(benedictus_ant(&config, &mut files, "Latin").as_deref(), Some("Benedíctus Dóminus * Deus Israël."));
+        assert_eq!(magnificat_ant(&config, &mut files, "Latin").as_deref(), Some("Magníficat * ánima mea Dóminum."));
+
+        config.daynames = vec!["Adv1-1".to_string()];
+        assert_eq!(benedictus_ant(&config, &mut files, "Latin").as_deref(), Some("Spíritus Sanctus * in te descéndet, María."));
+
+        // The office has its own antiphons.
+        config.winner_map.insert("Ant 1".to_string(), "Ecce Dóminus véniet.".to_string());
+        config.vespera = 1;
+        assert_eq!(magnificat_ant(&config, &mut files, "Latin").as_deref(), Some("Ecce Dóminus véniet."));
+    }
+
+    #[test]
+    fn test_canticum_doubling() {
+        let mut config = InputConfig::new(Hora::Laudes);
+        config.version = "Divino Afflatu - 1954".to_string();
+        config.dayofweek = 1;
+        config.daynames = vec!["Epi2-1".to_string()];
+        config.rank = 1.0;
+        assert_eq!(
+            canticum(&config, &mut psalterium(), "Latin"),
+            ["Ant. Benedíctus Dóminus", "&psalm(231)", "Ant. Benedíctus Dóminus Deus Israël."]
+        );
+        config.hora = Hora::Prima;
+        assert!(canticum(&config, &mut psalterium(), "Latin").is_empty());
+    }
+
+    #[test]
+    fn test_o_antiphon() {
+        let roman = RubricsVersion::parse("Divino Afflatu - 1954");
+        let dominican = RubricsVersion::parse("Ordo Praedicatorum - 1962");
+        assert_eq!(o_antiphon(&roman, 16, 12), None);
+        assert_eq!(o_antiphon(&roman, 17, 12), Some(OAntiphon::Sapientia));
+        assert_eq!(o_antiphon(&roman, 23, 12), Some(OAntiphon::Emmanuel));
+        assert_eq!(o_antiphon(&roman, 24, 12), None);
+        assert_eq!(o_antiphon(&dominican, 16, 12), Some(OAntiphon::Sapientia));
+        assert_eq!(o_antiphon(&dominican, 23, 12), Some(OAntiphon::VirgoVirginum));
+        assert_eq!(OAntiphon::Emmanuel.key(), "Adv Ant 23");
+    }
+
+    #[test]
+    fn test_canticum_o_antiphon() {
+        let mut config = InputConfig::new(Hora::Vespera);
+        config.version = "Divino Afflatu - 1954".to_string();
+        config.winner = "Tempora/Adv3-3.txt".to_string();
+        config.daynames = vec!["Adv3-3".to_string()];
+        config.day = 17;
+        config.month = 12;
+        config.rank = 2.0;
+        let lines = canticum(&config, &mut psalterium(), "Latin");
+        assert_eq!(lines[0], "/:Antiphona major:/");
+        // Doubled on a feria.
+        assert!(lines[1].starts_with("Ant. O Sapiéntia, * quæ ex ore"));
+        assert_eq!(lines[2], "&psalm(232)");
+
+        // At first Vespers of the Sunday the O antiphon is that of the evening.
+        config.day = 18;
+        config.vespera = 1;
+        assert!(magnificat_ant(&config, &mut psalterium(), "Latin").unwrap().starts_with("O Sapiéntia"));
+
+        // A feast keeps its own antiphon.
+        config.winner = "Sancti/12-21.txt".to_string();
+        config.winner_map.insert("Ant 1".to_string(), "Ne timeátis.".to_string());
+        assert_eq!(magnificat_ant(&config, &mut psalterium(), "Latin").as_deref(), Some("Ne timeátis."));
+    }
+}
@@ -5,493 +5,483 @@
  *
  * It defines three main functions:
  *
- *   - `capitulum_major(config: &InputConfig) -> Option<String>`
- *   - `monastic_major_responsory(config: &InputConfig) -> Option<String>`
- *   - `capitulum_minor(config: &InputConfig) -> Vec<String>`
+ *   - `capitulum_major(config, files, lang) -> Option<String>`
+ *   - `monastic_major_responsory(config, files, lang) -> Option<String>`
+ *   - `capitulum_minor(config, files, lang) -> (Vec<String>, TextSource)`
  *
  * In addition, it provides an HTML entry–point:
  *
- *   - `render_capitulis(config: InputConfig) -> HtmlString`
+ *   - `render_capitulis(config, files, lang) -> HtmlString`
  *
  * **Notable changes compared to the original Perl code:**
  *
  * - All globals (winner, vespera, seasonalflag, version, hora, label, and votive) are now
- *   bundled into a single configuration type.
+ *   read from the specials `InputConfig`.
  * - The helper function `attach_responsory_if_missing` now uses early returns to reduce nesting.
- * - New tests (using dummy implementations for external dependencies) exercise the behavior
- *   corresponding to the original Perl logic.
+ * - The tests exercise the behavior corresponding to the original Perl logic with
+ *   in-memory winner maps.
  */
 
- use std::collections::HashMap;
-
- //
- // --- External dependencies ---
- //
- // In production these functions are expected to be defined in their respective modules.
- // In tests, we override them with dummy implementations.
- #[cfg(not(test))]
- use crate::build::setbuild;
- #[cfg(test)]
- use self::dummy::*; // dummy implementations for testing
- 
- #[cfg(not(test))]
- use crate::proprium::getproprium;
- #[cfg(test)]
- use self::dummy::getproprium;
- 
- #[cfg(not(test))]
- use crate::setup_string::setupstring;
- #[cfg(test)]
- use self::dummy::setupstring;
- 
- #[cfg(not(test))]
- use crate::tempora::gettempora;
- #[cfg(test)]
- use self::dummy::gettempora;
- 
- #[cfg(not(test))]
- use crate::comment::setcomment;
- #[cfg(test)]
- use self::dummy::setcomment;
- 
- #[cfg(not(test))]
- use crate::postprocess::postprocess_short_resp;
- #[cfg(test)]
- use self::dummy::postprocess_short_resp;
- 
- //
- // --- Configuration Types ---
- //
- 
- /// Contains the global variables formerly obtained via functions.
- pub struct GlobalConfig {
-     pub winner: String,
-     pub vespera: i32,
-     pub seasonalflag: i32,
-     pub version: String,
-     pub hora: String,
-     pub label: String,
-     pub votive: String,
- }
- 
- /// Main input configuration for capitulus functions.
- pub struct InputConfig {
-     pub lang: String,
-     pub globals: GlobalConfig,
- }
- 
- /// A type alias for HTML strings.
- pub type HtmlString = String;
- 
- //
- // --- Helper Functions ---
- //
- 
- /// Attaches a responsory to the given text if one is not already present.
- ///
- /// The logic is as follows:
- ///
- /// 1. If no text was provided, return `None` immediately.
- /// 2. If the text already contains a responsory marker (`"\n_\nR.br"`), return it immediately.
- /// 3. Otherwise, look up a responsory using the key `"Responsory {hora}"`. If the version is
- ///    Monastic, append an `"M"` to the key.
- /// 4. If not found and the version is non–Monastic, try looking up `"Responsory Breve {hora}"`.
- /// 5. If still not found, use a replacement mapping (different for Monastic and non–Monastic).
- /// 6. If a responsory is found, attach it (separated by `\n_\n`) to the original text.
- ///
- /// # Arguments
- ///
- /// - `w`: The original capitulum text (if any).
- /// - `lang`: The language code.
- /// - `seasonalflag`: The seasonal flag.
- /// - `version`: The version string.
- /// - `hora`: The current hour.
- ///
- /// # Returns
- ///
- /// An updated version of the text with a responsory attached if possible.
- fn attach_responsory_if_missing(
-     w: Option<String>,
-     lang: &str,
-     seasonalflag: i32,
-     version: &str,
-     hora: &str,
- ) -> Option<String> {
-     // Early return if no text is provided.
-     let orig_text = w?;
-     // If the responsory marker is already present, return immediately.
-     if orig_text.contains("\n_\nR.br") {
-         return Some(orig_text);
-     }
- 
-     let mut new_name = format!("Responsory {}", hora);
-     if version.contains("Monastic") {
-         new_name.push('M');
-     }
-     let (mut wr, _cr) = getproprium(&new_name, lang, seasonalflag, 1);
- 
-     // For non–Monastic versions, try an alternate key.
-     if wr.is_none() && !version.contains("Monastic") {
-         let (wr_breve, _c_br) = getproprium(&format!("Responsory Breve {}", hora), lang, seasonalflag, 1);
-         wr = wr_breve;
-     }
- 
-     // If still missing, use a replacement mapping.
-     if wr.is_none() {
-         let replace = if !version.contains("Monastic") {
-             HashMap::from([
-                 ("Tertia", "Versum Tertia"),
-                 ("Sexta", "Versum Sexta"),
-                 ("Nona", "Versum Nona"),
-             ])
-         } else {
-             HashMap::from([
-                 ("Tertia", "Nocturn 1 Versum"),
-                 ("Sexta", "Nocturn 2 Versum"),
-                 ("Nona", "Nocturn 3 Versum"),
-             ])
-         };
-         if let Some(rep) = replace.get(hora) {
-             let (v_res, _cvers) = getproprium(rep, lang, seasonalflag, 1);
-             wr = v_res;
-         }
-     }
- 
-     if let Some(attached_text) = wr {
-         return Some(format!("{}\n_\n{}", orig_text, attached_text));
-     }
-     Some(orig_text)
- }
- 
- //
- // --- Main Functions ---
- //
- 
- /// Returns the major capitulum text for the given input configuration.
- ///
- /// The logic follows the original Perl:
- ///
- /// 1. Start with a default name `"Capitulum Laudes"`.
- /// 2. Change the name to `"Capitulum Vespera 1"` if `winner` contains `"12-25"` and `vespera == 1`.
- /// 3. Change the name to `"Capitulum Vespera"` if `winner` contains `"C12"` and `hora == "Vespera"`.
- /// 4. Call `setbuild` and try to get the proper text via `getproprium`.
- /// 5. If not found and the seasonal flag is false, try with the seasonal flag set to 1.
- /// 6. If still not found, load fallback text from `"Psalterium/Special/Major Special.txt"`.
- /// 7. Call `setcomment` using the provided label.
- /// 8. Return the found text (if any).
- pub fn capitulum_major(config: &InputConfig) -> Option<String> {
-     let globals = &config.globals;
-     let winner = &globals.winner;
-     let vespera = globals.vespera;
-     let seasonalflag = globals.seasonalflag;
-     let _version = &globals.version;
-     let hora = &globals.hora;
-     let label = &globals.label;
- 
-     // Default name.
-     let mut name = "Capitulum Laudes".to_string();
- 
-     // Special cases.
-     if winner.contains("12-25") && vespera == 1 {
-         name = "Capitulum Vespera 1".to_string();
-     }
-     if winner.contains("C12") && hora == "Vespera" {
-         name = "Capitulum Vespera".to_string();
-     }
- 
-     // Set build info.
-     setbuild("Psalterium/Special/Major Special", &name, "Capitulum ord");
- 
-     // Attempt to retrieve the proper text.
-     let (mut capit, mut c) = getproprium(&name, &config.lang, seasonalflag, 1);
-     if capit.is_none() && seasonalflag == 0 {
-         let (cap_alt, c_alt) = getproprium(&name, &config.lang, 1, 1);
-         capit = capit.or(cap_alt);
-         c = c.or(c_alt);
-     }
-     if capit.is_none() {
-         if let Some(cap_map) = setupstring(&config.lang, "Psalterium/Special/Major Special.txt") {
-             let key = format!("{} {}", gettempora("Capitulum major"), hora);
-             capit = cap_map.get(&key).cloned();
-         }
-     }
-     setcomment(label, "Source", c, &config.lang);
-     capit
- }
- 
- /// Returns the monastic major responsory as a single string for the given input configuration.
- ///
- /// The logic follows the original Perl:
- ///
- /// 1. Construct a key `"Responsory {hora}"`, appending `" 1"` if `winner` contains `"12-25"` and `vespera == 1`.
- /// 2. Attempt to retrieve the responsory via `getproprium`.
- /// 3. If not found, adjust the key (first replacing `"Vespera"` with `"Breve Sexta"` and `"Laudes"` with `"Breve Tertia"`,
- ///    then removing `"Breve "`).
- /// 4. If still missing, load fallback text from `"Psalterium/Special/Major Special.txt"`.
- /// 5. Remove any attached versicle (truncate at `"\n_"`), postprocess the lines, and (if needed) remove any substring starting with `"&gloria"`.
- pub fn monastic_major_responsory(config: &InputConfig) -> Option<String> {
-     let globals = &config.globals;
-     let winner = &globals.winner;
-     let vespera = globals.vespera;
-     let seasonalflag = globals.seasonalflag;
-     let version = &globals.version;
-     let hora = &globals.hora;
- 
-     // Construct key.
-     let mut key = format!("Responsory {}", hora);
-     if winner.contains("12-25") && vespera == 1 {
-         key.push_str(" 1");
-     }
-     let (mut resp, mut c) = getproprium(&key, &config.lang, seasonalflag, 1);
- 
-     // Try adjusted keys if not found.
-     if resp.is_none() {
-         let key_sub = key.replace("Vespera", "Breve Sexta")
-                          .replace("Laudes", "Breve Tertia");
-         let res = getproprium(&key_sub, &config.lang, seasonalflag, 1);
-         if res.0.is_some() {
-             resp = res.0;
-             c = res.1;
-         }
-     }
-     if resp.is_none() {
-         let key_no_breve = key.replace("Breve ", "");
-         let res = getproprium(&key_no_breve, &config.lang, seasonalflag, 1);
-         if res.0.is_some() {
-             resp = res.0;
-             c = res.1;
-         }
-     }
-     if resp.is_none() {
-         if let Some(resp_map) = setupstring(&config.lang, "Psalterium/Special/Major Special.txt") {
-             let key2 = format!("Responsory {} {}", gettempora("Capitulum major"), hora);
-             resp = resp_map.get(&key2).cloned();
-         }
-     }
-     // Remove any attached versicle.
-     if let Some(mut r) = resp {
-         if let Some(pos) = r.find("\n_") {
-             r.truncate(pos);
-         }
-         let mut lines: Vec<String> = r.lines().map(|s| s.to_string()).collect();
-         postprocess_short_resp(&mut lines, &config.lang);
-         r = lines.join("\n");
-         if version.to_lowercase().contains("cist") {
-             if let Some(pos) = r.to_lowercase().find("&gloria") {
-                 r.truncate(pos);
-             }
-         }
-         resp = Some(r);
-     }
-     resp
- }
- 
- /// Returns the minor capitulum as a vector of strings (split by newline) for the given input configuration.
- ///
- /// The logic follows the original Perl:
- ///
- /// 1. Load the “Minor Special” data from `"Psalterium/Special/Minor Special.txt"`.
- /// 2. Construct a key from `gettempora("Capitulum minor")` and the current hour (with a special case for `"Completorium"`).
- /// 3. Check for responsory keys and append them if present.
- /// 4. For `"Completorium"`, if the version does not start with `"Ordo Praedicatorum"`, append `"Versum 4"`.
- /// 5. Otherwise, set a comment value, call `setbuild`, and try to retrieve a responsory via `getproprium`.
- ///    (Here the responsory–attaching logic is factored out.)
- /// 6. Finally, postprocess the text and (if applicable) call `setcomment`.
- pub fn capitulum_minor(config: &InputConfig) -> Vec<String> {
-     let globals = &config.globals;
-     let hora = &globals.hora;
-     let version = &globals.version;
-     let seasonalflag = globals.seasonalflag;
-     let votive = &globals.votive;
- 
-     let mut capit_map = match setupstring(&config.lang, "Psalterium/Special/Minor Special.txt") {
-         Some(m) => m,
-         None => return Vec::new(),
-     };
-     let mut name = format!("{} {}", gettempora("Capitulum minor"), hora);
-     if hora == "Completorium" {
-         name = "Completorium".to_string();
-     }
-     let mut capit = capit_map
-         .get(&name)
-         .map(|s| s.trim_end().to_string())
-         .unwrap_or_default();
- 
-     let mut resp: Option<String> = None;
-     let mut comment: Option<i32> = None;
- 
-     if version.contains("Monastic") {
-         name.push('M');
-     }
- 
-     if let Some(r) = capit_map.get(&format!("Responsory {}", name)) {
-         let trimmed = r.trim_end().to_string();
-         capit.push_str(&format!("\n_\n{}", trimmed));
-         resp = Some(trimmed);
-     } else if let (Some(r), Some(_v)) = (
-         capit_map.get(&format!("Responsory breve {}", name)),
-         capit_map.get(&format!("Versum {}", name)),
-     ) {
-         let trimmed_r = r.trim_end().to_string();
-         let combined = format!("{}", trimmed_r);
-         capit.push_str(&format!("\n_\n{}", combined));
-         resp = Some(combined);
-     }
- 
-     if hora == "Completorium" && !version.starts_with("Ordo Praedicatorum") {
-         if let Some(v4) = capit_map.get("Versum 4") {
-             capit.push_str(&format!("\n_\n{}", v4));
-         }
-     } else {
-         // Set comment value.
-         comment = if name.contains("Dominica") || name.contains("Feria") {
-             Some(5)
-         } else {
-             Some(1)
-         };
-         setbuild("Psalterium/Special/Minor Special", &name, "Capitulum ord");
- 
-         let mut key = format!("Capitulum {}", hora);
-         if hora == "Tertia" && !votive.contains("C12") {
-             key = key.replace("Tertia", "Laudes");
-         }
-         let (mut w, c_val) = getproprium(&key, &config.lang, seasonalflag, 1);
-         if w.is_some() {
-             // Factor out the responsory–attaching branch.
-             w = attach_responsory_if_missing(w, &config.lang, seasonalflag, version, hora);
-             resp = w.clone();
-         }
-         if let Some(w_str) = w {
-             capit = w_str;
-             comment = c_val.and_then(|s| s.parse::<i32>().ok());
-         }
-     }
- 
-     let mut lines: Vec<String> = capit.lines().map(|s| s.to_string()).collect();
-     postprocess_short_resp(&mut lines, &config.lang);
-     if hora != "Completorium" {
-         setcomment(&globals.label, "Source", comment.map(|i| i.to_string()), &config.lang);
-     }
-     lines
- }
- 
- /// The entry–point function that takes an `InputConfig` and returns an HTML–formatted string.
- ///
- /// It calls the three main routines (for major, responsory, and minor texts) and then
- /// combines their output into a single HTML string.
- pub fn render_capitulis(config: InputConfig) -> HtmlString {
-     let mut html = String::new();
- 
-     html.push_str("<div class=\"capitulis\">\n");
- 
-     if let Some(major_text) = capitulum_major(&config) {
-         html.push_str("  <div class=\"capitulum-major\">\n");
-         html.push_str(&major_text);
-         html.push_str("\n  </div>\n");
-     }
- 
-     if let Some(resp_text) = monastic_major_responsory(&config) {
-         html.push_str("  <div class=\"monastic-major-responsory\">\n");
-         html.push_str(&resp_text);
-         html.push_str("\n  </div>\n");
-     }
- 
-     let minor_lines = capitulum_minor(&config);
-     if !minor_lines.is_empty() {
-         html.push_str("  <div class=\"capitulum-minor\">\n    <pre>\n");
-         html.push_str(&minor_lines.join("\n"));
-         html.push_str("\n    </pre>\n  </div>\n");
-     }
- 
-     html.push_str("</div>");
-     html
- }
-
- #[cfg(test)]
- mod tests {
-     use super::*;
- 
-     /// Helper to construct a full InputConfig with all globals.
-     fn make_config(
-         winner: &str,
-         vespera: i32,
-         seasonalflag: i32,
-         version: &str,
-         hora: &str,
-         label: &str,
-         votive: &str,
-     ) -> InputConfig {
-         InputConfig {
-             lang: "en".to_string(),
-             globals: GlobalConfig {
-                 winner: winner.to_string(),
-                 vespera,
-                 seasonalflag,
-                 version: version.to_string(),
-                 hora: hora.to_string(),
-                 label: label.to_string(),
-                 votive: votive.to_string(),
-             },
-         }
-     }
- 
-     #[test]
-     fn test_capitulum_major_12_25() {
-         // When winner contains "12-25" and vespera is 1, we expect the name to be "Capitulum Vespera 1".
-         let config = make_config("Celebration on 12-25", 1, 0, "TestVersion", "Laudes", "TestLabel", "SomeVotive");
-         let result = capitulum_major(&config).unwrap();
-         // Dummy getproprium returns text including the key.
-         assert!(result.contains("Dummy text for Capitulum Vespera 1"),
-                 "Expected text to contain key 'Capitulum Vespera 1'");
-     }
- 
-     #[test]
-     fn test_capitulum_major_C12() {
-         // When winner contains "C12" and hora is "Vespera", expect the name to be "Capitulum Vespera".
-         let config = make_config("Event C12 Special", 0, 0, "TestVersion", "Vespera", "TestLabel", "SomeVotive");
-         let result = capitulum_major(&config).unwrap();
-         assert!(result.contains("Dummy text for Capitulum Vespera"),
-                 "Expected text to contain key 'Capitulum Vespera'");
-     }
- 
-     #[test]
-     fn test_monastic_major_responsory_adjustments() {
-         // For monastic responsory, even if the first lookup fails, the key adjustments should
-         // result in a dummy text being returned.
-         let config = make_config("Normal", 0, 0, "TestVersion", "Laudes", "TestLabel", "SomeVotive");
-         let result = monastic_major_responsory(&config).unwrap();
-         // Since dummy getproprium always returns text with the key, check that the returned text
-         // mentions either "Responsory Laudes" or an adjusted key.
-         assert!(result.contains("Dummy text for Responsory Laudes")
-             || result.contains("Dummy text for Responsory Breve Laudes"),
-             "Responsory text was not properly constructed.");
-     }
- 
-     #[test]
-     fn test_capitulum_minor_responsory_attachment() {
-         // For a non-Completorium hour, if getproprium returns a value,
-         // then attach_responsory_if_missing should add a responsory.
-         let config = make_config("Normal", 0, 0, "TestVersion", "Tertia", "TestLabel", "No C12 here");
-         let result_lines = capitulum_minor(&config);
-         let result = result_lines.join("\n");
-         // Our dummy getproprium will return a string containing "Dummy text for Capitulum Laudes"
-         // (since for Tertia with no C12, key "Tertia" is replaced by "Laudes").
-         assert!(result.contains("Dummy text for Capitulum Laudes")
-             || result.contains("Dummy text for Responsory Tertia")
-             || result.contains("Dummy text for Responsory Breve Tertia"),
-             "Expected minor capitulum text to include attached responsory.");
-     }
- 
-     #[test]
-     fn test_render_capitulis_html() {
-         // Integration test for render_capitulis.
-         let config = make_config("Normal", 0, 0, "TestVersion", "Laudes", "TestLabel", "SomeVotive");
-         let html = render_capitulis(config);
-         assert!(html.contains("<div class=\"capitulis\">"));
-         assert!(html.contains("<div class=\"capitulum-major\">"));
-         // At least one of the sections should be present.
-         assert!(html.contains("<div class=\"monastic-major-responsory\">")
-             || html.contains("<div class=\"capitulum-minor\">"));
-     }
- }
- 
\ No newline at end of file
+use std::collections::HashMap;
+
+use super::{getproprium, gettempora, responsoria, setcomment, Comment, InputConfig, TextSource};
+use crate::horas::postprocess::postprocess_short_resp;
+use crate::horas::Hora;
+use crate::regex::ci_find;
+use crate::setup_string::{ResolveDirectives, SetupStringProvider};
+
+/// A type alias for HTML strings.
+#[allow(dead_code)]
+pub type HtmlString = String;
+
+//
+// --- Helper Functions ---
+//
+
+/// Attaches a responsory to the given text if one is not already present.
+///
+/// The logic is as follows:
+///
+/// 1. If no text was provided, return `None` immediately.
+/// 2. If the text already contains a responsory marker (`"\n_\nR.br"`), return it immediately.
+/// 3. Otherwise, look up a responsory using the key `"Responsory {hora}"`. If the version is
+///    Monastic, append an `"M"` to the key.
+/// 4. If not found and the version is non–Monastic, try looking up `"Responsory Breve {hora}"`.
+/// 5. If still not found, use a replacement mapping (different for Monastic and non–Monastic).
+/// 6. If a responsory is found, attach it (separated by `\n_\n`) to the original text.
+fn attach_responsory_if_missing(config: &InputConfig, w: Option<String>) -> Option<String> {
+    // Early return if no text is provided.
+    let orig_text = w?;
+    // If the responsory marker is already present, return immediately.
+    if orig_text.contains("\n_\nR.br") {
+        return Some(orig_text);
+    }
+    let hora = config.hora.as_str();
+    let monastic = config.version.contains("Monastic");
+    let seasonalflag = config.seasonalflag;
+
+    let mut new_name = format!("Responsory {}", hora);
+    if monastic {
+        new_name.push('M');
+    }
+    let (mut wr, _cr) = getproprium(config, &new_name, seasonalflag, true);
+
+    // For non–Monastic versions, try an alternate key.
+    if wr.is_none() && !monastic {
+        let (wr_breve, _c_br) = getproprium(config, &format!("Responsory Breve {}", hora), seasonalflag, true);
+        wr = wr_breve;
+    }
+
+    // If still missing, use a replacement mapping.
+    if wr.is_none() {
+        let replace = if !monastic {
+            HashMap::from([
+                ("Tertia", "Versum Tertia"),
+                ("Sexta", "Versum Sexta"),
+                ("Nona", "Versum Nona"),
+            ])
+        } else {
+            HashMap::from([
+                ("Tertia", "Nocturn 1 Versum"),
+                ("Sexta", "Nocturn 2 Versum"),
+                ("Nona", "Nocturn 3 Versum"),
+            ])
+        };
+        if let Some(rep) = replace.get(hora) {
+            let (v_res, _cvers) = getproprium(config, rep, seasonalflag, true);
+            wr = v_res;
+        }
+    }
+
+    if let Some(attached_text) = wr {
+        return Some(format!("{}\n_\n{}", orig_text, attached_text));
+    }
+    Some(orig_text)
+}
+
+//
+// --- Main Functions ---
+//
+
+/// Returns the major capitulum text, headed by its label, for the given input configuration.
+///
+/// The logic follows the original Perl:
+///
+/// 1. Start with a default name `"Capitulum Laudes"`.
+/// 2. Change the name to `"Capitulum Vespera 1"` if `winner` contains `"12-25"` and `vespera == 1`.
+/// 3. Change the name to `"Capitulum Vespera"` if `winner` contains `"C12"` and `hora == "Vespera"`.
+/// 4. Call `setbuild` and try to get the proper text via `getproprium`.
+/// 5. If not found and the seasonal flag is false, try with the seasonal flag set.
+/// 6. If still not found, load fallback text from `"Psalterium/Special/Major Special.txt"`.
+/// 7. Prefix the heading built by `setcomment` from the current label.
+/// 8. Return the found text (if any).
+pub fn capitulum_major(
+    config: &InputConfig,
+    files: &mut dyn SetupStringProvider,
+    lang: &str,
+) -> Option<String> {
+    let winner = &config.winner;
+    let hora = config.hora;
+
+    // Default name.
+    let mut name = "Capitulum Laudes".to_string();
+
+    // Special cases.
+    if winner.contains("12-25") && config.vespera == 1 {
+        name = "Capitulum Vespera 1".to_string();
+    }
+    if winner.contains("C12") && hora == Hora::Vespera {
+        name = "Capitulum Vespera".to_string();
+    }
+
+    // Set build info.
+    config.build.setbuild("Psalterium/Special/Major Special", &name, "Capitulum ord");
+
+    // Attempt to retrieve the proper text.
+    let (mut capit, mut c) = getproprium(config, &name, config.seasonalflag, true);
+    if capit.is_none() && !config.seasonalflag {
+        (capit, c) = getproprium(config, &name, true, true);
+    }
+    if capit.is_none() {
+        if let Some(cap_map) =
+            files.setupstring(lang, "Psalterium/Special/Major Special.txt", ResolveDirectives::All)
+        {
+            let key = format!("{} {}", gettempora(config, "Capitulum major"), hora);
+            capit = cap_map.get(&key).cloned();
+        }
+    }
+    // The monastic office has a short responsory after the chapter.
+    if config.rubrics().monastic {
+        if let (Some(text), Some(resp)) = (capit.as_mut(), monastic_major_responsory(config, files, lang)) {
+            *text = format!("{}\n_\n{}", text.trim_end(), resp);
+        }
+    }
+    let heading = setcomment(config, files, &config.label, Some(Comment::Source(c)), lang, "");
+    capit.map(|text| format!("{}\n{}", heading, text.trim_end()))
+}
+
+/// Returns the monastic major responsory as a single string for the given input configuration.
+///
+/// The logic follows the original Perl:
+///
+/// 1. Construct a key `"Responsory {hora}"`, appending `" 1"` if `winner` contains `"12-25"` and `vespera == 1`.
+/// 2. Attempt to retrieve the responsory via `getproprium`.
+/// 3. If not found, adjust the key (first replacing `"Vespera"` with `"Breve Sexta"` and `"Laudes"` with `"Breve Tertia"`,
+///    then removing `"Breve "`).
+/// 4. If still missing, load fallback text from `"Psalterium/Special/Major Special.txt"`.
+/// 5. Remove any attached versicle (truncate at `"\n_"`), postprocess the lines, and (if needed) remove any substring starting with `"&gloria"`.
+pub fn monastic_major_responsory(
+    config: &InputConfig,
+    files: &mut dyn SetupStringProvider,
+    lang: &str,
+) -> Option<String> {
+    let hora = config.hora;
+    let seasonalflag = config.seasonalflag;
+
+    // Construct key.
+    let mut key = format!("Responsory {}", hora);
+    if config.winner.contains("12-25") && config.vespera == 1 {
+        key.push_str(" 1");
+    }
+    let (mut resp, _c) = getproprium(config, &key, seasonalflag, true);
+
+    // Try adjusted keys if not found.
+    if resp.is_none() {
+        let key_sub = key.replace("Vespera", "Breve Sexta").replace("Laudes", "Breve Tertia");
+        resp = getproprium(config, &key_sub, seasonalflag, true).0;
+        if resp.is_none() {
+            let key_no_breve = key_sub.replace("Breve ", "");
+            resp = getproprium(config, &key_no_breve, seasonalflag, true).0;
+        }
+    }
+    if resp.is_none() {
+        if let Some(resp_map) =
+            files.setupstring(lang, "Psalterium/Special/Major Special.txt", ResolveDirectives::All)
+        {
+            let key2 = format!("Responsory {} {}", gettempora(config, "Capitulum major"), hora);
+            resp = resp_map.get(&key2).cloned();
+        }
+    }
+    // Remove any attached versicle.
+    let mut r = resp?;
+    if let Some(pos) = r.find("\n_") {
+        r.truncate(pos);
+    }
+    let mut lines: Vec<String> = r.lines().map(|s| s.to_string()).collect();
+    postprocess_short_resp(&mut lines, config.dayname0(), &config.votive, files, lang);
+    r = lines.join("\n");
+    if config.rubrics().is_cistercian() {
+        if let Some(pos) = ci_find(&r, "&gloria") {
+            r.truncate(pos);
+        }
+    }
+    Some(r)
+}
+
+/// Returns the key of the little chapter of the hour in the Minor Special of
+/// the Psalterium: the season ("Adv Tertia", "Quad5 Sexta", ...), or outside
+/// the seasons "Dominica" on Sundays and "Feria" on the other days.
+///
+/// The office of a Sunday said on another day (an anticipated or resumed
+/// Sunday of the Proper of Time) keeps the Sunday chapters.
+fn minor_special_name(config: &InputConfig) -> String {
+    if config.hora == Hora::Completorium {
+        return "Completorium".to_string();
+    }
+    let mut tempora = gettempora(config, "Capitulum minor");
+    if tempora == "Feria" && is_sunday_office(&config.winner) {
+        tempora = "Dominica".to_string();
+    }
+    format!("{} {}", tempora, config.hora)
+}
+
+/// Whether `winner` is the office of a Sunday of the Proper of Time, e.g.
+/// "Tempora/Pent03-0.txt".
+fn is_sunday_office(winner: &str) -> bool {
+    winner.contains("Tempora")
+        && winner.trim_end_matches(".txt").rsplit('/').next().is_some_and(|stem| stem.ends_with("-0"))
+}
+
+/// Returns the minor capitulum as a vector of strings (split by newline) for the given
+/// input configuration, with where the chapter was taken from.
+///
+/// The logic follows the original Perl:
+///
+/// 1. Load the “Minor Special” data from `"Psalterium/Special/Minor Special.txt"`.
+/// 2. Construct a key from `gettempora("Capitulum minor")` and the current hour (see
+///    `minor_special_name()`).
+/// 3. Check for responsory keys and append them if present.
+/// 4. For `"Completorium"`, if the version does not start with `"Ordo Praedicatorum"`, append `"Versum 4"`.
+/// 5. Otherwise, set a comment value, call `setbuild`, and try to retrieve the chapter of the
+///    office (proper or commune) via `getproprium`, with its responsory.
+/// 6. Finally, postprocess the text and (except at Completorium) head it with `setcomment`.
+///
+/// The source is `Season` or `PerAnnum` for the chapters of the Psalterium (`Psalterium`
+/// at Completorium), and the one given by `getproprium()` for the chapters of the office.
+pub fn capitulum_minor(
+    config: &InputConfig,
+    files: &mut dyn SetupStringProvider,
+    lang: &str,
+) -> (Vec<String>, TextSource) {
+    let hora = config.hora;
+    let version = &config.version;
+
+    let capit_map = files
+        .setupstring(lang, "Psalterium/Special/Minor Special.txt", ResolveDirectives::All)
+        .unwrap_or_default();
+    let mut name = minor_special_name(config);
+    let mut capit = capit_map
+        .get(&name)
+        .map(|s| s.trim_end().to_string())
+        .unwrap_or_default();
+
+    if version.contains("Monastic") {
+        name.push('M');
+    }
+
+    if let Some(r) = capit_map.get(&format!("Responsory {}", name)) {
+        capit.push_str(&format!("\n_\n{}", r.trim_end()));
+    } else if let (Some(r), Some(v)) = (
+        capit_map.get(&format!("Responsory breve {}", name)),
+        capit_map.get(&format!("Versum {}", name)),
+    ) {
+        capit.push_str(&format!("\n_\n{}\n_\n{}", r.trim_end(), v.trim_end()));
+    }
+
+    let mut source = TextSource::Psalterium;
+    if hora == Hora::Completorium && !version.starts_with("Ordo Praedicatorum") {
+        if let Some(v4) = capit_map.get("Versum 4") {
+            capit.push_str(&format!("\n_\n{}", v4.trim_end()));
+        }
+    } else {
+        source = if name.contains("Dominica") || name.contains("Feria") {
+            TextSource::PerAnnum
+        } else {
+            TextSource::Season
+        };
+        config.build.setbuild("Psalterium/Special/Minor Special", &name, "Capitulum ord");
+
+        let mut key = format!("Capitulum {}", hora);
+        if hora == Hora::Tertia && !config.votive.contains("C12") {
+            key = key.replace("Tertia", "Laudes");
+        }
+        let (w, c_val) = getproprium(config, &key, config.seasonalflag, true);
+        // Factor out the responsory–attaching branch.
+        if let Some(w_str) = attach_responsory_if_missing(config, w) {
+            capit = w_str;
+            source = c_val;
+        }
+    }
+
+    let mut lines: Vec<String> = capit.lines().map(|s| s.to_string()).collect();
+    responsoria::postprocess_responsory(&mut lines, config, files, lang);
+    if hora != Hora::Completorium {
+        lines.insert(0, setcomment(config, files, &config.label, Some(Comment::Source(source)), lang, ""));
+    }
+    (lines, source)
+}
+
+/// The entry–point function that takes an `InputConfig` and returns an HTML–formatted string.
+///
+/// It calls the three main routines (for major, responsory, and minor texts) and then
+/// combines their output into a single HTML string.
+#[allow(dead_code)]
+pub fn render_capitulis(
+    config: &InputConfig,
+    files: &mut dyn SetupStringProvider,
+    lang: &str,
+) -> HtmlString {
+    let mut html = String::new();
+
+    html.push_str("<div class=\"capitulis\">\n");
+
+    if let Some(major_text) = capitulum_major(config, files, lang) {
+        html.push_str("  <div class=\"capitulum-major\">\n");
+        html.push_str(&major_text);
+        html.push_str("\n  </div>\n");
+    }
+
+    if let Some(resp_text) = monastic_major_responsory(config, files, lang) {
+        html.push_str("  <div class=\"monastic-major-responsory\">\n");
+        html.push_str(&resp_text);
+        html.push_str("\n  </div>\n");
+    }
+
+    let (minor_lines, _source) = capitulum_minor(config, files, lang);
+    if !minor_lines.is_empty() {
+        html.push_str("  <div class=\"capitulum-minor\">\n    <pre>\n");
+        html.push_str(&minor_lines.join("\n"));
+        html.push_str("\n    </pre>\n  </div>\n");
+    }
+
+    html.push_str("</div>");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::horas::specials::tests::TestFiles;
+
+    /// Helper to construct an InputConfig whose winner has the given proper sections;
+    /// each section's text is "Dummy text for {key}".
+    fn make_config(winner: &str, vespera: i32, hora: Hora, votive: &str, keys: &[&str]) -> InputConfig {
+        let mut config = InputConfig::new(hora);
+        config.winner = winner.to_string();
+        config.vespera = vespera;
+        config.version = "TestVersion".to_string();
+        config.label = "#Capitulum".to_string();
+        config.votive = votive.to_string();
+        for key in keys {
+            config.winner_map.insert(key.to_string(), format!("Dummy text for {}", key));
+        }
+        config
+    }
+
+    #[test]
+    fn test_capitulum_major_12_25() {
+        // When winner contains "12-25" and vespera is 1, we expect the name to be "Capitulum Vespera 1".
+        let config = make_config("Sancti/12-25", 1, Hora::Vespera, "", &["Capitulum Laudes", "Capitulum Vespera 1"]);
+        let result = capitulum_major(&config, &mut TestFiles::default(), "Latin").unwrap();
+        assert!(result.contains("Dummy text for Capitulum Vespera 1"),
+                "Expected text to contain key 'Capitulum Vespera 1'");
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn test_capitulum_major_C12() {
+        // When winner contains "C12" and hora is "Vespera", expect the name to be "Capitulum Vespera".
+        let config = make_config("Commune/C12", 0, Hora::Vespera, "", &["Capitulum Laudes", "Capitulum Vespera"]);
+        let result = capitulum_major(&config, &mut TestFiles::default(), "Latin").unwrap();
+        assert!(result.contains("Dummy text for Capitulum Vespera"),
+                "Expected text to contain key 'Capitulum Vespera'");
+        assert!(!result.contains("Capitulum Laudes"));
+    }
+
+    #[test]
+    fn test_capitulum_major_psalterium_fallback() {
+        let config = make_config("Tempora/Pent05-2", 3, Hora::Vespera, "", &[]);
+        let mut config = config;
+        config.dayofweek = 2;
+        let mut files = TestFiles::default().with(
+            "Latin",
+            "Psalterium/Special/Major Special.txt",
+            &[("Day2 Vespera", "!2 Cor 1:3-4\nBenedíctus Deus.")],
+        );
+        let result = capitulum_major(&config, &mut files, "Latin").unwrap();
+        assert!(result.ends_with("!2 Cor 1:3-4\nBenedíctus Deus."));
+    }
+
+    #[test]
+    fn test_monastic_major_responsory_adjustments() {
+        // If the first lookup fails, the key adjustments should find the responsory
+        // of Tertia for Lauds.
+        let config = make_config("Normal", 0, Hora::Laudes, "", &["Responsory Tertia"]);
+        let result = monastic_major_responsory(&config, &mut TestFiles::default(), "Latin").unwrap();
+        assert_eq!(result, "Dummy text for Responsory Tertia");
+    }
+
+    #[test]
+    fn test_capitulum_minor_responsory_attachment() {
+        // For a non-Completorium hour, if getproprium returns a value,
+        // then attach_responsory_if_missing should add a responsory.
+        let config = make_config("Normal", 0, Hora::Tertia, "", &["Capitulum Laudes", "Responsory Tertia"]);
+        let (lines, source) = capitulum_minor(&config, &mut TestFiles::default(), "Latin");
+        let result = lines.join("\n");
+        assert_eq!(source, TextSource::Tempora);
+        // For Tertia with no C12, key "Tertia" is replaced by "Laudes".
+        assert!(result.contains("Dummy text for Capitulum Laudes\n_\nDummy text for Responsory Tertia"),
+            "Expected minor capitulum text to include attached responsory.");
+    }
+
+    #[test]
+    fn test_render_capitulis_html() {
+        // Integration test for render_capitulis.
+        let config = make_config("Normal", 0, Hora::Laudes, "", &["Capitulum Laudes", "Responsory Laudes"]);
+        let html = render_capitulis(&config, &mut TestFiles::default(), "Latin");
+        assert!(html.contains("<div class=\"capitulis\">"));
+        assert!(html.contains("<div class=\"capitulum-major\">"));
+        // At least one of the sections should be present.
+        assert!(html.contains("<div class=\"monastic-major-responsory\">")
+            || html.contains("<div class=\"capitulum-minor\">"));
+    }
+
+    #[test]
+    fn test_capitulum_minor_psalterium_selection() {
+        let mut files = TestFiles::default().with(
+            "Latin",
+            "Psalterium/Special/Minor Special.txt",
+            &[
+                ("Dominica Sexta", "!Gal 6:2\nAlter alteríus ónera portáte."),
+                ("Feria Sexta", "!Rom 13:8\nNémini quidquam debeátis."),
+                ("Adv Sexta", "!Jer 23:6\nIn diébus illis salvábitur Juda."),
+            ],
+        );
+        let mut config = make_config("Tempora/Pent03-2.txt", 3, Hora::Sexta, "", &[]);
+        config.daynames = vec!["Pent03".to_string()];
+        config.dayofweek = 2;
+        let (lines, source) = capitulum_minor(&config, &mut files, "Latin");
+        assert_eq!(source, TextSource::PerAnnum);
+        assert!(lines.join("\n").contains("Némini quidquam"));
+
+        // A Sunday office said on a weekday keeps the Sunday chapter.
+        config.winner = "Tempora/Pent03-0.txt".to_string();
+        let (lines, source) = capitulum_minor(&config, &mut files, "Latin");
+        assert_eq!(source, TextSource::PerAnnum);
+        assert!(lines.join("\n").contains("Alter alteríus"));
+
+        config.daynames = vec!["Adv2".to_string()];
+        config.winner = "Tempora/Adv2-2.txt".to_string();
+        let (lines, source) = capitulum_minor(&config, &mut files, "Latin");
+        assert_eq!(source, TextSource::Season);
+        assert!(lines.join("\n").contains("salvábitur Juda"));
+    }
+
+    #[test]
+    fn test_is_sunday_office() {
+        assert!(is_sunday_office("Tempora/Pent03-0.txt"));
+        assert!(is_sunday_office("Tempora/Epi4-0"));
+        assert!(!is_sunday_office("Tempora/Pent03-1.txt"));
+        assert!(!is_sunday_office("Sancti/10-10.txt"));
+    }
+}
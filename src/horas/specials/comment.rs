@@ -0,0 +1,154 @@
+//! comment.rs
+//!
+//! The comments of the chapter headings, from `setcomment()` in
+//! `/horas/specials.pl`: a heading such as `#Psalmi` gets, in braces, a line
+//! of `Psalterium/Comment.txt` saying where its text comes from
+//! (`#Psalmi {Antiphonæ et Psalmi de Psalterio}`), or that the preces or the
+//! suffragium are said or omitted.
+//!
+//! The Perl code passes the line of the comment file as a bare number; here
+//! the callers name it with a `Comment`, and every comment put on a heading
+//! is recorded in the `InputConfig` as an `Annotation`, so that a front end
+//! can show the sources of the hour apart from the text.
+
+use super::{translate, InputConfig};
+use crate::setup_string::{ResolveDirectives, SetupStringProvider};
+
+/// Where the text of a chapter comes from: the lines of the `Source`
+/// section of `Psalterium/Comment.txt`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TextSource {
+    /// The Psalterium of the day of the week.
+    Psalterium,
+    /// The seasonal part of the Psalterium.
+    Season,
+    /// The Proper of Time.
+    Tempora,
+    /// The Proper of Saints.
+    Sancti,
+    /// The commune of the office.
+    Commune,
+    /// The Psalterium of the Sundays and ferias "per annum".
+    PerAnnum,
+    /// The Sunday psalms, said on a feast.
+    PsalmiDominica,
+}
+
+impl TextSource {
+    /// The line of the source in the `Source` section of the comment file.
+    pub fn line(self) -> usize {
+        match self {
+            TextSource::Psalterium => 0,
+            TextSource::Season => 1,
+            TextSource::Tempora => 2,
+            TextSource::Sancti => 3,
+            TextSource::Commune => 4,
+            TextSource::PerAnnum => 5,
+            TextSource::PsalmiDominica => 6,
+        }
+    }
+}
+
+/// The comment put on a chapter heading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Comment {
+    /// Where the text of the chapter comes from.
+    Source(TextSource),
+    /// Whether the preces are said or omitted.
+    Preces { omitted: bool },
+    /// Which suffragium is said, the line of the `Suffragium` section.
+    Suffragium(usize),
+}
+
+impl Comment {
+    /// The section and the line of the comment in `Psalterium/Comment.txt`.
+    pub fn position(self) -> (&'static str, usize) {
+        match self {
+            Comment::Source(source) => ("Source", source.line()),
+            Comment::Preces { omitted } => ("Preces", usize::from(omitted)),
+            Comment::Suffragium(line) => ("Suffragium", line),
+        }
+    }
+}
+
+/// A comment put on a heading while the hour was built.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Annotation {
+    /// The heading, as in the Ordinarium (e.g. "#Psalmi").
+    pub label: String,
+    pub comment: Comment,
+    /// The text of the comment in the language of the column.
+    pub text: String,
+}
+
+/// Returns the heading `label` with `comment` from `Psalterium/Comment.txt`
+/// appended in braces, e.g. `#Psalmi {Antiphonæ et Psalmi de Psalterio}`,
+/// after `prefix`. No comment (or a missing line) leaves the heading without
+/// one, unless there is a prefix.
+///
+/// The comment is recorded among the annotations of `config`.
+pub fn setcomment(
+    config: &InputConfig,
+    files: &mut dyn SetupStringProvider,
+    label: &str,
+    comment: Option<Comment>,
+    lang: &str,
+    prefix: &str,
+) -> String {
+    let heading = translate(label, lang);
+    let Some(comment) = comment else {
+        return heading;
+    };
+    let (section, line) = comment.position();
+    let text = files
+        .setupstring(lang, "Psalterium/Comment.txt", ResolveDirectives::None)
+        .and_then(|comm| comm.get(section).cloned())
+        .and_then(|text| text.lines().nth(line).map(|l| l.trim().to_string()))
+        .unwrap_or_default();
+    if !text.is_empty() {
+        config.annotations.borrow_mut().push(Annotation { label: label.to_string(), comment, text: text.clone() });
+    }
+    if text.is_empty() && prefix.is_empty() {
+        return heading;
+    }
+    let text = format!("{}{}", prefix, text);
+    match heading.rfind('}') {
+        Some(pos) => format!("{} {}{}", &heading[..pos], text.trim(), &heading[pos..]),
+        None => format!("{} {{{}}}", heading, text.trim()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::horas::specials::tests::TestFiles;
+    use crate::horas::Hora;
+
+    #[test]
+    fn test_setcomment() {
+        let config = InputConfig::new(Hora::Laudes);
+        let mut files = TestFiles::default().with(
+            "Latin",
+            "Psalterium/Comment.txt",
+            &[("Source", "ex Ordinario\nex Psalterio\nex Proprio de Tempore\n"), ("Preces", "Preces dicuntur\nPreces omittuntur")],
+        );
+        assert_eq!(
+            setcomment(&config, &mut files, "#Psalmi", Some(Comment::Source(TextSource::Season)), "Latin", ""),
+            "#Psalmi {ex Psalterio}"
+        );
+        assert_eq!(setcomment(&config, &mut files, "#Psalmi", None, "Latin", ""), "#Psalmi");
+        assert_eq!(
+            setcomment(&config, &mut files, "#Preces", Some(Comment::Preces { omitted: true }), "Latin", ""),
+            "#Preces {Preces omittuntur}"
+        );
+        let annotations = config.take_annotations();
+        assert_eq!(annotations.len(), 2);
+        assert_eq!(annotations[0].label, "#Psalmi");
+        assert_eq!(annotations[0].comment, Comment::Source(TextSource::Season));
+        assert_eq!(annotations[1].text, "Preces omittuntur");
+        assert!(config.annotations().is_empty());
+    }
+}
@@ -0,0 +1,135 @@
+//! conclusio.rs
+//!
+//! The conclusion of the hours, after the oration: "Dominus vobiscum",
+//! "Benedicamus Domino" and "Fidelium animae", and, when the office is
+//! interrupted there (no other hour follows at once), the "Pater noster" in
+//! silence and "Dominus det nobis suam pacem".
+//!
+//! The conclusion depends on the hour and on the season:
+//!
+//! - Matins followed at once by Lauds has none, Lauds beginning at once;
+//! - Prima concludes with its own blessing ("Dominus nos benedicat"), and
+//!   Compline with "Benedicat et custodiat nos" before the final antiphon,
+//!   without "Fidelium animae";
+//! - in the octave of Easter the "Benedicamus Domino" of Lauds and Vespers
+//!   has a double alleluia (`Benedicamus Domino1`);
+//! - in the Triduum the hours end with the "Christus factus est" and the
+//!   "Miserere" of the day, without conclusion.
+//!
+//! The 1960 rubrics drop the "Pater noster" in silence; the monastic little
+//! hours have no "Dominus vobiscum".
+//!
+//! "Dominus vobiscum" is said by a priest or a deacon only; one who is not
+//! says "Domine, exaudi orationem meam" in its place (`dominus_vobiscum()`,
+//! after the `priest` preference), before the oration as in the conclusion.
+
+use super::InputConfig;
+use crate::horas::Hora;
+
+/// The versicle before the oration and the "Benedicamus Domino":
+/// "$Dominus vobiscum" if the office is said by a priest, "$Domine exaudi"
+/// otherwise.
+pub fn dominus_vobiscum(config: &InputConfig) -> &'static str {
+    if config.preferences.priest {
+        "$Dominus vobiscum"
+    } else {
+        "$Domine exaudi"
+    }
+}
+
+/// Returns the lines of the conclusion of the hour of `config`, as `$`
+/// references to the Prayers.
+pub fn conclusio(config: &InputConfig) -> Vec<String> {
+    let hora = config.hora;
+    let version = config.rubrics();
+    let dayname = config.dayname0();
+    let mut lines = Vec::new();
+    if ["Quad6-4", "Quad6-5", "Quad6-6"].iter().any(|d| dayname.starts_with(d)) {
+        return lines;
+    }
+    match hora {
+        Hora::Matutinum if config.followed_by_hour => return lines,
+        Hora::Prima => {
+            lines.push("$Dominus nos benedicat".to_string());
+        }
+        Hora::Completorium => {
+            lines.push(dominus_vobiscum(config).to_string());
+            lines.push("$Benedicamus Domino".to_string());
+            lines.push("$Benedictio Completorium".to_string());
+            return lines;
+        }
+        _ => {
+            if !(version.monastic && hora.is_minor()) {
+                lines.push(dominus_vobiscum(config).to_string());
+            }
+            let paschal = dayname.starts_with("Pasc0") && hora.is_major();
+            lines.push(if paschal { "$Benedicamus Domino1" } else { "$Benedicamus Domino" }.to_string());
+            lines.push("$Fidelium animae".to_string());
+        }
+    }
+    if !config.followed_by_hour {
+        if !version.is_1960() {
+            lines.push("$Pater secreto".to_string());
+        }
+        lines.push("$Dominus det nobis".to_string());
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(hora: Hora, version: &str, dayname: &str, followed: bool) -> InputConfig {
+        let mut config = InputConfig::new(hora);
+        config.version = version.to_string();
+        config.daynames = vec![dayname.to_string()];
+        config.followed_by_hour = followed;
+        config.preferences.priest = true;
+        config
+    }
+
+    #[test]
+    fn test_conclusio_by_hour() {
+        let da = "Divino Afflatu - 1954";
+        assert_eq!(
+            conclusio(&config(Hora::Tertia, da, "Pent05-2", true)),
+            ["$Dominus vobiscum", "$Benedicamus Domino", "$Fidelium animae"]
+        );
+        assert_eq!(
+            conclusio(&config(Hora::Vespera, da, "Pent05-2", false)),
+            ["$Dominus vobiscum", "$Benedicamus Domino", "$Fidelium animae", "$Pater secreto", "$Dominus det nobis"]
+        );
+        assert!(conclusio(&config(Hora::Matutinum, da, "Pent05-2", true)).is_empty());
+        assert_eq!(
+            conclusio(&config(Hora::Completorium, da, "Pent05-2", false)),
+            ["$Dominus vobiscum", "$Benedicamus Domino", "$Benedictio Completorium"]
+        );
+    }
+
+    #[test]
+    fn test_conclusio_by_season_and_version() {
+        let da = "Divino Afflatu - 1954";
+        assert_eq!(conclusio(&config(Hora::Laudes, da, "Pasc0-1", true))[1], "$Benedicamus Domino1");
+        assert_eq!(conclusio(&config(Hora::Sexta, da, "Pasc0-1", true))[1], "$Benedicamus Domino");
+        assert!(conclusio(&config(Hora::Laudes, da, "Quad6-5", false)).is_empty());
+        assert_eq!(
+            conclusio(&config(Hora::Nona, "Rubrics 1960 - 1960", "Pent05-2", false)),
+            ["$Dominus vobiscum", "$Benedicamus Domino", "$Fidelium animae", "$Dominus det nobis"]
+        );
+        assert_eq!(
+            conclusio(&config(Hora::Sexta, "Monastic - 1930", "Pent05-2", true)),
+            ["$Benedicamus Domino", "$Fidelium animae"]
+        );
+    }
+
+    #[test]
+    fn test_conclusio_without_priest() {
+        let mut config = config(Hora::Vespera, "Divino Afflatu - 1954", "Pent05-2", true);
+        config.preferences.priest = false;
+        assert_eq!(dominus_vobiscum(&config), "$Domine exaudi");
+        assert_eq!(conclusio(&config), ["$Domine exaudi", "$Benedicamus Domino", "$Fidelium animae"]);
+        config.hora = Hora::Completorium;
+        assert_eq!(conclusio(&config)[0], "$Domine exaudi");
+    }
+}
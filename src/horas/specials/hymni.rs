@@ -2,55 +2,54 @@
 //!
 //! This module implements the special hymn‐retrieval routines used in the Hours.
 //!
-//! It defines three primary functions:
+//! It defines four primary functions:
 //!
-//! - `get_hymn(lang: &str) -> Option<String>` – returns the hymn (with proper doxology, build–info, etc.).
-//! - `hymnus_major(lang: &str) -> (Option<String>, String)` – returns a tuple (hymn, hymn name)
+//! - `get_hymn(config, files, lang) -> Option<String>` – returns the hymn (with proper doxology, build–info, etc.).
+//! - `get_hymn_with_doxology(files, hymn_id, season, version, lang) -> Option<String>` – returns a
+//!   hymn of the psalter by name, with the doxology of the given season and the text of the version.
+//! - `hymnus_major(config, lang) -> (Option<String>, String)` – returns a tuple (hymn, hymn name)
 //!   for the major hours.
-//! - `doxology(lang: &str) -> (Option<String>, String)` – returns the doxology text and its key.
-
-use std::collections::HashMap;
-
-// Assume that these helper functions are defined elsewhere:
-use crate::language_text_tools::translate;
-use crate::setup_string::setupstring;
-use crate::specials_build::{setbuild, setbuild1, setbuild2};
-use crate::proprium::getproprium;
-use crate::tempora::gettempora;
-use crate::postprocess::{postprocess_short_resp, postprocess_vr};
-use crate::doxology_module::doxology; // our own doxology function
-use crate::globals::{
-    get_hora, get_version, get_vespera, get_daynames, get_seasonalflag, get_votive, get_winner,
-};
+//! - `doxology(config, files, lang) -> (Option<String>, String)` – returns the doxology text and its key.
+
+use super::{getproprium, gettempora, translate, InputConfig};
+use crate::directorium::hymnshift;
+use crate::horas::postprocess::postprocess_vr;
+use crate::horas::rite::rite_of;
+use crate::horas::Hora;
+use crate::rubrics::{CistercianUse, RubricsVersion};
+use crate::setup_string::{FileSections, ResolveDirectives, SetupStringProvider};
 
 /// Standardizes the hymn’s opening.  
 /// This replicates the Perl regex:  
 ///   `s/^(?:v\.\s*)?(\p{Lu})/v. $1/`  
-/// It checks whether the hymn starts with an optional `"v. "` followed by an uppercase letter.
-/// If so, it returns `"v. "` concatenated with that letter; otherwise, if the hymn starts with an uppercase letter,
-/// it prepends `"v. "` to it.
+/// If the hymn starts with an uppercase letter, optionally preceded by `"v."`,
+/// the opening becomes `"v. "` followed by that letter; the rest is kept.
 fn fix_initial(hymn: &str) -> String {
-    // Remove any leading whitespace.
-    let s = hymn.trim_start();
-    // Check if it already starts with "v. " (case–insensitive)
-    if s.to_lowercase().starts_with("v. ") {
-        // We standardize it by ensuring that after "v. " the next character is kept.
-        let rest = s[3..].trim_start();
-        if let Some(first) = rest.chars().next() {
-            return format!("v. {}", first);
-        }
-    } else if let Some(first) = s.chars().next() {
-        if first.is_uppercase() {
-            return format!("v. {}", first);
-        }
+    let rest = match hymn.strip_prefix("v.") {
+        Some(r) => r.trim_start(),
+        None => hymn,
+    };
+    match rest.chars().next() {
+        Some(first) if first.is_uppercase() => format!("v. {}", rest),
+        _ => hymn.to_string(),
     }
-    s.to_string()
 }
 
 /// Removes all asterisks and following whitespace from the hymn text.
 /// This replicates the Perl substitution: `s/\*\s*//g`
 fn remove_stars(hymn: &str) -> String {
-    hymn.replace("*", "").trim().to_string()
+    let mut result = String::with_capacity(hymn.len());
+    let mut chars = hymn.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch == '*' {
+            while chars.peek().is_some_and(|c| c.is_whitespace()) {
+                chars.next();
+            }
+        } else {
+            result.push(ch);
+        }
+    }
+    result
 }
 
 /// Replaces occurrences of `"_\n"` not followed by `"!"` with `"_\nr. "`.
@@ -92,117 +91,179 @@ fn remove_attached_versicle(hymn: &str) -> String {
     }
 }
 
+/// The files of the psalter hymns, searched by `get_hymn_with_doxology()`.
+const HYMN_SOURCES: [&str; 4] = ["Major", "Minor", "Prima", "Matutinum"];
+
+/// Replaces the doxology of a hymn, the stropha marked with an asterisk and
+/// everything after it, with `dox`.
+fn substitute_doxology(hymn: &str, dox: &str) -> String {
+    format!("{}\n{}", remove_attached_versicle(hymn), dox.trim_end())
+}
+
+/// Returns the doxology `key` of `Psalterium/Doxologies.txt` ("Nat", "Epi",
+/// "Pasch", ...) with the key actually used: the monastic and 1570 books
+/// have their own text of some doxologies, under the key with a "T" after it.
+fn doxology_text(
+    files: &mut dyn SetupStringProvider,
+    key: &str,
+    version: &RubricsVersion,
+    lang: &str,
+) -> Option<(String, String)> {
+    let dox_map = files.setupstring(lang, "Psalterium/Doxologies.txt", ResolveDirectives::All)?;
+    let mut key = key.to_string();
+    if (version.monastic || version.year == Some(1570)) && dox_map.contains_key(&format!("{}T", key)) {
+        key.push('T');
+    }
+    let dox = dox_map.get(&key).filter(|d| !d.trim().is_empty())?.clone();
+    Some((dox, key))
+}
+
+/// Returns the hymn `hymn_id` of the psalter with the doxology of `season`,
+/// in the text of `version`.
+///
+/// `hymn_id` is the name of the hymn in one of the `Psalterium/Special/*
+/// Special.txt` files (e.g. "Hymnus Tertia", "Hymnus Day0 Laudes"), or a
+/// `file:section` reference to another file ("Commune/C10:Hymnus Vespera").
+/// The books that keep the texts from before the revision of Urban VIII
+/// (monastic, 1570, Dominican) get the older text when there is one.
+///
+/// `season` is a key of `Psalterium/Doxologies.txt`, as `gettempora("Doxology")`
+/// returns it: "Nat" (Christmas and Marian feasts), "Epi", "Pasch", "Asc",
+/// "Pent", ... With an empty season, or under the 1960 rubrics which have
+/// abolished the seasonal doxologies, the hymn keeps its own doxology.
+///
+/// Returns `None` if the hymn cannot be found. Outside of an hour there is
+/// no building script, so nothing is recorded.
+pub fn get_hymn_with_doxology(
+    files: &mut dyn SetupStringProvider,
+    hymn_id: &str,
+    season: &str,
+    version: &str,
+    lang: &str,
+) -> Option<String> {
+    let (sources, name): (Vec<String>, &str) = match hymn_id.split_once(':') {
+        Some((file, name)) => (vec![format!("{}.txt", file.trim_end_matches(".txt"))], name),
+        None => (HYMN_SOURCES.iter().map(|s| format!("Psalterium/Special/{} Special.txt", s)).collect(), hymn_id),
+    };
+    let version = RubricsVersion::parse(version);
+    let mut hymn = sources.iter().find_map(|fname| {
+        let source = files.setupstring(lang, fname, ResolveDirectives::All)?;
+        let name = tryoldhymn(&version, &source, name);
+        source.get(&name).cloned()
+    })?;
+
+    if !season.is_empty() && !version.is_1960() && hymn.contains('*') {
+        if let Some((dox, _)) = doxology_text(files, season, &version, lang) {
+            hymn = substitute_doxology(&hymn, &dox);
+        }
+    }
+    Some(fix_stropha(&remove_stars(&fix_initial(&hymn))))
+}
+
 /// Returns the hymn text for the current hour (and its associated section) as a single string.
 /// 
 /// The function follows these steps:
 /// 
 /// 1. It obtains a base section via `translate("Hymnus", lang)`.
-/// 2. Depending on the global hour (`hora`):
-///    - If `"Matutinum"`, it calls `hymnusmatutinum(lang)`.  
+/// 2. Depending on the hour (`config.hora`):
+///    - If `Matutinum`, it calls `hymnusmatutinum()`.  
 ///      If no hymn is found, it sets hymn source to `"Matutinum"` and clears the section.
-///    - If `"Laudes"` or `"Vespera"`, it calls `hymnus_major(lang)`, prepends `"Hymnus "` to the hymn name,
+///    - If `Laudes` or `Vespera`, it calls `hymnus_major()`, prepends `"Hymnus "` to the hymn name,
 ///      sets hymn source to `"Major"` (if no hymn was found), and sets section to `"_\n!{section}"`.
 ///      It then calls `getantvers("Versum", ind, lang)` with `ind = 2` for Laudes or `vespera` for Vespera.
 ///    - Otherwise (minor hours), it sets the name to `"Hymnus {hora}"` (with a special substitution for Tertia)
-///      and, if the hour is `"Completorium"` and the version begins with `"Ordo Praedicatorum"`,
+///      and, if the hour is `Completorium` and the version is Dominican,
 ///      loads extra data from `"Psalterium/Special/Minor Special.txt"`, postprocesses it, and may append a seasonal
-///      suffix from `gettempora("*")`. It sets hymn source to `"Prima"` if the hour is `"Prima"`,
+///      suffix from `gettempora("*")`. It sets hymn source to `"Prima"` if the hour is `Prima`,
 ///      otherwise `"Minor"`, and prefixes the section with `"#"`.
 /// 3. If a hymn source is defined, it loads a lookup table from  
 ///    `"Psalterium/Special/{hymnsource} Special.txt"`, adjusts the hymn name using `tryoldhymn()`,
 ///    and retrieves the hymn text from the table.
-/// 4. If the version does not match `/1960/` and the hymn text contains an asterisk,
-///    it calls `doxology(lang)` to get a doxology and substitutes (replacing everything from the asterisk onward)
+/// 4. Unless the version is under the 1960 rubrics (`is_1960()`), if the hymn text contains an asterisk,
+///    it calls `doxology()` to get a doxology and substitutes (replacing everything from the asterisk onward)
 ///    with the doxology. Also, it appends `" {Doxology: dname}"` to the section if appropriate.
 /// 5. It then standardizes the hymn’s opening using `fix_initial()`, removes stars via `remove_stars()`,
 ///    and fixes stropha markers using `fix_stropha()`.
 /// 6. Finally, it concatenates the section, hymn, and (if present) the versum text and returns the result.
-pub fn get_hymn(lang: &str) -> Option<String> {
-    // Retrieve global variables from our globals module.
-    let hora = crate::globals::get_hora();
-    let version = crate::globals::get_version();
-    let vespera = crate::globals::get_vespera();
-    let daynames = crate::globals::get_daynames(); // Vec<String>
+pub fn get_hymn(
+    config: &InputConfig,
+    files: &mut dyn SetupStringProvider,
+    lang: &str,
+) -> Option<String> {
+    let hora = config.hora;
+    let version = config.rubrics();
     let mut section = translate("Hymnus", lang);
 
     // Variables that will be set by subsequent calls.
-    let mut name = String::new();
-    let mut hymn: Option<String> = None;
-    let mut hymn_source: Option<String> = None;
+    let mut name;
+    let mut hymn: Option<String>;
+    let mut hymn_source: Option<&str> = None;
     let mut versum: Option<String> = None;
-    let mut cr: Option<String> = None; // Comment or similar
 
-    if hora == "Matutinum" {
-        // Assume hymnusmatutinum(lang) -> (Option<String>, String)
-        let (h, n) = crate::specials_hymn::hymnusmatutinum(lang);
+    if hora == Hora::Matutinum {
+        let (h, n) = hymnusmatutinum(config);
         hymn = h;
         name = n;
         if hymn.is_none() {
-            hymn_source = Some("Matutinum".to_string());
+            hymn_source = Some("Matutinum");
         }
         section.clear();
-    } else if hora == "Laudes" || hora == "Vespera" {
-        // Call hymnus_major(lang) -> (Option<String>, String)
-        let (h, n) = hymnus_major(lang);
+    } else if hora.is_major() {
+        let (h, n) = hymnus_major(config, lang);
         hymn = h;
         name = format!("Hymnus {}", n);
         if hymn.is_none() {
-            hymn_source = Some("Major".to_string());
+            hymn_source = Some("Major");
         }
         section = format!("_\n!{}", section);
-        let ind = if hora == "Laudes" { 2 } else { vespera };
-        // Assume getantvers returns (Option<String>, Option<String>)
-        let (v, c) = crate::specials_hymn::getantvers("Versum", ind, lang);
-        versum = v;
-        cr = c;
+        let ind = if hora == Hora::Laudes { 2 } else { config.vespera };
+        versum = getantvers(config, files, "Versum", ind, lang);
     } else {
         // Minor hours:
+        hymn = None;
         name = format!("Hymnus {}", hora);
-        // If hora == "Tertia" and first dayname contains "Pasc7",
-        // replace the first space with " Pasc7 " (we do a simple check)
-        if hora == "Tertia" && !daynames.is_empty() && daynames[0].contains("Pasc7") {
-            if let Some(pos) = name.find(' ') {
-                name.replace_range(pos..=pos, " Pasc7 ");
-            }
+        // On Pentecost and in its octave, Tertia has the Veni Creator.
+        if hora == Hora::Tertia && config.dayname0().contains("Pasc7") {
+            name = name.replacen(' ', " Pasc7 ", 1);
         }
-        if hora == "Completorium" && version.starts_with("Ordo Praedicatorum") {
-            if let Some(ant_map) = setupstring(lang, "Psalterium/Special/Minor Special.txt") {
+        if hora == Hora::Completorium && version.dominican {
+            if let Some(ant_map) =
+                files.setupstring(lang, "Psalterium/Special/Minor Special.txt", ResolveDirectives::All)
+            {
                 versum = ant_map.get("Versum 4").cloned();
             }
             // Postprocess the versum text.
             if let Some(ref mut v) = versum {
-                postprocess_vr(v, lang);
+                postprocess_vr(v, config.dayname0(), &config.votive, files, lang);
             }
-            let tempname = gettempora("*");
-            // Instead of regex, we check whether tempname equals one of the allowed strings.
+            let tempname = gettempora(config, "*");
             if ["Quad5", "Quad", "Pasch", "Asc", "Pent"].iter().any(|&s| tempname.starts_with(s)) {
                 name.push(' ');
                 name.push_str(&tempname);
             }
         }
-        hymn_source = Some(if hora == "Prima" { "Prima" } else { "Minor" }.to_string());
+        hymn_source = Some(if hora == Hora::Prima { "Prima" } else { "Minor" });
         section = format!("#{}", section);
     }
 
     // If hymn_source is defined, load a lookup table from "Psalterium/Special/{source} Special.txt"
-    if let Some(ref src) = hymn_source {
-        if let Some(hmap) = setupstring(lang, &format!("Psalterium/Special/{} Special.txt", src)) {
-            // Assume tryoldhymn(hmap, name) -> String
-            name = crate::specials_hymn::tryoldhymn(&hmap, name);
+    if let Some(src) = hymn_source {
+        if let Some(hmap) =
+            files.setupstring(lang, &format!("Psalterium/Special/{} Special.txt", src), ResolveDirectives::All)
+        {
+            name = tryoldhymn(&version, &hmap, &name);
             hymn = hmap.get(&name).cloned();
+            config.build.setbuild(&format!("Psalterium/Special/{} Special", src), &name, "Hymnus ord");
         }
     }
 
-    // If version does not match /1960/ and hymn contains "*", do doxology.
-    if !version.contains("1960") {
+    // Unless under the 1960 rubrics, a hymn with a "*" takes the doxology.
+    if !version.is_1960() {
         if let Some(ref mut h) = hymn {
             if h.contains('*') {
-                let (dox, dname) = doxology(lang);
-                if !dname.is_empty() {
-                    // Replace from first "*" onward with dox.
-                    if let Some(pos) = h.find('*') {
-                        h.replace_range(pos.., &dox);
-                    }
+                let (dox, dname) = doxology(config, files, lang);
+                if let Some(dox) = dox {
+                    *h = substitute_doxology(h, &dox);
                     if !section.is_empty() {
                         section.push_str(&format!(" {{Doxology: {}}}", dname));
                     }
@@ -219,103 +280,134 @@ pub fn get_hymn(lang: &str) -> Option<String> {
     }
 
     // Build the final output.
-    let mut output = format!("{}\n{}", section, hymn.unwrap_or_default());
+    let mut output = format!("{}\n{}", section, hymn.unwrap_or_default().trim_end());
     if let Some(v) = versum {
-        output.push_str(&format!("\n_\n{}", v));
+        output.push_str(&format!("\n_\n{}", v.trim_end()));
     }
     Some(output)
 }
 
-/// Returns a tuple `(hymn, name)` for the major hymn corresponding to the given language.
+/// Returns the hymn of Matins from the proper, or the name of the
+/// psalter hymn to look up ("Day{n} Hymnus", or the season's hymn).
+fn hymnusmatutinum(config: &InputConfig) -> (Option<String>, String) {
+    let (hymn, _c) = getproprium(config, "Hymnus Matutinum", config.seasonalflag, true);
+    if hymn.is_some() {
+        return (hymn, "Hymnus Matutinum".to_string());
+    }
+    let name = match gettempora(config, "Hymnus Matutinum").as_str() {
+        "" => format!("Day{} Hymnus", config.dayofweek),
+        season => format!("Hymnus {}", season),
+    };
+    (None, name)
+}
+
+/// Returns the versicle after the hymn of Lauds (`ind` 2) or Vespers (`ind`
+/// 1 or 3): from the proper if there is one, else the ferial one of the psalter.
+fn getantvers(
+    config: &InputConfig,
+    files: &mut dyn SetupStringProvider,
+    item: &str,
+    ind: i32,
+    lang: &str,
+) -> Option<String> {
+    let key = format!("{} {}", item, ind);
+    let (mut w, _c) = getproprium(config, &key, config.seasonalflag, true);
+    if w.is_none() {
+        let psalter = files.setupstring(lang, "Psalterium/Special/Major Special.txt", ResolveDirectives::All)?;
+        let key = format!("{} {} {}", gettempora(config, "Capitulum major"), item, config.hora);
+        w = psalter.get(&key).or_else(|| psalter.get(&format!("Day{} {}", config.dayofweek, key))).cloned();
+    }
+    let mut w = w?;
+    postprocess_vr(&mut w, config.dayname0(), &config.votive, files, lang);
+    Some(w)
+}
+
+/// The older hymn texts (before the revision of Urban VIII) are stored under
+/// the same name with an "M" after "Hymnus", e.g. "HymnusM Laudes". They are
+/// used by the monastic, the 1570 and the Dominican books when present.
+fn tryoldhymn(version: &RubricsVersion, source: &FileSections, name: &str) -> String {
+    if !(version.monastic || version.year == Some(1570) || version.dominican) {
+        return name.to_string();
+    }
+    let Some(pos) = name.find("Hymnus") else {
+        return name.to_string();
+    };
+    let end = name[pos..].find(char::is_whitespace).map_or(name.len(), |e| pos + e);
+    let name1 = format!("{}M{}", &name[..end], &name[end..]);
+    if source.contains_key(&name1) {
+        name1
+    } else {
+        name.to_string()
+    }
+}
+
+/// Returns a tuple `(hymn, name)` for the major hymn of the current hour.
 /// 
-/// This function uses several global variables (such as `hora`, `version`, `vespera`, the winners map, etc.)
-/// which are assumed to be available via the `globals` module. It applies several rules:
+/// It applies several rules:
 /// 
-/// 1. Start with `"Hymnus"`. If `hora` equals `"Vespera"`, append the result of `checkmtv(version, winners)`.
+/// 1. Start with `"Hymnus"`.
 /// 2. Under certain conditions (depending on the winners map and the vespera value), the name is reset to `"Hymnus"`.
 /// 3. If `hymnshift(version, day, month, year)` returns true then append `" Matutinum"` for Laudes or `" Laudes"` for Vespera,
 ///    and call `setbuild2("Hymnus shifted")`. Otherwise, append `" {hora}"`.
 /// 4. If `hora` equals `"Vespera"` and `vespera == 3`, attempt to get the hymn via `getproprium("{name} 3", ...)`.
-/// 5. Under a special condition (if version matches /cist/i, hora matches Vespera and winners{Rule} matches specific patterns),
-///    set the name to `"Hymnus Vespera Hac die"`.
+/// 5. If the `Rite` of the version has a hymn of its own for the hour and rule (the Cistercian
+///    "Hymnus Vespera Hac die"), take its name.
 /// 6. If no hymn is found, set the name from `gettempora("Hymnus major")` concatenated with `hora`, and (if certain conditions hold)
 ///    append `" hiemalis"`, then call `setbuild1("Hymnus", name)`.
 /// 7. Finally, return the tuple `(hymn, name)`.
-pub fn hymnus_major(lang: &str) -> (Option<String>, String) {
-    let hora = crate::globals::get_hora();
-    let version = crate::globals::get_version();
-    let vespera = crate::globals::get_vespera();
-    let daynames = crate::globals::get_daynames();
-    let seasonalflag = crate::globals::get_seasonalflag();
-    let day = crate::globals::get_day();
-    let month = crate::globals::get_month();
-    let year = crate::globals::get_year();
-    let winners = crate::globals::get_winner_map(); // assumed to be a HashMap<String,String>
+pub fn hymnus_major(config: &InputConfig, _lang: &str) -> (Option<String>, String) {
+    let hora = config.hora;
+    let version = &config.version;
+    let vespera = config.vespera;
+    let seasonalflag = config.seasonalflag;
+    let winners = config.winners();
 
     let mut name = "Hymnus".to_string();
-    // If hora is "Vespera", append checkmtv(version, winners)
-    if hora == "Vespera" {
-        name.push_str(&crate::specials_hymn::checkmtv(&version, &winners));
-    }
     // Reset name if certain conditions hold:
     if !winners.contains_key(&format!("{} Vespera", name))
         && vespera == 3
         && !winners.contains_key(&format!("{} Vespera 3", name))
-        && ((vespera == 3 && winners.contains_key("Hymnus Vespera 3"))
-            || winners.contains_key("Hymnus Vespera"))
+        && (winners.contains_key("Hymnus Vespera 3") || winners.contains_key("Hymnus Vespera"))
     {
         name = "Hymnus".to_string();
     }
-    if crate::specials_hymn::hymnshift(&version, day, month, year) {
-        if hora == "Laudes" {
+    if hymnshift(version, config.day, config.month, config.year) {
+        if hora == Hora::Laudes {
             name.push_str(" Matutinum");
         }
-        if hora == "Vespera" {
+        if hora == Hora::Vespera {
             name.push_str(" Laudes");
         }
-        setbuild2("Hymnus shifted");
+        config.build.setbuild2("Hymnus shifted");
     } else {
         name.push_str(&format!(" {}", hora));
     }
-    let mut cr: Option<String> = None;
     let mut hymn: Option<String> = None;
-    if hora == "Vespera" && vespera == 3 {
-        let (h, c) = getproprium(&format!("{} 3", name), lang, seasonalflag, 1);
-        hymn = h;
-        cr = c;
+    if hora == Hora::Vespera && vespera == 3 {
+        hymn = getproprium(config, &format!("{} 3", name), seasonalflag, true).0;
     }
-    if version.to_lowercase().contains("cist")
-        && hora.to_lowercase().contains("vespera")
-        && winners.get("Rule").map_or(false, |s| s.contains("C4") || s.contains("C5"))
-        && winners.get("Rule").map_or(false, |s| s.contains("Hac die"))
-    {
-        name = "Hymnus Vespera Hac die".to_string();
+    let rubrics = config.rubrics();
+    let rule = winners.get("Rule").map(String::as_str).unwrap_or("");
+    if let Some(proper) = rite_of(&rubrics).proper_hymn(&rubrics, hora, rule) {
+        name = proper;
     }
     if hymn.is_none() {
-        let (h, c) = getproprium(&name, lang, seasonalflag, 1);
-        hymn = h;
-        cr = c;
+        hymn = getproprium(config, &name, seasonalflag, true).0;
     }
     if hymn.is_none() {
-        name = format!("{} {}", gettempora("Hymnus major"), hora);
+        name = format!("{} {}", gettempora(config, "Hymnus major"), hora);
         // Check additional conditions:
+        let dayname = config.dayname0();
         if name.contains("Day0")
-            && (name.contains("Laudes") || version.to_lowercase().contains("cist"))
-            && (daynames.get(0).map_or(false, |s| {
-                s.contains("Epi2")
-                    || s.contains("Epi3")
-                    || s.contains("Epi4")
-                    || s.contains("Epi5")
-                    || s.contains("Epi6")
-                    || s.contains("Quadp")
-            })
-                || winners.get("Rank")
-                    .map_or(false, |s| s.contains("Novembris")
-                        || (s.contains("Octobris") && !version.to_lowercase().contains("cist"))))
+            && (name.contains("Laudes") || rubrics.winter_hymn_at_vespers())
+            && (["Epi2", "Epi3", "Epi4", "Epi5", "Epi6", "Quadp"].iter().any(|s| dayname.contains(s))
+                || winners.get("Rank").is_some_and(|s| {
+                    s.contains("Novembris") || (s.contains("Octobris") && rubrics.winter_hymns_in_october())
+                }))
         {
             name.push_str(" hiemalis");
         }
-        setbuild1("Hymnus", &name);
+        config.build.setbuild1("Hymnus", &name);
     }
     (hymn, name)
 }
@@ -323,8 +415,7 @@ pub fn hymnus_major(lang: &str) -> (Option<String>, String) {
 /// Returns a tuple `(dox, dname)` representing the doxology text and its key.
 /// 
 /// The function first checks if the winners map has a key `"Doxology"`. If so,
-/// it selects one of two winners maps (based on the return of `columnsel(lang)`) and uses that.
-/// Otherwise, it attempts to extract a doxology key from either the provided rule string,
+/// it uses that. Otherwise, it attempts to extract a doxology key from either the rule,
 /// or (if the version is Tridentine or the winner’s Rank does not match Adventus)
 /// from the commemoratio data. If none of these apply, it sets the key to `"Nat"` in certain conditions,
 /// or else calls `gettempora("Doxology")`.
@@ -335,69 +426,59 @@ pub fn hymnus_major(lang: &str) -> (Option<String>, String) {
 /// **Regex replacements:**  
 /// Rather than using a regex to extract the key from a string (e.g. `/Doxology=([a-z]+)/i`),
 /// we use simple string methods (such as splitting on `"Doxology="`) and case–insensitive comparisons.
-pub fn doxology(lang: &str) -> (Option<String>, String) {
-    let version = crate::globals::get_version();
-    let rule = crate::globals::get_rule(); // assumed function returning Option<String>
-    let daynames = crate::globals::get_daynames();
-    let winners = crate::globals::get_winner_map();
-    let winners2 = crate::globals::get_winner2_map();
-    let commemoratio = crate::globals::get_commemoratio(); // assumed HashMap<String,String>
-    let day = crate::globals::get_day();
-    let month = crate::globals::get_month();
-    let year = crate::globals::get_year();
-    let dayofweek = crate::globals::get_dayofweek();
+pub fn doxology(
+    config: &InputConfig,
+    files: &mut dyn SetupStringProvider,
+    lang: &str,
+) -> (Option<String>, String) {
+    let version = config.rubrics();
+    let winners = config.winners();
+    let (day, month) = (config.day, config.month);
 
     let mut dox = String::new();
-    let mut dname = String::new();
+    let dname;
 
-    if winners.contains_key("Doxology") {
-        // Choose winners map based on columnsel(lang)
-        let use_winner = if crate::globals::columnsel(lang) {
-            &winners
-        } else {
-            &winners2
-        };
-        dox = use_winner.get("Doxology").cloned().unwrap_or_default();
+    if let Some(special) = winners.get("Doxology") {
+        dox = special.clone();
         dname = "Special".to_string();
-        setbuild2("Special doxology");
+        config.build.setbuild2("Special doxology");
     } else {
-        if let Some(r) = rule.clone() {
-            // Look for substring "Doxology=" and then letters.
-            if let Some(pos) = r.to_lowercase().find("doxology=") {
-                // The key is the letters following "Doxology="
-                dname = r[pos + "doxology=".len()..].split_whitespace().next().unwrap_or("").to_string();
-            }
-        } else if (version.contains("Trident") || 
-                  !winners.get("Rank").map_or(false, |s| s.contains("Adventus")))
-            && commemoratio.get("Rule").map_or(false, |s| s.to_lowercase().contains("doxology="))
-        {
-            // Extract the key from commemoratio's "Rule" value.
-            if let Some(rule_str) = commemoratio.get("Rule") {
-                if let Some(pos) = rule_str.to_lowercase().find("doxology=") {
-                    dname = rule_str[pos + "doxology=".len()..].split_whitespace().next().unwrap_or("").to_string();
-                }
-            }
-        } else if (month == 8 && day > 15 && day < 23 && !version.contains("1955") && !version.contains("1963"))
-            || (!version.contains("1570") && !version.contains("1617") && !version.contains("altovadensis") &&
-                month == 12 && day > 8 && day < 16 && dayofweek > 0)
+        let rule_key = |rule: &str| -> Option<String> {
+            let pos = rule.to_lowercase().find("doxology=")?;
+            let key: String = rule[pos + "doxology=".len()..]
+                .chars()
+                .take_while(|c| c.is_ascii_alphabetic())
+                .collect();
+            if key.is_empty() { None } else { Some(key) }
+        };
+        let commem_rule = config.commemoratio_map.get("Rule").cloned().unwrap_or_default();
+        let mut key = if let Some(k) = rule_key(&config.rule) {
+            k
+        } else if let Some(k) = rule_key(&commem_rule).filter(|_| {
+            version.is_tridentine() || !winners.get("Rank").is_some_and(|s| s.contains("Adventus"))
+        }) {
+            k
+        } else if (month == 8 && day > 15 && day < 23 && !matches!(version.year, Some(1955 | 1963)))
+            || (!matches!(version.year, Some(1570 | 1617))
+                && version.cistercian != Some(CistercianUse::Altovadensis)
+                && month == 12
+                && day > 8
+                && day < 16
+                && config.dayofweek > 0)
         {
-            dname = "Nat".to_string();
+            "Nat".to_string()
         } else {
-            dname = gettempora("Doxology");
-        }
+            gettempora(config, "Doxology")
+        };
 
-        if !dname.is_empty() {
-            if let Some(dox_map) = setupstring(lang, "Psalterium/Doxologies.txt") {
-                // If version is Monastic or contains 1570 and key with "T" exists, append "T"
-                if (version.contains("Monastic") || version.contains("1570"))
-                    && dox_map.contains_key(&(dname.clone() + "T"))
-                {
-                    dname.push('T');
-                }
-                dox = dox_map.get(&dname).cloned().unwrap_or_default();
-                setbuild2(&format!("Doxology: {}", dname));
+        if !key.is_empty() {
+            if let Some((text, k)) = doxology_text(files, &key, &version, lang) {
+                dox = text;
+                key = k;
+                config.build.setbuild2(&format!("Doxology: {}", key));
             }
         }
+        dname = key;
     }
     (if dox.is_empty() { None } else { Some(dox) }, dname)
 }
@@ -424,26 +505,24 @@ mod tests {
     //!    inserting `"r. "` after an underscore-line marker) are done via simple string methods.
 
     use super::*;
+    use crate::horas::specials::tests::TestFiles;
 
     #[test]
     fn test_fix_initial() {
-        // Test that a string beginning with a capital letter gets prefixed with "v. "
-        let s = "HELLO world";
-        let fixed = fix_initial(s);
-        // Our implementation will only prepend the first letter.
-        assert_eq!(fixed, "v. H");
-
-        // If already starts with "v. " (in any case), we standardize it.
-        let s2 = "V. Greet";
-        let fixed2 = fix_initial(s2);
-        assert_eq!(fixed2, "v. G");
+        // A hymn beginning with a capital letter gets prefixed with "v. "
+        assert_eq!(fix_initial("HELLO world"), "v. HELLO world");
+
+        // An existing "v." is standardized, not doubled.
+        assert_eq!(fix_initial("v.Greet"), "v. Greet");
+        assert_eq!(fix_initial("v. Greet"), "v. Greet");
+        assert_eq!(fix_initial("lower case"), "lower case");
     }
 
     #[test]
     fn test_remove_stars() {
         let s = "This * is a * test *";
         let cleaned = remove_stars(s);
-        assert_eq!(cleaned, "This  is a  test ");
+        assert_eq!(cleaned, "This is a test ");
     }
 
     #[test]
@@ -462,4 +541,68 @@ mod tests {
         let cleaned = remove_attached_versicle(s);
         assert_eq!(cleaned, "Hymn text here");
     }
+
+    #[test]
+    fn test_get_hymn_minor_with_doxology() {
+        let mut config = InputConfig::new(Hora::Tertia);
+        config.version = "Divino Afflatu".to_string();
+        config.daynames = vec!["Pasc2-1".to_string()];
+        let mut files = TestFiles::default()
+            .with("Latin", "Psalterium/Special/Minor Special.txt", &[(
+                "Hymnus Tertia",
+                "Nunc, Sancte, nobis, Spíritus,\nUnum Patri cum Fílio,\n_\n* Præsta, Pater piíssime,\nPatríque compar Unice.",
+            )])
+            .with("Latin", "Psalterium/Doxologies.txt", &[("Pasch", "Deo Patri sit glória,\nEt Fílio, qui a mórtuis")]);
+        let hymn = get_hymn(&config, &mut files, "Latin").unwrap();
+        assert_eq!(
+            hymn,
+            "#Hymnus {Doxology: Pasch}\nv. Nunc, Sancte, nobis, Spíritus,\nUnum Patri cum Fílio,\n_\nr. Deo Patri sit glória,\nEt Fílio, qui a mórtuis"
+        );
+    }
+
+    #[test]
+    fn test_tryoldhymn() {
+        let mut source = FileSections::new();
+        source.insert("HymnusM Day0 Laudes".to_string(), "Ætérne rerum Cónditor".to_string());
+        let version = RubricsVersion::parse;
+        assert_eq!(tryoldhymn(&version("Divino Afflatu"), &source, "Hymnus Day0 Laudes"), "Hymnus Day0 Laudes");
+        assert_eq!(tryoldhymn(&version("Rubrics 1960 - 1960"), &source, "Hymnus Day0 Laudes"), "Hymnus Day0 Laudes");
+        for old in ["Monastic - 1963", "Tridentine - 1570", "Ordo Praedicatorum - 1962"] {
+            assert_eq!(tryoldhymn(&version(old), &source, "Hymnus Day0 Laudes"), "HymnusM Day0 Laudes");
+        }
+    }
+
+    #[test]
+    fn test_get_hymn_with_doxology() {
+        let mut files = TestFiles::default()
+            .with("Latin", "Psalterium/Special/Minor Special.txt", &[
+                ("Hymnus Tertia", "Nunc, Sancte, nobis, Spíritus,\n_\n* Præsta, Pater piíssime,\nPatríque compar Unice."),
+                ("HymnusM Tertia", "Nunc Sancte nobis Spíritus,\n_\n* Præsta Pater piíssime,\nPatríque compar Unice."),
+            ])
+            .with("Latin", "Psalterium/Doxologies.txt", &[
+                ("Nat", "Jesu, tibi sit glória,\nQui natus es de Vírgine,"),
+                ("NatT", "Glória tibi, Dómine,\nQui natus es de Vírgine,"),
+            ]);
+        let advent = "v. Nunc, Sancte, nobis, Spíritus,\n_\nr. Jesu, tibi sit glória,\nQui natus es de Vírgine,";
+        assert_eq!(
+            get_hymn_with_doxology(&mut files, "Hymnus Tertia", "Nat", "Divino Afflatu", "Latin").as_deref(),
+            Some(advent)
+        );
+        // The pre-Urbanite text, with the older doxology.
+        assert_eq!(
+            get_hymn_with_doxology(&mut files, "Hymnus Tertia", "Nat", "1570", "Latin").as_deref(),
+            Some("v. Nunc Sancte nobis Spíritus,\n_\nr. Glória tibi, Dómine,\nQui natus es de Vírgine,")
+        );
+        // No seasonal doxology under the 1960 rubrics.
+        assert_eq!(
+            get_hymn_with_doxology(&mut files, "Hymnus Tertia", "Nat", "Rubrics 1960 - 1960", "Latin").as_deref(),
+            Some("v. Nunc, Sancte, nobis, Spíritus,\n_\nr. Præsta, Pater piíssime,\nPatríque compar Unice.")
+        );
+        assert_eq!(
+            get_hymn_with_doxology(&mut files, "Psalterium/Special/Minor Special:Hymnus Tertia", "Nat", "Divino Afflatu", "Latin")
+                .as_deref(),
+            Some(advent)
+        );
+        assert!(get_hymn_with_doxology(&mut files, "Hymnus Nona", "", "Divino Afflatu", "Latin").is_none());
+    }
 }
@@ -0,0 +1,203 @@
+//! invitatorium.rs
+//!
+//! The invitatory of Matins: its antiphon and the Venite (Psalm 94), which
+//! the antiphon punctuates.
+//!
+//! The antiphon is that of the proper of the day or of its commune, else
+//! that of the season or of the day of the week in
+//! `Psalterium/Invitatorium.txt`. In Paschaltide it takes the alleluia.
+//!
+//! The psalm is said in five stanzas (verses 1, 3, 5, 8 and 10). The whole
+//! antiphon is said twice before the first, and after the first, the third
+//! and the fifth; its second half (after the `*`) after the second and the
+//! fourth, and after the Gloria Patri, then the whole once more:
+//!
+//! ```text
+//! Ant. Regem, cui ómnia vivunt, * Veníte, adorémus.   (twice)
+//! 94:1 Veníte, exsultémus Dómino ...
+//! Ant. Regem, cui ómnia vivunt, * Veníte, adorémus.
+//! 94:3 Quóniam Deus magnus Dóminus ...
+//! Ant. Veníte, adorémus.
+//! ...
+//! ```
+//!
+//! In Passiontide the Gloria Patri is left out, the half of the antiphon
+//! following the fifth stanza at once. On the Epiphany, whose Venite is said
+//! in the third nocturn, and in the Triduum there is no invitatory.
+
+use super::{getproprium, gettempora, InputConfig, TextSource};
+use crate::horas::postprocess::postprocess_ant;
+use crate::horas::psalterium::load_psalm;
+use crate::regex::contains_ci;
+use crate::rubrics::GloriaPart;
+use crate::setup_string::{ResolveDirectives, SetupStringProvider};
+
+/// The first verse of each stanza of the Venite.
+const STANZAS: [u32; 5] = [1, 3, 5, 8, 10];
+
+/// A part of the invitatory, in the order it is said.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvitatoriumPart {
+    /// The whole antiphon.
+    Antiphon,
+    /// The second half of the antiphon, after the `*`.
+    Repetition,
+    /// A stanza of the Venite: its verses, "94:1 ...".
+    Stanza(Vec<String>),
+    /// The Gloria Patri.
+    Gloria,
+}
+
+/// The invitatory of the day, as `invitatorium()` builds it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invitatorium {
+    /// The antiphon, e.g. "Regem, cui ómnia vivunt, * Veníte, adorémus.".
+    pub antiphon: String,
+    /// Where the antiphon was taken from.
+    pub source: TextSource,
+    pub parts: Vec<InvitatoriumPart>,
+}
+
+impl Invitatorium {
+    /// The second half of the antiphon, or the whole one if it has no `*`.
+    pub fn repetition(&self) -> &str {
+        self.antiphon.split_once('*').map_or(self.antiphon.as_str(), |(_, half)| half).trim()
+    }
+
+    /// The invitatory as the lines of the hour: "Ant." lines, the verses of
+    /// the psalm and "&Gloria".
+    pub fn text(&self) -> String {
+        let mut lines = Vec::new();
+        for part in &self.parts {
+            match part {
+                InvitatoriumPart::Antiphon => lines.push(format!("Ant. {}", self.antiphon)),
+                InvitatoriumPart::Repetition => lines.push(format!("Ant. {}", self.repetition())),
+                InvitatoriumPart::Stanza(verses) => lines.extend(verses.iter().cloned()),
+                InvitatoriumPart::Gloria => lines.push("&Gloria".to_string()),
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+/// Returns the invitatory of the day, or `None` when it is not said or has
+/// no antiphon.
+///
+/// If the Venite cannot be read, the stanzas are left to the psalm as a
+/// whole: the antiphon, `&psalm(94)` and the antiphon.
+pub fn invitatorium(config: &InputConfig, files: &mut dyn SetupStringProvider, lang: &str) -> Option<Invitatorium> {
+    let dayname = config.dayname0();
+    if contains_ci(&config.winner, "01-06") || ["Quad6-4", "Quad6-5", "Quad6-6"].iter().any(|d| dayname.starts_with(d)) {
+        return None;
+    }
+    let (antiphon, source) = match getproprium(config, "Invit", true, true) {
+        (Some(antiphon), source) => (antiphon, source),
+        _ => {
+            let inv = files.setupstring(lang, "Psalterium/Invitatorium.txt", ResolveDirectives::All)?;
+            match gettempora(config, "Invitatorium").as_str() {
+                "" => (inv.get(&format!("Day{}", config.dayofweek))?.clone(), TextSource::Psalterium),
+                season => (inv.get(season)?.clone(), TextSource::Season),
+            }
+        }
+    };
+    let mut antiphon = antiphon.trim().to_string();
+    postprocess_ant(&mut antiphon, dayname, &config.votive, files, lang);
+
+    let gloria = config.gloria_patri(GloriaPart::Invitatorium);
+    let Some(stanzas) = venite(files, lang) else {
+        let psalm = InvitatoriumPart::Stanza(vec![super::psalm_directive(94, gloria)]);
+        let parts = vec![InvitatoriumPart::Antiphon, psalm, InvitatoriumPart::Antiphon];
+        return Some(Invitatorium { antiphon, source, parts });
+    };
+    let mut parts = vec![InvitatoriumPart::Antiphon, InvitatoriumPart::Antiphon];
+    for (i, stanza) in stanzas.into_iter().enumerate() {
+        parts.push(InvitatoriumPart::Stanza(stanza));
+        parts.push(if i % 2 == 0 { InvitatoriumPart::Antiphon } else { InvitatoriumPart::Repetition });
+    }
+    if gloria {
+        parts.push(InvitatoriumPart::Gloria);
+    }
+    parts.push(InvitatoriumPart::Repetition);
+    parts.push(InvitatoriumPart::Antiphon);
+    Some(Invitatorium { antiphon, source, parts })
+}
+
+/// The verses of Psalm 94 in `lang`, grouped into the five stanzas.
+fn venite(files: &mut dyn SetupStringProvider, lang: &str) -> Option<Vec<Vec<String>>> {
+    let psalm = load_psalm(files, lang, "94", None)?;
+    let mut stanzas = vec![Vec::new(); STANZAS.len()];
+    for ((_, number, _), line) in psalm.verses.iter().zip(psalm.lines()) {
+        let verse = number.unwrap_or(1);
+        let stanza = STANZAS.iter().rposition(|&first| first <= verse).unwrap_or(0);
+        stanzas[stanza].push(line);
+    }
+    stanzas.iter().all(|s| !s.is_empty()).then_some(stanzas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::horas::specials::tests::TestFiles;
+    use crate::horas::Hora;
+
+    fn files() -> TestFiles {
+        TestFiles::default()
+            .with("Latin", "Psalterium/Invitatorium.txt", &[
+                ("Day2", "Dóminum, qui fecit nos, * Veníte, adorémus."),
+                ("Pasch", "Surréxit Dóminus vere, * Allelúja."),
+            ])
+            .with("Latin", "Psalterium/Psalmorum/Psalm94.txt", &[(
+                "__preamble",
+                "94:1 Veníte, exsultémus Dómino.\n94:3 Quóniam Deus magnus Dóminus.\n94:5 Quóniam ipsíus est mare.\n\
+                 94:6 Veníte, adorémus.\n94:7a Quia ipse est Dóminus Deus noster.\n94:8 Hódie, si vocem ejus.\n94:10 Quadragínta annis.",
+            )])
+    }
+
+    #[test]
+    fn test_invitatorium_venite() {
+        let mut config = InputConfig::new(Hora::Matutinum);
+        config.daynames = vec!["Epi3-2".to_string()];
+        (config.month, config.day, config.dayofweek) = (2, 4, 2);
+        let invit = invitatorium(&config, &mut files(), "Latin").unwrap();
+        assert_eq!((invit.source, invit.repetition()), (TextSource::Psalterium, "Veníte, adorémus."));
+        assert_eq!(invit.parts.len(), 15);
+        // "94:7a" belongs to the stanza of verse 5, its label kept as it is.
+        let stanza = ["94:5 Quóniam ipsíus est mare.", "94:6 Veníte, adorémus.", "94:7a Quia ipse est Dóminus Deus noster."];
+        assert_eq!(invit.parts[6], InvitatoriumPart::Stanza(stanza.map(String::from).to_vec()));
+        let text = invit.text();
+        assert!(text.starts_with("Ant. Dóminum, qui fecit nos, * Veníte, adorémus.\nAnt. Dóminum, qui fecit nos, * Veníte, adorémus.\n94:1"));
+        assert!(text.contains("94:3 Quóniam Deus magnus Dóminus.\nAnt. Veníte, adorémus.\n94:5"));
+        assert!(text.ends_with("94:10 Quadragínta annis.\nAnt. Dóminum, qui fecit nos, * Veníte, adorémus.\n&Gloria\nAnt. Veníte, adorémus.\nAnt. Dóminum, qui fecit nos, * Veníte, adorémus."));
+
+        // A proper antiphon; no Gloria Patri in Passiontide.
+        config.daynames = vec!["Quad5-2".to_string()];
+        config.winner = "Tempora/Quad5-2.txt".to_string();
+        config.winner_map.insert("Invit".to_string(), "Hódie, si vocem Dómini audiéritis, * Nolíte obduráre corda vestra.".to_string());
+        let invit = invitatorium(&config, &mut files(), "Latin").unwrap();
+        assert_eq!(invit.source, TextSource::Tempora);
+        assert!(!invit.parts.contains(&InvitatoriumPart::Gloria));
+        assert!(invit.text().ends_with("Quadragínta annis.\nAnt. Hódie, si vocem Dómini audiéritis, * Nolíte obduráre corda vestra.\nAnt. Nolíte obduráre corda vestra.\nAnt. Hódie, si vocem Dómini audiéritis, * Nolíte obduráre corda vestra."));
+    }
+
+    #[test]
+    fn test_invitatorium_seasons() {
+        let mut config = InputConfig::new(Hora::Matutinum);
+        config.daynames = vec!["Pasc2-3".to_string()];
+        config.dayofweek = 3;
+        let invit = invitatorium(&config, &mut files(), "Latin").unwrap();
+        assert_eq!((invit.source, invit.antiphon.as_str()), (TextSource::Season, "Surréxit Dóminus vere, * Allelúja."));
+
+        // Without the psalm, the Venite is said whole.
+        config.daynames = vec!["Epi3-2".to_string()];
+        (config.month, config.day, config.dayofweek) = (2, 4, 2);
+        let mut no_psalm = TestFiles::default().with("Latin", "Psalterium/Invitatorium.txt", &[("Day2", "Dóminum, qui fecit nos.")]);
+        let invit = invitatorium(&config, &mut no_psalm, "Latin").unwrap();
+        assert_eq!(invit.text(), "Ant. Dóminum, qui fecit nos.\n&psalm(94)\nAnt. Dóminum, qui fecit nos.");
+
+        config.winner = "Sancti/01-06.txt".to_string();
+        assert!(invitatorium(&config, &mut files(), "Latin").is_none());
+        config.winner = String::new();
+        config.daynames = vec!["Quad6-5".to_string()];
+        assert!(invitatorium(&config, &mut files(), "Latin").is_none());
+    }
+}
@@ -0,0 +1,168 @@
+//! martyrologium.rs
+//!
+//! The Roman Martyrology read at Prima, from `martyrologium()` and `luna()`
+//! in `/horas/specials/specprima.pl`.
+//!
+//! The Martyrology is read in anticipation: at Prima of one day the entry of
+//! the following day is announced, as in `Martyrologium/MM-DD.txt` (with the
+//! leap-year numbering of `get_sday()`), followed by the age of the moon on
+//! that day and the year of the Lord. The age of the moon is that of the
+//! Gregorian ecclesiastical lunar calendar (`date::lunar_day`).
+//!
+//! On 24 December the Martyrology of Christmas is the Kalenda, the solemn
+//! proclamation of the Nativity: it is announced in the solemn tone, and
+//! the last line of its first paragraph ("Nativitas Domini nostri Jesu
+//! Christi secundum carnem") is sung with raised voice while all kneel.
+
+use super::{rubric_line, translate, InputConfig};
+use crate::date::{date_to_ydays, leap_year, lunar_day, nextday, ydays_to_date};
+use crate::setup_string::{ResolveDirectives, SetupStringProvider};
+
+/// The age of the moon, as announced in the Latin Martyrology.
+const LUNA_ORDINALS: [&str; 30] = [
+    "prima", "secúnda", "tértia", "quarta", "quinta", "sexta", "séptima", "octáva", "nona", "décima",
+    "undécima", "duodécima", "tértia décima", "quarta décima", "quinta décima", "sexta décima",
+    "séptima décima", "duodevicésima", "undevicésima", "vicésima", "vicésima prima",
+    "vicésima secúnda", "vicésima tértia", "vicésima quarta", "vicésima quinta", "vicésima sexta",
+    "vicésima séptima", "vicésima octáva", "vicésima nona", "tricésima",
+];
+
+/// Returns the Martyrology of the day following the date of `config`:
+/// the announcement of the date ("v.") with the age of the moon and the
+/// year, the entries ("r."), and the `$Conclmart` conclusion.
+///
+/// Returns `None` if there is no Martyrology file for that day.
+pub fn martyrologium(
+    config: &InputConfig,
+    files: &mut dyn SetupStringProvider,
+    lang: &str,
+) -> Option<String> {
+    let fname = format!("Martyrologium/{}.txt", nextday(config.month, config.day, config.year));
+    let (day, month, year) = tomorrow(config.day, config.month, config.year);
+    let text = files
+        .setupstring(lang, &fname, ResolveDirectives::None)?
+        .get("__preamble")
+        .filter(|t| !t.trim().is_empty())?
+        .clone();
+    config.build.setbuild(&fname, "Martyrologium", "subst");
+
+    let kalenda = month == 12 && day == 25;
+    let version = config.rubrics();
+    let mut out = Vec::new();
+    let lines: Vec<&str> = text.lines().map(str::trim).skip_while(|l| l.is_empty()).collect();
+    let (announcement, lines) = lines.split_first()?;
+    if kalenda {
+        out.push(rubric_line("Kalenda", lang, &version));
+    }
+    out.push(format!("v. {} {}", announcement, luna(day, month, year, lang)));
+    // The line of the Nativity closes the first paragraph of the Kalenda.
+    let nativitas = lines.iter().position(|l| l.is_empty()).unwrap_or(lines.len()).checked_sub(1);
+    for (i, line) in lines.iter().enumerate() {
+        if line.is_empty() {
+            out.push("_".to_string());
+        } else {
+            if kalenda && Some(i) == nativitas {
+                out.push(rubric_line("Nativitas", lang, &version));
+            }
+            out.push(format!("r. {}", line));
+        }
+    }
+    if out.last().is_some_and(|l| l != "_") {
+        out.push("_".to_string());
+    }
+    out.push("$Conclmart".to_string());
+    Some(out.join("\n"))
+}
+
+/// The calendar date of the day after `day`/`month`/`year`.
+fn tomorrow(day: u32, month: u32, year: i32) -> (u32, u32, i32) {
+    let days = if leap_year(year) { 366 } else { 365 };
+    let yday = date_to_ydays(day, month, year);
+    if yday >= days {
+        (1, 1, year + 1)
+    } else {
+        ydays_to_date(yday + 1, year)
+    }
+}
+
+/// Returns the announcement of the age of the moon and of the year, e.g.
+/// "Luna quarta décima. Anno Dómini 2025."; other languages than Latin give
+/// the age as a number.
+fn luna(day: u32, month: u32, year: i32, lang: &str) -> String {
+    let age = lunar_day(day, month, year) as usize;
+    if lang.contains("Latin") {
+        format!("Luna {}. Anno Dómini {}.", LUNA_ORDINALS[age - 1], year)
+    } else {
+        format!("{} {}. {} {}.", translate("Luna", lang), age, translate("Anno Domini", lang), year)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::horas::specials::tests::TestFiles;
+    use crate::horas::Hora;
+
+    #[test]
+    fn test_luna() {
+        // The paschal full moon of 2025 is 13 April (Easter 20 April).
+        assert_eq!(luna(13, 4, 2025, "Latin"), "Luna quarta décima. Anno Dómini 2025.");
+        // And that of 2024 is 25 March (Easter 31 March).
+        assert_eq!(luna(25, 3, 2024, "Latin"), "Luna quarta décima. Anno Dómini 2024.");
+        assert_eq!(luna(1, 1, 2025, "Latin"), "Luna prima. Anno Dómini 2025.");
+    }
+
+    #[test]
+    fn test_tomorrow() {
+        assert_eq!(tomorrow(31, 12, 2024), (1, 1, 2025));
+        assert_eq!(tomorrow(28, 2, 2024), (29, 2, 2024));
+        assert_eq!(tomorrow(28, 2, 2025), (1, 3, 2025));
+    }
+
+    #[test]
+    fn test_martyrologium_reads_following_day() {
+        let mut config = InputConfig::new(Hora::Prima);
+        config.day = 12;
+        config.month = 4;
+        config.year = 2025;
+        let mut files = TestFiles::default().with(
+            "Latin",
+            "Martyrologium/04-13.txt",
+            &[("__preamble", "Idibus Aprilis.\nRomæ sancti Hermenegildi Martyris.\n\nEt alibi.\n")],
+        );
+        let text = martyrologium(&config, &mut files, "Latin").unwrap();
+        assert_eq!(
+            text,
+            "v. Idibus Aprilis. Luna quarta décima. Anno Dómini 2025.\n\
+             r. Romæ sancti Hermenegildi Martyris.\n_\nr. Et alibi.\n_\n$Conclmart"
+        );
+        config.day = 13;
+        assert!(martyrologium(&config, &mut files, "Latin").is_none());
+    }
+
+    #[test]
+    fn test_martyrologium_kalenda() {
+        let mut config = InputConfig::new(Hora::Prima);
+        config.day = 24;
+        config.month = 12;
+        config.year = 2025;
+        let mut files = TestFiles::default().with(
+            "Latin",
+            "Martyrologium/12-25.txt",
+            &[(
+                "__preamble",
+                "Octávo Kaléndas Januárii.\nAnno a creatióne mundi...\nNatívitas Dómini nostri Jesu Christi secúndum carnem.\n\nIpso die natális sanctæ Anastásiæ.\n",
+            )],
+        );
+        let text = martyrologium(&config, &mut files, "Latin").unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines[0].starts_with("/:") && lines[0].ends_with(":/"));
+        assert!(lines[1].starts_with("v. Octávo Kaléndas Januárii. Luna"));
+        assert_eq!(lines[2], "r. Anno a creatióne mundi...");
+        assert!(lines[3].starts_with("/:"));
+        assert_eq!(lines[4], "r. Natívitas Dómini nostri Jesu Christi secúndum carnem.");
+        assert_eq!(lines[5], "_");
+        assert_eq!(lines[6], "r. Ipso die natális sanctæ Anastásiæ.");
+        assert_eq!(text.matches("/:").count(), 2);
+    }
+}
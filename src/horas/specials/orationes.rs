@@ -9,15 +9,20 @@
 //! - **check_commemoratio** – Returns the text found in one of the keys  
 //!   "Commemoratio", "Commemoratio 1", "Commemoratio 2", or "Commemoratio 3".
 //!
-//! - **oratio** – Collects and returns the oratio (and its associated commemorations)
-//!   according to various conditions (special rules, seasonal adjustments, etc.).
+//! - **oratio_blocks** – Collects the oratio and its commemorations as `OratioBlock`s
+//!   (antiphon, versicle, prayer and conclusion) according to various conditions
+//!   (special rules, seasonal adjustments, etc.); **oratio** renders them.
+//!
+//! - **select_conclusion** – Finds the conclusion a prayer calls for from the words
+//!   naming the Son and the Holy Ghost, with a table of the known exceptions.
 //!
 //! - **delconclusio** – Removes any “conclusio” (final appended text) from a string,
 //!   returning both the cleaned string and the removed portion.
 //!
 //! - **get_refs** – Expands “@‑references” found in a string (used for oratio, lectio, etc.).
 //!
-//! - **vigilia_commemoratio** and **getsuffragium** – Retrieve special commemoratory texts.
+//! - **vigilia_commemoratio** and **suffragium** – Retrieve special commemoratory texts; the
+//!   suffragium with its seasonal rules.
 //!
 //! (Many details—such as the precise lookup in winner maps, calls to setbuild(), and so on—are
 //! delegated to external helper functions that must be provided elsewhere in the crate.)
@@ -37,18 +42,16 @@
 
 use std::collections::HashMap;
 
-// Assume that these helper functions (and global stubs) are defined elsewhere:
-use crate::setup_string::setupstring;
-use crate::specials_build::{setbuild, setbuild1, setbuild2};
-use crate::globals::{
-    columnsel, get_day, get_dayofweek, get_daynames, get_hora, get_month, get_rule, get_version,
-    get_votive, get_winner, get_winner_map, get_winner2_map, get_commune, get_commune2,
-};
-use crate::offices::officestring;
-use crate::specials_papal::{papal_rule, papal_prayer, papal_commem_rule, papal_antiphon_dum_esset, replace_ndot};
-use crate::postprocess::{postprocess_ant, postprocess_vr};
-use crate::tempora::gettempora;
-use crate::inclusions::do_inclusion_substitutions;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::specials_papal::{papal_office, papal_prayer, replace_ndot};
+use super::{getproprium, InputConfig, TextSource};
+use crate::horas::Hora;
+use crate::language_text_tools::strip_accents;
+use crate::regex::contains_ci;
+use crate::rubrics::CistercianUse;
+use crate::setup_string::{ResolveDirectives, SetupStringContext, SetupStringProvider};
 
 /// Returns the first nonempty value among the keys
 /// "Commemoratio", "Commemoratio 1", "Commemoratio 2", or "Commemoratio 3"
@@ -63,24 +66,420 @@ pub fn check_commemoratio(office: &HashMap<String, String>) -> String {
         .unwrap_or_else(String::new)
 }
 
+/// Whether an oration is the collect of the office, a commemoration or an
+/// oration ordered by the bishop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OratioKind {
+    Oratio,
+    Commemoratio,
+    /// An oratio imperata of the preferences (`Preferences::imperatae`).
+    Imperata,
+}
+
+/// One oration of an hour, as `oratio_blocks()` assembles it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OratioBlock {
+    pub kind: OratioKind,
+    /// The file the prayer was taken from, e.g. "Sancti/01-21.txt".
+    pub source: String,
+    /// The heading of a commemoration, e.g. "!Commemoratio S. Emerentianæ".
+    pub title: Option<String>,
+    /// The antiphon of a commemoration at Lauds and Vespers, without "Ant.".
+    pub antiphon: Option<String>,
+    /// Its versicle and response, "V. ...\nR. ...".
+    pub versicle: Option<String>,
+    /// The prayer, without "$Oremus" and without its conclusion.
+    pub prayer: String,
+    /// The conclusion of the prayer, e.g. "$Per Dominum": its own, or the
+    /// one `select_conclusion()` finds for it.
+    pub conclusion: String,
+}
+
+impl OratioBlock {
+    /// The block as the lines of the hour, without the conclusion: a
+    /// commemoration with an antiphon or a versicle has them before
+    /// "$Oremus", as in the books.
+    fn text(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(title) = &self.title {
+            parts.push(title.clone());
+        }
+        if let Some(antiphon) = &self.antiphon {
+            parts.push(format!("Ant. {}", antiphon));
+        }
+        if let Some(versicle) = &self.versicle {
+            if self.antiphon.is_some() {
+                parts.push("_".to_string());
+            }
+            parts.push(versicle.clone());
+        }
+        if self.kind == OratioKind::Oratio || self.antiphon.is_some() || self.versicle.is_some() {
+            if parts.len() > usize::from(self.title.is_some()) {
+                parts.push("_".to_string());
+            }
+            parts.push("$Oremus".to_string());
+        }
+        parts.push(self.prayer.clone());
+        parts.join("\n")
+    }
+}
+
+/// Collects the oratio of the hour and appends the prayers of the
+/// commemorations, each introduced by the `_` separator.
+///
+/// The blocks are those of `oratio_blocks()`; all are said under one
+/// conclusion, that of the first prayer, after the last commemoration.
+pub fn oratio(
+    config: &InputConfig,
+    files: &mut dyn SetupStringProvider,
+    lang: &str,
+) -> Option<String> {
+    let blocks = oratio_blocks(config, files, lang);
+    let first = blocks.first()?;
+    let mut out = blocks.iter().map(OratioBlock::text).collect::<Vec<_>>().join("\n_\n");
+    if !first.conclusion.is_empty() {
+        out.push('\n');
+        out.push_str(&first.conclusion);
+    }
+    Some(out)
+}
+
+/// The orations of the hour: the collect, then one block for each
+/// commemoration of `config.commemoentries`.
+///
+/// The collect is looked up as "Oratio {n}" (first or second Vespers, Lauds
+/// being 2) before the plain "Oratio", first in the winner and then in the
+/// commune; a feria without a prayer of its own takes that of the Sunday
+/// before. The office of a Pope takes its prayer from the common of the
+/// Supreme Pontiffs (see `specials_papal`). "@" references are expanded.
+///
+/// A commemoration is taken from the "Commemoratio" section of its office,
+/// else from its prayer; at Lauds and Vespers a commemoration given by its
+/// prayer alone gets the antiphon and the versicle of the hour of that
+/// office.
+///
+/// The orationes imperatae of the preferences come last, except at
+/// Compline, on the days the version admits them and as long as the hour
+/// has fewer orations than it allows (`RubricsVersion::max_orationes()`).
+pub fn oratio_blocks(
+    config: &InputConfig,
+    files: &mut dyn SetupStringProvider,
+    lang: &str,
+) -> Vec<OratioBlock> {
+    let ind = match config.hora {
+        Hora::Vespera => config.vespera as u32,
+        _ => 2,
+    };
+    let mut blocks = Vec::new();
+    let (text, source) = match getproprium(config, &format!("Oratio {}", ind), false, true) {
+        (Some(t), c) => (Some(t), c),
+        _ => getproprium(config, "Oratio", true, true),
+    };
+    let mut source = match source {
+        TextSource::Commune => config.commune.clone(),
+        _ => config.winner.clone(),
+    };
+    let text = match papal_office(config.winners(), false) {
+        Some(rule) => papal_prayer(files, lang, &rule, "Oratio").or(text),
+        None => text,
+    };
+    let text = text.or_else(|| {
+        let (sunday, text) = sunday_oratio(config, files, lang)?;
+        source = sunday;
+        Some(text)
+    });
+    let Some(text) = text else { return blocks };
+    let text = get_refs(config, files, text.trim_end(), lang, ind);
+    let parsed = ParsedOratio::parse(&text);
+    blocks.push(OratioBlock {
+        kind: OratioKind::Oratio,
+        source,
+        title: None,
+        antiphon: None,
+        versicle: None,
+        conclusion: parsed.conclusion.unwrap_or_else(|| select_conclusion(&parsed.prayer, lang)),
+        prayer: parsed.prayer,
+    });
+
+    for entry in config.commemoentries.iter() {
+        let c_map = if *entry == config.commemoratio {
+            Some(config.commemoratio_map.clone())
+        } else {
+            files.setupstring(lang, entry, ResolveDirectives::All)
+        };
+        let Some(c_map) = c_map else { continue };
+        let comm = check_commemoratio(&c_map);
+        let papal = papal_office(&c_map, true).or_else(|| papal_office(&c_map, false));
+        let (comm, proper) = if !comm.is_empty() {
+            (comm, true)
+        } else if let Some(comm) = papal.and_then(|rule| papal_prayer(files, lang, &rule, "Oratio")) {
+            (comm, false)
+        } else if c_map.get("Rank").is_some_and(|r| contains_ci(r, "vigil")) {
+            (vigilia_commemoratio(files, entry, lang).unwrap_or_default(), false)
+        } else {
+            (c_map.get("Oratio").cloned().unwrap_or_default(), false)
+        };
+        if comm.trim().is_empty() {
+            continue;
+        }
+        config.build.setbuild(entry, "Commemoratio", "subst");
+        let comm = get_refs(config, files, comm.trim_end(), lang, ind);
+        let mut parsed = ParsedOratio::parse(&comm);
+        if !proper && config.hora.is_major() && parsed.antiphon.is_none() && parsed.versicle.is_none() {
+            let keys: &[u32] = if ind == 1 { &[1, 3] } else { &[ind, 3, 1] };
+            let lookup = |name: &str| {
+                keys.iter().find_map(|n| c_map.get(&format!("{} {}", name, n))).map(|t| t.trim().to_string())
+            };
+            parsed.antiphon = lookup("Ant");
+            parsed.versicle = lookup("Versum");
+        }
+        blocks.push(OratioBlock {
+            kind: OratioKind::Commemoratio,
+            source: entry.clone(),
+            title: parsed.title,
+            antiphon: parsed.antiphon,
+            versicle: parsed.versicle,
+            conclusion: parsed.conclusion.unwrap_or_else(|| select_conclusion(&parsed.prayer, lang)),
+            prayer: parsed.prayer,
+        });
+    }
+
+    let version = config.rubrics();
+    if config.hora != Hora::Completorium && version.admits_oratio_imperata(config.rank) {
+        for reference in &config.preferences.imperatae {
+            if blocks.len() >= version.max_orationes() {
+                break;
+            }
+            let Some((file, section)) = reference.trim_start_matches('@').split_once(':') else { continue };
+            let fname = format!("{}.txt", file);
+            let Some(text) = files.setupstring(lang, &fname, ResolveDirectives::All).and_then(|m| m.get(section).cloned())
+            else {
+                continue;
+            };
+            config.build.setbuild(&fname, section, "imperata");
+            let parsed = ParsedOratio::parse(&get_refs(config, files, text.trim_end(), lang, ind));
+            blocks.push(OratioBlock {
+                kind: OratioKind::Imperata,
+                source: fname,
+                title: parsed.title,
+                antiphon: None,
+                versicle: None,
+                conclusion: parsed.conclusion.unwrap_or_else(|| select_conclusion(&parsed.prayer, lang)),
+                prayer: parsed.prayer,
+            });
+        }
+    }
+    blocks
+}
+
+/// The prayer of the Sunday for a feria of the Tempora without its own,
+/// with the file it was taken from.
+fn sunday_oratio(
+    config: &InputConfig,
+    files: &mut dyn SetupStringProvider,
+    lang: &str,
+) -> Option<(String, String)> {
+    if !config.winner.is_empty() && !contains_ci(&config.winner, "tempora") {
+        return None;
+    }
+    let (week, day) = config.dayname0().split_once('-')?;
+    if day == "0" || week.is_empty() {
+        return None;
+    }
+    let fname = format!("Tempora/{}-0.txt", week);
+    let s_map = files.setupstring(lang, &fname, ResolveDirectives::All)?;
+    let text = s_map.get("Oratio").filter(|t| !t.trim().is_empty())?.clone();
+    config.build.setbuild(&fname, "Oratio", "subst");
+    Some((fname, text))
+}
+
+/// The text of an oration split into its parts.
+#[derive(Debug, Default)]
+struct ParsedOratio {
+    title: Option<String>,
+    antiphon: Option<String>,
+    versicle: Option<String>,
+    prayer: String,
+    conclusion: Option<String>,
+}
+
+impl ParsedOratio {
+    /// Splits `text`: the "!" heading, the "Ant." antiphon, the "V."/"R."
+    /// lines, the first "$" line other than "$Oremus" (the conclusion) and
+    /// the rest (the prayer). The "_" separators are dropped.
+    fn parse(text: &str) -> Self {
+        let (text, conclusion) = delconclusio(text);
+        let conclusion = conclusion.lines().next().map(|l| l.trim().to_string()).filter(|l| !l.is_empty());
+        let mut parsed = ParsedOratio { conclusion, ..Default::default() };
+        let mut versicle = Vec::new();
+        let mut prayer = Vec::new();
+        for line in text.lines() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed == "_" || trimmed == "$Oremus" {
+                continue;
+            }
+            if trimmed.starts_with('!') && parsed.title.is_none() && prayer.is_empty() {
+                parsed.title = Some(trimmed.to_string());
+            } else if let Some(antiphon) = trimmed.strip_prefix("Ant.").filter(|_| prayer.is_empty()) {
+                parsed.antiphon = Some(antiphon.trim().to_string());
+            } else if (trimmed.starts_with("V.") || trimmed.starts_with("R.")) && prayer.is_empty() {
+                versicle.push(trimmed);
+            } else {
+                prayer.push(line.trim_end());
+            }
+        }
+        if !versicle.is_empty() {
+            parsed.versicle = Some(versicle.join("\n"));
+        }
+        parsed.prayer = prayer.join("\n");
+        parsed
+    }
+}
+
+/// The words by which `select_conclusion()` judges a prayer in a language,
+/// each a sequence of lowercase words without accents.
+struct ConclusionTerms {
+    /// Those that address the Son in the opening clause.
+    address: &'static [&'static [&'static str]],
+    /// Those that name the Son.
+    son: &'static [&'static [&'static str]],
+    /// Those that name the Holy Ghost.
+    spirit: &'static [&'static [&'static str]],
+}
+
+static LATIN_TERMS: ConclusionTerms = ConclusionTerms {
+    address: &[&["iesu", "christe"], &["christe"], &["fili", "dei"], &["redemptor", "mundi"]],
+    son: &[
+        &["filii", "tui"],
+        &["filium", "tuum"],
+        &["filio", "tuo"],
+        &["filius", "tuus"],
+        &["unigeniti"],
+        &["unigenitum"],
+        &["unigenito"],
+        &["unigenitus"],
+        &["iesu"],
+        &["iesum"],
+        &["iesus"],
+        &["christi"],
+        &["christum"],
+        &["christo"],
+        &["christus"],
+    ],
+    spirit: &[
+        &["spiritus", "sancti"],
+        &["spiritu", "sancto"],
+        &["spiritum", "sanctum"],
+        &["spiritus", "sanctus"],
+        &["sancti", "spiritus"],
+        &["sancto", "spiritu"],
+        &["sanctum", "spiritum"],
+        &["sanctus", "spiritus"],
+        &["paracliti"],
+        &["paraclitum"],
+        &["paraclito"],
+        &["paraclitus"],
+    ],
+};
+
+static ENGLISH_TERMS: ConclusionTerms = ConclusionTerms {
+    address: &[&["lord", "jesus", "christ"], &["o", "jesus"], &["o", "christ"]],
+    son: &[&["thy", "son"], &["your", "son"], &["only", "begotten"], &["jesus"], &["christ"]],
+    spirit: &[&["holy", "ghost"], &["holy", "spirit"], &["paraclete"]],
+};
+
+/// The prayers whose conclusion the words do not tell, by their opening
+/// words: those addressed to the Son without naming Him, and the collect of
+/// Holy Week, said under "Qui tecum" though it names the Son in its middle.
+static CONCLUSION_OVERRIDES: &[(&str, &str)] = &[
+    ("Excita, quæsumus, Dómine, poténtiam tuam, et veni", "$Qui vivis"),
+    ("Aurem tuam, quæsumus, Dómine, précibus nostris accómmoda", "$Qui vivis"),
+    ("Deus, qui nobis sub Sacraménto mirábili", "$Qui vivis"),
+    ("Fidélium, Deus, ómnium Cónditor et Redémptor", "$Qui vivis"),
+    ("Réspice, quæsumus, Dómine, super hanc famíliam tuam, pro qua", "$Qui tecum"),
+    ("Stir up thy power, we beseech thee, O Lord, and come", "$Qui vivis"),
+    ("O God, who under a wonderful Sacrament", "$Qui vivis"),
+];
+
+/// The conclusion a prayer calls for, as the "$" line naming it: "$Qui
+/// vivis" for a prayer addressed to the Son, "$Qui tecum" when it names Him
+/// in its last clause, "$Per eumdem" when it names Him before, and "$Per
+/// Dominum" otherwise; " eiusdem" is added when it names the Holy Ghost
+/// ("in unitate eiusdem Spiritus Sancti").
+///
+/// English prayers are judged by their English words, those of the other
+/// languages by the Latin ones; `CONCLUSION_OVERRIDES` settles the known
+/// exceptions. The "$Oremus" and "$" lines of `text` are ignored.
+pub fn select_conclusion(text: &str, lang: &str) -> String {
+    let english = lang.starts_with("English");
+    let terms = if english { &ENGLISH_TERMS } else { &LATIN_TERMS };
+    let prayer: Vec<&str> = text.lines().filter(|l| !l.trim_start().starts_with('$')).collect();
+    let prayer = prayer.join(" ");
+    let words = conclusion_words(&prayer, english);
+
+    let spirit = |base: &str| {
+        if terms.spirit.iter().any(|term| find_words(&words, term).is_some()) {
+            format!("{} eiusdem", base)
+        } else {
+            base.to_string()
+        }
+    };
+    if let Some((_, conclusion)) = CONCLUSION_OVERRIDES.iter().find(|(incipit, _)| {
+        let incipit = conclusion_words(incipit, english);
+        words.starts_with(&incipit)
+    }) {
+        return spirit(conclusion);
+    }
+
+    let opening = prayer.split([',', ';', ':', '.', '!']).next().unwrap_or("");
+    let opening = conclusion_words(opening, english);
+    if terms.address.iter().any(|term| find_words(&opening, term).is_some()) {
+        return spirit("$Qui vivis");
+    }
+    let last_clause = prayer.trim_end().trim_end_matches(['.', '!', ' ']).rsplit([',', ';', ':', '.']).next().unwrap_or("");
+    let last_clause = conclusion_words(last_clause, english);
+    if terms.son.iter().any(|term| find_words(&last_clause, term).is_some()) {
+        spirit("$Qui tecum")
+    } else if terms.son.iter().any(|term| find_words(&words, term).is_some()) {
+        spirit("$Per eumdem")
+    } else {
+        spirit("$Per Dominum")
+    }
+}
+
+/// The words of `text`, lowercase and without accents; the Latin "j" is
+/// read as "i" ("Jesu", "ejusdem").
+fn conclusion_words(text: &str, english: bool) -> Vec<String> {
+    let text = strip_accents(text).to_lowercase().replace('æ', "ae").replace('œ', "oe");
+    let text = if english { text } else { text.replace('j', "i") };
+    text.split(|c: char| !c.is_alphabetic()).filter(|w| !w.is_empty()).map(str::to_string).collect()
+}
+
+/// The position of the sequence `term` in `words`.
+fn find_words(words: &[String], term: &[&str]) -> Option<usize> {
+    words.windows(term.len()).position(|window| window.iter().zip(term).all(|(w, t)| w == t))
+}
+
 /// Given a string `ostr` (typically the oratio text), removes an initial “conclusio”
 /// that is, a leading block starting with a dollar sign (except “$Oremus”)
 /// followed by a newline, and returns a tuple `(new_string, conclusio)`.
 pub fn delconclusio(ostr: &str) -> (String, String) {
     // We mimic the Perl regex:
     //    s/^(\$(?!Oremus).*?(\n|$)((_|\s*)(\n|$))*)//m
-    // For simplicity, we search for the first line that starts with '$' and not "$Oremus".
-    let lines: Vec<&str> = ostr.lines().collect();
-    if !lines.is_empty() {
-        if let Some((first, rest)) = lines.split_first() {
-            if first.starts_with('$') && !first.contains("Oremus") {
-                let conclusio = first.to_string() + "\n";
-                let remaining = rest.join("\n");
-                return (remaining, conclusio);
-            }
-        }
+    // i.e. the first line starting with '$' (but not "$Oremus") together with
+    // the empty or '_' lines that follow it.
+    let lines: Vec<&str> = ostr.split('\n').collect();
+    let Some(start) = lines.iter().position(|l| l.starts_with('$') && !l.starts_with("$Oremus")) else {
+        return (ostr.to_string(), String::new());
+    };
+    let mut end = start + 1;
+    while end < lines.len() && matches!(lines[end].trim(), "" | "_") {
+        end += 1;
     }
-    (ostr.to_string(), String::new())
+    let conclusio = lines[start..end].join("\n") + "\n";
+    let mut remaining = lines[..start].to_vec();
+    remaining.extend_from_slice(&lines[end..]);
+    (remaining.join("\n"), conclusio)
 }
 
 /// Expands an “@‑reference” found within the string `w`.
@@ -110,36 +509,33 @@ pub fn delconclusio(ostr: &str) -> (String, String) {
 /// 
 /// Returns a tuple of five strings: (before, filename, item, substitutions, after).
 pub fn parse_at_reference(s: &str) -> Option<(String, String, String, String, String)> {
-    // Find the first occurrence of '@'
-    let pos_at = s.find('@')?;
-    let before = s[..pos_at].to_string();
-    let after_at = &s[pos_at + 1..];
-    // Split the remainder into up to 4 parts (filename, item, substitutions, remainder)
-    let parts: Vec<&str> = after_at.splitn(4, ':').collect();
-    if parts.len() < 2 {
-        // Not enough parts to form a reference.
-        return None;
-    }
-    let filename = parts[0].trim().to_string();
-    let item = parts[1].trim().to_string();
-    let substitutions = if parts.len() >= 3 { parts[2].trim().to_string() } else { "".to_string() };
-    let after = if parts.len() == 4 { parts[3].to_string() } else { "".to_string() };
-    Some((before, filename, item, substitutions, after))
+    static AT_REFERENCE: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"(?s)^(.*?)@([A-Za-z0-9/\-]+?):([A-Za-z0-9 ]+)(?::(\S*))?(.*)$").unwrap()
+    });
+    let caps = AT_REFERENCE.captures(s)?;
+    let part = |i: usize| caps.get(i).map_or("", |m| m.as_str()).to_string();
+    Some((part(1), part(2), part(3).trim().to_string(), part(4), part(5)))
 }
 
 /// Internal function to process an @‑reference found in the input string.
 /// It calls `parse_at_reference()` and then performs lookups via `setupstring()`,
 /// applies any substitutions (via `do_inclusion_substitutions()`), and then
 /// reassembles the final string.
-/// 
+///
 /// Returns Some(expanded_string) if the reference is successfully processed.
-pub fn get_refs_internal(s: &str, lang: &str, ind: u32, rule: &str) -> Option<String> {
+pub fn get_refs_internal(
+    config: &InputConfig,
+    files: &mut dyn SetupStringProvider,
+    s: &str,
+    lang: &str,
+    ind: u32,
+) -> Option<String> {
     // Parse the @‑reference into its parts.
     let (before, file, item, substitutions, after) = parse_at_reference(s)?;
     // Special case: if the filename (case–insensitively) equals "feria"
     if file.eq_ignore_ascii_case("feria") {
-        if let Some(s_map) = crate::setup_string::setupstring(lang, "Psalterium/Major Special.txt", &[]) {
-            let dayofweek = crate::globals::get_dayofweek();
+        if let Some(s_map) = files.setupstring(lang, "Psalterium/Special/Major Special.txt", ResolveDirectives::All) {
+            let dayofweek = config.dayofweek;
             let a = s_map.get(&format!("Day{} Ant {}", dayofweek, ind))
                 .cloned()
                 .unwrap_or_else(|| format!("Day{} Ant {} missing", dayofweek, ind));
@@ -147,40 +543,33 @@ pub fn get_refs_internal(s: &str, lang: &str, ind: u32, rule: &str) -> Option<St
                 .cloned()
                 .unwrap_or_else(|| format!("Day{} Versum {} missing", dayofweek, ind));
             let mut a_sub = a.clone();
-            crate::inclusions::do_inclusion_substitutions(&mut a_sub, &substitutions);
+            SetupStringContext::do_inclusion_substitutions(&mut a_sub, &substitutions);
             let mut v_sub = v.clone();
-            crate::inclusions::do_inclusion_substitutions(&mut v_sub, &substitutions);
+            SetupStringContext::do_inclusion_substitutions(&mut v_sub, &substitutions);
             return Some(format!("{}_\nAnt. {}\n{}\n{}", before, a_sub, v_sub, after));
         }
     }
-    // If the first dayname (from globals) contains "pasc", adjust the filename.
-    let daynames = crate::globals::get_daynames();
+    // In Paschaltide, the commune of martyrs is taken from its Paschal form.
     let mut file_adj = file.clone();
-    if !daynames.is_empty() && daynames[0].to_lowercase().contains("pasc") {
+    if contains_ci(config.dayname0(), "pasc") {
         // For simplicity, we replace "C2" with "C2p". (More logic can be added as needed.)
         file_adj = file_adj.replace("C2", "C2p");
     }
-    // Look up the file via setupstring (assuming it returns a HashMap).
-    if let Some(s_map) = crate::setup_string::setupstring(lang, &format!("{}.txt", file_adj), &[]) {
+    // Look up the file via setupstring.
+    if let Some(s_map) = files.setupstring(lang, &format!("{}.txt", file_adj), ResolveDirectives::All) {
         // Depending on the item, choose a lookup:
-        let text = if item.to_lowercase().contains("commemoratio") || item.to_lowercase().contains("octava") {
+        let text = if contains_ci(&item, "commemoratio") || contains_ci(&item, "octava") {
             s_map.get(&format!("{} {}", item, ind))
-                .or_else(|| s_map.get(item))
-                .cloned()
-                .unwrap_or_else(|| format!("{} {} missing\n", file, item))
-        } else if item.to_lowercase().contains("oratio") {
-            // For "oratio" we assume additional logic; here we try to load a second-level file.
-            // (In a complete implementation, this branch would be more complex.)
-            s_map.get(item)
+                .or_else(|| s_map.get(&item))
                 .cloned()
                 .unwrap_or_else(|| format!("{} {} missing\n", file, item))
         } else {
-            s_map.get(item)
+            s_map.get(&item)
                 .cloned()
                 .unwrap_or_else(|| format!("{} {} missing\n", file, item))
         };
         let mut text_mut = text.clone();
-        crate::inclusions::do_inclusion_substitutions(&mut text_mut, &substitutions);
+        SetupStringContext::do_inclusion_substitutions(&mut text_mut, &substitutions);
         return Some(format!("{}{}{}", before, text_mut, after));
     }
     // If no lookup, return a fallback string.
@@ -190,8 +579,14 @@ pub fn get_refs_internal(s: &str, lang: &str, ind: u32, rule: &str) -> Option<St
 /// Public function to process @‑references in a given string.
 /// If a reference is found and successfully expanded, returns the expanded text;
 /// otherwise, returns the original string with underscores normalized.
-pub fn get_refs(s: &str, lang: &str, ind: u32, rule: &str) -> String {
-    if let Some(expanded) = get_refs_internal(s, lang, ind, rule) {
+pub fn get_refs(
+    config: &InputConfig,
+    files: &mut dyn SetupStringProvider,
+    s: &str,
+    lang: &str,
+    ind: u32,
+) -> String {
+    if let Some(expanded) = get_refs_internal(config, files, s, lang, ind) {
         // Also, remove any duplicate underscores.
         expanded.replace("_\n_", "_")
     } else {
@@ -200,77 +595,158 @@ pub fn get_refs(s: &str, lang: &str, ind: u32, rule: &str) -> String {
 }
 
 /// Returns the commemoratio text for a vigilia from the given filename and language.
-/// 
+///
 /// First adjusts the filename if necessary (appending ".txt" and a prefix if needed),
 /// then loads the file via `setupstring()` and returns the "Oratio" or "Oratio Vigilia" value.
-/// (For files that do not match, an empty string is returned.)
-pub fn vigilia_commemoratio(fname: &str, lang: &str) -> Option<String> {
+/// (For files that do not match, `None` is returned.)
+pub fn vigilia_commemoratio(
+    files: &mut dyn SetupStringProvider,
+    fname: &str,
+    lang: &str,
+) -> Option<String> {
     let mut fname_adj = fname.to_string();
     if !fname_adj.to_lowercase().ends_with(".txt") {
         fname_adj.push_str(".txt");
     }
-    if !fname_adj.to_lowercase().contains("tempora")
-        && !fname_adj.to_lowercase().contains("sancti")
-    {
+    if !contains_ci(&fname_adj, "tempora") && !contains_ci(&fname_adj, "sancti") {
         fname_adj = format!("Sancti/{}", fname_adj);
     }
-    let s_map = setupstring(lang, &fname_adj, &[])?;
-    let mut wrank = s_map.get("Rank").map(|s| s.split(";;").collect::<Vec<_>>()).unwrap_or_default();
-    let mut w_val = if let Some(val) = s_map.get("Oratio") {
-        val.clone()
-    } else if s_map.contains_key("Oratio Vigilia") {
-        s_map.get("Oratio Vigilia").cloned().unwrap()
-    } else {
-        return None;
-    };
-    // (Optional) if the Rank field contains "Vigilia" then try to use a fallback from "Oratio Vigilia"
-    if w_val.is_empty() && s_map.get("Rank").map_or(false, |r| r.contains("Vigilia")) {
-        // Use commune fallback.
-        if let Some(com_map) = setupstring(lang, "Psalterium/Special/Major Special.txt", &[]) {
+    let s_map = files.setupstring(lang, &fname_adj, ResolveDirectives::All)?;
+    let mut w_val = s_map
+        .get("Oratio Vigilia")
+        .or_else(|| s_map.get("Oratio"))
+        .cloned()?;
+    // A vigil without its own prayer takes the one of the commune.
+    if w_val.trim().is_empty() && s_map.get("Rank").is_some_and(|r| r.contains("Vigilia")) {
+        if let Some(com_map) = files.setupstring(lang, "Commune/C1v.txt", ResolveDirectives::All) {
             w_val = com_map.get("Oratio").cloned().unwrap_or_default();
-            w_val = replace_ndot(&w_val, lang, &com_map.get("Name").cloned().unwrap_or_default());
+            w_val = replace_ndot(&w_val, lang, s_map.get("Name").map(String::as_str).unwrap_or(""));
         }
     }
-    if w_val.is_empty() {
+    if w_val.trim().is_empty() {
         None
     } else {
         Some(w_val)
     }
 }
 
-/// Returns a tuple `(suffragium_text, comment)` for the given language.
-/// The comment is determined by the version and dayname and is an integer.
-pub fn getsuffragium(lang: &str) -> (String, i32) {
-    let version = get_version();
-    let dayname = get_daynames();
-    let hora = get_hora();
-    let commune = ""; // assume from globals
-    let day = get_day();
-    let comment = if version.to_lowercase().contains("altovadensis") {
-        5
-    } else if version.to_lowercase().contains("cisterciensis") {
-        4
-    } else if version.to_lowercase().contains("trident") {
-        3
-    } else if !dayname.is_empty() && dayname[0].to_lowercase().contains("pasc") {
-        2
-    } else {
-        1
+/// Which suffragium is said at Lauds and Vespers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuffragiumKind {
+    /// The suffragium of All Saints, "A cunctis" (Divino Afflatu).
+    Sanctorum,
+    /// The commemoration of the Cross of Paschaltide.
+    Crucis,
+    /// The commemorations of the Tridentine books, proper to each hour.
+    Tridentinum,
+    /// The suffragia of the Cistercian Breviary.
+    Cisterciensis,
+    /// The suffragia of the use of Vyšší Brod.
+    Altovadensis,
+}
+
+impl SuffragiumKind {
+    /// The line of the `Suffragium` section of `Psalterium/Comment.txt`
+    /// describing it.
+    pub fn comment(self) -> usize {
+        match self {
+            SuffragiumKind::Sanctorum => 1,
+            SuffragiumKind::Crucis => 2,
+            SuffragiumKind::Tridentinum => 3,
+            SuffragiumKind::Cisterciensis => 4,
+            SuffragiumKind::Altovadensis => 5,
+        }
+    }
+}
+
+/// The suffragium of an hour: which it is, its text and the section of
+/// `Psalterium/Special/Major Special.txt` it was taken from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suffragium {
+    pub kind: SuffragiumKind,
+    pub text: String,
+    pub key: String,
+}
+
+/// Returns the suffragium of Lauds or Vespers of the office of `config`,
+/// or `None` where it is not said:
+///
+/// - under the simplifications of 1955 and the 1960 rubrics, which
+///   abolished it, and where the rule of the office says "no suffragium";
+/// - in double offices (the class of the `[Rank]` line begins with
+///   "Duplex"), within octaves, from Christmas to the Epiphany and in
+///   Passiontide.
+///
+/// In Paschaltide the suffragium of All Saints gives way to the
+/// commemoration of the Cross. In Advent and Lent the section of the season
+/// ("Suffragium Adv", "Suffragium Quad Laudes", ...) replaces the ordinary
+/// one where the Psalterium has it.
+pub fn suffragium(
+    config: &InputConfig,
+    files: &mut dyn SetupStringProvider,
+    lang: &str,
+) -> Option<Suffragium> {
+    let version = config.rubrics();
+    if version.uses_1955_simplifications() || contains_ci(&config.rule, "no suffragium") {
+        return None;
+    }
+    let dayname = config.dayname0();
+    let title = config.daynames.get(1).map(String::as_str).unwrap_or("");
+    let within_octave = contains_ci(title, "octav") || contains_ci(&config.commemoratio, "octav");
+    if is_double(config)
+        || within_octave
+        || dayname.starts_with("Nat")
+        || dayname.starts_with("Quad5")
+        || dayname.starts_with("Quad6")
+    {
+        return None;
+    }
+
+    let kind = match version.cistercian {
+        Some(CistercianUse::Altovadensis) => SuffragiumKind::Altovadensis,
+        Some(CistercianUse::Cisterciensis) => SuffragiumKind::Cisterciensis,
+        None if version.is_tridentine() => SuffragiumKind::Tridentinum,
+        None if contains_ci(dayname, "pasc") => SuffragiumKind::Crucis,
+        None => SuffragiumKind::Sanctorum,
     };
-    let s_map = setupstring(lang, "Psalterium/Special/Major Special.txt", &[]).unwrap_or_default();
-    let suffr = if comment > 2 {
-        s_map.get(&format!("Suffragium {}", hora)).cloned()
-    } else {
-        s_map.get("Suffragium").cloned()
+    let base = match kind {
+        SuffragiumKind::Sanctorum => "Suffragium".to_string(),
+        SuffragiumKind::Crucis => "Suffragium Paschale".to_string(),
+        _ => format!("Suffragium {}", config.hora),
+    };
+    // The seasonal section first ("Suffragium Adv", "Suffragium Quad Laudes").
+    let mut keys = Vec::new();
+    if let Some(season) = ["Adv", "Quad"].into_iter().find(|season| dayname.starts_with(season)) {
+        keys.push(base.replacen("Suffragium", &format!("Suffragium {}", season), 1));
+    }
+    keys.push(base);
+    if kind == SuffragiumKind::Crucis {
+        keys.push("Suffragium".to_string());
+    }
+
+    let s_map = files
+        .setupstring(lang, "Psalterium/Special/Major Special.txt", ResolveDirectives::All)
+        .unwrap_or_default();
+    let (key, text) = keys.into_iter().find_map(|key| {
+        let text = s_map.get(&key)?.clone();
+        Some((key, text))
+    })?;
+    Some(Suffragium { kind, text, key })
+}
+
+/// Whether the office of `config` is a double: the class of its `[Rank]`
+/// line begins with "Duplex", or, without one, its rank is 3 or more.
+fn is_double(config: &InputConfig) -> bool {
+    match config.winners().get("Rank").and_then(|rank| rank.lines().next()?.split(";;").nth(1)) {
+        Some(class) => class.trim_start().starts_with("Duplex"),
+        None => config.rank >= 3.0,
     }
-    .unwrap_or_else(|| "Suffragium missing".to_string());
-    // (Additional altovadensis processing omitted for brevity)
-    (suffr, comment)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::horas::specials::tests::TestFiles;
 
     #[test]
     fn test_parse_at_reference_full() {
@@ -300,7 +776,8 @@ mod tests {
     fn test_get_refs_no_reference() {
         // If there is no "@" in the string, get_refs should return the string with underscores normalized.
         let input = "No reference here _\n_ remains.";
-        let output = get_refs(input, "Latin", 2, "dummy rule");
+        let config = InputConfig::new(Hora::Laudes);
+        let output = get_refs(&config, &mut TestFiles::default(), input, "Latin", 2);
         assert_eq!(output, "No reference here _ remains.");
     }
 
@@ -317,6 +794,9 @@ mod tests {
         let (cleaned, conclusio) = delconclusio(s);
         assert_eq!(conclusio, "$Some text\n");
         assert_eq!(cleaned, "rest of text".to_string());
+        let (cleaned, conclusio) = delconclusio("$Oremus\nDeus, qui.\n$Per Dominum\n_");
+        assert_eq!(conclusio, "$Per Dominum\n_\n");
+        assert_eq!(cleaned, "$Oremus\nDeus, qui.");
     }
 
     #[test]
@@ -330,7 +810,8 @@ mod tests {
         // Here we override by inserting into a temporary map.
         // For simplicity we assume get_refs returns a string that reassembles the parts.
         // (This test is illustrative only.)
-        let result = get_refs(input, "Latin", 2, "dummy_rule");
+        let config = InputConfig::new(Hora::Laudes);
+        let result = get_refs(&config, &mut TestFiles::default(), input, "Latin", 2);
         // Since our dummy setupstring likely returns None, we expect fallback text.
         assert!(result.contains("Reference missing") || result.contains("dummy"));
     }
@@ -338,7 +819,168 @@ mod tests {
     #[test]
     fn test_vigilia_commemoratio_fallback() {
         // This test illustrates that if the file does not exist, vigilia_commemoratio returns None.
-        let result = vigilia_commemoratio("nonexistent", "Latin");
+        let result = vigilia_commemoratio(&mut TestFiles::default(), "nonexistent", "Latin");
         assert!(result.is_none());
     }
-}
+
+    #[test]
+    fn test_oratio_with_commemoration() {
+        let mut config = InputConfig::new(Hora::Laudes);
+        config.winner = "Sancti/01-25.txt".to_string();
+        config.winner_map.insert("Oratio".to_string(), "Deus, qui universum mundum.\n$Per Dominum".to_string());
+        config.commemoratio = "Sancti/01-25c.txt".to_string();
+        config.commemoentries = vec![config.commemoratio.clone()];
+        config
+            .commemoratio_map
+            .insert("Commemoratio".to_string(), "!Commemoratio S. Pauli\nOratio Pauli.".to_string());
+        let text = oratio(&config, &mut TestFiles::default(), "Latin").unwrap();
+        assert_eq!(
+            text,
+            "$Oremus\nDeus, qui universum mundum.\n_\n!Commemoratio S. Pauli\nOratio Pauli.\n$Per Dominum"
+        );
+        assert!(oratio(&InputConfig::new(Hora::Laudes), &mut TestFiles::default(), "Latin").is_none());
+    }
+
+    #[test]
+    fn test_oratio_papal() {
+        let mut config = InputConfig::new(Hora::Laudes);
+        config.winner = "Sancti/09-16.txt".to_string();
+        config.winner_map.insert("Rule".to_string(), "ex C2b;\nOPapaM=Cornélio;".to_string());
+        let mut files = TestFiles::default().with("Latin", "Commune/C2b.txt", &[(
+            "Oratio",
+            "Gregem tuum, Pastor ætérne, beáto N. Mártyre tuo atque Summo Pontífice.\n$Per Dominum",
+        )]);
+        let text = oratio(&config, &mut files, "Latin").unwrap();
+        assert_eq!(text, "$Oremus\nGregem tuum, Pastor ætérne, beáto Cornélio Mártyre tuo atque Summo Pontífice.\n$Per Dominum");
+    }
+
+    #[test]
+    fn test_oratio_blocks() {
+        let mut config = InputConfig::new(Hora::Laudes);
+        config.winner = "Tempora/Epi2-2.txt".to_string();
+        config.daynames = vec!["Epi2-2".to_string(), "Feria III".to_string()];
+        config.commemoentries = vec!["Sancti/01-21.txt".to_string(), "Sancti/01-22.txt".to_string()];
+        let mut files = TestFiles::default()
+            .with("Latin", "Tempora/Epi2-0.txt", &[("Oratio", "Omnípotens sempitérne Deus.\n$Per Dominum")])
+            .with("Latin", "Sancti/01-21.txt", &[
+                ("Oratio", "Omnípotens sempitérne Deus, qui infírma mundi éligis.\n$Per Dominum"),
+                ("Ant 2", "Beáta Agnes."),
+                ("Versum 2", "V. Diffúsa est grátia.\nR. Proptérea benedíxit te Deus."),
+            ])
+            .with("Latin", "Sancti/01-22.txt", &[(
+                "Commemoratio",
+                "!Commemoratio Ss. Vincentii et Anastasii\nAnt. Istórum est.\n_\nV. Lætámini.\nR. Et gloriámini.\n_\n$Oremus\nAdésto, Dómine.\n$Per Dominum",
+            )]);
+        let blocks = oratio_blocks(&config, &mut files, "Latin");
+        assert_eq!(blocks.len(), 3);
+        // The feria takes the prayer of the Sunday.
+        assert_eq!((blocks[0].kind, blocks[0].source.as_str()), (OratioKind::Oratio, "Tempora/Epi2-0.txt"));
+        assert_eq!((blocks[0].prayer.as_str(), blocks[0].conclusion.as_str()), ("Omnípotens sempitérne Deus.", "$Per Dominum"));
+        // The antiphon and the versicle of Lauds of the office commemorated.
+        assert_eq!(blocks[1].antiphon.as_deref(), Some("Beáta Agnes."));
+        assert_eq!(blocks[1].versicle.as_deref(), Some("V. Diffúsa est grátia.\nR. Proptérea benedíxit te Deus."));
+        assert_eq!(blocks[2].title.as_deref(), Some("!Commemoratio Ss. Vincentii et Anastasii"));
+        assert_eq!((blocks[2].antiphon.as_deref(), blocks[2].prayer.as_str()), (Some("Istórum est."), "Adésto, Dómine."));
+
+        // One conclusion, after the last commemoration.
+        let text = oratio(&config, &mut files, "Latin").unwrap();
+        assert_eq!(text.matches("$Per Dominum").count(), 1);
+        assert!(text.ends_with("Ant. Istórum est.\n_\nV. Lætámini.\nR. Et gloriámini.\n_\n$Oremus\nAdésto, Dómine.\n$Per Dominum"));
+    }
+
+    #[test]
+    fn test_oratio_imperata() {
+        let mut config = InputConfig::new(Hora::Laudes);
+        config.version = "Divino Afflatu - 1954".to_string();
+        config.winner = "Sancti/01-21.txt".to_string();
+        config.winner_map.insert("Oratio".to_string(), "Omnípotens sempitérne Deus.\n$Per Dominum".to_string());
+        config.rank = 3.0;
+        config.preferences.imperatae = vec!["Commune/C10:Oratio".to_string(), "Commune/C11:Oratio".to_string()];
+        let mut files = TestFiles::default()
+            .with("Latin", "Commune/C10.txt", &[("Oratio", "Deus, refúgium nostrum et virtus.")])
+            .with("Latin", "Commune/C11.txt", &[("Oratio", "Deus, qui culpa offénderis.\n$Per Dominum")]);
+        let blocks = oratio_blocks(&config, &mut files, "Latin");
+        let kinds: Vec<OratioKind> = blocks.iter().map(|b| b.kind).collect();
+        assert_eq!(kinds, [OratioKind::Oratio, OratioKind::Imperata, OratioKind::Imperata]);
+        assert_eq!((blocks[1].source.as_str(), blocks[1].prayer.as_str()), ("Commune/C10.txt", "Deus, refúgium nostrum et virtus."));
+        let text = oratio(&config, &mut files, "Latin").unwrap();
+        assert!(text.ends_with("Omnípotens sempitérne Deus.\n_\nDeus, refúgium nostrum et virtus.\n_\nDeus, qui culpa offénderis.\n$Per Dominum"));
+
+        // Not on a double of the first class, nor at Compline.
+        config.rank = 6.5;
+        assert_eq!(oratio_blocks(&config, &mut files, "Latin").len(), 1);
+        config.rank = 3.0;
+        config.hora = Hora::Completorium;
+        assert_eq!(oratio_blocks(&config, &mut files, "Latin").len(), 1);
+        // Since 1955 an hour has three orations at most.
+        config.hora = Hora::Vespera;
+        config.version = "Rubrics 1960 - 1960".to_string();
+        config.commemoentries = vec!["Sancti/01-22.txt".to_string()];
+        let mut files = files.with("Latin", "Sancti/01-22.txt", &[("Oratio", "Adésto, Dómine.")]);
+        let kinds: Vec<OratioKind> = oratio_blocks(&config, &mut files, "Latin").iter().map(|b| b.kind).collect();
+        assert_eq!(kinds, [OratioKind::Oratio, OratioKind::Commemoratio, OratioKind::Imperata]);
+    }
+
+    #[test]
+    fn test_select_conclusion() {
+        let latin = |text| select_conclusion(text, "Latin");
+        assert_eq!(latin("Da, quǽsumus, omnípotens Deus: ut qui nova incarnáti Verbi tui luce perfúndimur."), "$Per Dominum");
+        assert_eq!(latin("Dómine Jesu Christe, qui Vírginem Maríam.\n$Per Dominum"), "$Qui vivis");
+        assert_eq!(
+            latin("Deus, qui Unigénitum tuum Géntibus stella duce revelásti: concéde propítius, ut qui jam te ex fide cognóvimus."),
+            "$Per eumdem"
+        );
+        assert_eq!(latin("Deus, qui nos redemptiónis nostræ ánnua exspectatióne lætíficas: præsta, ut Unigénitum tuum."), "$Qui tecum");
+        assert_eq!(latin("Deus, qui hodiérna die corda fidélium Sancti Spíritus illustratióne docuísti."), "$Per Dominum eiusdem");
+        // The exceptions of the table.
+        assert_eq!(latin("Excita, quǽsumus, Dómine, poténtiam tuam, et veni: ut ab imminéntibus."), "$Qui vivis");
+        assert_eq!(latin("Réspice, quǽsumus, Dómine, super hanc famíliam tuam, pro qua Dóminus noster Jesus Christus."), "$Qui tecum");
+        assert_eq!(select_conclusion("O Lord Jesus Christ, who didst say.", "English"), "$Qui vivis");
+        assert_eq!(select_conclusion("Grant that we may behold thy Son.", "English"), "$Qui tecum");
+    }
+
+    fn suffragia() -> TestFiles {
+        TestFiles::default().with("Latin", "Psalterium/Special/Major Special.txt", &[
+            ("Suffragium", "Ant. Beáta Dei Génitrix.\n$Oremus\nA cunctis nos."),
+            ("Suffragium Paschale", "Ant. Crucifíxus surréxit.\n$Oremus\nDeus, qui pro nobis."),
+            ("Suffragium Laudes", "Ant. Sancta María.\n$Oremus\nConcéde nos fámulos tuos."),
+            ("Suffragium Adv Laudes", "Ant. Ecce Dóminus véniet.\n$Oremus\nDeus, qui de beátæ."),
+        ])
+    }
+
+    #[test]
+    fn test_suffragium_by_season_and_version() {
+        let mut config = InputConfig::new(Hora::Laudes);
+        config.version = "Divino Afflatu - 1954".to_string();
+        config.daynames = vec!["Epi3-2".to_string(), "Feria III".to_string()];
+        config.rank = 1.0;
+        let found = suffragium(&config, &mut suffragia(), "Latin").unwrap();
+        assert_eq!((found.kind, found.key.as_str()), (SuffragiumKind::Sanctorum, "Suffragium"));
+
+        config.daynames[0] = "Pasc2-2".to_string();
+        let found = suffragium(&config, &mut suffragia(), "Latin").unwrap();
+        assert_eq!((found.kind, found.kind.comment()), (SuffragiumKind::Crucis, 2));
+        assert!(found.text.contains("Crucifíxus"));
+
+        // Omitted in Passiontide and on doubles.
+        config.daynames[0] = "Quad5-2".to_string();
+        assert!(suffragium(&config, &mut suffragia(), "Latin").is_none());
+        config.daynames[0] = "Epi3-2".to_string();
+        config.winner_map.insert("Rank".to_string(), "S. Timothei;;Duplex;;3".to_string());
+        assert!(suffragium(&config, &mut suffragia(), "Latin").is_none());
+        config.winner_map.insert("Rank".to_string(), "S. Timothei;;Semiduplex;;2.2".to_string());
+        assert!(suffragium(&config, &mut suffragia(), "Latin").is_some());
+
+        // The Tridentine commemorations of Advent replace the ordinary ones.
+        config.version = "Tridentine - 1570".to_string();
+        config.daynames[0] = "Adv1-2".to_string();
+        let found = suffragium(&config, &mut suffragia(), "Latin").unwrap();
+        assert_eq!((found.kind, found.key.as_str()), (SuffragiumKind::Tridentinum, "Suffragium Adv Laudes"));
+
+        // Abolished in 1955 and 1960.
+        config.version = "Rubrics 1960 - 1960".to_string();
+        assert!(suffragium(&config, &mut suffragia(), "Latin").is_none());
+        config.version = "Reduced - 1955".to_string();
+        assert!(suffragium(&config, &mut suffragia(), "Latin").is_none());
+    }
+}
\ No newline at end of file
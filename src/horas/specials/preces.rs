@@ -2,17 +2,18 @@
 //!
 //! This module implements routines from `/horas/specials/preces.pl`.
 //!
-//! It provides two public functions:
+//! It provides three public functions:
 //! 
-//! - `preces(item: &str) -> bool` – decides whether to use preces (returns true) or to omit them.
-//! - `get_preces(hora: &str, lang: &str, flag: bool) -> Option<String>` – returns the preces text.
+//! - `preces_required(version, dayname, rank, hora) -> PrecesKind` – the rule table of the preces.
+//! - `preces(config, files, item) -> bool` – decides whether to use preces (returns true) or to omit them.
+//! - `get_preces(config, files, lang, flag) -> Option<String>` – returns the preces text.
 //!
 //! The module has been broken into several helper functions:
 //!
-//! - `handle_dominicales_branch(...) -> Option<bool>`  
+//! - `handle_dominicales_branch(config, files, item) -> Option<bool>`  
 //!   (Returns Some(true) if the “Dominicales” branch dictates preces should be used.)
 //!
-//! - `handle_feriales_branch(...) -> bool`  
+//! - `handle_feriales_branch(config, item) -> bool`  
 //!   (Returns true if the “Feriales” branch conditions are met.)
 //!
 //! All regex checks have been replaced by simple string methods or by small custom functions.
@@ -22,18 +23,15 @@
 //! Tests at the end verify that our helper functions produce results equivalent
 //! to the original Perl regex checks.
 
-use once_cell::sync::Lazy;
 use std::collections::HashMap;
-use std::sync::Mutex;
 
-// Assume these external functions and globals are defined elsewhere:
-use crate::globals::{
-    get_commemoratio, get_commemoentries, get_daynames, get_dayofweek, get_duplex, get_rule,
-    get_version, get_winner, get_winner_map, get_hora, emberday, get_datafolder, get_day,
-};
-use crate::offices::officestring;
-use crate::setup_string::setupstring;
+use super::InputConfig;
+use crate::date::day_of_week;
+use crate::horas::rite::rite_of;
+use crate::horas::Hora;
 use crate::regex::contains_ci;
+use crate::rubrics::RubricsVersion;
+use crate::setup_string::{ResolveDirectives, SetupStringProvider};
 
 /// Returns the first nonempty value among the keys "Commemoratio", "Commemoratio 1", etc.
 fn check_commemoratio(map: &HashMap<String, String>) -> String {
@@ -42,219 +40,267 @@ fn check_commemoratio(map: &HashMap<String, String>) -> String {
         .or_else(|| map.get("Commemoratio 2"))
         .or_else(|| map.get("Commemoratio 3"))
         .cloned()
-        .unwrap_or_else(String::new)
+        .unwrap_or_default()
 }
 
-/// Helper to handle the Dominicales branch.
+/// Whether a commemorated office (by its `Rank` line and commemoratio text)
+/// excludes the Sunday preces: a double, or anything of an octave.
+fn excludes_dominicales(office: &HashMap<String, String>) -> bool {
+    let rank_str = office.get("Rank").cloned().unwrap_or_default();
+    rank_str
+        .split(";;")
+        .nth(2)
+        .and_then(|s| s.trim().parse::<f64>().ok())
+        .unwrap_or(0.0)
+        >= 3.0
+        || contains_ci(&rank_str, "octav")
+        || contains_ci(&check_commemoratio(office), "octav")
+}
+
+/// Whether the day is an ember day: the Wednesday, Friday and Saturday of the
+/// third week of Advent, of the first week of Lent, of Pentecost week, and of
+/// September (after the Exaltation of the Cross, or after the third Sunday of
+/// September in the 1960 rubrics).
+fn emberday(config: &InputConfig) -> bool {
+    if !matches!(config.dayofweek, 3 | 5 | 6) {
+        return false;
+    }
+    let dayname = config.dayname0();
+    if contains_ci(dayname, "Adv3") || contains_ci(dayname, "Quad1") || contains_ci(dayname, "Pasc7") {
+        return true;
+    }
+    if config.month != 9 {
+        return false;
+    }
+    // The ember Wednesday of September.
+    let wednesday = if config.rubrics().is_1960() {
+        let first = day_of_week(1, 9, config.year);
+        let third_sunday = 1 + (7 - first) % 7 + 14;
+        third_sunday + 3
+    } else {
+        let dow15 = day_of_week(15, 9, config.year);
+        15 + (10 - dow15) % 7
+    };
+    config.day >= wednesday && config.day <= wednesday + 3
+}
+
+/// Which preces, if any, the rubrics prescribe at an hour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrecesKind {
+    /// No preces.
+    Omit,
+    /// The Sunday preces of Prima and Completorium.
+    Dominicales,
+    /// The ferial preces.
+    Feriales,
+}
+
+/// The rule table of the preces, from the rubrics `version`, the day of the
+/// Proper of Time (`dayname`, e.g. "Quad1-3", or "093-3" for the ember days
+/// of September), the `rank` of the office of the day and the hour.
+///
+/// - Before 1955, the ferial preces are said at every hour but Matutinum on
+///   the ferias of Advent and Lent and on the ember days, and the Sunday
+///   preces at Prima and Completorium of every office below a double (at
+///   Completorium these are the only preces).
+/// - From 1955 on, the Sunday preces are abolished, and the ferial ones are
+///   said only at Laudes and Vespera of the Wednesdays and Fridays of Advent
+///   and Lent and of the ember days.
 ///
-/// It receives:
-/// - `item`: the original item string,
-/// - `winner`: a string from the globals,
-/// - `winner_map`: a map (from globals) of winner values,
-/// - `rule`: the current rule string,
-/// - `duplex`: a numeric value,
-/// - `seasonalflag`: a boolean flag,
-/// - `daynames`: vector of dayname strings,
-/// - `version`: the version string,
-/// - `commemoratio`: a map with commemoratio data,
-/// - `commemoentries`: a list of commemo entries,
-/// - `datafolder`: the datafolder string.
+/// Sunday, first Vespers of Sunday and the octaves of Ascension and Pentecost
+/// (Pasc6, Pasc7) never have ferial preces; doubles (rank 3 and above) have
+/// none at all. The exceptions that depend on the office itself (rules,
+/// commemorations, vigils) are left to `preces()`.
+pub fn preces_required(version: &RubricsVersion, dayname: &str, rank: f64, hora: Hora) -> PrecesKind {
+    if hora == Hora::Matutinum
+        || rank >= 3.0
+        || contains_ci(dayname, "pasc6")
+        || contains_ci(dayname, "pasc7")
+    {
+        return PrecesKind::Omit;
+    }
+    let dayofweek = dayname
+        .rsplit('-')
+        .next()
+        .and_then(|d| d.parse::<u32>().ok())
+        .unwrap_or(0);
+    let penitential = contains_ci(dayname, "adv")
+        || (contains_ci(dayname, "quad") && !contains_ci(dayname, "quadp"))
+        || is_ember_dayname(dayname);
+    if penitential && feriales_hour(version, dayname, dayofweek, hora) {
+        return PrecesKind::Feriales;
+    }
+    if !version.uses_1955_simplifications() && matches!(hora, Hora::Prima | Hora::Completorium) {
+        return PrecesKind::Dominicales;
+    }
+    PrecesKind::Omit
+}
+
+/// Whether the ferial preces may be said at `hora` of that day of the week:
+/// never on Sunday or at first Vespers of Sunday, and from 1955 on only at
+/// Laudes and Vespera of the Wednesdays and Fridays (or of the ember days).
+fn feriales_hour(version: &RubricsVersion, dayname: &str, dayofweek: u32, hora: Hora) -> bool {
+    if dayofweek == 0 || (dayofweek == 6 && hora == Hora::Vespera) || hora == Hora::Completorium {
+        return false;
+    }
+    if version.uses_1955_simplifications() || version.is_innovated() {
+        return hora.is_major() && (matches!(dayofweek, 3 | 5) || is_ember_dayname(dayname));
+    }
+    true
+}
+
+/// Whether the day of the Proper of Time is an ember day that may have the
+/// ferial preces: Wednesday, Friday or Saturday of Adv3, Quad1, or of the
+/// third week of September. Those of Pentecost fall in its octave (Pasc7),
+/// which has none.
+fn is_ember_dayname(dayname: &str) -> bool {
+    let ember_week = ["Adv3-", "Quad1-", "093-"]
+        .iter()
+        .any(|w| dayname.starts_with(w));
+    ember_week && (dayname.ends_with("-3") || dayname.ends_with("-5") || dayname.ends_with("-6"))
+}
+
+/// The day of the Proper of Time for `preces_required()`: the ember days of
+/// September are named after their week, as the `093-` files are.
+fn tempora_dayname(config: &InputConfig) -> String {
+    if config.month == 9 && emberday(config) {
+        format!("093-{}", config.dayofweek)
+    } else {
+        config.dayname0().to_string()
+    }
+}
+
+/// Helper to handle the Dominicales branch.
 ///
-/// If all conditions are met, it returns Some(true) indicating that preces should be used,
-/// otherwise it returns None.
+/// If the Sunday preces apply (no double or octave is commemorated and the
+/// winner is not in an octave), it returns Some(true) indicating that preces
+/// should be used, otherwise it returns None.
 fn handle_dominicales_branch(
+    config: &InputConfig,
+    files: &mut dyn SetupStringProvider,
     item: &str,
-    winner: &str,
-    winner_map: &HashMap<String, String>,
-    rule: &str,
-    duplex: f64,
-    seasonalflag: bool,
-    daynames: &[String],
-    version: &str,
-    commemoratio: &HashMap<String, String>,
-    commemoentries: &[String],
-    datafolder: &str,
 ) -> Option<bool> {
-    if !item.to_lowercase().contains("dominicales") {
+    if !contains_ci(item, "dominicales") {
+        return None;
+    }
+    let version = config.rubrics();
+    let kind = rite_of(&version).preces(&version, &tempora_dayname(config), config.rank, config.hora);
+    if kind == PrecesKind::Omit {
         return None;
     }
     let mut dominicales = true;
-    if !commemoratio.is_empty() {
-        let rank_str = commemoratio.get("Rank").unwrap_or(&String::new());
-        let parts: Vec<&str> = rank_str.split(";;").collect();
-        if parts
-            .get(2)
-            .and_then(|s| s.parse::<f64>().ok())
-            .unwrap_or(0.0)
-            >= 3.0
-            || rank_str.to_lowercase().contains("octav")
-            || check_commemoratio(commemoratio).to_lowercase().contains("octav")
-        {
+    if !config.commemoratio_map.is_empty() {
+        if excludes_dominicales(&config.commemoratio_map) {
             dominicales = false;
-        } else if !commemoentries.is_empty() {
-            for commemo in commemoentries.iter() {
+        } else {
+            for commemo in config.commemoentries.iter() {
                 let mut filename = commemo.clone();
                 // Append ".txt" if necessary.
-                if !std::path::Path::new(&format!("{}/Latin/{}", datafolder, filename))
-                    .exists()
-                    && !filename.to_lowercase().ends_with("txt")
-                {
+                if !filename.to_lowercase().ends_with("txt") {
                     filename.push_str(".txt");
                 }
-                let c = officestring("Latin", &filename, 0).unwrap_or_default();
-                let rank_c = c.get("Rank").unwrap_or(&String::new());
-                let parts_c: Vec<&str> = rank_c.split(";;").collect();
-                if parts_c
-                    .get(2)
-                    .and_then(|s| s.parse::<f64>().ok())
-                    .unwrap_or(0.0)
-                    >= 3.0
-                    || rank_c.to_lowercase().contains("octav")
-                    || check_commemoratio(&c).to_lowercase().contains("octav")
-                {
+                let c = files.setupstring("Latin", &filename, ResolveDirectives::All).unwrap_or_default();
+                if excludes_dominicales(&c) {
                     dominicales = false;
                 }
             }
         }
     }
+    let winner_rank = config.winners().get("Rank").cloned().unwrap_or_default();
     if dominicales
-        && (!contains_ci(
-            winner_map.get("Rank").unwrap_or(&String::new()),
-            "octav",
-        ) || contains_ci(
-            winner_map.get("Rank").unwrap_or(&String::new()),
-            "post octav",
-        ))
-        && !check_commemoratio(winner_map).to_lowercase().contains("octav")
+        && (!contains_ci(&winner_rank, "octav") || contains_ci(&winner_rank, "post octav"))
+        && !contains_ci(&check_commemoratio(config.winners()), "octav")
     {
-        // Here, we assume that calling get_preces("Feriales", ...) returns Some(_)
-        if get_preces("Feriales", "dummy_lang", false).is_some() {
-            return Some(true);
-        }
+        return Some(true);
     }
     None
 }
 
 /// Helper to handle the Feriales branch.
 ///
-/// Returns true if the conditions for the feriales branch are met.
-fn handle_feriales_branch(
-    item: &str,
-    dayofweek: u32,
-    hora: &str,
-    winner: &str,
-    rule: &str,
-    daynames: &[String],
-    version: &str,
-) -> bool {
-    if !item.to_lowercase().contains("feriales") {
+/// Returns true if the conditions for the feriales branch are met: the rule
+/// table prescribes them for a ferial office, or the office asks for them
+/// (its rule, or a vigil before 1955) at an hour that admits them.
+fn handle_feriales_branch(config: &InputConfig, item: &str) -> bool {
+    if !contains_ci(item, "feriales") {
         return false;
     }
-    if dayofweek == 0 || (dayofweek == 6 && hora == "Vespera") {
-        return false;
+    let version = config.rubrics();
+    let dayname = tempora_dayname(config);
+    let sancti = contains_ci(&config.winner, "sancti");
+    if !sancti
+        && rite_of(&version).preces(&version, &dayname, config.rank, config.hora) == PrecesKind::Feriales
+    {
+        return true;
     }
-    let cond1 = !winner.to_lowercase().contains("sancti")
-        && (rule.to_lowercase().contains("preces")
-            || (!daynames.is_empty()
-                && (daynames[0].to_lowercase().contains("adv")
-                    || (daynames[0].to_lowercase().contains("quad")
-                        && !daynames[0].to_lowercase().contains("quadp")))
-            )
-            || emberday());
-    let cond2 = (!version.contains("1955")
-        && !version.contains("1960")
-        && !version.contains("Newcal")
-        && winner
-            .to_lowercase()
-            .contains("vigil")
-        && daynames.get(1).map_or(false, |s| {
-            !s.to_lowercase().contains("epi") && !s.to_lowercase().contains("pasc")
-        }));
-    let cond3 = !version.contains("1955")
-        && !version.contains("1960")
-        && !version.contains("Newcal")
-        || (matches!(dayofweek, 3 | 5) || emberday());
-    cond1 || cond2 && cond3
+    let by_rule = !sancti && contains_ci(&config.rule, "preces");
+    let vigil = !version.uses_1955_simplifications()
+        && !version.is_innovated()
+        && contains_ci(&config.winner, "vigil")
+        && config
+            .daynames
+            .get(1)
+            .is_some_and(|s| !contains_ci(s, "epi") && !contains_ci(s, "pasc"));
+    (by_rule || vigil) && feriales_hour(&version, &dayname, config.dayofweek, config.hora)
 }
 
 /// Public function `preces` returns true if preces should be used, false otherwise.
-pub fn preces(item: &str) -> bool {
-    // Get global values.
-    let winner = get_winner();
-    let rule = get_rule();
-    let duplex = get_duplex();
-    let seasonalflag = crate::globals::get_seasonalflag();
-    let daynames = get_daynames();
-    let version = get_version();
-    let commemoratio = get_commemoratio(); // HashMap<String, String>
-    let commemoentries = get_commemoentries(); // Vec<String>
-    let hora = get_hora();
-    let dayofweek = get_dayofweek();
-    let datafolder = get_datafolder();
+pub fn preces(config: &InputConfig, files: &mut dyn SetupStringProvider, item: &str) -> bool {
+    let rule = &config.rule;
+    let dayname = config.dayname0();
 
     // Early return if any of the following conditions are met:
-    if contains_ci(&winner, "C12")
-        || (rule.to_lowercase().contains("omit") && rule.to_lowercase().contains(" preces"))
-        || (duplex > 2.0 && seasonalflag)
-        || (!daynames.is_empty()
-            && (daynames[0].to_lowercase().contains("pasc6")
-                || daynames[0].to_lowercase().contains("pasc7")))
+    if contains_ci(&config.winner, "C12")
+        || (contains_ci(rule, "omit") && contains_ci(rule, " preces"))
+        || (config.duplex > 2 && config.seasonalflag)
+        || contains_ci(dayname, "pasc6")
+        || contains_ci(dayname, "pasc7")
     {
         return false;
     }
 
     // Check Dominicales branch:
-    if let Some(true) = handle_dominicales_branch(
-        item,
-        &winner,
-        &get_winner_map(),
-        rule,
-        duplex,
-        seasonalflag,
-        &daynames,
-        &version,
-        &commemoratio,
-        &commemoentries,
-        &datafolder,
-    ) {
+    if let Some(true) = handle_dominicales_branch(config, files, item) {
         return true;
     }
 
     // Check Feriales branch:
-    if handle_feriales_branch(item, dayofweek, &hora, &winner, rule, &daynames, &version) {
-        return true;
-    }
-
-    false
+    handle_feriales_branch(config, item)
 }
 
 /// Public function `get_preces` returns the preces text based on the current hour.
 /// The `flag` parameter indicates whether we are using the 'Dominicales' variant.
-pub fn get_preces(hora: &str, lang: &str, flag: bool) -> Option<String> {
-    let version = get_version();
-    let (src, key) = if hora == "Tertia" || hora == "Sexta" || hora == "Nona" {
+pub fn get_preces(
+    config: &InputConfig,
+    files: &mut dyn SetupStringProvider,
+    lang: &str,
+    flag: bool,
+) -> Option<String> {
+    let hora = config.hora;
+    let (src, key) = if hora.is_minor() {
         ("Minor", "Feriales".to_string())
-    } else if hora == "Laudes" || hora == "Vespera" {
+    } else if hora.is_major() {
         ("Major", format!("feriales {}", hora))
-    } else if hora == "Completorium" {
+    } else if hora == Hora::Completorium {
         ("Minor", "Dominicales".to_string())
     } else if flag {
-        let src = "Prima";
-        let mod_val = if version.starts_with("Monastic") { 1 } else { 2 };
-        let mut counter = PREC_DOMFER.lock().unwrap();
-        let value = ((*counter + 1) % mod_val) + 1;
-        *counter += 1;
-        (src, format!("Dominicales Prima {}", value))
+        let mod_val = if config.version.starts_with("Monastic") { 1 } else { 2 };
+        let counter = config.precdomfer.get();
+        let value = ((counter + 1) % mod_val) + 1;
+        config.precdomfer.set(counter + 1);
+        ("Prima", format!("Dominicales Prima {}", value))
     } else {
         ("Prima", "feriales Prima".to_string())
     };
 
-    if let Some(brevis_map) = setupstring(lang, &format!("Psalterium/Special/{} Special.txt", src), &[]) {
-        return brevis_map.get(&format!("Preces {}", key)).cloned();
-    }
-    None
+    let brevis_map = files.setupstring(
+        lang,
+        &format!("Psalterium/Special/{} Special.txt", src),
+        ResolveDirectives::All,
+    )?;
+    brevis_map.get(&format!("Preces {}", key)).cloned()
 }
- 
+
 // =============================================================================
 // Tests
 // =============================================================================
@@ -262,6 +308,7 @@ pub fn get_preces(hora: &str, lang: &str, flag: bool) -> Option<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::horas::specials::tests::TestFiles;
 
     #[test]
     fn test_contains_ci() {
@@ -280,43 +327,80 @@ mod tests {
     #[test]
     fn test_handle_dominicales_branch_returns_none_when_not_applicable() {
         // If the item does not contain "dominicales", it should return None.
-        let result = handle_dominicales_branch(
-            "Some other item",
-            "winner",
-            &HashMap::new(),
-            "Some rule",
-            1.0,
-            false,
-            &vec!["Monday".to_string()],
-            "TestVersion",
-            &HashMap::new(),
-            &vec![],
-            "datafolder",
-        );
+        let config = InputConfig::new(Hora::Prima);
+        let result = handle_dominicales_branch(&config, &mut TestFiles::default(), "Some other item");
         assert!(result.is_none());
     }
 
     #[test]
     fn test_parse_feriales_branch() {
         // For a sample input that should trigger the feriales branch.
-        let daynames = vec!["Adv".to_string(), "SomeSecond".to_string()];
-        let result = handle_feriales_branch(
-            "Feriales",
-            3,                  // dayofweek nonzero and not Saturday (6)
-            "Laudes",
-            "non sancti text",  // winner does not contain "sancti"
-            "Preces something",
-            &daynames,
-            "TestVersion",
-        );
-        // Depending on emberday() (stubbed as false) and other conditions,
-        // we expect true if the conditions are met.
-        assert!(result);
+        let mut config = InputConfig::new(Hora::Laudes);
+        config.daynames = vec!["Adv".to_string(), "SomeSecond".to_string()];
+        config.dayofweek = 3; // nonzero and not Saturday (6)
+        config.winner = "Tempora/Adv1-3.txt".to_string(); // winner does not contain "sancti"
+        config.rule = "Preces something".to_string();
+        config.version = "TestVersion".to_string();
+        assert!(handle_feriales_branch(&config, "Feriales"));
+        // Never on Sunday.
+        config.dayofweek = 0;
+        assert!(!handle_feriales_branch(&config, "Feriales"));
+    }
+
+    #[test]
+    fn test_preces_required() {
+        use PrecesKind::*;
+        let trident = RubricsVersion::parse("Tridentine - 1570");
+        let divino = RubricsVersion::parse("Divino Afflatu - 1954");
+        let r1960 = RubricsVersion::parse("Rubrics 1960 - 1960");
+        let cases: &[(&str, f64, Hora, PrecesKind, PrecesKind)] = &[
+            // (dayname, rank, hora, before 1955, 1960)
+            ("Quad1-3", 2.1, Hora::Laudes, Feriales, Feriales),
+            ("Quad1-2", 2.1, Hora::Laudes, Feriales, Omit),
+            ("Quad1-3", 2.1, Hora::Tertia, Feriales, Omit),
+            ("Adv1-2", 1.0, Hora::Prima, Feriales, Omit),
+            ("Adv1-2", 1.0, Hora::Completorium, Dominicales, Omit),
+            ("Adv1-6", 1.0, Hora::Vespera, Omit, Omit),
+            ("Adv1-0", 1.0, Hora::Laudes, Omit, Omit),
+            ("Adv1-0", 1.0, Hora::Prima, Dominicales, Omit),
+            ("Pent05-2", 1.0, Hora::Prima, Dominicales, Omit),
+            ("Pent05-2", 1.0, Hora::Laudes, Omit, Omit),
+            ("093-6", 1.5, Hora::Laudes, Feriales, Feriales),
+            ("Pasc7-3", 1.5, Hora::Laudes, Omit, Omit),
+            ("Quad1-3", 3.0, Hora::Laudes, Omit, Omit),
+            ("Quad1-3", 2.1, Hora::Matutinum, Omit, Omit),
+        ];
+        for &(dayname, rank, hora, older, modern) in cases {
+            assert_eq!(preces_required(&trident, dayname, rank, hora), older, "1570 {} {}", dayname, hora);
+            assert_eq!(preces_required(&divino, dayname, rank, hora), older, "DA {} {}", dayname, hora);
+            assert_eq!(preces_required(&r1960, dayname, rank, hora), modern, "1960 {} {}", dayname, hora);
+        }
+    }
+
+    #[test]
+    fn test_emberday() {
+        let mut config = InputConfig::new(Hora::Laudes);
+        config.daynames = vec!["Pent16-3".to_string()];
+        config.dayofweek = 3;
+        config.month = 9;
+        config.year = 2025;
+        // 2025-09-14 is a Sunday; the third Sunday of September is the 21st.
+        config.version = "Divino Afflatu".to_string();
+        config.day = 17;
+        assert!(emberday(&config));
+        config.version = "Rubrics 1960 - 1960".to_string();
+        assert!(!emberday(&config));
+        config.day = 24;
+        assert!(emberday(&config));
+        config.daynames = vec!["Quad1-3".to_string()];
+        config.month = 3;
+        assert!(emberday(&config));
     }
 
     #[test]
     fn test_get_preces_returns_none_if_setupstring_fails() {
-        // Without a proper override of setupstring, get_preces should return None.
-        assert_eq!(get_preces("Tertia", "Latin", false), None);
+        // Without a data file, get_preces should return None.
+        let config = InputConfig::new(Hora::Tertia);
+        assert_eq!(get_preces(&config, &mut TestFiles::default(), "Latin", false), None);
     }
 }
@@ -0,0 +1,112 @@
+//! proprium.rs
+//!
+//! The lookup of a text of the hour through the whole chain of sources, as
+//! the chapters of `specials.pl` do it one after the other: the proper of the
+//! office (`getproprium()`), its commune, the season of the Psalterium and
+//! the Psalterium of the day of the week.
+//!
+//! The result names the source that matched (a `TextSource`), so that
+//! callers can annotate the heading of the chapter with its comment.
+
+use super::{getproprium, gettempora, InputConfig, TextSource};
+use crate::horas::Hora;
+use crate::setup_string::{ResolveDirectives, SetupStringProvider};
+
+/// A text found by `get_proprium()`: the text, where it was found and the
+/// file and section it came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Proprium {
+    pub text: String,
+    pub source: TextSource,
+    pub file: String,
+    pub key: String,
+}
+
+/// Looks up `key` (e.g. "Capitulum Laudes", "Ant Vespera") for the hour of
+/// `config`, in order:
+///
+/// 1. the office of the day (the winner);
+/// 2. its commune, if the office is taken "ex" the commune;
+/// 3. the season of the Psalterium: "{season} {key}" (as `gettempora()`
+///    names the season, e.g. "Adv Capitulum Laudes");
+/// 4. the Psalterium of the day of the week, "Day{n} {key}", and then its
+///    common text, "{key}".
+///
+/// The Psalterium file is the `Special` file of the hour: `Major Special`
+/// for Lauds and Vespers, `Prima Special`, `Matutinum Special`, and `Minor
+/// Special` for the other hours. Returns `None` if no source has the key.
+pub fn get_proprium(
+    config: &InputConfig,
+    files: &mut dyn SetupStringProvider,
+    key: &str,
+    lang: &str,
+) -> Option<Proprium> {
+    let (text, source) = getproprium(config, key, false, true);
+    if let Some(text) = text {
+        let file = if source == TextSource::Commune { &config.commune } else { &config.winner };
+        return Some(Proprium { text, source, file: file.clone(), key: key.to_string() });
+    }
+
+    let file = psalterium_file(config.hora);
+    let psalterium = files.setupstring(lang, &file, ResolveDirectives::All)?;
+    let season = gettempora(config, "");
+    let candidates = [
+        (TextSource::Season, (!season.is_empty()).then(|| format!("{} {}", season, key))),
+        (TextSource::Psalterium, Some(format!("Day{} {}", config.dayofweek, key))),
+        (TextSource::Psalterium, Some(key.to_string())),
+    ];
+    candidates.into_iter().find_map(|(source, name)| {
+        let name = name?;
+        let text = psalterium.get(&name)?.clone();
+        config.build.setbuild(&file, &name, "ord");
+        Some(Proprium { text, source, file: file.clone(), key: name })
+    })
+}
+
+/// The `Special` file of the Psalterium for `hora`.
+fn psalterium_file(hora: Hora) -> String {
+    let name = match hora {
+        Hora::Laudes | Hora::Vespera => "Major",
+        Hora::Prima => "Prima",
+        Hora::Matutinum => "Matutinum",
+        _ => "Minor",
+    };
+    format!("Psalterium/Special/{} Special.txt", name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::horas::specials::tests::TestFiles;
+
+    #[test]
+    fn test_get_proprium_chain() {
+        let mut config = InputConfig::new(Hora::Laudes);
+        config.winner = "Sancti/01-21.txt".to_string();
+        config.commune = "Commune/C6.txt".to_string();
+        config.communetype = "ex".to_string();
+        config.dayofweek = 2;
+        config.daynames = vec!["Epi2-2".to_string()];
+        config.winner_map.insert("Oratio".to_string(), "Omnípotens sempitérne Deus.".to_string());
+        config.commune_map.insert("Capitulum Laudes".to_string(), "Dómine Deus meus.".to_string());
+        let mut files = TestFiles::default().with("Latin", "Psalterium/Special/Major Special.txt", &[
+            ("Adv Hymnus Laudes", "Vox clara ecce íntonat"),
+            ("Day2 Hymnus Laudes", "Ales diéi núntius"),
+            ("Versum Laudes", "V. Repléti sumus mane misericórdia tua."),
+        ]);
+
+        let found = get_proprium(&config, &mut files, "Oratio", "Latin").unwrap();
+        assert_eq!((found.source, found.source.line()), (TextSource::Sancti, 3));
+        let found = get_proprium(&config, &mut files, "Capitulum Laudes", "Latin").unwrap();
+        assert_eq!((found.source, found.file.as_str()), (TextSource::Commune, "Commune/C6.txt"));
+        let found = get_proprium(&config, &mut files, "Hymnus Laudes", "Latin").unwrap();
+        assert_eq!((found.source, found.text.as_str()), (TextSource::Psalterium, "Ales diéi núntius"));
+        let found = get_proprium(&config, &mut files, "Versum Laudes", "Latin").unwrap();
+        assert_eq!(found.key, "Versum Laudes");
+
+        config.daynames = vec!["Adv1-2".to_string()];
+        let found = get_proprium(&config, &mut files, "Hymnus Laudes", "Latin").unwrap();
+        assert_eq!((found.source, found.key.as_str()), (TextSource::Season, "Adv Hymnus Laudes"));
+        assert!(get_proprium(&config, &mut files, "Lectio1", "Latin").is_none());
+    }
+}
@@ -0,0 +1,212 @@
+//! responsoria.rs
+//!
+//! The short responsories (responsoria brevia) of the little hours and of
+//! Compline, with the versicle that follows them, as `capitulum_prima()` and
+//! `capitulum_minor()` put them after the chapter.
+//!
+//! At Prima the responsory "Christe, Fili Dei vivi" takes the verse of the
+//! season ("Qui natus es de María Vírgine", `get_prima_responsory()`); at
+//! Terce, Sext and None the office may have its own responsory, otherwise
+//! it is the one of the season or of the Sundays and ferias in `Minor
+//! Special`; at Compline it is always "In manus tuas".
+//!
+//! Two seasons change the responsory: in Paschaltide the response gets a
+//! double alleluia and the verse a single one (`postprocess_short_resp()`),
+//! and in Passiontide the Gloria Patri is not said but the response is
+//! repeated in full.
+//!
+//! ```ignore
+//! let resp = responsoria_brevia(&mut ctx, "03-31-2025", Hora::Sexta, "Latin", "Divino Afflatu")?;
+//! ```
+
+use std::io;
+
+use super::specprima::get_prima_responsory;
+use super::{getproprium, gettempora, InputConfig};
+use crate::horas::officium::{office_config, Kalendar};
+use crate::horas::postprocess::postprocess_short_resp;
+use crate::horas::Hora;
+use crate::rubrics::GloriaPart;
+use crate::setup_string::{ResolveDirectives, SetupStringContext, SetupStringProvider};
+
+/// Returns the short responsory of the hour of `config` with its versicle,
+/// or `None` at the hours that have none (Matins, Lauds and Vespers) or if
+/// the data have none.
+pub fn responsorium_breve(
+    config: &InputConfig,
+    files: &mut dyn SetupStringProvider,
+    lang: &str,
+) -> Option<String> {
+    let mut lines = match config.hora {
+        Hora::Prima => prima(config, files, lang),
+        Hora::Tertia | Hora::Sexta | Hora::Nona | Hora::Completorium => minor(config, files, lang),
+        _ => return None,
+    };
+    if lines.is_empty() {
+        return None;
+    }
+    postprocess_responsory(&mut lines, config, files, lang);
+    Some(lines.join("\n"))
+}
+
+/// The short responsory of the hour on `date` ("MM-DD-YYYY") in `lang`
+/// according to `version`, as `responsorium_breve()` gives it.
+///
+/// Fails with `InvalidInput` for a malformed date.
+pub fn responsoria_brevia(
+    ctx: &mut SetupStringContext,
+    date: &str,
+    hora: Hora,
+    lang: &str,
+    version: &str,
+) -> io::Result<Option<String>> {
+    let office = Kalendar::new(version).office(ctx, date, lang)?;
+    let config = office_config(ctx, &office, hora, lang, 1);
+    Ok(responsorium_breve(&config, ctx, lang))
+}
+
+/// Applies the changes of the season to the lines of a short responsory:
+/// the Gloria Patri is replaced by the response in Passiontide, and the
+/// alleluias are added in Paschaltide.
+pub(crate) fn postprocess_responsory(
+    lines: &mut [String],
+    config: &InputConfig,
+    files: &mut dyn SetupStringProvider,
+    lang: &str,
+) {
+    if !config.gloria_patri(GloriaPart::ResponsoriumBreve) {
+        omit_gloria(lines);
+    }
+    postprocess_short_resp(lines, config.dayname0(), &config.votive, files, lang);
+}
+
+/// Replaces the `&Gloria` of each responsory by its response said in full.
+fn omit_gloria(lines: &mut [String]) {
+    let mut response = None;
+    for line in lines.iter_mut() {
+        if let Some(text) = line.strip_prefix("R.br.") {
+            response = Some(format!("R. {}", text.trim_start()));
+        } else if line.starts_with("&Gloria") {
+            if let Some(response) = &response {
+                *line = response.clone();
+            }
+        }
+    }
+}
+
+/// The responsory of Prima, its verse taken from the season or the office.
+pub(crate) fn prima(config: &InputConfig, files: &mut dyn SetupStringProvider, lang: &str) -> Vec<String> {
+    let brevis_map = files
+        .setupstring(lang, "Psalterium/Special/Prima Special.txt", ResolveDirectives::All)
+        .unwrap_or_default();
+    let mut lines: Vec<String> = brevis_map
+        .get("Responsory")
+        .map(|r| r.lines().map(str::to_string).collect())
+        .unwrap_or_default();
+    let mut verse = get_prima_responsory(config, files, lang);
+    if let Some(val) = config.winners().get("Versum Prima") {
+        verse = val.clone();
+    }
+    if !verse.is_empty() && lines.len() > 2 {
+        lines[2] = format!("V. {}", verse);
+    }
+    lines.push("_".to_string());
+    if let Some(versum) = brevis_map.get("Versum") {
+        lines.extend(versum.lines().map(str::to_string));
+    }
+    lines
+}
+
+/// The responsory of Terce, Sext, None or Compline: that of the office if
+/// it has one, otherwise that of `Minor Special`.
+fn minor(config: &InputConfig, files: &mut dyn SetupStringProvider, lang: &str) -> Vec<String> {
+    let hora = config.hora;
+    let monastic = config.rubrics().monastic;
+    let proper = if hora == Hora::Completorium {
+        None
+    } else {
+        let key = format!("Responsory {}{}", hora, if monastic { "M" } else { "" });
+        getproprium(config, &key, config.seasonalflag, true).0.or_else(|| {
+            let breve = format!("Responsory Breve {}", hora);
+            getproprium(config, &breve, config.seasonalflag, true).0.filter(|_| !monastic)
+        })
+    };
+    let text = proper.or_else(|| {
+        let capit_map = files.setupstring(lang, "Psalterium/Special/Minor Special.txt", ResolveDirectives::All)?;
+        let mut name = match hora {
+            Hora::Completorium => "Completorium".to_string(),
+            _ => format!("{} {}", gettempora(config, "Capitulum minor"), hora),
+        };
+        if monastic {
+            name.push('M');
+        }
+        match capit_map.get(&format!("Responsory {}", name)) {
+            Some(r) => Some(r.trim_end().to_string()),
+            None => {
+                let r = capit_map.get(&format!("Responsory breve {}", name))?;
+                let v = capit_map.get(&format!("Versum {}", name))?;
+                Some(format!("{}\n_\n{}", r.trim_end(), v.trim_end()))
+            }
+        }
+    });
+    text.map(|t| t.lines().map(str::to_string).collect()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::horas::specials::tests::TestFiles;
+
+    fn minor_special() -> TestFiles {
+        TestFiles::default().with("Latin", "Psalterium/Special/Minor Special.txt", &[
+            (
+                "Responsory Feria Sexta",
+                "R.br. In ætérnum, Dómine, * Pérmanet verbum tuum.\nR. In ætérnum, Dómine, * Pérmanet verbum tuum.\n\
+                 V. In sǽculum sǽculi véritas tua.\nR. Pérmanet verbum tuum.\n&Gloria1\n\
+                 R. In ætérnum, Dómine, * Pérmanet verbum tuum.\n_\nV. Dóminus regit me.\nR. In loco páscuæ ibi me collocávit.",
+            ),
+            (
+                "Responsory Quad5 Sexta",
+                "R.br. Érue a frámea, * Deus, ánimam meam.\nR. Érue a frámea, * Deus, ánimam meam.\n\
+                 V. Et de manu canis únicam meam.\nR. Deus, ánimam meam.\n&Gloria1\n\
+                 R. Érue a frámea, * Deus, ánimam meam.\n_\nV. Salva me ex ore leónis.\nR. Et a córnibus unicórnium humilitátem meam.",
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_responsorium_breve_seasons() {
+        let mut config = InputConfig::new(Hora::Sexta);
+        config.dayofweek = 3;
+        config.daynames = vec!["Pent05-3".to_string()];
+        let mut files = minor_special();
+        let resp = responsorium_breve(&config, &mut files, "Latin").unwrap();
+        assert!(resp.starts_with("R.br. In ætérnum, Dómine, * Pérmanet verbum tuum.\n"));
+        assert!(resp.contains("\n&Gloria1\n"));
+
+        // In Passiontide the response is repeated instead of the Gloria.
+        config.daynames = vec!["Quad5-3".to_string()];
+        let resp = responsorium_breve(&config, &mut files, "Latin").unwrap();
+        let lines: Vec<&str> = resp.lines().collect();
+        assert!(!resp.contains("&Gloria"));
+        assert_eq!(lines[4], "R. Érue a frámea, * Deus, ánimam meam.");
+
+        assert!(responsorium_breve(&InputConfig::new(Hora::Laudes), &mut files, "Latin").is_none());
+    }
+
+    #[test]
+    fn test_responsorium_breve_proper() {
+        let mut config = InputConfig::new(Hora::Nona);
+        config.winner_map.insert("Responsory Nona".to_string(), "R.br. Elégit eam Deus.\n_\nV. Adjuvábit eam.".to_string());
+        let resp = responsorium_breve(&config, &mut minor_special(), "Latin").unwrap();
+        assert_eq!(resp, "R.br. Elégit eam Deus.\n_\nV. Adjuvábit eam.");
+    }
+
+    #[test]
+    fn test_omit_gloria() {
+        let mut lines: Vec<String> =
+            ["R.br. Christe, * Miserére nobis.", "V. Qui sedes.", "&Gloria1"].iter().map(|s| s.to_string()).collect();
+        omit_gloria(&mut lines);
+        assert_eq!(lines[2], "R. Christe, * Miserére nobis.");
+    }
+}
@@ -0,0 +1,130 @@
+//! specials_build.rs
+//!
+//! The "building script": a log of which files and sections were used to
+//! assemble an hour, shown by the Perl site when build mode is on.
+//!
+//! The Perl code appends to the global `$buildscript` from anywhere in the
+//! specials. Here the log is a `BuildTrace` kept in the `InputConfig` of the
+//! hour, so that each hour (and each column) has its own; it records the
+//! steps as entries and renders them as the lines of the Perl script.
+
+use std::cell::RefCell;
+
+/// One step of the building script: the section `key` of `source_file` used
+/// for `action` (e.g. "Capitulum ord", "subst"). A step about a whole chapter
+/// has no file, and a free-form remark has neither file nor key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BuildEntry {
+    pub source_file: Option<String>,
+    pub key: Option<String>,
+    pub action: String,
+}
+
+impl BuildEntry {
+    /// The line of the entry in the building script.
+    pub fn line(&self) -> String {
+        match (&self.source_file, &self.key) {
+            (Some(file), Some(key)) => format!("{}: {}:{}", self.action, file, key),
+            (Some(file), None) => format!("{}: {}", self.action, file),
+            (None, Some(key)) => format!("{}: {}", key, self.action),
+            (None, None) => format!(",,,{}", self.action),
+        }
+    }
+}
+
+/// The building script of an hour: a header and the steps recorded by the
+/// `setbuild*` methods, in order.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BuildTrace {
+    header: RefCell<String>,
+    entries: RefCell<Vec<BuildEntry>>,
+}
+
+impl BuildTrace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn push(&self, source_file: Option<String>, key: Option<String>, action: &str) {
+        self.entries.borrow_mut().push(BuildEntry { source_file, key, action: action.to_string() });
+    }
+
+    /// Starts a new building script with the given header (the
+    /// `$buildscript = ...` at the start of `specials()`).
+    pub fn set_buildscript(&self, header: &str) {
+        *self.header.borrow_mut() = header.to_string();
+        self.entries.borrow_mut().clear();
+    }
+
+    /// Records that section `name` of `file` was used, e.g.
+    /// `setbuild("Psalterium/Special/Major Special", "Day0 Laudes", "Capitulum ord")`.
+    pub fn setbuild(&self, file: &str, name: &str, ord: &str) {
+        let file = file.trim_end_matches(".txt");
+        self.push(Some(file.to_string()), Some(name.to_string()), ord);
+    }
+
+    /// Records what was done with a whole chapter, e.g. `setbuild1("#Preces", "omit")`.
+    pub fn setbuild1(&self, label: &str, comment: &str) {
+        let label: String = label.chars().filter(|c| *c != '#' && *c != ',').collect();
+        self.push(None, Some(label.trim().to_string()), comment);
+    }
+
+    /// Records a free-form remark, e.g. `setbuild2("Hymnus shifted")`.
+    pub fn setbuild2(&self, comment: &str) {
+        self.push(None, None, comment);
+    }
+
+    /// The steps recorded so far.
+    pub fn entries(&self) -> Vec<BuildEntry> {
+        self.entries.borrow().clone()
+    }
+
+    /// Returns the building script as the Perl site shows it: the header,
+    /// then one line per step.
+    pub fn dump(&self) -> String {
+        let mut script = self.header.borrow().clone();
+        for entry in self.entries.borrow().iter() {
+            script.push_str(&entry.line());
+            script.push('\n');
+        }
+        script
+    }
+
+    /// Returns the building script collected so far and clears it.
+    pub fn take_buildscript(&self) -> String {
+        let script = self.dump();
+        self.header.borrow_mut().clear();
+        self.entries.borrow_mut().clear();
+        script
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buildscript_lines() {
+        let trace = BuildTrace::new();
+        trace.set_buildscript("Laudes\n");
+        trace.setbuild("Psalterium/Special/Major Special.txt", "Day0 Laudes", "Capitulum ord");
+        trace.setbuild1("#Preces", "omit");
+        trace.setbuild2("Hymnus shifted");
+        assert_eq!(
+            trace.entries()[0],
+            BuildEntry {
+                source_file: Some("Psalterium/Special/Major Special".to_string()),
+                key: Some("Day0 Laudes".to_string()),
+                action: "Capitulum ord".to_string(),
+            }
+        );
+        assert_eq!(
+            trace.take_buildscript(),
+            "Laudes\nCapitulum ord: Psalterium/Special/Major Special:Day0 Laudes\nPreces: omit\n,,,Hymnus shifted\n"
+        );
+        assert!(trace.entries().is_empty());
+        assert_eq!(trace.dump(), "");
+    }
+}
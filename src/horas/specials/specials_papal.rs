@@ -0,0 +1,200 @@
+//! specials_papal.rs
+//!
+//! The offices of the Popes: the Perl `papal_*` subroutines.
+//!
+//! A feast of a Pope says so in its rule, with a clause naming the Pope and
+//! his class: `OPapaM=Cornelius;` for the office of a martyr Pope,
+//! `OPapaeM=Soter et Caius;` for several, and `CPapaC=...;` for a Pope who
+//! is only commemorated. The class is `C` (confessor), `M` (martyr) or `D`
+//! (doctor). Such an office takes its prayers from the common of the Supreme
+//! Pontiffs (the "Si diligis me" common): `Commune/C2b` for martyrs and
+//! `Commune/C4b` for the others, with the name of the Pope put for the "N."
+//! of the prayer.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::setup_string::{FileSections, ResolveDirectives, SetupStringProvider};
+
+/// The class of a Pope in his office.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PopeClass {
+    Confessor,
+    Martyr,
+    Doctor,
+}
+
+impl PopeClass {
+    fn from_char(c: char) -> Option<Self> {
+        match c.to_ascii_uppercase() {
+            'C' => Some(PopeClass::Confessor),
+            'M' => Some(PopeClass::Martyr),
+            'D' => Some(PopeClass::Doctor),
+            _ => None,
+        }
+    }
+
+    /// The common of the Supreme Pontiffs for the class.
+    pub fn commune(self) -> &'static str {
+        match self {
+            PopeClass::Martyr => "Commune/C2b.txt",
+            PopeClass::Confessor | PopeClass::Doctor => "Commune/C4b.txt",
+        }
+    }
+}
+
+/// The papal clause of a rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PapalRule {
+    /// True for the office of several Popes (`OPapae...`).
+    pub plural: bool,
+    pub class: PopeClass,
+    /// The name (or names) of the Pope, as the prayers say it.
+    pub name: String,
+}
+
+static PAPAL_CLAUSE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\b([OC])Papa(e)?([CMD])=(.*?);").unwrap());
+
+/// Returns the papal clause of `rule`: the office of a Pope (`OPapa...`), or
+/// with `commemoration` his commemoration (`CPapa...`).
+pub fn papal_rule(rule: &str, commemoration: bool) -> Option<PapalRule> {
+    let wanted = if commemoration { "C" } else { "O" };
+    PAPAL_CLAUSE.captures_iter(rule).find_map(|caps| {
+        if !caps[1].eq_ignore_ascii_case(wanted) {
+            return None;
+        }
+        Some(PapalRule {
+            plural: caps.get(2).is_some(),
+            class: PopeClass::from_char(caps[3].chars().next()?)?,
+            name: caps[4].trim().to_string(),
+        })
+    })
+}
+
+/// The papal clause of the rule of `office`, with the name taken from the
+/// `[Name]` section of the file when the clause does not give it.
+pub fn papal_office(office: &FileSections, commemoration: bool) -> Option<PapalRule> {
+    let mut rule = papal_rule(office.get("Rule")?, commemoration)?;
+    if rule.name.is_empty() {
+        rule.name = pope_name(office).unwrap_or_default();
+    }
+    Some(rule)
+}
+
+/// The name of the Pope of a Sancti file: its `[Name]` section (the name as
+/// the prayers use it, e.g. "Cornélium"), or the name of its papal clause.
+pub fn pope_name(office: &FileSections) -> Option<String> {
+    office
+        .get("Name")
+        .map(|n| n.lines().next().unwrap_or("").trim().to_string())
+        .filter(|n| !n.is_empty())
+        .or_else(|| {
+            let rule = office.get("Rule")?;
+            papal_rule(rule, false).or_else(|| papal_rule(rule, true)).map(|r| r.name)
+        })
+        .filter(|n| !n.is_empty())
+}
+
+/// Returns the prayer `kind` ("Oratio", "Secreta", "Postcommunio") of the
+/// common of the Supreme Pontiffs for `rule`, with the name of the Pope. The
+/// prayer for several Popes is the section "{kind} pl".
+pub fn papal_prayer(
+    files: &mut dyn SetupStringProvider,
+    lang: &str,
+    rule: &PapalRule,
+    kind: &str,
+) -> Option<String> {
+    let common = files.setupstring(lang, rule.class.commune(), ResolveDirectives::All)?;
+    let prayer = if rule.plural {
+        common.get(&format!("{} pl", kind)).or_else(|| common.get(kind))
+    } else {
+        common.get(kind)
+    }?;
+    Some(replace_ndot(prayer, lang, &rule.name))
+}
+
+/// Returns the antiphon "Dum esset summus Pontifex" of the common of the
+/// Supreme Pontiffs, said at the Magnificat of a confessor Pope.
+pub fn papal_antiphon_dum_esset(files: &mut dyn SetupStringProvider, lang: &str) -> Option<String> {
+    let common = files.setupstring(lang, PopeClass::Confessor.commune(), ResolveDirectives::All)?;
+    common.get("Ant 3 summi Pontificis").cloned()
+}
+
+/// Replaces the "N." of a prayer of a common with `name`; "N. et N." (in the
+/// language of the prayer) stands for the names of several saints and is
+/// replaced as a whole. Nothing is replaced without a name.
+pub fn replace_ndot(text: &str, lang: &str, name: &str) -> String {
+    if name.is_empty() {
+        return text.to_string();
+    }
+    let and = match lang {
+        l if l.starts_with("English") => "and",
+        l if l.starts_with("Deutsch") => "und",
+        l if l.starts_with("Magyar") => "és",
+        l if l.starts_with("Espanol") => "y",
+        l if l.starts_with("Italiano") || l.starts_with("Portugues") => "e",
+        l if l.starts_with("Polski") || l.starts_with("Cesky") => "i",
+        _ => "et",
+    };
+    let pair = format!("N. {} N.", and);
+    let text = if text.contains(&pair) { text.replacen(&pair, name, 1) } else { text.to_string() };
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text.as_str();
+    while let Some(i) = rest.find("N.") {
+        let at_word = rest[..i].chars().last().is_none_or(|c| !c.is_alphanumeric());
+        out.push_str(&rest[..i]);
+        out.push_str(if at_word { name } else { "N." });
+        rest = &rest[i + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::horas::specials::tests::TestFiles;
+
+    #[test]
+    fn test_papal_rule() {
+        let rule = papal_rule("ex C2b;\nOPapaM=Cornélium;\n9 lectiones", false).unwrap();
+        assert_eq!(rule, PapalRule { plural: false, class: PopeClass::Martyr, name: "Cornélium".to_string() });
+        let rule = papal_rule("OPapaeC=Sotérem et Caium;", false).unwrap();
+        assert!(rule.plural);
+        assert!(papal_rule("OPapaC=Leónem;", true).is_none());
+        assert_eq!(papal_rule("CPapaD=Leónem;", true).unwrap().class, PopeClass::Doctor);
+        assert!(papal_rule("ex C4a;", false).is_none());
+
+        let office: FileSections = [("Rule", "OPapaC=;"), ("Name", "Pium\n")]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        assert_eq!(papal_office(&office, false).unwrap().name, "Pium");
+    }
+
+    #[test]
+    fn test_papal_prayer_and_ndot() {
+        let mut files = TestFiles::default()
+            .with("Latin", "Commune/C2b.txt", &[
+                ("Oratio", "Gregem tuum, Pastor ætérne, beáto N. Mártyre tuo atque Summo Pontífice."),
+                ("Oratio pl", "Gregem tuum, beátis N. et N. Mártyribus tuis atque Summis Pontifícibus."),
+            ])
+            .with("Latin", "Commune/C4b.txt", &[("Ant 3 summi Pontificis", "Dum esset summus Póntifex")]);
+        let rule = PapalRule { plural: false, class: PopeClass::Martyr, name: "Cornélio".to_string() };
+        assert_eq!(
+            papal_prayer(&mut files, "Latin", &rule, "Oratio").unwrap(),
+            "Gregem tuum, Pastor ætérne, beáto Cornélio Mártyre tuo atque Summo Pontífice."
+        );
+        let rule = PapalRule { plural: true, name: "Sotére et Caio".to_string(), ..rule };
+        assert_eq!(
+            papal_prayer(&mut files, "Latin", &rule, "Oratio").unwrap(),
+            "Gregem tuum, beátis Sotére et Caio Mártyribus tuis atque Summis Pontifícibus."
+        );
+        assert!(papal_prayer(&mut files, "Latin", &rule, "Secreta").is_none());
+        assert_eq!(papal_antiphon_dum_esset(&mut files, "Latin").unwrap(), "Dum esset summus Póntifex");
+
+        assert_eq!(replace_ndot("for blessed N. and N.", "English", "Fabian and Sebastian"), "for blessed Fabian and Sebastian");
+        assert_eq!(replace_ndot("pro N. Papa, INRI.", "Latin", "Pio"), "pro Pio Papa, INRI.");
+        assert_eq!(replace_ndot("pro N. Papa", "Latin", ""), "pro N. Papa");
+    }
+}
@@ -3,175 +3,199 @@
 //! This module implements the “Prima Special” routines from the original Perl
 //! `/horas/specials/specprima.pl`. It provides three public functions:
 //!
-//! 1. `lectio_brevis_prima(lang: &str) -> (String, i32)` – collects the brief lecture
-//!    for Prima (returning the text and a comment code).
-//! 2. `capitulum_prima(lang: &str, with_responsory: bool) -> String` – collects the capitulum
+//! 1. `lectio_brevis_prima(config, files, lang) -> LectioBrevis` – collects the brief lecture
+//!    for Prima of the season or of the office, with the file and section it came from.
+//! 2. `capitulum_prima(config, files, lang, with_responsory) -> String` – collects the capitulum
 //!    (with optional responsory) for Prima.
-//! 3. `get_prima_responsory(lang: &str) -> String` – looks up the Prima responsory.
+//! 3. `get_prima_responsory(config, files, lang) -> String` – looks up the Prima responsory.
+//! 4. `quicumque_required(date, version) -> bool` – whether the Athanasian Creed is said at
+//!    Prima on a date; `quicumque(config)` asks the same of the office of the hour.
 //!
 //! Throughout, we use early–return style and split out branches into helper functions.
 //! “Regexes” from the original code (for example, case–insensitive matching) are replaced by
 //! dedicated helper functions (see `contains_ci()` and `parse_doxology_key()`).
 //!
-//! ## External dependencies
-//!
-//! This module assumes the existence of functions such as:
-//! - `setupstring(lang, filename)` returning a `HashMap<String,String>`,
-//! - `gettempora(key)` returning a `String`,
-//! - `setbuild(section, name, ord)` and variants,
-//! - `setcomment(label, source, comment, lang, prefix)`,
-//! - and various globals via a `globals` module.
-//!
-//! See the tests at the end for examples of how our helper functions mimic the original Perl behavior.
-
-use std::collections::HashMap;
-
-use crate::globals::{
-    get_commune, get_commune2, get_version, get_winner, get_winner2, gettempora, get_label,
-};
-use crate::setup_string::setupstring;
-use crate::specials_build::{setbuild, setbuild1, setbuild2};
-use crate::comment::setcomment;
-use crate::columnsel;
-use crate::specials_papal::replace_ndot;
-use crate::regex::contains_ci;
-
-/// Returns the brief lecture for Prima as a tuple `(text, comment)`.
-pub fn lectio_brevis_prima(lang: &str) -> (String, i32) {
-    // Get globals (version, winner maps, commune maps)
-    let version = crate::globals::get_version();
-    let winner = get_winner();
-    let winner2 = get_winner2();
-    let commune = get_commune();
-    let commune2 = get_commune2();
+//! The state of the hour comes from the specials `InputConfig`, and the data
+//! files are read through a `SetupStringProvider`.
+
+use super::{gettempora, responsoria, setcomment, Comment, InputConfig, TextSource};
+use crate::date::{day_of_week, getweek, CalendarSystem};
+use crate::horas::officium::parse_date;
+use crate::regex::{ci_find, contains_ci};
+use crate::rubrics::{RubricsFamily, RubricsVersion};
+use crate::setup_string::{ResolveDirectives, SetupStringProvider};
+
+/// The short lesson of Prima, with where it was taken from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LectioBrevis {
+    /// The lesson, with the blessing before it and "$Tu autem" after.
+    pub text: String,
+    /// What the lesson is proper to, for the comment heading it.
+    pub source: TextSource,
+    /// The file it was taken from, e.g. "Psalterium/Special/Prima Special.txt".
+    pub file: String,
+    /// The section of that file, e.g. "Adv" or "Lectio Prima".
+    pub section: String,
+}
+
+/// The sections of `Psalterium/Special/Prima Special.txt` tried for a season
+/// of `gettempora()`, in order: the seasons without a lesson of their own
+/// take that of the season they belong to, and all end with "Regi
+/// sæculórum" of the year.
+fn prima_sections(season: &str) -> Vec<&str> {
+    let mut sections = vec![season];
+    match season {
+        "Quad5" => sections.push("Quad"),
+        "Asc" | "Pent" => sections.push("Pasch"),
+        _ => {}
+    }
+    if season != "Per Annum" {
+        sections.push("Per Annum");
+    }
+    sections
+}
+
+/// Returns the brief lecture for Prima.
+///
+/// The lesson is that of the season (see `prima_sections()`), unless the
+/// office or its commune has a "Lectio Prima" of its own, which the
+/// simplified rubrics of 1955 and 1960 and the Cistercian books do not use.
+pub fn lectio_brevis_prima(
+    config: &InputConfig,
+    files: &mut dyn SetupStringProvider,
+    lang: &str,
+) -> LectioBrevis {
+    let version = &config.version;
 
     // Load the special data file.
-    let brevis_map = setupstring(lang, "Psalterium/Special/Prima Special.txt", &[]).unwrap_or_default();
-    let name = gettempora("Lectio brevis Prima");
-    // Get the initial brevis text from the map.
-    let mut brevis = brevis_map.get(&name).cloned().unwrap_or_default();
-    // Set comment: if name (case-insensitive) contains "per annum", comment=5, else 1.
-    let mut comment = if contains_ci(&name, "per annum") { 5 } else { 1 };
+    let file = "Psalterium/Special/Prima Special.txt";
+    let brevis_map = files.setupstring(lang, file, ResolveDirectives::All).unwrap_or_default();
+    let season = gettempora(config, "Lectio brevis Prima");
+    let sections = prima_sections(&season);
+    let section = sections.iter().find(|s| brevis_map.contains_key(**s)).unwrap_or(&sections[0]).to_string();
+    let mut lectio = LectioBrevis {
+        text: brevis_map.get(&section).cloned().unwrap_or_default(),
+        source: if section == "Per Annum" { TextSource::PerAnnum } else { TextSource::Season },
+        file: file.to_string(),
+        section,
+    };
 
-    setbuild("Psalterium/Special/Prima Special", &name, "Lectio brevis ord");
+    config.build.setbuild("Psalterium/Special/Prima Special", &lectio.section, "Lectio brevis ord");
 
     // If version does not match /1955|196|cist/i, then try to substitute a new Lectio Prima.
-    if !(contains_ci(&version, "1955")
-        || contains_ci(&version, "196")
-        || contains_ci(&version, "cist"))
-    {
-        let b = if let Some(val) = winner.get("Lectio Prima") {
-            if columnsel(lang) { val.clone() } else { winner2.get("Lectio Prima").cloned().unwrap_or_default() }
-        } else if let Some(val) = commune.get("Lectio Prima") {
-            if columnsel(lang) { val.clone() } else { commune2.get("Lectio Prima").cloned().unwrap_or_default() }
-        } else {
-            String::new()
+    let rubrics = config.rubrics();
+    if !(rubrics.uses_1955_simplifications() || rubrics.is_cistercian()) {
+        let proper = match config.winners().get("Lectio Prima") {
+            Some(b) => Some((b, &config.winner)),
+            None => config.commune_map.get("Lectio Prima").map(|b| (b, &config.commune)),
         };
-        if !b.is_empty() {
-            setbuild2(&format!("Subst Lectio Prima {}", winner));
-            comment = 3.max(comment); // If substitution from winner occurred, comment becomes 3.
+        if let Some((b, source)) = proper.filter(|(b, _)| !b.is_empty()) {
+            config.build.setbuild2(&format!("Subst Lectio Prima {}", config.winner));
+            // A lesson of the office itself (outside the season "per annum").
+            if lectio.source == TextSource::Season {
+                lectio.source = TextSource::Sancti;
+            }
             // Use substituted text if available.
-            brevis = b;
+            lectio.text = b.clone();
+            lectio.file = source.clone();
+            lectio.section = "Lectio Prima".to_string();
         }
     }
     // Unless the version starts with "Monastic" (case-insensitive), prepend a benedictio.
     if !version.to_lowercase().starts_with("monastic") {
-        brevis = format!("$benedictio Prima\n{}", brevis);
+        lectio.text = format!("$benedictio Prima\n{}", lectio.text);
     }
-    brevis.push_str("\n$Tu autem");
-    (brevis, comment)
+    lectio.text.push_str("\n$Tu autem");
+    lectio
 }
 
-/// Returns the capitulum for Prima as a String.
+/// Returns the capitulum for Prima as a String, headed by its label.
 /// The parameter `with_responsory` indicates whether responsory text should be included.
-pub fn capitulum_prima(lang: &str, with_responsory: bool) -> String {
-    // Retrieve needed globals.
-    let dayofweek = crate::globals::get_dayofweek();
-    let version = crate::globals::get_version();
-    let winner = get_winner();
-    let commune = get_commune();
-    let rank = crate::globals::get_rank();
-    let daynames = crate::globals::get_daynames();
-    let label = get_label();
-    let winner2 = get_winner2();
-
-    let brevis_map = setupstring(lang, "Psalterium/Special/Prima Special.txt", &[]).unwrap_or_default();
+pub fn capitulum_prima(
+    config: &InputConfig,
+    files: &mut dyn SetupStringProvider,
+    lang: &str,
+    with_responsory: bool,
+) -> String {
+    let version = &config.version;
+    let winners = config.winners();
+    let rank_line = winners.get("Rank").map(|r| r.to_lowercase()).unwrap_or_default();
+    let dayname = config.dayname0();
+
+    let brevis_map = files
+        .setupstring(lang, "Psalterium/Special/Prima Special.txt", ResolveDirectives::All)
+        .unwrap_or_default();
 
     // Compute key based on conditions.
-    let key = if dayofweek > 0
-        && !contains_ci(&version, "196")
-        && (winner.get("Rank").unwrap_or(&String::new()).to_lowercase().contains("feria")
-            || winner.get("Rank").unwrap_or(&String::new()).to_lowercase().contains("vigilia"))
-        && !winner.get("Rank").unwrap_or(&String::new()).to_lowercase().contains("vigilia epi")
-        && (commune.is_empty() || !contains_ci(&commune, "C10"))
-        && (rank < 3 || (!daynames.is_empty() && contains_ci(&daynames[0], "quad6")))
-        && (!daynames.is_empty() && !contains_ci(&daynames[0], "pasc"))
+    let key = if config.dayofweek > 0
+        && !contains_ci(version, "196")
+        && (rank_line.contains("feria") || rank_line.contains("vigilia"))
+        && !rank_line.contains("vigilia epi")
+        && !contains_ci(&config.commune, "C10")
+        && (config.rank < 3.0 || contains_ci(dayname, "quad6"))
+        && !contains_ci(dayname, "pasc")
     {
-        "Feria".to_string()
+        "Feria"
     } else {
-        "Dominica".to_string()
+        "Dominica"
     };
 
-    let mut capit = brevis_map.get(&key).cloned().unwrap_or_default();
+    let mut capit = brevis_map.get(key).cloned().unwrap_or_default();
     capit.push_str("\n$Deo gratias\n_\n");
-    setbuild1("Capitulum", &format!("Psalterium {}", key));
+    config.build.setbuild1("Capitulum", &format!("Psalterium {}", key));
 
-    if contains_ci(&version, "1963") {
-        capit = format!("{}\n{}", label, capit);
+    let heading = if contains_ci(version, "1963") {
+        config.label.clone()
     } else {
-        setcomment(&label, "Source", key == "Feria", lang, "");
-    }
+        let source = if key == "Feria" { TextSource::Season } else { TextSource::Psalterium };
+        setcomment(config, files, &config.label, Some(Comment::Source(source)), lang, "")
+    };
+    capit = format!("{}\n{}", heading, capit);
 
-    let mut resp_lines = Vec::new();
-    if with_responsory {
-        if let Some(resp_text) = brevis_map.get("Responsory") {
-            resp_lines = resp_text.lines().map(|s| s.to_string()).collect();
-        }
-        let mut prima_responsory = get_prima_responsory(lang);
-        let wpr = if columnsel(lang) { winner.clone() } else { winner2.clone() };
-        if let Some(val) = wpr.get("Versum Prima") {
-            prima_responsory = val.clone();
-        }
-        if !prima_responsory.is_empty() && resp_lines.len() > 2 {
-            resp_lines[2] = format!("V. {}", prima_responsory);
-        }
-        resp_lines.push("_".to_string());
-    }
-    if let Some(versum) = brevis_map.get("Versum") {
-        let mut vers_lines: Vec<String> = versum.lines().map(|s| s.to_string()).collect();
-        resp_lines.append(&mut vers_lines);
-    }
-    crate::postprocess::postprocess_short_resp(&mut resp_lines, lang);
+    let mut resp_lines = if with_responsory {
+        responsoria::prima(config, files, lang)
+    } else {
+        brevis_map.get("Versum").map(|v| v.lines().map(str::to_string).collect()).unwrap_or_default()
+    };
+    responsoria::postprocess_responsory(&mut resp_lines, config, files, lang);
     format!("{}{}", capit, resp_lines.join("\n"))
 }
 
 /// Returns the Prima responsory as a String.
 /// If no key can be determined, returns an empty string.
-pub fn get_prima_responsory(lang: &str) -> String {
-    let version = crate::globals::get_version();
-    let month = crate::globals::get_day(); // assuming month is available (stub)
-    let day = crate::globals::get_day();   // assuming day
-    let rule = crate::globals::get_rule();
-    // Assume commemoratio is a map loaded from globals.
-    let commemoratio = crate::globals::get_commemoratio();
-    
-    let mut key = gettempora("Prima responsory");
-
-    if let Some(k) = parse_doxology_key(rule) {
+pub fn get_prima_responsory(
+    config: &InputConfig,
+    files: &mut dyn SetupStringProvider,
+    lang: &str,
+) -> String {
+    let version = &config.version;
+    let (month, day) = (config.month, config.day);
+
+    let mut key = gettempora(config, "Prima responsory");
+
+    if let Some(k) = parse_doxology_key(&config.rule) {
         key = k;
-    } else if !contains_ci(&version, "196") && month == 8 && day > 15 && day < 23 {
+    } else if !contains_ci(version, "196") && month == 8 && day > 15 && day < 23 {
         key = "Nat".to_string();
     }
-    if contains_ci(&version, "196") && month == 12 && day > 8 && day < 16 && !contains_ci(&version, "Newcal") && day != 12 {
+    if contains_ci(version, "196")
+        && month == 12
+        && day > 8
+        && day < 16
+        && !contains_ci(version, "Newcal")
+        && day != 12
+    {
         key = "Adv".to_string();
     }
-    if contains_ci(&version, "196") && (key.contains("Corp") || key.contains("Heart")) {
+    if contains_ci(version, "196") && (key.contains("Corp") || key.contains("Heart")) {
         key.clear();
     }
     if key.is_empty() {
         return String::new();
     }
-    let t_map = setupstring(lang, "Psalterium/Special/Prima Special.txt", &[]).unwrap_or_default();
+    let t_map = files
+        .setupstring(lang, "Psalterium/Special/Prima Special.txt", ResolveDirectives::All)
+        .unwrap_or_default();
     t_map.get(&format!("Responsory {}", key)).cloned().unwrap_or_default()
 }
 
@@ -179,8 +203,7 @@ pub fn get_prima_responsory(lang: &str) -> String {
 /// Looks for a substring like "Doxology=Nat" (case-insensitive) and returns "Nat".
 fn parse_doxology_key(s: &str) -> Option<String> {
     // Instead of using a regex, we search for "doxology=" (case-insensitive)
-    let lower = s.to_lowercase();
-    if let Some(pos) = lower.find("doxology=") {
+    if let Some(pos) = ci_find(s, "doxology=") {
         // Get the substring after "doxology="
         let remainder = &s[pos + "doxology=".len()..];
         // Take the first word (split on whitespace)
@@ -190,9 +213,57 @@ fn parse_doxology_key(s: &str) -> Option<String> {
     None
 }
 
+/// The number of the Athanasian Creed among the psalms (`&psalm(234)`).
+pub const QUICUMQUE: u32 = 234;
+
+/// Whether the Athanasian Creed (Quicumque) is said at Prima on `date`
+/// ("MM-DD-YYYY") under the rubrics of `version`, if the office of the day
+/// is that of the Sunday:
+///
+/// - on Trinity Sunday in every Roman version;
+/// - under the Tridentine rubrics on the Sundays of Advent, after the
+///   Epiphany, of Septuagesima and Lent and after Pentecost;
+/// - under Divino Afflatu on the Sundays after the Epiphany and after
+///   Pentecost;
+/// - from 1955 only on Trinity Sunday.
+///
+/// The monastic Prima has no Quicumque.
+pub fn quicumque_required(date: &str, version: &RubricsVersion) -> bool {
+    let Some((month, day, year)) = parse_date(date) else {
+        return false;
+    };
+    day_of_week(day, month, year) == 0
+        && sunday_quicumque(&getweek(day, month, year, false, false, CalendarSystem::Gregorian), version)
+}
+
+/// Whether the Quicumque is said at Prima of the office of `config`: an
+/// office of a Sunday (of the Proper of Time) on which
+/// `quicumque_required()` asks for it.
+pub fn quicumque(config: &InputConfig) -> bool {
+    let week = config.dayname0().split('-').next().unwrap_or("");
+    config.dayofweek == 0 && contains_ci(&config.winner, "tempora") && sunday_quicumque(week, &config.rubrics())
+}
+
+/// Whether the Sunday of `week` ("Pent01", "Epi3") takes the Quicumque.
+fn sunday_quicumque(week: &str, version: &RubricsVersion) -> bool {
+    if version.monastic {
+        return false;
+    }
+    if week == "Pent01" {
+        return true;
+    }
+    match version.family {
+        RubricsFamily::Tridentine => ["Adv", "Epi", "Quad", "Pent"].iter().any(|s| week.starts_with(s)),
+        RubricsFamily::DivinoAfflatu => week.starts_with("Epi") || week.starts_with("Pent"),
+        RubricsFamily::Reduced1955 | RubricsFamily::Rubrics1960 => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::horas::specials::tests::TestFiles;
+    use crate::horas::Hora;
 
     #[test]
     fn test_contains_ci() {
@@ -205,20 +276,91 @@ mod tests {
         let s = "Some text Doxology=Nat and more";
         let key = parse_doxology_key(s);
         assert_eq!(key.unwrap(), "Nat");
+        assert_eq!(parse_doxology_key("ẞ Doxology=Pasch").as_deref(), Some("Pasch"));
     }
 
     #[test]
     fn test_lectio_brevis_prima() {
-        // With a dummy setupstring, gettempora returns the key itself.
-        let result = lectio_brevis_prima("Latin");
+        let config = InputConfig::new(Hora::Prima);
+        let result = lectio_brevis_prima(&config, &mut TestFiles::default(), "Latin");
         // We expect the returned text to include "$Tu autem" at the end.
-        assert!(result.0.contains("$Tu autem"));
+        assert!(result.text.contains("$Tu autem"));
+    }
+
+    fn prima_special() -> TestFiles {
+        TestFiles::default().with("Latin", "Psalterium/Special/Prima Special.txt", &[
+            ("Per Annum", "!1 Tim 1:17\nRegi sæculórum immortáli."),
+            ("Adv", "!Isa 33:2\nDómine, miserére nostri."),
+            ("Pasch", "!Col 3:1\nSi consurrexístis cum Christo."),
+        ])
+    }
+
+    #[test]
+    fn test_lectio_brevis_prima_by_season() {
+        let mut config = InputConfig::new(Hora::Prima);
+        config.version = "Divino Afflatu - 1954".to_string();
+        config.daynames = vec!["Adv2-3".to_string(), "Feria IV".to_string()];
+        config.dayofweek = 3;
+        let lectio = lectio_brevis_prima(&config, &mut prima_special(), "Latin");
+        assert_eq!((lectio.section.as_str(), lectio.source), ("Adv", TextSource::Season));
+        assert_eq!(lectio.text, "$benedictio Prima\n!Isa 33:2\nDómine, miserére nostri.\n$Tu autem");
+
+        // Paschaltide, and the weeks of the Ascension that have no lesson of their own.
+        config.daynames[0] = "Pasc2-3".to_string();
+        assert_eq!(lectio_brevis_prima(&config, &mut prima_special(), "Latin").section, "Pasch");
+        config.daynames[0] = "Pasc6-3".to_string();
+        let lectio = lectio_brevis_prima(&config, &mut prima_special(), "Latin");
+        assert_eq!((lectio.section.as_str(), lectio.file.as_str()), ("Pasch", "Psalterium/Special/Prima Special.txt"));
+        assert!(lectio.text.contains("Si consurrexístis"));
+
+        config.daynames[0] = "Pent10-3".to_string();
+        let lectio = lectio_brevis_prima(&config, &mut prima_special(), "Latin");
+        assert_eq!((lectio.section.as_str(), lectio.source), ("Per Annum", TextSource::PerAnnum));
+
+        // A feast with a lesson of its own, but not under the rubrics of 1960.
+        config.daynames[0] = "Pasc2-3".to_string();
+        config.winner = "Sancti/05-01.txt".to_string();
+        config.winner_map.insert("Lectio Prima".to_string(), "!Sap 10:10\nJustum dedúxit Dóminus.".to_string());
+        let lectio = lectio_brevis_prima(&config, &mut prima_special(), "Latin");
+        assert_eq!((lectio.file.as_str(), lectio.section.as_str()), ("Sancti/05-01.txt", "Lectio Prima"));
+        assert_eq!(lectio.source, TextSource::Sancti);
+        config.version = "Rubrics 1960 - 1960".to_string();
+        assert_eq!(lectio_brevis_prima(&config, &mut prima_special(), "Latin").section, "Pasch");
     }
 
     #[test]
     fn test_get_prima_responsory_empty() {
         // With no data in setupstring, we expect an empty string.
-        let result = get_prima_responsory("Latin");
+        let config = InputConfig::new(Hora::Prima);
+        let result = get_prima_responsory(&config, &mut TestFiles::default(), "Latin");
         assert!(result.is_empty());
     }
-}
+
+    #[test]
+    fn test_quicumque_required() {
+        let version = RubricsVersion::parse;
+        // Trinity Sunday 2025 and the third Sunday after the Epiphany.
+        for v in ["Tridentine - 1570", "Divino Afflatu - 1954", "Reduced - 1955", "Rubrics 1960 - 1960"] {
+            assert!(quicumque_required("06-15-2025", &version(v)), "{}", v);
+            assert!(!quicumque_required("06-16-2025", &version(v)), "{}", v);
+        }
+        assert!(quicumque_required("01-26-2025", &version("Divino Afflatu - 1954")));
+        assert!(!quicumque_required("01-26-2025", &version("Rubrics 1960 - 1960")));
+        // The first Sunday of Advent only under the Tridentine rubrics.
+        assert!(quicumque_required("11-30-2025", &version("Tridentine - 1570")));
+        assert!(!quicumque_required("11-30-2025", &version("Divino Afflatu - 1954")));
+        assert!(!quicumque_required("06-15-2025", &version("Monastic - 1930")));
+    }
+
+    #[test]
+    fn test_quicumque_of_the_office() {
+        let mut config = InputConfig::new(Hora::Prima);
+        config.version = "Divino Afflatu - 1954".to_string();
+        config.daynames = vec!["Pent05-0".to_string()];
+        config.winner = "Tempora/Pent05-0.txt".to_string();
+        assert!(quicumque(&config));
+        // A feast on the Sunday.
+        config.winner = "Sancti/07-02.txt".to_string();
+        assert!(!quicumque(&config));
+    }
+}
\ No newline at end of file
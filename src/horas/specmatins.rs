@@ -187,10 +187,93 @@ pub fn gettype1960(
     type_code
 }
 
+/// 8. nocturn
+///
+/// Returns the lines of the psalmody of the nocturn `num`: its heading
+/// ("!Nocturn II.", with `label` the word for "Nocturn"; none for `num` 0),
+/// then the entries `select` of `psalmi`:
+///   * an "antiphon;;psalms" entry gives the antiphon as `intone` says it
+///     before the psalms (see `psalterium::intonation()`), the psalms
+///     (separated by ';') as `&psalm(...)` lines and the antiphon again;
+///   * an entry without an antiphon puts its psalms under the antiphon of the
+///     entry before it, which is then said after the last of them;
+///   * the "V." and "R." lines of the versicle are copied as they are;
+///   * empty (or missing) entries are skipped.
+pub fn nocturn(
+    num: usize,
+    label: &str,
+    psalmi: &[String],
+    select: &[usize],
+    intone: &dyn Fn(&str) -> Option<String>,
+) -> Vec<String> {
+    let mut output = Vec::new();
+    if num > 0 {
+        let roman = ["I", "II", "III"].get(num - 1).copied().unwrap_or("");
+        output.push(format!("!{} {}.", label, roman));
+    }
+    let mut antiphon = String::new();
+    for entry in select.iter().filter_map(|&i| psalmi.get(i)).map(|e| e.trim()) {
+        if entry.is_empty() {
+            continue;
+        }
+        let Some((ant, psalms)) = entry.split_once(";;") else {
+            close_antiphon(&mut output, &mut antiphon);
+            output.push(entry.to_string());
+            continue;
+        };
+        let ant = ant.trim();
+        if !ant.is_empty() {
+            close_antiphon(&mut output, &mut antiphon);
+            if let Some(intonation) = intone(ant) {
+                output.push(format!("Ant. {}", intonation));
+                antiphon = ant.to_string();
+            }
+        }
+        for psalm in psalms.split(';').map(str::trim).filter(|p| !p.is_empty()) {
+            output.push(format!("&psalm({})", psalm));
+        }
+    }
+    close_antiphon(&mut output, &mut antiphon);
+    output
+}
+
+/// Repeats the pending antiphon of `nocturn()` after its psalms.
+fn close_antiphon(output: &mut Vec<String>, antiphon: &mut String) {
+    if !antiphon.is_empty() {
+        output.push(format!("Ant. {}", std::mem::take(antiphon)));
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_nocturn() {
+        let psalmi: Vec<String> = ["Ant1;;3", ";;6", "Ant2;;7;8", "", "V. Versus.", "R. Responsio."]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let lines = nocturn(2, "Nocturn", &psalmi, &[0, 1, 2, 3, 4, 5, 6], &|ant| Some(ant.to_string()));
+        assert_eq!(
+            lines,
+            [
+                "!Nocturn II.",
+                "Ant. Ant1",
+                "&psalm(3)",
+                "&psalm(6)",
+                "Ant. Ant1",
+                "Ant. Ant2",
+                "&psalm(7)",
+                "&psalm(8)",
+                "Ant. Ant2",
+                "V. Versus.",
+                "R. Responsio.",
+            ]
+        );
+        assert_eq!(nocturn(0, "Nocturn", &psalmi, &[4], &|ant| Some(ant.to_string())), ["V. Versus."]);
+    }
+
     #[test]
     fn test_dayofweek2i() {
         assert_eq!(dayofweek2i(0), 1);
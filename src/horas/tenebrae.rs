@@ -0,0 +1,141 @@
+//! tenebrae.rs
+//!
+//! Tenebrae: the Matins and Lauds of the last three days of Holy Week,
+//! anticipated on the evening before. They are the hours of the day as the
+//! office builds them, turned into the service of the hearse:
+//!
+//! - the Gloria Patri is left out after the psalms and the responsories;
+//! - after each of the nine psalms of Matins and the five of Lauds, a
+//!   candle of the triangular hearse is put out, fourteen of fifteen;
+//! - after the Benedictus the last candle is hidden behind the altar, the
+//!   *Christus factus est* is sung, longer on each of the three days, and
+//!   the service ends with the strepitus.
+//!
+//! ```ignore
+//! // Tenebrae of Good Friday, sung on Thursday evening.
+//! let text = build_tenebrae(&mut ctx, "04-18-2025", "Latin", "Divino Afflatu")?;
+//! ```
+
+/// The day of the Triduum (1 for Maundy Thursday, 2 for Good Friday, 3 for
+/// Holy Saturday) of a day of the Proper of Time.
+pub fn tenebrae_day(dayname: &str) -> Option<u8> {
+    match dayname {
+        "Quad6-4" => Some(1),
+        "Quad6-5" => Some(2),
+        "Quad6-6" => Some(3),
+        _ => None,
+    }
+}
+
+/// The *Christus factus est* of the `day` of the Triduum.
+pub fn christus_factus_est(day: u8) -> String {
+    let mut text = String::from("V. Christus factus est pro nobis obédiens usque ad mortem");
+    if day >= 2 {
+        text.push_str(", mortem autem crucis");
+    }
+    if day >= 3 {
+        text.push_str(". Propter quod et Deus exaltávit illum: et dedit illi nomen, quod est super omne nomen");
+    }
+    text.push('.');
+    text
+}
+
+/// The number of candles of the hearse put out after the psalms.
+const HEARSE_CANDLES: usize = 14;
+
+/// Makes the Tenebrae of the `day` of the Triduum out of the text of its
+/// Matins and Lauds.
+pub fn tenebrae(matutinum: &str, laudes: &str, day: u8) -> String {
+    let mut candles = 0;
+    let mut out = Vec::new();
+    extinguish(matutinum, &mut candles, &mut out);
+    let mut laudes_out = Vec::new();
+    extinguish(laudes, &mut candles, &mut laudes_out);
+
+    let conclusio = [
+        "/:Candela quæ remansit accensa absconditur post altare.:/".to_string(),
+        christus_factus_est(day),
+        "/:Pater noster totum secreto.:/".to_string(),
+    ];
+    match laudes_out.iter().position(|l| l.trim_start().starts_with("#Oratio")) {
+        Some(i) => {
+            laudes_out.splice(i + 1..i + 1, conclusio);
+        }
+        None => laudes_out.extend(conclusio),
+    }
+    laudes_out.push("/:Fit fragor et strepitus aliquantulum, et producitur candela accensa.:/".to_string());
+    out.extend(laudes_out);
+    let mut text = out.join("\n");
+    text.push('\n');
+    text
+}
+
+/// Copies the lines of `text` to `out` without the Gloria Patri, putting
+/// out a candle after each psalm until the hearse has one left.
+fn extinguish(text: &str, candles: &mut usize, out: &mut Vec<String>) {
+    let lines: Vec<&str> = text.lines().filter(|l| !is_gloria(l)).collect();
+    for (i, line) in lines.iter().enumerate() {
+        out.push(line.to_string());
+        let psalm_ends = is_verse(line) && !lines.get(i + 1).is_some_and(|next| is_verse(next));
+        if psalm_ends && *candles < HEARSE_CANDLES {
+            *candles += 1;
+            out.push(format!("/:Extinguitur candela {} candelabri triangularis.:/", *candles));
+        }
+    }
+}
+
+/// Whether `line` is (a part of) the Gloria Patri.
+fn is_gloria(line: &str) -> bool {
+    let line = line.trim();
+    let line = line.strip_prefix("V.").or_else(|| line.strip_prefix("R.")).unwrap_or(line).trim_start();
+    line.starts_with("&Gloria")
+        || line.starts_with("Glória Patri")
+        || line.starts_with("Gloria Patri")
+        || line.starts_with("Sicut erat")
+}
+
+/// Whether `line` is a verse of a psalm ("109:1 Dixit Dóminus").
+fn is_verse(line: &str) -> bool {
+    let Some(number) = line.split_whitespace().next() else { return false };
+    match number.split_once(':') {
+        Some((chapter, verse)) => {
+            !chapter.is_empty()
+                && chapter.chars().all(|c| c.is_ascii_digit())
+                && verse.starts_with(|c: char| c.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_christus_factus_est() {
+        assert_eq!(christus_factus_est(1), "V. Christus factus est pro nobis obédiens usque ad mortem.");
+        assert!(christus_factus_est(2).ends_with("mortem, mortem autem crucis."));
+        assert!(christus_factus_est(3).ends_with("quod est super omne nomen."));
+        assert_eq!(tenebrae_day("Quad6-5"), Some(2));
+        assert_eq!(tenebrae_day("Quad6-3"), None);
+    }
+
+    #[test]
+    fn test_tenebrae() {
+        let psalm = |n: u32| format!("Ant. Antiphona {n}.\n!Psalmus {n}\n{n}:1 Versus primus.\n{n}:2 Versus secundus.\n&Gloria\nAnt. Antiphona {n}.");
+        let matutinum: Vec<String> = (1..=9).map(psalm).collect();
+        let matutinum = format!("#Psalmi\n{}\nR. Responsorium.\nV. Glória Patri, et Fílio.\n", matutinum.join("\n"));
+        let laudes: Vec<String> = (50..=54).map(psalm).collect();
+        let laudes = format!("#Psalmi\n{}\n#Canticum\n1:68 Benedíctus Dóminus.\n#Oratio\nRéspice, quǽsumus, Dómine.\n", laudes.join("\n"));
+
+        let text = tenebrae(&matutinum, &laudes, 2);
+        assert!(!text.contains("Gloria") && !text.contains("Glória"));
+        assert!(text.contains("9:2 Versus secundus.\n/:Extinguitur candela 9 candelabri triangularis.:/\nAnt. Antiphona 9."));
+        assert!(text.contains("/:Extinguitur candela 14 "));
+        assert!(!text.contains("candela 15 "), "the Benedictus puts out no candle of the hearse");
+        assert!(text.contains(
+            "#Oratio\n/:Candela quæ remansit accensa absconditur post altare.:/\nV. Christus factus est pro nobis obédiens usque ad mortem, mortem autem crucis.\n"
+        ));
+        assert!(text.ends_with("Réspice, quǽsumus, Dómine.\n/:Fit fragor et strepitus aliquantulum, et producitur candela accensa.:/\n"));
+    }
+}
@@ -9,11 +9,28 @@
 //! - Look up translations, prayers, rubrics, and preces using language–specific maps,
 //! - And load the language data from disk.
 //!
+//! The headings and labels of the hours are translated from tables loaded
+//! once per build (`load_translations()`, `translate_label()`); the keys
+//! without a translation are kept for the translators
+//! (`missing_translations()`).
+//!
+//! It also holds the accent setting of the Latin texts (`set_accented()`):
+//! in `AccentMode::Plain` the accents are stripped (á→a) when the hour is
+//! rendered, and `ligaturize()` writes the æ/œ ligatures of the Latin
+//! sources that spell them out.
+//!
 //! File–loading is performed via a setup provider (of type implementing
 //! [`SetupStringProvider`]) which, in production, is the real SetupStringContext
 //! (a struct) from the `setupstring` module.
 
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+use once_cell::sync::Lazy;
+
+use crate::regex::{ci_regex, escape};
+use crate::rubrics::RubricsVersion;
 use crate::setup_string::{FileSections, ResolveDirectives, SetupStringProvider};
 
 /// Holds the language data previously stored in globals.
@@ -27,6 +44,10 @@ pub struct LanguageTextContext {
     pub omit_words: Vec<String>,
     pub fb_lang: String,
     pub version: String,
+    /// The name put for the "N." of the prayers for the Pope, if known.
+    pub pope: String,
+    /// The name put for the "N." of the prayers for the bishop, if known.
+    pub bishop: String,
 }
 
 /// Initializes a new LanguageTextContext using data loaded from disk via the given
@@ -119,6 +140,8 @@ pub fn initialize_language_text_context(
         omit_words: omits,
         fb_lang: langfb.to_string(),
         version: version.to_string(),
+        pope: String::new(),
+        bishop: String::new(),
     }
 }
 
@@ -161,16 +184,12 @@ pub fn suppress_alleluia(ctx: &LanguageTextContext, text: &mut String) {
 /// Helper that removes a trailing alleluia variant (ignoring case and punctuation).
 fn remove_trailing_alleluia(text: &str, variants: &[String]) -> String {
     let trimmed = text.trim_end();
-    let lower = trimmed.to_lowercase();
     for allele in variants {
-        let allele_lower = allele.to_lowercase();
-        if lower.ends_with(&allele_lower) {
-            if let Some(pos) = lower.rfind(&allele_lower) {
-                let prefix = &trimmed[..pos];
-                return prefix
-                    .trim_end_matches(|c: char| c.is_ascii_punctuation() || c.is_whitespace())
-                    .to_string();
-            }
+        let Ok(re) = ci_regex(&format!(r"{}[\p{{P}}\s]*$", escape(allele))) else { continue };
+        if let Some(m) = re.find(trimmed) {
+            return trimmed[..m.start()]
+                .trim_end_matches(|c: char| c.is_ascii_punctuation() || c.is_whitespace())
+                .to_string();
         }
     }
     text.to_string()
@@ -312,30 +331,32 @@ pub fn alleluia_ant(ctx: &LanguageTextContext, lang: &str) -> String {
 
 /// Returns the translated text for the given name and language.
 pub fn translate(ctx: &LanguageTextContext, name: &str, lang: &str) -> String {
-    let mut prefix = String::new();
-    let mut name = name.to_string();
-    if name.starts_with('$') || name.starts_with('&') {
-        prefix = name.chars().next().unwrap().to_string();
-        name = name[1..].to_string();
-    }
-    let result = if lang.to_lowercase().contains("latin") {
-        ctx.translate
-            .get("Latin")
-            .and_then(|m| m.get(&name))
-            .map(|s| s.trim_end().to_string())
-            .unwrap_or(name.clone())
-    } else {
-        ctx.translate
-            .get(lang)
-            .and_then(|m| m.get(&name))
-            .or_else(|| ctx.translate.get(&ctx.fb_lang).and_then(|m| m.get(&name)))
-            .or_else(|| ctx.translate.get("Latin").and_then(|m| m.get(&name)))
-            .cloned()
-            .unwrap_or(name.clone())
-            .trim_end()
-            .to_string()
-    };
-    format!("{}{}", prefix, result)
+    let (prefix, key) = split_translation_prefix(name);
+    let text = lookup_translation(&ctx.translate, &ctx.fb_lang, key, lang).unwrap_or_else(|| key.to_string());
+    format!("{}{}", prefix, text)
+}
+
+/// Splits the `$`, `&` or `#` of a prayer, a function or a heading off
+/// `name`, the rest being the key of the translation table.
+fn split_translation_prefix(name: &str) -> (&str, &str) {
+    let n = name.strip_prefix(['$', '&', '#']).map_or(0, |_| 1);
+    (&name[..n], name[n..].trim_end())
+}
+
+/// Looks `key` up in the translation `tables` (by language): in `lang`,
+/// else in `fallback`, else in Latin. A Latin text is only looked up in
+/// the Latin table.
+fn lookup_translation(
+    tables: &HashMap<String, FileSections>,
+    fallback: &str,
+    key: &str,
+    lang: &str,
+) -> Option<String> {
+    let chain: &[&str] = if lang.to_lowercase().contains("latin") { &["Latin"] } else { &[lang, fallback, "Latin"] };
+    chain
+        .iter()
+        .find_map(|l| tables.get(*l).and_then(|m| m.get(key)))
+        .map(|s| s.trim_end().to_string())
 }
 
 /// Returns the prayer text for the given name and language,
@@ -343,22 +364,68 @@ pub fn translate(ctx: &LanguageTextContext, name: &str, lang: &str) -> String {
 /// Also, if the version string contains “cist” (case–insensitive) and
 /// the name is not exempt, removes occurrences of “+ ”.
 pub fn prayer(ctx: &LanguageTextContext, name: &str, lang: &str) -> String {
-    let version = &ctx.version;
-    let key1 = format!("{}{}", lang, version);
-    let key2 = format!("{}{}", ctx.fb_lang, version);
-    let key3 = format!("Latin{}", version);
-    let candidate = ctx
-        .prayers
-        .get(&key1)
-        .and_then(|m| m.get(name))
-        .or_else(|| ctx.prayers.get(&key2).and_then(|m| m.get(name)))
-        .or_else(|| ctx.prayers.get(&key3).and_then(|m| m.get(name)))
-        .cloned()
-        .unwrap_or_else(|| name.to_string());
-    if version.to_lowercase().contains("cist") && !name_contains_exempt(name) {
-        candidate.replace("+ ", "")
-    } else {
-        candidate
+    ctx.prayer(name, lang, &ctx.version)
+}
+
+impl LanguageTextContext {
+    /// Returns the prayer `key` of `Prayers.txt` in `lang` for `version`.
+    ///
+    /// The file may hold variants of a prayer for a rite, in sections named
+    /// after the prayer with the suffix of the rite: "Confiteor OP" for the
+    /// Dominicans, "Cist" for the Cistercians, "M" for the monastic uses. The
+    /// variant is preferred to the common text, in each language of the
+    /// fallback order (lang → fallback → Latin). The "N." of the prayers for
+    /// the Pope and the bishop is replaced by `pope` and `bishop` when set.
+    pub fn prayer(&self, key: &str, lang: &str, version: &str) -> String {
+        let rubrics = RubricsVersion::parse(version);
+        let suffix = rite_suffix(&rubrics);
+        let names: Vec<String> = suffix.map(|s| format!("{} {}", key, s)).into_iter().chain([key.to_string()]).collect();
+        let candidate = [lang, self.fb_lang.as_str(), "Latin"]
+            .iter()
+            .find_map(|l| {
+                let sections = self
+                    .prayers
+                    .get(&format!("{}{}", l, version))
+                    .or_else(|| self.prayers.get(&format!("{}{}", l, self.version)))?;
+                names.iter().find_map(|name| sections.get(name))
+            })
+            .cloned()
+            .unwrap_or_else(|| key.to_string());
+        let candidate = self.fill_names(&candidate);
+        if rubrics.is_cistercian() && !name_contains_exempt(key) {
+            candidate.replace("+ ", "")
+        } else {
+            candidate
+        }
+    }
+
+    /// Replaces the "N." placeholders that follow a title of the Pope
+    /// ("Papa", "Pontifice", "Pope") or of the bishop ("Antistite",
+    /// "Episcopo", "Bishop") by their names; the others are kept.
+    fn fill_names(&self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(i) = rest.find(" N.") {
+            let before = &rest[..i];
+            let title = strip_accents(&before.rsplit(['\n', ',']).next().unwrap_or(before).to_lowercase());
+            let name = if ["papa", "pontific", "pope"].iter().any(|t| title.contains(t)) {
+                &self.pope
+            } else if ["antistit", "episcop", "bishop"].iter().any(|t| title.contains(t)) {
+                &self.bishop
+            } else {
+                ""
+            };
+            out.push_str(before);
+            if name.is_empty() {
+                out.push_str(" N.");
+            } else {
+                out.push(' ');
+                out.push_str(name);
+            }
+            rest = &rest[i + 3..];
+        }
+        out.push_str(rest);
+        out
     }
 }
 
@@ -371,19 +438,72 @@ fn name_contains_exempt(name: &str) -> bool {
         || (lower.contains("bene") && lower.contains("final"))
 }
 
-/// Returns the rubric text for the given name and language.
+/// The suffix of the sections of the variants of a text for the rite of
+/// `version`: "OP" for the Dominicans, "Cist" for the Cistercians, "M" for
+/// the monastic uses.
+fn rite_suffix(version: &RubricsVersion) -> Option<&'static str> {
+    if version.dominican {
+        Some("OP")
+    } else if version.is_cistercian() {
+        Some("Cist")
+    } else if version.monastic {
+        Some("M")
+    } else {
+        None
+    }
+}
+
+/// The names a rubric `name` of `Rubricae.txt` may have for `version`, the
+/// most particular first: "Antiphona 1960" in the 1960 rubrics, "Antiphona
+/// 1955" from 1955 on, "Antiphona Trident" in the Tridentine books, then
+/// the variant of the rite ("Antiphona Cist") and the common text.
+fn rubric_names(name: &str, version: &RubricsVersion) -> Vec<String> {
+    let family = if version.is_1960() {
+        &["1960", "1955"][..]
+    } else if version.uses_1955_simplifications() {
+        &["1955"][..]
+    } else if version.is_tridentine() {
+        &["Trident"][..]
+    } else {
+        &[][..]
+    };
+    family
+        .iter()
+        .copied()
+        .chain(rite_suffix(version))
+        .map(|suffix| format!("{} {}", name, suffix))
+        .chain([name.to_string()])
+        .collect()
+}
+
+/// Looks the rubric `name` up in the rubric `tables` (by language) for
+/// `version`: in `lang`, else in `fallback`, else in Latin.
+fn lookup_rubric(
+    tables: &HashMap<String, FileSections>,
+    fallback: &str,
+    name: &str,
+    lang: &str,
+    version: &RubricsVersion,
+) -> Option<String> {
+    let names = rubric_names(name, version);
+    [lang, fallback, "Latin"]
+        .iter()
+        .find_map(|l| {
+            let table = tables.get(*l)?;
+            names.iter().find_map(|n| table.get(n))
+        })
+        .map(|s| s.trim_end().to_string())
+}
+
+/// Returns the rubric text for the given name and language, in the variant
+/// of the version of `ctx`.
 pub fn rubric(ctx: &LanguageTextContext, name: &str, lang: &str) -> String {
-    let version = &ctx.version;
-    let key1 = format!("{}{}", lang, version);
-    let key2 = format!("{}{}", ctx.fb_lang, version);
-    let key3 = format!("Latin{}", version);
-    ctx.rubrics
-        .get(&key1)
-        .and_then(|m| m.get(name))
-        .cloned()
-        .or_else(|| ctx.rubrics.get(&key2).and_then(|m| m.get(name)).cloned())
-        .or_else(|| ctx.rubrics.get(&key3).and_then(|m| m.get(name)).cloned())
-        .unwrap_or_else(|| name.to_string())
+    let version = RubricsVersion::parse(&ctx.version);
+    let tables: HashMap<String, FileSections> = [lang, ctx.fb_lang.as_str(), "Latin"]
+        .iter()
+        .filter_map(|l| Some((l.to_string(), ctx.rubrics.get(&format!("{}{}", l, ctx.version))?.clone())))
+        .collect();
+    lookup_rubric(&tables, &ctx.fb_lang, name, lang, &version).unwrap_or_else(|| name.to_string())
 }
 
 /// Returns the preces text for the given name and language.
@@ -401,6 +521,217 @@ pub fn prex(ctx: &LanguageTextContext, name: &str, lang: &str) -> String {
         .unwrap_or_else(|| name.to_string())
 }
 
+/// How the Latin texts are printed: with their accents (as in the data
+/// files) or without. The Perl `$accented` setting, "plain" for `Plain`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AccentMode {
+    #[default]
+    Accented,
+    Plain,
+}
+
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+/// Sets how the Latin texts are rendered from now on.
+pub fn set_accented(mode: AccentMode) {
+    PLAIN.store(mode == AccentMode::Plain, Ordering::Relaxed);
+}
+
+/// The mode set by `set_accented()` (`Accented` by default).
+pub fn accent_mode() -> AccentMode {
+    if PLAIN.load(Ordering::Relaxed) {
+        AccentMode::Plain
+    } else {
+        AccentMode::Accented
+    }
+}
+
+/// Removes the accents of `text`: "Dómine, ǽterna" gives "Domine, æterna".
+/// The ligatures are kept.
+pub fn strip_accents(text: &str) -> String {
+    text.chars()
+        .filter(|&c| c != '\u{301}')
+        .map(|c| match c {
+            'á' | 'à' => 'a',
+            'é' | 'è' | 'ë' => 'e',
+            'í' | 'ì' => 'i',
+            'ó' | 'ò' => 'o',
+            'ú' | 'ù' => 'u',
+            'ý' => 'y',
+            'Á' | 'À' => 'A',
+            'É' | 'È' | 'Ë' => 'E',
+            'Í' | 'Ì' => 'I',
+            'Ó' | 'Ò' => 'O',
+            'Ú' | 'Ù' => 'U',
+            'Ý' => 'Y',
+            'ǽ' => 'æ',
+            'Ǽ' => 'Æ',
+            c => c,
+        })
+        .collect()
+}
+
+/// The starts of the Latin words in which "ae" or "oe" are two vowels, not
+/// a diphthong (lowercase: "poet" covers "poeta", "poetæ", ...). The rest of
+/// the word still gets its ligatures.
+const LIGATURE_EXCEPTIONS: &[&str] = &[
+    "poet", "poem", "poes", "michael", "raphael", "ismael", "nathanael", "coegi", "coerc", "coemer", "coemp",
+];
+
+/// The words that are exceptions as a whole: the forms of *aer* (not
+/// "ærumna") and "Noe".
+const LIGATURE_EXCEPTION_WORDS: &[&str] = &["aer", "aeris", "aere", "aerem", "aera", "noe"];
+
+/// `word` with its ligatures, the exceptions keeping their "ae"/"oe".
+fn ligaturize_word(word: &str) -> String {
+    let lower = word.to_lowercase();
+    if LIGATURE_EXCEPTION_WORDS.contains(&lower.as_str()) {
+        return word.to_string();
+    }
+    let kept = LIGATURE_EXCEPTIONS
+        .iter()
+        .find(|e| lower.starts_with(*e))
+        .map_or(0, |e| word.char_indices().nth(e.chars().count()).map_or(word.len(), |(i, _)| i));
+    let (kept, rest) = word.split_at(kept);
+    let rest = rest
+        .replace("oe", "œ")
+        .replace("Oe", "Œ")
+        .replace("ae", "æ")
+        .replace("Ae", "Æ")
+        .replace("cæl", "cœl")
+        .replace("Cæl", "Cœl");
+    format!("{}{}", kept, rest)
+}
+
+/// Writes the ligatures of a Latin text that spells them out: "ae" and "oe"
+/// become "æ" and "œ" ("cælum" is "cœlum"), except in the words where they
+/// are two vowels ("poeta", "aer", "Michael"). Texts in other languages are
+/// returned unchanged.
+pub fn ligaturize(text: &str, lang: &str) -> String {
+    if !lang.starts_with("Latin") {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut word = String::new();
+    let flush = |word: &mut String, out: &mut String| {
+        out.push_str(&ligaturize_word(word));
+        word.clear();
+    };
+    for c in text.chars() {
+        if c.is_alphabetic() {
+            word.push(c);
+        } else {
+            flush(&mut word, &mut out);
+            out.push(c);
+        }
+    }
+    flush(&mut word, &mut out);
+    out
+}
+
+/// The transformation of the rendered text of `lang` for `mode`: a Latin
+/// text in `Plain` mode loses its accents, other texts are unchanged.
+pub fn apply_accents(text: &str, lang: &str, mode: AccentMode) -> String {
+    if mode == AccentMode::Plain && lang.starts_with("Latin") {
+        strip_accents(text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// The translation tables of the process, from which the headings and the
+/// labels of the hours are translated (`translate_label()`), with the keys
+/// that were asked for and had no translation.
+#[derive(Debug, Default)]
+struct Translations {
+    tables: HashMap<String, FileSections>,
+    /// The rubrics (`Rubricae.txt`) of each language.
+    rubrics: HashMap<String, FileSections>,
+    /// The fallback language of each language.
+    fallbacks: HashMap<String, String>,
+    missing: BTreeSet<MissingTranslation>,
+}
+
+static TRANSLATIONS: Lazy<RwLock<Translations>> = Lazy::new(|| RwLock::new(Translations::default()));
+
+/// A key of `Translate.txt` that a language has no translation of.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MissingTranslation {
+    pub lang: String,
+    pub key: String,
+}
+
+/// Loads `Psalterium/Common/Translate.txt` and `Rubricae.txt` of `lang`, of
+/// its `fallback` language and of Latin into the translation tables of the
+/// process, and makes `fallback` the language looked in when `lang` has no
+/// translation.
+pub fn load_translations(files: &mut dyn SetupStringProvider, lang: &str, fallback: &str) {
+    let mut translations = TRANSLATIONS.write().unwrap();
+    for l in ["Latin", lang, fallback] {
+        if l.is_empty() {
+            continue;
+        }
+        let table = files
+            .setupstring(l, "Psalterium/Common/Translate.txt", ResolveDirectives::All)
+            .unwrap_or_default();
+        translations.tables.insert(l.to_string(), table);
+        let rubrics = files
+            .setupstring(l, "Psalterium/Common/Rubricae.txt", ResolveDirectives::All)
+            .unwrap_or_default();
+        translations.rubrics.insert(l.to_string(), rubrics);
+    }
+    translations.fallbacks.insert(lang.to_string(), fallback.to_string());
+}
+
+/// Returns `name` (a heading such as "#Psalmi", a label such as
+/// "Antiphona") in `lang`, from the tables of `load_translations()`: the
+/// translation of `lang`, else that of the fallback language, else the
+/// Latin text, else `name` itself.
+///
+/// A key the table of `lang` lacks is recorded for `missing_translations()`,
+/// unless `lang` is Latin, the language of the keys.
+pub fn translate_label(name: &str, lang: &str) -> String {
+    let (prefix, key) = split_translation_prefix(name);
+    if key.is_empty() {
+        return name.to_string();
+    }
+    let translations = TRANSLATIONS.read().unwrap();
+    let fallback = translations.fallbacks.get(lang).map(String::as_str).unwrap_or("");
+    let text = lookup_translation(&translations.tables, fallback, key, lang);
+    let translated = translations.tables.get(lang).is_some_and(|m| m.contains_key(key));
+    drop(translations);
+    if !translated && !lang.to_lowercase().contains("latin") {
+        let missing = MissingTranslation { lang: lang.to_string(), key: key.to_string() };
+        TRANSLATIONS.write().unwrap().missing.insert(missing);
+    }
+    format!("{}{}", prefix, text.as_deref().unwrap_or(key))
+}
+
+/// Returns the rubric `name` ("secreto", "Antiphona") in `lang`, in its
+/// variant for `version` (see `load_translations()`): a section "Antiphona
+/// 1960" of `Rubricae.txt` is taken in the 1960 rubrics, "Antiphona Cist"
+/// in the Cistercian use, and so on, before "Antiphona". A rubric that none
+/// of the languages has is translated as a label (`translate_label()`).
+pub fn rubric_label(name: &str, lang: &str, version: &RubricsVersion) -> String {
+    let translations = TRANSLATIONS.read().unwrap();
+    let fallback = translations.fallbacks.get(lang).map(String::as_str).unwrap_or("");
+    let text = lookup_rubric(&translations.rubrics, fallback, name, lang, version);
+    drop(translations);
+    text.unwrap_or_else(|| translate_label(name, lang))
+}
+
+/// The keys `translate_label()` found no translation of since the last
+/// `clear_missing_translations()`, by language and key.
+pub fn missing_translations() -> Vec<MissingTranslation> {
+    TRANSLATIONS.read().unwrap().missing.iter().cloned().collect()
+}
+
+/// Forgets the missing translations recorded so far, e.g. before a build.
+pub fn clear_missing_translations() {
+    TRANSLATIONS.write().unwrap().missing.clear();
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -438,6 +769,9 @@ mod tests {
         let input = "This is a prayer, Alleluja.  ";
         let expected = "This is a prayer";
         assert_eq!(super::remove_trailing_alleluia(input, &variants), expected);
+        let variants = vec!["allelúja".to_string()];
+        assert_eq!(super::remove_trailing_alleluia("ẞẞ cæli, ALLELÚJA.", &variants), "ẞẞ cæli");
+        assert_eq!(super::remove_trailing_alleluia("Příští neděle", &variants), "Příští neděle");
     }
 
     #[test]
@@ -545,6 +879,58 @@ mod tests {
         assert_eq!(tr_latin, "LatinTest");
     }
 
+    #[test]
+    fn test_translate_label_and_missing() {
+        let mut dummy = DummySetupStringContext::new();
+        let table = |pairs: &[(&str, &str)]| pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        dummy.set_dummy("Lingua Test", "Psalterium/Common/Translate.txt", table(&[("Versus Test", "Verse\n")]));
+        dummy.set_dummy("Lingua Fallback", "Psalterium/Common/Translate.txt", table(&[("Antiphona Test", "Antiphon")]));
+        load_translations(&mut dummy, "Lingua Test", "Lingua Fallback");
+        clear_missing_translations();
+
+        assert_eq!(translate_label("#Versus Test", "Lingua Test"), "#Verse");
+        assert_eq!(translate_label("Antiphona Test ", "Lingua Test"), "Antiphon");
+        assert_eq!(translate_label("$Oratio Test", "Lingua Test"), "$Oratio Test");
+        let missing = missing_translations();
+        let key = |k: &str| MissingTranslation { lang: "Lingua Test".to_string(), key: k.to_string() };
+        assert!(missing.contains(&key("Antiphona Test")) && missing.contains(&key("Oratio Test")));
+        assert!(!missing.contains(&key("Versus Test")));
+    }
+
+    #[test]
+    fn test_rubric_label_by_version() {
+        let mut dummy = DummySetupStringContext::new();
+        let table = |pairs: &[(&str, &str)]| pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        dummy.set_dummy(
+            "Lingua Rubrica",
+            "Psalterium/Common/Rubricae.txt",
+            table(&[("Antiphona", "Antiphon"), ("Antiphona 1955", "Antiphon (1955)"), ("secreto Cist", "in silence, standing")]),
+        );
+        dummy.set_dummy("Lingua Rubrica", "Psalterium/Common/Translate.txt", table(&[("secreto", "in silence")]));
+        load_translations(&mut dummy, "Lingua Rubrica", "");
+
+        let version = RubricsVersion::parse;
+        let lang = "Lingua Rubrica";
+        assert_eq!(rubric_label("Antiphona", lang, &version("Divino Afflatu - 1954")), "Antiphon");
+        assert_eq!(rubric_label("Antiphona", lang, &version("Rubrics 1960 - 1960")), "Antiphon (1955)");
+        assert_eq!(rubric_label("secreto", lang, &version("Monastic Tridentinum Cisterciensis 1951")), "in silence, standing");
+        assert_eq!(rubric_label("secreto", lang, &version("Divino Afflatu - 1954")), "in silence");
+        assert_eq!(
+            rubric_names("Antiphona", &version("Monastic - 1963")),
+            ["Antiphona 1960", "Antiphona 1955", "Antiphona M", "Antiphona"]
+        );
+    }
+
+    #[test]
+    fn test_lookup_translation_chain() {
+        let mut tables = HashMap::new();
+        tables.insert("Latin".to_string(), HashMap::from([("Hymnus".to_string(), "Hymnus".to_string())]));
+        tables.insert("Magyar".to_string(), HashMap::from([("Oratio".to_string(), "Könyörgés".to_string())]));
+        assert_eq!(lookup_translation(&tables, "English", "Oratio", "Magyar").as_deref(), Some("Könyörgés"));
+        assert_eq!(lookup_translation(&tables, "English", "Hymnus", "Magyar").as_deref(), Some("Hymnus"));
+        assert_eq!(lookup_translation(&tables, "Magyar", "Oratio", "Latin"), None);
+    }
+
     #[test]
     fn test_prayer_public_cist() {
         let mut dummy = DummySetupStringContext::new();
@@ -556,4 +942,45 @@ mod tests {
         let pr = prayer(&ctx, "Test", "English");
         assert_eq!(pr, "Some text");
     }
+
+    #[test]
+    fn test_prayer_variants_and_names() {
+        let mut dummy = DummySetupStringContext::new();
+        let mut prayers = HashMap::new();
+        prayers.insert("Confiteor".to_string(), "Confíteor Deo omnipoténti, beátæ Maríæ semper Vírgini".to_string());
+        prayers.insert("Confiteor OP".to_string(), "Confíteor Deo omnipoténti et beátæ Maríæ semper Vírgini et beáto Domínico".to_string());
+        prayers.insert("pro Papa".to_string(), "Orémus pro Pontífice nostro N.\nDóminus consérvet eum.".to_string());
+        prayers.insert("pro Episcopo".to_string(), "Orémus pro Antístite nostro N., et pro famulo tuo N.".to_string());
+        dummy.set_dummy("Latin", "Psalterium/Common/Prayers.txt", prayers);
+
+        let mut ctx = initialize_language_text_context(&mut dummy, "Latin", "English", "English", "Rubrics 1960 - 1960", false);
+        assert!(ctx.prayer("Confiteor", "Latin", "Rubrics 1960 - 1960").ends_with("semper Vírgini"));
+        assert!(ctx.prayer("Confiteor", "English", "Ordo Praedicatorum - 1962").ends_with("beáto Domínico"));
+        assert_eq!(ctx.prayer("pro Papa", "Latin", "Rubrics 1960 - 1960"), "Orémus pro Pontífice nostro N.\nDóminus consérvet eum.");
+
+        ctx.pope = "Leóne".to_string();
+        ctx.bishop = "Carolo".to_string();
+        assert_eq!(prayer(&ctx, "pro Papa", "Latin"), "Orémus pro Pontífice nostro Leóne\nDóminus consérvet eum.");
+        assert_eq!(prayer(&ctx, "pro Episcopo", "Latin"), "Orémus pro Antístite nostro Carolo, et pro famulo tuo N.");
+        assert_eq!(prayer(&ctx, "Missing", "Latin"), "Missing");
+    }
+
+    #[test]
+    fn test_ligaturize_exceptions() {
+        assert_eq!(ligaturize("poetae et poemata", "Latin"), "poetæ et poemata");
+        assert_eq!(ligaturize("in aere, ærumnae; aerumna", "Latin"), "in aere, ærumnæ; ærumna");
+        assert_eq!(ligaturize("Michael et Raphael, Noe", "Latin"), "Michael et Raphael, Noe");
+        assert_eq!(ligaturize("coepit, proelium", "Latin"), "cœpit, prœlium");
+        assert_eq!(ligaturize("Teuto, Aetna", "Cesky"), "Teuto, Aetna");
+    }
+
+    #[test]
+    fn test_accents_and_ligatures() {
+        assert_eq!(strip_accents("Dómine, ǽterna cœ́li Ýmnus"), "Domine, æterna cœli Ymnus");
+        assert_eq!(ligaturize("Caeli et terrae, Aegyptus, coelum", "Latin"), "Cœli et terræ, Ægyptus, cœlum");
+        assert_eq!(apply_accents("Dóminus", "Latin", AccentMode::Plain), "Dominus");
+        assert_eq!(apply_accents("Dóminus", "Latin-Bea", AccentMode::Plain), "Dominus");
+        assert_eq!(apply_accents("Dóminus", "Latin", AccentMode::Accented), "Dóminus");
+        assert_eq!(apply_accents("Café", "Francais", AccentMode::Plain), "Café");
+    }
 }
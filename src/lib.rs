@@ -32,20 +32,39 @@
 
 use std::io;
 
+pub mod compare;
 pub mod date;
 pub mod dialogcommon;
 pub mod directorium;
+#[cfg(feature = "embedded-data")]
+pub mod embedded;
+pub mod export;
 pub mod fileio;
+#[cfg(feature = "fuzzing")]
+pub mod fuzz;
 pub mod language_text_tools;
 pub mod runtime_options;
+pub mod scripture;
 pub mod scripting;
+#[cfg(feature = "server")]
+pub mod server;
 pub mod setup_string;
 pub mod setup;
+#[cfg(test)]
+mod test_support;
 pub mod regex;
+pub mod render;
+pub mod rubrics;
+pub mod validate;
+#[cfg(feature = "watch")]
+pub mod watch;
 pub mod missa;
 pub mod horas;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
-/// STARDAYS constant used in epactcycle.
+/// The new moons of the lunar calendar for epact 0 (days of the year), as
+/// used by the epact cycle of `date::lunar_day`.
 const STARDAYS: [i32; 14] = [1, 31, 60, 90, 119, 149, 178, 208, 237, 267, 296, 326, 355, 385];
 
 /// Month lengths (index 1..12); index 0 is unused.
@@ -68,9 +87,10 @@ static MONTH_NAMES: [&str; 13] = [
     "December",
 ];
 
-/// Wraps text in a font–styled SPAN. The font description is in "[size][ italic][ bold] color" format.
+/// Wraps text in a font–styled SPAN. The font description is in "[size][ italic][ bold] color" format
+/// (see `render::style::Style`).
 pub fn setfont(font: &str, text: &str) -> String {
-    format!("<SPAN STYLE=\"font:{};\">{}</SPAN>", font, text)
+    render::style::Style::parse(font).span(text)
 }
 
 /// Returns a list of available vernacular languages for the datafiles
@@ -0,0 +1,34 @@
+//! defunctorum.rs
+//!
+//! The Mass of the Dead, said with the Office of the Dead (`votive =
+//! Defunctorum`): its proper is `Commune/C9.txt` of the Mass data, whose
+//! `[Sequentia]` section is the *Dies iræ*.
+
+use crate::setup_string::{ResolveDirectives, SetupStringProvider};
+
+/// The file of the Mass of the Dead.
+pub const DEFUNCTORUM_FILE: &str = "Commune/C9.txt";
+
+/// Returns the sequence *Dies iræ* of the Mass of the Dead in `lang`, or
+/// `None` if the Mass data has no such section.
+pub fn dies_irae(files: &mut dyn SetupStringProvider, lang: &str) -> Option<String> {
+    files
+        .setupstring(lang, DEFUNCTORUM_FILE, ResolveDirectives::All)?
+        .get("Sequentia")
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::horas::specials::tests::TestFiles;
+
+    #[test]
+    fn test_dies_irae() {
+        let mut files = TestFiles::default()
+            .with("Latin", DEFUNCTORUM_FILE, &[("Sequentia", "Dies iræ, dies illa,\nSolvet sæclum in favílla:\n")]);
+        assert_eq!(dies_irae(&mut files, "Latin").unwrap(), "Dies iræ, dies illa,\nSolvet sæclum in favílla:");
+        assert!(dies_irae(&mut TestFiles::default(), "Latin").is_none());
+    }
+}
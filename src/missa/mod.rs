@@ -0,0 +1,13 @@
+//! The Mass (the `missa` folder of the Perl site). Its data lives in
+//! `web/www/missa`, with the same layout and file format as the Hours, so
+//! the functions here read it through a `SetupStringProvider` whose data
+//! folder is the Mass one.
+
+pub mod defunctorum;
+pub mod preface;
+pub mod propers;
+pub mod triduum;
+
+pub use preface::{canon_insertions, preface_for, Preface};
+pub use propers::{propers, Propers};
+pub use triduum::{triduum, HolyWeekService};
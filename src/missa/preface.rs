@@ -0,0 +1,343 @@
+//! preface.rs
+//!
+//! The changing parts of the Canon of the Mass: the preface of the day and
+//! the proper *Communicantes* and *Hanc igitur* of the great feasts and
+//! their octaves.
+//!
+//! ```ignore
+//! let preface = preface_for(&mut ctx, "12-25-2025", "Rubrics 1960 - 1960")?;
+//! assert_eq!(preface, Preface::Nativitate);
+//! let canon = canon_insertions(&mut ctx, "04-21-2025", "Rubrics 1960 - 1960")?;
+//! assert_eq!(canon.hanc_igitur, Some(HancIgitur::Paschalis));
+//! ```
+//!
+//! An office can name its preface in its `[Rule]` ("Prefatio=Apostolis",
+//! "Prefatio=Maria=et te in Assumptione"); otherwise the feast of the day
+//! and then the season decide. The texts are the sections of
+//! `PREFATIONES_FILE` named by `section()`.
+
+use std::io;
+
+use crate::horas::{DayOffice, Kalendar};
+use crate::regex::contains_ci;
+use crate::setup_string::{ResolveDirectives, SetupStringContext};
+
+/// The file of the prefaces and of the proper texts of the Canon.
+pub const PREFATIONES_FILE: &str = "Ordo/Prefationes.txt";
+
+/// A preface of the Mass.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Preface {
+    Communis,
+    Nativitate,
+    Epiphania,
+    Quadragesima,
+    /// Of the Holy Cross, also of Passiontide.
+    Cruce,
+    Paschalis,
+    Ascensione,
+    Pentecoste,
+    /// Of the Holy Trinity, also of the Sundays since 1759.
+    Trinitate,
+    /// Of Our Lady, with the name of the feast ("et te in Festivitate").
+    Maria(String),
+    Apostolis,
+    Defunctorum,
+    Joseph,
+    Cordis,
+    ChristiRegis,
+}
+
+impl Preface {
+    /// The section of the preface in `PREFATIONES_FILE`.
+    pub fn section(&self) -> &'static str {
+        match self {
+            Preface::Communis => "Communis",
+            Preface::Nativitate => "Nat",
+            Preface::Epiphania => "Epi",
+            Preface::Quadragesima => "Quad",
+            Preface::Cruce => "Cruce",
+            Preface::Paschalis => "Pasch",
+            Preface::Ascensione => "Asc",
+            Preface::Pentecoste => "Pent",
+            Preface::Trinitate => "Trinitate",
+            Preface::Maria(_) => "Maria",
+            Preface::Apostolis => "Apostolis",
+            Preface::Defunctorum => "Defunctorum",
+            Preface::Joseph => "Joseph",
+            Preface::Cordis => "Cordis",
+            Preface::ChristiRegis => "Christi Regis",
+        }
+    }
+
+    /// The preface a `[Rule]` names: "Maria=et te in Assumptione",
+    /// "Apostolis", or a section name.
+    pub fn from_rule(name: &str) -> Option<Self> {
+        let (name, clause) = match name.split_once('=') {
+            Some((name, clause)) => (name.trim(), Some(clause.trim())),
+            None => (name.trim(), None),
+        };
+        let preface = match name {
+            "Communis" => Preface::Communis,
+            "Nat" | "Nativitate" => Preface::Nativitate,
+            "Epi" | "Epiphania" => Preface::Epiphania,
+            "Quad" | "Quadragesima" => Preface::Quadragesima,
+            "Cruce" | "Quad5" => Preface::Cruce,
+            "Pasch" | "Pasc" | "Paschalis" => Preface::Paschalis,
+            "Asc" | "Ascensione" => Preface::Ascensione,
+            "Pent" | "Pentecoste" => Preface::Pentecoste,
+            "Trinitate" | "Trin" => Preface::Trinitate,
+            "Maria" | "BMV" => Preface::Maria(clause.unwrap_or(FESTIVITATE).to_string()),
+            "Apostolis" | "Apostoli" => Preface::Apostolis,
+            "Defunctorum" | "Defunct" => Preface::Defunctorum,
+            "Joseph" => Preface::Joseph,
+            "Cordis" | "SCJ" => Preface::Cordis,
+            "Christi Regis" | "ChristiRegis" | "DNJC" => Preface::ChristiRegis,
+            _ => return None,
+        };
+        Some(preface)
+    }
+}
+
+const FESTIVITATE: &str = "et te in Festivitate";
+
+/// The proper *Communicantes* of a feast and its octave.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Communicantes {
+    Nativitate,
+    Epiphania,
+    /// Of Maundy Thursday.
+    CoenaDomini,
+    Paschalis,
+    Ascensione,
+    Pentecoste,
+}
+
+impl Communicantes {
+    /// The section of the text in `PREFATIONES_FILE`.
+    pub fn section(self) -> &'static str {
+        match self {
+            Communicantes::Nativitate => "Communicantes Nat",
+            Communicantes::Epiphania => "Communicantes Epi",
+            Communicantes::CoenaDomini => "Communicantes Coena",
+            Communicantes::Paschalis => "Communicantes Pasch",
+            Communicantes::Ascensione => "Communicantes Asc",
+            Communicantes::Pentecoste => "Communicantes Pent",
+        }
+    }
+}
+
+/// The proper *Hanc igitur*.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HancIgitur {
+    CoenaDomini,
+    Paschalis,
+    Pentecoste,
+}
+
+impl HancIgitur {
+    /// The section of the text in `PREFATIONES_FILE`.
+    pub fn section(self) -> &'static str {
+        match self {
+            HancIgitur::CoenaDomini => "Hanc igitur Coena",
+            HancIgitur::Paschalis => "Hanc igitur Pasch",
+            HancIgitur::Pentecoste => "Hanc igitur Pent",
+        }
+    }
+}
+
+/// The proper insertions into the Canon of a day; `None` where the Canon
+/// has its ordinary text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CanonInsertions {
+    pub communicantes: Option<Communicantes>,
+    pub hanc_igitur: Option<HancIgitur>,
+}
+
+/// Returns the preface of the Mass of `date` ("MM-DD-YYYY") in `version`,
+/// resolving its office from the Latin files of `ctx.datafolder`.
+pub fn preface_for(ctx: &mut SetupStringContext, date: &str, version: &str) -> io::Result<Preface> {
+    let office = Kalendar::new(version).office(ctx, date, "Latin")?;
+    let winner = ctx.setupstring("Latin", &office.winner, ResolveDirectives::All).unwrap_or_default();
+    let rule = winner.get("Rule").map(String::as_str).unwrap_or("");
+    if let Some(preface) = rule
+        .lines()
+        .find_map(|l| l.trim().strip_prefix("Prefatio="))
+        .and_then(Preface::from_rule)
+    {
+        return Ok(preface);
+    }
+    let title = winner.get("Rank").and_then(|r| r.split(";;").next()).unwrap_or("");
+    Ok(preface_of(&office, title, &ctx.votive, version))
+}
+
+/// The preface of `office` (whose title is `title`) by its feast and its
+/// season.
+fn preface_of(office: &DayOffice, title: &str, votive: &str, version: &str) -> Preface {
+    let tridentine = contains_ci(version, "Tridentine");
+    let reformed = version.contains("1955") || version.contains("196");
+    let (week, dow) = split_dayname(&office.dayname);
+    let (month, day) = (office.month, office.day);
+
+    if contains_ci(votive, "Defunct") || contains_ci(title, "Defunctorum") {
+        return if tridentine { Preface::Communis } else { Preface::Defunctorum };
+    }
+    // The feasts with a preface of their own.
+    match (month, day) {
+        (12, 25..=31) | (1, 1..=5) | (2, 2) => return Preface::Nativitate,
+        (1, 6..=13) | (8, 6) => return Preface::Epiphania,
+        (9, 14) | (7, 1) => return Preface::Cruce,
+        (5, 3) if !reformed => return Preface::Cruce,
+        (3, 19) if !tridentine => return Preface::Joseph,
+        (5, 1) if reformed => return Preface::Joseph,
+        _ => {}
+    }
+    if !tridentine {
+        if office.dayname == "Pent02-5" {
+            return Preface::Cordis;
+        }
+        // Christ the King, the last Sunday of October.
+        if month == 10 && dow == 0 && day >= 25 {
+            return Preface::ChristiRegis;
+        }
+        if office.dayname == "Pasc2-3" && !reformed {
+            return Preface::Joseph;
+        }
+    }
+    if office.dayname == "Pent01-0" {
+        return Preface::Trinitate;
+    }
+    // Corpus Christi and its octave, suppressed in 1955.
+    let corpus_christi = matches!(office.dayname.as_str(), "Pent01-4" | "Pent01-5" | "Pent01-6")
+        || (week == "Pent02" && (1..=4).contains(&dow));
+    if corpus_christi && !reformed {
+        return Preface::Nativitate;
+    }
+    if contains_ci(title, "Apostol") {
+        return Preface::Apostolis;
+    }
+    let marian = ["Mariæ", "Mariae", "B.M.V.", "B. M. V."].iter().any(|m| contains_ci(title, m));
+    if marian {
+        return Preface::Maria(FESTIVITATE.to_string());
+    }
+
+    // The seasons.
+    match season_number(week, "Quad") {
+        Some(5..=6) if office.dayname == "Quad6-6" => return Preface::Paschalis,
+        Some(5..=6) => return Preface::Cruce,
+        Some(_) => return Preface::Quadragesima,
+        None => {}
+    }
+    if week == "Quadp3" && dow >= 3 {
+        return Preface::Quadragesima;
+    }
+    match season_number(week, "Pasc") {
+        Some(5) if dow >= 4 => return Preface::Ascensione,
+        Some(6) if dow == 6 => return Preface::Pentecoste,
+        Some(6) => return Preface::Ascensione,
+        Some(7) => return Preface::Pentecoste,
+        Some(_) => return Preface::Paschalis,
+        None => {}
+    }
+    if dow == 0 && !version.contains("1570") {
+        return Preface::Trinitate;
+    }
+    Preface::Communis
+}
+
+/// Returns the proper *Communicantes* and *Hanc igitur* of the Mass of
+/// `date` ("MM-DD-YYYY") in `version`.
+pub fn canon_insertions(ctx: &mut SetupStringContext, date: &str, version: &str) -> io::Result<CanonInsertions> {
+    let office = Kalendar::new(version).office(ctx, date, "Latin")?;
+    Ok(insertions_of(&office, version))
+}
+
+fn insertions_of(office: &DayOffice, version: &str) -> CanonInsertions {
+    let reformed = version.contains("1955") || version.contains("196");
+    let (week, dow) = split_dayname(&office.dayname);
+    let communicantes = match (office.month, office.day) {
+        (12, 25..=31) | (1, 1) => Some(Communicantes::Nativitate),
+        (1, 6) => Some(Communicantes::Epiphania),
+        (1, 7..=13) if !reformed => Some(Communicantes::Epiphania),
+        _ => None,
+    };
+    let paschal = |c, h| CanonInsertions { communicantes: Some(c), hanc_igitur: Some(h) };
+    match (week, dow) {
+        ("Quad6", 4) => paschal(Communicantes::CoenaDomini, HancIgitur::CoenaDomini),
+        ("Quad6", 6) | ("Pasc0", _) => paschal(Communicantes::Paschalis, HancIgitur::Paschalis),
+        ("Pasc6", 6) | ("Pasc7", _) => paschal(Communicantes::Pentecoste, HancIgitur::Pentecoste),
+        ("Pasc5", 4..=6) | ("Pasc6", 0..=4) => CanonInsertions { communicantes: Some(Communicantes::Ascensione), hanc_igitur: None },
+        _ => CanonInsertions { communicantes, hanc_igitur: None },
+    }
+}
+
+/// Splits a day of the Proper of Time ("Pasc5-4") into its week and its
+/// day of the week.
+fn split_dayname(dayname: &str) -> (&str, u32) {
+    match dayname.rsplit_once('-') {
+        Some((week, dow)) => (week, dow.parse().unwrap_or(0)),
+        None => (dayname, 0),
+    }
+}
+
+/// The number of the week `week` of the season `prefix` ("Quad5" is the
+/// fifth of "Quad"), or `None` if it is of another season.
+fn season_number(week: &str, prefix: &str) -> Option<u32> {
+    week.strip_prefix(prefix).and_then(|n| n.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn office(date: (u32, u32), dayname: &str) -> DayOffice {
+        let (month, day) = date;
+        DayOffice {
+            date: format!("{:02}-{:02}-2025", month, day),
+            month,
+            day,
+            year: 2025,
+            dayofweek: split_dayname(dayname).1,
+            dayname: dayname.to_string(),
+            version: String::new(),
+            winner: String::new(),
+            commemoratio: String::new(),
+            commune: String::new(),
+            communetype: String::new(),
+            octave: None,
+        }
+    }
+
+    #[test]
+    fn test_preface_of() {
+        let rubrics = "Rubrics 1960 - 1960";
+        assert_eq!(preface_of(&office((12, 25), "Nat25-4"), "", "", rubrics), Preface::Nativitate);
+        assert_eq!(preface_of(&office((3, 19), "Quad3-3"), "S. Joseph", "", rubrics), Preface::Joseph);
+        assert_eq!(preface_of(&office((3, 19), "Quad3-3"), "S. Joseph", "", "Tridentine - 1570"), Preface::Quadragesima);
+        assert_eq!(preface_of(&office((4, 7), "Quad5-1"), "", "", rubrics), Preface::Cruce);
+        assert_eq!(preface_of(&office((5, 29), "Pasc5-4"), "", "", rubrics), Preface::Ascensione);
+        assert_eq!(preface_of(&office((6, 7), "Pasc6-6"), "", "", rubrics), Preface::Pentecoste);
+        assert_eq!(preface_of(&office((10, 26), "Pent20-0"), "", "", rubrics), Preface::ChristiRegis);
+        assert_eq!(preface_of(&office((8, 24), "Pent11-0"), "", "", rubrics), Preface::Trinitate);
+        assert_eq!(preface_of(&office((8, 24), "Pent11-0"), "", "", "Tridentine - 1570"), Preface::Communis);
+        assert_eq!(preface_of(&office((11, 30), "Adv1-0"), "S. Andreæ Apostoli", "", rubrics), Preface::Apostolis);
+        assert_eq!(
+            preface_of(&office((8, 15), "Pent10-5"), "In Assumptione B.M.V.", "", rubrics),
+            Preface::Maria(FESTIVITATE.to_string())
+        );
+        assert_eq!(preface_of(&office((8, 12), "Pent10-2"), "", "Defunctorum", rubrics), Preface::Defunctorum);
+        assert_eq!(Preface::from_rule("Maria=et te in Assumptione"), Some(Preface::Maria("et te in Assumptione".to_string())));
+    }
+
+    #[test]
+    fn test_insertions_of() {
+        let rubrics = "Rubrics 1960 - 1960";
+        let canon = insertions_of(&office((4, 21), "Pasc0-1"), rubrics);
+        assert_eq!(canon, CanonInsertions { communicantes: Some(Communicantes::Paschalis), hanc_igitur: Some(HancIgitur::Paschalis) });
+        assert_eq!(insertions_of(&office((4, 17), "Quad6-4"), rubrics).hanc_igitur, Some(HancIgitur::CoenaDomini));
+        assert_eq!(insertions_of(&office((5, 29), "Pasc5-4"), rubrics).communicantes, Some(Communicantes::Ascensione));
+        assert_eq!(insertions_of(&office((1, 8), "Epi1-3"), rubrics), CanonInsertions::default());
+        assert_eq!(insertions_of(&office((1, 8), "Epi1-3"), "Divino Afflatu").communicantes, Some(Communicantes::Epiphania));
+        assert_eq!(insertions_of(&office((8, 24), "Pent11-0"), rubrics), CanonInsertions::default());
+    }
+}
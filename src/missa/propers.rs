@@ -0,0 +1,122 @@
+//! propers.rs
+//!
+//! The proper of the Mass of a day: its Introit, Collect, Epistle, Gradual,
+//! Gospel, Offertory, Secret, Communion and Postcommunion, without the
+//! Ordinary around them.
+//!
+//! ```ignore
+//! let propers = propers(&mut ctx, "01-21-2025", "Divino Afflatu", "Latin")?;
+//! let gospel = propers.gospel.unwrap();
+//! println!("{} {}", gospel.references[0], gospel.text);
+//! ```
+//!
+//! The office of the day is resolved by `Kalendar::office()` over the Mass
+//! data; a part its file does not have is taken from its commune. The
+//! scripture references are those of the rubric lines of the parts ("!Matt
+//! 25:1-13"), as `scripture::references_in()` reads them.
+
+use std::io;
+
+use crate::horas::Kalendar;
+use crate::scripture::{references_in, Reference};
+use crate::setup_string::{FileSections, ResolveDirectives, SetupStringContext};
+
+/// A part of the proper: its text, as in the data, and the references of
+/// its rubric lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Proper {
+    pub text: String,
+    pub references: Vec<Reference>,
+}
+
+impl Proper {
+    fn new(text: &str) -> Self {
+        Proper { text: text.trim().to_string(), references: references_in(text) }
+    }
+}
+
+/// The proper of a Mass; `None` for a part neither the file of the day nor
+/// its commune has.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Propers {
+    /// The file of the Mass, e.g. "Sancti/01-21.txt".
+    pub file: String,
+    pub introit: Option<Proper>,
+    pub collect: Option<Proper>,
+    pub epistle: Option<Proper>,
+    /// The Gradual, or in its place the Tract.
+    pub gradual: Option<Proper>,
+    pub gospel: Option<Proper>,
+    pub offertory: Option<Proper>,
+    pub secret: Option<Proper>,
+    pub communion: Option<Proper>,
+    pub postcommunion: Option<Proper>,
+}
+
+/// Returns the proper of the Mass of `date` ("MM-DD-YYYY") in `version`,
+/// reading the Mass data of `ctx.datafolder` in `lang`.
+pub fn propers(ctx: &mut SetupStringContext, date: &str, version: &str, lang: &str) -> io::Result<Propers> {
+    let office = Kalendar::new(version).office(ctx, date, lang)?;
+    let winner = ctx.setupstring(lang, &office.winner, ResolveDirectives::All).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, format!("no Mass file {} in {}", office.winner, lang))
+    })?;
+    let commune = if office.commune.is_empty() {
+        FileSections::new()
+    } else {
+        ctx.setupstring(lang, &office.commune, ResolveDirectives::All).unwrap_or_default()
+    };
+    let part = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|name| {
+                [&winner, &commune].into_iter().find_map(|s| s.get(*name).filter(|t| !t.trim().is_empty()))
+            })
+            .map(|text| Proper::new(text))
+    };
+    Ok(Propers {
+        introit: part(&["Introitus"]),
+        collect: part(&["Oratio"]),
+        epistle: part(&["Lectio"]),
+        gradual: part(&["Graduale", "Tractus"]),
+        gospel: part(&["Evangelium"]),
+        offertory: part(&["Offertorium"]),
+        secret: part(&["Secreta"]),
+        communion: part(&["Communio"]),
+        postcommunion: part(&["Postcommunio"]),
+        file: office.winner,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::data_tree;
+
+    #[test]
+    fn test_propers() {
+        let (_root, mut ctx) = data_tree(&[
+            ("Latin/Tempora/Epi2-2.txt", "[Rank]\nFeria Tertia;;Feria;;1\n"),
+            (
+                "Latin/Sancti/01-21.txt",
+                "[Rank]\nS. Agnetis Virginis et Martyris;;Duplex;;3;;vide C6\n\n\
+                 [Introitus]\n!Ps 118:95-96\nv. Me exspectavérunt peccatóres.\n\n\
+                 [Oratio]\nOmnípotens sempitérne Deus.\n\n\
+                 [Evangelium]\nSequéntia ✠ sancti Evangélii secúndum Matthǽum.\n!Matt 25:1-13\nIn illo témpore.\n",
+            ),
+            ("Latin/Commune/C6.txt", "[Offertorium]\n!Ps 44:15-16\nAfferéntur Regi vírgines.\n"),
+        ]);
+
+        let propers = propers(&mut ctx, "01-21-2025", "Divino Afflatu", "Latin").unwrap();
+        assert_eq!(propers.file, "Sancti/01-21.txt");
+        assert_eq!(propers.introit.unwrap().references[0].to_string(), "Ps 118:95-96");
+        assert_eq!(propers.collect.unwrap().text, "Omnípotens sempitérne Deus.");
+        let gospel = propers.gospel.unwrap();
+        assert_eq!(gospel.references, [Reference { book: "Matt".to_string(), chapter: 25, verses: vec![(1, 13)] }]);
+        assert!(gospel.text.ends_with("In illo témpore."));
+        // From the commune.
+        assert_eq!(propers.offertory.unwrap().references[0].book, "Ps");
+        assert!(propers.epistle.is_none());
+    }
+}
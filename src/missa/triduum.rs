@@ -0,0 +1,177 @@
+//! triduum.rs
+//!
+//! The services of Holy Week that are not a Mass of the ordinary form: the
+//! blessing of the palms and the procession of Palm Sunday, the Mass of
+//! Maundy Thursday with the stripping of the altars and the Mandatum, the
+//! Good Friday liturgy and the Easter Vigil with its prophecies and the
+//! *Exsultet*.
+//!
+//! ```ignore
+//! let text = triduum(&mut ctx, "04-19-2025", "Rubrics 1960 - 1960", "Latin")?;
+//! assert!(text.starts_with("#Benedictio ignis"));
+//! ```
+//!
+//! The parts of a service and their order are those of the Ordo of the
+//! reform of 1955 for the versions from then, and of the older Missal
+//! before; each part is the section of the same name in the file of the
+//! day ("Tempora/Quad6-5.txt"), skipped where the data has none.
+
+use std::io;
+
+use crate::horas::Kalendar;
+use crate::setup_string::{ResolveDirectives, SetupStringContext};
+
+/// A service of Holy Week.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HolyWeekService {
+    PalmSunday,
+    MaundyThursday,
+    GoodFriday,
+    EasterVigil,
+}
+
+impl HolyWeekService {
+    /// The service of a day of the Proper of Time ("Quad6-5"), if any.
+    pub fn of_day(dayname: &str) -> Option<Self> {
+        match dayname {
+            "Quad6-0" => Some(HolyWeekService::PalmSunday),
+            "Quad6-4" => Some(HolyWeekService::MaundyThursday),
+            "Quad6-5" => Some(HolyWeekService::GoodFriday),
+            "Quad6-6" => Some(HolyWeekService::EasterVigil),
+            _ => None,
+        }
+    }
+
+    /// The parts of the service in order, in the form of `version`.
+    pub fn parts(self, version: &str) -> Vec<String> {
+        let reformed = is_reformed(version);
+        let parts: &[&str] = match (self, reformed) {
+            (HolyWeekService::PalmSunday, false) => &[
+                "Benedictio Palmarum",
+                "Lectio",
+                "Graduale",
+                "Evangelium",
+                "Praefatio",
+                "Sanctus",
+                "Orationes",
+                "Distributio Palmarum",
+                "Processio",
+                "Missa",
+            ],
+            (HolyWeekService::PalmSunday, true) => {
+                &["Benedictio Palmarum", "Distributio Palmarum", "Processio", "Missa"]
+            }
+            (HolyWeekService::MaundyThursday, false) => {
+                &["Missa", "Processio ad repositorium", "Denudatio altarium", "Mandatum"]
+            }
+            (HolyWeekService::MaundyThursday, true) => {
+                &["Missa", "Mandatum", "Processio ad repositorium", "Denudatio altarium"]
+            }
+            (HolyWeekService::GoodFriday, false) => &[
+                "Lectiones",
+                "Passio",
+                "Orationes solemnes",
+                "Adoratio Crucis",
+                "Missa Praesanctificatorum",
+            ],
+            (HolyWeekService::GoodFriday, true) => {
+                &["Lectiones", "Passio", "Orationes solemnes", "Adoratio Crucis", "Communio"]
+            }
+            (HolyWeekService::EasterVigil, false) => {
+                return ["Benedictio ignis", "Exsultet"]
+                    .into_iter()
+                    .map(String::from)
+                    .chain((1..=12).map(|n| format!("Prophetia {}", n)))
+                    .chain(["Benedictio fontis", "Litaniae", "Missa"].into_iter().map(String::from))
+                    .collect();
+            }
+            (HolyWeekService::EasterVigil, true) => {
+                return ["Benedictio ignis", "Processio", "Exsultet"]
+                    .into_iter()
+                    .map(String::from)
+                    .chain((1..=4).map(|n| format!("Lectio {}", n)))
+                    .chain(
+                        ["Benedictio fontis", "Renovatio promissionum", "Litaniae", "Missa"]
+                            .into_iter()
+                            .map(String::from),
+                    )
+                    .collect();
+            }
+        };
+        parts.iter().map(|p| p.to_string()).collect()
+    }
+}
+
+/// Whether `version` follows the Holy Week of the reform of 1955.
+fn is_reformed(version: &str) -> bool {
+    version.contains("1955") || version.contains("196")
+}
+
+/// Returns the text of the Holy Week service of `date` ("MM-DD-YYYY") in
+/// `version`, in `lang`: its parts as `#` chapters, read from the Mass data
+/// of `ctx.datafolder`.
+///
+/// Fails with `InvalidInput` if the day has no such service, and with
+/// `NotFound` if the data has none of its parts.
+pub fn triduum(ctx: &mut SetupStringContext, date: &str, version: &str, lang: &str) -> io::Result<String> {
+    let office = Kalendar::new(version).office(ctx, date, lang)?;
+    let service = HolyWeekService::of_day(&office.dayname).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, format!("{} ({}) has no Holy Week service", date, office.dayname))
+    })?;
+    let file = format!("Tempora/{}.txt", office.dayname);
+    let sections = ctx.setupstring(lang, &file, ResolveDirectives::All).unwrap_or_default();
+    let text: String = service
+        .parts(version)
+        .iter()
+        .filter_map(|part| {
+            let body = sections.get(part.as_str())?.trim();
+            (!body.is_empty()).then(|| format!("#{}\n{}\n\n", part, body))
+        })
+        .collect();
+    if text.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("no parts of {:?} in {}", service, file)));
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::data_tree;
+
+    #[test]
+    fn test_parts_by_version() {
+        let vigil = HolyWeekService::EasterVigil;
+        let old = vigil.parts("Divino Afflatu");
+        assert_eq!(old.iter().filter(|p| p.starts_with("Prophetia")).count(), 12);
+        assert_eq!(old[..3], ["Benedictio ignis", "Exsultet", "Prophetia 1"]);
+        let new = vigil.parts("Rubrics 1960 - 1960");
+        assert_eq!(new[..3], ["Benedictio ignis", "Processio", "Exsultet"]);
+        assert!(new.contains(&"Renovatio promissionum".to_string()));
+        assert!(HolyWeekService::GoodFriday.parts("Reduced - 1955").contains(&"Communio".to_string()));
+        assert_eq!(HolyWeekService::of_day("Quad6-3"), None);
+    }
+
+    #[test]
+    fn test_triduum() {
+        let (_root, mut ctx) = data_tree(&[(
+            "Latin/Tempora/Quad6-5.txt",
+            "[Rank]\nFeria VI in Parasceve;;Duplex I. classis;;7\n\n[Passio]\nPassio Dómini nostri Jesu Christi.\n\n\
+             [Adoratio Crucis]\nEcce lignum Crucis.\n\n[Missa Praesanctificatorum]\nPræcéptis salutáribus móniti.\n\n\
+             [Communio]\nPater noster.\n",
+        )]);
+
+        // Good Friday 2025 is 18 April.
+        let text = triduum(&mut ctx, "04-18-2025", "Divino Afflatu", "Latin").unwrap();
+        assert!(text.starts_with("#Passio\nPassio Dómini nostri Jesu Christi.\n\n#Adoratio Crucis\n"));
+        assert!(text.contains("#Missa Praesanctificatorum"));
+        assert!(!text.contains("#Communio"));
+        let text = triduum(&mut ctx, "04-18-2025", "Rubrics 1960 - 1960", "Latin").unwrap();
+        assert!(text.ends_with("#Communio\nPater noster.\n\n"));
+
+        let err = triduum(&mut ctx, "04-16-2025", "Divino Afflatu", "Latin").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let err = triduum(&mut ctx, "04-17-2025", "Divino Afflatu", "Latin").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -1,3 +1,9 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use ::regex::{NoExpand, Regex, RegexBuilder};
+use once_cell::sync::Lazy;
+
 
 /// Replaces words in `s` that start with `prefix` (case-insensitively) by `replacement`.
 /// The function splits the string on whitespace and reassembles it with single spaces.
@@ -16,38 +22,14 @@ pub fn replace_word_prefix(s: &str, prefix: &str, replacement: &str) -> String {
         .join(" ")
 }
 
-/// Helper function: replaces all occurrences of a pattern in a case–insensitive manner.
-/// This is a simple implementation that uses the regex crate.
 /// Replaces all occurrences of `pat` in `s` with `rep` in a case–insensitive manner.
-/// This implementation does not use regex but uses simple string search and slicing.
+///
+/// The matches are those of `s` itself, not of a lowercased copy, whose
+/// offsets differ from those of `s` for some letters (e.g. "ẞ").
 ///
 /// If `pat` is empty, the original string is returned unchanged.
 pub fn replace_all_case_insensitive(s: &str, pat: &str, rep: &str) -> String {
-    if pat.is_empty() {
-        return s.to_string();
-    }
-    
-    // Convert the source string and the pattern to lowercase for case-insensitive matching.
-    let s_lower = s.to_lowercase();
-    let pat_lower = pat.to_lowercase();
-    
-    let mut result = String::with_capacity(s.len());
-    let mut start = 0;
-    
-    // Loop while we can find the pattern in the lowercase version.
-    while let Some(pos) = s_lower[start..].find(&pat_lower) {
-        let match_start = start + pos;
-        // Append the part of the original string before the match.
-        result.push_str(&s[start..match_start]);
-        // Append the replacement.
-        result.push_str(rep);
-        // Advance the start index beyond the matched pattern.
-        start = match_start + pat.len();
-    }
-    
-    // Append the remaining part of the original string.
-    result.push_str(&s[start..]);
-    result
+    ci_replace_all(s, pat, rep)
 }
 
 /// Searches for all occurrences of `needle` in `s` (which is assumed to be lowercase)
@@ -135,9 +117,16 @@ pub fn contains_in_order(haystack: &str, first: &str, second: &str) -> bool {
     }
 }
 
+/// Returns the byte offset in `haystack` of the first occurrence of `needle`
+/// (ignoring case), to slice `haystack` with: the offset of a match in a
+/// lowercased copy need not be on a char boundary of the original.
+pub fn ci_find(haystack: &str, needle: &str) -> Option<usize> {
+    ci_literal(needle).find(haystack).map(|m| m.start())
+}
+
 /// Returns true if `haystack` contains `needle` (ignoring case).
 pub fn ci_contains(haystack: &str, needle: &str) -> bool {
-    haystack.to_lowercase().contains(&needle.to_lowercase())
+    ci_literal(needle).is_match(haystack)
 }
 
 /// Returns true if the given filename matches the pattern "C1[a-z]?".
@@ -173,19 +162,12 @@ pub fn fallback_lang(lang: &str) -> Option<String> {
 }
 
 /// Replaces all occurrences of `needle` with `replacement` in a case–insensitive way.
+/// `replacement` is inserted literally (no `$1` expansion).
 pub fn ci_replace_all(haystack: &str, needle: &str, replacement: &str) -> String {
-    let lower_h = haystack.to_lowercase();
-    let lower_n = needle.to_lowercase();
-    let mut result = String::new();
-    let mut start = 0;
-    while let Some(pos) = lower_h[start..].find(&lower_n) {
-        let pos = start + pos;
-        result.push_str(&haystack[start..pos]);
-        result.push_str(replacement);
-        start = pos + needle.len();
+    if needle.is_empty() {
+        return haystack.to_string();
     }
-    result.push_str(&haystack[start..]);
-    result
+    ci_literal(needle).replace_all(haystack, NoExpand(replacement)).into_owned()
 }
 
 /// Returns true if `s` starts with `prefix` (ignoring case).
@@ -249,19 +231,115 @@ pub fn starts_with_ignore_case(haystack: &str, prefix: &str) -> bool {
 }
 
 pub fn contains_ci(haystack: &str, needle: &str) -> bool {
-    let hl = haystack.to_lowercase();
-    for n in needle.split("|").map(|s| s.trim()) {
-        if hl.contains(&needle.to_lowercase()) {
-            return true;
+    ci_contains(haystack, needle)
+}
+
+/// Compiled case-insensitive patterns, by pattern text. Patterns are compiled
+/// on first use and shared afterwards, so the helpers below cost one lookup
+/// per call instead of lowercased copies of their arguments.
+static PATTERNS: Lazy<RwLock<HashMap<String, Arc<Regex>>>> = Lazy::new(Default::default);
+
+/// Returns the compiled case-insensitive matcher for the regular expression
+/// `pattern`, compiling it the first time it is asked for.
+pub fn ci_regex(pattern: &str) -> Result<Arc<Regex>, ::regex::Error> {
+    if let Some(re) = PATTERNS.read().unwrap_or_else(|e| e.into_inner()).get(pattern) {
+        return Ok(re.clone());
+    }
+    let re = Arc::new(RegexBuilder::new(pattern).case_insensitive(true).build()?);
+    let mut patterns = PATTERNS.write().unwrap_or_else(|e| e.into_inner());
+    Ok(patterns.entry(pattern.to_string()).or_insert(re).clone())
+}
+
+/// Returns the cached case-insensitive matcher for the literal text `needle`.
+pub fn ci_literal(needle: &str) -> Arc<Regex> {
+    ci_regex(&::regex::escape(needle)).expect("escaped literal is a valid pattern")
+}
+
+/// Replaces the `n`th match (counting from 0) of the case-insensitive regular
+/// expression `pattern` in `s` by `replacement`, inserted literally.
+///
+/// Returns `s` unchanged if there are fewer matches or `pattern` is invalid.
+pub fn replace_nth(s: &str, pattern: &str, n: usize, replacement: &str) -> String {
+    let Some(m) = ci_regex(pattern).ok().and_then(|re| re.find_iter(s).nth(n)) else {
+        return s.to_string();
+    };
+    let mut result = String::with_capacity(s.len() + replacement.len());
+    result.push_str(&s[..m.start()]);
+    result.push_str(replacement);
+    result.push_str(&s[m.end()..]);
+    result
+}
+
+/// Returns the first capture group of the first match of the case-insensitive
+/// regular expression `pattern` in `s` (the whole match if it has no group).
+pub fn capture_first(s: &str, pattern: &str) -> Option<String> {
+    let re = ci_regex(pattern).ok()?;
+    let caps = re.captures(s)?;
+    caps.get(1).or_else(|| caps.get(0)).map(|m| m.as_str().to_string())
+}
+
+/// Splits `s` at the matches of the case-insensitive regular expression
+/// `pattern`, keeping each separator as an item of its own:
+/// `"a, b; c"` split at `"[,;]"` gives `["a", ",", " b", ";", " c"]`.
+///
+/// Empty pieces between adjacent separators are dropped; an invalid
+/// `pattern` gives `s` whole.
+pub fn split_keepsep<'a>(s: &'a str, pattern: &str) -> Vec<&'a str> {
+    let Ok(re) = ci_regex(pattern) else {
+        return vec![s];
+    };
+    let mut parts = Vec::new();
+    let mut start = 0;
+    for m in re.find_iter(s).filter(|m| !m.is_empty()) {
+        if m.start() > start {
+            parts.push(&s[start..m.start()]);
         }
+        parts.push(m.as_str());
+        start = m.end();
     }
-    false
+    if start < s.len() {
+        parts.push(&s[start..]);
+    }
+    parts
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_ci_helpers() {
+        assert!(ci_contains("Feria Quinta in Cena Domini", "CENA"));
+        assert!(contains_ci("Commune/C10", "c10"));
+        assert!(!ci_contains("Dominica", "Feria"));
+        assert!(ci_contains("a.b", "A.B") && !ci_contains("axb", "a.b"));
+        assert_eq!(ci_replace_all("Ant. ALLELUIA, alleluia.", "alleluia", "$1"), "Ant. $1, $1.");
+        assert_eq!(ci_replace_all("abc", "", "x"), "abc");
+        assert!(Arc::ptr_eq(&ci_regex("^v\\.").unwrap(), &ci_regex("^v\\.").unwrap()));
+        assert!(ci_regex("(").is_err());
+    }
+
+    #[test]
+    fn test_replace_nth() {
+        assert_eq!(replace_nth("a * b * c", r"\*", 1, "+"), "a * b + c");
+        assert_eq!(replace_nth("a * b", r"\*", 3, "+"), "a * b");
+        assert_eq!(replace_nth("V. x V. y", "v\\.", 0, "R."), "R. x V. y");
+    }
+
+    #[test]
+    fn test_capture_first() {
+        assert_eq!(capture_first("Lectio 7 et 8", r"lectio (\d+)"), Some("7".to_string()));
+        assert_eq!(capture_first("Pasc0-1", r"pasc\d"), Some("Pasc0".to_string()));
+        assert_eq!(capture_first("Pent05", r"quad(\d)"), None);
+    }
+
+    #[test]
+    fn test_split_keepsep() {
+        assert_eq!(split_keepsep("a, b; c", "[,;]"), ["a", ",", " b", ";", " c"]);
+        assert_eq!(split_keepsep(",,x", ","), [",", ",", "x"]);
+        assert_eq!(split_keepsep("x", "("), ["x"]);
+    }
+
     #[test]
     fn test_remove_prefix_to_last() {
         // This should mimic Perl’s s/.*99!// removal.
@@ -385,4 +463,14 @@ mod tests {
         assert_eq!(result, s);
     }
 
+    #[test]
+    fn test_ci_offsets_of_multibyte_text() {
+        // "ẞ" is three bytes, its lowercase "ß" two: offsets in a lowercased
+        // copy are not those of the text.
+        assert_eq!(replace_all_case_insensitive("ẞX ẞx œ", "x", "y"), "ẞy ẞy œ");
+        assert_eq!(ci_find("ẞẞ Vespera", "VESPERA"), Some(7));
+        assert_eq!(ci_find("Příští neděle", "NEDĚLE"), Some(11));
+        assert_eq!(ci_find("Cælum", "cœlum"), None);
+    }
+
 }
\ No newline at end of file
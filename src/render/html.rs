@@ -0,0 +1,396 @@
+//! html.rs
+//!
+//! The HTML backend, for the web page, in two markups:
+//!
+//! - `Markup::Legacy`, that of the Perl site: the typefaces of the
+//!   `StyleSheet` as inline SPAN styles, lines ended by `<br/>`, the two
+//!   columns in a TABLE;
+//! - `Markup::Semantic`, HTML5 for the front ends that style the page
+//!   themselves: classed tags without inline styles, whose classes are the
+//!   contract of `CSS`.
+//!
+//! ```ignore
+//! let text = build_hour(&mut ctx, "01-25-2025", Hora::Laudes, "Latin", "Divino Afflatu")?;
+//! let html = Html::semantic().hour(&text);
+//! ```
+//!
+//! The classes of the semantic markup, which do not change between
+//! releases:
+//!
+//! | element                      | markup                                      |
+//! |------------------------------|---------------------------------------------|
+//! | the hour, the office         | `<h1 class="hour">`, `<h2 class="feast">`   |
+//! | a chapter (`#`)              | `<h3 class="section">`                      |
+//! | a rubric (`!`, `/:...:/`)    | `<p class="rubric">`                        |
+//! | "Ant.", "V.", "R.", "R.br."  | `<span class="antiphon">`, `<span class="versicle">`, `<span class="response">` |
+//! | a verse of a psalm           | `<p class="verse">`, `<span class="verse-number">` |
+//! | the flex (†), the mediant (*) | `<span class="flexa">`, `<span class="mediatio">` |
+//! | a pause (`_`)                | `<div class="separator">`                   |
+//! | two columns                  | `<div class="columns">`, `<div class="row">`, `<div class="column">` |
+//!
+//! A renderer given a `LinkResolver` (`with_links()`) links the numbers of
+//! the psalms ("Psalmus 109", the verse numbers "109:1"), the references to
+//! the Scripture of the rubrics ("!Rom 12:6-16") and the commune pointers
+//! ("vide C2") to the addresses it gives for them:
+//!
+//! ```ignore
+//! let html = Html::semantic().with_links(|target: &LinkTarget| match target {
+//!     LinkTarget::Psalm(n) => Some(format!("/psalmi/{}", n)),
+//!     _ => None,
+//! });
+//! ```
+
+use std::fmt;
+use std::sync::Arc;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::latex::split_verse;
+use super::style::StyleSheet;
+use crate::scripture::{parse_references, Reference};
+
+/// The default styles of the classes of the semantic markup, the red of
+/// the books for the rubrics and marks.
+pub const CSS: &str = ".hour { font-size: 1.5em; text-align: center; }
+.feast { font-size: 1em; font-weight: normal; text-align: center; }
+.section { color: red; font-size: 1.2em; font-style: italic; }
+.rubric { color: red; font-size: 0.85em; font-style: italic; }
+.antiphon, .versicle, .response, .verse-number, .flexa, .mediatio { color: red; }
+.verse-number { font-size: 0.85em; }
+.separator { height: 0.5em; }
+.columns { display: table; width: 100%; }
+.row { display: table-row; }
+.column { display: table-cell; width: 50%; padding: 0 0.5em; vertical-align: top; }
+";
+
+/// The markup of the HTML output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Markup {
+    /// SPAN styles, `<br/>` and TABLE, as the Perl site.
+    #[default]
+    Legacy,
+    /// Classed HTML5 tags (see `CSS`).
+    Semantic,
+}
+
+/// What a link of the rendered text points to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkTarget {
+    /// A psalm or canticle, by its number in the data (the Vulgate's).
+    Psalm(u32),
+    /// The passage of the Scripture of a rubric.
+    Scripture(Reference),
+    /// A commune, as the file it names: "Commune/C2".
+    Commune(String),
+}
+
+/// Gives the address of a link, or `None` to leave the text unlinked.
+/// Closures taking a `&LinkTarget` are resolvers.
+pub trait LinkResolver {
+    fn resolve(&self, target: &LinkTarget) -> Option<String>;
+}
+
+impl<F: Fn(&LinkTarget) -> Option<String>> LinkResolver for F {
+    fn resolve(&self, target: &LinkTarget) -> Option<String> {
+        self(target)
+    }
+}
+
+/// The resolver of a renderer.
+#[derive(Clone)]
+struct Links(Arc<dyn LinkResolver + Send + Sync>);
+
+impl fmt::Debug for Links {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Links")
+    }
+}
+
+/// The HTML renderer.
+#[derive(Debug, Clone, Default)]
+pub struct Html {
+    markup: Markup,
+    styles: StyleSheet,
+    links: Option<Links>,
+}
+
+/// A line of the text of an hour, by what it is.
+enum Line<'a> {
+    Heading(&'a str),
+    Rubric(&'a str),
+    /// A line opening with a mark ("Ant.", "V.", "R.", "R.br."): the mark,
+    /// the class of the mark and the rest.
+    Marked(&'a str, &'static str, &'a str),
+    Verse(&'a str, &'a str),
+    Separator,
+    Text(&'a str),
+}
+
+impl Html {
+    /// A renderer of the legacy markup, with the default typefaces.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A renderer of the semantic markup.
+    pub fn semantic() -> Self {
+        Self::new().with_markup(Markup::Semantic)
+    }
+
+    pub fn with_markup(mut self, markup: Markup) -> Self {
+        self.markup = markup;
+        self
+    }
+
+    /// Renders the legacy markup with the typefaces of `styles` (the
+    /// semantic markup has none).
+    pub fn with_styles(mut self, styles: StyleSheet) -> Self {
+        self.styles = styles;
+        self
+    }
+
+    /// Links the psalms, the Scripture and the communes of the text to the
+    /// addresses `resolver` gives.
+    pub fn with_links(mut self, resolver: impl LinkResolver + Send + Sync + 'static) -> Self {
+        self.links = Some(Links(Arc::new(resolver)));
+        self
+    }
+
+    pub fn markup(&self) -> Markup {
+        self.markup
+    }
+
+    /// Returns the title of the page: the hour ("Laudes 01-25-2025") and
+    /// the office of the day.
+    pub fn header(&self, hour: &str, feast: &str) -> String {
+        match self.markup {
+            Markup::Legacy => format!(
+                "{}<br/>\n{}<br/>\n",
+                self.styles.largefont.span(&escape_html(hour)),
+                self.styles.smallblack.span(&escape_html(feast))
+            ),
+            Markup::Semantic => format!(
+                "<h1 class=\"hour\">{}</h1>\n<h2 class=\"feast\">{}</h2>\n",
+                escape_html(hour),
+                escape_html(feast)
+            ),
+        }
+    }
+
+    /// Returns the HTML of the text of an hour, a line of the text for a
+    /// line of the output.
+    pub fn hour(&self, text: &str) -> String {
+        text.lines().map(|line| self.line(line)).collect::<Vec<_>>().join("\n")
+    }
+
+    /// Returns the sections of two languages side by side, a row for each
+    /// pair.
+    pub fn bilingual(&self, sections: &[(String, String)]) -> String {
+        let (table, row, cell) = match self.markup {
+            Markup::Legacy => (("<table>", "</table>"), ("<tr>", "</tr>"), ("<td>", "</td>")),
+            Markup::Semantic => (
+                ("<div class=\"columns\">", "</div>"),
+                ("<div class=\"row\">", "</div>"),
+                ("<div class=\"column\">", "</div>"),
+            ),
+        };
+        let mut out = format!("{}\n", table.0);
+        for (left, right) in sections {
+            out.push_str(&format!(
+                "{}{}{}{}{}{}{}{}\n",
+                row.0,
+                cell.0,
+                self.hour(left),
+                cell.1,
+                cell.0,
+                self.hour(right),
+                cell.1,
+                row.1
+            ));
+        }
+        out.push_str(table.1);
+        out
+    }
+
+    fn line(&self, line: &str) -> String {
+        let semantic = self.markup == Markup::Semantic;
+        let styles = &self.styles;
+        match classify(line.trim_end()) {
+            Line::Heading(heading) if semantic => format!("<h3 class=\"section\">{}</h3>", self.text(heading)),
+            Line::Heading(heading) => format!("{}<br/>", styles.titlefont.span(&self.text(heading))),
+            Line::Rubric(rubric) if semantic => format!("<p class=\"rubric\">{}</p>", self.rubric(rubric)),
+            Line::Rubric(rubric) => format!("{}<br/>", styles.smallfont.span(&self.rubric(rubric))),
+            Line::Marked(mark, class, rest) if semantic => {
+                format!("<p><span class=\"{}\">{}</span> {}</p>", class, mark, self.text(rest))
+            }
+            Line::Marked(mark, _, rest) => format!("{} {}<br/>", styles.redfont.span(mark), self.text(rest)),
+            Line::Verse(number, verse) if semantic => format!(
+                "<p class=\"verse\"><span class=\"verse-number\">{}</span> {}</p>",
+                self.verse_number(number),
+                self.verse(verse)
+            ),
+            Line::Verse(number, verse) => {
+                format!("{} {}<br/>", styles.smallfont.span(&self.verse_number(number)), self.verse(verse))
+            }
+            Line::Separator if semantic => "<div class=\"separator\"></div>".to_string(),
+            Line::Separator => "<br/>".to_string(),
+            Line::Text(text) if semantic => format!("<p>{}</p>", self.text(text)),
+            Line::Text(text) => format!("{}<br/>", self.text(text)),
+        }
+    }
+
+    /// `text` escaped, its commune pointers linked.
+    fn text(&self, text: &str) -> String {
+        static COMMUNE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b(vide|ex)\s+(C\d+[a-z]*)\b").unwrap());
+        let text = escape_html(text);
+        if self.links.is_none() {
+            return text;
+        }
+        COMMUNE
+            .replace_all(&text, |caps: &regex::Captures| {
+                let target = LinkTarget::Commune(format!("Commune/{}", &caps[2]));
+                format!("{} {}", &caps[1], self.link(&target, &caps[2]))
+            })
+            .into_owned()
+    }
+
+    /// A rubric, linked to its passage of the Scripture or its psalm.
+    fn rubric(&self, rubric: &str) -> String {
+        static PSALM: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(Psalmus|Psalm|Canticum)\s+(\d+)").unwrap());
+        if self.links.is_none() {
+            return escape_html(rubric);
+        }
+        if let Some(reference) = parse_references(rubric).into_iter().next() {
+            return self.link(&LinkTarget::Scripture(reference), &escape_html(rubric));
+        }
+        if let Some(caps) = PSALM.captures(rubric) {
+            if let Ok(number) = caps[2].parse() {
+                let end = caps.get(2).map_or(0, |m| m.end());
+                let linked = self.link(&LinkTarget::Psalm(number), &escape_html(&rubric[..end]));
+                return format!("{}{}", linked, self.text(&rubric[end..]));
+            }
+        }
+        self.text(rubric)
+    }
+
+    /// The number of a verse ("109:1"), linked to its psalm.
+    fn verse_number(&self, number: &str) -> String {
+        match number.split(':').next().and_then(|n| n.parse().ok()) {
+            Some(psalm) => self.link(&LinkTarget::Psalm(psalm), number),
+            None => number.to_string(),
+        }
+    }
+
+    /// `html` as a link to `target`, or as it is where the resolver gives
+    /// no address for it.
+    fn link(&self, target: &LinkTarget, html: &str) -> String {
+        match self.links.as_ref().and_then(|links| links.0.resolve(target)) {
+            Some(href) => format!("<a href=\"{}\">{}</a>", escape_html(&href), html),
+            None => html.to_string(),
+        }
+    }
+
+    /// A verse of a psalm, its † and * marked.
+    fn verse(&self, verse: &str) -> String {
+        let (flexa, mediatio) = match self.markup {
+            Markup::Legacy => (self.styles.redfont.span("†"), self.styles.redfont.span("*")),
+            Markup::Semantic => (
+                "<span class=\"flexa\">†</span>".to_string(),
+                "<span class=\"mediatio\">*</span>".to_string(),
+            ),
+        };
+        escape_html(verse).replace('†', &flexa).replace('*', &mediatio)
+    }
+}
+
+/// What a line of the text of an hour is.
+fn classify(line: &str) -> Line<'_> {
+    if let Some(heading) = line.strip_prefix('#') {
+        return Line::Heading(heading.trim());
+    }
+    if line.trim().is_empty() || line == "_" {
+        return Line::Separator;
+    }
+    if let Some(rubric) = line.strip_prefix('!') {
+        return Line::Rubric(rubric.trim());
+    }
+    if line.starts_with("/:") && line.ends_with(":/") {
+        return Line::Rubric(line.trim_matches(['/', ':']).trim());
+    }
+    for (mark, class) in [("Ant.", "antiphon"), ("V.", "versicle"), ("R.br.", "response"), ("R.", "response")] {
+        if let Some(rest) = line.strip_prefix(mark) {
+            return Line::Marked(mark, class, rest.trim());
+        }
+    }
+    match split_verse(line) {
+        Some((number, verse)) => Line::Verse(number, verse),
+        None => Line::Text(line),
+    }
+}
+
+/// Escapes the characters HTML gives a meaning to.
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "#Psalmi\nAnt. Dixit Dóminus.\n!Psalmus 109\n\
+                        109:1 Dixit Dóminus Dómino meo: * Sede a dextris meis.\nV. Dirigátur <oratio>.\nR. Sicut incénsum.\n_";
+
+    #[test]
+    fn test_semantic_hour() {
+        assert_eq!(
+            Html::semantic().hour(TEXT),
+            "<h3 class=\"section\">Psalmi</h3>\n<p><span class=\"antiphon\">Ant.</span> Dixit Dóminus.</p>\n\
+             <p class=\"rubric\">Psalmus 109</p>\n\
+             <p class=\"verse\"><span class=\"verse-number\">109:1</span> Dixit Dóminus Dómino meo: \
+             <span class=\"mediatio\">*</span> Sede a dextris meis.</p>\n\
+             <p><span class=\"versicle\">V.</span> Dirigátur &lt;oratio&gt;.</p>\n\
+             <p><span class=\"response\">R.</span> Sicut incénsum.</p>\n<div class=\"separator\"></div>"
+        );
+        let html = Html::semantic();
+        assert_eq!(html.header("Laudes 01-25-2025", "In Conversione S. Pauli"), "<h1 class=\"hour\">Laudes 01-25-2025</h1>\n<h2 class=\"feast\">In Conversione S. Pauli</h2>\n");
+        let columns = html.bilingual(&[("Orémus.".to_string(), "Let us pray.".to_string())]);
+        assert!(columns.contains("<div class=\"row\"><div class=\"column\"><p>Orémus.</p></div><div class=\"column\"><p>Let us pray.</p></div></div>"));
+        assert!(!html.hour(TEXT).contains("style") && !html.hour(TEXT).contains("SPAN"));
+    }
+
+    #[test]
+    fn test_legacy_hour() {
+        let html = Html::new().hour(TEXT);
+        assert!(html.contains("<SPAN STYLE=\"font-style:italic;color:red\">Ant.</SPAN> Dixit Dóminus.<br/>"));
+        assert!(html.contains("Dómino meo: <SPAN STYLE=\"font-style:italic;color:red\">*</SPAN> Sede"));
+        assert!(html.ends_with("Sicut incénsum.<br/>\n<br/>"));
+        assert!(!html.contains("class="));
+        assert!(Html::new().bilingual(&[]).starts_with("<table>"));
+    }
+
+    #[test]
+    fn test_links() {
+        let html = Html::semantic().with_links(|target: &LinkTarget| match target {
+            LinkTarget::Psalm(n) => Some(format!("/psalmi/{}", n)),
+            LinkTarget::Scripture(reference) => Some(format!("https://bible.example/{}", reference.osis())),
+            LinkTarget::Commune(file) if file != "Commune/C11" => Some(format!("/{}", file)),
+            LinkTarget::Commune(_) => None,
+        });
+        assert_eq!(
+            html.hour("!Psalmus 109 [1]\n109:1 Dixit Dóminus"),
+            "<p class=\"rubric\"><a href=\"/psalmi/109\">Psalmus 109</a> [1]</p>\n\
+             <p class=\"verse\"><span class=\"verse-number\"><a href=\"/psalmi/109\">109:1</a></span> Dixit Dóminus</p>"
+        );
+        assert_eq!(
+            html.hour("!Rom 12:6-8"),
+            "<p class=\"rubric\"><a href=\"https://bible.example/Rom.12.6-Rom.12.8\">Rom 12:6-8</a></p>"
+        );
+        assert_eq!(
+            html.hour("S. Agnetis;;Duplex;;3;;vide C6 et ex C11"),
+            "<p>S. Agnetis;;Duplex;;3;;vide <a href=\"/Commune/C6\">C6</a> et ex C11</p>"
+        );
+        // Without a resolver nothing is linked.
+        assert_eq!(Html::semantic().hour("!Psalmus 109"), "<p class=\"rubric\">Psalmus 109</p>");
+    }
+}
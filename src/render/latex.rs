@@ -0,0 +1,222 @@
+//! latex.rs
+//!
+//! The LaTeX backend, for printing an hour.
+//!
+//! The output uses a few macros of its own (`\DOant`, `\DOversicle`,
+//! `\DOrubric`, `\DOverse`, `\DOflexa`, `\DOmediatio`), defined by
+//! `PREAMBLE`, so that the look of the page is decided there:
+//!
+//! ```ignore
+//! let text = build_hour(&mut ctx, "01-25-2025", Hora::Laudes, "Latin", "Divino Afflatu")?;
+//! let tex = Latex::new().document(&Latex::new().hour(&text));
+//! ```
+//!
+//! The verses of the psalms ("109:1 Dixit Dóminus Dómino meo: * Sede a
+//! dextris meis.") are broken at their flex (†) and mediant (*), each part
+//! on a line of its own; a hyphenation function can be plugged in to mark
+//! where the words may be broken.
+
+/// The definitions of the macros of the output, for the preamble of the
+/// document. Bilingual output needs the `paracol` package.
+pub const PREAMBLE: &str = r"\usepackage{paracol}
+\newcommand{\DOant}[1]{\textcolor{red}{Ant.} #1\par}
+\newcommand{\DOversicle}[2]{\textcolor{red}{#1.} #2\par}
+\newcommand{\DOrubric}[1]{{\small\itshape\textcolor{red}{#1}}\par}
+\newcommand{\DOverse}[2]{\noindent\hangindent=2em{\small\textcolor{red}{#1}} #2\par}
+\newcommand{\DOflexa}{\ \textcolor{red}{\dag}}
+\newcommand{\DOmediatio}{\ \textcolor{red}{*}}
+";
+
+/// A function marking the syllables of a word with "-" ("Dó-mi-nus").
+pub type Hyphenator = fn(&str) -> String;
+
+/// The LaTeX renderer.
+#[derive(Debug, Clone, Copy)]
+pub struct Latex {
+    break_verses: bool,
+    hyphenate: Option<Hyphenator>,
+}
+
+impl Default for Latex {
+    fn default() -> Self {
+        Self { break_verses: true, hyphenate: None }
+    }
+}
+
+impl Latex {
+    /// A renderer breaking the verses of the psalms, without hyphenation.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the verses of the psalms are broken at their † and *.
+    pub fn with_verse_breaks(mut self, break_verses: bool) -> Self {
+        self.break_verses = break_verses;
+        self
+    }
+
+    /// Marks the breaking points `hyphenate` gives in the words of the text
+    /// as discretionary hyphens (`\-`), so that TeX breaks there only.
+    pub fn with_hyphenation(mut self, hyphenate: Hyphenator) -> Self {
+        self.hyphenate = Some(hyphenate);
+        self
+    }
+
+    /// Returns the body of the document for the text of an hour: its `#`
+    /// chapters as unnumbered sections.
+    pub fn hour(&self, text: &str) -> String {
+        text.lines().map(|line| self.line(line)).collect()
+    }
+
+    /// Returns the body of the document for the sections of two languages,
+    /// side by side in the two columns of `paracol`.
+    pub fn bilingual(&self, sections: &[(String, String)]) -> String {
+        let mut out = String::from("\\begin{paracol}{2}\n");
+        for (left, right) in sections {
+            out.push_str(&self.hour(left));
+            out.push_str("\\switchcolumn\n");
+            out.push_str(&self.hour(right));
+            out.push_str("\\switchcolumn*\n");
+        }
+        out.push_str("\\end{paracol}\n");
+        out
+    }
+
+    /// Wraps `body` into a complete document.
+    pub fn document(&self, body: &str) -> String {
+        format!(
+            "\\documentclass{{article}}\n\\usepackage{{fontspec}}\n\\usepackage{{xcolor}}\n{}\\begin{{document}}\n{}\\end{{document}}\n",
+            PREAMBLE, body
+        )
+    }
+
+    fn line(&self, line: &str) -> String {
+        let line = line.trim_end();
+        if let Some(heading) = line.strip_prefix('#') {
+            return format!("\\section*{{{}}}\n", self.text(heading.trim()));
+        }
+        if line.trim().is_empty() || line == "_" {
+            return "\\medskip\n".to_string();
+        }
+        if let Some(rubric) = line.strip_prefix('!') {
+            return format!("\\DOrubric{{{}}}\n", self.text(rubric.trim()));
+        }
+        if line.starts_with("/:") && line.ends_with(":/") {
+            return format!("\\DOrubric{{{}}}\n", self.text(line.trim_matches(['/', ':']).trim()));
+        }
+        if let Some(ant) = line.strip_prefix("Ant.") {
+            return format!("\\DOant{{{}}}\n", self.text(ant.trim()));
+        }
+        for mark in ["V.", "R.br.", "R."] {
+            if let Some(rest) = line.strip_prefix(mark) {
+                return format!("\\DOversicle{{{}}}{{{}}}\n", mark.trim_end_matches('.'), self.text(rest.trim()));
+            }
+        }
+        if let Some((number, verse)) = split_verse(line) {
+            return format!("\\DOverse{{{}}}{{{}}}\n", number, self.verse(verse));
+        }
+        format!("{}\\par\n", self.text(line))
+    }
+
+    /// A verse of a psalm, broken after its † and * if the renderer does.
+    fn verse(&self, verse: &str) -> String {
+        let sep = if self.break_verses { "\\\\\n" } else { " " };
+        let mut out = String::new();
+        let mut part = String::new();
+        for c in verse.chars() {
+            match c {
+                '†' | '*' => {
+                    out.push_str(&self.text(part.trim()));
+                    out.push_str(if c == '†' { "\\DOflexa" } else { "\\DOmediatio" });
+                    out.push_str(sep);
+                    part.clear();
+                }
+                c => part.push(c),
+            }
+        }
+        out.push_str(&self.text(part.trim()));
+        out
+    }
+
+    /// `text` escaped for TeX, with the breaking points of its words.
+    fn text(&self, text: &str) -> String {
+        let Some(hyphenate) = self.hyphenate else { return escape_tex(text) };
+        text.split(' ')
+            .map(|word| {
+                // The punctuation after a word is not given to the hyphenator.
+                let core = word.trim_end_matches(|c: char| !c.is_alphabetic());
+                if core.is_empty() || !core.chars().all(char::is_alphabetic) {
+                    return escape_tex(word);
+                }
+                let marked = hyphenate(core);
+                let parts: Vec<String> = marked.split('-').map(escape_tex).collect();
+                format!("{}{}", parts.join("\\-"), escape_tex(&word[core.len()..]))
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Splits a verse of a psalm into its number and text ("109:1", "Dixit").
+pub(super) fn split_verse(line: &str) -> Option<(&str, &str)> {
+    let (number, verse) = line.split_once(' ')?;
+    let (chapter, v) = number.split_once(':')?;
+    let numeric = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit() || c.is_ascii_lowercase());
+    (numeric(chapter) && chapter.starts_with(|c: char| c.is_ascii_digit()) && numeric(v)).then_some((number, verse))
+}
+
+/// Escapes the characters TeX gives a meaning to.
+pub fn escape_tex(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\textbackslash{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '~' => out.push_str("\\textasciitilde{}"),
+            '^' => out.push_str("\\textasciicircum{}"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latex_hour() {
+        let text = "#Psalmi\nAnt. Dixit Dóminus.\n!Psalmus 109\n109:1 Dixit Dóminus Dómino meo: * Sede a dextris meis.\n\
+                    110:1 Confitébor tibi, Dómine, † in toto corde meo: * in consílio justórum.\nV. Dirigátur & crescat 50%.\n_";
+        let tex = Latex::new().hour(text);
+        assert_eq!(
+            tex,
+            "\\section*{Psalmi}\n\\DOant{Dixit Dóminus.}\n\\DOrubric{Psalmus 109}\n\
+             \\DOverse{109:1}{Dixit Dóminus Dómino meo:\\DOmediatio\\\\\nSede a dextris meis.}\n\
+             \\DOverse{110:1}{Confitébor tibi, Dómine,\\DOflexa\\\\\nin toto corde meo:\\DOmediatio\\\\\nin consílio justórum.}\n\
+             \\DOversicle{V}{Dirigátur \\& crescat 50\\%.}\n\\medskip\n"
+        );
+        let tex = Latex::new().with_verse_breaks(false).hour("109:1 Dixit * Sede");
+        assert_eq!(tex, "\\DOverse{109:1}{Dixit\\DOmediatio Sede}\n");
+        assert_eq!(escape_tex("a_b {c} ~ \\"), "a\\_b \\{c\\} \\textasciitilde{} \\textbackslash{}");
+    }
+
+    #[test]
+    fn test_latex_bilingual_and_hyphenation() {
+        fn syllables(word: &str) -> String {
+            if word == "Dóminus" { "Dó-mi-nus".to_string() } else { word.to_string() }
+        }
+        let latex = Latex::new().with_hyphenation(syllables);
+        assert_eq!(latex.hour("Dóminus, sancti-fied"), "Dó\\-mi\\-nus, sancti-fied\\par\n");
+        assert_eq!(latex.hour("Dixit Dóminus:"), "Dixit Dó\\-mi\\-nus:\\par\n");
+
+        let sections = vec![("#Oratio\nOrémus.".to_string(), "#Prayer\nLet us pray.".to_string())];
+        let tex = Latex::new().bilingual(&sections);
+        assert!(tex.starts_with("\\begin{paracol}{2}\n\\section*{Oratio}\nOrémus.\\par\n\\switchcolumn\n"));
+        assert!(tex.ends_with("\\switchcolumn*\n\\end{paracol}\n"));
+        assert!(Latex::new().document(&tex).contains("\\newcommand{\\DOant}"));
+    }
+}
@@ -0,0 +1,11 @@
+//! Renderers of the text of an hour.
+//!
+//! They take the text as `build_hour()` returns it (and the pairs of
+//! sections of `build_hour_bilingual()` for two columns).
+//!
+//! `html` renders the web page, in the markup of the Perl site or in
+//! semantic HTML5; `style` has the typefaces of the former and their themes.
+
+pub mod html;
+pub mod latex;
+pub mod style;
@@ -0,0 +1,264 @@
+//! style.rs
+//!
+//! The typefaces of the web page. The Perl code describes a typeface with a
+//! free-form string, "[size] [italic] [bold] color" ("+1 bold italic red"),
+//! kept as such in the setup (`Preferences`); here it is parsed once into a
+//! `Style`, which renders as an inline CSS style, the same for the same
+//! description.
+//!
+//! A `StyleSheet` names the typefaces of a page (`largefont`, `smallblack`,
+//! `redfont`, `initiale`, ...). It is built from the preferences of the user
+//! or is one of the themes (`dark()`, `print()`), and is handed to the
+//! builder of the hours (`Kalendar::with_styles`), so that a theme is
+//! swapped without touching the code that uses the typefaces.
+//!
+//! ```ignore
+//! let kalendar = Kalendar::new("Divino Afflatu").with_styles(StyleSheet::dark());
+//! let text = kalendar.build_hour(&mut ctx, "01-25-2025", Hora::Laudes, "Latin")?;
+//! ```
+
+use std::fmt;
+
+use crate::setup::Preferences;
+
+/// A typeface: size, slant, weight and color.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Style {
+    /// An HTML font size, absolute ("1" to "7") or relative ("+1", "-1"),
+    /// or a CSS length ("1.2em").
+    pub size: Option<String>,
+    pub italic: bool,
+    pub bold: bool,
+    pub color: Option<String>,
+}
+
+impl Style {
+    /// Parses a "[size] [italic] [bold] color" description. The words may
+    /// come in any order; a second size is ignored, and of two colors the
+    /// last one is taken.
+    pub fn parse(desc: &str) -> Self {
+        let mut style = Style::default();
+        for word in desc.split_whitespace() {
+            if word.eq_ignore_ascii_case("italic") {
+                style.italic = true;
+            } else if word.eq_ignore_ascii_case("bold") {
+                style.bold = true;
+            } else if word.starts_with(|c: char| c.is_ascii_digit() || c == '+' || c == '-' || c == '.') {
+                style.size.get_or_insert_with(|| word.to_string());
+            } else {
+                style.color = Some(word.to_string());
+            }
+        }
+        style
+    }
+
+    /// The style with its color replaced by `color`.
+    pub fn with_color(mut self, color: &str) -> Self {
+        self.color = Some(color.to_string());
+        self
+    }
+
+    /// The inline CSS of the style, e.g. "font-size:1.2em;font-style:italic;
+    /// color:red", or "" for the plain text.
+    pub fn css(&self) -> String {
+        let mut css = Vec::new();
+        if let Some(size) = self.size.as_deref().and_then(css_size) {
+            css.push(format!("font-size:{}", size));
+        }
+        if self.italic {
+            css.push("font-style:italic".to_string());
+        }
+        if self.bold {
+            css.push("font-weight:bold".to_string());
+        }
+        if let Some(color) = &self.color {
+            css.push(format!("color:{}", color));
+        }
+        css.join(";")
+    }
+
+    /// Wraps `text` in a SPAN of the style.
+    pub fn span(&self, text: &str) -> String {
+        let css = self.css();
+        if css.is_empty() {
+            text.to_string()
+        } else {
+            format!("<SPAN STYLE=\"{}\">{}</SPAN>", css, text)
+        }
+    }
+}
+
+/// The CSS `font-size` of an HTML font size: the sizes 1 to 7 are the CSS
+/// keywords, the relative sizes steps of 1.2 from the size of the text.
+/// CSS lengths are kept; 0 and the relative size 0 are the size of the text.
+fn css_size(size: &str) -> Option<String> {
+    if let Some(step) = size.strip_prefix(['+', '-']).and_then(|n| n.parse::<i32>().ok()) {
+        let step = if size.starts_with('-') { -step } else { step };
+        if step == 0 {
+            return None;
+        }
+        let em = (1.2f64.powi(step) * 100.0).round() / 100.0;
+        return Some(format!("{}em", em));
+    }
+    let keyword = match size {
+        "0" => return None,
+        "1" => "x-small",
+        "2" => "small",
+        "3" => "medium",
+        "4" => "large",
+        "5" => "x-large",
+        "6" => "xx-large",
+        "7" => "xxx-large",
+        _ => return Some(size.to_string()),
+    };
+    Some(keyword.to_string())
+}
+
+impl fmt::Display for Style {
+    /// The description of the style, as `parse()` reads it.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let words: Vec<&str> = [
+            self.size.as_deref(),
+            self.italic.then_some("italic"),
+            self.bold.then_some("bold"),
+            self.color.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        f.write_str(&words.join(" "))
+    }
+}
+
+impl From<&str> for Style {
+    fn from(desc: &str) -> Self {
+        Style::parse(desc)
+    }
+}
+
+/// The named typefaces of a page, those of the setup of the web site.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StyleSheet {
+    /// The text.
+    pub blackfont: Style,
+    /// The small text of the headings ("Vespera ~ Feria").
+    pub smallblack: Style,
+    /// The rubrics.
+    pub redfont: Style,
+    /// The initial letter of a lesson or a chapter.
+    pub initiale: Style,
+    /// The title of the hour.
+    pub largefont: Style,
+    /// The small rubrics and marks.
+    pub smallfont: Style,
+    /// The titles of the sections.
+    pub titlefont: Style,
+}
+
+impl StyleSheet {
+    /// The typefaces of the preferences of the user.
+    pub fn from_preferences(preferences: &Preferences) -> Self {
+        StyleSheet {
+            blackfont: Style::parse(&preferences.blackfont),
+            smallblack: Style::parse(&preferences.smallblack),
+            redfont: Style::parse(&preferences.redfont),
+            initiale: Style::parse(&preferences.initiale),
+            largefont: Style::parse(&preferences.largefont),
+            smallfont: Style::parse(&preferences.smallfont),
+            titlefont: Style::parse(&preferences.titlefont),
+        }
+    }
+
+    /// The default typefaces for a dark background: light text and a
+    /// lighter red.
+    pub fn dark() -> Self {
+        Self::default().recolored(|color| match color {
+            None | Some("black") => Some("#e0e0e0"),
+            Some("red") => Some("#ff7b7b"),
+            Some("maroon") => Some("#d08080"),
+            _ => None,
+        })
+    }
+
+    /// The default typefaces for printing in black and white.
+    pub fn print() -> Self {
+        Self::default().recolored(|_| Some("black"))
+    }
+
+    /// The typeface named `name` ("largefont", "redfont", ...).
+    pub fn get(&self, name: &str) -> Option<&Style> {
+        Some(match name {
+            "blackfont" => &self.blackfont,
+            "smallblack" => &self.smallblack,
+            "redfont" => &self.redfont,
+            "initiale" => &self.initiale,
+            "largefont" => &self.largefont,
+            "smallfont" => &self.smallfont,
+            "titlefont" => &self.titlefont,
+            _ => return None,
+        })
+    }
+
+    /// Replaces the color of every typeface by the one `map` gives for it,
+    /// where it gives one.
+    fn recolored(mut self, map: impl Fn(Option<&str>) -> Option<&'static str>) -> Self {
+        for style in [
+            &mut self.blackfont,
+            &mut self.smallblack,
+            &mut self.redfont,
+            &mut self.initiale,
+            &mut self.largefont,
+            &mut self.smallfont,
+            &mut self.titlefont,
+        ] {
+            if let Some(color) = map(style.color.as_deref()) {
+                style.color = Some(color.to_string());
+            }
+        }
+        self
+    }
+}
+
+impl Default for StyleSheet {
+    /// The typefaces of the default preferences (`horas.setup`).
+    fn default() -> Self {
+        Self::from_preferences(&Preferences::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_style_parse_and_css() {
+        let style = Style::parse("+1 bold italic red");
+        assert_eq!(style.size.as_deref(), Some("+1"));
+        assert!(style.bold && style.italic);
+        assert_eq!(style.css(), "font-size:1.2em;font-style:italic;font-weight:bold;color:red");
+        assert_eq!(style.to_string(), "+1 italic bold red");
+        assert_eq!(Style::parse(&style.to_string()), style);
+
+        assert_eq!(Style::parse("-1").css(), "font-size:0.83em");
+        assert_eq!(Style::parse("1 maroon").css(), "font-size:x-small;color:maroon");
+        assert_eq!(Style::parse("bold 1.2em maroon").css(), "font-size:1.2em;font-weight:bold;color:maroon");
+        assert_eq!(Style::parse("").span("Ant."), "Ant.");
+        assert_eq!(Style::parse(" italic red").span("Ant."), "<SPAN STYLE=\"font-style:italic;color:red\">Ant.</SPAN>");
+    }
+
+    #[test]
+    fn test_style_sheet() {
+        let sheet = StyleSheet::default();
+        assert_eq!(sheet.largefont, Style::parse("+1 bold italic red"));
+        assert_eq!(sheet.get("smallblack"), Some(&Style::parse("-1")));
+        assert_eq!(sheet.get("nofont"), None);
+
+        let dark = StyleSheet::dark();
+        assert_eq!(dark.redfont.color.as_deref(), Some("#ff7b7b"));
+        assert_eq!(dark.smallblack.color.as_deref(), Some("#e0e0e0"));
+        assert!(dark.largefont.bold, "a theme keeps the sizes and faces");
+        assert!(StyleSheet::print().initiale.span("A").contains("color:black"));
+    }
+}
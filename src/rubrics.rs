@@ -0,0 +1,409 @@
+//! rubrics.rs
+//!
+//! Structured view of a rubrics version string.
+//!
+//! Divinum Officium identifies the rubrics in force by their display name
+//! ("Tridentine - 1570", "Divino Afflatu - 1954", "Reduced - 1955",
+//! "Rubrics 1960 - 1960", "Monastic Tridentinum 1617", "Ordo Praedicatorum -
+//! 1962", ...). The Perl code then asks questions of that string with regexes
+//! like `$version =~ /196/` or `$version =~ /Trident/`. `RubricsVersion`
+//! answers the same questions once, from parsed fields, so that callers can ask
+//! `version.is_1960()` instead of repeating the pattern.
+//!
+//! The original name is kept, since the data tables (`Tabulae/data.txt`, the
+//! setupstring cache) are keyed by it.
+//!
+//! The Cistercian versions are a use of the monastic office
+//! (`CistercianUse`); where their psalter, hymns and Compline differ from the
+//! other monastic books the version says so (`psalter_table()`,
+//! `winter_hymn_at_vespers()`, `salve_regina_at_compline()`, ...).
+
+use std::fmt;
+
+use crate::horas::Hora;
+
+/// The general reform a version belongs to, in historical order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum RubricsFamily {
+    /// The Tridentine books (1570, 1617, 1888, 1906, 1910 ...) — `/Trident/`.
+    Tridentine,
+    /// The reform of Pius X, *Divino Afflatu* (1911) — `/Divino/`.
+    DivinoAfflatu,
+    /// The simplification of 1955, *Cum nostra hac aetate* — `/1955/`.
+    Reduced1955,
+    /// The Code of Rubrics of 1960 and its derivatives — `/196/`.
+    Rubrics1960,
+}
+
+/// The Cistercian uses of the monastic office (`/cist/i`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CistercianUse {
+    /// The Breviary of the Order, "Monastic Tridentinum Cisterciensis 1951".
+    Cisterciensis,
+    /// The use of the abbey of Vyšší Brod, "... Cisterciensis Altovadensis".
+    Altovadensis,
+}
+
+/// A parsed rubrics version.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RubricsVersion {
+    /// The version string as passed in, e.g. "Rubrics 1960 - 1960".
+    pub name: String,
+    pub family: RubricsFamily,
+    /// The first four-digit year in the name, if any.
+    pub year: Option<u16>,
+    /// Monastic cursus (`/Monastic/`), including the Cistercian uses.
+    pub monastic: bool,
+    /// Dominican rite (`/Ordo Praedicatorum/`).
+    pub dominican: bool,
+    /// The Cistercian use, for the Cistercian versions of the monastic office.
+    pub cistercian: Option<CistercianUse>,
+}
+
+impl RubricsVersion {
+    /// Parses a version name. This never fails: a name that matches none of
+    /// the families is treated as Divino Afflatu, which is how the string
+    /// checks treated it too (not Tridentine, not one of the reforms).
+    pub fn parse(name: &str) -> Self {
+        let family = if name.contains("196") {
+            RubricsFamily::Rubrics1960
+        } else if name.contains("1955") {
+            RubricsFamily::Reduced1955
+        } else if name.contains("Trident") {
+            RubricsFamily::Tridentine
+        } else {
+            RubricsFamily::DivinoAfflatu
+        };
+        RubricsVersion {
+            name: name.to_string(),
+            family,
+            year: first_year(name),
+            monastic: name.contains("Monastic"),
+            dominican: name.contains("Ordo Praedicatorum"),
+            cistercian: cistercian_use(name),
+        }
+    }
+
+    pub fn is_tridentine(&self) -> bool {
+        self.family == RubricsFamily::Tridentine
+    }
+
+    pub fn is_divino(&self) -> bool {
+        self.family == RubricsFamily::DivinoAfflatu
+    }
+
+    /// The 1960 rubrics; this includes "Monastic - 1963" and the Dominican 1962.
+    pub fn is_1960(&self) -> bool {
+        self.family == RubricsFamily::Rubrics1960
+    }
+
+    /// Cistercian use (`/cist/i`), a variant of the monastic office.
+    pub fn is_cistercian(&self) -> bool {
+        self.cistercian.is_some()
+    }
+
+    /// The prefix of the weekly psalter tables of "Psalmi major.txt": "Dayc"
+    /// for the Cistercian distribution of the psalms, "Daym" for the other
+    /// monastic uses and "Day" for the Roman psalter.
+    pub fn psalter_table(&self) -> &'static str {
+        if self.is_cistercian() {
+            "Dayc"
+        } else if self.monastic {
+            "Daym"
+        } else {
+            "Day"
+        }
+    }
+
+    /// The lowest rank of a feast of the Sanctoral that takes the festive
+    /// psalms at Lauds (the Sunday psalms, or the "F" table of the monastic
+    /// psalter): 3 for the Cistercians, 4 for the other monastic uses and 5
+    /// in the Roman office.
+    pub fn festive_lauds_rank(&self) -> f64 {
+        if self.is_cistercian() {
+            3.0
+        } else if self.monastic {
+            4.0
+        } else {
+            5.0
+        }
+    }
+
+    /// Whether the Sundays of Paschaltide have their own psalms at Lauds
+    /// ("DaymP"), as in the monastic psalter but not the Cistercian one.
+    pub fn has_paschal_lauds_table(&self) -> bool {
+        self.monastic && !self.is_cistercian()
+    }
+
+    /// The index, among the five antiphons of Lauds, of the seasonal antiphon
+    /// said at the minor hour `hora`. The Cistercians say the second at Prime,
+    /// the third at Terce and the fourth at Sext; the others the first, the
+    /// second and the third. Both say the fifth at None.
+    pub fn minor_hour_antiphon(&self, hora: Hora) -> Option<usize> {
+        let shift = usize::from(self.is_cistercian());
+        match hora {
+            Hora::Prima => Some(shift),
+            Hora::Tertia => Some(1 + shift),
+            Hora::Sexta => Some(2 + shift),
+            Hora::Nona => Some(4),
+            _ => None,
+        }
+    }
+
+    /// Whether Compline always ends with the Salve Regina, whatever the
+    /// season: so the Dominican and the Cistercian books.
+    pub fn salve_regina_at_compline(&self) -> bool {
+        self.dominican || self.is_cistercian()
+    }
+
+    /// Whether the winter hymns of Sunday ("hiemalis") are also sung at
+    /// Vespers, as in the Cistercian use, and not only at Lauds.
+    pub fn winter_hymn_at_vespers(&self) -> bool {
+        self.is_cistercian()
+    }
+
+    /// Whether the Sundays of October already take the winter hymns; in the
+    /// Cistercian use they begin in November.
+    pub fn winter_hymns_in_october(&self) -> bool {
+        !self.is_cistercian()
+    }
+
+    /// Whether the feasts of the commons of Confessors whose rule has "Hac
+    /// die" sing the proper hymn "Hymnus Vespera Hac die" at Vespers.
+    pub fn has_hac_die_hymn(&self) -> bool {
+        self.is_cistercian()
+    }
+
+    /// The post-1960 calendar changes ("2020 USA", "NewCal"),
+    /// the `innovata` predicate of the data files.
+    pub fn is_innovated(&self) -> bool {
+        self.name.contains("2020 USA") || self.name.contains("NewCal")
+    }
+
+    /// Whether the simplifications of 1955 apply: suppression of most octaves
+    /// and vigils, semidoubles said as simples, no suffrages or Marian
+    /// antiphon after every hour. They carried over into the 1960 rubrics.
+    pub fn uses_1955_simplifications(&self) -> bool {
+        self.family >= RubricsFamily::Reduced1955
+    }
+
+    /// Whether an oratio imperata is added to the orations of a day of
+    /// `rank`: not on the doubles of the first class (rank 6 and above),
+    /// nor, since 1955, on the days of the second class.
+    pub fn admits_oratio_imperata(&self, rank: f64) -> bool {
+        rank < if self.uses_1955_simplifications() { 5.0 } else { 6.0 }
+    }
+
+    /// Whether Lauds of an office of `rank` admit the commemoration of an
+    /// office that is not privileged (a simple, an impeded feast, a common
+    /// feria). Before 1955 every office does but the greatest feasts (rank
+    /// 7: Christmas, Easter, Pentecost ...); since 1955 the days of the first
+    /// class (rank 6 and above) admit only the privileged commemorations. The
+    /// monastic books follow their reform: the Tridentine ones the older
+    /// rule, those of 1963 the rubrics of 1960.
+    pub fn has_commemorations_at_lauds(&self, rank: f64) -> bool {
+        rank < if self.uses_1955_simplifications() { 6.0 } else { 7.0 }
+    }
+
+    /// The most orations said under one conclusion, those of the
+    /// commemorations and the imperata included: seven before 1955, three
+    /// since.
+    pub fn max_orationes(&self) -> usize {
+        if self.uses_1955_simplifications() {
+            3
+        } else {
+            7
+        }
+    }
+}
+
+/// The parts of the office that end with the Gloria Patri.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GloriaPart {
+    /// The psalms and canticles.
+    Psalm,
+    /// The invitatory psalm (Venite) of Matins.
+    Invitatorium,
+    /// The last responsory of a nocturn of Matins.
+    Responsory,
+    /// The short responsories of the little hours and of Compline.
+    ResponsoriumBreve,
+}
+
+/// The day and the office in which a Gloria Patri would be said.
+#[derive(Debug, Clone, Copy)]
+pub struct GloriaContext<'a> {
+    /// The name of the day in the Proper of Time, e.g. "Quad5-0".
+    pub dayname: &'a str,
+    /// The votive office said instead of the office of the day, if any.
+    pub votive: &'a str,
+    pub part: GloriaPart,
+}
+
+/// Whether the Gloria Patri is said at the end of `ctx.part`:
+///
+/// - never in the Office of the Dead (where the "Requiem aeternam" takes its
+///   place) nor in the last three days of Holy Week;
+/// - in Passiontide (from Passion Sunday) not at the invitatory psalm nor at
+///   the responsories, whose response is repeated instead; the psalms keep
+///   it.
+pub fn gloria_patri_allowed(ctx: &GloriaContext) -> bool {
+    let votive = ctx.votive.to_lowercase();
+    if votive.contains("c9") || votive.contains("defunct") {
+        return false;
+    }
+    if ["Quad6-4", "Quad6-5", "Quad6-6"].iter().any(|d| ctx.dayname.starts_with(d)) {
+        return false;
+    }
+    let passiontide = ctx.dayname.starts_with("Quad5") || ctx.dayname.starts_with("Quad6");
+    !passiontide || ctx.part == GloriaPart::Psalm
+}
+
+/// The Cistercian use named by `name`, if any.
+fn cistercian_use(name: &str) -> Option<CistercianUse> {
+    let name = name.to_lowercase();
+    if name.contains("altovadensis") {
+        Some(CistercianUse::Altovadensis)
+    } else if name.contains("cist") {
+        Some(CistercianUse::Cisterciensis)
+    } else {
+        None
+    }
+}
+
+/// Returns the first run of exactly four ASCII digits in `s`.
+fn first_year(s: &str) -> Option<u16> {
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i].is_ascii_digit() {
+            let start = i;
+            while i < bytes.len() && bytes[i].is_ascii_digit() {
+                i += 1;
+            }
+            if i - start == 4 {
+                return s[start..i].parse().ok();
+            }
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
+impl From<&str> for RubricsVersion {
+    fn from(name: &str) -> Self {
+        RubricsVersion::parse(name)
+    }
+}
+
+impl fmt::Display for RubricsVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_families() {
+        assert_eq!(RubricsVersion::parse("Tridentine - 1570").family, RubricsFamily::Tridentine);
+        assert_eq!(RubricsVersion::parse("Divino Afflatu - 1954").family, RubricsFamily::DivinoAfflatu);
+        assert_eq!(RubricsVersion::parse("Reduced - 1955").family, RubricsFamily::Reduced1955);
+        assert_eq!(RubricsVersion::parse("Rubrics 1960 - 1960").family, RubricsFamily::Rubrics1960);
+        // The 1963 monastic books follow the 1960 rubrics, as `/196/` does.
+        assert_eq!(RubricsVersion::parse("Monastic - 1963").family, RubricsFamily::Rubrics1960);
+    }
+
+    #[test]
+    fn test_parse_flags_and_year() {
+        let v = RubricsVersion::parse("Monastic Tridentinum Cisterciensis 1951");
+        assert!(v.monastic && v.is_cistercian() && v.is_tridentine());
+        assert_eq!(v.year, Some(1951));
+
+        let op = RubricsVersion::parse("Ordo Praedicatorum - 1962");
+        assert!(op.dominican && !op.monastic && op.is_1960());
+
+        let usa = RubricsVersion::parse("Rubrics 1960 - 2020 USA");
+        assert_eq!(usa.year, Some(1960));
+        assert!(usa.is_innovated());
+
+        assert_eq!(RubricsVersion::parse("Monastic Tridentinum Cisterciensis Altovadensis").year, None);
+    }
+
+    #[test]
+    fn test_capabilities() {
+        assert!(!RubricsVersion::parse("Divino Afflatu - 1954").uses_1955_simplifications());
+        assert!(RubricsVersion::parse("Reduced - 1955").uses_1955_simplifications());
+        assert!(RubricsVersion::parse("Rubrics 1960 - 1960").uses_1955_simplifications());
+        let da = RubricsVersion::parse("Divino Afflatu - 1954");
+        assert!(da.admits_oratio_imperata(5.0) && !da.admits_oratio_imperata(6.5));
+        assert_eq!(da.max_orationes(), 7);
+        let r60 = RubricsVersion::parse("Rubrics 1960 - 1960");
+        assert!(r60.admits_oratio_imperata(4.0) && !r60.admits_oratio_imperata(5.0));
+        assert_eq!(r60.max_orationes(), 3);
+    }
+
+    #[test]
+    fn test_commemorations_at_lauds() {
+        for name in ["Tridentine - 1570", "Divino Afflatu - 1954", "Monastic Tridentinum 1617"] {
+            let version = RubricsVersion::parse(name);
+            assert!(version.has_commemorations_at_lauds(6.5), "{}", name);
+            assert!(!version.has_commemorations_at_lauds(7.0), "{}", name);
+        }
+        for name in ["Reduced - 1955", "Rubrics 1960 - 1960", "Monastic - 1963"] {
+            let version = RubricsVersion::parse(name);
+            assert!(version.has_commemorations_at_lauds(5.0), "{}", name);
+            assert!(!version.has_commemorations_at_lauds(6.0), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_cistercian_use() {
+        let cist = RubricsVersion::parse("Monastic Tridentinum Cisterciensis 1951");
+        assert_eq!(cist.cistercian, Some(CistercianUse::Cisterciensis));
+        let alt = RubricsVersion::parse("Monastic Tridentinum Cisterciensis Altovadensis");
+        assert_eq!(alt.cistercian, Some(CistercianUse::Altovadensis));
+        let monastic = RubricsVersion::parse("Monastic Tridentinum 1617");
+        assert_eq!(monastic.cistercian, None);
+
+        assert_eq!(cist.psalter_table(), "Dayc");
+        assert_eq!(monastic.psalter_table(), "Daym");
+        assert_eq!(RubricsVersion::parse("Divino Afflatu - 1954").psalter_table(), "Day");
+        assert_eq!(cist.festive_lauds_rank(), 3.0);
+        assert!(monastic.has_paschal_lauds_table() && !cist.has_paschal_lauds_table());
+        assert_eq!(cist.minor_hour_antiphon(Hora::Tertia), Some(2));
+        assert_eq!(monastic.minor_hour_antiphon(Hora::Tertia), Some(1));
+        assert_eq!(cist.minor_hour_antiphon(Hora::Nona), Some(4));
+        assert_eq!(cist.minor_hour_antiphon(Hora::Vespera), None);
+        assert!(alt.salve_regina_at_compline() && !monastic.salve_regina_at_compline());
+        assert!(cist.winter_hymn_at_vespers() && !cist.winter_hymns_in_october());
+    }
+
+    #[test]
+    fn test_gloria_patri_allowed() {
+        let allowed = |dayname, votive, part| gloria_patri_allowed(&GloriaContext { dayname, votive, part });
+        // Lent before Passion Sunday keeps every Gloria.
+        assert!(allowed("Quad4-6", "", GloriaPart::Responsory));
+        assert!(allowed("Quad4-6", "", GloriaPart::Invitatorium));
+        // From Passion Sunday only the psalms have it.
+        assert!(allowed("Quad5-0", "", GloriaPart::Psalm));
+        assert!(!allowed("Quad5-0", "", GloriaPart::Responsory));
+        assert!(!allowed("Quad5-0", "", GloriaPart::ResponsoriumBreve));
+        assert!(!allowed("Quad5-0", "", GloriaPart::Invitatorium));
+        assert!(allowed("Quad6-3", "", GloriaPart::Psalm));
+        assert!(!allowed("Quad6-3", "", GloriaPart::ResponsoriumBreve));
+        // The Triduum and the Office of the Dead have none.
+        assert!(!allowed("Quad6-4", "", GloriaPart::Psalm));
+        assert!(!allowed("Quad6-6", "", GloriaPart::Psalm));
+        assert!(!allowed("Pent05-2", "C9", GloriaPart::Psalm));
+        assert!(allowed("Pasc0-0", "", GloriaPart::Responsory));
+    }
+
+    #[test]
+    fn test_display_keeps_name() {
+        assert_eq!(RubricsVersion::from("Reduced - 1955").to_string(), "Reduced - 1955");
+    }
+}
@@ -12,10 +12,18 @@
 //! path components (i.e. return only the file name portion).
 //!
 //! Legacy version mappings are defined as lazy–static hash maps.
+//!
+//! `load_dialog(basedir, kind)` reads the whole `horas.dialog` or
+//! `missa.dialog` as typed `DialogOptions`, so that front-ends can list the
+//! valid values of the parameters (versions, languages, votives, ...).
 
 use once_cell::sync::Lazy;
 use regex::Regex;
 use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+
+use crate::dialogcommon::DialogData;
 
 /// Returns a string with any leading path (up to and including the last '/') removed.
 fn strip_path(s: &str) -> String {
@@ -120,3 +128,119 @@ pub fn check_horas(h: &str) -> Vec<Option<String>> {
 pub fn check_language(l: &str) -> Option<String> {
     unequivocal(l, "languages")
 }
+
+/// The option file to read: `horas/horas.dialog` or `missa/missa.dialog`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DialogKind {
+    Horas,
+    Missa,
+}
+
+impl DialogKind {
+    /// The name of the folder and of the file, "horas" or "missa".
+    pub fn name(self) -> &'static str {
+        match self {
+            DialogKind::Horas => "horas",
+            DialogKind::Missa => "missa",
+        }
+    }
+}
+
+/// A value of a parameter and its display name. The dialog lists such values
+/// as "Display name/value"; a bare value is its own display name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DialogOption {
+    pub value: String,
+    pub display: String,
+}
+
+impl DialogOption {
+    fn parse(entry: &str) -> Self {
+        match entry.rsplit_once('/') {
+            Some((display, value)) => Self { value: value.trim().to_string(), display: display.trim().to_string() },
+            None => Self { value: entry.to_string(), display: entry.to_string() },
+        }
+    }
+}
+
+/// The option sets of a dialog file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DialogOptions {
+    pub kind: DialogKind,
+    /// The versions of the rubrics, from the "versions" section.
+    pub versions: Vec<DialogOption>,
+    /// The languages, from "languages".
+    pub languages: Vec<String>,
+    /// The hours, from "horas" (empty for the Mass).
+    pub horas: Vec<String>,
+    /// The votive offices or Masses, from "votives".
+    pub votives: Vec<DialogOption>,
+    /// The settings of the expansion of the texts (e.g. "all", "psalms"),
+    /// from "expand".
+    pub expand: Vec<String>,
+}
+
+impl DialogOptions {
+    /// Returns the display name of `version`, or `None` if the dialog does
+    /// not list it.
+    pub fn version_displayname(&self, version: &str) -> Option<&str> {
+        self.versions.iter().find(|v| v.value == version).map(|v| v.display.as_str())
+    }
+
+    /// Whether `version` is one of the versions of the dialog.
+    pub fn is_valid_version(&self, version: &str) -> bool {
+        self.version_displayname(version).is_some()
+    }
+}
+
+/// Reads the option file of `kind` under `basedir` (the `web/www` folder of
+/// the Perl project): `{basedir}/horas/horas.dialog` or
+/// `{basedir}/missa/missa.dialog`. A section missing from the file gives an
+/// empty set; a missing file is an error.
+pub fn load_dialog(basedir: &Path, kind: DialogKind) -> io::Result<DialogOptions> {
+    let mut data = DialogData::open(basedir, kind.name())?;
+    let mut list = |name: &str| -> Vec<String> {
+        data.get_dialog_array(name).into_iter().filter(|s| !s.is_empty()).collect()
+    };
+    Ok(DialogOptions {
+        kind,
+        versions: list("versions").iter().map(|v| DialogOption::parse(v)).collect(),
+        languages: list("languages"),
+        horas: list("horas"),
+        votives: list("votives").iter().map(|v| DialogOption::parse(v)).collect(),
+        expand: list("expand"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempRoot;
+
+    #[test]
+    fn test_load_dialog() {
+        let root = TempRoot::new("dialog");
+        root.write(
+            "horas/horas.dialog",
+            "[horas]\nMatutinum,Laudes,Prima,Tertia,Sexta,Nona,Vespera,Completorium\n\n\
+             [versions]\nTridentine - 1570,Divino Afflatu/Divino Afflatu - 1954,\nRubrics 1960 - 1960\n\
+             [languages]\nLatin,English,Deutsch\n\
+             [votives]\nHodie,Defunctorum/C9\n\
+             [expand]\nall,psalms,nothing,skeleton\n",
+        );
+
+        let options = load_dialog(&root, DialogKind::Horas).unwrap();
+        assert_eq!(options.horas.len(), 8);
+        assert_eq!(options.versions.len(), 3);
+        assert_eq!(options.version_displayname("Divino Afflatu - 1954"), Some("Divino Afflatu"));
+        assert_eq!(options.version_displayname("Rubrics 1960 - 1960"), Some("Rubrics 1960 - 1960"));
+        assert!(!options.is_valid_version("Divino Afflatu"));
+        assert_eq!(options.languages, ["Latin", "English", "Deutsch"]);
+        assert_eq!(options.votives[1], DialogOption { value: "C9".to_string(), display: "Defunctorum".to_string() });
+        assert_eq!(options.expand, ["all", "psalms", "nothing", "skeleton"]);
+
+        let mut data = DialogData::open(&root, "horas").unwrap();
+        assert_eq!(crate::dialogcommon::version_displayname(&mut data, "Divino Afflatu - 1954"), "Divino Afflatu");
+        assert!(load_dialog(&root, DialogKind::Missa).is_err());
+    }
+}
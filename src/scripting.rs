@@ -26,6 +26,19 @@
 //!   either a numeric literal or a single-quoted string. This replicates the
 //!   minimal argument parsing from `Scripting.pm`.
 //!
+//! - **`FunctionRegistry`**: A registry of the functions called from the
+//!   data files as `&funcname(arg1, arg2)`, with the built-in ones (`psalm`,
+//!   `Gloria`, `ant`, `translate_cz`) registered by `with_builtins()`.
+//!
+//! - **`expand_directives(text, ctx)`**: Resolves the `$Prayer` and
+//!   `&function(args)` references left in the output of the Hours against
+//!   the Prayers and Psalterium data, as the web front end does before
+//!   printing a column.
+//!
+//! - **`ExpandMode`**: how much of the psalms and of the prayers the
+//!   expansion writes out, the "expand" preference of the Perl site
+//!   (`FunctionRegistry::with_expand_mode()`).
+//!
 //! Because Rust does not have `Attribute::Handlers` the same way Perl does,
 //! the attribute-based logic (`sub UNIVERSAL::ScriptFunc : ATTR(CODE,BEGIN) {...}`)
 //! is omitted. Instead, you can define your script functions and register
@@ -53,6 +66,9 @@ use std::collections::HashMap;
 use once_cell::sync::Lazy;
 use std::fmt;
 
+use crate::horas::altovadum::translate_cz;
+use crate::setup_string::{ResolveDirectives, SetupStringProvider};
+
 /// A type alias for the function signature. In Perl, subroutines can have
 /// variable arguments. In Rust, we unify them into `Vec<String>`. The script
 /// function can return a `String`, though some might prefer `String` or an
@@ -174,6 +190,354 @@ pub fn dispatch_script_function(function_name: &str, args: &[String]) -> String
     code_ref(args)
 }
 
+/// The data `expand_directives()` resolves references against: the files of
+/// the language of the column.
+pub struct DirectiveContext<'a> {
+    pub files: &'a mut dyn SetupStringProvider,
+    pub lang: &'a str,
+}
+
+/// A function that needs the data files, e.g. `psalm`. It returns `None`
+/// when the call cannot be resolved.
+pub type DataFunc = fn(&mut DirectiveContext, &[String]) -> Option<String>;
+
+/// A registered function: a plain script function, or one reading the data.
+#[derive(Clone, Copy)]
+enum Handler {
+    Script(ScriptFunc),
+    Data(DataFunc),
+}
+
+/// How much of the psalms and of the prayers (the common prayers, the
+/// creeds, the conclusions) the expansion writes out.
+///
+/// An abbreviated prayer is its incipit, "Pater noster...", and an
+/// abbreviated psalm its heading with the incipit of its first verse.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ExpandMode {
+    /// Everything in full.
+    #[default]
+    All,
+    /// The psalms in full, the prayers abbreviated.
+    Psalms,
+    /// The psalms and the prayers abbreviated.
+    Nothing,
+    /// The outline of the hour: each psalm by its heading, each prayer by
+    /// its name as a rubric ("!Pater noster").
+    Skeleton,
+}
+
+impl ExpandMode {
+    /// The mode of an "expand" preference of the Perl site: "all",
+    /// "psalms", "nothing" or "skeleton".
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "all" => Some(ExpandMode::All),
+            "psalms" => Some(ExpandMode::Psalms),
+            "nothing" => Some(ExpandMode::Nothing),
+            "skeleton" => Some(ExpandMode::Skeleton),
+            _ => None,
+        }
+    }
+}
+
+/// What a directive line stands for, to `ExpandMode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Directive {
+    Psalm,
+    Prayer,
+    /// A plain script function, whose output is not abbreviated.
+    Function,
+}
+
+/// The functions that `&funcname(args)` calls in the data files may name.
+///
+/// Unlike the global registry of `register_script_function()`, a registry is
+/// a value: each front end (or test) can build its own, starting from the
+/// built-in functions of `with_builtins()`.
+#[derive(Clone, Default)]
+pub struct FunctionRegistry {
+    functions: HashMap<String, Handler>,
+    expand: ExpandMode,
+}
+
+impl FunctionRegistry {
+    /// An empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry with the built-in functions:
+    ///
+    /// - `psalm(num)`: the psalm `num` ("116", or "118(1-16)" for a range of
+    ///   verses) from `Psalterium/Psalmorum/`, with its heading and the Gloria
+    ///   (without it for `psalm(num, 'nogloria')`).
+    /// - `Gloria`: the Gloria Patri of the Prayers.
+    /// - `ant(name)`: the antiphon `name` of the Prayers (e.g. "Salve Regina").
+    /// - `translate_cz(text)`: the Czech rendering of the Altovadum Necrologium.
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        registry.register_data("psalm", |ctx, args| {
+            psalm_text(ctx, args.first()?, !args.iter().any(|a| a == "nogloria"))
+        });
+        registry.register_data("Gloria", |ctx, _| prayer_text(ctx, "Gloria"));
+        registry.register_data("ant", |ctx, args| {
+            let name = args.join(" ");
+            prayer_text(ctx, &format!("ant {}", name)).or_else(|| prayer_text(ctx, &name))
+        });
+        registry.register("translate_cz", translate_cz);
+        registry
+    }
+
+    /// Expands the psalms and the prayers as `mode` says (in full by
+    /// default).
+    pub fn with_expand_mode(mut self, mode: ExpandMode) -> Self {
+        self.expand = mode;
+        self
+    }
+
+    /// Registers a plain script function under `name`, replacing any
+    /// function of that name.
+    pub fn register(&mut self, name: &str, code: ScriptFunc) {
+        self.functions.insert(name.to_string(), Handler::Script(code));
+    }
+
+    /// Registers a function reading the data files under `name`.
+    pub fn register_data(&mut self, name: &str, code: DataFunc) {
+        self.functions.insert(name.to_string(), Handler::Data(code));
+    }
+
+    /// Whether a function is registered under `name`.
+    pub fn contains(&self, name: &str) -> bool {
+        self.functions.contains_key(name)
+    }
+
+    /// Calls the function `name` with `args`. Returns `None` if there is no
+    /// such function or it cannot resolve the call.
+    pub fn call(&self, name: &str, args: &[String], ctx: &mut DirectiveContext) -> Option<String> {
+        match self.functions.get(name)? {
+            Handler::Script(code) => Some(code(args)),
+            Handler::Data(code) => code(ctx, args),
+        }
+    }
+
+    /// Calls the function of a `&funcname(arg1, arg2)` line of a data file.
+    pub fn dispatch(&self, call: &str, ctx: &mut DirectiveContext) -> Option<String> {
+        let (name, args) = parse_function_call(call)?;
+        self.call(&name, &args, ctx)
+    }
+
+    /// Expands the directives of `text` with the functions of the registry,
+    /// as `expand_directives()` does with the built-in ones, the psalms and
+    /// the prayers as the `ExpandMode` of the registry says.
+    pub fn expand(&self, text: &str, ctx: &mut DirectiveContext) -> String {
+        self.expand_lines(text, ctx, 0, self.expand)
+    }
+
+    fn expand_lines(&self, text: &str, ctx: &mut DirectiveContext, depth: usize, mode: ExpandMode) -> String {
+        text.lines()
+            .map(|line| match self.expand_line(line, ctx) {
+                Some(expanded) if depth < MAX_EXPANSION_DEPTH => match (self.directive(line), mode) {
+                    (Directive::Function, _) | (_, ExpandMode::All) | (Directive::Psalm, ExpandMode::Psalms) => {
+                        self.expand_lines(&expanded, ctx, depth + 1, mode)
+                    }
+                    (Directive::Psalm, ExpandMode::Skeleton) => expanded.lines().next().unwrap_or("").to_string(),
+                    (Directive::Psalm, _) => {
+                        let mut lines = expanded.lines();
+                        let heading = lines.next().unwrap_or("");
+                        match lines.next() {
+                            Some(verse) => format!("{}\n{}", heading, incipit(verse)),
+                            None => heading.to_string(),
+                        }
+                    }
+                    (Directive::Prayer, ExpandMode::Skeleton) => format!("!{}", prayer_name(line)),
+                    (Directive::Prayer, _) => incipit(&self.expand_lines(&expanded, ctx, depth + 1, ExpandMode::All)),
+                },
+                _ => line.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// What the directive `line` stands for: `&psalm` a psalm, a plain
+    /// script function itself, anything else a prayer.
+    fn directive(&self, line: &str) -> Directive {
+        let Some((name, _)) = parse_function_call(line) else { return Directive::Prayer };
+        let script = match self.functions.get(&name) {
+            Some(handler) => matches!(handler, Handler::Script(_)),
+            None => SCRIPT_FUNCTIONS.lock().unwrap().get(&name).is_some_and(|f| f.contains_key("func")),
+        };
+        if name == "psalm" {
+            Directive::Psalm
+        } else if script {
+            Directive::Function
+        } else {
+            Directive::Prayer
+        }
+    }
+
+    /// Returns the expansion of a directive line, or `None` if it is not one
+    /// or cannot be resolved.
+    fn expand_line(&self, line: &str, ctx: &mut DirectiveContext) -> Option<String> {
+        let line = line.trim();
+        if let Some(name) = line.strip_prefix('$') {
+            let name = name.trim();
+            return prayer_text(ctx, name).or_else(|| prayer_text(ctx, name.split_once(' ')?.1.trim()));
+        }
+        let (name, args) = parse_function_call(line)?;
+        if self.contains(&name) {
+            return self.call(&name, &args, ctx);
+        }
+        let registered = SCRIPT_FUNCTIONS.lock().unwrap().get(&name).and_then(|f| f.get("func")).copied();
+        match registered {
+            Some(code) => Some(code(&args)),
+            None => prayer_text(ctx, &name.replace('_', " ")),
+        }
+    }
+}
+
+/// Parses a call of a data file, `&funcname(arg1, arg2)` or `&funcname`,
+/// into the name of the function and its arguments (see
+/// `parse_script_arguments()`).
+///
+/// ```
+/// use divinum_officium::scripting::parse_function_call;
+/// assert_eq!(
+///     parse_function_call("&psalm(116)"),
+///     Some(("psalm".to_string(), vec!["116".to_string()]))
+/// );
+/// assert_eq!(parse_function_call("&Gloria"), Some(("Gloria".to_string(), vec![])));
+/// assert_eq!(parse_function_call("$Pater noster"), None);
+/// ```
+pub fn parse_function_call(call: &str) -> Option<(String, Vec<String>)> {
+    let call = call.trim().strip_prefix('&')?;
+    let (name, args) = match call.split_once('(') {
+        Some((name, rest)) => (name.trim(), parse_script_arguments(rest.trim_end().strip_suffix(')')?)),
+        None => (call.trim(), Vec::new()),
+    };
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+    Some((name.to_string(), args))
+}
+
+/// The name of the prayer of a directive line: "Pater noster" for
+/// `$Pater noster`, "Divinum auxilium" for `&Divinum_auxilium`, "Salve
+/// Regina" for `&ant('Salve Regina')`.
+fn prayer_name(line: &str) -> String {
+    let line = line.trim();
+    if let Some(name) = line.strip_prefix('$') {
+        return name.trim().to_string();
+    }
+    match parse_function_call(line) {
+        Some((_, args)) if !args.is_empty() => args.join(" "),
+        Some((name, _)) => name.replace('_', " "),
+        None => line.to_string(),
+    }
+}
+
+/// The incipit of a text: the first words of its first line, up to the
+/// first pause and three at most, then "...". The mark of a versicle or a
+/// response and the number of a verse are kept: "V. Divínum auxílium...",
+/// "116:1 Laudáte Dóminum...".
+fn incipit(text: &str) -> String {
+    let first = text.lines().map(str::trim).find(|l| !l.is_empty() && !l.starts_with(['!', '#'])).unwrap_or("");
+    let mut words = first.split_whitespace().peekable();
+    let mut out = Vec::new();
+    if let Some(&lead) = words.peek() {
+        if matches!(lead, "V." | "R." | "R.br." | "Ant.") || lead.starts_with(|c: char| c.is_ascii_digit()) && lead.contains(':') {
+            out.push(lead);
+            words.next();
+        }
+    }
+    let start = out.len();
+    for word in words {
+        if word == "*" || word == "†" {
+            break;
+        }
+        out.push(word);
+        if out.len() - start == 3 || word.ends_with([',', '.', ':', ';', '!', '?']) {
+            break;
+        }
+    }
+    let text = out.join(" ");
+    format!("{}...", text.trim_end_matches([',', '.', ':', ';', '!', '?']))
+}
+
+/// How deep references inside expanded texts are followed.
+const MAX_EXPANSION_DEPTH: usize = 8;
+
+/// Expands the directives of `text`, line by line:
+///
+/// - `$Name` (e.g. `$Pater noster`, `$Per Dominum`) is replaced by the
+///   section `Name` of `Psalterium/Common/Prayers.txt`; a qualified name such
+///   as `$ant Salve Regina` falls back to the name without its first word.
+/// - `&function(args)` calls the function of that name, of the built-in
+///   ones of `FunctionRegistry::with_builtins()` or else of the functions
+///   registered with `register_script_function()`. `&psalm(116)` or
+///   `&psalm(118(1-16))` is replaced by the psalm (or the verses of it). Any
+///   other `&Name` (e.g. `&Divinum_auxilium`) is the prayer of that name,
+///   with `_` read as a space.
+///
+/// References inside the expanded texts are expanded in turn. A reference
+/// that cannot be resolved is left as it is.
+pub fn expand_directives(text: &str, ctx: &mut DirectiveContext) -> String {
+    FunctionRegistry::with_builtins().expand(text, ctx)
+}
+
+/// `expand_directives()`, the psalms and the prayers as `mode` says.
+pub fn expand_directives_with(text: &str, ctx: &mut DirectiveContext, mode: ExpandMode) -> String {
+    FunctionRegistry::with_builtins().with_expand_mode(mode).expand(text, ctx)
+}
+
+/// The section `name` of the Prayers of the language.
+fn prayer_text(ctx: &mut DirectiveContext, name: &str) -> Option<String> {
+    ctx.files
+        .setupstring(ctx.lang, "Psalterium/Common/Prayers.txt", ResolveDirectives::All)?
+        .get(name)
+        .map(|t| t.trim_end().to_string())
+}
+
+/// The psalm `spec` ("116", or "118(1-16)" for a range of verses) with its
+/// heading and, if `gloria`, the Gloria Patri.
+fn psalm_text(ctx: &mut DirectiveContext, spec: &str, gloria: bool) -> Option<String> {
+    let (num, range) = match spec.split_once('(') {
+        Some((num, range)) => {
+            let (from, to) = range.trim_end_matches(')').split_once('-')?;
+            (num.trim(), Some((from.trim().parse::<u32>().ok()?, to.trim().parse::<u32>().ok()?)))
+        }
+        None => (spec.trim(), None),
+    };
+    let fname = format!("Psalterium/Psalmorum/Psalm{}.txt", num);
+    let text = ctx.files.setupstring(ctx.lang, &fname, ResolveDirectives::All)?.get("__preamble")?.clone();
+    let title = ctx
+        .files
+        .setupstring(ctx.lang, "Psalterium/Common/Translate.txt", ResolveDirectives::All)
+        .and_then(|t| t.get("Psalmus").map(|p| p.trim().to_string()))
+        .unwrap_or_else(|| "Psalmus".to_string());
+    let mut out = vec![match range {
+        Some((from, to)) => format!("!{} {} [{}-{}]", title, num, from, to),
+        None => format!("!{} {}", title, num),
+    }];
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        // Verses are numbered "116:1 ..."; a range keeps the verses within it.
+        if let Some((from, to)) = range {
+            let verse = line
+                .split_once(' ')
+                .and_then(|(v, _)| v.split_once(':'))
+                .and_then(|(_, v)| v.trim_end_matches(|c: char| !c.is_ascii_digit()).parse::<u32>().ok());
+            if !verse.is_some_and(|v| v >= from && v <= to) {
+                continue;
+            }
+        }
+        out.push(line.to_string());
+    }
+    if gloria {
+        out.push("&Gloria".to_string());
+    }
+    Some(out.join("\n"))
+}
+
 /// Parse a string of arguments in a simplistic style.
 /// 
 /// - Splits on commas that are not within single quotes,
@@ -232,6 +596,101 @@ pub fn parse_script_arguments(list_str: &str) -> Vec<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::horas::specials::tests::TestFiles;
+
+    fn dummy_files() -> TestFiles {
+        TestFiles::default()
+            .with("Latin", "Psalterium/Common/Prayers.txt", &[
+                ("Pater noster", "Pater noster, qui es in cælis..."),
+                ("Gloria", "Glória Patri, et Fílio, * et Spirítui Sancto.\nSicut erat in princípio..."),
+                ("Divinum auxilium", "V. Divínum auxílium máneat semper nobíscum.\nR. Amen."),
+                ("Salve Regina", "Salve, Regína, mater misericórdiæ..."),
+            ])
+            .with("Latin", "Psalterium/Psalmorum/Psalm116.txt", &[(
+                "__preamble",
+                "116:1 Laudáte Dóminum, omnes gentes: * laudáte eum, omnes pópuli:\n116:2 Quóniam confirmáta est super nos misericórdia ejus: * et véritas Dómini manet in ætérnum.\n",
+            )])
+    }
+
+    #[test]
+    fn test_expand_directives_prayers() {
+        let mut files = dummy_files();
+        let mut ctx = DirectiveContext { files: &mut files, lang: "Latin" };
+        assert_eq!(
+            expand_directives("#Pater noster\n$Pater noster\n&Divinum_auxilium\n$ant Salve Regina", &mut ctx),
+            "#Pater noster\nPater noster, qui es in cælis...\nV. Divínum auxílium máneat semper nobíscum.\nR. Amen.\nSalve, Regína, mater misericórdiæ..."
+        );
+        // Unknown references are kept.
+        assert_eq!(expand_directives("$Oremus\n&Dominus_vobiscum", &mut ctx), "$Oremus\n&Dominus_vobiscum");
+    }
+
+    #[test]
+    fn test_expand_directives_psalm() {
+        let mut files = dummy_files();
+        let mut ctx = DirectiveContext { files: &mut files, lang: "Latin" };
+        assert_eq!(
+            expand_directives("&psalm(116)", &mut ctx),
+            "!Psalmus 116\n116:1 Laudáte Dóminum, omnes gentes: * laudáte eum, omnes pópuli:\n\
+             116:2 Quóniam confirmáta est super nos misericórdia ejus: * et véritas Dómini manet in ætérnum.\n\
+             Glória Patri, et Fílio, * et Spirítui Sancto.\nSicut erat in princípio..."
+        );
+        assert!(expand_directives("&psalm('116(2-2)')", &mut ctx).starts_with("!Psalmus 116 [2-2]\n116:2 Quóniam"));
+        assert!(expand_directives("&psalm(116,'nogloria')", &mut ctx).ends_with("véritas Dómini manet in ætérnum."));
+    }
+
+    #[test]
+    fn test_expand_modes() {
+        let mut files = dummy_files().with("Latin", "Psalterium/Common/Prayers.txt", &[
+            ("Pater noster", "Pater noster, qui es in cælis..."),
+            ("Credo", "Credo in Deum, Patrem omnipoténtem, Creatórem cæli et terræ."),
+            ("Gloria", "Glória Patri, et Fílio, * et Spirítui Sancto."),
+            ("Divinum auxilium", "V. Divínum auxílium máneat semper nobíscum.\nR. Amen."),
+        ]);
+        let mut ctx = DirectiveContext { files: &mut files, lang: "Latin" };
+        let text = "#Psalmi\nAnt. Laudáte.\n&psalm(116)\n$Pater noster\n$Credo\n&Divinum_auxilium";
+        let expand = |ctx: &mut DirectiveContext, mode| expand_directives_with(text, ctx, mode);
+        assert_eq!(expand(&mut ctx, ExpandMode::All), expand_directives(text, &mut ctx));
+        assert_eq!(
+            expand(&mut ctx, ExpandMode::Psalms),
+            "#Psalmi\nAnt. Laudáte.\n!Psalmus 116\n116:1 Laudáte Dóminum, omnes gentes: * laudáte eum, omnes pópuli:\n\
+             116:2 Quóniam confirmáta est super nos misericórdia ejus: * et véritas Dómini manet in ætérnum.\n\
+             Glória Patri...\nPater noster...\nCredo in Deum...\nV. Divínum auxílium máneat..."
+        );
+        assert_eq!(
+            expand(&mut ctx, ExpandMode::Nothing),
+            "#Psalmi\nAnt. Laudáte.\n!Psalmus 116\n116:1 Laudáte Dóminum...\nPater noster...\nCredo in Deum...\nV. Divínum auxílium máneat..."
+        );
+        assert_eq!(
+            expand(&mut ctx, ExpandMode::Skeleton),
+            "#Psalmi\nAnt. Laudáte.\n!Psalmus 116\n!Pater noster\n!Credo\n!Divinum auxilium"
+        );
+        // Unknown references are kept, and plain script functions are written out.
+        assert_eq!(expand_directives_with("$Oremus", &mut ctx, ExpandMode::Nothing), "$Oremus");
+        assert_eq!(ExpandMode::from_name("Psalms"), Some(ExpandMode::Psalms));
+        assert_eq!(ExpandMode::from_name("some"), None);
+    }
+
+    fn shout(args: &[String]) -> String {
+        args.join(" ").to_uppercase()
+    }
+
+    #[test]
+    fn test_function_registry() {
+        let mut files = dummy_files();
+        let mut ctx = DirectiveContext { files: &mut files, lang: "Latin" };
+        let mut registry = FunctionRegistry::with_builtins();
+        assert!(registry.contains("psalm") && registry.contains("translate_cz"));
+        assert!(!registry.contains("shout"));
+        registry.register("shout", shout);
+        assert_eq!(registry.dispatch("&shout('Deo', 'gratias')", &mut ctx).as_deref(), Some("DEO GRATIAS"));
+        assert_eq!(
+            registry.dispatch("&ant('Salve Regina')", &mut ctx).as_deref(),
+            Some("Salve, Regína, mater misericórdiæ...")
+        );
+        assert_eq!(registry.expand("&Gloria", &mut ctx), "Glória Patri, et Fílio, * et Spirítui Sancto.\nSicut erat in princípio...");
+        assert!(registry.dispatch("&psalm(999)", &mut ctx).is_none());
+        assert!(FunctionRegistry::new().dispatch("&Gloria", &mut ctx).is_none());
+    }
 
     #[test]
     fn test_empty_input() {
@@ -0,0 +1,361 @@
+//! scripture.rs
+//!
+//! The references to the Scripture of the lessons and propers: the rubric
+//! lines of the data ("!Rom 12:6-16", "!Ps 44:8; 44:3") and the headings
+//! of the lessons ("Léctio Epístolæ beáti Pauli Apóstoli ad Romános"),
+//! normalized to the books of the Bible, so that a lesson can be linked to
+//! the text of a Bible.
+//!
+//! ```
+//! # use divinum_officium::scripture::{parse_references, book_of_heading};
+//! let refs = parse_references("Rom. 12:6-16");
+//! assert_eq!(refs[0].book, "Rom");
+//! assert_eq!(refs[0].format("English"), "Romans 12:6-16");
+//! assert_eq!(refs[0].format("Deutsch"), "Röm 12,6-16");
+//! assert_eq!(book_of_heading("Léctio Epístolæ beáti Pauli Apóstoli ad Romános"), Some("Rom"));
+//! ```
+//!
+//! A book is named by its OSIS identifier ("Gen", "1Cor", "Matt"). The
+//! chapters and verses are those of the Vulgate, as in the data: the psalms
+//! keep their Vulgate numbers, and "3 Reg" is the first book of Kings.
+
+use std::fmt;
+
+/// A book of the Bible: its OSIS identifier, its number among the books of
+/// the same name (0 without), its abbreviations in Latin, English and
+/// German, the other names it goes by in the data, and the phrase naming it
+/// in the headings of the lessons (in lowercase, without accents).
+struct Book {
+    osis: &'static str,
+    number: u8,
+    latin: &'static str,
+    english: &'static str,
+    deutsch: &'static str,
+    aliases: &'static [&'static str],
+    heading: &'static str,
+}
+
+const fn book(
+    osis: &'static str,
+    number: u8,
+    latin: &'static str,
+    english: &'static str,
+    deutsch: &'static str,
+    aliases: &'static [&'static str],
+    heading: &'static str,
+) -> Book {
+    Book { osis, number, latin, english, deutsch, aliases, heading }
+}
+
+static BOOKS: &[Book] = &[
+    book("Gen", 0, "Gen", "Genesis", "Gen", &["gn"], "genesis"),
+    book("Exod", 0, "Exod", "Exodus", "Ex", &["ex", "exo"], "exodi"),
+    book("Lev", 0, "Lev", "Leviticus", "Lev", &["lv"], "levitici"),
+    book("Num", 0, "Num", "Numbers", "Num", &["nm"], "numeri"),
+    book("Deut", 0, "Deut", "Deuteronomy", "Dtn", &["dt", "dtn"], "deuteronomii"),
+    book("Josh", 0, "Jos", "Joshua", "Jos", &["josue"], "josue"),
+    book("Judg", 0, "Judic", "Judges", "Ri", &["jdc", "judc"], "judicum"),
+    book("Ruth", 0, "Ruth", "Ruth", "Rut", &["rt"], "ruth"),
+    book("1Sam", 1, "1 Reg", "1 Samuel", "1 Sam", &["1reg", "1rg", "1sm"], "regum"),
+    book("2Sam", 2, "2 Reg", "2 Samuel", "2 Sam", &["2reg", "2rg", "2sm"], "regum"),
+    book("1Kgs", 3, "3 Reg", "1 Kings", "1 Kön", &["3reg", "3rg", "1kings", "1kon"], "regum"),
+    book("2Kgs", 4, "4 Reg", "2 Kings", "2 Kön", &["4reg", "4rg", "2kings", "2kon"], "regum"),
+    book("1Chr", 1, "1 Par", "1 Chronicles", "1 Chr", &["1par", "1paral"], "paralipomenon"),
+    book("2Chr", 2, "2 Par", "2 Chronicles", "2 Chr", &["2par", "2paral"], "paralipomenon"),
+    book("Ezra", 0, "Esdr", "Ezra", "Esra", &["1esdr", "esr"], "esdrae"),
+    book("Neh", 0, "Neh", "Nehemiah", "Neh", &["2esdr"], "nehemiae"),
+    book("Tob", 0, "Tob", "Tobit", "Tob", &["tb"], "tobiae"),
+    book("Jdt", 0, "Judith", "Judith", "Jdt", &["judt"], "judith"),
+    book("Esth", 0, "Esth", "Esther", "Est", &["est"], "esther"),
+    book("Job", 0, "Job", "Job", "Ijob", &["jb"], "job"),
+    book("Ps", 0, "Ps", "Psalm", "Ps", &["psalm", "psalmus", "pss"], "psalm"),
+    book("Prov", 0, "Prov", "Proverbs", "Spr", &["prv"], "proverbiorum"),
+    book("Eccl", 0, "Eccl", "Ecclesiastes", "Koh", &["eccle", "qo"], "ecclesiastes"),
+    book("Song", 0, "Cant", "Song of Songs", "Hld", &["ct"], "canticorum"),
+    book("Wis", 0, "Sap", "Wisdom", "Weish", &["sg"], "sapientiae"),
+    book("Sir", 0, "Eccli", "Sirach", "Sir", &["ecclus", "sirach"], "ecclesiastici"),
+    book("Isa", 0, "Isa", "Isaiah", "Jes", &["is", "isai"], "isaiae"),
+    book("Jer", 0, "Jer", "Jeremiah", "Jer", &["jr"], "jeremiae"),
+    book("Lam", 0, "Lam", "Lamentations", "Klgl", &["thren"], "lamentatione"),
+    book("Bar", 0, "Bar", "Baruch", "Bar", &["ba"], "baruch"),
+    book("Ezek", 0, "Ezech", "Ezekiel", "Ez", &["ez", "ezek"], "ezechielis"),
+    book("Dan", 0, "Dan", "Daniel", "Dan", &["dn"], "daniel"),
+    book("Hos", 0, "Osee", "Hosea", "Hos", &["os"], "osee"),
+    book("Joel", 0, "Joel", "Joel", "Joël", &["jl"], "joel"),
+    book("Amos", 0, "Amos", "Amos", "Am", &["am"], "amos"),
+    book("Obad", 0, "Abd", "Obadiah", "Obd", &["abdias"], "abdiae"),
+    book("Jonah", 0, "Jonae", "Jonah", "Jona", &["jon", "jonas"], "jonae"),
+    book("Mic", 0, "Mich", "Micah", "Mi", &["mi"], "michaeae"),
+    book("Nah", 0, "Nah", "Nahum", "Nah", &["na"], "nahum"),
+    book("Hab", 0, "Hab", "Habakkuk", "Hab", &["habac"], "habacuc"),
+    book("Zeph", 0, "Soph", "Zephaniah", "Zef", &["so"], "sophoniae"),
+    book("Hag", 0, "Agg", "Haggai", "Hag", &["ag"], "aggaei"),
+    book("Zech", 0, "Zach", "Zechariah", "Sach", &["za"], "zachariae"),
+    book("Mal", 0, "Mal", "Malachi", "Mal", &["ml"], "malachiae"),
+    book("1Macc", 1, "1 Mach", "1 Maccabees", "1 Makk", &["1mach", "1mac", "1macc"], "machabaeorum"),
+    book("2Macc", 2, "2 Mach", "2 Maccabees", "2 Makk", &["2mach", "2mac", "2macc"], "machabaeorum"),
+    book("Matt", 0, "Matt", "Matthew", "Mt", &["mt", "matth"], "secundum matthaeum"),
+    book("Mark", 0, "Marc", "Mark", "Mk", &["mc", "mk", "mar"], "secundum marcum"),
+    book("Luke", 0, "Luc", "Luke", "Lk", &["lc", "lk", "lucas"], "secundum lucam"),
+    book("John", 0, "Joann", "John", "Joh", &["jo", "joh", "jn", "joan"], "secundum joannem"),
+    book("Acts", 0, "Act", "Acts", "Apg", &["actus", "act apost"], "actuum apostolorum"),
+    book("Rom", 0, "Rom", "Romans", "Röm", &["rm"], "ad romanos"),
+    book("1Cor", 1, "1 Cor", "1 Corinthians", "1 Kor", &["1co", "1kor"], "ad corinthios"),
+    book("2Cor", 2, "2 Cor", "2 Corinthians", "2 Kor", &["2co", "2kor"], "ad corinthios"),
+    book("Gal", 0, "Gal", "Galatians", "Gal", &["ga"], "ad galatas"),
+    book("Eph", 0, "Eph", "Ephesians", "Eph", &["ephes"], "ad ephesios"),
+    book("Phil", 0, "Phil", "Philippians", "Phil", &["philip", "php"], "ad philippenses"),
+    book("Col", 0, "Col", "Colossians", "Kol", &["kol"], "ad colossenses"),
+    book("1Thess", 1, "1 Thess", "1 Thessalonians", "1 Thess", &["1th", "1thes"], "ad thessalonicenses"),
+    book("2Thess", 2, "2 Thess", "2 Thessalonians", "2 Thess", &["2th", "2thes"], "ad thessalonicenses"),
+    book("1Tim", 1, "1 Tim", "1 Timothy", "1 Tim", &["1tm"], "ad timotheum"),
+    book("2Tim", 2, "2 Tim", "2 Timothy", "2 Tim", &["2tm"], "ad timotheum"),
+    book("Titus", 0, "Tit", "Titus", "Tit", &["tt"], "ad titum"),
+    book("Phlm", 0, "Philem", "Philemon", "Phlm", &["phm"], "ad philemonem"),
+    book("Heb", 0, "Hebr", "Hebrews", "Hebr", &["hb"], "ad hebraeos"),
+    book("Jas", 0, "Jac", "James", "Jak", &["jc", "jak"], "jacobi apostoli"),
+    book("1Pet", 1, "1 Pet", "1 Peter", "1 Petr", &["1pt", "1petr"], "petri apostoli"),
+    book("2Pet", 2, "2 Pet", "2 Peter", "2 Petr", &["2pt", "2petr"], "petri apostoli"),
+    book("1John", 1, "1 Joann", "1 John", "1 Joh", &["1jo", "1joh", "1jn"], "joannis apostoli"),
+    book("2John", 2, "2 Joann", "2 John", "2 Joh", &["2jo", "2joh", "2jn"], "joannis apostoli"),
+    book("3John", 3, "3 Joann", "3 John", "3 Joh", &["3jo", "3joh", "3jn"], "joannis apostoli"),
+    book("Jude", 0, "Jud", "Jude", "Jud", &["judae"], "judae apostoli"),
+    book("Rev", 0, "Apoc", "Revelation", "Offb", &["apc", "apocalypsis", "offb"], "apocalypsis"),
+];
+
+/// A reference to a chapter of a book and verses of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Reference {
+    /// The OSIS identifier of the book, e.g. "Rom".
+    pub book: String,
+    pub chapter: u32,
+    /// The ranges of verses (both included); a single verse is `(v, v)`.
+    pub verses: Vec<(u32, u32)>,
+}
+
+impl Reference {
+    /// Parses a single reference ("Matt 25:1-13"), or the first of several.
+    pub fn parse(text: &str) -> Option<Self> {
+        parse_references(text).into_iter().next()
+    }
+
+    /// The reference in the usage of `lang`: "Rom 12:6-16" in Latin,
+    /// "Romans 12:6-16" in English, "Röm 12,6-16" in German; the other
+    /// languages have the Latin form.
+    pub fn format(&self, lang: &str) -> String {
+        let book = book_by_osis(&self.book);
+        let (name, sep) = match (lang.split('-').next().unwrap_or(lang), book) {
+            ("English", Some(b)) => (b.english, ':'),
+            ("Deutsch", Some(b)) => (b.deutsch, ','),
+            (_, Some(b)) => (b.latin, ':'),
+            (_, None) => (self.book.as_str(), ':'),
+        };
+        format!("{} {}{}{}", name, self.chapter, sep, self.verse_list())
+    }
+
+    /// The reference as an OSIS reference, for linking: "Rom.12.6-Rom.12.16".
+    pub fn osis(&self) -> String {
+        self.verses
+            .iter()
+            .map(|&(a, b)| {
+                let start = format!("{}.{}.{}", self.book, self.chapter, a);
+                if a == b { start } else { format!("{}-{}.{}.{}", start, self.book, self.chapter, b) }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn verse_list(&self) -> String {
+        self.verses
+            .iter()
+            .map(|&(a, b)| if a == b { a.to_string() } else { format!("{}-{}", a, b) })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl fmt::Display for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.format("Latin"))
+    }
+}
+
+/// Parses the references of a rubric line of the data: the book, then the
+/// chapter and verses, with more chapters after ";" ("Ps 44:8; 44:3" is two
+/// references). Returns no reference if the book is unknown or there is no
+/// chapter.
+///
+/// ```
+/// # use divinum_officium::scripture::parse_references;
+/// let refs = parse_references("!Ps 44:8; 44:3");
+/// assert_eq!(refs.len(), 2);
+/// assert_eq!(refs[1].to_string(), "Ps 44:3");
+/// assert_eq!(parse_references("1 Cor 13:1-3, 5")[0].verses, [(1, 3), (5, 5)]);
+/// assert!(parse_references("Glória Patri").is_empty());
+/// ```
+pub fn parse_references(text: &str) -> Vec<Reference> {
+    let text = text.trim().trim_start_matches('!');
+    let tokens: Vec<&str> = text.split_whitespace().collect();
+    let Some(i) = tokens
+        .iter()
+        .position(|t| t.starts_with(|c: char| c.is_ascii_digit()) && t.contains(':'))
+        .filter(|&i| i > 0)
+    else {
+        return Vec::new();
+    };
+    let Some(book) = book_of(&tokens[..i].join(" ")) else { return Vec::new() };
+    let mut refs = Vec::new();
+    let mut chapter = 0;
+    for part in tokens[i..].join(" ").split(';') {
+        let verses = match part.split_once(':') {
+            Some((c, v)) => match number(c) {
+                Some(c) => {
+                    chapter = c;
+                    v
+                }
+                None => break,
+            },
+            None => part,
+        };
+        let verses: Option<Vec<(u32, u32)>> = verses
+            .split(',')
+            .map(|range| match range.split_once('-') {
+                Some((a, b)) => number(a).zip(number(b)),
+                None => number(range).map(|v| (v, v)),
+            })
+            .collect();
+        match verses {
+            Some(verses) if chapter > 0 => refs.push(Reference { book: book.to_string(), chapter, verses }),
+            _ => break,
+        }
+    }
+    refs
+}
+
+/// The references of the rubric lines ("!...") of a text, e.g. a lesson.
+pub fn references_in(text: &str) -> Vec<Reference> {
+    text.lines()
+        .filter(|l| l.trim_start().starts_with('!'))
+        .flat_map(parse_references)
+        .collect()
+}
+
+/// The OSIS identifier of the book named `name`, an abbreviation or name
+/// of it in Latin, English or German ("Joann.", "1 Cor", "3 Reg", "Romans").
+///
+/// ```
+/// # use divinum_officium::scripture::book_of;
+/// assert_eq!(book_of("Joann."), Some("John"));
+/// assert_eq!(book_of("3 Reg"), Some("1Kgs"));
+/// assert_eq!(book_of("1 Corinthians"), Some("1Cor"));
+/// assert_eq!(book_of("Hesiod"), None);
+/// ```
+pub fn book_of(name: &str) -> Option<&'static str> {
+    let key: String = fold(name).chars().filter(|c| !c.is_whitespace()).collect();
+    if key.is_empty() {
+        return None;
+    }
+    let squash = |s: &str| -> String { fold(s).chars().filter(|c| !c.is_whitespace()).collect() };
+    BOOKS
+        .iter()
+        .find(|b| {
+            [b.osis, b.latin, b.english, b.deutsch].iter().any(|n| squash(n) == key)
+                || b.aliases.iter().any(|a| squash(a) == key)
+        })
+        .map(|b| b.osis)
+}
+
+/// The OSIS identifier of the book a heading of a lesson names ("Léctio
+/// libri Sapiéntiæ", "Sequéntia ✠ sancti Evangélii secúndum Lucam"), or
+/// `None` if it names none, or several books of the same name without
+/// saying which ("ad Corínthios").
+///
+/// ```
+/// # use divinum_officium::scripture::book_of_heading;
+/// assert_eq!(book_of_heading("Sequéntia ✠ sancti Evangélii secúndum Lucam."), Some("Luke"));
+/// assert_eq!(book_of_heading("Léctio Epístolæ secúndæ beáti Petri Apóstoli"), Some("2Pet"));
+/// assert_eq!(book_of_heading("Léctio Epístolæ beáti Pauli Apóstoli ad Corínthios"), None);
+/// ```
+pub fn book_of_heading(heading: &str) -> Option<&'static str> {
+    let folded = fold(heading);
+    let words: Vec<&str> = folded.split_whitespace().collect();
+    let text = format!(" {} ", words.join(" "));
+    let ordinal = words.iter().find_map(|w| match *w {
+        "prima" | "primae" | "primi" | "primo" => Some(1),
+        "secunda" | "secundae" | "secundi" | "secundo" => Some(2),
+        "tertia" | "tertiae" | "tertii" | "tertio" => Some(3),
+        "quarta" | "quartae" | "quarti" | "quarto" => Some(4),
+        _ => None,
+    });
+    let candidates: Vec<&Book> = BOOKS
+        .iter()
+        .filter(|b| text.contains(&format!(" {}", b.heading)))
+        .filter(|b| ordinal.is_none() || b.number == 0 || Some(b.number) == ordinal)
+        .collect();
+    match candidates[..] {
+        [book] => Some(book.osis),
+        _ => None,
+    }
+}
+
+fn book_by_osis(osis: &str) -> Option<&'static Book> {
+    BOOKS.iter().find(|b| b.osis == osis)
+}
+
+/// `text` in lowercase, without accents and punctuation.
+fn fold(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars().flat_map(char::to_lowercase) {
+        match c {
+            'á' | 'à' | 'â' => out.push('a'),
+            'é' | 'è' | 'ê' | 'ë' => out.push('e'),
+            'í' | 'ì' | 'î' | 'ï' => out.push('i'),
+            'ó' | 'ò' | 'ô' => out.push('o'),
+            'ú' | 'ù' | 'û' => out.push('u'),
+            'ý' => out.push('y'),
+            'æ' | 'ǽ' => out.push_str("ae"),
+            'œ' => out.push_str("oe"),
+            'ö' => out.push('o'),
+            'ü' => out.push('u'),
+            c if c.is_alphanumeric() || c.is_whitespace() => out.push(c),
+            _ => out.push(' '),
+        }
+    }
+    out
+}
+
+/// The number at the start of `text`, ignoring the letters of a part of a
+/// verse ("1a").
+fn number(text: &str) -> Option<u32> {
+    let digits: String = text.trim().chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reference_formats() {
+        let r = Reference::parse("!3 Reg 17:17-24").unwrap();
+        assert_eq!(r.book, "1Kgs");
+        assert_eq!(r.format("Latin"), "3 Reg 17:17-24");
+        assert_eq!(r.format("English-Newcastle"), "1 Kings 17:17-24");
+        assert_eq!(r.format("Magyar"), "3 Reg 17:17-24");
+        assert_eq!(r.osis(), "1Kgs.17.17-1Kgs.17.24");
+        let r = Reference::parse("Eccli 44:16a, 20").unwrap();
+        assert_eq!((r.book.as_str(), r.verses.as_slice()), ("Sir", &[(16, 16), (20, 20)][..]));
+        assert_eq!(r.osis(), "Sir.44.16 Sir.44.20");
+    }
+
+    #[test]
+    fn test_references_in_lesson() {
+        let lesson = "Léctio Epístolæ beáti Pauli Apóstoli ad Romános\n!Rom 12:6-16\nFratres: Habéntes donatiónes.";
+        assert_eq!(references_in(lesson), [Reference { book: "Rom".to_string(), chapter: 12, verses: vec![(6, 16)] }]);
+        assert_eq!(book_of_heading("Léctio libri Sapiéntiæ"), Some("Wis"));
+        assert_eq!(book_of_heading("Léctio Isaíæ Prophétæ"), Some("Isa"));
+        assert_eq!(book_of_heading("Léctio libri Regum tértii"), Some("1Kgs"));
+        assert_eq!(book_of_heading("Sermo sancti Augustíni Epíscopi"), None);
+    }
+}
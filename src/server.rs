@@ -0,0 +1,195 @@
+//! The HTTP backend of the web site, with the `server` feature: a handler
+//! taking the query parameters of `officium.pl` and returning the office as
+//! an HTML page, so that the Rust crate can replace the Perl CGI.
+//!
+//! The parameters are those of the Perl site:
+//!
+//! - `date`: the day, "MM-DD-YYYY" (today by default);
+//! - `command`: "pray" and the hour, e.g. "prayLaudes";
+//! - `version`: the rubrics ("Rubrics 1960 - 1960" by default);
+//! - `lang1`, `lang2`: the languages of the two columns; without `lang2`
+//!   (or with the same language) the page has one column;
+//! - `version2`: a second version, whose hour is set beside that of
+//!   `version` in `lang1` (the compare mode of the Perl site);
+//! - `expand`: how much of the psalms and prayers is written out, "all"
+//!   (the default), "psalms", "nothing" or "skeleton" (`ExpandMode`);
+//! - `markup`: "html5" for the semantic markup of `render::html` (with its
+//!   `CSS`) instead of that of the Perl site.
+//!
+//! ```ignore
+//! let config = ServerConfig { datafolder: "web/www/horas".into() };
+//! divinum_officium::server::serve("0.0.0.0:8080", config).await?;
+//! ```
+
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use chrono::{Datelike, Local};
+
+use crate::horas::{build_hour, build_hour_bilingual, build_hour_compare, Hora};
+use crate::render::html::{escape_html, Html as HtmlRenderer, Markup, CSS};
+use crate::scripting::{DirectiveContext, ExpandMode, FunctionRegistry};
+use crate::setup_string::SetupStringContext;
+
+/// The configuration of the server.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// The data of the Hours, the `web/www/horas` folder of the Perl project.
+    pub datafolder: PathBuf,
+}
+
+/// Returns the router of the site: the office at `/officium` (and at
+/// `/cgi-bin/horas/officium.pl`, the address of the Perl script).
+pub fn router(config: ServerConfig) -> Router {
+    Router::new()
+        .route("/officium", get(officium))
+        .route("/cgi-bin/horas/officium.pl", get(officium))
+        .with_state(Arc::new(config))
+}
+
+/// Serves the site on `addr`, e.g. "0.0.0.0:8080".
+pub async fn serve(addr: &str, config: ServerConfig) -> io::Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, router(config)).await
+}
+
+async fn officium(State(config): State<Arc<ServerConfig>>, Query(params): Query<HashMap<String, String>>) -> Response {
+    let result = tokio::task::spawn_blocking(move || officium_page(&config, &params)).await;
+    match result {
+        Ok(Ok(page)) => Html(page).into_response(),
+        Ok(Err(e)) => {
+            let status = match e.kind() {
+                io::ErrorKind::InvalidInput => StatusCode::BAD_REQUEST,
+                io::ErrorKind::NotFound => StatusCode::NOT_FOUND,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            (status, e.to_string()).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// Returns the page of the office for the query `params` of `officium.pl`.
+///
+/// Fails with `InvalidInput` for an unknown hour or a malformed date, and
+/// with `NotFound` if the data of the hour is missing.
+pub fn officium_page(config: &ServerConfig, params: &HashMap<String, String>) -> io::Result<String> {
+    let param = |name: &str| params.get(name).map(|v| v.trim()).filter(|v| !v.is_empty());
+    let today = Local::now().date_naive();
+    let date = param("date")
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("{:02}-{:02}-{}", today.month(), today.day(), today.year()));
+    let version = param("version").unwrap_or("Rubrics 1960 - 1960");
+    let lang1 = param("lang1").unwrap_or("Latin");
+    let lang2 = param("lang2").filter(|l| *l != lang1);
+    let version2 = param("version2").filter(|v| *v != version);
+    let command = param("command").unwrap_or("prayLaudes");
+    let hora = command
+        .strip_prefix("pray")
+        .unwrap_or(command)
+        .parse::<Hora>()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+
+    let mut ctx = SetupStringContext {
+        hora: hora.to_string(),
+        langfb: "English".to_string(),
+        ..SetupStringContext::new(config.datafolder.clone(), version)
+    };
+    let html = match param("markup") {
+        Some(markup) if markup.eq_ignore_ascii_case("html5") => HtmlRenderer::semantic(),
+        _ => HtmlRenderer::new(),
+    };
+    let mode = param("expand").and_then(ExpandMode::from_name).unwrap_or_default();
+    let registry = FunctionRegistry::with_builtins().with_expand_mode(mode);
+    let expand = |text: &str, lang: &str, ctx: &mut SetupStringContext| {
+        registry.expand(text, &mut DirectiveContext { files: ctx, lang })
+    };
+    let body = match (version2, lang2) {
+        (Some(version2), _) => {
+            let sections = build_hour_compare(&mut ctx, &date, hora, lang1, version, version2)?;
+            let sections: Vec<(String, String)> = sections
+                .iter()
+                .map(|(left, right)| (expand(left, lang1, &mut ctx), expand(right, lang1, &mut ctx)))
+                .collect();
+            html.bilingual(&sections)
+        }
+        (None, Some(lang2)) => {
+            let sections = build_hour_bilingual(&mut ctx, &date, hora, lang1, lang2, version)?;
+            let sections: Vec<(String, String)> = sections
+                .iter()
+                .map(|(left, right)| (expand(left, lang1, &mut ctx), expand(right, lang2, &mut ctx)))
+                .collect();
+            html.bilingual(&sections)
+        }
+        (None, None) => {
+            let text = build_hour(&mut ctx, &date, hora, lang1, version)?;
+            format!("<div>{}</div>", html.hour(&expand(&text, lang1, &mut ctx)))
+        }
+    };
+    let style = match html.markup() {
+        Markup::Semantic => format!("<style>\n{}</style>", CSS),
+        Markup::Legacy => String::new(),
+    };
+    Ok(format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{} {}</title>{}</head><body>\n{}\n</body></html>\n",
+        hora,
+        escape_html(&date),
+        style,
+        body
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::data_tree;
+
+    #[test]
+    fn test_officium_page_query() {
+        let (root, _) = data_tree(&[
+            ("Latin/Tempora/Epi2-2.txt", "[Rank]\nFeria Tertia;;Feria;;1\n"),
+            ("Latin/Ordinarium/Vespera.txt", "#Incipit\nDeus in adjutorium\n&psalm(109)\n"),
+            ("Latin/Psalterium/Psalmorum/Psalm109.txt", "109:1 Dixit Dóminus Dómino meo.\n"),
+            ("English/Ordinarium/Vespera.txt", "#Incipit\nO God, come to my assistance\n"),
+        ]);
+        let config = ServerConfig { datafolder: root.to_path_buf() };
+        let query = |pairs: &[(&str, &str)]| pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+
+        let page = officium_page(&config, &query(&[("date", "01-21-2025"), ("command", "prayVespera"), ("version", "Divino Afflatu")]))
+            .unwrap();
+        assert!(page.contains("<title>Vespera 01-21-2025</title>"));
+        assert!(page.contains("Deus in adjutorium<br/>"));
+        assert!(!page.contains("<table>"));
+
+        let page = officium_page(&config, &query(&[("date", "01-21-2025"), ("command", "prayVespera"), ("lang2", "English")]))
+            .unwrap();
+        assert!(page.contains("<table>"));
+        assert!(page.contains("O God, come to my assistance"));
+
+        let page = officium_page(
+            &config,
+            &query(&[("date", "01-21-2025"), ("command", "prayVespera"), ("version", "Divino Afflatu"), ("version2", "Rubrics 1960 - 1960")]),
+        )
+        .unwrap();
+        assert_eq!(page.matches("Deus in adjutorium<br/>").count(), 2);
+
+        let page = officium_page(&config, &query(&[("date", "01-21-2025"), ("command", "prayVespera"), ("markup", "html5")]))
+            .unwrap();
+        assert!(page.contains("<h3 class=\"section\">Incipit</h3>") && page.contains("<p>Deus in adjutorium</p>"));
+        assert!(page.contains(".rubric {"));
+
+        let page = officium_page(&config, &query(&[("date", "01-21-2025"), ("command", "prayVespera"), ("expand", "skeleton")]))
+            .unwrap();
+        assert!(page.contains("Psalmus 109") && !page.contains("Dixit Dóminus"));
+
+        let err = officium_page(&config, &query(&[("command", "prayBrunch")])).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -43,9 +43,17 @@
 //! The code below uses a struct `Setup` that contains a `HashMap<String, String>`
 //! replicating the `_setup` hash. You may integrate this with your web framework
 //! or UI to replicate the original CGI behavior.
+//!
+//! Each entry of the hash (e.g. "general", "parameters") is a list of
+//! assignments, `$expand='all';;$version='Rubrics 1960 - 1960';;...`;
+//! `get()` and `set()` read and change one of them, and `preferences()`
+//! collects the user preferences as a typed `Preferences`, which
+//! `InputConfig::with_preferences()` takes.
 
 use std::collections::HashMap;
 
+use crate::dialogcommon::{parse_dialog, parse_line};
+
 /// Holds the internal `_setup` data (key → string). In Perl, this was `%_setup`.
 #[derive(Default)]
 pub struct Setup {
@@ -110,20 +118,78 @@ impl Setup {
         }
     }
 
-    /// A placeholder for loading from the typical "horas.setup" or "missa.setup".
-    /// In the original code, it uses `setupstring("", "$1.setup")`.  
-    /// You can adapt this to your own code that calls the Rust version of
-    /// “setupstring(...)”.
+    /// Loads the defaults of "horas.setup" or "missa.setup". In the original
+    /// code, it uses `setupstring("", "$1.setup")`: the file has a `[name]`
+    /// section per entry, e.g. `[general]` and `[parameters]`. A missing file
+    /// changes nothing.
     pub fn load_from_file(&mut self, path: &str) {
-        // We pretend to read from a data file, parse it as though it were
-        // a set of lines we store as a single string. The real code might
-        // differ. This is just an example.
-        if let Ok(content) = std::fs::read_to_string(path) {
-            // This content might be in the same "k v;;;k2 v2" format or
-            // might be a multi-section text from the `setupstring`.
-            // We'll do a naive approach to match load_from_str.
-            self.load_from_str(&content);
+        if let Ok(content) = crate::fileio::data_source().read_to_string(path) {
+            for (name, value) in parse_dialog(&content) {
+                let value: String = value.lines().map(str::trim).collect();
+                self.store.insert(name, value);
+            }
+        }
+    }
+
+    /// The assignments of entry `name`, as (variable, value) pairs in order:
+    /// `$expand='all';;$psalmvar='0'` gives `[("expand", "all"), ("psalmvar", "0")]`.
+    pub fn values(&self, name: &str) -> Vec<(String, String)> {
+        self.getsetup_string(name)
+            .split(";;")
+            .filter_map(parse_line)
+            .collect()
+    }
+
+    /// The value of `$var` in entry `name`.
+    pub fn get(&self, name: &str, var: &str) -> Option<String> {
+        let var = var.trim_start_matches('$');
+        self.values(name).into_iter().find(|(v, _)| v == var).map(|(_, value)| value)
+    }
+
+    /// Sets `$var` to `value` in entry `name`, adding the assignment if the
+    /// entry does not have it.
+    pub fn set(&mut self, name: &str, var: &str, value: &str) {
+        let var = var.trim_start_matches('$');
+        let mut values = self.values(name);
+        match values.iter_mut().find(|(v, _)| v == var) {
+            Some(entry) => entry.1 = value.to_string(),
+            None => values.push((var.to_string(), value.to_string())),
+        }
+        let script: Vec<String> = values.iter().map(|(v, value)| format!("${}='{}'", v, value)).collect();
+        self.store.insert(name.to_string(), script.join(";;"));
+    }
+
+    /// The user preferences of the "general" and "parameters" entries; a
+    /// variable missing from both keeps its default.
+    pub fn preferences(&self) -> Preferences {
+        let mut prefs = Preferences::default();
+        let values = self.values("general").into_iter().chain(self.values("parameters"));
+        for (var, value) in values {
+            let flag = value.trim() == "1";
+            match var.as_str() {
+                "expand" => prefs.expand = value,
+                "accented" => prefs.accented = !matches!(value.trim().to_ascii_lowercase().as_str(), "plain" | "0" | ""),
+                "psalmvar" => prefs.psalmvar = flag,
+                "priest" => prefs.priest = flag,
+                "imperata" => {
+                    prefs.imperatae = value.split(',').map(str::trim).filter(|r| !r.is_empty()).map(String::from).collect()
+                }
+                "version" => prefs.version = value,
+                "lang1" => prefs.lang1 = value,
+                "lang2" => prefs.lang2 = value,
+                "langfb" => prefs.langfb = value,
+                "votive" => prefs.votive = value,
+                "blackfont" => prefs.blackfont = value,
+                "smallblack" => prefs.smallblack = value,
+                "redfont" => prefs.redfont = value,
+                "initiale" => prefs.initiale = value,
+                "largefont" => prefs.largefont = value,
+                "smallfont" => prefs.smallfont = value,
+                "titlefont" => prefs.titlefont = value,
+                _ => {}
+            }
         }
+        prefs
     }
 
     /// Equivalent to `setsetupvalue($name, $ind, $value)`.  
@@ -350,6 +416,65 @@ impl Setup {
     }
 }
 
+/// The preferences of the user, as the web site keeps them in its setup
+/// cookie. The fonts are the Perl typeface settings, e.g. "+1 bold italic
+/// red" for `largefont`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Preferences {
+    /// How much of the texts is printed in full: "all", "psalms", "nothing"
+    /// or "skeleton".
+    pub expand: String,
+    /// Whether the texts keep their accents ("plain" turns them off).
+    pub accented: bool,
+    /// Whether the psalms are taken from the alternative (Bea) psalter.
+    pub psalmvar: bool,
+    /// Whether the office is said by a priest (the "Dominus vobiscum").
+    pub priest: bool,
+    /// The orations ordered by the bishop (orationes imperatae), added
+    /// after those of the day, as "file:section" references to the data,
+    /// e.g. "Commune/C10:Oratio" (the `imperata` variable, separated by
+    /// commas).
+    pub imperatae: Vec<String>,
+    pub version: String,
+    pub lang1: String,
+    pub lang2: String,
+    pub langfb: String,
+    pub votive: String,
+    pub blackfont: String,
+    pub smallblack: String,
+    pub redfont: String,
+    pub initiale: String,
+    pub largefont: String,
+    pub smallfont: String,
+    pub titlefont: String,
+}
+
+impl Default for Preferences {
+    /// The defaults of `horas.setup`.
+    fn default() -> Self {
+        Preferences {
+            expand: "all".to_string(),
+            accented: true,
+            psalmvar: false,
+            priest: false,
+            imperatae: Vec::new(),
+            version: "Rubrics 1960 - 1960".to_string(),
+            lang1: "Latin".to_string(),
+            lang2: "English".to_string(),
+            langfb: "English".to_string(),
+            votive: String::new(),
+            blackfont: String::new(),
+            smallblack: "-1".to_string(),
+            redfont: " italic red".to_string(),
+            initiale: "+2 bold italic red".to_string(),
+            largefont: "+1 bold italic red".to_string(),
+            smallfont: "1 red".to_string(),
+            titlefont: "+1 red".to_string(),
+        }
+    }
+}
+
 //-------------------------------------
 // Stubs for references from setup.pl
 //-------------------------------------
@@ -371,3 +496,36 @@ fn cleanse(input: &str) -> String {
     // Minimal placeholder, could do advanced HTML escaping or other logic.
     input.trim().to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_setup_preferences() {
+        let mut setup = Setup::new();
+        setup.load_from_str(
+            "general;;;$expand='psalms';;$version='Divino Afflatu - 1954';;$accented='plain';;;\
+             parameters;;;$priest='1';;$psalmvar='0';;$largefont='+2 bold red';;$imperata='Commune/C10:Oratio, Commune/C11:Oratio';;;",
+        );
+        assert_eq!(setup.get("general", "$version").as_deref(), Some("Divino Afflatu - 1954"));
+        assert_eq!(setup.get("parameters", "lang1"), None);
+
+        setup.set("parameters", "psalmvar", "1");
+        setup.set("parameters", "lang1", "Deutsch");
+        assert_eq!(setup.getsetup_string("parameters"), "$priest='1';;$psalmvar='1';;$largefont='+2 bold red';;$imperata='Commune/C10:Oratio, Commune/C11:Oratio';;$lang1='Deutsch'");
+
+        let prefs = setup.preferences();
+        assert_eq!(prefs.expand, "psalms");
+        assert!(!prefs.accented && prefs.priest && prefs.psalmvar);
+        assert_eq!((prefs.lang1.as_str(), prefs.lang2.as_str()), ("Deutsch", "English"));
+        assert_eq!(prefs.largefont, "+2 bold red");
+        assert_eq!(prefs.smallblack, Preferences::default().smallblack);
+        assert_eq!(prefs.imperatae, ["Commune/C10:Oratio", "Commune/C11:Oratio"]);
+
+        let config = crate::horas::specials::InputConfig::with_preferences(crate::horas::Hora::Laudes, &prefs);
+        assert_eq!(config.version, "Divino Afflatu - 1954");
+        assert_eq!(config.styles.largefont.to_string(), "+2 bold red");
+        assert!(config.preferences.psalmvar);
+    }
+}
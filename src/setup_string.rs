@@ -29,12 +29,19 @@
 //! of the Rust codebase (e.g. a main “engine” that sets up the context).
 
 use lazy_static::lazy_static;
-use std::collections::HashMap;
-use regex::Regex;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use regex::{Regex, RegexBuilder};
 use std::path::PathBuf;
 
 use crate::fileio::do_read;
-use crate::date::monthday; // If you need `monthday(...)` from date.rs
+use crate::date::{monthday, CalendarSystem}; // If you need `monthday(...)` from date.rs
+use crate::regex::fallback_lang;
+use crate::rubrics::{RubricsFamily, RubricsVersion};
 
 /// These enums mirror the Perl constants `RESOLVE_NONE`, `RESOLVE_WHOLEFILE`,
 /// and `RESOLVE_ALL`, controlling how thoroughly we expand `@filename:section`
@@ -53,6 +60,7 @@ pub enum ResolveDirectives {
 /// This struct holds the global variables or context that `setupstring`-like
 /// functions need to replicate the original logic. In the Perl code, these
 /// were stored in global variables. Adapt as needed for your real usage.
+#[derive(Clone)]
 pub struct SetupStringContext {
     /// The “version” string, e.g. "Rubrics 1960", used for rubrical checks
     /// in conditionals (subject: "rubrica", "rubricis").
@@ -61,7 +69,13 @@ pub struct SetupStringContext {
     pub datafolder: PathBuf,
     /// A place to cache the resulting parsed data for specific `(lang, filename)`
     /// plus version-based expansions. The original used `%setupstring_caches_by_version`.
-    pub cache_by_version: HashMap<String, HashMap<String, FileSections>>,
+    /// The files are behind an `Arc`, so that the contexts cloned from this one
+    /// (the workers of `export::batch`) share them.
+    pub cache_by_version: HashMap<String, HashMap<String, Arc<FileSections>>>,
+    /// The files of the cache that included each file, as `inclusion_node()`
+    /// names them ("Commune/C4" by "Sancti/01-21"), so that `invalidate()`
+    /// evicts the sections into which a changed file was expanded.
+    pub included_by: HashMap<String, HashSet<String>>,
 
     /// Additional variables used in conditionals, e.g. `$missa`, `$commune`, `$votive`.
     /// Adjust or fill them in as you integrate with the rest of the code.
@@ -74,6 +88,10 @@ pub struct SetupStringContext {
     /// If needed: store “dayname” array, where dayname[0] is e.g. "Quadp2–4",
     /// dayname[1] might be the “short label,” etc. You can define or skip as needed.
     pub dayname: [String; 2],
+
+    /// The fallback language of the site (`$langfb` in Perl), read for the
+    /// sections missing in other languages before Latin. Empty for none.
+    pub langfb: String,
 }
 
 /// This trait abstracts the file–loading functionality. In production, your
@@ -93,6 +111,144 @@ impl SetupStringProvider for SetupStringContext {
 /// We replicate that logic here.
 pub type FileSections = HashMap<String, String>;
 
+/// The default of `set_inclusion_depth_limit()`.
+pub const DEFAULT_INCLUSION_DEPTH: usize = 10;
+
+static INCLUSION_DEPTH: AtomicUsize = AtomicUsize::new(DEFAULT_INCLUSION_DEPTH);
+
+/// Sets how deeply `@File:Section` inclusions may nest from now on: an
+/// inclusion found in text included `limit` times over is not expanded but
+/// replaced by an `InclusionError::TooDeep`.
+pub fn set_inclusion_depth_limit(limit: usize) {
+    INCLUSION_DEPTH.store(limit, Ordering::Relaxed);
+}
+
+/// The limit set by `set_inclusion_depth_limit()`.
+pub fn inclusion_depth_limit() -> usize {
+    INCLUSION_DEPTH.load(Ordering::Relaxed)
+}
+
+/// An `@File:Section` inclusion that cannot be expanded. In the text of the
+/// section, the inclusion is replaced by the message of the error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InclusionError {
+    /// The inclusion leads back to a section being expanded: the path runs
+    /// from that section to its inclusion, as `File:Section`.
+    Cycle(Vec<String>),
+    /// The inclusions nest deeper than the limit: the path runs from the
+    /// section being expanded to the inclusion not expanded.
+    TooDeep { limit: usize, path: Vec<String> },
+}
+
+impl fmt::Display for InclusionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InclusionError::Cycle(path) => write!(f, "inclusion cycle: {}", path.join(" -> ")),
+            InclusionError::TooDeep { limit, path } => {
+                write!(f, "inclusions nested deeper than {}: {}", limit, path.join(" -> "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for InclusionError {}
+
+/// The inclusions being expanded on a thread, across the files that
+/// `setupstring()` loads for them.
+#[derive(Default)]
+struct Inclusions {
+    /// The sections being expanded, outermost first, as `File:Section`.
+    stack: Vec<String>,
+    /// The errors met, while `inclusion_errors()` collects them.
+    errors: Option<Vec<InclusionError>>,
+}
+
+thread_local! {
+    static INCLUSIONS: RefCell<Inclusions> = RefCell::new(Inclusions::default());
+}
+
+/// The name of `file` in `SetupStringContext::included_by` and in the
+/// nodes of the inclusions.
+fn file_key(file: &str) -> &str {
+    file.strip_suffix(".txt").unwrap_or(file)
+}
+
+/// Records in `included_by` that the files being expanded on this thread
+/// include `fname`.
+fn record_inclusion(included_by: &mut HashMap<String, HashSet<String>>, fname: &str) {
+    let file = file_key(fname);
+    let including: Vec<String> = INCLUSIONS.with(|i| {
+        i.borrow()
+            .stack
+            .iter()
+            .filter_map(|node| node.split_once(':').map(|(f, _)| f))
+            .filter(|f| *f != file)
+            .map(str::to_string)
+            .collect()
+    });
+    if !including.is_empty() {
+        included_by.entry(file.to_string()).or_default().extend(including);
+    }
+}
+
+/// The name of `section` of `file` in the paths of `InclusionError`.
+fn inclusion_node(file: &str, section: &str) -> String {
+    format!("{}:{}", file.strip_suffix(".txt").unwrap_or(file), section)
+}
+
+/// Starts the expansion of `node`, unless it is the section being expanded
+/// (a file loaded for its inclusion). Returns whether it was entered.
+fn enter_expansion(node: &str) -> bool {
+    INCLUSIONS.with(|i| {
+        let mut i = i.borrow_mut();
+        if i.stack.last().map(String::as_str) == Some(node) {
+            return false;
+        }
+        i.stack.push(node.to_string());
+        true
+    })
+}
+
+/// Starts the inclusion of `node` in the sections being expanded, or returns
+/// why it cannot be included.
+fn enter_inclusion(node: &str) -> Result<(), InclusionError> {
+    INCLUSIONS.with(|i| {
+        let mut i = i.borrow_mut();
+        let error = if let Some(start) = i.stack.iter().position(|n| n == node) {
+            let mut path = i.stack[start..].to_vec();
+            path.push(node.to_string());
+            Some(InclusionError::Cycle(path))
+        } else if i.stack.len() > inclusion_depth_limit() {
+            let mut path = i.stack.clone();
+            path.push(node.to_string());
+            Some(InclusionError::TooDeep { limit: inclusion_depth_limit(), path })
+        } else {
+            None
+        };
+        match error {
+            Some(error) => {
+                if let Some(errors) = i.errors.as_mut() {
+                    if !errors.contains(&error) {
+                        errors.push(error.clone());
+                    }
+                }
+                Err(error)
+            }
+            None => {
+                i.stack.push(node.to_string());
+                Ok(())
+            }
+        }
+    })
+}
+
+/// Ends the expansion or inclusion last started.
+fn leave_inclusion() {
+    INCLUSIONS.with(|i| {
+        i.borrow_mut().stack.pop();
+    });
+}
+
 lazy_static! {
     /// We replicate the “stopword_weights” from the Perl code:
     ///   - "sed", "vero" => 1
@@ -161,6 +317,23 @@ lazy_static! {
         "#;
         Regex::new(pat).unwrap()
     };
+
+    /// Matches a conditional `( stopwords? condition scope? )`: the stopwords
+    /// in group 1, the condition in group 2, the scope in group 3.
+    static ref CONDITIONAL_REGEX: Regex = {
+        // The original code calls `conditional_regex()` which references $stopwords_regex, $scope_regex.
+        // We build something akin to `\(\s*($stopwords_regex\b)*(.*?)($scope_regex)?\s*\)`.
+        let stopwords_pattern = r"(?:\b(?:sed|vero|atque|attamen|si|deinde)\b\s*)*";
+        let pat = format!(r#"\(\s*({})?(.*?)({})?\s*\)"#, stopwords_pattern, SCOPE_REGEX.as_str());
+        Regex::new(&pat).unwrap()
+    };
+
+    /// Matches an inclusion line `@File:Section:subs`: the file in group 1,
+    /// the section in group 2, the substitutions in group 3.
+    static ref INCLUSION_REGEX: Regex = Regex::new(r"(?m)^\@([^\n:]+)?(?::([^\n:]+))?(?::(.*))?$").unwrap();
+
+    /// Matches a section header, e.g. `[Rank]`, the name in group 1.
+    static ref SECTION_REGEX: Regex = Regex::new(r"^\s*\[([\pL\pN_ #,:-]+)\]").unwrap();
 }
 
 /// We model the four scope modes from the Perl code:
@@ -178,7 +351,90 @@ enum ScopeDirection {
 /// into a single system or integrate with actual code that returns booleans about rubrical
 /// states.
 
+/// The evaluation of one piece of a condition, `rubrica 1960`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PieceTrace {
+    pub subject: String,
+    /// The value of the subject in the context, e.g. the version.
+    pub value: String,
+    pub predicate: String,
+    /// Whether the predicate matched the value.
+    pub matched: bool,
+    /// Whether the piece follows `nisi`, and holds if the predicate does not match.
+    pub negated: bool,
+}
+
+/// The evaluation of one alternative (of `aut`) of a condition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlternativeTrace {
+    pub text: String,
+    /// The pieces evaluated, up to the first that failed.
+    pub pieces: Vec<PieceTrace>,
+    pub verdict: bool,
+}
+
+/// The evaluation of a condition, as `SetupStringContext::explain_condition()`
+/// returns it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConditionTrace {
+    pub expr: String,
+    /// The alternatives evaluated, up to the first that held.
+    pub alternatives: Vec<AlternativeTrace>,
+    pub verdict: bool,
+}
+
+impl fmt::Display for ConditionTrace {
+    /// `(rubrica 1960) rubrica "Divino Afflatu" ~ 1960: false => false`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({})", self.expr)?;
+        for (i, alternative) in self.alternatives.iter().enumerate() {
+            write!(f, "{}", if i == 0 { " " } else { " | " })?;
+            for (j, piece) in alternative.pieces.iter().enumerate() {
+                let op = match (j, piece.negated) {
+                    (_, true) => "nisi ",
+                    (0, false) => "",
+                    _ => "et ",
+                };
+                write!(
+                    f,
+                    "{}{}{} \"{}\" ~ {}: {}",
+                    if j == 0 { "" } else { " " },
+                    op,
+                    piece.subject,
+                    piece.value,
+                    piece.predicate,
+                    piece.matched
+                )?;
+            }
+        }
+        write!(f, " => {}", self.verdict)
+    }
+}
+
 impl SetupStringContext {
+    /// A context reading the data files under `datafolder` for `version`
+    /// (e.g. "Rubrics 1960 - 1960"), with an empty cache and no day set.
+    pub fn new(datafolder: impl Into<PathBuf>, version: &str) -> Self {
+        SetupStringContext {
+            version: version.to_string(),
+            datafolder: datafolder.into(),
+            cache_by_version: HashMap::new(),
+            included_by: HashMap::new(),
+            missa_number: String::new(),
+            dayofweek: 0,
+            commune: String::new(),
+            votive: String::new(),
+            hora: String::new(),
+            dayname: [String::new(), String::new()],
+            langfb: String::new(),
+        }
+    }
+
+    /// The `version` string as a structured `RubricsVersion`.
+    pub fn rubrics(&self) -> RubricsVersion {
+        RubricsVersion::parse(&self.version)
+    }
+
     /// Evaluate a condition expression (e.g. "rubrica monastica et tempore paschali")
     /// returning whether it is “true” under the current context (`self`).
     ///
@@ -189,47 +445,45 @@ impl SetupStringContext {
     /// - Each piece is either `subject predicate` or just `predicate`.
     /// - Looks up subject in e.g. `tempore`, `rubrica`, etc. If omitted => `tempore`.
     /// - Looks up predicate in e.g. `monastica => sub { ... }` or treat as regex.
-    fn evaluate_condition(&self, expr: &str) -> bool {
-        let mut cond = expr.trim();
+    pub(crate) fn evaluate_condition(&self, expr: &str) -> bool {
+        self.explain_condition(expr).verdict
+    }
+
+    /// Evaluates `expr` as `evaluate_condition()` does, returning with the
+    /// verdict each alternative and piece evaluated: the subject, its value,
+    /// the predicate and whether it matched. Evaluation stops, as there, at
+    /// the first alternative that holds and at the first piece that fails.
+    pub fn explain_condition(&self, expr: &str) -> ConditionTrace {
+        let cond = expr.trim();
+        let mut trace = ConditionTrace { expr: cond.to_string(), alternatives: Vec::new(), verdict: false };
         if cond.is_empty() {
             // The original code returns true for empty conditions.
-            return true;
+            trace.verdict = true;
+            return trace;
         }
 
         // In Perl, code is:
         //   for (split /\baut\b/, $condition) { ... check each subpart ...}
         //   Each subpart => split on /\bet|nisi\b/.
-        //   If we see “nisi,” it toggles negation for everything after that token.
-        // We'll replicate that approach in a more structured manner.
-        let parts = cond.split(|c: char| {
-            let c_lower = c.to_ascii_lowercase();
-            // If "aut" => separate
-            // But we only do a naive approach to handle spaces or weird combos.
-            false
-        });
-
-        // Because the original code is complex, let's do a simpler approach:
-        // We'll do a mini parser that finds “\baut\b” as the top-level separator
-        let subexprs = split_on_word(cond, "aut");
-
         // If any subexpression is “true”, the entire condition is “true”
-        for sub in subexprs {
-            if self.evaluate_sub_condition(&sub) {
-                return true;
+        for sub in split_on_word(cond, "aut") {
+            let alternative = self.explain_sub_condition(&sub);
+            let verdict = alternative.verdict;
+            trace.alternatives.push(alternative);
+            if verdict {
+                trace.verdict = true;
+                break;
             }
         }
-        false
+        trace
     }
 
     /// Evaluate a sub-expression that uses “et” or “nisi” as sub-operators (like logical AND).
-    /// If “nisi” occurs, it inverts subsequent conditions. If all conditions pass, we return true.
-    fn evaluate_sub_condition(&self, expr: &str) -> bool {
-        // We'll do a partial parse:
+    /// If “nisi” occurs, it inverts subsequent conditions. If all conditions pass, it is true.
+    fn explain_sub_condition(&self, expr: &str) -> AlternativeTrace {
         let tokens = split_preserving_operator(expr, &["et", "nisi"]);
+        let mut trace = AlternativeTrace { text: expr.to_string(), pieces: Vec::new(), verdict: true };
         let mut negation = false;
-        // The subexpr is only true if all conditions match (barring negation).
-        // If we see “nisi,” we flip negation for the remainder.
-        let mut is_first_condition = true;
         for t in tokens {
             let t_lower = t.trim().to_ascii_lowercase();
             if t_lower == "et" {
@@ -240,15 +494,57 @@ impl SetupStringContext {
                 continue;
             }
             // Now we evaluate t as a “condition piece.” If subject is missing => “tempore”.
-            let (subject_word, predicate_word) = parse_subject_predicate(&t);
-            let subject_val = self.subject_value(&subject_word);
-            let matched = self.predicate_matches(&predicate_word, &subject_val);
-            if !matched ^ negation {
+            let (subject, predicate) = parse_subject_predicate(&t);
+            let value = self.subject_value(&subject);
+            let matched = self.predicate_matches(&predicate, &value);
+            let passed = matched != negation;
+            trace.pieces.push(PieceTrace { subject, value, predicate, matched, negated: negation });
+            if !passed {
                 // If we fail => subexpr fails
-                return false;
+                trace.verdict = false;
+                break;
             }
         }
-        true
+        trace
+    }
+
+    /// Returns the traces of the conditions of `lines` (a data file) as
+    /// `setupstring()` evaluates them, each with its line (from 1): that of
+    /// a section header and those in parentheses within the lines.
+    pub fn explain_file(&self, lines: &[String]) -> Vec<(usize, ConditionTrace)> {
+        let mut traces = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
+            let text = match SECTION_REGEX.find(line) {
+                Some(header) => {
+                    if let Some(caps) = CONDITIONAL_REGEX.captures(&line[header.end()..]) {
+                        let cond = caps.get(2).map_or("", |m| m.as_str());
+                        traces.push((i + 1, self.explain_condition(cond)));
+                    }
+                    continue;
+                }
+                None => line,
+            };
+            for caps in CONDITIONAL_REGEX.captures_iter(text) {
+                traces.push((i + 1, self.explain_condition(caps.get(2).map_or("", |m| m.as_str()))));
+            }
+        }
+        traces
+    }
+
+    /// Returns `lines` (a data file) with the trace of each of its conditions
+    /// (`explain_file()`) on a line of its own after the line it is on,
+    /// marked `=> `.
+    pub fn annotate_conditions(&self, lines: &[String]) -> String {
+        let traces = self.explain_file(lines);
+        let mut out = String::new();
+        for (i, line) in lines.iter().enumerate() {
+            out.push_str(line);
+            out.push('\n');
+            for (_, trace) in traces.iter().filter(|(n, _)| *n == i + 1) {
+                out.push_str(&format!("=> {}\n", trace));
+            }
+        }
+        out
     }
 
     /// Return the “value” of a subject. This replicates `%subjects` from SetupString.pl:
@@ -286,12 +582,9 @@ impl SetupStringContext {
     fn predicate_matches(&self, predicate: &str, subj_value: &str) -> bool {
         let p_lower = predicate.to_ascii_lowercase();
         match p_lower.as_str() {
-            "tridentina" => subj_value.contains("Trident"),
-            "monastica" => subj_value.contains("Monastic"),
-            "innovata" | "innovatis" => {
-                let re = Regex::new(r"(2020 USA|NewCal)").unwrap();
-                re.is_match(subj_value)
-            }
+            "tridentina" => RubricsVersion::parse(subj_value).is_tridentine(),
+            "monastica" => RubricsVersion::parse(subj_value).monastic,
+            "innovata" | "innovatis" => RubricsVersion::parse(subj_value).is_innovated(),
             "paschali" => {
                 let re = Regex::new(r"(Paschæ|Ascensionis|Octava Pentecostes)").unwrap();
                 re.is_match(subj_value)
@@ -305,9 +598,16 @@ impl SetupStringContext {
             "tertia" => subj_value == "3",
             "longior" => subj_value == "1",
             "brevior" => subj_value == "2",
+            // `^(Divino|1955|196)` in the Perl: the families of Divino
+            // Afflatu, 1955 and 1960, named (a version of no known family is
+            // none of them).
             "summorum pontificum" => {
-                let re = Regex::new(r"^(Divino|1955|196)").unwrap();
-                re.is_match(subj_value)
+                let version = RubricsVersion::parse(subj_value);
+                match version.family {
+                    RubricsFamily::DivinoAfflatu => subj_value.contains("Divino"),
+                    RubricsFamily::Reduced1955 | RubricsFamily::Rubrics1960 => true,
+                    RubricsFamily::Tridentine => false,
+                }
             }
             "feriali" => {
                 let re = Regex::new(r"(feria|vigilia)").unwrap();
@@ -433,6 +733,64 @@ fn is_known_subject(s: &str) -> bool {
     )
 }
 
+/// Checks the syntax of a condition ("rubrica monastica et tempore
+/// paschali"), as `evaluate_condition()` reads it: each alternative of
+/// `aut` is a series of pieces joined by `et`/`nisi`, each piece a
+/// predicate with an optional subject, an unknown predicate being a
+/// pattern. Returns what is wrong with it.
+pub fn check_condition(expr: &str) -> Result<(), String> {
+    if expr.trim().is_empty() {
+        return Err("empty condition".to_string());
+    }
+    for alternative in split_on_word(expr, "aut") {
+        if alternative.is_empty() {
+            return Err(format!("empty alternative in \"{}\"", expr.trim()));
+        }
+        let tokens = split_preserving_operator(&alternative, &["et", "nisi"]);
+        let mut expect_piece = true;
+        for token in &tokens {
+            let is_operator = token.eq_ignore_ascii_case("et") || token.eq_ignore_ascii_case("nisi");
+            if is_operator {
+                if expect_piece && token.eq_ignore_ascii_case("et") {
+                    return Err(format!("\"et\" without a condition before it in \"{}\"", alternative));
+                }
+                expect_piece = true;
+                continue;
+            }
+            let (_, predicate) = parse_subject_predicate(token);
+            if predicate.is_empty() {
+                return Err(format!("\"{}\" has no predicate", token));
+            }
+            if Regex::new(&predicate.to_ascii_lowercase()).is_err() {
+                return Err(format!("the predicate \"{}\" is not a valid pattern", predicate));
+            }
+            expect_piece = false;
+        }
+        if expect_piece {
+            return Err(format!("\"{}\" ends with an operator", alternative));
+        }
+    }
+    Ok(())
+}
+
+/// Checks a conditional in parentheses at the start of `text` ("(sed
+/// rubrica 1960 omittitur)"), as the lines of a data file have them.
+/// Returns `None` if `text` does not start with a conditional (but, say,
+/// with a rubric in parentheses), otherwise the result of
+/// `check_condition()` on it.
+pub fn check_conditional(text: &str) -> Option<Result<(), String>> {
+    let inner = text.trim_start().strip_prefix('(')?;
+    let first = inner.split(|c: char| c.is_whitespace() || c == ')').next().unwrap_or("");
+    if !STOPWORD_WEIGHTS.contains_key(first.to_ascii_lowercase().as_str()) && !is_known_subject(first) {
+        return None;
+    }
+    if !inner.contains(')') {
+        return Some(Err("unclosed conditional".to_string()));
+    }
+    let caps = CONDITIONAL_REGEX.captures(text)?;
+    Some(check_condition(caps.get(2).map_or("", |m| m.as_str())))
+}
+
 //-----------------------------------
 // The Core “SetupString” Logic
 //-----------------------------------
@@ -458,6 +816,7 @@ impl SetupStringContext {
         // We'll incorporate the “lang” dimension as well for uniqueness.
         let version_key = format!("{}::{}", self.version, lang);
         let fullpath = self.make_full_path(lang, fname);
+        record_inclusion(&mut self.included_by, fname);
 
         {
             let cache_for_version = self
@@ -467,7 +826,7 @@ impl SetupStringContext {
 
             if let Some(secs) = cache_for_version.get(fname) {
                 // Already in cache; possibly do partial expansions if needed.
-                let mut cloned = secs.clone();
+                let mut cloned = FileSections::clone(secs);
                 if resolve == ResolveDirectives::All {
                     // We do final expansions for each section. If they were
                     // never resolved, we must do them now. The original code
@@ -483,19 +842,16 @@ impl SetupStringContext {
             }
         }
 
+        let base_sections = self.setupstring_fallback_layer(lang, fname);
+
         // Otherwise, we must read & parse the file. E.g. `$datafolder/$lang/$fname`.
-        let file_contents = match do_read(&fullpath) {
-            Ok(lines) => lines,
-            Err(_) => {
-                // The original code returns '' if no file. We'll do None in Rust.
-                return None;
-            }
+        // A file missing in this language is read from its fallbacks; if it
+        // is missing there too, there is nothing (the Perl code returns '').
+        let parsed_sections = match do_read(&fullpath) {
+            Ok(lines) => self.setupstring_parse_file(&lines, lang, fname),
+            Err(_) if !base_sections.is_empty() => HashMap::new(),
+            Err(_) => return None,
         };
-
-        let base_sections = self.setupstring_fallback_layer(lang, fname, &version_key);
-
-        // Parse the top-level sections from the file we just read
-        let parsed_sections = self.setupstring_parse_file(&file_contents, lang, fname);
         // Merge them with base_sections if needed. The original code for non-Latin or fallback logic:
         let mut final_sections = merge_section_maps(base_sections, parsed_sections);
 
@@ -516,123 +872,106 @@ impl SetupStringContext {
         .entry(version_key.clone())
         .or_insert_with(HashMap::new);
 
-        cache_for_version.insert(fname.to_string(), final_sections.clone());
+        cache_for_version.insert(fname.to_string(), Arc::new(final_sections.clone()));
         Some(final_sections)
     }
 
-    /// Helper that merges the “fallback” layer (Latin or the “monastic” fallback, etc.)
-    /// for the file being loaded. The original code calls setupstring with an empty
-    /// language or partial language if not found. Return the fallback sections or an empty map.
-    fn setupstring_fallback_layer(
-        &mut self,
-        lang: &str,
-        fname: &str,
-        version_key: &str,
-    ) -> FileSections {
-        // The original logic:
-        //   if lang == main::langfb => fallback is "Latin"
-        //   if lang has "-" => remove trailing part
-        //   else => fallback is e.g. "Latin"
-        // In real code, we read from config. We'll do a simplistic approach:
+    /// Returns the language whose sections fill in those missing in `lang`
+    /// (`None` for Latin, the bottom of every chain):
+    ///
+    /// - a regional variant falls back to its language, "English-Newcastle"
+    ///   to "English";
+    /// - a language falls back to `langfb`, unless it is `langfb` (or its
+    ///   base language), which falls back to Latin;
+    /// - without a `langfb`, every language falls back to Latin.
+    pub fn next_fallback(&self, lang: &str) -> Option<String> {
         if lang.eq_ignore_ascii_case("Latin") {
-            // no fallback
-            return HashMap::new();
+            return None;
         }
-        // Example fallback: "Latin"
-        let fallback_lang = "Latin";
-        // Recursively call setupstring with no expansions. We only want the raw data for fallback.
-        if let Some(secs) = self.setupstring(fallback_lang, fname, ResolveDirectives::None) {
-            secs
+        if let Some(base) = fallback_lang(lang) {
+            return Some(base);
+        }
+        let langfb = self.langfb.as_str();
+        let is_langfb = langfb.eq_ignore_ascii_case(lang) || langfb.starts_with(&format!("{}-", lang));
+        if langfb.is_empty() || is_langfb {
+            Some("Latin".to_string())
         } else {
-            HashMap::new()
+            Some(langfb.to_string())
+        }
+    }
+
+    /// Returns the fallback languages of `lang` in the order they are read,
+    /// e.g. "English-Newcastle" → `["English", langfb, "Latin"]`.
+    pub fn fallback_chain(&self, lang: &str) -> Vec<String> {
+        let mut chain: Vec<String> = Vec::new();
+        let mut current = lang.to_string();
+        while let Some(next) = self.next_fallback(&current) {
+            chain.push(next.clone());
+            current = next;
+        }
+        chain
+    }
+
+    /// Returns the sections of `fname` in the fallback languages of `lang`,
+    /// to be overridden by those of `lang` itself.
+    ///
+    /// The next language of the chain is loaded with its own fallback layer
+    /// underneath, so a section comes from the first language of the chain
+    /// that has it: "English-Newcastle", then "English", then `langfb`, then
+    /// Latin. A language without the file is skipped. The fallback layers are
+    /// read without expanding their directives; that is done once on the
+    /// merged sections.
+    fn setupstring_fallback_layer(&mut self, lang: &str, fname: &str) -> FileSections {
+        match self.next_fallback(lang) {
+            Some(fallback) => self.setupstring(&fallback, fname, ResolveDirectives::None).unwrap_or_default(),
+            None => HashMap::new(),
         }
     }
 
     /// The function that parses lines and splits them by `[section]` boundaries.
     /// Also processes conditionals via `process_conditional_lines()`.
-    fn setupstring_parse_file(
+    ///
+    /// The lines of a section are borrowed from `lines` until its text is
+    /// built, in one allocation; a section whose header condition fails is
+    /// kept as `__skip__Name`. A section repeated replaces the one before.
+    pub(crate) fn setupstring_parse_file(
         &self,
         lines: &[String],
         _lang: &str,
         _fname: &str,
     ) -> FileSections {
-        let mut sections: FileSections = HashMap::new();
-        let section_regex = Regex::new(r"^\s*\[([\pL\pN_ #,:-]+)\]").unwrap(); // e.g. [Rank], [Rule], etc.
-
-        let mut current_section = "__preamble".to_string();
-        sections.insert(current_section.clone(), String::new());
-
-        // We also handle optional inline condition: e.g. `[Rank](condition)`.
-        let conditional_regex = self.conditional_regex();
-
+        let mut bodies: Vec<(Cow<'_, str>, Vec<&str>)> = vec![(Cow::Borrowed("__preamble"), Vec::new())];
         for line in lines {
-            if let Some(caps) = section_regex.captures(line) {
-                // We found a new section
-                let new_section_name = caps.get(1).unwrap().as_str().trim().to_string();
-
-                // We check if there’s a trailing conditional
-                // e.g. "[Rank](monastica et tempore paschali)"
-                if let Some(rem) = line.get(caps.get(0).unwrap().end()..) {
-                    // parse any conditional
-                    if let Some(cond_caps) = conditional_regex.captures(rem) {
-                        // If the condition is false, we skip this entire section.
-                        let cond_str = cond_caps.get(2).map(|m| m.as_str()).unwrap_or("").trim();
-                        if !self.evaluate_condition(cond_str) {
-                            // Mark that we skip subsequent lines until next section
-                            // We'll do a "use_this_section = false" approach in the original code. For now, we just skip.
-                            current_section = format!("__skip__{}", new_section_name);
-                            sections.insert(current_section.clone(), String::new());
-                            continue;
-                        }
-                    }
-                }
-
-                // Otherwise, we accept the new section. Clear it or create it.
-                current_section = new_section_name;
-                sections.insert(current_section.clone(), String::new());
-            } else {
-                // Append line to the current section
-                if let Some(ent) = sections.get_mut(&current_section) {
-                    ent.push_str(line);
-                    ent.push('\n');
+            let Some(caps) = SECTION_REGEX.captures(line) else {
+                if let Some((_, body)) = bodies.last_mut() {
+                    body.push(line);
                 }
-            }
+                continue;
+            };
+            let name = caps.get(1).unwrap().as_str().trim();
+            // A trailing conditional, e.g. "[Rank](monastica et tempore paschali)".
+            let rest = &line[caps.get(0).unwrap().end()..];
+            let skipped = CONDITIONAL_REGEX
+                .captures(rest)
+                .is_some_and(|cond| !self.evaluate_condition(cond.get(2).map_or("", |m| m.as_str()).trim()));
+            let name = if skipped { Cow::Owned(format!("__skip__{}", name)) } else { Cow::Borrowed(name) };
+            bodies.push((name, Vec::new()));
         }
 
-        // Now process conditionals in each section’s text
-        for (sec, content) in sections.iter_mut() {
-            let processed = self.process_conditional_lines(content);
-            *content = processed.join("\n") + "\n";
+        let mut sections: FileSections = HashMap::with_capacity(bodies.len());
+        for (name, body) in bodies {
+            sections.insert(name.into_owned(), self.process_conditional_lines(&body));
         }
-
         sections
     }
 
-    /// Returns a compiled regex that matches conditionals in the style `( stopwords? condition scope? )`.
-    fn conditional_regex(&self) -> Regex {
-        // The original code calls `conditional_regex()` which references $stopwords_regex, $scope_regex.
-        // We'll build something akin to `\(\s*($stopwords_regex\b)*(.*?)($scope_regex)?\s*\)`.
-        // Because we have them as lazy_static, we do string interpolation.
-        let stopwords_pattern = {
-            let mut pat = String::new();
-            // We approximate. The original used /o. We'll just do a single build.
-            pat.push_str(r"(\b(?:sed|vero|atque|attamen|si|deinde)\b)*");
-            pat
-        };
-        // Similarly for scope, we approximate. We hold it as a large pattern in `SCOPE_REGEX`.
-        // We have to embed them carefully in a single pattern with capturing groups.
-        let pat = format!(
-            r#"\(\s*({})?(.*?)({})?\s*\)"#,
-            stopwords_pattern,
-            SCOPE_REGEX.as_str(),
-        );
-        Regex::new(&pat).unwrap()
-    }
-
     /// Process conditional lines (the second pass from the original `process_conditional_lines(@lines)`).
     /// We look for embedded conditionals like `(sed monastica ... )`, handle the backscope and forwardscope
     /// logic, remove or keep lines. The logic is quite complicated; here we implement a simplified approach.
-    fn process_conditional_lines(&self, content: &str) -> Vec<String> {
+    ///
+    /// Returns the lines kept, each followed by a newline (a single newline
+    /// if none is).
+    pub(crate) fn process_conditional_lines(&self, lines: &[&str]) -> String {
         // For brevity, we implement partial logic. The original code:
         //   - parse line by line
         //   - if line starts with (conditional), parse & apply backscope
@@ -646,78 +985,53 @@ impl SetupStringContext {
         // and merges lines if needed. This is enough for many DO texts. If you rely heavily on chunk/nest scopes,
         // you’ll have to implement the entire stack logic as in the Perl code.
 
-        let mut output = Vec::new();
-        // We'll do a line-based approach:
-        for line in content.lines() {
-            let trimmed = line.trim_start();
-            let cond_re = self.conditional_regex();
-            let mut current_line = line.to_string();
-
-            // If we find a match like `(sed monastica ... )`, check if it’s true or false.
-            for cap in cond_re.captures_iter(&line) {
-                let stopwords_raw = cap.get(1).map(|m| m.as_str()).unwrap_or("").trim();
-                let condition_str = cap.get(2).map(|m| m.as_str()).unwrap_or("").trim();
-
-                // Evaluate condition
-                let pass = self.evaluate_condition(condition_str);
-                if !pass {
-                    // remove this entire conditional block from the line
-                    let full_match_range = cap.get(0).unwrap().range();
-                    // We just replace it with empty string in the line
-                    current_line.replace_range(full_match_range, "");
-                }
-            }
-            // Now we handle if the line was fully removed:
-            if !current_line.trim().is_empty() {
-                output.push(current_line);
+        let mut output = String::with_capacity(lines.iter().map(|l| l.len() + 1).sum::<usize>() + 1);
+        for line in lines {
+            let line = self.strip_failed_conditionals(line);
+            if !line.trim().is_empty() {
+                output.push_str(&line);
+                output.push('\n');
             }
         }
+        if output.is_empty() {
+            output.push('\n');
+        }
         output
     }
 
-    /// In the original code, this function is `do_inclusion_substitutions(\$text, $substitutions)`,
-    /// applying s/// or line slicing. We replicate a simpler approach that only does
-    /// a few common replacements. Expand or adapt as needed.
-    fn do_inclusion_substitutions(&self, text: &mut String, subs: &str) {
-        // The original code handles e.g. "1-3" to keep lines 1..3, or "s/old/new/g".
-        // We can parse `subs` carefully. For demonstration, we do a naive approach:
-        let tokens = subs.split(':').collect::<Vec<_>>();
-        for t in tokens {
-            // example t: "s/foo/bar/g"
-            if t.starts_with("s/") {
-                let re_split = &t[2..]; // skip "s/"
-                if let Some(idx) = re_split.find('/') {
-                    let pattern = &re_split[..idx];
-                    let remainder = &re_split[idx + 1..];
-                    // remainder might be "bar/g"
-                    let mut flags = "";
-                    let mut replacement = remainder.to_string();
-                    if let Some(idx2) = remainder.rfind('/') {
-                        replacement = remainder[..idx2].to_string();
-                        flags = &remainder[idx2 + 1..];
-                    }
-                    // Perform the actual replacement
-                    let re = Regex::new(pattern).unwrap_or_else(|_| Regex::new(".*").unwrap());
-                    if flags.contains('g') {
-                        *text = re.replace_all(text, replacement.as_str()).to_string();
-                    } else {
-                        *text = re.replace(text, replacement.as_str()).to_string();
-                    }
-                }
-            } else {
-                // line slicing e.g. "1-3"
-                if let Some(dash_idx) = t.find('-') {
-                    let start_str = &t[..dash_idx];
-                    let end_str = &t[dash_idx + 1..];
-                    if let (Ok(snum), Ok(enum_)) = (start_str.parse::<usize>(), end_str.parse::<usize>()) {
-                        // keep lines from snum..enum
-                        let lines = text.lines().collect::<Vec<_>>();
-                        let snippet = &lines[snum.saturating_sub(1)..enum_.min(lines.len())];
-                        *text = snippet.join("\n") + "\n";
-                    }
-                }
+    /// `line` without its conditional blocks `( ... )` whose condition fails,
+    /// borrowed when there are none.
+    ///
+    /// A directive line (`&psalm(109)`, `$rubrica(...)`) is kept whole: its
+    /// parentheses hold the arguments of the directive, not a condition.
+    fn strip_failed_conditionals<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        if !line.contains('(') || line.trim_start().starts_with(['&', '$']) {
+            return Cow::Borrowed(line);
+        }
+        let mut kept = Cow::Borrowed("");
+        let mut end = 0;
+        for cap in CONDITIONAL_REGEX.captures_iter(line) {
+            let condition = cap.get(2).map_or("", |m| m.as_str()).trim();
+            if !self.evaluate_condition(condition) {
+                let block = cap.get(0).unwrap().range();
+                kept.to_mut().push_str(&line[end..block.start]);
+                end = block.end;
             }
         }
+        if end == 0 {
+            return Cow::Borrowed(line);
+        }
+        kept.to_mut().push_str(&line[end..]);
+        kept
+    }
+
+    /// Applies the substitutions of an inclusion (`@File:Section:subs`) to
+    /// the included `text`, in order; see `parse_substitutions()`. As the
+    /// Perl `eval` does, a substitution that does not parse is skipped.
+    pub(crate) fn do_inclusion_substitutions(text: &mut String, subs: &str) {
+        for substitution in scan_substitutions(subs).into_iter().flatten() {
+            substitution.apply(text);
+        }
     }
 
     /// “Include” a section from another file, e.g. `@OtherFile:Section:subs`.
@@ -729,7 +1043,7 @@ impl SetupStringContext {
             // local reference
             if let Some(text) = sections.get(section) {
                 let mut text = text.clone();
-                self.do_inclusion_substitutions(&mut text, subs);
+                Self::do_inclusion_substitutions(&mut text, subs);
                 return text;
             } else {
                 return format!("MISSING local section: {}", section);
@@ -739,7 +1053,7 @@ impl SetupStringContext {
             if let Some(external) = self.setupstring(lang, &format!("{}.txt", ftitle), ResolveDirectives::WholeFile) {
                 if let Some(text) = external.get(section) {
                     let mut text = text.clone();
-                    self.do_inclusion_substitutions(&mut text, subs);
+                    Self::do_inclusion_substitutions(&mut text, subs);
                     return text;
                 } else {
                     return format!("{}:{} is missing!", ftitle, section);
@@ -769,51 +1083,44 @@ impl SetupStringContext {
     }
 
     fn expand_section_inclusions(&mut self, sections: &mut FileSections, key: &str, lang: &str, fname: &str) {
-        let mut b = sections.get_mut(key).cloned();
-
-        if let Some(body) = b.as_mut() {
-            // We look for lines beginning with `@`
-            // e.g. `@SomeFile:Section:substitutions`.
-            // The original code does a loop rewriting text until no more expansions.
-            // We'll do up to 10 passes to avoid infinite recursion.
-            for _pass in 0..10 {
-                let old = body.clone();
-                let new_body = self.expand_inclusions_in_text(&old, sections, lang, fname, key);
-                if new_body == old {
-                    break;
-                }
-                *body = new_body;
+        // We look for lines beginning with `@`, e.g.
+        // `@SomeFile:Section:substitutions`, and expand the included text in
+        // turn; a cycle or a nesting too deep is reported in the text.
+        if let Some(body) = sections.get(key).cloned() {
+            let entered = enter_expansion(&inclusion_node(fname, key));
+            let body = self.expand_inclusions_in_text(&body, sections, lang, fname, key);
+            if entered {
+                leave_inclusion();
             }
-        }
-
-        if let Some(b) = b {
-            sections.insert(key.to_string(), b);
+            sections.insert(key.to_string(), body);
         }
     }
 
-    fn expand_inclusions_in_text(&mut self, text: &str, sections: &FileSections, lang: &str, fname: &str, current_section: &str) -> String {
+    pub(crate) fn expand_inclusions_in_text(&mut self, text: &str, sections: &FileSections, lang: &str, fname: &str, current_section: &str) -> String {
         let mut result = String::new();
-        // The pattern: `^@([^\n:]+)?(?::([^\n:]+?))?(?::(.*))?$`.
-        // that is => optional file, optional section, optional substitutions
-        // We replicate something similar:
-        let re = Regex::new(r"(?m)^\@([^\n:]+)?(?::([^\n:]+))?(?::(.*))?$").unwrap();
+        // `INCLUSION_REGEX`: optional file, optional section, optional substitutions.
         let mut last_end = 0;
-        for cap in re.find_iter(text) {
+        for c in INCLUSION_REGEX.captures_iter(text) {
             // push everything before the match
-            let start = cap.start();
-            let end = cap.end();
+            let whole = c.get(0).unwrap();
+            let (start, end) = (whole.start(), whole.end());
             result.push_str(&text[last_end..start]);
-            let entire = cap.as_str();
-            // parse the sub captures
             // group(1) => file, group(2) => section or current, group(3) => substitutions
-            let c = re.captures(entire).unwrap();
             let ftitle = c.get(1).map(|m| m.as_str()).unwrap_or("");
             let sec = c.get(2).map(|m| m.as_str()).unwrap_or("");
             let sub = c.get(3).map(|m| m.as_str()).unwrap_or("");
             let section_name = if sec.is_empty() { current_section } else { sec };
-            // If file is empty => self reference
-            let included = self.get_loadtime_inclusion(sections, lang, ftitle, section_name, sub);
-            result.push_str(&included);
+            let node = inclusion_node(if ftitle.is_empty() { fname } else { ftitle }, section_name);
+            match enter_inclusion(&node) {
+                Ok(()) => {
+                    // If file is empty => self reference
+                    let included = self.get_loadtime_inclusion(sections, lang, ftitle, section_name, sub);
+                    let included = self.expand_inclusions_in_text(&included, sections, lang, fname, current_section);
+                    leave_inclusion();
+                    result.push_str(&included);
+                }
+                Err(error) => result.push_str(&error.to_string()),
+            }
             last_end = end;
         }
         // push remainder
@@ -823,10 +1130,73 @@ impl SetupStringContext {
 
     /// Called to expand references in the `__preamble` only (i.e. “whole file” expansions).
     fn resolve_inclusions_in_preamble(&mut self, preamble: &mut String, lang: &str, fname: &str) {
-        // This is essentially the same logic as above but we only do it for the preamble once.
-        let old = preamble.clone();
-        let new_preamble = self.expand_inclusions_in_text(&old, &HashMap::new(), lang, fname, "__preamble");
-        *preamble = new_preamble;
+        let entered = enter_expansion(&inclusion_node(fname, "__preamble"));
+        *preamble = self.expand_inclusions_in_text(preamble, &HashMap::new(), lang, fname, "__preamble");
+        if entered {
+            leave_inclusion();
+        }
+    }
+
+    /// Loads `fname` in `lang` afresh, as `setupstring()` with all its
+    /// inclusions, and returns the cycles and nestings too deep met on the
+    /// way, in this file or in those it includes.
+    pub fn inclusion_errors(&mut self, lang: &str, fname: &str) -> Vec<InclusionError> {
+        let cache = std::mem::take(&mut self.cache_by_version);
+        INCLUSIONS.with(|i| i.borrow_mut().errors = Some(Vec::new()));
+        self.setupstring(lang, fname, ResolveDirectives::All);
+        let errors = INCLUSIONS.with(|i| i.borrow_mut().errors.take()).unwrap_or_default();
+        self.cache_by_version = cache;
+        errors
+    }
+
+    /// Evicts from the cache the file at `path`, in the data folder
+    /// ("…/horas/Latin/Sancti/01-21.txt") or relative to it
+    /// ("Latin/Sancti/01-21.txt"), after it changed: its sections in every
+    /// version and language, since the languages fall back on each other,
+    /// and those of the files into which it was included. Returns the number
+    /// of entries evicted.
+    ///
+    /// The files evicted are dropped from `included_by` as includers: they
+    /// record their inclusions again when they are read again, so that a
+    /// file which no longer includes another is not evicted with it.
+    pub fn invalidate(&mut self, path: &str) -> usize {
+        let path = path.replace('\\', "/");
+        let datafolder = self.datafolder.to_string_lossy().replace('\\', "/");
+        let relative = path
+            .strip_prefix(datafolder.trim_end_matches('/'))
+            .filter(|rest| rest.starts_with('/'))
+            .unwrap_or(&path)
+            .trim_start_matches("./")
+            .trim_start_matches('/');
+        let Some((_, fname)) = relative.split_once('/') else {
+            return 0;
+        };
+
+        let mut evicted = 0;
+        let mut pending = vec![file_key(fname).to_string()];
+        let mut seen: HashSet<String> = pending.iter().cloned().collect();
+        while let Some(file) = pending.pop() {
+            for files in self.cache_by_version.values_mut() {
+                let before = files.len();
+                files.retain(|name, _| file_key(name) != file);
+                evicted += before - files.len();
+            }
+            if let Some(including) = self.included_by.get(&file) {
+                pending.extend(including.iter().filter(|f| seen.insert(f.to_string())).cloned());
+            }
+        }
+        self.included_by.retain(|_, including| {
+            including.retain(|f| !seen.contains(f));
+            !including.is_empty()
+        });
+        evicted
+    }
+
+    /// Finds `fname` for `lang` in the data source (see `resolve_file()`),
+    /// with the fallback language and the rubrical version of the context.
+    pub fn resolve_file(&self, lang: &str, fname: &str) -> ResolvedPath {
+        let datafolder = self.datafolder.to_string_lossy();
+        resolve_file(&datafolder, &self.langfb, lang, &self.version, fname, &crate::fileio::file_exists)
     }
 
     /// Helper to build the full path `<datafolder>/<lang>/<fname>`.
@@ -851,6 +1221,231 @@ fn merge_section_maps(base: FileSections, mut newsec: FileSections) -> FileSecti
     result
 }
 
+lazy_static! {
+    /// One substitution of an inclusion, as the Perl code scans them: `s/pattern/replacement/flags`
+    /// (`/` and `\\` escaped by a backslash) or a line selection `n` or `n-m`.
+    static ref SUBSTITUTION_REGEX: Regex =
+        Regex::new(r"s/((?:[^/\\]|\\.)*)/((?:[^/\\]|\\.)*)/([gism]*)|(\d+)(?:-(\d+))?").unwrap();
+}
+
+/// One step of the substitutions of an inclusion.
+#[derive(Debug, Clone)]
+pub enum Substitution {
+    /// Keeps the lines `first` to `last` (from 1, inclusive).
+    Lines { first: usize, last: usize },
+    /// `s/pattern/replacement/flags`, the replacement in the syntax of
+    /// `Regex::replace` (`${1}` for `$1`); only the first match unless `g`.
+    Replace { regex: Regex, replacement: String, global: bool },
+}
+
+impl Substitution {
+    /// Applies the substitution to `text`.
+    pub fn apply(&self, text: &mut String) {
+        match self {
+            Substitution::Lines { first, last } => {
+                let lines: Vec<&str> = text.split('\n').collect();
+                let start = (first - 1).min(lines.len());
+                let end = (*last).clamp(start, lines.len());
+                *text = lines[start..end].join("\n") + "\n";
+            }
+            Substitution::Replace { regex, replacement, global } => {
+                let replaced = if *global {
+                    regex.replace_all(text, replacement.as_str())
+                } else {
+                    regex.replace(text, replacement.as_str())
+                };
+                *text = replaced.into_owned();
+            }
+        }
+    }
+}
+
+/// A substitution of an inclusion that cannot be applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubstitutionError {
+    /// The text of the substitution, e.g. `s/N(/Ioannes/`.
+    pub spec: String,
+    pub message: String,
+}
+
+impl fmt::Display for SubstitutionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.spec, self.message)
+    }
+}
+
+/// Parses the substitutions of an inclusion, the part after the section in
+/// `@Commune/C2:Oratio:s/N\./Ioannes/g:1-4`, into the steps applied in
+/// order:
+///
+/// - `s/pattern/replacement/flags` replaces the matches of the pattern,
+///   with the flags `g` (all matches), `i` (ignoring case), `s` and `m`;
+/// - `n-m` keeps the lines `n` to `m`, `n` the line `n` only.
+///
+/// The steps are separated by `:`. Fails at the first step that is not one
+/// of these or whose pattern is not valid (a back-reference such as `\1`
+/// in the pattern is not supported).
+pub fn parse_substitutions(subs: &str) -> Result<Vec<Substitution>, SubstitutionError> {
+    let mut steps = Vec::new();
+    let mut last = 0;
+    for m in SUBSTITUTION_REGEX.find_iter(subs) {
+        let gap = subs[last..m.start()].trim_matches(|c: char| c == ':' || c.is_whitespace());
+        if !gap.is_empty() {
+            return Err(SubstitutionError { spec: gap.to_string(), message: "not a substitution".to_string() });
+        }
+        last = m.end();
+    }
+    let rest = subs[last..].trim_matches(|c: char| c == ':' || c.is_whitespace());
+    if !rest.is_empty() {
+        return Err(SubstitutionError { spec: rest.to_string(), message: "not a substitution".to_string() });
+    }
+    for step in scan_substitutions(subs) {
+        steps.push(step?);
+    }
+    Ok(steps)
+}
+
+/// Scans `subs` as the Perl code does, every step that matches the syntax
+/// of a substitution, whatever is between them.
+fn scan_substitutions(subs: &str) -> Vec<Result<Substitution, SubstitutionError>> {
+    SUBSTITUTION_REGEX
+        .captures_iter(subs)
+        .map(|caps| {
+            let spec = caps[0].to_string();
+            if let Some(first) = caps.get(4) {
+                let first: usize = first.as_str().parse().unwrap_or(0);
+                let last = caps.get(5).map_or(first, |m| m.as_str().parse().unwrap_or(0));
+                if first == 0 || last < first {
+                    return Err(SubstitutionError { spec, message: "invalid line range".to_string() });
+                }
+                return Ok(Substitution::Lines { first, last });
+            }
+            let flags = &caps[3];
+            let mut builder = RegexBuilder::new(&unescape_slashes(&caps[1]));
+            builder.case_insensitive(flags.contains('i'));
+            builder.dot_matches_new_line(flags.contains('s'));
+            builder.multi_line(flags.contains('m'));
+            match builder.build() {
+                Ok(regex) => Ok(Substitution::Replace {
+                    regex,
+                    replacement: perl_replacement(&caps[2]),
+                    global: flags.contains('g'),
+                }),
+                Err(e) => Err(SubstitutionError { spec, message: e.to_string() }),
+            }
+        })
+        .collect()
+}
+
+/// `\/` in a pattern is a plain `/`.
+fn unescape_slashes(pattern: &str) -> String {
+    pattern.replace("\\/", "/")
+}
+
+/// Converts the replacement of a Perl `s///` into that of `Regex::replace`:
+/// `$1` becomes `${1}`, `$&` `${0}`, and backslash escapes their character
+/// (`\n` a new line).
+fn perl_replacement(replacement: &str) -> String {
+    let mut out = String::with_capacity(replacement.len());
+    let mut chars = replacement.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some('$') => out.push_str("$$"),
+                Some(other) => out.push(other),
+                None => out.push('\\'),
+            },
+            '$' => match chars.peek() {
+                Some('&') => {
+                    chars.next();
+                    out.push_str("${0}");
+                }
+                Some(d) if d.is_ascii_digit() => {
+                    let mut group = String::new();
+                    while let Some(d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                        group.push(*d);
+                        chars.next();
+                    }
+                    out.push_str(&format!("${{{}}}", group));
+                }
+                Some('{') => out.push('$'),
+                _ => out.push_str("$$"),
+            },
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// A section header of a data file: `[Name]`, or `[Name] (condition)` for a
+/// section read only when the condition holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionHeader {
+    pub name: String,
+    /// The text between the parentheses, stopwords and scope included.
+    pub condition: Option<String>,
+}
+
+/// Returns the section headers of the lines of a data file, in the order of
+/// the file, for `write_sections()`.
+pub fn section_headers(lines: &[String]) -> Vec<SectionHeader> {
+    lines
+        .iter()
+        .filter_map(|line| {
+            let caps = SECTION_REGEX.captures(line)?;
+            let rest = line[caps.get(0)?.end()..].trim();
+            let condition = rest.strip_prefix('(').and_then(|r| r.strip_suffix(')')).map(|c| c.trim().to_string());
+            Some(SectionHeader { name: caps[1].trim().to_string(), condition })
+        })
+        .collect()
+}
+
+/// Writes `sections` back in the format of the data files: the preamble,
+/// then each section under its `[Name]` header, separated by blank lines.
+///
+/// The sections come in the order of `headers` (the headers of the file
+/// they were read from, see `section_headers()`), those not among them
+/// after, by name. A section dropped by the condition of its header
+/// (`__skip__Name`) is written back under that header with its condition,
+/// and so is a section whose only header is conditional; conditional lines
+/// within the sections are already resolved and cannot be restored.
+pub fn write_sections(sections: &FileSections, headers: &[SectionHeader]) -> String {
+    let mut chunks: Vec<String> = Vec::new();
+    if let Some(preamble) = sections.get("__preamble").filter(|p| !p.trim().is_empty()) {
+        chunks.push(preamble.trim_end().to_string());
+    }
+    let mut written: Vec<String> = Vec::new();
+    let mut write = |key: &str, header: String, written: &mut Vec<String>| {
+        if let Some(text) = sections.get(key).filter(|_| !written.iter().any(|w| w == key)) {
+            let body = text.trim_end();
+            chunks.push(if body.is_empty() { header } else { format!("{}\n{}", header, body) });
+            written.push(key.to_string());
+        }
+    };
+    for header in headers {
+        let skipped = format!("__skip__{}", header.name);
+        let single = headers.iter().filter(|h| h.name == header.name).count() == 1;
+        match &header.condition {
+            Some(cond) if sections.contains_key(&skipped) => {
+                write(&skipped, format!("[{}] ({})", header.name, cond), &mut written);
+            }
+            Some(cond) if single => write(&header.name, format!("[{}] ({})", header.name, cond), &mut written),
+            _ => write(&header.name, format!("[{}]", header.name), &mut written),
+        }
+    }
+    let mut rest: Vec<&String> = sections
+        .keys()
+        .filter(|k| *k != "__preamble" && !k.starts_with("__skip__") && !written.contains(k))
+        .collect();
+    rest.sort();
+    for key in rest {
+        write(key, format!("[{}]", key), &mut written);
+    }
+    chunks.join("\n\n") + "\n"
+}
+
 impl SetupStringContext {
     /// This replicates `officestring($lang, $fname, $flag)`.
     /// In the original code, it loads a file from Tempora or something,
@@ -882,8 +1477,8 @@ impl SetupStringContext {
         // If it is e.g. "Tempora/Epi1-0" or "Tempora/Pent...", we might do extra merges from partial files:
         // The function `monthday(day, month, year, (version=1960?), flag)` => returns something like "081-1".
         // We'll do a dummy call: in real code, we rely on crate::date::monthday(...) logic.
-        let modern = self.version.contains("1960"); // or some check
-        let md = monthday(day, month, year, modern, flag);
+        let modern = self.rubrics().is_1960();
+        let md = monthday(day, month, year, modern, flag, CalendarSystem::Gregorian);
         if md.is_empty() {
             return Some(base_opt);
         }
@@ -914,37 +1509,85 @@ pub fn setupstring(
         version: "Rubrics 1960".to_string(),
         datafolder: PathBuf::from("data"), // adjust as needed
         cache_by_version: HashMap::new(),
+        included_by: HashMap::new(),
         missa_number: "".to_string(),
         dayofweek: 0,
         commune: "".to_string(),
         votive: "".to_string(),
         hora: "".to_string(),
         dayname: [String::from("DayName1"), String::from("DayName2")],
+        langfb: String::new(),
     };
 
     context.setupstring(lang, fname, resolve)
 }
 
-/// Returns a file path according to the fallback logic:
-/// 1. If a file exists at `{datafolder}{redirect}/{lang}/{filename}`, return that path.
-/// 2. If `lang` contains a dash, remove the last dash–component (recursively) and try again.
-/// 3. Otherwise, if a file exists at `{datafolder}{redirect}/{main_langfb}/{filename}`, return that.
-/// 4. Else, return `{datafolder}{redirect}/Latin/{filename}`.
+/// The layer of the search of `resolve_file()` where a file was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathLayer {
+    /// The requested language itself.
+    Language,
+    /// The base language of a regional variant ("En" for "En-UK").
+    RegionalFallback,
+    /// The fallback language of the site (`$langfb`).
+    MainFallback,
+    /// Latin.
+    Latin,
+    /// Found nowhere: the path is the Latin one, which does not exist.
+    Missing,
+}
+
+/// A data file resolved by `resolve_file()`: its path, the language folder
+/// it was found in and the layer of the search that matched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedPath {
+    pub path: String,
+    /// The folder of the file under the data folder, e.g. "English" or
+    /// "Latin-1960".
+    pub lang: String,
+    pub layer: PathLayer,
+    /// Whether the file came from the folder of the rubrical version of
+    /// its language (e.g. "Latin-1960" rather than "Latin").
+    pub version_specific: bool,
+}
+
+/// The suffix of the version-specific folders of a rubrical version: the
+/// year closing its name ("Rubrics 1960 - 1960" gives "1960", "Tridentine -
+/// 1570" gives "1570"), if any.
+fn version_folder_suffix(version: &str) -> Option<&str> {
+    version
+        .split_whitespace()
+        .last()
+        .filter(|tag| !tag.is_empty() && tag.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Finds `filename` in the data, trying in order:
+///
+/// 1. `{datafolder}{redirect}/{lang}/{filename}`;
+/// 2. the base languages of `lang`, removing one dash–component at a time
+///    ("En-UK" → "En");
+/// 3. `main_langfb`, the fallback language of the site;
+/// 4. Latin.
+///
+/// In each language the folder of the rubrical `version` comes first, e.g.
+/// `Latin-1960/` before `Latin/` under the 1960 rubrics. If the file exists
+/// nowhere, the Latin path is returned with `PathLayer::Missing`.
 ///
 /// The `redirect` is set to "/../horas" if:
 ///   - `datafolder` contains "missa" (case–insensitive) AND
 ///   - `filename` matches the pattern "C1[a-z]?" (here implemented simply as containing "C1").
-///  
+///
 /// The `file_exists` parameter is a closure that, given a path, returns whether that file exists.
-pub fn checkfile<F: Fn(&str) -> bool>(
+pub fn resolve_file<F: Fn(&str) -> bool>(
     datafolder: &str,
     main_langfb: &str,
     lang: &str,
+    version: &str,
     filename: &str,
     file_exists: &F,
-) -> String {
-    use crate::regex::{file_matches_c1, ci_contains, fallback_lang};
-    
+) -> ResolvedPath {
+    use crate::regex::{ci_contains, file_matches_c1};
+
     // Determine the redirect part.
     let redirect = if ci_contains(datafolder, "missa") && file_matches_c1(filename) {
         "/../horas"
@@ -952,32 +1595,51 @@ pub fn checkfile<F: Fn(&str) -> bool>(
         ""
     };
 
-    // Build the candidate path for the given language.
-    let candidate = format!("{datafolder}{redirect}/{lang}/{filename}");
-    if file_exists(&candidate) {
-        return candidate;
+    let mut layers = vec![(lang.to_string(), PathLayer::Language)];
+    let mut current = lang.to_string();
+    while let Some(base) = fallback_lang(&current) {
+        layers.push((base.clone(), PathLayer::RegionalFallback));
+        current = base;
     }
-
-    // If lang contains a dash, remove the last dash–component and try recursively.
-    if lang.contains('-') {
-        if let Some(new_lang) = fallback_lang(lang) {
-            return checkfile(datafolder, main_langfb, &new_lang, filename, &file_exists);
+    if !main_langfb.is_empty() {
+        layers.push((main_langfb.to_string(), PathLayer::MainFallback));
+    }
+    layers.push(("Latin".to_string(), PathLayer::Latin));
+
+    let suffix = version_folder_suffix(version);
+    for (folder, layer) in &layers {
+        let versioned = suffix.map(|suffix| (format!("{}-{}", folder, suffix), true));
+        for (folder, version_specific) in versioned.into_iter().chain([(folder.clone(), false)]) {
+            let path = format!("{datafolder}{redirect}/{folder}/{filename}");
+            if file_exists(&path) {
+                return ResolvedPath { path, lang: folder, layer: *layer, version_specific };
+            }
         }
     }
-
-    // Try the main fallback language.
-    let candidate_main = format!("{datafolder}{redirect}/{main_langfb}/{filename}");
-    if file_exists(&candidate_main) {
-        return candidate_main;
+    ResolvedPath {
+        path: format!("{datafolder}{redirect}/Latin/{filename}"),
+        lang: "Latin".to_string(),
+        layer: PathLayer::Missing,
+        version_specific: false,
     }
+}
 
-    // Otherwise, default to Latin.
-    format!("{datafolder}{redirect}/Latin/{filename}")
+/// Returns a file path according to the fallback logic of `resolve_file()`,
+/// without a rubrical version, as `checkfile($lang, $file)` does in Perl.
+pub fn checkfile<F: Fn(&str) -> bool>(
+    datafolder: &str,
+    main_langfb: &str,
+    lang: &str,
+    filename: &str,
+    file_exists: &F,
+) -> String {
+    resolve_file(datafolder, main_langfb, lang, "", filename, file_exists).path
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::TempRoot;
 
     /// A dummy file existence function.
     fn dummy_file_exists(path: &str) -> bool {
@@ -986,7 +1648,6 @@ mod tests {
             "/data/missa/English/sample.txt",
             "/data/missa/Latin/sample.txt",
             "/data/missa/En/sample.txt",
-            "/data/missa/En-UK/sample.txt",
         ];
         existing.contains(&path)
     }
@@ -1004,6 +1665,10 @@ mod tests {
         let path = checkfile("/data/missa", "English", "En-UK", "sample.txt", &dummy_file_exists);
         // Our dummy_file_exists returns true for "/data/missa/En/sample.txt".
         assert_eq!(path, "/data/missa/En/sample.txt");
+        // A file of the regional variant itself comes before that of its base.
+        let regional = |p: &str| dummy_file_exists(p) || p == "/data/missa/En-UK/sample.txt";
+        let path = checkfile("/data/missa", "English", "En-UK", "sample.txt", &regional);
+        assert_eq!(path, "/data/missa/En-UK/sample.txt");
     }
 
     #[test]
@@ -1022,12 +1687,259 @@ mod tests {
         assert_eq!(path, "/data/missa/Latin/sample.txt");
     }
 
+    #[test]
+    fn test_fallback_chain_merge_order() {
+        let root = TempRoot::new("setupstring-fallback");
+        let write = |lang: &str, content: &str| root.write(&format!("{}/Psalterium/Test.txt", lang), content);
+        write("Latin", "[A]\nLatin A\n\n[B]\nLatin B\n\n[C]\nLatin C\n\n[D]\nLatin D\n");
+        write("Deutsch", "[C]\nDeutsch C\n\n[D]\nDeutsch D\n");
+        write("English-Newcastle", "[D]\nNewcastle D\n");
+        let mut ctx = SetupStringContext {
+            langfb: "Deutsch".to_string(),
+            ..SetupStringContext::new(&*root, "Divino Afflatu")
+        };
+        assert_eq!(ctx.fallback_chain("English-Newcastle"), vec!["English", "Deutsch", "Latin"]);
+        assert_eq!(ctx.fallback_chain("Deutsch"), vec!["Latin"]);
+        assert!(ctx.fallback_chain("Latin").is_empty());
+
+        // "English" has no file of its own and is skipped.
+        let secs = ctx.setupstring("English-Newcastle", "Psalterium/Test.txt", ResolveDirectives::All).unwrap();
+        assert_eq!(secs["A"].trim(), "Latin A");
+        assert_eq!(secs["C"].trim(), "Deutsch C");
+        assert_eq!(secs["D"].trim(), "Newcastle D");
+        let secs = ctx.setupstring("English", "Psalterium/Test.txt", ResolveDirectives::All).unwrap();
+        assert_eq!(secs["D"].trim(), "Deutsch D");
+        assert!(ctx.setupstring("English", "Psalterium/Missing.txt", ResolveDirectives::All).is_none());
+    }
+
     #[test]
     fn test_checkfile_redirect() {
         // Test that if datafolder contains "missa" and filename contains "C1",
         // the redirect "/../horas" is applied.
-        let custom_exists = |p: &str| p == "/data/missa/../horas/English/sample.txt";
+        let custom_exists = |p: &str| p == "/data/missa/../horas/English/C1_sample.txt";
         let path = checkfile("/data/missa", "English", "English", "C1_sample.txt", &custom_exists);
-        assert_eq!(path, "/data/missa/../horas/English/sample.txt");
+        assert_eq!(path, "/data/missa/../horas/English/C1_sample.txt");
+        // Other files of the Mass stay in its own folder.
+        let path = checkfile("/data/missa", "English", "English", "sample.txt", &dummy_file_exists);
+        assert_eq!(path, "/data/missa/English/sample.txt");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_resolve_file_layers() {
+        let exists = |p: &str| {
+            ["/data/horas/Latin/Psalterium/Psalmi.txt", "/data/horas/Latin-1960/Psalterium/Psalmi.txt", "/data/horas/English/Psalterium/Psalmi.txt"]
+                .contains(&p)
+        };
+        let found = resolve_file("/data/horas", "Deutsch", "English-Newcastle", "Divino Afflatu", "Psalterium/Psalmi.txt", &exists);
+        assert_eq!((found.lang.as_str(), found.layer, found.version_specific), ("English", PathLayer::RegionalFallback, false));
+        let found = resolve_file("/data/horas", "Deutsch", "Magyar", "Rubrics 1960 - 1960", "Psalterium/Psalmi.txt", &exists);
+        assert_eq!(found.path, "/data/horas/Latin-1960/Psalterium/Psalmi.txt");
+        assert_eq!((found.layer, found.version_specific), (PathLayer::Latin, true));
+        let found = resolve_file("/data/horas", "Deutsch", "Magyar", "Rubrics 1960 - 1960", "Psalterium/Missing.txt", &exists);
+        assert_eq!((found.path.as_str(), found.layer), ("/data/horas/Latin/Psalterium/Missing.txt", PathLayer::Missing));
+        // The commons "C1…" of the Mass are read from the Office.
+        let exists = |p: &str| p == "/data/missa/../horas/English/Commune/C1.txt";
+        let found = resolve_file("/data/missa", "English", "English", "Divino Afflatu", "Commune/C1.txt", &exists);
+        assert_eq!((found.path.as_str(), found.layer), ("/data/missa/../horas/English/Commune/C1.txt", PathLayer::Language));
+    }
+
+    #[test]
+    fn test_summorum_pontificum() {
+        let ctx = SetupStringContext::new(PathBuf::new(), "");
+        let matches = |version: &str| ctx.predicate_matches("summorum pontificum", version);
+        assert!(!matches("Tridentine - 1570"));
+        assert!(!matches("Monastic Tridentinum 1617"));
+        assert!(matches("Divino Afflatu - 1954"));
+        assert!(matches("Reduced - 1955"));
+        assert!(matches("Rubrics 1960 - 1960"));
+        assert!(matches("Monastic - 1963"));
+        assert!(!matches("Ordo Romanus"));
+    }
+
+    #[test]
+    fn test_check_conditional() {
+        assert_eq!(check_condition("rubrica monastica et tempore paschali nisi rubrica 1960"), Ok(()));
+        assert!(check_condition("rubrica monastica aut").is_err());
+        assert!(check_condition("et rubrica 1960").is_err());
+        assert!(check_condition("rubrica 19[60").is_err());
+        assert_eq!(check_conditional("(sed rubrica 1960 omittitur)"), Some(Ok(())));
+        assert_eq!(check_conditional("(Hic genuflectitur)"), None);
+        assert!(matches!(check_conditional("(sed rubrica 1960"), Some(Err(_))));
+        let caps = CONDITIONAL_REGEX.captures("(sed rubrica monastica dicitur)").unwrap();
+        assert_eq!((caps[1].trim(), caps[2].trim(), caps[3].trim()), ("sed", "rubrica monastica", "dicitur"));
+    }
+
+    #[test]
+    fn test_process_conditional_lines_directives() {
+        let ctx = SetupStringContext::new(PathBuf::new(), "Divino Afflatu");
+        let lines = ["&psalm(109)", "$rubrica(Psalmi)", "Dixit Dóminus (sed rubrica 1960 omittitur)"];
+        assert_eq!(ctx.process_conditional_lines(&lines), "&psalm(109)\n$rubrica(Psalmi)\nDixit Dóminus \n");
+    }
+
+    #[test]
+    fn test_write_sections_round_trip() {
+        let ctx = SetupStringContext::new(PathBuf::new(), "Divino Afflatu");
+        let text = "@Commune/C4\n\n[Officium]\nS. Pauli\n\n[Rank] (rubrica monastica)\nDuplex;;5\n\n[Oratio]\nDeus, qui\n$Per Dominum\n";
+        let lines: Vec<String> = text.lines().map(str::to_string).collect();
+        let headers = section_headers(&lines);
+        assert_eq!(headers[1], SectionHeader { name: "Rank".to_string(), condition: Some("rubrica monastica".to_string()) });
+        let sections = ctx.setupstring_parse_file(&lines, "Latin", "Sancti/01-25.txt");
+        let written = write_sections(&sections, &headers);
+        assert_eq!(written, text);
+        let reread: Vec<String> = written.lines().map(str::to_string).collect();
+        assert_eq!(ctx.setupstring_parse_file(&reread, "Latin", "Sancti/01-25.txt"), sections);
+
+        // A section added by an editor comes after those of the file.
+        let mut edited = sections.clone();
+        edited.insert("Lectio1".to_string(), "Lectio\n".to_string());
+        assert!(write_sections(&edited, &headers).ends_with("$Per Dominum\n\n[Lectio1]\nLectio\n"));
+
+        // Two failing conditionals on a line, and a line of a failing one only.
+        let lines: Vec<String> = [
+            "[Rank] (rubrica monastica)",
+            "Duplex;;5",
+            "[Oratio]",
+            "Deus (rubrica monastica), qui (rubrica cisterciensis)nos",
+            "(rubrica monastica)",
+            "$Per Dominum",
+        ]
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+        let sections = ctx.setupstring_parse_file(&lines, "Latin", "Sancti/01-25.txt");
+        assert_eq!(sections["__skip__Rank"], "Duplex;;5\n");
+        assert_eq!(sections["Oratio"], "Deus , qui nos\n$Per Dominum\n");
+        assert_eq!(sections["__preamble"], "\n");
+    }
+
+    #[test]
+    fn test_explain_condition() {
+        let ctx = SetupStringContext::new(PathBuf::new(), "Divino Afflatu - 1954");
+        let trace = ctx.explain_condition("rubrica 1960 aut rubrica divino nisi rubrica monastica");
+        assert!(trace.verdict);
+        assert_eq!(trace.alternatives.len(), 2);
+        assert_eq!(trace.alternatives[0].pieces[0].value, "Divino Afflatu - 1954");
+        assert!(!trace.alternatives[0].verdict);
+        assert_eq!(trace.alternatives[1].pieces.len(), 2);
+        assert!(trace.alternatives[1].pieces[1].negated && !trace.alternatives[1].pieces[1].matched);
+        assert_eq!(
+            ctx.explain_condition("rubrica 1960").to_string(),
+            "(rubrica 1960) rubrica \"Divino Afflatu - 1954\" ~ 1960: false => false"
+        );
+
+        let lines: Vec<String> =
+            ["[Rank] (rubrica 1960)", "Duplex", "V. Ora pro nobis.", "(sed rubrica divino omittitur)"]
+                .iter()
+                .map(|l| l.to_string())
+                .collect();
+        let traces = ctx.explain_file(&lines);
+        assert_eq!(traces.iter().map(|(n, t)| (*n, t.verdict)).collect::<Vec<_>>(), [(1, false), (4, true)]);
+        let annotated = ctx.annotate_conditions(&lines);
+        assert!(annotated.starts_with("[Rank] (rubrica 1960)\n=> (rubrica 1960) rubrica"));
+        assert!(annotated.ends_with("(sed rubrica divino omittitur)\n=> (rubrica divino) rubrica \"Divino Afflatu - 1954\" ~ divino: true => true\n"));
+    }
+
+    #[test]
+    fn test_inclusion_cycles() {
+        let root = TempRoot::new("setupstring-cycles");
+        let write = |name: &str, content: &str| root.write(&format!("Latin/Psalterium/{}.txt", name), content);
+        write("A", "[Oratio]\n@Psalterium/B\n\n[Lectio]\nIncipit\n@:Lectio\n\n[Capitulum]\n@Psalterium/B:Capitulum\n\n[Versus]\n@Psalterium/C1:Versus\n");
+        write("B", "[Oratio]\n@Psalterium/A\n\n[Capitulum]\n@Psalterium/B:Textus\n\n[Textus]\nFratres\n");
+        for n in 1..=12 {
+            write(&format!("C{}", n), &format!("[Versus]\nC{}\n@Psalterium/C{}\n", n, n + 1));
+        }
+        let mut ctx = SetupStringContext {
+            langfb: "Latin".to_string(),
+            ..SetupStringContext::new(&*root, "Divino Afflatu")
+        };
+        let sections = ctx.setupstring("Latin", "Psalterium/A.txt", ResolveDirectives::All).unwrap();
+        assert_eq!(sections["Capitulum"].trim(), "Fratres");
+        assert_eq!(sections["Oratio"].trim(), "inclusion cycle: Psalterium/A:Oratio -> Psalterium/B:Oratio -> Psalterium/A:Oratio");
+        assert_eq!(sections["Lectio"].trim(), "Incipit\ninclusion cycle: Psalterium/A:Lectio -> Psalterium/A:Lectio");
+        // The chain of C1 … is cut after DEFAULT_INCLUSION_DEPTH inclusions.
+        let versus = &sections["Versus"];
+        assert!(versus.contains("C10") && !versus.contains("C11\n"));
+        assert!(versus.contains("inclusions nested deeper than 10: Psalterium/A:Versus -> Psalterium/C1:Versus"));
+
+        let errors = ctx.inclusion_errors("Latin", "Psalterium/A.txt");
+        assert_eq!(errors.len(), 3);
+        assert!(errors.contains(&InclusionError::Cycle(vec!["Psalterium/A:Lectio".to_string(), "Psalterium/A:Lectio".to_string()])));
+        assert!(errors.iter().any(|e| matches!(e, InclusionError::TooDeep { limit: 10, path } if path.len() == 12)));
+    }
+
+    #[test]
+    fn test_invalidate() {
+        let root = TempRoot::new("setupstring-invalidate");
+        let write = |path: &str, content: &str| root.write(path, content);
+        write("Latin/Commune/C4.txt", "[Oratio]\nDa, quǽsumus\n");
+        write("Latin/Sancti/01-21.txt", "[Oratio]\n@Commune/C4\n");
+        write("Latin/Sancti/01-22.txt", "[Oratio]\nAdésto\n");
+        write("English/Sancti/01-21.txt", "[Rank]\nSt. Agnes\n");
+        let mut ctx = SetupStringContext {
+            langfb: "Latin".to_string(),
+            ..SetupStringContext::new(&*root, "Divino Afflatu")
+        };
+        let oratio = |ctx: &mut SetupStringContext, lang: &str| {
+            ctx.setupstring(lang, "Sancti/01-21.txt", ResolveDirectives::All).unwrap()["Oratio"].trim().to_string()
+        };
+        assert_eq!(oratio(&mut ctx, "English"), "Da, quǽsumus");
+        ctx.setupstring("Latin", "Sancti/01-22.txt", ResolveDirectives::All);
+
+        write("Latin/Commune/C4.txt", "[Oratio]\nExáudi\n");
+        assert_eq!(oratio(&mut ctx, "English"), "Da, quǽsumus");
+        // Commune/C4 and Sancti/01-21, which includes it, in both languages.
+        let evicted = ctx.invalidate(&root.join("Latin/Commune/C4.txt").to_string_lossy());
+        assert_eq!(evicted, 4);
+        assert_eq!(oratio(&mut ctx, "English"), "Exáudi");
+        assert!(ctx.cache_by_version.values().any(|files| files.contains_key("Sancti/01-22.txt")));
+        assert_eq!(ctx.invalidate("Latin/Sancti/01-22.txt"), 1);
+        assert_eq!(ctx.invalidate("Latin"), 0);
+
+        // Sancti/01-21 no longer includes Commune/C4: a change of C4 leaves it.
+        write("Latin/Sancti/01-21.txt", "[Oratio]\nOmnípotens\n");
+        assert_eq!(ctx.invalidate("Latin/Sancti/01-21.txt"), 2);
+        assert_eq!(oratio(&mut ctx, "English"), "Omnípotens");
+        assert!(ctx.included_by.is_empty());
+        // Commune/C4 alone, in both languages.
+        assert_eq!(ctx.invalidate("Latin/Commune/C4.txt"), 2);
+        assert!(ctx.cache_by_version.values().any(|files| files.contains_key("Sancti/01-21.txt")));
+
+        // The inclusions are those of the files of each context.
+        write("Latin/Sancti/01-21.txt", "[Oratio]\n@Commune/C4\n");
+        ctx.invalidate("Latin/Sancti/01-21.txt");
+        oratio(&mut ctx, "Latin");
+        assert!(ctx.included_by["Commune/C4"].contains("Sancti/01-21"));
+        assert!(SetupStringContext::new(&*root, "Divino Afflatu").included_by.is_empty());
+    }
+
+    #[test]
+    fn test_inclusion_substitutions() {
+        let apply = |text: &str, subs: &str| {
+            let mut text = text.to_string();
+            SetupStringContext::do_inclusion_substitutions(&mut text, subs);
+            text
+        };
+        // Commune/C2 Oratio, as Sancti/12-27 includes it.
+        let oratio = "Ecclésiam tuam, quǽsumus, Dómine, benígnus illústra:\nut, beáti N. Apóstoli tui et Evangelístæ illumináta doctrínis,\nad dona pervéniat sempitérna.\n$Per Dominum\n";
+        let text = apply(oratio, r"s/N\./Ioánnis/g:1-3");
+        assert_eq!(text.lines().nth(1), Some("ut, beáti Ioánnis Apóstoli tui et Evangelístæ illumináta doctrínis,"));
+        assert_eq!(text.lines().count(), 3);
+        // The order matters: the selection first, then the substitution.
+        assert_eq!(apply(oratio, r"2:s/N\./Ioánnis/"), "ut, beáti Ioánnis Apóstoli tui et Evangelístæ illumináta doctrínis,\n");
+        // Flags, groups and escaped slashes.
+        assert_eq!(apply("Beáti N. et N. Mártyrum\n", r"s/n\. et n\./Cosmæ et Damiáni/i"), "Beáti Cosmæ et Damiáni Mártyrum\n");
+        assert_eq!(apply("Ant. Allelúja, allelúja.\n", r"s/(\w+), (\w+)/$2\/$1/"), "Ant. allelúja/Allelúja.\n");
+        assert_eq!(apply("a\nb\n", r"s/a.b/x/s"), "x\n");
+        // A bad pattern is skipped, the steps after it still apply.
+        assert_eq!(apply("N. N.\n", r"s/N(/x/:s/N\./Petri/g"), "Petri Petri\n");
+
+        assert!(matches!(
+            parse_substitutions(r"s/N\./Ioánnis/g:1-4").as_deref(),
+            Ok([Substitution::Replace { global: true, .. }, Substitution::Lines { first: 1, last: 4 }])
+        ));
+        let err = parse_substitutions(r"s/N(/x/").unwrap_err();
+        assert_eq!(err.spec, "s/N(/x/");
+        assert_eq!(parse_substitutions("s/N/x").unwrap_err().spec, "s/N/x");
+        assert_eq!(parse_substitutions("4-2").unwrap_err().message, "invalid line range");
+    }
+}
@@ -0,0 +1,86 @@
+//! test_support.rs
+//!
+//! Fixtures shared by the unit tests: a tree of data files written under the
+//! temporary directory, as the tests of the hours, the Mass, the exports and
+//! the server read them through a `SetupStringContext`. The tree is removed
+//! when the test is done, whether it passed or not.
+
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::setup_string::SetupStringContext;
+
+/// A directory under `std::env::temp_dir()`, removed with its contents when
+/// dropped. It dereferences to its path.
+pub(crate) struct TempRoot(PathBuf);
+
+impl TempRoot {
+    /// An empty directory, unique to the process and to the call, so that
+    /// tests running in parallel do not share one.
+    pub fn new(name: &str) -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let n = NEXT.fetch_add(1, Ordering::Relaxed);
+        let root = std::env::temp_dir().join(format!("divinum-officium-{}-{}-{}", name, std::process::id(), n));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        TempRoot(root)
+    }
+
+    /// Writes `content` to `rel` (e.g. "Latin/Sancti/01-21.txt"), creating
+    /// its folders.
+    pub fn write(&self, rel: &str, content: &str) {
+        let path = self.0.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+}
+
+impl Deref for TempRoot {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempRoot {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempRoot {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Writes `files`, as (path under the data folder, content), to a new
+/// `TempRoot` and returns it with a context reading from it. The context has
+/// no version; the functions under test are given theirs.
+pub(crate) fn data_tree(files: &[(&str, &str)]) -> (TempRoot, SetupStringContext) {
+    let root = TempRoot::new("data");
+    for (rel, content) in files {
+        root.write(rel, content);
+    }
+    let ctx = SetupStringContext::new(&*root, "");
+    (root, ctx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_tree_removed_on_drop() {
+        let (root, ctx) = data_tree(&[("Latin/Sancti/01-21.txt", "[Oratio]\nOmnípotens\n")]);
+        let path = root.to_path_buf();
+        assert_eq!(ctx.datafolder, path);
+        assert!(path.join("Latin/Sancti/01-21.txt").is_file());
+        assert_ne!(TempRoot::new("data").to_path_buf(), path);
+        drop(root);
+        assert!(!path.exists());
+    }
+}
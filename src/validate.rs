@@ -0,0 +1,350 @@
+//! validate.rs
+//!
+//! Checks the data files of a tree (`<datafolder>/<lang>/Sancti/…`,
+//! `…/Tempora/…`, and their version folders such as `SanctiM`) for the
+//! mistakes that make a file read wrong without any error at run time:
+//!
+//! - a section header that `setupstring` does not recognise, the lines
+//!   under it landing in the section before;
+//! - a conditional whose condition cannot be parsed (`check_condition()`);
+//! - an `@File:Section` reference to a file or section that does not exist,
+//!   in the language or in the languages it falls back to;
+//! - a file of a vernacular without its Latin counterpart to fall back to,
+//!   and a Latin file a vernacular has no translation of.
+//!
+//! ```ignore
+//! for diagnostic in validate_tree("web/www/horas")? {
+//!     eprintln!("{}", diagnostic);
+//! }
+//! ```
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::fileio::{self, DataSource};
+use crate::regex::fallback_lang;
+use crate::setup_string::{check_condition, check_conditional};
+
+/// A section header, with its optional condition in group 2.
+static SECTION_HEADER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*\[([\pL\pN_ #,:-]+)\]\s*(?:\((.*)\))?\s*$").unwrap());
+
+/// An inclusion `@File:Section:substitutions`, file and section optional.
+static INCLUSION: Lazy<Regex> = Lazy::new(|| Regex::new(r"^@([^\n:]+)?(?::([^\n:]+))?(?::(.*))?$").unwrap());
+
+/// How serious a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The file is read wrong.
+    Error,
+    /// The file is read, but probably not as meant.
+    Warning,
+}
+
+/// What a diagnostic is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Check {
+    /// A line starting with `[` that is not a valid section header.
+    SectionHeader,
+    /// A conditional that cannot be parsed.
+    Conditional,
+    /// An `@File:Section` reference that does not resolve.
+    UnresolvedReference,
+    /// A vernacular file without a Latin file to fall back to.
+    MissingFallback,
+    /// A Latin file the vernacular has no translation of.
+    MissingTranslation,
+    /// A file that cannot be read as text (`fileio::decode()`).
+    Unreadable,
+}
+
+/// A problem found in a data file.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Diagnostic {
+    /// The file, relative to the data folder ("Latin/Sancti/01-25.txt").
+    pub path: String,
+    /// The line, from 1; 0 for the file as a whole.
+    pub line: usize,
+    pub severity: Severity,
+    pub check: Check,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}:{}: {}: {}", self.path, self.line, severity, self.message)
+    }
+}
+
+/// Validates the Sancti and Tempora files of every language under
+/// `datafolder`, returning the diagnostics sorted by file and line.
+///
+/// The files are read from the data source of the process
+/// (`fileio::data_source()`), as `setupstring` reads them.
+///
+/// Fails only if `datafolder` cannot be listed; a file that cannot be read
+/// is a diagnostic.
+pub fn validate_tree(datafolder: impl AsRef<Path>) -> io::Result<Vec<Diagnostic>> {
+    validate_tree_from(&*fileio::data_source(), datafolder)
+}
+
+/// Like `validate_tree`, but reads from `source` instead of the data source
+/// of the process.
+pub fn validate_tree_from(source: &dyn DataSource, datafolder: impl AsRef<Path>) -> io::Result<Vec<Diagnostic>> {
+    let mut tree = Tree::new(source, datafolder.as_ref())?;
+    let mut diagnostics = Vec::new();
+    for lang in tree.langs.clone() {
+        let files = tree.files.get(&lang).cloned().unwrap_or_default();
+        for file in &files {
+            let path = format!("{}/{}", lang, file);
+            match tree.lines(&lang, file) {
+                Ok(lines) => {
+                    diagnostics.extend(validate_lines(&path, &lines));
+                    diagnostics.extend(tree.check_references(&lang, file, &lines));
+                }
+                Err(e) => diagnostics.push(Diagnostic {
+                    path: path.clone(),
+                    line: 0,
+                    severity: Severity::Error,
+                    check: Check::Unreadable,
+                    message: format!("the file cannot be read as text ({})", e),
+                }),
+            }
+        }
+        if lang != "Latin" {
+            diagnostics.extend(tree.check_translations(&lang));
+        }
+    }
+    diagnostics.sort();
+    Ok(diagnostics)
+}
+
+/// Checks the section headers and the conditionals of the lines of one
+/// file, `path` being the file named in the diagnostics.
+pub fn validate_lines(path: &str, lines: &[String]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut error = |line: usize, check: Check, message: String| {
+        diagnostics.push(Diagnostic { path: path.to_string(), line, severity: Severity::Error, check, message });
+    };
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            match SECTION_HEADER.captures(trimmed) {
+                None => error(i + 1, Check::SectionHeader, format!("malformed section header \"{}\"", trimmed)),
+                Some(caps) => {
+                    if let Some(Err(message)) = caps.get(2).map(|c| check_condition(c.as_str())) {
+                        error(i + 1, Check::Conditional, message);
+                    }
+                }
+            }
+        } else if let Some(Err(message)) = check_conditional(trimmed) {
+            error(i + 1, Check::Conditional, message);
+        }
+    }
+    diagnostics
+}
+
+/// The files of the tree, by language, and their sections as far as read.
+struct Tree<'a> {
+    source: &'a dyn DataSource,
+    root: &'a Path,
+    langs: Vec<String>,
+    /// The validated files of each language, relative to its folder.
+    files: HashMap<String, BTreeSet<String>>,
+    /// The section names of the files read so far, by path.
+    sections: HashMap<String, Option<BTreeSet<String>>>,
+}
+
+impl<'a> Tree<'a> {
+    fn new(source: &'a dyn DataSource, root: &'a Path) -> io::Result<Self> {
+        // The `.txt` files of the Sancti* and Tempora* folders of each language.
+        let mut files: HashMap<String, BTreeSet<String>> = HashMap::new();
+        for path in fileio::walk_from(source, &root.to_string_lossy())? {
+            let mut parts = path.splitn(3, '/');
+            let (Some(lang), Some(dir), Some(_)) = (parts.next(), parts.next(), parts.next()) else {
+                continue;
+            };
+            if (dir.starts_with("Sancti") || dir.starts_with("Tempora")) && path.ends_with(".txt") {
+                files.entry(lang.to_string()).or_default().insert(path[lang.len() + 1..].to_string());
+            }
+        }
+        let mut langs: Vec<String> = files.keys().cloned().collect();
+        langs.sort();
+        Ok(Tree { source, root, langs, files, sections: HashMap::new() })
+    }
+
+    /// The lines of `file` in `lang`, decoded as `fileio::decode()` does.
+    fn lines(&self, lang: &str, file: &str) -> io::Result<Vec<String>> {
+        let text = self.source.read_to_string(&self.root.join(lang).join(file).to_string_lossy())?;
+        Ok(text.lines().map(str::to_string).collect())
+    }
+
+    /// The section names of `file` in `lang`, `None` if there is no such file.
+    fn sections(&mut self, lang: &str, file: &str) -> Option<&BTreeSet<String>> {
+        let key = format!("{}/{}", lang, file);
+        if !self.sections.contains_key(&key) {
+            let sections = self.lines(lang, file).ok().map(|lines| {
+                lines
+                    .iter()
+                    .filter_map(|l| SECTION_HEADER.captures(l.trim()))
+                    .map(|c| c[1].trim().to_string())
+                    .collect()
+            });
+            self.sections.insert(key.clone(), sections);
+        }
+        self.sections[&key].as_ref()
+    }
+
+    /// `lang` and the languages it falls back to, as `setupstring` reads them.
+    fn chain(lang: &str) -> Vec<String> {
+        let mut chain = vec![lang.to_string()];
+        let mut current = lang.to_string();
+        while let Some(base) = fallback_lang(&current) {
+            chain.push(base.clone());
+            current = base;
+        }
+        if !lang.eq_ignore_ascii_case("Latin") {
+            chain.push("Latin".to_string());
+        }
+        chain
+    }
+
+    /// Checks the `@` references of `file` of `lang`: the file must exist in
+    /// the language or a fallback, and have the section.
+    fn check_references(&mut self, lang: &str, file: &str, lines: &[String]) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let mut current = String::new();
+        for (i, line) in lines.iter().enumerate() {
+            let trimmed = line.trim();
+            if let Some(caps) = SECTION_HEADER.captures(trimmed) {
+                current = caps[1].trim().to_string();
+                continue;
+            }
+            let Some(caps) = INCLUSION.captures(trimmed) else {
+                continue;
+            };
+            let target = caps.get(1).map_or(file.to_string(), |m| format!("{}.txt", m.as_str().trim()));
+            // Without a section, the preamble includes the whole file and a
+            // section the section of the same name.
+            let section = caps.get(2).map(|m| m.as_str().trim().to_string()).or_else(|| {
+                (!current.is_empty()).then(|| current.clone())
+            });
+            let mut found_file = false;
+            let mut found = false;
+            for candidate in Self::chain(lang) {
+                if let Some(sections) = self.sections(&candidate, &target) {
+                    found_file = true;
+                    if section.as_ref().is_none_or(|s| sections.contains(s)) {
+                        found = true;
+                        break;
+                    }
+                }
+            }
+            if !found {
+                let message = match (&section, found_file) {
+                    (Some(s), true) => format!("{} has no section [{}]", target, s),
+                    _ => format!("{} does not exist", target),
+                };
+                diagnostics.push(Diagnostic {
+                    path: format!("{}/{}", lang, file),
+                    line: i + 1,
+                    severity: Severity::Error,
+                    check: Check::UnresolvedReference,
+                    message,
+                });
+            }
+        }
+        diagnostics
+    }
+
+    /// Compares the files of `lang` with those of Latin.
+    fn check_translations(&self, lang: &str) -> Vec<Diagnostic> {
+        let empty = BTreeSet::new();
+        let latin = self.files.get("Latin").unwrap_or(&empty);
+        let files = self.files.get(lang).unwrap_or(&empty);
+        let warning = |path: String, check: Check, message: &str| Diagnostic {
+            path,
+            line: 0,
+            severity: Severity::Warning,
+            check,
+            message: message.to_string(),
+        };
+        let mut diagnostics: Vec<Diagnostic> = files
+            .difference(latin)
+            .map(|f| warning(format!("{}/{}", lang, f), Check::MissingFallback, "no Latin file to fall back to"))
+            .collect();
+        diagnostics.extend(
+            latin
+                .difference(files)
+                .map(|f| warning(format!("{}/{}", lang, f), Check::MissingTranslation, "not translated, read in Latin")),
+        );
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fileio::{FileSystem, MemorySource};
+    use crate::test_support::TempRoot;
+    use std::fs;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_validate_lines() {
+        let text = "[Officium]\nIn Conversione S. Pauli\n[Rank] (rubrica 1960)\n[Oratio\n(sed rubrica monastica et)\n\
+                    (Hic genuflectitur)\n(sed rubrica 196[0 dicitur)\n[Lectio1](rubrica monastica aut)";
+        let found: Vec<(usize, Check)> = validate_lines("Latin/Sancti/01-25.txt", &lines(text))
+            .iter()
+            .map(|d| (d.line, d.check))
+            .collect();
+        assert_eq!(
+            found,
+            [(4, Check::SectionHeader), (5, Check::Conditional), (7, Check::Conditional), (8, Check::Conditional)]
+        );
+    }
+
+    #[test]
+    fn test_validate_tree() {
+        let root = TempRoot::new("validate");
+        let write = |path: &str, content: &str| root.write(path, content);
+        write("Latin/Sancti/01-25.txt", "[Officium]\nConversio\n[Oratio]\n@Commune/C4:Oratio\n[Lectio1]\n@Sancti/01-24\n@:Oratio\n");
+        write("Latin/Sancti/01-24.txt", "[Officium]\nTimotheus\n[Lectio1]\nLectio\n");
+        write("Latin/Commune/C4.txt", "[Oratio]\nDeus\n");
+        write("English/Sancti/01-25.txt", "[Officium]\nConversion\n[Oratio]\n@Commune/C4:Lectio9\n");
+        write("English/Sancti/12-31.txt", "[Officium]\nSylvester\n");
+
+        let diagnostics = validate_tree(&root).unwrap();
+        let found: Vec<(&str, usize, Check)> =
+            diagnostics.iter().map(|d| (d.path.as_str(), d.line, d.check)).collect();
+        assert_eq!(
+            found,
+            [
+                ("English/Sancti/01-24.txt", 0, Check::MissingTranslation),
+                ("English/Sancti/01-25.txt", 4, Check::UnresolvedReference),
+                ("English/Sancti/12-31.txt", 0, Check::MissingFallback),
+            ]
+        );
+        assert_eq!(diagnostics[1].message, "Commune/C4.txt has no section [Lectio9]");
+        assert_eq!(diagnostics[1].to_string(), "English/Sancti/01-25.txt:4: error: Commune/C4.txt has no section [Lectio9]");
+
+        // The same tree in memory, as the embedded data would give it.
+        let mut memory = MemorySource::new();
+        for path in fileio::walk_from(&FileSystem, &root.to_string_lossy()).unwrap() {
+            memory.insert(&format!("/data/horas/{}", path), &fs::read_to_string(root.join(&path)).unwrap());
+        }
+        assert_eq!(validate_tree_from(&memory, "/data/horas").unwrap(), diagnostics);
+    }
+}
@@ -0,0 +1,36 @@
+//! The bindings of the web front end, with the `wasm` feature: the hours
+//! built in the browser from the embedded data (see `embedded`), without
+//! the Perl CGI. The module itself is the `cdylib` of the `wasm/` crate:
+//!
+//! ```js
+//! import init, { build_hour_json } from "./divinum_officium_wasm.js";
+//! await init();
+//! const hour = JSON.parse(build_hour_json("01-25-2025", "Laudes", "Latin", "Divino Afflatu"));
+//! ```
+
+use std::sync::Once;
+
+use wasm_bindgen::prelude::*;
+
+use crate::embedded;
+use crate::horas::build_hour_json as build_json;
+use crate::setup_string::SetupStringContext;
+
+/// The mount point of the embedded data, the `web/www` folder of the
+/// Perl project.
+const DATA_PREFIX: &str = "www";
+
+static INSTALL: Once = Once::new();
+
+/// Returns `hour` on `date` ("MM-DD-YYYY") in `lang` according to
+/// `version`, as the JSON object of `horas::build_hour_json`.
+#[wasm_bindgen]
+pub fn build_hour_json(date: &str, hour: &str, lang: &str, version: &str) -> String {
+    INSTALL.call_once(|| embedded::install(DATA_PREFIX));
+    let mut ctx = SetupStringContext {
+        hora: hour.to_string(),
+        langfb: "English".to_string(),
+        ..SetupStringContext::new(format!("{}/horas", DATA_PREFIX), version)
+    };
+    build_json(&mut ctx, date, hour, lang, version)
+}
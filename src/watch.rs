@@ -0,0 +1,148 @@
+//! watch.rs
+//!
+//! The data files that changed under a folder, found by polling, so that a
+//! long-running server drops what it cached of them after a `git pull` of
+//! the data instead of restarting.
+//!
+//! A `Watcher` remembers the modification time and the size of every file;
+//! `changed()` compares them with those on disk. `poll()` evicts the files
+//! that changed from a context (`SetupStringContext::invalidate()`) and from
+//! the tables of the directorium (`directorium::invalidate()`):
+//!
+//! ```ignore
+//! let mut watcher = Watcher::new(&ctx.datafolder)?;
+//! loop {
+//!     serve_requests(&mut ctx);
+//!     watcher.poll(&mut ctx)?;
+//! }
+//! ```
+//!
+//! A server whose contexts live for one request only has nothing to evict
+//! but the caches of the process; `spawn()` polls for it on a thread.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::directorium;
+use crate::fileio::{self, FileSystem};
+use crate::setup_string::SetupStringContext;
+
+/// The files under a folder, as they were when last looked at.
+#[derive(Debug, Clone)]
+pub struct Watcher {
+    root: PathBuf,
+    /// The modification time and the size of each file, by its path
+    /// relative to `root`.
+    stamps: HashMap<String, (SystemTime, u64)>,
+}
+
+impl Watcher {
+    /// Watches the files under `root`, as they are now.
+    pub fn new(root: impl Into<PathBuf>) -> io::Result<Self> {
+        let root = root.into();
+        let stamps = stamps(&root)?;
+        Ok(Watcher { root, stamps })
+    }
+
+    /// The folder watched.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The files changed, added or removed since the last call (or since
+    /// `new()`), relative to the folder and sorted.
+    pub fn changed(&mut self) -> io::Result<Vec<String>> {
+        let stamps = stamps(&self.root)?;
+        let mut changed: Vec<String> = stamps
+            .iter()
+            .filter(|(path, stamp)| self.stamps.get(*path) != Some(stamp))
+            .map(|(path, _)| path.clone())
+            .chain(self.stamps.keys().filter(|path| !stamps.contains_key(*path)).cloned())
+            .collect();
+        changed.sort();
+        self.stamps = stamps;
+        Ok(changed)
+    }
+
+    /// Evicts the files that changed from `ctx` and from the directorium,
+    /// and returns them as `changed()` does.
+    pub fn poll(&mut self, ctx: &mut SetupStringContext) -> io::Result<Vec<String>> {
+        let changed = self.changed()?;
+        for path in &changed {
+            let path = self.root.join(path).to_string_lossy().into_owned();
+            ctx.invalidate(&path);
+            directorium::invalidate(&path);
+        }
+        Ok(changed)
+    }
+
+    /// Polls every `interval` on a thread of its own: evicts the files that
+    /// changed from the directorium and passes them to `on_change`, until it
+    /// returns `false` or the folder cannot be read.
+    pub fn spawn<F>(mut self, interval: Duration, mut on_change: F) -> thread::JoinHandle<io::Result<()>>
+    where
+        F: FnMut(&[String]) -> bool + Send + 'static,
+    {
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            let changed = self.changed()?;
+            if changed.is_empty() {
+                continue;
+            }
+            for path in &changed {
+                directorium::invalidate(&self.root.join(path).to_string_lossy());
+            }
+            if !on_change(&changed) {
+                return Ok(());
+            }
+        })
+    }
+}
+
+/// The modification time and the size of the files under `root`.
+fn stamps(root: &Path) -> io::Result<HashMap<String, (SystemTime, u64)>> {
+    let mut stamps = HashMap::new();
+    for path in fileio::walk_from(&FileSystem, &root.to_string_lossy())? {
+        // A file removed since the listing is left out, as if already gone.
+        if let Ok(metadata) = fs::metadata(root.join(&path)) {
+            stamps.insert(path, (metadata.modified()?, metadata.len()));
+        }
+    }
+    Ok(stamps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::data_tree;
+
+    #[test]
+    fn test_poll() {
+        let (root, mut ctx) = data_tree(&[
+            ("Latin/Sancti/01-21.txt", "[Oratio]\nOmnípotens\n"),
+            ("Latin/Sancti/01-22.txt", "[Oratio]\nAdésto\n"),
+        ]);
+        let oratio = |ctx: &mut SetupStringContext| {
+            let sections = ctx.setupstring("Latin", "Sancti/01-21.txt", crate::setup_string::ResolveDirectives::All);
+            sections.unwrap()["Oratio"].trim().to_string()
+        };
+        assert_eq!(oratio(&mut ctx), "Omnípotens");
+
+        let mut watcher = Watcher::new(&*root).unwrap();
+        assert!(watcher.poll(&mut ctx).unwrap().is_empty());
+        // The size changes, whatever the resolution of the times.
+        fs::write(root.join("Latin/Sancti/01-21.txt"), "[Oratio]\nDeus, qui\n").unwrap();
+        fs::remove_file(root.join("Latin/Sancti/01-22.txt")).unwrap();
+        fs::write(root.join("Latin/Sancti/01-23.txt"), "").unwrap();
+        assert_eq!(
+            watcher.poll(&mut ctx).unwrap(),
+            ["Latin/Sancti/01-21.txt", "Latin/Sancti/01-22.txt", "Latin/Sancti/01-23.txt"]
+        );
+        assert_eq!(oratio(&mut ctx), "Deus, qui");
+        assert!(watcher.changed().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,257 @@
+//! golden.rs
+//!
+//! The hours rendered by the crate compared with those of the Perl site,
+//! captured as fixtures under `tests/golden`:
+//!
+//! ```text
+//! tests/golden/<version>/<MM-DD-YYYY>/<Hora>-<lang>.html
+//! tests/golden/Divino_Afflatu/01-21-2025/Vespera-Latin.html
+//! ```
+//!
+//! `<version>` is the version with `_` for its spaces. A fixture is the text
+//! of the hour as officium.pl renders it (the table of the office, without
+//! the page around it). Both texts are normalized (`normalize()`): the
+//! markup is dropped, the entities decoded and the whitespace collapsed, so
+//! that only the words and their lines are compared.
+//!
+//! The data is that of the folder named by `DIVINUM_OFFICIUM_DATA` (the
+//! `horas` folder of the Perl project): with fixtures but without the
+//! data the test fails, without fixtures it only says so. Each case is
+//! reported with the share of its lines found in order, and a summary is
+//! printed per hour. The cases listed in `tests/golden/passing.txt` must
+//! match exactly:
+//!
+//! ```text
+//! DIVINUM_OFFICIUM_DATA=../divinum-officium/web/www/horas cargo test --test golden -- --nocapture
+//! ```
+//!
+//! No fixtures of the Perl site ship with the crate: they are taken with
+//! the data they come from, and `passing.txt` lists none yet.
+//!
+//! `snapshot()` is not a comparison with the Perl site. It runs on every
+//! build over a small tree of its own (`tests/snapshot/data`: a feast over
+//! a feria, an office taken from another file by `@`, a second language)
+//! and compares the hours with snapshots of the crate's own output, with
+//! the same layout under `tests/snapshot` (`<Hora>-<lang>.txt`), so that a
+//! change of the output shows up. Every case must match; after a change
+//! meant to alter the output, the snapshots are written again with
+//!
+//! ```text
+//! DIVINUM_OFFICIUM_UPDATE_SNAPSHOTS=1 cargo test --test golden snapshot
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use divinum_officium::horas::{Hora, Kalendar};
+use divinum_officium::setup_string::SetupStringContext;
+
+/// A fixture: the hour of a day in a version and a language.
+struct Case {
+    /// "<version dir>/<MM-DD-YYYY>/<Hora>-<lang>".
+    name: String,
+    version: String,
+    date: String,
+    hora: Hora,
+    lang: String,
+    path: PathBuf,
+}
+
+#[test]
+fn golden() {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let cases = cases(&fixtures);
+    if cases.is_empty() {
+        eprintln!("golden: no fixtures under {}", fixtures.display());
+        return;
+    }
+    let data = std::env::var_os("DIVINUM_OFFICIUM_DATA").unwrap_or_else(|| {
+        panic!("golden: {} fixtures, but DIVINUM_OFFICIUM_DATA does not name the data they were taken from", cases.len())
+    });
+    let passing = fs::read_to_string(fixtures.join("passing.txt")).unwrap_or_default();
+    let passing: Vec<&str> = passing.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')).collect();
+    let regressions = compare(&cases, Path::new(&data), |name| passing.contains(&name));
+    assert!(regressions.is_empty(), "cases of passing.txt that no longer match:\n{}", regressions.join("\n"));
+}
+
+#[test]
+fn snapshot() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/snapshot");
+    let cases = cases(&dir);
+    assert!(!cases.is_empty(), "no snapshots under {}", dir.display());
+    if std::env::var_os("DIVINUM_OFFICIUM_UPDATE_SNAPSHOTS").is_some() {
+        for case in &cases {
+            let mut ctx = context(dir.join("data"));
+            let text = Kalendar::new(&case.version).build_hour(&mut ctx, &case.date, case.hora, &case.lang).unwrap();
+            fs::write(&case.path, text).unwrap();
+        }
+    }
+    let regressions = compare(&cases, &dir.join("data"), |_| true);
+    assert!(regressions.is_empty(), "snapshots that no longer match:\n{}", regressions.join("\n"));
+}
+
+/// Builds the hour of each case over `data` and compares it with its
+/// fixture. Returns the differences of the cases `required` to match.
+fn compare(cases: &[Case], data: &Path, required: impl Fn(&str) -> bool) -> Vec<String> {
+    let mut by_hour: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+    let mut regressions = Vec::new();
+    for case in cases {
+        let expected = normalize(&fs::read_to_string(&case.path).unwrap());
+        let mut ctx = context(data.to_path_buf());
+        let actual = match Kalendar::new(&case.version).build_hour(&mut ctx, &case.date, case.hora, &case.lang) {
+            Ok(text) => normalize(&text),
+            Err(e) => {
+                eprintln!("{}: {}", case.name, e);
+                Vec::new()
+            }
+        };
+        let score = similarity(&expected, &actual);
+        let exact = expected == actual;
+        println!("{:<50} {:>5.1}%{}", case.name, score * 100.0, if exact { " exact" } else { "" });
+        let hour = by_hour.entry(case.hora.to_string()).or_default();
+        hour.0 += exact as usize;
+        hour.1 += 1;
+        if !exact && required(&case.name) {
+            regressions.push(format!("{}\n{}", case.name, first_difference(&expected, &actual)));
+        }
+    }
+    for (hora, (exact, total)) in &by_hour {
+        println!("{:<12} {}/{} exact", hora, exact, total);
+    }
+    regressions
+}
+
+/// The fixtures under `dir`, in the order of their names; a `data` folder
+/// holds the tree of the snapshots, not fixtures.
+fn cases(dir: &Path) -> Vec<Case> {
+    let mut cases = Vec::new();
+    for version in read_dirs(dir).into_iter().filter(|v| file_name(v) != "data") {
+        for date in read_dirs(&version) {
+            let Ok(files) = fs::read_dir(&date) else { continue };
+            for path in files.flatten().map(|e| e.path()) {
+                let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else { continue };
+                let Some((hora, lang)) = stem.split_once('-') else { continue };
+                let Ok(hora) = hora.parse() else { continue };
+                let (version, date) = (file_name(&version), file_name(&date));
+                cases.push(Case {
+                    name: format!("{}/{}/{}", version, date, stem),
+                    version: version.replace('_', " "),
+                    date,
+                    hora,
+                    lang: lang.to_string(),
+                    path,
+                });
+            }
+        }
+    }
+    cases.sort_by(|a, b| a.name.cmp(&b.name));
+    cases
+}
+
+fn read_dirs(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir).map(|d| d.flatten().map(|e| e.path()).filter(|p| p.is_dir()).collect()).unwrap_or_default()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+fn context(datafolder: PathBuf) -> SetupStringContext {
+    SetupStringContext::new(datafolder, "")
+}
+
+/// The lines of the text of an hour without its markup: the tags that break
+/// a line (`<br>`, `<p>`, `<tr>`, ...) become line breaks, the others are
+/// dropped, the entities are decoded, the whitespace within a line collapsed
+/// and the empty lines left out.
+fn normalize(html: &str) -> Vec<String> {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            rest = &rest[start..];
+            break;
+        };
+        let tag = rest[start + 1..start + end].trim_start_matches('/').to_ascii_lowercase();
+        let name = tag.split(|c: char| c.is_whitespace() || c == '/').next().unwrap_or("");
+        if matches!(name, "br" | "p" | "div" | "tr" | "td" | "li" | "h1" | "h2" | "h3" | "h4") {
+            text.push('\n');
+        }
+        rest = &rest[start + end + 1..];
+    }
+    text.push_str(rest);
+    decode_entities(&text)
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..].find(';').filter(|&n| n <= 8).map(|n| &rest[1..n + 1]);
+        let decoded = match entity {
+            Some("nbsp") => Some(' '),
+            Some("amp") => Some('&'),
+            Some("lt") => Some('<'),
+            Some("gt") => Some('>'),
+            Some("quot") => Some('"'),
+            Some(e) if e.starts_with("#x") => u32::from_str_radix(&e[2..], 16).ok().and_then(char::from_u32),
+            Some(e) if e.starts_with('#') => e[1..].parse().ok().and_then(char::from_u32),
+            _ => None,
+        };
+        match (decoded, entity) {
+            (Some(c), Some(e)) => {
+                out.push(c);
+                rest = &rest[e.len() + 2..];
+            }
+            _ => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The share of the lines of the longer text found in the other, in order
+/// (their longest common subsequence).
+fn similarity(a: &[String], b: &[String]) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let mut row = vec![0usize; b.len() + 1];
+    for x in a {
+        let mut diagonal = 0;
+        for (j, y) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if x == y { diagonal + 1 } else { above.max(row[j]) };
+            diagonal = above;
+        }
+    }
+    row[b.len()] as f64 / a.len().max(b.len()) as f64
+}
+
+/// The first line where `expected` and `actual` differ.
+fn first_difference(expected: &[String], actual: &[String]) -> String {
+    let i = expected.iter().zip(actual).take_while(|(e, a)| e == a).count();
+    let line = |lines: &[String]| lines.get(i).cloned().unwrap_or_else(|| "(end)".to_string());
+    format!("  line {}:\n  - {}\n  + {}", i + 1, line(expected), line(actual))
+}
+
+#[test]
+fn test_normalize() {
+    let html = "<p><font color=\"red\">V.</font> Deus in adjutórium &amp; meum inténde.<br/>\n  R. Dómine,&nbsp;ad  adjuvándum</p>\n\n<b>&#198;terna</b>";
+    assert_eq!(normalize(html), ["V. Deus in adjutórium & meum inténde.", "R. Dómine, ad adjuvándum", "Æterna"]);
+    assert_eq!(decode_entities("a & b &unknown; &#x41;"), "a & b &unknown; A");
+    let lines = |s: &str| s.split(' ').map(str::to_string).collect::<Vec<_>>();
+    assert_eq!(similarity(&lines("a b c d"), &lines("a c d")), 0.75);
+    assert_eq!(first_difference(&lines("a b"), &lines("a c")), "  line 2:\n  - b\n  + c");
+}
@@ -0,0 +1,5 @@
+//! The WebAssembly module of the web front end: the bindings of
+//! `divinum_officium::wasm`, re-exported so that they are linked into the
+//! `cdylib`.
+
+pub use divinum_officium::wasm::*;